@@ -1,8 +1,17 @@
 //! CLI argument definitions.
 
+use std::ffi::OsString;
+
 use clap::{Parser, Subcommand};
 
+use crate::commands::batch::BatchArgs;
+use crate::commands::bsky::BskyCommand;
+use crate::commands::doctor::DoctorArgs;
+use crate::commands::history::HistoryCommand;
+use crate::commands::mirror::MirrorCommand;
 use crate::commands::pds::PdsCommand;
+use crate::commands::serve::ServeArgs;
+use crate::output::Format;
 
 /// AT Protocol CLI tool for PDS exploration.
 #[derive(Parser, Debug)]
@@ -17,6 +26,10 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub json_logs: bool,
 
+    /// Output format for command results
+    #[arg(long, short = 'o', global = true, value_enum, default_value_t = Format::Text)]
+    pub output: Format,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -25,4 +38,26 @@ pub struct Cli {
 pub enum Commands {
     /// PDS (Personal Data Server) operations
     Pds(PdsCommand),
+
+    /// Bluesky (app.bsky) actions
+    Bsky(BskyCommand),
+
+    /// Run a JSON Lines script of record operations against the session
+    Batch(BatchArgs),
+
+    /// Diagnose the session, PDS and firehose setup
+    Doctor(DoctorArgs),
+
+    /// Serve a local PDS directory over XRPC
+    Serve(ServeArgs),
+
+    /// Keep a local copy of repos hosted on another PDS
+    Mirror(MirrorCommand),
+
+    /// List and rerun commands recorded with ATPROTO_HISTORY
+    History(HistoryCommand),
+
+    /// Any other command runs the `atproto-<command>` plugin from PATH
+    #[command(external_subcommand)]
+    External(Vec<OsString>),
 }
@@ -0,0 +1,229 @@
+//! Batch command implementation: run a script of record operations.
+//!
+//! Each non-blank line of the script is one JSON operation:
+//!
+//! ```text
+//! {"op": "createRecord", "params": {"collection": "org.example.note", "record": {...}}}
+//! {"op": "getRecord", "params": {"uri": "at://did:plc:.../org.example.note/3k..."}}
+//! {"op": "deleteRecord", "params": {"uri": "at://did:plc:.../org.example.note/3k..."}}
+//! ```
+//!
+//! An optional `id` is echoed in the operation's result. Results are written
+//! to stdout as JSON Lines, in script order, whatever the `--output` format.
+
+use std::io::{self, Read};
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::Args;
+use futures_util::{StreamExt, stream};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use muat_core::traits::Session;
+use muat_core::{AtUri, Nsid, RecordValue};
+
+use crate::commands::pds::bulk;
+use crate::output;
+use crate::session::CliSession;
+use crate::session::storage;
+
+#[derive(Args, Debug)]
+pub struct BatchArgs {
+    /// JSON Lines file of operations (use - for stdin)
+    #[arg(long, short = 'f')]
+    pub file: String,
+
+    /// Maximum operations in flight; operations start in script order
+    #[arg(long, default_value_t = 1)]
+    pub concurrency: usize,
+
+    /// Exit successfully as long as no more than this many operations fail
+    #[arg(long, default_value_t = 0)]
+    pub max_failures: usize,
+}
+
+/// One line of the script.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Operation {
+    op: String,
+    #[serde(default)]
+    params: Value,
+    #[serde(default)]
+    id: Option<Value>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CreateParams {
+    collection: String,
+    record: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UriParams {
+    uri: String,
+}
+
+/// The result of one operation, written as one line of output.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchResult {
+    /// Line of the script, counting from 1.
+    line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<Value>,
+    op: String,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+pub async fn run(args: BatchArgs) -> Result<()> {
+    let session = storage::require_session().await?;
+    let script = read_script(&args.file)?;
+
+    let lines: Vec<(usize, &str)> = script
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .collect();
+    let total = lines.len();
+
+    let cancel = bulk::interrupt_token();
+    let (session, cancel) = (&session, &cancel);
+    // Operations start in script order, so once one sees the token
+    // cancelled every later one does too.
+    let requests: Vec<_> = lines
+        .into_iter()
+        .map(|(line, text)| async move {
+            if cancel.is_cancelled() {
+                return None;
+            }
+            Some(run_line(session, line, text).await)
+        })
+        .collect();
+    let mut results = stream::iter(requests).buffered(args.concurrency.max(1));
+
+    let (mut ran, mut failed) = (0, 0);
+    let mut first_error = None;
+    while let Some(Some((result, error))) = results.next().await {
+        output::json(&result)?;
+        ran += 1;
+        if let Some(error) = error {
+            failed += 1;
+            first_error.get_or_insert(error);
+        }
+    }
+
+    if ran < total {
+        bail!(
+            "Interrupted; {} of {} operations not run",
+            total - ran,
+            total
+        );
+    }
+    match first_error {
+        Some(error) if failed > args.max_failures => Err(error.context(format!(
+            "{} of {} operations failed (allowed: {})",
+            failed, total, args.max_failures
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Read the script from a file, or stdin for `-`.
+fn read_script(path: &str) -> Result<String> {
+    if path == "-" {
+        let mut script = String::new();
+        io::stdin()
+            .read_to_string(&mut script)
+            .context("Failed to read from stdin")?;
+        Ok(script)
+    } else {
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))
+    }
+}
+
+/// Run one line of the script, returning its result and, if it failed, the
+/// error.
+async fn run_line(
+    session: &CliSession,
+    line: usize,
+    text: &str,
+) -> (BatchResult, Option<anyhow::Error>) {
+    let (id, op, outcome) = match serde_json::from_str::<Operation>(text) {
+        Ok(operation) => {
+            let outcome = execute(session, &operation).await;
+            (operation.id, operation.op, outcome)
+        }
+        Err(e) => (
+            None,
+            String::new(),
+            Err(anyhow!(e).context("Invalid operation")),
+        ),
+    };
+
+    match outcome {
+        Ok(result) => (
+            BatchResult {
+                line,
+                id,
+                op,
+                ok: true,
+                result: Some(result),
+                error: None,
+            },
+            None,
+        ),
+        Err(e) => (
+            BatchResult {
+                line,
+                id,
+                op,
+                ok: false,
+                result: None,
+                error: Some(format!("{:#}", e)),
+            },
+            Some(e),
+        ),
+    }
+}
+
+async fn execute(session: &CliSession, operation: &Operation) -> Result<Value> {
+    match operation.op.as_str() {
+        "createRecord" => {
+            let params: CreateParams = params(operation)?;
+            let collection = Nsid::new(&params.collection).context("Invalid collection NSID")?;
+            let value = RecordValue::new(params.record).context("Invalid record value")?;
+            let uri = session.create_record(&collection, &value).await?;
+            Ok(json!({ "uri": uri }))
+        }
+        "getRecord" => {
+            let params: UriParams = params(operation)?;
+            let uri = AtUri::new(&params.uri).context("Invalid AT URI")?;
+            let record = session.get_record(&uri).await?;
+            Ok(serde_json::to_value(record)?)
+        }
+        "deleteRecord" => {
+            let params: UriParams = params(operation)?;
+            let uri = AtUri::new(&params.uri).context("Invalid AT URI")?;
+            session.delete_record(&uri).await?;
+            Ok(json!({ "uri": uri }))
+        }
+        other => bail!(
+            "Unknown op '{}' (expected createRecord, getRecord or deleteRecord)",
+            other
+        ),
+    }
+}
+
+/// Decode an operation's parameters.
+fn params<T: serde::de::DeserializeOwned>(operation: &Operation) -> Result<T> {
+    serde_json::from_value(operation.params.clone())
+        .with_context(|| format!("Invalid params for {}", operation.op))
+}
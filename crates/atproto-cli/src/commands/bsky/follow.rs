@@ -0,0 +1,53 @@
+//! Follow command implementation.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use serde::Serialize;
+
+use muat_core::Did;
+
+use crate::output::{self, Format, Report};
+
+#[derive(Args, Debug)]
+pub struct FollowArgs {
+    /// Handle or DID of the account to follow
+    pub actor: String,
+}
+
+/// The follow record and the account it points at.
+#[derive(Serialize)]
+struct FollowOutput {
+    uri: String,
+    subject: String,
+}
+
+impl Report for FollowOutput {
+    fn print_text(&self) {
+        println!("{}", self.uri);
+        output::success(&format!("Followed: {}", self.subject));
+    }
+}
+
+pub async fn run(args: FollowArgs, format: Format) -> Result<()> {
+    let bsky = super::load_bsky().await?;
+
+    let did = if args.actor.starts_with("did:") {
+        Did::new(&args.actor).context("Invalid DID")?
+    } else {
+        let profile = bsky
+            .get_profile(&args.actor)
+            .await
+            .context("Failed to resolve handle")?;
+        Did::new(&profile.did).context("App view returned an invalid DID")?
+    };
+
+    let follow = bsky.follow(&did).await.context("Failed to follow")?;
+
+    output::report(
+        format,
+        &FollowOutput {
+            uri: follow.to_string(),
+            subject: did.to_string(),
+        },
+    )
+}
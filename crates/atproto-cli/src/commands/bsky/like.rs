@@ -0,0 +1,55 @@
+//! Like command implementation.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use serde::Serialize;
+
+use muat_core::AtUri;
+use muat_core::traits::Session;
+
+use crate::output::{self, Format, Report};
+
+#[derive(Args, Debug)]
+pub struct LikeArgs {
+    /// AT URI of the post to like
+    pub uri: String,
+}
+
+/// The like record and the post it points at.
+#[derive(Serialize)]
+struct LikeOutput {
+    uri: String,
+    subject: String,
+}
+
+impl Report for LikeOutput {
+    fn print_text(&self) {
+        println!("{}", self.uri);
+        output::success(&format!("Liked: {}", self.subject));
+    }
+}
+
+pub async fn run(args: LikeArgs, format: Format) -> Result<()> {
+    let bsky = super::load_bsky().await?;
+    let uri = AtUri::new(&args.uri).context("Invalid AT URI")?;
+
+    // A like references the exact version of the post, so fetch its CID.
+    let post = bsky
+        .session()
+        .get_record(&uri)
+        .await
+        .context("Failed to fetch post")?;
+
+    let like = bsky
+        .like(&uri, &post.cid)
+        .await
+        .context("Failed to like post")?;
+
+    output::report(
+        format,
+        &LikeOutput {
+            uri: like.to_string(),
+            subject: uri.to_string(),
+        },
+    )
+}
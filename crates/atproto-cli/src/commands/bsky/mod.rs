@@ -0,0 +1,50 @@
+//! Bluesky subcommand implementations.
+
+mod follow;
+mod like;
+mod post;
+
+use anyhow::{Result, bail};
+use clap::{Args, Subcommand};
+
+use muat_bsky::Bsky;
+
+use crate::output::Format;
+use crate::session::storage;
+
+#[derive(Args, Debug)]
+pub struct BskyCommand {
+    #[command(subcommand)]
+    pub command: BskySubcommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BskySubcommand {
+    /// Publish a text post
+    Post(post::PostArgs),
+
+    /// Like a post
+    Like(like::LikeArgs),
+
+    /// Follow an account
+    Follow(follow::FollowArgs),
+}
+
+pub async fn handle(cmd: BskyCommand, format: Format) -> Result<()> {
+    match cmd.command {
+        BskySubcommand::Post(args) => post::run(args, format).await,
+        BskySubcommand::Like(args) => like::run(args, format).await,
+        BskySubcommand::Follow(args) => follow::run(args, format).await,
+    }
+}
+
+/// Load the stored session as a Bluesky client.
+async fn load_bsky() -> Result<Bsky> {
+    let session = storage::require_session().await?;
+
+    let Some(xrpc_session) = session.as_xrpc() else {
+        bail!("Bluesky commands are only supported for network PDS sessions.");
+    };
+
+    Ok(Bsky::new(xrpc_session.clone()))
+}
@@ -0,0 +1,39 @@
+//! Post command implementation.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use serde::Serialize;
+
+use crate::output::{self, Format, Report};
+
+#[derive(Args, Debug)]
+pub struct PostArgs {
+    /// Post text
+    pub text: String,
+}
+
+/// The published post.
+#[derive(Serialize)]
+struct PostOutput {
+    uri: String,
+}
+
+impl Report for PostOutput {
+    fn print_text(&self) {
+        println!("{}", self.uri);
+        output::success(&format!("Posted: {}", self.uri));
+    }
+}
+
+pub async fn run(args: PostArgs, format: Format) -> Result<()> {
+    let bsky = super::load_bsky().await?;
+
+    let uri = bsky.post(&args.text).await.context("Failed to post")?;
+
+    output::report(
+        format,
+        &PostOutput {
+            uri: uri.to_string(),
+        },
+    )
+}
@@ -0,0 +1,385 @@
+//! Doctor command implementation.
+//!
+//! Runs a series of environment checks and prints a fix for each problem.
+
+use std::path::Path;
+use std::pin::Pin;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::Args;
+use colored::Colorize;
+use futures_util::StreamExt;
+use serde::Serialize;
+
+use muat_core::traits::{Firehose, Pds};
+use muat_core::types::PdsUrl;
+use muat_file::FilePds;
+use muat_xrpc::XrpcPds;
+
+use crate::output::{self, Format, Report};
+use crate::session::CliSession;
+use crate::session::expiry::jwt_expiry;
+use crate::session::storage::{self, StoredSession};
+
+/// How long to wait for the firehose before calling it connected.
+const FIREHOSE_WAIT: Duration = Duration::from_secs(3);
+
+/// Warn when the refresh token expires sooner than this.
+const REFRESH_WARN_DAYS: i64 = 7;
+
+#[derive(Args, Debug)]
+pub struct DoctorArgs {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+    Skip,
+}
+
+#[derive(Serialize)]
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix: Option<String>,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn fix(mut self, fix: impl Into<String>) -> Self {
+        self.fix = Some(fix.into());
+        self
+    }
+
+    fn print(&self) {
+        let marker = match self.status {
+            Status::Ok => "✓".green(),
+            Status::Warn => "!".yellow(),
+            Status::Fail => "✗".red(),
+            Status::Skip => "-".dimmed(),
+        };
+        println!("{} {}: {}", marker, self.name, self.detail);
+        if let Some(fix) = &self.fix {
+            println!("    {} {}", "fix:".dimmed(), fix);
+        }
+    }
+}
+
+/// Results of every check, in the order they ran.
+#[derive(Serialize)]
+struct DoctorOutput {
+    checks: Vec<Check>,
+}
+
+impl Report for DoctorOutput {
+    fn print_text(&self) {
+        for check in &self.checks {
+            check.print();
+        }
+    }
+}
+
+pub async fn run(_args: DoctorArgs, format: Format) -> Result<()> {
+    let mut checks = Vec::new();
+
+    let stored = check_session_file(&mut checks);
+    checks.push(Check::new(
+        "Keyring",
+        Status::Skip,
+        "not used; credentials are kept in the session file",
+    ));
+
+    match stored.as_ref().map(|s| PdsUrl::new(&s.pds)) {
+        Some(Ok(pds_url)) => {
+            check_pds(&mut checks, &pds_url).await;
+            // Loading a stale session would remove it; report it instead.
+            match stored.as_ref().and_then(|s| s.staleness(Utc::now())) {
+                Some(staleness) => checks.push(
+                    Check::new("Session", Status::Fail, format!("stale: {}", staleness)).fix(
+                        "Run 'atproto pds login' again; the next command removes the stale session",
+                    ),
+                ),
+                None => check_session(&mut checks).await,
+            }
+            check_firehose(&mut checks, &pds_url).await;
+        }
+        Some(Err(e)) => checks.push(
+            Check::new(
+                "PDS",
+                Status::Fail,
+                format!("invalid PDS URL in session: {}", e),
+            )
+            .fix("Run 'atproto pds login' again"),
+        ),
+        None => {
+            for name in ["PDS", "Session", "Firehose"] {
+                checks.push(Check::new(name, Status::Skip, "no session"));
+            }
+        }
+    }
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    output::report(format, &DoctorOutput { checks })?;
+
+    if failed > 0 {
+        anyhow::bail!("{} check(s) failed", failed);
+    }
+    Ok(())
+}
+
+/// Check the session file parses and is private.
+fn check_session_file(checks: &mut Vec<Check>) -> Option<StoredSession> {
+    let path = match storage::session_path() {
+        Ok(path) => path,
+        Err(e) => {
+            checks.push(
+                Check::new("Session file", Status::Fail, e.to_string())
+                    .fix("Set ATPROTO_DATA_DIR to a writable directory"),
+            );
+            return None;
+        }
+    };
+    let shown = path.display().to_string();
+
+    match storage::read_stored_session() {
+        Ok(Some(stored)) => {
+            checks.push(session_file_permissions(&path, &shown));
+            Some(stored)
+        }
+        Ok(None) => {
+            checks.push(
+                Check::new(
+                    "Session file",
+                    Status::Warn,
+                    format!("not found at {}", shown),
+                )
+                .fix("Run 'atproto pds login'"),
+            );
+            None
+        }
+        Err(e) => {
+            checks.push(
+                Check::new("Session file", Status::Fail, format!("{:#} ({})", e, shown)).fix(
+                    format!("Delete {} and run 'atproto pds login' again", shown),
+                ),
+            );
+            None
+        }
+    }
+}
+
+#[cfg(unix)]
+fn session_file_permissions(path: &Path, shown: &str) -> Check {
+    use std::os::unix::fs::PermissionsExt;
+
+    match std::fs::metadata(path).map(|m| m.permissions().mode() & 0o777) {
+        Ok(mode) if mode & 0o077 != 0 => Check::new(
+            "Session file",
+            Status::Warn,
+            format!("{} is readable by other users (mode {:o})", shown, mode),
+        )
+        .fix(format!("chmod 600 {}", shown)),
+        _ => Check::new("Session file", Status::Ok, shown),
+    }
+}
+
+#[cfg(not(unix))]
+fn session_file_permissions(_path: &Path, shown: &str) -> Check {
+    Check::new("Session file", Status::Ok, shown)
+}
+
+/// Check a network PDS answers, or a local PDS root is usable.
+async fn check_pds(checks: &mut Vec<Check>, pds_url: &PdsUrl) {
+    if pds_url.is_local() {
+        let Some(path) = pds_url.to_file_path() else {
+            checks.push(
+                Check::new(
+                    "PDS",
+                    Status::Fail,
+                    format!("{} is not a valid path", pds_url),
+                )
+                .fix("Run 'atproto pds login' with a valid --pds file:// URL"),
+            );
+            return;
+        };
+        let pds = FilePds::new(&path, pds_url.clone());
+        let root = path.display();
+
+        if !path.join("pds").is_dir() {
+            checks.push(
+                Check::new("PDS", Status::Fail, format!("no local PDS at {}", root)).fix(
+                    "Create an account there with 'atproto pds create-account', or log in to another PDS",
+                ),
+            );
+            return;
+        }
+        let accounts = match pds.account_count() {
+            Ok(n) => n,
+            Err(e) => {
+                checks.push(
+                    Check::new(
+                        "PDS",
+                        Status::Fail,
+                        format!("cannot read accounts in {}: {}", root, e),
+                    )
+                    .fix("Check the directory's permissions and contents"),
+                );
+                return;
+            }
+        };
+        if !pds.is_writable() {
+            checks.push(
+                Check::new("PDS", Status::Warn, format!("{} is read-only", root))
+                    .fix("Fix the directory's permissions to create or delete records"),
+            );
+            return;
+        }
+        checks.push(Check::new(
+            "PDS",
+            Status::Ok,
+            format!("local PDS at {} with {} account(s)", root, accounts),
+        ));
+    } else {
+        match XrpcPds::new(pds_url.clone()).describe().await {
+            Ok(description) => checks.push(Check::new(
+                "PDS",
+                Status::Ok,
+                format!("{} is reachable ({})", pds_url, description.did),
+            )),
+            Err(e) => checks.push(
+                Check::new(
+                    "PDS",
+                    Status::Fail,
+                    format!("{} is not reachable: {}", pds_url, e),
+                )
+                .fix("Check the PDS URL and your network connection"),
+            ),
+        }
+    }
+}
+
+/// Check the session is accepted and its tokens are current.
+async fn check_session(checks: &mut Vec<Check>) {
+    let session = match storage::load_session().await {
+        Ok(Some(session)) => session,
+        Ok(None) => return,
+        Err(e) => {
+            checks.push(
+                Check::new("Session", Status::Fail, format!("{:#}", e))
+                    .fix("Run 'atproto pds login' again"),
+            );
+            return;
+        }
+    };
+
+    let valid = match &session {
+        CliSession::File(s) => s.validate(),
+        CliSession::Xrpc(s) => s.validate().await,
+    };
+    match valid {
+        Ok(()) => checks.push(Check::new(
+            "Session",
+            Status::Ok,
+            format!("valid for {}", session.did()),
+        )),
+        Err(e) => checks.push(
+            Check::new(
+                "Session",
+                Status::Fail,
+                format!("rejected by the PDS: {}", e),
+            )
+            .fix("Run 'atproto pds login' again"),
+        ),
+    }
+
+    if session.as_xrpc().is_some() {
+        checks.push(check_token_expiry(&session));
+    }
+}
+
+fn check_token_expiry(session: &CliSession) -> Check {
+    let access = jwt_expiry(session.access_token().as_str());
+    let refresh = session
+        .refresh_token()
+        .and_then(|token| jwt_expiry(token.as_str()));
+
+    let Some(refresh) = refresh else {
+        return Check::new("Token expiry", Status::Skip, "tokens carry no expiry");
+    };
+
+    let now = Utc::now();
+    let detail = format!(
+        "access expires {}, refresh expires {}",
+        format_time(access),
+        format_time(Some(refresh))
+    );
+
+    if refresh <= now {
+        Check::new("Token expiry", Status::Fail, detail).fix("Run 'atproto pds login' again")
+    } else if refresh - now < chrono::Duration::days(REFRESH_WARN_DAYS) {
+        Check::new("Token expiry", Status::Warn, detail)
+            .fix("Log in again soon with 'atproto pds login'")
+    } else {
+        Check::new("Token expiry", Status::Ok, detail)
+    }
+}
+
+fn format_time(time: Option<DateTime<Utc>>) -> String {
+    time.map_or_else(|| "at an unknown time".to_string(), |t| t.to_rfc3339())
+}
+
+/// Check the firehose can be opened and does not error straight away.
+async fn check_firehose(checks: &mut Vec<Check>, pds_url: &PdsUrl) {
+    let stream: muat_core::Result<Pin<Box<dyn Firehose>>> = if pds_url.is_local() {
+        // An invalid path is already reported by the PDS check.
+        let Some(path) = pds_url.to_file_path() else {
+            return;
+        };
+        FilePds::new(&path, pds_url.clone())
+            .firehose()
+            .map(|f| Box::pin(f) as Pin<Box<dyn Firehose>>)
+    } else {
+        XrpcPds::new(pds_url.clone())
+            .firehose()
+            .map(|f| Box::pin(f) as Pin<Box<dyn Firehose>>)
+    };
+
+    let mut stream = match stream {
+        Ok(stream) => stream,
+        Err(e) => {
+            checks.push(
+                Check::new("Firehose", Status::Fail, e.to_string())
+                    .fix("Check the PDS URL and that WebSocket traffic is allowed"),
+            );
+            return;
+        }
+    };
+
+    let check = match tokio::time::timeout(FIREHOSE_WAIT, stream.next()).await {
+        Ok(Some(Ok(_))) => Check::new("Firehose", Status::Ok, "connected and receiving events"),
+        Err(_) => Check::new(
+            "Firehose",
+            Status::Ok,
+            format!("connected; no events within {}s", FIREHOSE_WAIT.as_secs()),
+        ),
+        Ok(Some(Err(e))) => Check::new("Firehose", Status::Fail, e.to_string())
+            .fix("Check the PDS URL and that WebSocket traffic is allowed"),
+        Ok(None) => Check::new("Firehose", Status::Fail, "closed immediately")
+            .fix("Check the PDS URL and that WebSocket traffic is allowed"),
+    };
+    checks.push(check);
+}
@@ -0,0 +1,157 @@
+//! History command implementation.
+//!
+//! Lists and reruns the invocations recorded when `ATPROTO_HISTORY` is set.
+
+use anyhow::{Context, Result, bail};
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use serde::Serialize;
+
+use crate::commands::plugin::PluginExit;
+use crate::history::{self, Entry};
+use crate::output::{self, Format, Report};
+
+#[derive(Args, Debug)]
+pub struct HistoryCommand {
+    #[command(subcommand)]
+    pub command: HistorySubcommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum HistorySubcommand {
+    /// List recorded commands, oldest first
+    List(ListArgs),
+
+    /// Run a recorded command again
+    Rerun(RerunArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ListArgs {
+    /// Show only the last N entries
+    #[arg(long, short = 'n', default_value_t = 20)]
+    pub limit: usize,
+}
+
+#[derive(Args, Debug)]
+pub struct RerunArgs {
+    /// Entry number, as shown by `history list`
+    pub n: usize,
+}
+
+pub async fn handle(cmd: HistoryCommand, format: Format) -> Result<()> {
+    match cmd.command {
+        HistorySubcommand::List(args) => list(args, format),
+        HistorySubcommand::Rerun(args) => rerun(args).await,
+    }
+}
+
+/// A recorded command and its number.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NumberedEntry {
+    n: usize,
+    #[serde(flatten)]
+    entry: Entry,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HistoryOutput {
+    recording: bool,
+    entries: Vec<NumberedEntry>,
+}
+
+impl Report for HistoryOutput {
+    fn print_text(&self) {
+        if self.entries.is_empty() {
+            println!("No commands recorded.");
+        }
+        for NumberedEntry { n, entry } in &self.entries {
+            let outcome = if entry.ok() {
+                "ok".green()
+            } else {
+                format!("exit {}", entry.exit_code).red()
+            };
+            println!(
+                "{:>4}  {}  {:>7}  {}  atproto {}",
+                n,
+                entry
+                    .started_at
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S"),
+                format!("{}ms", entry.duration_ms),
+                outcome,
+                entry.args.join(" ")
+            );
+            for uri in &entry.uris {
+                println!("      {}", uri.dimmed());
+            }
+        }
+        if !self.recording {
+            println!(
+                "{}",
+                format!("History is off. Set {}=1 to record commands.", history::ENV).dimmed()
+            );
+        }
+    }
+}
+
+fn list(args: ListArgs, format: Format) -> Result<()> {
+    let entries = history::read()?;
+    let skip = entries.len().saturating_sub(args.limit);
+    let entries = entries
+        .into_iter()
+        .enumerate()
+        .skip(skip)
+        .map(|(i, entry)| NumberedEntry { n: i + 1, entry })
+        .collect();
+
+    output::report(
+        format,
+        &HistoryOutput {
+            recording: history::enabled_path()?.is_some(),
+            entries,
+        },
+    )
+}
+
+async fn rerun(args: RerunArgs) -> Result<()> {
+    let entries = history::read()?;
+    let Some(entry) = args.n.checked_sub(1).and_then(|i| entries.get(i)) else {
+        bail!(
+            "No history entry {}; there are {}. Run 'atproto history list' to see them.",
+            args.n,
+            entries.len()
+        );
+    };
+    if entry.redacted() {
+        bail!(
+            "History entry {} had secrets redacted and cannot be rerun as recorded.",
+            args.n
+        );
+    }
+
+    eprintln!("{} atproto {}", "Rerunning:".dimmed(), entry.args.join(" "));
+    let exe = std::env::current_exe().context("Failed to find the atproto executable")?;
+    let mut command = tokio::process::Command::new(exe);
+    command.args(&entry.args);
+    // Relative paths such as `file://./pds` resolve as they did.
+    if let Some(cwd) = entry.cwd.as_ref().filter(|cwd| cwd.is_dir()) {
+        command.current_dir(cwd);
+    }
+
+    let status = command
+        .status()
+        .await
+        .context("Failed to rerun the command")?;
+    if status.success() {
+        return Ok(());
+    }
+    // The command has reported its own failure.
+    let code = status
+        .code()
+        .and_then(|code| u8::try_from(code).ok())
+        .unwrap_or(crate::exit::FAILURE);
+    Err(PluginExit(code).into())
+}
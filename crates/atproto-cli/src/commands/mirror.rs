@@ -0,0 +1,166 @@
+//! Mirror command implementation.
+//!
+//! Copies repos from a PDS into a local PDS directory and keeps them up to
+//! date from the source's firehose until stopped with Ctrl-C. The firehose
+//! cursor is kept next to the copies, so a restarted mirror resumes where it
+//! left off.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use clap::{Args, Subcommand};
+use colored::Colorize;
+use serde::Serialize;
+
+use muat_core::traits::Pds;
+use muat_core::{CancellationToken, Did, PdsUrl};
+use muat_file::{FileCursorStore, FilePds, Mirror, MirrorReport};
+use muat_xrpc::XrpcPds;
+
+use crate::output::{self, Format, Report};
+
+/// Name of the cursors file kept in the mirror's root.
+const CURSORS_FILE: &str = "mirror-cursors.json";
+
+#[derive(Args, Debug)]
+pub struct MirrorCommand {
+    #[command(subcommand)]
+    pub command: MirrorSubcommand,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MirrorSubcommand {
+    /// Copy repos into a local PDS directory and follow their changes
+    Start(StartArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct StartArgs {
+    /// DIDs of the repos to mirror
+    #[arg(required = true, value_name = "DID")]
+    pub repos: Vec<String>,
+
+    /// PDS hosting the repos (https:// or file://)
+    #[arg(long)]
+    pub source: String,
+
+    /// Local PDS directory to keep the copies in
+    #[arg(long, default_value = "./mirror")]
+    pub root: PathBuf,
+
+    /// Copy the repos once and exit instead of following the firehose
+    #[arg(long)]
+    pub once: bool,
+}
+
+pub async fn handle(cmd: MirrorCommand, format: Format) -> Result<()> {
+    match cmd.command {
+        MirrorSubcommand::Start(args) => start(args, format).await,
+    }
+}
+
+/// What the mirror copied, printed when it stops.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MirrorOutput {
+    source: String,
+    root: String,
+    repos: Vec<String>,
+    created: usize,
+    updated: usize,
+    deleted: usize,
+    unchanged: usize,
+    commits: u64,
+    cursor: Option<i64>,
+}
+
+impl Report for MirrorOutput {
+    fn print_text(&self) {
+        output::field("Source", &self.source);
+        output::field("Root", &self.root);
+        output::field("Repos", &self.repos.join(", "));
+        output::field(
+            "Backfill",
+            &format!(
+                "{} created, {} updated, {} deleted, {} unchanged",
+                self.created, self.updated, self.deleted, self.unchanged
+            ),
+        );
+        output::field("Commits", &self.commits.to_string());
+        output::field(
+            "Cursor",
+            &self
+                .cursor
+                .map_or_else(|| "none".to_string(), |seq| seq.to_string()),
+        );
+    }
+}
+
+async fn start(args: StartArgs, format: Format) -> Result<()> {
+    let repos = args
+        .repos
+        .iter()
+        .map(|repo| Did::new(repo).with_context(|| format!("Invalid DID: {}", repo)))
+        .collect::<Result<Vec<_>>>()?;
+    let source_url = PdsUrl::new(&args.source).context("Invalid source PDS URL")?;
+
+    let local_url =
+        PdsUrl::new(format!("file://{}", args.root.display())).context("Invalid mirror root")?;
+    let local = FilePds::new(&args.root, local_url);
+    if !local.is_writable() {
+        bail!("Mirror root {} is not writable", args.root.display());
+    }
+    let cursors = Arc::new(FileCursorStore::new(args.root.join(CURSORS_FILE)));
+
+    let report = if source_url.is_local() {
+        let path = source_url
+            .to_file_path()
+            .context("Failed to convert file:// URL to path")?;
+        let source = FilePds::new(&path, source_url);
+        run(Mirror::new(source, local, &repos), cursors, args.once).await?
+    } else {
+        let source = XrpcPds::new(source_url);
+        run(Mirror::new(source, local, &repos), cursors, args.once).await?
+    };
+
+    output::report(
+        format,
+        &MirrorOutput {
+            source: args.source,
+            root: args.root.display().to_string(),
+            repos: args.repos,
+            created: report.created,
+            updated: report.updated,
+            deleted: report.deleted,
+            unchanged: report.unchanged,
+            commits: report.commits,
+            cursor: report.cursor,
+        },
+    )
+}
+
+async fn run<P: Pds>(
+    mirror: Mirror<P>,
+    cursors: Arc<FileCursorStore>,
+    once: bool,
+) -> Result<MirrorReport> {
+    if once {
+        eprintln!("{}", "Copying repos...".dimmed());
+        return mirror.backfill().await.context("Mirror failed");
+    }
+
+    eprintln!("{}", "Mirroring; press Ctrl-C to stop.".dimmed());
+    let cancel = CancellationToken::new();
+    let on_interrupt = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            on_interrupt.cancel();
+        }
+    });
+    mirror
+        .with_cursor_store(cursors)
+        .run(cancel)
+        .await
+        .context("Mirror failed")
+}
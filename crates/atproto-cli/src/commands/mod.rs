@@ -1,3 +1,10 @@
 //! CLI command implementations.
 
+pub mod batch;
+pub mod bsky;
+pub mod doctor;
+pub mod history;
+pub mod mirror;
 pub mod pds;
+pub mod plugin;
+pub mod serve;
@@ -0,0 +1,66 @@
+//! Account status command implementation.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use serde::Serialize;
+
+use muat_core::AccountStatus;
+use muat_core::traits::Session;
+
+use crate::output::{self, Format, Report};
+use crate::session::storage;
+
+#[derive(Args, Debug)]
+pub struct AccountStatusArgs {}
+
+/// The session's account and its status.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountStatusOutput {
+    did: String,
+    #[serde(flatten)]
+    status: AccountStatus,
+}
+
+impl Report for AccountStatusOutput {
+    fn print_text(&self) {
+        let status = &self.status;
+        output::field("DID", &self.did);
+        output::field(
+            "Status",
+            if status.activated {
+                "active"
+            } else {
+                "deactivated"
+            },
+        );
+        output::field("Valid DID", &status.valid_did.to_string());
+        if let Some(commit) = &status.repo_commit {
+            output::field("Commit", commit);
+        }
+        output::field("Rev", status.repo_rev.as_deref().unwrap_or("none"));
+        output::field("Records", &status.indexed_records.to_string());
+        output::field("Blocks", &status.repo_blocks.to_string());
+        output::field(
+            "Blobs",
+            &format!("{} of {}", status.imported_blobs, status.expected_blobs),
+        );
+    }
+}
+
+pub async fn run(_args: AccountStatusArgs, format: Format) -> Result<()> {
+    let session = storage::require_session().await?;
+
+    let status = session
+        .check_account_status()
+        .await
+        .context("Failed to check account status")?;
+
+    output::report(
+        format,
+        &AccountStatusOutput {
+            did: session.did().to_string(),
+            status,
+        },
+    )
+}
@@ -0,0 +1,42 @@
+//! Activate account command implementation.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use serde::Serialize;
+
+use muat_core::traits::Session;
+
+use crate::output::{self, Format, Report};
+use crate::session::storage;
+
+#[derive(Args, Debug)]
+pub struct ActivateAccountArgs {}
+
+/// The activated account.
+#[derive(Serialize)]
+struct ActivateAccountOutput {
+    did: String,
+}
+
+impl Report for ActivateAccountOutput {
+    fn print_text(&self) {
+        output::success("Account activated");
+        output::field("DID", &self.did);
+    }
+}
+
+pub async fn run(_args: ActivateAccountArgs, format: Format) -> Result<()> {
+    let session = storage::require_session().await?;
+
+    session
+        .activate_account()
+        .await
+        .context("Failed to activate account")?;
+
+    output::report(
+        format,
+        &ActivateAccountOutput {
+            did: session.did().to_string(),
+        },
+    )
+}
@@ -0,0 +1,109 @@
+//! Shared options and output for bulk record commands.
+
+use anyhow::Result;
+use clap::Args;
+use serde::Serialize;
+
+use muat_core::{AtUri, BulkReport, CancellationToken};
+
+use crate::output::{self, Format, Report};
+
+#[derive(Args, Debug)]
+pub struct BulkArgs {
+    /// Maximum requests in flight
+    #[arg(long, default_value_t = 4)]
+    pub concurrency: usize,
+
+    /// Exit successfully as long as no more than this many items fail
+    #[arg(long, default_value_t = 0)]
+    pub max_failures: usize,
+}
+
+/// The outcome of a bulk command.
+#[derive(Serialize)]
+struct BulkOutput {
+    succeeded: Vec<String>,
+    failed: Vec<BulkFailure>,
+    skipped: usize,
+    #[serde(skip)]
+    verb: &'static str,
+}
+
+/// An item that failed, and why.
+#[derive(Serialize)]
+struct BulkFailure {
+    item: String,
+    error: String,
+}
+
+impl Report for BulkOutput {
+    fn print_text(&self) {
+        for uri in &self.succeeded {
+            println!("{}", uri);
+        }
+        for failure in &self.failed {
+            output::error(&format!("{}: {}", failure.item, failure.error));
+        }
+        let total = self.succeeded.len() + self.failed.len() + self.skipped;
+        output::success(&format!(
+            "{} {} of {} records",
+            self.verb,
+            self.succeeded.len(),
+            total
+        ));
+        if self.skipped > 0 {
+            output::error(&format!("Stopped; {} records not attempted", self.skipped));
+        }
+    }
+}
+
+/// A token cancelled by Ctrl+C, so in-flight writes finish before exiting.
+pub fn interrupt_token() -> CancellationToken {
+    let cancel = CancellationToken::new();
+    let on_interrupt = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            on_interrupt.cancel();
+        }
+    });
+    cancel
+}
+
+/// Print a bulk report, failing only if more than `max_failures` items failed.
+///
+/// The error carries the first failure, so the exit code reflects its kind.
+pub fn finish<I>(
+    format: Format,
+    verb: &'static str,
+    report: BulkReport<I, AtUri>,
+    describe: impl Fn(&I) -> String,
+    max_failures: usize,
+) -> Result<()> {
+    let total = report.len();
+    let first_error = report.failed.first().map(|(_, e)| e.clone());
+    let output = BulkOutput {
+        succeeded: report.succeeded.iter().map(ToString::to_string).collect(),
+        failed: report
+            .failed
+            .iter()
+            .map(|(item, error)| BulkFailure {
+                item: describe(item),
+                error: error.to_string(),
+            })
+            .collect(),
+        skipped: report.skipped.len(),
+        verb,
+    };
+    output::report(format, &output)?;
+
+    match first_error {
+        Some(error) if output.failed.len() > max_failures => Err(anyhow::Error::new(error)
+            .context(format!(
+                "{} of {} records failed (allowed: {})",
+                output.failed.len(),
+                total,
+                max_failures
+            ))),
+        _ => Ok(()),
+    }
+}
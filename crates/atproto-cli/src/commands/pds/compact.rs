@@ -0,0 +1,88 @@
+//! Compact command implementation.
+//!
+//! This command rewrites the record files of a local filesystem-backed PDS
+//! in a single storage format, compressing or decompressing them in place.
+//! It is not supported for remote PDS instances.
+
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use serde::Serialize;
+
+use muat_core::PdsUrl;
+use muat_file::{CompactionStats, Compression, FilePds};
+
+use crate::output::{self, Format, Report};
+
+#[derive(Args, Debug)]
+pub struct CompactArgs {
+    /// zstd compression level to use
+    #[arg(long, default_value_t = 3, conflicts_with = "decompress")]
+    pub level: i32,
+
+    /// Rewrite compressed records as plain JSON instead
+    #[arg(long)]
+    pub decompress: bool,
+
+    /// PDS URL (must be file://)
+    #[arg(long, default_value = "file://./pds")]
+    pub pds: String,
+}
+
+/// Compaction counts.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CompactOutput {
+    records: usize,
+    rewritten: usize,
+    bytes_before: u64,
+    bytes_after: u64,
+}
+
+impl From<CompactionStats> for CompactOutput {
+    fn from(stats: CompactionStats) -> Self {
+        Self {
+            records: stats.records,
+            rewritten: stats.rewritten,
+            bytes_before: stats.bytes_before,
+            bytes_after: stats.bytes_after,
+        }
+    }
+}
+
+impl Report for CompactOutput {
+    fn print_text(&self) {
+        output::field("Records", &self.records.to_string());
+        output::field("Rewritten", &self.rewritten.to_string());
+        output::field("Bytes before", &self.bytes_before.to_string());
+        output::field("Bytes after", &self.bytes_after.to_string());
+        output::success("Compaction complete");
+    }
+}
+
+pub async fn run(args: CompactArgs, format: Format) -> Result<()> {
+    let pds_url = PdsUrl::new(&args.pds).context("Invalid PDS URL")?;
+
+    if !pds_url.is_local() {
+        bail!(
+            "Compaction is only supported for local PDS storage.\n\
+             Use a file:// URL (e.g., file://./pds)"
+        );
+    }
+
+    let path = pds_url
+        .to_file_path()
+        .context("Failed to convert file:// URL to path")?;
+
+    let compression = if args.decompress {
+        Compression::None
+    } else {
+        Compression::Zstd(args.level)
+    };
+
+    let backend = FilePds::new(&path, pds_url).with_compression(compression);
+    let stats = backend
+        .compact_records()
+        .context("Failed to compact records")?;
+
+    output::report(format, &CompactOutput::from(stats))
+}
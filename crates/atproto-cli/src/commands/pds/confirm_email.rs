@@ -0,0 +1,53 @@
+//! Confirm email command implementation.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use serde::Serialize;
+
+use muat_core::traits::Session;
+
+use crate::output::{self, Format, Report};
+use crate::session::storage;
+
+#[derive(Args, Debug)]
+pub struct ConfirmEmailArgs {
+    /// The account's email address
+    #[arg(long)]
+    pub email: String,
+
+    /// Token from the confirmation email
+    #[arg(long)]
+    pub token: String,
+}
+
+/// The confirmed address.
+#[derive(Serialize)]
+struct ConfirmEmailOutput {
+    did: String,
+    email: String,
+}
+
+impl Report for ConfirmEmailOutput {
+    fn print_text(&self) {
+        output::success("Email confirmed");
+        output::field("DID", &self.did);
+        output::field("Email", &self.email);
+    }
+}
+
+pub async fn run(args: ConfirmEmailArgs, format: Format) -> Result<()> {
+    let session = storage::require_session().await?;
+
+    session
+        .confirm_email(&args.email, &args.token)
+        .await
+        .context("Failed to confirm email")?;
+
+    output::report(
+        format,
+        &ConfirmEmailOutput {
+            did: session.did().to_string(),
+            email: args.email,
+        },
+    )
+}
@@ -5,12 +5,13 @@
 
 use anyhow::{Context, Result, bail};
 use clap::Args;
+use serde::Serialize;
 
 use muat_core::PdsUrl;
 use muat_core::traits::Pds;
 use muat_file::FilePds;
 
-use crate::output;
+use crate::output::{self, Format, Report};
 
 #[derive(Args, Debug)]
 pub struct CreateAccountArgs {
@@ -21,12 +22,33 @@ pub struct CreateAccountArgs {
     #[arg(long)]
     pub password: String,
 
+    /// Email address, for email confirmation and password resets
+    #[arg(long)]
+    pub email: Option<String>,
+
     /// PDS URL (must be file://)
     #[arg(long, default_value = "file://./pds")]
     pub pds: String,
 }
 
-pub async fn run(args: CreateAccountArgs) -> Result<()> {
+/// The created account.
+#[derive(Serialize)]
+struct CreateAccountOutput {
+    did: String,
+    handle: String,
+    pds: String,
+}
+
+impl Report for CreateAccountOutput {
+    fn print_text(&self) {
+        output::field("DID", &self.did);
+        output::field("Handle", &self.handle);
+        output::field("PDS", &self.pds);
+        output::success("Account created successfully");
+    }
+}
+
+pub async fn run(args: CreateAccountArgs, format: Format) -> Result<()> {
     let pds_url = PdsUrl::new(&args.pds).context("Invalid PDS URL")?;
 
     if !pds_url.is_local() {
@@ -42,15 +64,22 @@ pub async fn run(args: CreateAccountArgs) -> Result<()> {
         .context("Failed to convert file:// URL to path")?;
 
     let backend = FilePds::new(&path, pds_url);
-    let output = backend
-        .create_account(&args.handle, Some(&args.password), None, None)
+    let created = backend
+        .create_account(
+            &args.handle,
+            Some(&args.password),
+            args.email.as_deref(),
+            None,
+        )
         .await
         .context("Failed to create account")?;
 
-    output::field("DID", output.did.as_str());
-    output::field("Handle", &output.handle);
-    output::field("PDS", &args.pds);
-    output::success("Account created successfully");
-
-    Ok(())
+    output::report(
+        format,
+        &CreateAccountOutput {
+            did: created.did.to_string(),
+            handle: created.handle,
+            pds: args.pds,
+        },
+    )
 }
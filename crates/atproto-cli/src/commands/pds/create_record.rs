@@ -2,14 +2,17 @@
 
 use std::io::{self, Read};
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use clap::Args;
+use serde::Serialize;
 use serde_json::Value;
 
 use muat_core::traits::Session;
 use muat_core::{Nsid, RecordValue};
 
-use crate::output;
+use crate::form::RecordForm;
+use crate::lexicon::Lexicon;
+use crate::output::{self, Format, Report};
 use crate::session::storage;
 
 #[derive(Args, Debug)]
@@ -22,20 +25,48 @@ pub struct CreateRecordArgs {
     pub record_type: String,
 
     /// JSON file with record data (use - for stdin)
-    #[arg(long)]
+    #[arg(long, conflicts_with = "interactive")]
     pub json: Option<String>,
+
+    /// Lexicon schema to validate the record against (path or URL)
+    #[arg(long)]
+    pub lexicon: Option<String>,
+
+    /// Prompt for each field of the lexicon instead of reading JSON
+    #[arg(long, short = 'i', requires = "lexicon")]
+    pub interactive: bool,
 }
 
-pub async fn run(args: CreateRecordArgs) -> Result<()> {
-    let session = storage::load_session()
-        .await
-        .context("Failed to load session")?
-        .context("No active session. Run 'atproto pds login' first.")?;
+/// The created record.
+#[derive(Serialize)]
+struct CreateRecordOutput {
+    uri: String,
+}
+
+impl Report for CreateRecordOutput {
+    fn print_text(&self) {
+        println!("{}", self.uri);
+        output::success(&format!("Created record: {}", self.uri));
+    }
+}
+
+pub async fn run(args: CreateRecordArgs, format: Format) -> Result<()> {
+    let session = storage::require_session().await?;
 
     let collection = Nsid::new(&args.collection).context("Invalid collection NSID")?;
 
-    // Read base JSON if provided
-    let base_value: Value = if let Some(ref path) = args.json {
+    let lexicon = match args.lexicon {
+        Some(ref source) => Some(Lexicon::load(source).await?),
+        None => None,
+    };
+
+    // Build the record from prompts, or read base JSON if provided
+    let base_value: Value = if args.interactive {
+        let lexicon = lexicon
+            .as_ref()
+            .context("--interactive requires --lexicon")?;
+        RecordForm::new(lexicon, io::stdin().lock(), io::stderr()).prompt_record()?
+    } else if let Some(ref path) = args.json {
         if path == "-" {
             let mut buf = String::new();
             io::stdin()
@@ -54,15 +85,31 @@ pub async fn run(args: CreateRecordArgs) -> Result<()> {
     let record_value =
         RecordValue::with_type(&args.record_type, base_value).context("Invalid record value")?;
 
+    // Validate against the lexicon before sending anything
+    if let Some(ref lexicon) = lexicon {
+        let errors = lexicon.validate_record(record_value.as_value())?;
+        if !errors.is_empty() {
+            for error in &errors {
+                output::error(&error.to_string());
+            }
+            bail!(
+                "Record does not match lexicon {} ({} error(s))",
+                lexicon.id(),
+                errors.len()
+            );
+        }
+    }
+
     // Create the record
     let uri = session
         .create_record(&collection, &record_value)
         .await
         .context("Failed to create record")?;
 
-    // Output the created record's URI
-    println!("{}", uri);
-    output::success(&format!("Created record: {}", uri));
-
-    Ok(())
+    output::report(
+        format,
+        &CreateRecordOutput {
+            uri: uri.to_string(),
+        },
+    )
 }
@@ -0,0 +1,78 @@
+//! Bulk create records command implementation.
+
+use std::io::{self, Read};
+
+use anyhow::{Context, Result};
+use clap::Args;
+use serde_json::Value;
+
+use muat_core::traits::Session;
+use muat_core::{Nsid, RecordValue};
+
+use super::bulk::{self, BulkArgs};
+use crate::output::Format;
+use crate::session::storage;
+
+#[derive(Args, Debug)]
+pub struct CreateRecordsArgs {
+    /// Collection NSID (e.g., org.example.record)
+    pub collection: String,
+
+    /// Record type ($type field value)
+    #[arg(long = "type", short = 't')]
+    pub record_type: String,
+
+    /// JSON Lines file with one record per line (use - for stdin)
+    #[arg(long)]
+    pub jsonl: String,
+
+    #[command(flatten)]
+    pub bulk: BulkArgs,
+}
+
+pub async fn run(args: CreateRecordsArgs, format: Format) -> Result<()> {
+    let session = storage::require_session().await?;
+
+    let collection = Nsid::new(&args.collection).context("Invalid collection NSID")?;
+
+    let content = if args.jsonl == "-" {
+        let mut buf = String::new();
+        io::stdin()
+            .read_to_string(&mut buf)
+            .context("Failed to read from stdin")?;
+        buf
+    } else {
+        std::fs::read_to_string(&args.jsonl).context("Failed to read JSON Lines file")?
+    };
+
+    // Check every line before writing anything.
+    let mut values = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_number = index + 1;
+        let value: Value = serde_json::from_str(line)
+            .with_context(|| format!("Invalid JSON on line {}", line_number))?;
+        let value = RecordValue::with_type(&args.record_type, value)
+            .with_context(|| format!("Invalid record value on line {}", line_number))?;
+        values.push(value);
+    }
+
+    let report = session
+        .create_records_bulk(
+            &collection,
+            values,
+            args.bulk.concurrency,
+            Some(&bulk::interrupt_token()),
+        )
+        .await;
+
+    bulk::finish(
+        format,
+        "Created",
+        report,
+        |value| value.as_value().to_string(),
+        args.bulk.max_failures,
+    )
+}
@@ -0,0 +1,55 @@
+//! Deactivate account command implementation.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::Args;
+use serde::Serialize;
+
+use muat_core::traits::Session;
+
+use crate::output::{self, Format, Report};
+use crate::session::storage;
+
+#[derive(Args, Debug)]
+pub struct DeactivateAccountArgs {
+    /// Allow the PDS to delete the account after this time (RFC 3339)
+    #[arg(long)]
+    pub delete_after: Option<DateTime<Utc>>,
+}
+
+/// The deactivated account.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DeactivateAccountOutput {
+    did: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    delete_after: Option<DateTime<Utc>>,
+}
+
+impl Report for DeactivateAccountOutput {
+    fn print_text(&self) {
+        output::success("Account deactivated");
+        output::field("DID", &self.did);
+        if let Some(delete_after) = &self.delete_after {
+            output::field("Delete after", &delete_after.to_rfc3339());
+        }
+        println!("Reactivate it with 'atproto pds activate-account'.");
+    }
+}
+
+pub async fn run(args: DeactivateAccountArgs, format: Format) -> Result<()> {
+    let session = storage::require_session().await?;
+
+    session
+        .deactivate_account(args.delete_after)
+        .await
+        .context("Failed to deactivate account")?;
+
+    output::report(
+        format,
+        &DeactivateAccountOutput {
+            did: session.did().to_string(),
+            delete_after: args.delete_after,
+        },
+    )
+}
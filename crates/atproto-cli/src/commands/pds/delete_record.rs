@@ -2,11 +2,12 @@
 
 use anyhow::{Context, Result};
 use clap::Args;
+use serde::Serialize;
 
 use muat_core::traits::Session;
 use muat_core::{AtUri, Did, Nsid, Rkey};
 
-use crate::output;
+use crate::output::{self, Format, Report};
 use crate::session::storage;
 
 #[derive(Args, Debug)]
@@ -27,11 +28,20 @@ pub struct DeleteRecordArgs {
     pub rkey: Option<String>,
 }
 
-pub async fn run(args: DeleteRecordArgs) -> Result<()> {
-    let session = storage::load_session()
-        .await
-        .context("Failed to load session")?
-        .context("No active session. Run 'atproto pds login' first.")?;
+/// The deleted record.
+#[derive(Serialize)]
+struct DeleteRecordOutput {
+    uri: String,
+}
+
+impl Report for DeleteRecordOutput {
+    fn print_text(&self) {
+        output::success(&format!("Deleted: {}", self.uri));
+    }
+}
+
+pub async fn run(args: DeleteRecordArgs, format: Format) -> Result<()> {
+    let session = storage::require_session().await?;
 
     let uri = if let Some(uri_str) = &args.uri {
         AtUri::new(uri_str).context("Invalid AT URI")?
@@ -61,7 +71,10 @@ pub async fn run(args: DeleteRecordArgs) -> Result<()> {
         .await
         .context("Failed to delete record")?;
 
-    output::success(&format!("Deleted: {}", uri));
-
-    Ok(())
+    output::report(
+        format,
+        &DeleteRecordOutput {
+            uri: uri.to_string(),
+        },
+    )
 }
@@ -0,0 +1,71 @@
+//! Bulk delete records command implementation.
+
+use std::io::{self, Read};
+
+use anyhow::{Context, Result, bail};
+use clap::Args;
+
+use muat_core::AtUri;
+use muat_core::traits::Session;
+
+use super::bulk::{self, BulkArgs};
+use crate::output::Format;
+use crate::session::storage;
+
+#[derive(Args, Debug)]
+pub struct DeleteRecordsArgs {
+    /// AT URIs of the records to delete
+    pub uris: Vec<String>,
+
+    /// File with one AT URI per line (use - for stdin)
+    #[arg(long)]
+    pub from: Option<String>,
+
+    #[command(flatten)]
+    pub bulk: BulkArgs,
+}
+
+pub async fn run(args: DeleteRecordsArgs, format: Format) -> Result<()> {
+    let session = storage::require_session().await?;
+
+    let mut lines = args.uris;
+    if let Some(ref path) = args.from {
+        let content = if path == "-" {
+            let mut buf = String::new();
+            io::stdin()
+                .read_to_string(&mut buf)
+                .context("Failed to read from stdin")?;
+            buf
+        } else {
+            std::fs::read_to_string(path).context("Failed to read URI file")?
+        };
+        lines.extend(
+            content
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(String::from),
+        );
+    }
+    if lines.is_empty() {
+        bail!("No URIs given; pass them as arguments or with --from");
+    }
+
+    // Check every URI before deleting anything.
+    let uris = lines
+        .iter()
+        .map(|line| AtUri::new(line).with_context(|| format!("Invalid AT URI: {}", line)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let report = session
+        .delete_records_bulk(uris, args.bulk.concurrency, Some(&bulk::interrupt_token()))
+        .await;
+
+    bulk::finish(
+        format,
+        "Deleted",
+        report,
+        ToString::to_string,
+        args.bulk.max_failures,
+    )
+}
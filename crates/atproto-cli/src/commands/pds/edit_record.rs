@@ -0,0 +1,147 @@
+//! Edit record command implementation.
+
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use serde::Serialize;
+use serde_json::Value;
+
+use muat_core::traits::Session;
+use muat_core::{AtUri, RecordValue};
+
+use crate::lexicon::Lexicon;
+use crate::output::{self, Format, Report};
+use crate::session::storage;
+
+#[derive(Args, Debug)]
+pub struct EditRecordArgs {
+    /// AT URI of the record to edit (must be in the session repo)
+    pub uri: String,
+
+    /// Lexicon schema to validate the edited record against (path or URL)
+    #[arg(long)]
+    pub lexicon: Option<String>,
+}
+
+/// The edited record.
+#[derive(Serialize)]
+struct EditRecordOutput {
+    uri: String,
+    updated: bool,
+}
+
+impl Report for EditRecordOutput {
+    fn print_text(&self) {
+        if self.updated {
+            output::success(&format!("Updated record: {}", self.uri));
+        } else {
+            output::success(&format!("No changes to {}", self.uri));
+        }
+    }
+}
+
+pub async fn run(args: EditRecordArgs, format: Format) -> Result<()> {
+    let session = storage::require_session().await?;
+
+    let uri = AtUri::new(&args.uri).context("Invalid AT URI")?;
+    let (collection, rkey) = uri.record_path().context("Not a record URI")?;
+    if uri.repo() != session.did() {
+        bail!("Can only edit records in your own repo ({})", session.did());
+    }
+
+    let lexicon = match args.lexicon {
+        Some(ref source) => Some(Lexicon::load(source).await?),
+        None => None,
+    };
+
+    let record = session
+        .get_record(&uri)
+        .await
+        .context("Failed to get record")?;
+
+    let path =
+        std::env::temp_dir().join(format!("atproto-edit-{}-{}.json", rkey, std::process::id()));
+    let original = serde_json::to_string_pretty(record.value.as_value())?;
+    std::fs::write(&path, format!("{}\n", original)).context("Failed to write edit file")?;
+
+    // From here on a failure keeps the file, so the edit is not lost.
+    let edited = match edit(&path).and_then(|value| validate(value, lexicon.as_ref())) {
+        Ok(edited) => edited,
+        Err(e) => {
+            output::error(&format!("Your edit is kept in {}", path.display()));
+            return Err(e);
+        }
+    };
+
+    let updated = edited.as_value() != record.value.as_value();
+    if updated
+        && let Err(e) = session
+            .put_record(collection, rkey, &edited, Some(&record.cid))
+            .await
+    {
+        output::error(&format!("Your edit is kept in {}", path.display()));
+        if let muat_core::Error::Protocol(ref p) = e
+            && p.is_invalid_swap()
+        {
+            return Err(e).context("Record changed while you were editing it; edit it again");
+        }
+        return Err(e).context("Failed to update record");
+    }
+
+    let _ = std::fs::remove_file(&path);
+    output::report(
+        format,
+        &EditRecordOutput {
+            uri: uri.to_string(),
+            updated,
+        },
+    )
+}
+
+/// Open `path` in the user's editor and read back the JSON it holds.
+fn edit(path: &Path) -> Result<Value> {
+    let editor = editor();
+    let mut words = editor.split_whitespace();
+    let program = words.next().context("Editor command is empty")?;
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to run editor '{}'", editor))?;
+    if !status.success() {
+        bail!("Editor '{}' exited with {}", editor, status);
+    }
+
+    let content = std::fs::read_to_string(path).context("Failed to read edit file")?;
+    serde_json::from_str(&content).context("Edited record is not valid JSON")
+}
+
+/// Check the edited JSON is a record, and matches the lexicon if given.
+fn validate(value: Value, lexicon: Option<&Lexicon>) -> Result<RecordValue> {
+    let value = RecordValue::new(value).context("Invalid record value")?;
+    if let Some(lexicon) = lexicon {
+        let errors = lexicon.validate_record(value.as_value())?;
+        if !errors.is_empty() {
+            for error in &errors {
+                output::error(&error.to_string());
+            }
+            bail!(
+                "Record does not match lexicon {} ({} error(s))",
+                lexicon.id(),
+                errors.len()
+            );
+        }
+    }
+    Ok(value)
+}
+
+/// The editor command: `$VISUAL`, then `$EDITOR`, then `vi`.
+fn editor() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
@@ -0,0 +1,121 @@
+//! Export records command implementation.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::pin::pin;
+
+use anyhow::{Context, Result};
+use clap::Args;
+use futures_util::TryStreamExt;
+use serde::Serialize;
+
+use muat_core::{ListRecordsOptions, Nsid};
+
+use crate::output::{self, Format, Report};
+use crate::session::reader::RecordReader;
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// Collection NSID to export
+    #[arg(long, required_unless_present = "all", conflicts_with = "all")]
+    pub collection: Option<String>,
+
+    /// Export every collection in the repo
+    #[arg(long)]
+    pub all: bool,
+
+    /// JSON Lines file to write, one record per line (use - for stdout)
+    #[arg(long)]
+    pub out: String,
+
+    /// Repository DID or handle (defaults to session DID)
+    #[arg(long)]
+    pub repo: Option<String>,
+
+    /// Read anonymously from this PDS instead of through the session
+    #[arg(long)]
+    pub pds: Option<String>,
+}
+
+/// The finished export.
+#[derive(Serialize)]
+struct ExportOutput {
+    repo: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    collection: Option<String>,
+    /// The collections exported with `--all`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    collections: Option<Vec<String>>,
+    out: String,
+    exported: usize,
+}
+
+impl Report for ExportOutput {
+    fn print_text(&self) {
+        let from = match (&self.collection, &self.collections) {
+            (Some(collection), _) => collection.clone(),
+            (None, Some(collections)) => format!("{} collections", collections.len()),
+            (None, None) => self.repo.clone(),
+        };
+        output::success(&format!(
+            "Exported {} records from {} to {}",
+            self.exported, from, self.out
+        ));
+    }
+}
+
+pub async fn run(args: ExportArgs, format: Format) -> Result<()> {
+    let reader = RecordReader::open(args.pds.as_deref()).await?;
+
+    let repo = match &args.repo {
+        Some(r) => reader.resolve_repo(r).await?,
+        None => reader.default_repo()?,
+    };
+
+    let collections = match &args.collection {
+        Some(collection) => vec![Nsid::new(collection).context("Invalid collection NSID")?],
+        None => reader
+            .list_collections(&repo)
+            .await
+            .context("Failed to list collections")?,
+    };
+
+    let to_stdout = args.out == "-";
+    let mut out: Box<dyn Write> = if to_stdout {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    } else {
+        let file =
+            File::create(&args.out).with_context(|| format!("Failed to create {}", args.out))?;
+        Box::new(BufWriter::new(file))
+    };
+
+    // Pages are fetched as the file is written, so memory use stays flat.
+    let mut exported = 0;
+    for collection in &collections {
+        let mut records = pin!(reader.records(&repo, collection, ListRecordsOptions::new()));
+        while let Some(record) = records.try_next().await.context("Failed to list records")? {
+            serde_json::to_writer(&mut out, &record)?;
+            out.write_all(b"\n").context("Failed to write record")?;
+            exported += 1;
+        }
+    }
+    out.flush().context("Failed to write record")?;
+    drop(out);
+
+    // The records are the output; a summary would corrupt them.
+    if to_stdout {
+        return Ok(());
+    }
+    output::report(
+        format,
+        &ExportOutput {
+            repo: repo.to_string(),
+            collection: args.collection,
+            collections: args
+                .all
+                .then(|| collections.iter().map(ToString::to_string).collect()),
+            out: args.out,
+            exported,
+        },
+    )
+}
@@ -1,20 +1,31 @@
 //! Get record command implementation.
 
+use std::pin::Pin;
+use std::time::Duration;
+
 use anyhow::{Context, Result};
+use chrono::Local;
 use clap::Args;
+use colored::Colorize;
+use futures_util::StreamExt;
+use serde::Serialize;
 
-use muat_core::traits::Session;
-use muat_core::{AtUri, Did, Nsid, Rkey};
+use muat_core::repo::{Record, RepoEvent};
+use muat_core::traits::{Firehose, Pds, Session};
+use muat_core::{AtUri, CancellationToken, Error, FirehoseExt, Nsid, Rkey};
+use muat_xrpc::XrpcPds;
 
-use crate::output;
-use crate::session::storage;
+use super::bulk;
+use crate::output::{self, Format, Report};
+use crate::session::CliSession;
+use crate::session::reader::RecordReader;
 
 #[derive(Args, Debug)]
 pub struct GetRecordArgs {
     /// AT URI of the record (e.g., at://did:plc:.../app.bsky.feed.post/...)
     pub uri: Option<String>,
 
-    /// Repository DID (defaults to session DID)
+    /// Repository DID or handle (defaults to session DID)
     #[arg(long)]
     pub repo: Option<String>,
 
@@ -25,13 +36,51 @@ pub struct GetRecordArgs {
     /// Record key (alternative to URI)
     #[arg(long)]
     pub rkey: Option<String>,
+
+    /// Read anonymously from this PDS instead of through the session
+    #[arg(long)]
+    pub pds: Option<String>,
+
+    /// Read the version with this CID; a local PDS also keeps earlier ones
+    #[arg(long, conflicts_with = "watch")]
+    pub cid: Option<String>,
+
+    /// Keep running and print the record again whenever it changes
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Seconds between reads when watching without a firehose
+    #[arg(long, default_value_t = 2, requires = "watch")]
+    pub interval: u64,
 }
 
-pub async fn run(args: GetRecordArgs) -> Result<()> {
-    let session = storage::load_session()
-        .await
-        .context("Failed to load session")?
-        .context("No active session. Run 'atproto pds login' first.")?;
+/// Returned by `--watch` when the record is deleted.
+#[derive(Debug)]
+pub struct RecordDeleted(pub AtUri);
+
+impl std::fmt::Display for RecordDeleted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Record {} was deleted", self.0)
+    }
+}
+
+impl std::error::Error for RecordDeleted {}
+
+/// The fetched record; text output shows only its value.
+#[derive(Serialize)]
+#[serde(transparent)]
+struct GetRecordOutput(Record);
+
+impl Report for GetRecordOutput {
+    fn print_text(&self) {
+        if let Err(e) = output::json_pretty(&self.0.value) {
+            output::error(&e.to_string());
+        }
+    }
+}
+
+pub async fn run(args: GetRecordArgs, format: Format) -> Result<()> {
+    let reader = RecordReader::open(args.pds.as_deref()).await?;
 
     let uri = if let Some(uri_str) = &args.uri {
         AtUri::new(uri_str).context("Invalid AT URI")?
@@ -47,8 +96,8 @@ pub async fn run(args: GetRecordArgs) -> Result<()> {
             .context("Either --uri or --rkey is required")?;
 
         let repo = match &args.repo {
-            Some(r) => Did::new(r).context("Invalid repo DID")?,
-            None => session.did().clone(),
+            Some(r) => reader.resolve_repo(r).await?,
+            None => reader.default_repo()?,
         };
         let collection = Nsid::new(collection).context("Invalid collection NSID")?;
         let rkey = Rkey::new(rkey).context("Invalid rkey")?;
@@ -56,12 +105,181 @@ pub async fn run(args: GetRecordArgs) -> Result<()> {
         AtUri::from_parts(repo, collection, rkey)
     };
 
-    let record = session
-        .get_record(&uri)
+    let record = match &args.cid {
+        Some(cid) => reader.get_record_at(&uri, cid).await,
+        None => reader.get_record(&uri).await,
+    }
+    .context("Failed to get record")?;
+
+    if !args.watch {
+        return output::report(format, &GetRecordOutput(record));
+    }
+    let firehose = watch_firehose(&reader, &uri, args.pds.is_some());
+    let mut watch = Watch {
+        reader: &reader,
+        uri: &uri,
+        format,
+        cid: record.cid.clone(),
+    };
+    print_version(format, record)?;
+    watch
+        .run(firehose, Duration::from_secs(args.interval.max(1)))
         .await
-        .context("Failed to get record")?;
+}
+
+/// A firehose carrying the record's commits, if one can be read.
+///
+/// A local PDS always has one. Over XRPC the record's repo must be hosted
+/// on the PDS being read: the session's own repo, or any repo when `--pds`
+/// names its host.
+fn watch_firehose(
+    reader: &RecordReader,
+    uri: &AtUri,
+    explicit_pds: bool,
+) -> Option<muat_core::Result<Pin<Box<dyn Firehose>>>> {
+    fn boxed<F: Firehose + 'static>(
+        firehose: muat_core::Result<F>,
+    ) -> muat_core::Result<Pin<Box<dyn Firehose>>> {
+        firehose.map(|f| Box::pin(f) as Pin<Box<dyn Firehose>>)
+    }
+    match reader {
+        RecordReader::File(pds) => Some(boxed(pds.firehose())),
+        RecordReader::Session(CliSession::File(session)) => {
+            Some(boxed(session.file_pds().firehose()))
+        }
+        RecordReader::Session(CliSession::Xrpc(session)) if session.did() == uri.repo() => {
+            Some(boxed(XrpcPds::new(session.pds().clone()).firehose()))
+        }
+        RecordReader::Xrpc(pds) if explicit_pds => Some(boxed(pds.firehose())),
+        RecordReader::Session(_) | RecordReader::Xrpc(_) => None,
+    }
+}
+
+/// Follows one record, printing each new version until it is deleted or
+/// the user presses Ctrl+C.
+struct Watch<'a> {
+    reader: &'a RecordReader,
+    uri: &'a AtUri,
+    format: Format,
+    /// CID of the version last printed.
+    cid: String,
+}
 
-    output::json_pretty(&record.value)?;
+impl Watch<'_> {
+    /// Follow the firehose if there is one, falling back to polling every
+    /// `interval` when it cannot be opened or fails.
+    async fn run(
+        &mut self,
+        firehose: Option<muat_core::Result<Pin<Box<dyn Firehose>>>>,
+        interval: Duration,
+    ) -> Result<()> {
+        let cancel = bulk::interrupt_token();
+        match firehose {
+            Some(Ok(firehose)) => {
+                eprintln!(
+                    "{}",
+                    "Watching the firehose for changes. Press Ctrl+C to stop.".dimmed()
+                );
+                self.follow(firehose, &cancel).await?;
+                if cancel.is_cancelled() {
+                    return Ok(());
+                }
+            }
+            Some(Err(e)) => {
+                eprintln!("{} Cannot open the firehose: {}", "Warning:".yellow(), e);
+            }
+            None => {}
+        }
+        eprintln!(
+            "{}",
+            format!(
+                "Checking for changes every {}s. Press Ctrl+C to stop.",
+                interval.as_secs()
+            )
+            .dimmed()
+        );
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => return Ok(()),
+                _ = tokio::time::sleep(interval) => {}
+            }
+            self.refresh().await?;
+        }
+    }
+
+    /// Re-read the record after each commit touching it. Returns when the
+    /// stream ends or fails; the caller then polls.
+    async fn follow(
+        &mut self,
+        firehose: Pin<Box<dyn Firehose>>,
+        cancel: &CancellationToken,
+    ) -> Result<()> {
+        let (collection, rkey) = self.uri.record_path()?;
+        let path = format!("{}/{}", collection, rkey);
+        let mut events = firehose
+            .filter_repos(std::slice::from_ref(self.uri.repo()))
+            .commits_only()
+            .until_cancelled(cancel.clone());
+        // Catch changes made between the first read and subscribing.
+        self.refresh().await?;
+        while let Some(event) = events.next().await {
+            let commit = match event {
+                Ok(RepoEvent::Commit(commit)) => commit,
+                Ok(_) => continue,
+                Err(e) => {
+                    eprintln!("{} Firehose failed: {}", "Warning:".yellow(), e);
+                    return Ok(());
+                }
+            };
+            match commit.ops.iter().find(|op| op.path == path) {
+                Some(op) if op.action == "delete" => {
+                    return Err(RecordDeleted(self.uri.clone()).into());
+                }
+                Some(_) => self.refresh().await?,
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the record and print it if its CID changed. Failed reads other
+    /// than not found are reported and the watch carries on.
+    async fn refresh(&mut self) -> Result<()> {
+        match self.reader.get_record(self.uri).await {
+            Ok(record) if record.cid != self.cid => {
+                self.cid = record.cid.clone();
+                eprintln!(
+                    "{}",
+                    format!(
+                        "Updated at {} (cid {})",
+                        Local::now().format("%H:%M:%S"),
+                        record.cid
+                    )
+                    .dimmed()
+                );
+                print_version(self.format, record)
+            }
+            Ok(_) => Ok(()),
+            Err(Error::Protocol(e)) if e.is_not_found() => {
+                Err(RecordDeleted(self.uri.clone()).into())
+            }
+            Err(e) => {
+                eprintln!("{} Failed to read record: {}", "Warning:".yellow(), e);
+                Ok(())
+            }
+        }
+    }
+}
 
-    Ok(())
+/// Print one version of a watched record: a JSON line, a YAML document,
+/// or its value as text.
+fn print_version(format: Format, record: Record) -> Result<()> {
+    match format {
+        Format::Json => output::json(&record),
+        Format::Yaml => {
+            println!("---");
+            output::yaml(&record)
+        }
+        Format::Text | Format::Table => output::report(format, &GetRecordOutput(record)),
+    }
 }
@@ -0,0 +1,96 @@
+//! Import records command implementation.
+
+use std::io::{self, Read};
+
+use anyhow::{Context, Result};
+use clap::Args;
+use serde::Deserialize;
+
+use muat_core::traits::Session;
+use muat_core::{AtUri, BulkReport, Nsid, RecordValue};
+
+use super::bulk::{self, BulkArgs};
+use crate::output::Format;
+use crate::session::storage;
+
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// JSON Lines file written by `pds export` (use - for stdin)
+    pub file: String,
+
+    /// Import every record into this collection instead of its own
+    #[arg(long)]
+    pub collection: Option<String>,
+
+    #[command(flatten)]
+    pub bulk: BulkArgs,
+}
+
+/// One line of an export. Other fields, such as `cid`, are ignored.
+#[derive(Deserialize)]
+struct ExportedRecord {
+    uri: AtUri,
+    value: RecordValue,
+}
+
+pub async fn run(args: ImportArgs, format: Format) -> Result<()> {
+    let session = storage::require_session().await?;
+
+    let target = match args.collection {
+        Some(ref collection) => Some(Nsid::new(collection).context("Invalid collection NSID")?),
+        None => None,
+    };
+
+    let content = if args.file == "-" {
+        let mut buf = String::new();
+        io::stdin()
+            .read_to_string(&mut buf)
+            .context("Failed to read from stdin")?;
+        buf
+    } else {
+        std::fs::read_to_string(&args.file).context("Failed to read JSON Lines file")?
+    };
+
+    // Check every line before writing anything, grouping records by
+    // collection in the order each collection first appears.
+    let mut groups: Vec<(Nsid, Vec<RecordValue>)> = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_number = index + 1;
+        let record: ExportedRecord = serde_json::from_str(line)
+            .with_context(|| format!("Invalid exported record on line {}", line_number))?;
+        let collection = match target {
+            Some(ref collection) => collection.clone(),
+            None => record
+                .uri
+                .record_path()
+                .map(|(collection, _)| collection.clone())
+                .with_context(|| format!("Not a record URI on line {}", line_number))?,
+        };
+        match groups.iter_mut().find(|(c, _)| *c == collection) {
+            Some((_, values)) => values.push(record.value),
+            None => groups.push((collection, vec![record.value])),
+        }
+    }
+
+    let cancel = bulk::interrupt_token();
+    let mut report = BulkReport::new();
+    for (collection, values) in groups {
+        let created = session
+            .create_records_bulk(&collection, values, args.bulk.concurrency, Some(&cancel))
+            .await;
+        report.succeeded.extend(created.succeeded);
+        report.failed.extend(created.failed);
+        report.skipped.extend(created.skipped);
+    }
+
+    bulk::finish(
+        format,
+        "Imported",
+        report,
+        |value| value.as_value().to_string(),
+        args.bulk.max_failures,
+    )
+}
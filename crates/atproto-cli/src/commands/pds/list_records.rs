@@ -1,25 +1,31 @@
 //! List records command implementation.
 
+use std::pin::pin;
+
 use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 use clap::Args;
 use colored::Colorize;
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use rand::Rng;
+use serde::Serialize;
 
-use muat_core::traits::Session;
-use muat_core::{Did, Nsid};
+use muat_core::repo::Record;
+use muat_core::{ListRecordsOptions, Nsid};
 
-use crate::output;
-use crate::session::storage;
+use crate::output::{self, Format, Report};
+use crate::session::reader::RecordReader;
 
 #[derive(Args, Debug)]
 pub struct ListRecordsArgs {
     /// Collection NSID (e.g., app.bsky.feed.post)
     pub collection: String,
 
-    /// Repository DID (defaults to session DID)
+    /// Repository DID or handle (defaults to session DID)
     #[arg(long)]
     pub repo: Option<String>,
 
-    /// Maximum number of records to return
+    /// Maximum number of records to return (page size with --head, --tail or --sample)
     #[arg(long)]
     pub limit: Option<u32>,
 
@@ -27,47 +33,213 @@ pub struct ListRecordsArgs {
     #[arg(long)]
     pub cursor: Option<String>,
 
-    /// Pretty-print JSON output
+    /// List newest records first (descending record key)
+    #[arg(long)]
+    pub reverse: bool,
+
+    /// Pretty-print record values (text output only)
     #[arg(long)]
     pub pretty: bool,
+
+    /// Read anonymously from this PDS instead of through the session
+    #[arg(long)]
+    pub pds: Option<String>,
+
+    /// Show the first N records, following pages as needed
+    #[arg(long, value_name = "N", conflicts_with_all = ["tail", "sample"])]
+    pub head: Option<usize>,
+
+    /// Show the last N records
+    #[arg(long, value_name = "N", conflicts_with_all = ["sample", "cursor"])]
+    pub tail: Option<usize>,
+
+    /// Show N records chosen at random from the whole collection
+    #[arg(long, value_name = "N")]
+    pub sample: Option<usize>,
+
+    /// Only records with TID keys created at or after this date or RFC 3339 time
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only records with TID keys created before this date or RFC 3339 time
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    pub until: Option<DateTime<Utc>>,
 }
 
-pub async fn run(args: ListRecordsArgs) -> Result<()> {
-    let session = storage::load_session()
-        .await
-        .context("Failed to load session")?
-        .context("No active session. Run 'atproto pds login' first.")?;
+/// Parse `2024-01-01` as midnight UTC, or a full RFC 3339 timestamp.
+fn parse_time(value: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_time(NaiveTime::MIN).and_utc());
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| format!("'{}' is not a date (YYYY-MM-DD) or RFC 3339 time", value))
+}
+
+/// A page of records.
+#[derive(Serialize)]
+struct ListRecordsReport {
+    records: Vec<Record>,
+    cursor: Option<String>,
+    #[serde(skip)]
+    pretty: bool,
+}
+
+impl Report for ListRecordsReport {
+    fn print_text(&self) {
+        if self.records.is_empty() {
+            eprintln!("{}", "No records found.".dimmed());
+            return;
+        }
+
+        for record in &self.records {
+            let printed = if self.pretty {
+                output::json_pretty(&record.value)
+            } else {
+                output::json(&record)
+            };
+            if let Err(e) = printed {
+                output::error(&e.to_string());
+            }
+            println!();
+        }
+
+        if let Some(cursor) = &self.cursor {
+            eprintln!();
+            eprintln!("{}: {}", "Next cursor".dimmed(), cursor);
+        }
+    }
+}
+
+pub async fn run(args: ListRecordsArgs, format: Format) -> Result<()> {
+    let reader = RecordReader::open(args.pds.as_deref()).await?;
 
     let repo = match &args.repo {
-        Some(r) => Did::new(r).context("Invalid repo DID")?,
-        None => session.did().clone(),
+        Some(r) => reader.resolve_repo(r).await?,
+        None => reader.default_repo()?,
     };
 
     let collection = Nsid::new(&args.collection).context("Invalid collection NSID")?;
 
-    let result = session
-        .list_records(&repo, &collection, args.limit, args.cursor.as_deref())
-        .await
-        .context("Failed to list records")?;
-
-    if result.records.is_empty() {
-        eprintln!("{}", "No records found.".dimmed());
-        return Ok(());
+    let mut options = ListRecordsOptions::new().reverse(args.reverse);
+    if let Some(limit) = args.limit {
+        options = options.limit(limit);
+    }
+    if let Some(cursor) = args.cursor {
+        options = options.cursor(cursor);
+    }
+    if let Some(since) = args.since {
+        options = options.since(since);
+    }
+    if let Some(until) = args.until {
+        options = options.until(until);
     }
 
-    for record in &result.records {
-        if args.pretty {
-            output::json_pretty(&record.value)?;
+    let (records, cursor) = if let Some(n) = args.head {
+        let records = reader.records(&repo, &collection, options).take(n);
+        (collect(records).await?, None)
+    } else if let Some(n) = args.tail {
+        // The last records in one order are the first in the other.
+        let options = options.reverse(!args.reverse);
+        let records = reader.records(&repo, &collection, options).take(n);
+        let mut records = collect(records).await?;
+        records.reverse();
+        (records, None)
+    } else if let Some(n) = args.sample {
+        let records = reader.records(&repo, &collection, options);
+        (sample(records, n, &mut rand::thread_rng()).await?, None)
+    } else {
+        let page = reader
+            .list_records_with(&repo, &collection, &options)
+            .await
+            .context("Failed to list records")?;
+        (page.records, page.cursor)
+    };
+
+    output::report(
+        format,
+        &ListRecordsReport {
+            records,
+            cursor,
+            pretty: args.pretty,
+        },
+    )
+}
+
+async fn collect<S>(records: S) -> Result<Vec<Record>>
+where
+    S: Stream<Item = muat_core::Result<Record>>,
+{
+    records
+        .try_collect()
+        .await
+        .context("Failed to list records")
+}
+
+/// Reservoir-sample `n` items from a stream in one pass, keeping the
+/// chosen items in stream order.
+async fn sample<T, S, R>(items: S, n: usize, rng: &mut R) -> Result<Vec<T>>
+where
+    S: Stream<Item = muat_core::Result<T>>,
+    R: Rng,
+{
+    let mut items = pin!(items);
+    let mut reservoir: Vec<(usize, T)> = Vec::with_capacity(n);
+    let mut seen = 0;
+
+    while let Some(item) = items.try_next().await.context("Failed to list records")? {
+        if reservoir.len() < n {
+            reservoir.push((seen, item));
         } else {
-            output::json(&record)?;
+            let slot = rng.gen_range(0..=seen);
+            if slot < n {
+                reservoir[slot] = (seen, item);
+            }
         }
-        println!();
+        seen += 1;
     }
 
-    if let Some(cursor) = &result.cursor {
-        eprintln!();
-        eprintln!("{}: {}", "Next cursor".dimmed(), cursor);
+    reservoir.sort_by_key(|(index, _)| *index);
+    Ok(reservoir.into_iter().map(|(_, item)| item).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::stream;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn numbers(count: usize) -> impl Stream<Item = muat_core::Result<usize>> {
+        stream::iter((0..count).map(Ok))
     }
 
-    Ok(())
+    #[tokio::test]
+    async fn sample_keeps_stream_order() {
+        let mut rng = StdRng::seed_from_u64(7);
+
+        let all = sample(numbers(3), 5, &mut rng).await.unwrap();
+        assert_eq!(all, vec![0, 1, 2]);
+
+        let some = sample(numbers(1000), 10, &mut rng).await.unwrap();
+        assert_eq!(some.len(), 10);
+        assert!(some.windows(2).all(|pair| pair[0] < pair[1]));
+        // A uniform sample of 10 from 1000 is vanishingly unlikely to be the first 10.
+        assert_ne!(some, (0..10).collect::<Vec<_>>());
+
+        assert!(sample(numbers(5), 0, &mut rng).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn parses_dates_and_times() {
+        assert_eq!(
+            parse_time("2024-01-01").unwrap(),
+            "2024-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(
+            parse_time("2024-01-01T02:00:00+02:00").unwrap(),
+            "2024-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert!(parse_time("yesterday").is_err());
+    }
 }
@@ -1,21 +1,25 @@
 //! Login command implementation.
 
+use std::time::Duration;
+
 use anyhow::{Context, Result};
 use clap::Args;
 use colored::Colorize;
+use serde::Serialize;
 
+use muat_core::error::AuthError;
 use muat_core::traits::Pds;
-use muat_core::{Credentials, PdsUrl};
+use muat_core::{Credentials, Error, PdsUrl};
 use muat_file::FilePds;
 use muat_xrpc::XrpcPds;
 
-use crate::output;
+use crate::output::{self, Format, Report};
 use crate::session::CliSession;
 use crate::session::storage;
 
 #[derive(Args, Debug)]
 pub struct LoginArgs {
-    /// Handle or DID to authenticate with
+    /// Handle, DID or account email to authenticate with
     #[arg(long)]
     pub identifier: String,
 
@@ -26,35 +30,156 @@ pub struct LoginArgs {
     /// PDS base URL
     #[arg(long, default_value = "https://bsky.social")]
     pub pds: String,
+
+    /// When the PDS rate limits the login, wait and retry up to this many
+    /// times (3 if no count is given)
+    #[arg(long, value_name = "COUNT", num_args = 0..=1, default_missing_value = "3")]
+    pub retry: Option<u32>,
+
+    /// Remove the stored session once it goes unused this long (e.g. 30m,
+    /// 12h, 30d or 2w)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub max_idle: Option<Duration>,
+}
+
+/// Parse a whole number of seconds, minutes, hours, days or weeks.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("'{}' is not a duration like 30m, 12h, 30d or 2w", value);
+    let unit_at = value
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (count, unit) = value.split_at(unit_at);
+    let count: u64 = count.parse().map_err(|_| invalid())?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    count
+        .checked_mul(seconds)
+        .filter(|secs| *secs > 0)
+        .map(Duration::from_secs)
+        .ok_or_else(invalid)
+}
+
+/// First wait when the PDS does not say how long to back off.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Longest wait before giving up instead of retrying.
+const MAX_RETRY_WAIT: Duration = Duration::from_secs(15 * 60);
+
+/// Result of a successful login.
+#[derive(Serialize)]
+struct LoginOutput {
+    did: String,
+    pds: String,
+}
+
+impl Report for LoginOutput {
+    fn print_text(&self) {
+        output::success("Logged in successfully");
+        println!();
+        output::field("DID", &self.did);
+        output::field("PDS", &self.pds);
+    }
 }
 
-pub async fn run(args: LoginArgs) -> Result<()> {
+pub async fn run(args: LoginArgs, format: Format) -> Result<()> {
     let pds_url = PdsUrl::new(&args.pds).context("Invalid PDS URL")?;
     let credentials = Credentials::new(&args.identifier, &args.password);
 
     eprintln!("{}", "Logging in...".dimmed());
 
+    let retries = args.retry.unwrap_or(0);
     let session = if pds_url.is_local() {
         let path = pds_url
             .to_file_path()
             .context("Failed to convert file:// URL to path")?;
         let pds = FilePds::new(&path, pds_url);
-        CliSession::File(pds.login(credentials).await.context("Failed to login")?)
+        CliSession::File(Box::new(
+            login_with_retry(&pds, credentials, retries).await?,
+        ))
     } else {
         let pds = XrpcPds::new(pds_url.clone());
-        CliSession::Xrpc(pds.login(credentials).await.context("Failed to login")?)
+        CliSession::Xrpc(login_with_retry(&pds, credentials, retries).await?)
     };
 
     // Save session
-    storage::save_session(&session)
+    storage::save_new_session(&session, args.max_idle)
         .await
         .context("Failed to save session")?;
 
-    // Print success
-    output::success("Logged in successfully");
-    println!();
-    output::field("DID", session.did().as_str());
-    output::field("PDS", session.pds().as_str());
+    output::report(
+        format,
+        &LoginOutput {
+            did: session.did().to_string(),
+            pds: session.pds().to_string(),
+        },
+    )
+}
+
+/// Log in, waiting out rate limits up to `retries` times.
+///
+/// Waits as long as the PDS asks, or backs off exponentially when it does not
+/// say. Retrying sooner only extends a lockout, so this never shortens the wait.
+async fn login_with_retry<P: Pds>(
+    pds: &P,
+    credentials: Credentials,
+    retries: u32,
+) -> Result<P::Session> {
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 0;
+    loop {
+        let error = match pds.login(credentials.clone()).await {
+            Ok(session) => return Ok(session),
+            Err(error) => error,
+        };
+        let Error::Auth(AuthError::RateLimited { retry_after }) = &error else {
+            return Err(login_error(error));
+        };
+        let wait = retry_after.unwrap_or(backoff);
+        if attempt >= retries || wait > MAX_RETRY_WAIT {
+            return Err(login_error(error));
+        }
+        eprintln!(
+            "{}",
+            format!(
+                "Rate limited; retrying in {}s ({}/{})...",
+                wait.as_secs(),
+                attempt + 1,
+                retries
+            )
+            .dimmed()
+        );
+        tokio::time::sleep(wait).await;
+        backoff *= 2;
+        attempt += 1;
+    }
+}
 
-    Ok(())
+/// Explain login failures for accounts the PDS operator has acted on.
+fn login_error(error: Error) -> anyhow::Error {
+    let guidance = match &error {
+        Error::Auth(AuthError::AccountSuspended(_)) => Some(
+            "This account is suspended. It can log in again when the suspension ends; \
+             contact the PDS operator for details.",
+        ),
+        Error::Auth(AuthError::AccountTakendown(_)) => Some(
+            "This account has been taken down by the PDS operator. Contact them to appeal; \
+             a new password will not help.",
+        ),
+        Error::Auth(AuthError::RateLimited { .. }) => Some(
+            "Too many login attempts. Wait before trying again, or pass --retry to wait \
+             automatically; retrying sooner extends the lockout.",
+        ),
+        _ => None,
+    };
+    let error = anyhow::Error::new(error).context("Failed to login");
+    match guidance {
+        Some(guidance) => error.context(guidance),
+        None => error,
+    }
 }
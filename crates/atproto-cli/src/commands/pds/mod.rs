@@ -1,19 +1,36 @@
 //! PDS subcommand implementations.
 
+mod account_status;
+mod activate_account;
+pub mod bulk;
+mod compact;
+mod confirm_email;
 mod create_account;
 mod create_record;
+mod create_records;
+mod deactivate_account;
 mod delete_record;
-mod get_record;
+mod delete_records;
+mod edit_record;
+mod export;
+pub mod get_record;
+mod import;
 mod list_records;
 mod login;
+mod purge_collection;
 mod refresh_token;
 mod remove_account;
+mod request_email_confirmation;
+mod request_password_reset;
+mod reset_password;
 mod subscribe;
 mod whoami;
 
 use anyhow::Result;
 use clap::{Args, Subcommand};
 
+use crate::output::Format;
+
 #[derive(Args, Debug)]
 pub struct PdsCommand {
     #[command(subcommand)]
@@ -37,33 +54,93 @@ pub enum PdsSubcommand {
     /// Remove an account (local PDS only)
     RemoveAccount(remove_account::RemoveAccountArgs),
 
+    /// Email a token to confirm the account's email address
+    RequestEmailConfirmation(request_email_confirmation::RequestEmailConfirmationArgs),
+
+    /// Confirm the account's email address with an emailed token
+    ConfirmEmail(confirm_email::ConfirmEmailArgs),
+
+    /// Email a password reset token to an account's address
+    RequestPasswordReset(request_password_reset::RequestPasswordResetArgs),
+
+    /// Set a new password with an emailed reset token
+    ResetPassword(reset_password::ResetPasswordArgs),
+
+    /// Show whether the account is active and what its repository holds
+    AccountStatus(account_status::AccountStatusArgs),
+
+    /// Activate the account, such as after migrating it
+    ActivateAccount(activate_account::ActivateAccountArgs),
+
+    /// Deactivate the account until it is activated again
+    DeactivateAccount(deactivate_account::DeactivateAccountArgs),
+
     /// Create a new record in a collection
     CreateRecord(create_record::CreateRecordArgs),
 
+    /// Create many records from JSON Lines
+    CreateRecords(create_records::CreateRecordsArgs),
+
     /// List records in a collection
     ListRecords(list_records::ListRecordsArgs),
 
     /// Fetch a single record
     GetRecord(get_record::GetRecordArgs),
 
+    /// Edit a record in $EDITOR and write it back
+    EditRecord(edit_record::EditRecordArgs),
+
     /// Delete a record
     DeleteRecord(delete_record::DeleteRecordArgs),
 
+    /// Delete many records
+    DeleteRecords(delete_records::DeleteRecordsArgs),
+
+    /// Delete every record in a collection, after confirming
+    PurgeCollection(purge_collection::PurgeCollectionArgs),
+
+    /// Export a collection to JSON Lines
+    Export(export::ExportArgs),
+
+    /// Import records from a JSON Lines export
+    Import(import::ImportArgs),
+
     /// Subscribe to repository events
     Subscribe(subscribe::SubscribeArgs),
+
+    /// Compress or decompress stored records (local PDS only)
+    Compact(compact::CompactArgs),
 }
 
-pub async fn handle(cmd: PdsCommand) -> Result<()> {
+pub async fn handle(cmd: PdsCommand, format: Format) -> Result<()> {
     match cmd.command {
-        PdsSubcommand::Login(args) => login::run(args).await,
-        PdsSubcommand::Whoami(args) => whoami::run(args).await,
-        PdsSubcommand::RefreshToken(args) => refresh_token::run(args).await,
-        PdsSubcommand::CreateAccount(args) => create_account::run(args).await,
-        PdsSubcommand::RemoveAccount(args) => remove_account::run(args).await,
-        PdsSubcommand::CreateRecord(args) => create_record::run(args).await,
-        PdsSubcommand::ListRecords(args) => list_records::run(args).await,
-        PdsSubcommand::GetRecord(args) => get_record::run(args).await,
-        PdsSubcommand::DeleteRecord(args) => delete_record::run(args).await,
-        PdsSubcommand::Subscribe(args) => subscribe::run(args).await,
+        PdsSubcommand::Login(args) => login::run(args, format).await,
+        PdsSubcommand::Whoami(args) => whoami::run(args, format).await,
+        PdsSubcommand::RefreshToken(args) => refresh_token::run(args, format).await,
+        PdsSubcommand::CreateAccount(args) => create_account::run(args, format).await,
+        PdsSubcommand::RemoveAccount(args) => remove_account::run(args, format).await,
+        PdsSubcommand::RequestEmailConfirmation(args) => {
+            request_email_confirmation::run(args, format).await
+        }
+        PdsSubcommand::ConfirmEmail(args) => confirm_email::run(args, format).await,
+        PdsSubcommand::RequestPasswordReset(args) => {
+            request_password_reset::run(args, format).await
+        }
+        PdsSubcommand::ResetPassword(args) => reset_password::run(args, format).await,
+        PdsSubcommand::AccountStatus(args) => account_status::run(args, format).await,
+        PdsSubcommand::ActivateAccount(args) => activate_account::run(args, format).await,
+        PdsSubcommand::DeactivateAccount(args) => deactivate_account::run(args, format).await,
+        PdsSubcommand::CreateRecord(args) => create_record::run(args, format).await,
+        PdsSubcommand::CreateRecords(args) => create_records::run(args, format).await,
+        PdsSubcommand::ListRecords(args) => list_records::run(args, format).await,
+        PdsSubcommand::GetRecord(args) => get_record::run(args, format).await,
+        PdsSubcommand::EditRecord(args) => edit_record::run(args, format).await,
+        PdsSubcommand::DeleteRecord(args) => delete_record::run(args, format).await,
+        PdsSubcommand::DeleteRecords(args) => delete_records::run(args, format).await,
+        PdsSubcommand::PurgeCollection(args) => purge_collection::run(args, format).await,
+        PdsSubcommand::Export(args) => export::run(args, format).await,
+        PdsSubcommand::Import(args) => import::run(args, format).await,
+        PdsSubcommand::Subscribe(args) => subscribe::run(args, format).await,
+        PdsSubcommand::Compact(args) => compact::run(args, format).await,
     }
 }
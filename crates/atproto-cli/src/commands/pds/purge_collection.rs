@@ -0,0 +1,170 @@
+//! Purge collection command implementation.
+
+use std::io::{self, Write};
+
+use anyhow::{Context, Result};
+use clap::Args;
+use colored::Colorize;
+use serde::Serialize;
+
+use muat_core::traits::Session;
+use muat_core::{DeleteCollectionOptions, Nsid};
+
+use super::bulk::{self, BulkArgs};
+use crate::output::{self, Format, Report};
+use crate::session::storage;
+
+#[derive(Args, Debug)]
+pub struct PurgeCollectionArgs {
+    /// Collection NSID (e.g. app.bsky.feed.post)
+    pub collection: String,
+
+    /// Skip confirmation prompt
+    #[arg(long, short = 'y')]
+    pub yes: bool,
+
+    /// Count the records without deleting them
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Records fetched and deleted per page
+    #[arg(long)]
+    pub page_size: Option<u32>,
+
+    #[command(flatten)]
+    pub bulk: BulkArgs,
+}
+
+/// The outcome of purging a collection.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PurgeCollectionOutput {
+    collection: String,
+    dry_run: bool,
+    scanned: usize,
+    deleted: usize,
+    failed: Vec<Failure>,
+    cancelled: bool,
+}
+
+/// A record whose delete failed, and why.
+#[derive(Serialize)]
+struct Failure {
+    uri: String,
+    error: String,
+}
+
+impl Report for PurgeCollectionOutput {
+    fn print_text(&self) {
+        for failure in &self.failed {
+            output::error(&format!("{}: {}", failure.uri, failure.error));
+        }
+        if self.dry_run {
+            output::success(&format!(
+                "Would delete {} records from {}",
+                self.deleted, self.collection
+            ));
+        } else {
+            output::success(&format!(
+                "Deleted {} of {} records from {}",
+                self.deleted, self.scanned, self.collection
+            ));
+        }
+        if self.cancelled {
+            output::error("Stopped before the collection was empty");
+        }
+    }
+}
+
+pub async fn run(args: PurgeCollectionArgs, format: Format) -> Result<()> {
+    let session = storage::require_session().await?;
+    let collection = Nsid::new(&args.collection).context("Invalid collection NSID")?;
+
+    let mut options = DeleteCollectionOptions::new()
+        .concurrency(args.bulk.concurrency)
+        .cancel(bulk::interrupt_token());
+    if let Some(page_size) = args.page_size {
+        options = options.page_size(page_size);
+    }
+
+    // Count first, so the prompt and progress can give a total.
+    let counted = session
+        .delete_collection(session.did(), &collection, &options.clone().dry_run(true))
+        .await
+        .context("Failed to list records")?;
+    let total = counted.scanned;
+
+    if args.dry_run || total == 0 {
+        return output::report(
+            format,
+            &PurgeCollectionOutput {
+                collection: collection.to_string(),
+                dry_run: args.dry_run,
+                scanned: total,
+                deleted: if args.dry_run { total } else { 0 },
+                failed: Vec::new(),
+                cancelled: counted.cancelled,
+            },
+        );
+    }
+
+    // Confirm unless --yes
+    if !args.yes {
+        eprint!(
+            "This will delete {} records from {} in {}. Continue? [y/N] ",
+            total,
+            collection,
+            session.did()
+        );
+        io::stderr().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+
+        if !input.trim().eq_ignore_ascii_case("y") {
+            eprintln!("Aborted.");
+            return Ok(());
+        }
+    }
+
+    let options = options.on_progress(move |report| {
+        eprintln!(
+            "{}",
+            format!("Deleted {} of {} records", report.deleted, total).dimmed()
+        );
+    });
+    let report = session
+        .delete_collection(session.did(), &collection, &options)
+        .await
+        .context("Failed to list records")?;
+
+    let first_error = report.failed.first().map(|(_, e)| e.clone());
+    let output = PurgeCollectionOutput {
+        collection: collection.to_string(),
+        dry_run: false,
+        scanned: report.scanned,
+        deleted: report.deleted,
+        failed: report
+            .failed
+            .iter()
+            .map(|(uri, error)| Failure {
+                uri: uri.to_string(),
+                error: error.to_string(),
+            })
+            .collect(),
+        cancelled: report.cancelled,
+    };
+    output::report(format, &output)?;
+
+    match first_error {
+        Some(error) if output.failed.len() > args.bulk.max_failures => {
+            Err(anyhow::Error::new(error).context(format!(
+                "{} of {} records failed (allowed: {})",
+                output.failed.len(),
+                output.scanned,
+                args.bulk.max_failures
+            )))
+        }
+        _ => Ok(()),
+    }
+}
@@ -1,39 +1,83 @@
 //! Refresh token command implementation.
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use clap::Args;
 use colored::Colorize;
+use serde::Serialize;
 
-use crate::output;
+use crate::output::{self, Format, Report};
+use crate::session::expiry::jwt_expiry;
 use crate::session::storage;
 
 #[derive(Args, Debug)]
-pub struct RefreshTokenArgs {}
+pub struct RefreshTokenArgs {
+    /// Print the result as JSON (same as `--output json`)
+    #[arg(long)]
+    pub json: bool,
+}
 
-pub async fn run(_args: RefreshTokenArgs) -> Result<()> {
-    let session = storage::load_session()
-        .await
-        .context("Failed to load session")?
-        .context("No active session. Run 'atproto pds login' first.")?;
+/// Result of a successful refresh.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RefreshOutput {
+    did: String,
+    pds: String,
+    access_expires_at: Option<DateTime<Utc>>,
+    refresh_expires_at: Option<DateTime<Utc>>,
+    persisted: bool,
+}
+
+impl Report for RefreshOutput {
+    fn print_text(&self) {
+        output::success("Session refreshed and saved");
+        output::field("DID", &self.did);
+        output::field("Access expires", &format_expiry(self.access_expires_at));
+        output::field("Refresh expires", &format_expiry(self.refresh_expires_at));
+    }
+}
 
-    eprintln!("{}", "Refreshing session...".dimmed());
+pub async fn run(args: RefreshTokenArgs, format: Format) -> Result<()> {
+    let format = if args.json { Format::Json } else { format };
+
+    let session = storage::require_session().await?;
 
     let Some(xrpc_session) = session.as_xrpc() else {
         anyhow::bail!("Refresh is only supported for network PDS sessions.");
     };
 
+    if format == Format::Text {
+        eprintln!("{}", "Refreshing session...".dimmed());
+    }
+
     xrpc_session
         .refresh()
         .await
-        .context("Failed to refresh session")?;
+        .context("Failed to refresh session; stored credentials were not changed")?;
 
-    // Save the updated session with new tokens
+    // The old refresh token is spent once the PDS rotates it, so a failed
+    // save leaves no usable credentials on disk.
     storage::save_session(&session)
         .await
-        .context("Failed to save refreshed session")?;
+        .context("Session was refreshed but could not be saved; run 'atproto pds login' again")?;
 
-    output::success("Session refreshed successfully");
-    output::field("DID", session.did().as_str());
+    let access_expires_at = jwt_expiry(session.access_token().as_str());
+    let refresh_expires_at = session
+        .refresh_token()
+        .and_then(|token| jwt_expiry(token.as_str()));
+
+    output::report(
+        format,
+        &RefreshOutput {
+            did: session.did().to_string(),
+            pds: session.pds().to_string(),
+            access_expires_at,
+            refresh_expires_at,
+            persisted: true,
+        },
+    )
+}
 
-    Ok(())
+fn format_expiry(expiry: Option<DateTime<Utc>>) -> String {
+    expiry.map_or_else(|| "unknown".to_string(), |t| t.to_rfc3339())
 }
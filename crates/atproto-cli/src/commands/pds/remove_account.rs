@@ -7,12 +7,13 @@ use std::io::{self, Write};
 
 use anyhow::{Context, Result, bail};
 use clap::Args;
+use serde::Serialize;
 
 use muat_core::traits::{Pds, Session};
 use muat_core::{Credentials, Did, PdsUrl};
 use muat_file::FilePds;
 
-use crate::output;
+use crate::output::{self, Format, Report};
 
 #[derive(Args, Debug)]
 pub struct RemoveAccountArgs {
@@ -36,7 +37,21 @@ pub struct RemoveAccountArgs {
     pub pds: String,
 }
 
-pub async fn run(args: RemoveAccountArgs) -> Result<()> {
+/// The removed account.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RemoveAccountOutput {
+    did: String,
+    records_deleted: bool,
+}
+
+impl Report for RemoveAccountOutput {
+    fn print_text(&self) {
+        output::success(&format!("Account {} removed", self.did));
+    }
+}
+
+pub async fn run(args: RemoveAccountArgs, format: Format) -> Result<()> {
     let pds_url = PdsUrl::new(&args.pds).context("Invalid PDS URL")?;
 
     if !pds_url.is_local() {
@@ -92,7 +107,11 @@ pub async fn run(args: RemoveAccountArgs) -> Result<()> {
         .await
         .context("Failed to remove account")?;
 
-    output::success(&format!("Account {} removed", args.did));
-
-    Ok(())
+    output::report(
+        format,
+        &RemoveAccountOutput {
+            did: args.did,
+            records_deleted: args.delete_records,
+        },
+    )
 }
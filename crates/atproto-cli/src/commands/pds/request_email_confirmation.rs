@@ -0,0 +1,43 @@
+//! Request email confirmation command implementation.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use serde::Serialize;
+
+use muat_core::traits::Session;
+
+use crate::output::{self, Format, Report};
+use crate::session::storage;
+
+#[derive(Args, Debug)]
+pub struct RequestEmailConfirmationArgs {}
+
+/// The account a confirmation token was sent for.
+#[derive(Serialize)]
+struct RequestEmailConfirmationOutput {
+    did: String,
+}
+
+impl Report for RequestEmailConfirmationOutput {
+    fn print_text(&self) {
+        output::success("Confirmation email requested");
+        output::field("DID", &self.did);
+        println!("Pass the emailed token to 'atproto pds confirm-email'.");
+    }
+}
+
+pub async fn run(_args: RequestEmailConfirmationArgs, format: Format) -> Result<()> {
+    let session = storage::require_session().await?;
+
+    session
+        .request_email_confirmation()
+        .await
+        .context("Failed to request email confirmation")?;
+
+    output::report(
+        format,
+        &RequestEmailConfirmationOutput {
+            did: session.did().to_string(),
+        },
+    )
+}
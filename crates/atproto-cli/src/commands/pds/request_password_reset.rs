@@ -0,0 +1,70 @@
+//! Request password reset command implementation.
+//!
+//! Needs no session: the PDS emails a reset token to the address if an
+//! account has it.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use serde::Serialize;
+
+use muat_core::PdsUrl;
+use muat_core::traits::Pds;
+use muat_file::FilePds;
+use muat_xrpc::XrpcPds;
+
+use crate::output::{self, Format, Report};
+
+#[derive(Args, Debug)]
+pub struct RequestPasswordResetArgs {
+    /// Email address of the account
+    #[arg(long)]
+    pub email: String,
+
+    /// PDS base URL
+    #[arg(long, default_value = "https://bsky.social")]
+    pub pds: String,
+}
+
+/// The address a reset token was requested for.
+#[derive(Serialize)]
+struct RequestPasswordResetOutput {
+    email: String,
+    pds: String,
+}
+
+impl Report for RequestPasswordResetOutput {
+    fn print_text(&self) {
+        output::success("Password reset requested");
+        output::field("Email", &self.email);
+        output::field("PDS", &self.pds);
+        println!(
+            "If an account has this address, pass the emailed token to 'atproto pds reset-password'."
+        );
+    }
+}
+
+pub async fn run(args: RequestPasswordResetArgs, format: Format) -> Result<()> {
+    let pds_url = PdsUrl::new(&args.pds).context("Invalid PDS URL")?;
+
+    let requested = if pds_url.is_local() {
+        let path = pds_url
+            .to_file_path()
+            .context("Failed to convert file:// URL to path")?;
+        FilePds::new(&path, pds_url)
+            .request_password_reset(&args.email)
+            .await
+    } else {
+        XrpcPds::new(pds_url)
+            .request_password_reset(&args.email)
+            .await
+    };
+    requested.context("Failed to request password reset")?;
+
+    output::report(
+        format,
+        &RequestPasswordResetOutput {
+            email: args.email,
+            pds: args.pds,
+        },
+    )
+}
@@ -0,0 +1,61 @@
+//! Reset password command implementation.
+
+use anyhow::{Context, Result};
+use clap::Args;
+use serde::Serialize;
+
+use muat_core::PdsUrl;
+use muat_core::traits::Pds;
+use muat_file::FilePds;
+use muat_xrpc::XrpcPds;
+
+use crate::output::{self, Format, Report};
+
+#[derive(Args, Debug)]
+pub struct ResetPasswordArgs {
+    /// Token from the password reset email
+    #[arg(long)]
+    pub token: String,
+
+    /// New account password
+    #[arg(long)]
+    pub password: String,
+
+    /// PDS base URL
+    #[arg(long, default_value = "https://bsky.social")]
+    pub pds: String,
+}
+
+/// The PDS the password was reset on.
+#[derive(Serialize)]
+struct ResetPasswordOutput {
+    pds: String,
+}
+
+impl Report for ResetPasswordOutput {
+    fn print_text(&self) {
+        output::success("Password reset");
+        output::field("PDS", &self.pds);
+        println!("Log in again with 'atproto pds login'.");
+    }
+}
+
+pub async fn run(args: ResetPasswordArgs, format: Format) -> Result<()> {
+    let pds_url = PdsUrl::new(&args.pds).context("Invalid PDS URL")?;
+
+    let reset = if pds_url.is_local() {
+        let path = pds_url
+            .to_file_path()
+            .context("Failed to convert file:// URL to path")?;
+        FilePds::new(&path, pds_url)
+            .reset_password(&args.token, &args.password)
+            .await
+    } else {
+        XrpcPds::new(pds_url)
+            .reset_password(&args.token, &args.password)
+            .await
+    };
+    reset.context("Failed to reset password")?;
+
+    output::report(format, &ResetPasswordOutput { pds: args.pds })
+}
@@ -3,78 +3,225 @@
 use anyhow::{Context, Result};
 use clap::Args;
 use colored::Colorize;
+use serde::Serialize;
 use std::pin::Pin;
+use std::time::Instant;
 
 use futures_util::StreamExt;
 
 use muat_core::repo::RepoEvent;
 use muat_core::traits::{Firehose, Pds};
+use muat_core::{CancellationToken, FirehoseExt, PdsUrl};
 use muat_file::FilePds;
 use muat_xrpc::XrpcPds;
 
+use crate::output::{self, Format};
 use crate::session::storage;
 
 #[derive(Args, Debug)]
 pub struct SubscribeArgs {
+    /// PDS URL to subscribe to (defaults to the session PDS; no login needed when set)
+    #[arg(long)]
+    pub pds: Option<String>,
+
     /// Starting cursor position
     #[arg(long)]
     pub cursor: Option<i64>,
 
-    /// Output events as JSON
+    /// Output events as JSON lines (same as `--output json`)
     #[arg(long)]
     pub json: bool,
 
     /// Filter events by collection prefix (e.g., "app.bsky.")
     #[arg(long)]
     pub filter: Option<String>,
+
+    /// When the stream ends, also print the summary as a final
+    /// `{"summary": ...}` JSON line on stdout
+    #[arg(long)]
+    pub summary_json: bool,
+}
+
+/// Why the subscription ended.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+enum EndReason {
+    /// Ctrl+C was pressed or SIGTERM received.
+    Interrupted,
+    /// The server ended the stream.
+    Closed,
+    /// The stream failed; see the summary's `error`.
+    Error,
+}
+
+/// What a subscription received, printed when it ends so wrappers can log
+/// the session and resume from `lastCursor`.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SubscribeSummary {
+    /// Events received, including those the filter hid.
+    events: u64,
+    /// Error items received, such as undecodable frames.
+    errors: u64,
+    /// The highest sequence number received, or the starting `--cursor`
+    /// if nothing arrived; pass it as `--cursor` to resume after it.
+    last_cursor: Option<i64>,
+    duration_secs: f64,
+    reason: EndReason,
+    /// The error that ended the stream, for `reason: error`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
 }
 
-pub async fn run(args: SubscribeArgs) -> Result<()> {
-    let session = storage::load_session()
-        .await
-        .context("Failed to load session")?
-        .context("No active session. Run 'atproto pds login' first.")?;
+impl SubscribeSummary {
+    fn print_stderr(&self) {
+        let reason = match (self.reason, &self.error) {
+            (EndReason::Error, Some(error)) => format!("error: {}", error),
+            (EndReason::Error, None) => "error".to_string(),
+            (EndReason::Interrupted, _) => "interrupted".to_string(),
+            (EndReason::Closed, _) => "closed by server".to_string(),
+        };
+        let cursor = self
+            .last_cursor
+            .map_or_else(|| "none".to_string(), |seq| seq.to_string());
+        eprintln!("{} {}", "Stopped:".dimmed(), reason);
+        eprintln!("  {} {}", "Events:".dimmed(), self.events);
+        eprintln!("  {} {}", "Errors:".dimmed(), self.errors);
+        eprintln!("  {} {}", "Last cursor:".dimmed(), cursor);
+        eprintln!("  {} {:.1}s", "Duration:".dimmed(), self.duration_secs);
+    }
+}
+
+pub async fn run(args: SubscribeArgs, format: Format) -> Result<()> {
+    // The firehose is unauthenticated; the session is only used to find the PDS.
+    let pds_url = match &args.pds {
+        Some(url) => PdsUrl::new(url).context("Invalid PDS URL")?,
+        None => storage::require_session()
+            .await
+            .context("Pass --pds to subscribe without a session")?
+            .pds()
+            .clone(),
+    };
 
     eprintln!("{}", "Connecting to firehose...".dimmed());
     eprintln!("{}", "Press Ctrl+C to stop.".dimmed());
     eprintln!();
 
-    let json_output = args.json;
+    let format = if args.json { Format::Json } else { format };
     let filter = args.filter.clone();
 
-    let mut stream: Pin<Box<dyn Firehose>> = if session.pds().is_local() {
-        let path = session
-            .pds()
+    let stream: Pin<Box<dyn Firehose>> = if pds_url.is_local() {
+        let path = pds_url
             .to_file_path()
             .context("Failed to convert file:// URL to path")?;
-        let pds = FilePds::new(&path, session.pds().clone());
+        let pds = FilePds::new(&path, pds_url);
         Box::pin(
             pds.firehose_from(args.cursor)
                 .context("Failed to start subscription")?,
         )
     } else {
-        let pds = XrpcPds::new(session.pds().clone());
+        let pds = XrpcPds::new(pds_url);
         Box::pin(
             pds.firehose_from(args.cursor)
                 .context("Failed to start subscription")?,
         )
     };
 
+    // Ctrl+C or SIGTERM ends the stream, so the loop below exits normally
+    // and the summary is still printed.
+    let cancel = CancellationToken::new();
+    let on_interrupt = cancel.clone();
+    tokio::spawn(async move {
+        if interrupted().await {
+            on_interrupt.cancel();
+        }
+    });
+    let mut stream = stream.until_cancelled(cancel.clone());
+
+    let started = Instant::now();
+    let mut events = 0;
+    let mut errors = 0;
+    let mut last_cursor = args.cursor.filter(|cursor| *cursor > 0);
+    // The most recent error, if nothing has arrived since; a stream that
+    // ends right after one ended because of it.
+    let mut last_error = None;
     while let Some(result) = stream.next().await {
         match result {
             Ok(event) => {
-                handle_event(&event, json_output, filter.as_deref());
+                events += 1;
+                last_error = None;
+                if let Some(seq) = event.seq().filter(|seq| *seq > 0) {
+                    last_cursor = Some(last_cursor.map_or(seq, |last: i64| last.max(seq)));
+                }
+                handle_event(&event, format, filter.as_deref());
             }
             Err(e) => {
+                errors += 1;
                 eprintln!("{} {}", "ERROR".red(), e);
+                last_error = Some(e.to_string());
             }
         }
     }
 
+    let reason = if cancel.is_cancelled() {
+        EndReason::Interrupted
+    } else if last_error.is_some() {
+        EndReason::Error
+    } else {
+        EndReason::Closed
+    };
+    let summary = SubscribeSummary {
+        events,
+        errors,
+        last_cursor,
+        duration_secs: started.elapsed().as_secs_f64(),
+        reason,
+        error: last_error.filter(|_| reason == EndReason::Error),
+    };
+    summary.print_stderr();
+    if args.summary_json {
+        output::json(&serde_json::json!({ "summary": summary }))?;
+    }
     Ok(())
 }
 
-fn handle_event(event: &RepoEvent, json_output: bool, filter: Option<&str>) {
+/// Print one structured event: a JSON line, or a YAML document.
+///
+/// Returns `false` for text and table output, which are printed as text.
+fn print_structured<T: Serialize>(event: &T, format: Format) -> bool {
+    let printed = match format {
+        Format::Json => serde_json::to_string(event)
+            .map(|json| println!("{}", json))
+            .is_ok(),
+        Format::Yaml => serde_yaml::to_string(event)
+            .map(|yaml| print!("---\n{}", yaml))
+            .is_ok(),
+        Format::Text | Format::Table => return false,
+    };
+    if !printed {
+        eprintln!("{} failed to encode event", "ERROR".red());
+    }
+    true
+}
+
+/// Wait for Ctrl+C or, on Unix, SIGTERM from a supervisor. Returns false
+/// if no handler could be installed.
+async fn interrupted() -> bool {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+            return tokio::select! {
+                result = tokio::signal::ctrl_c() => result.is_ok(),
+                _ = terminate.recv() => true,
+            };
+        }
+    }
+    tokio::signal::ctrl_c().await.is_ok()
+}
+
+fn handle_event(event: &RepoEvent, format: Format, filter: Option<&str>) {
+    let structured = !matches!(format, Format::Text | Format::Table);
     match event {
         RepoEvent::Commit(commit) => {
             // Apply filter if specified
@@ -85,11 +232,7 @@ fn handle_event(event: &RepoEvent, json_output: bool, filter: Option<&str>) {
                 }
             }
 
-            if json_output {
-                if let Ok(json) = serde_json::to_string(&commit) {
-                    println!("{}", json);
-                }
-            } else {
+            if !print_structured(commit, format) {
                 println!(
                     "{} {} {} ops @ seq {}",
                     "COMMIT".green(),
@@ -109,11 +252,7 @@ fn handle_event(event: &RepoEvent, json_output: bool, filter: Option<&str>) {
             }
         }
         RepoEvent::Identity(identity) => {
-            if json_output {
-                if let Ok(json) = serde_json::to_string(&identity) {
-                    println!("{}", json);
-                }
-            } else {
+            if !print_structured(identity, format) {
                 println!(
                     "{} {} @ seq {}",
                     "IDENTITY".blue(),
@@ -123,11 +262,7 @@ fn handle_event(event: &RepoEvent, json_output: bool, filter: Option<&str>) {
             }
         }
         RepoEvent::Handle(handle) => {
-            if json_output {
-                if let Ok(json) = serde_json::to_string(&handle) {
-                    println!("{}", json);
-                }
-            } else {
+            if !print_structured(handle, format) {
                 println!(
                     "{} {} -> {} @ seq {}",
                     "HANDLE".magenta(),
@@ -138,7 +273,7 @@ fn handle_event(event: &RepoEvent, json_output: bool, filter: Option<&str>) {
             }
         }
         RepoEvent::Info(info) => {
-            if !json_output {
+            if !structured {
                 eprintln!(
                     "{} {} {}",
                     "INFO".dimmed(),
@@ -148,7 +283,7 @@ fn handle_event(event: &RepoEvent, json_output: bool, filter: Option<&str>) {
             }
         }
         RepoEvent::Unknown { kind } => {
-            if !json_output {
+            if !structured {
                 eprintln!("{} {}", "UNKNOWN".dimmed(), kind);
             }
         }
@@ -1,22 +1,76 @@
 //! Whoami command implementation.
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use clap::Args;
+use serde::Serialize;
 
-use crate::output;
+use crate::output::{self, Format, Report};
 use crate::session::storage;
 
 #[derive(Args, Debug)]
 pub struct WhoamiArgs {}
 
-pub async fn run(_args: WhoamiArgs) -> Result<()> {
-    let session = storage::load_session()
-        .await
-        .context("Failed to load session")?
-        .context("No active session. Run 'atproto pds login' first.")?;
+/// The active session.
+#[derive(Serialize)]
+struct WhoamiOutput {
+    did: String,
+    pds: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local: Option<LocalPdsOutput>,
+}
+
+/// Details of a local PDS directory.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LocalPdsOutput {
+    root: String,
+    accounts: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accounts_error: Option<String>,
+    writable: bool,
+}
+
+impl Report for WhoamiOutput {
+    fn print_text(&self) {
+        output::field("DID", &self.did);
+        output::field("PDS", &self.pds);
+
+        if let Some(local) = &self.local {
+            let accounts = match (local.accounts, &local.accounts_error) {
+                (Some(n), _) => n.to_string(),
+                (None, Some(e)) => format!("unknown ({})", e),
+                (None, None) => "unknown".to_string(),
+            };
+            output::field("Root", &local.root);
+            output::field("Accounts", &accounts);
+            output::field("Writable", if local.writable { "yes" } else { "no" });
+        }
+    }
+}
+
+pub async fn run(_args: WhoamiArgs, format: Format) -> Result<()> {
+    let session = storage::require_session().await?;
+
+    // Local PDS roots are easy to mix up, so show which one is in use.
+    let local = session.as_file().map(|file_session| {
+        let pds = file_session.file_pds();
+        let root = std::fs::canonicalize(pds.root()).unwrap_or_else(|_| pds.root().to_path_buf());
+        let accounts = pds.account_count();
 
-    output::field("DID", session.did().as_str());
-    output::field("PDS", session.pds().as_str());
+        LocalPdsOutput {
+            root: root.display().to_string(),
+            accounts: accounts.as_ref().ok().copied(),
+            accounts_error: accounts.err().map(|e| e.to_string()),
+            writable: pds.is_writable(),
+        }
+    });
 
-    Ok(())
+    output::report(
+        format,
+        &WhoamiOutput {
+            did: session.did().to_string(),
+            pds: session.pds().to_string(),
+            local,
+        },
+    )
 }
@@ -0,0 +1,162 @@
+//! External subcommands.
+//!
+//! Like git, `atproto foo ARGS...` runs `atproto-foo ARGS...` from `PATH`
+//! when `foo` is not a built-in command. The plugin inherits stdin, stdout
+//! and stderr, and its exit code becomes ours.
+//!
+//! Context is passed in environment variables. `ATPROTO_PLUGIN_HANDSHAKE`
+//! holds all of it as one JSON object with a `protocol` version, so plugins
+//! can check they understand it; the other variables repeat single fields for
+//! shell scripts. Tokens are never put in the environment: a plugin that
+//! needs the session reads `ATPROTO_SESSION_FILE`.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::output::Format;
+use crate::session::storage;
+
+/// Version of the handshake object. Bumped only for incompatible changes;
+/// new fields may be added without one.
+const PROTOCOL: u32 = 1;
+
+/// Prefix of plugin executables.
+const PREFIX: &str = "atproto-";
+
+/// No `atproto-<name>` plugin was found for an unknown command.
+#[derive(Debug)]
+pub struct UnknownCommand(pub String);
+
+impl std::fmt::Display for UnknownCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unknown command '{}', and no '{}{}' plugin on PATH. Run 'atproto --help' for commands.",
+            self.0, PREFIX, self.0
+        )
+    }
+}
+
+impl std::error::Error for UnknownCommand {}
+
+/// A plugin, or a command rerun from history, ran and exited
+/// unsuccessfully; it has reported why itself.
+#[derive(Debug)]
+pub struct PluginExit(pub u8);
+
+impl std::fmt::Display for PluginExit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Command exited with code {}", self.0)
+    }
+}
+
+impl std::error::Error for PluginExit {}
+
+/// Context handed to a plugin in `ATPROTO_PLUGIN_HANDSHAKE`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Handshake {
+    protocol: u32,
+    cli_version: &'static str,
+    /// Path of the `atproto` executable, to run built-in commands.
+    cli: Option<PathBuf>,
+    output: String,
+    verbose: u8,
+    session: Option<SessionContext>,
+}
+
+/// The stored session, without its tokens.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionContext {
+    did: String,
+    pds: String,
+    session_file: PathBuf,
+}
+
+/// Run the plugin for `args`, which start with the subcommand name.
+pub async fn run(args: Vec<OsString>, format: Format, verbose: u8) -> Result<()> {
+    let mut args = args.into_iter();
+    let name = args.next().unwrap_or_default();
+    let name = name.to_string_lossy().into_owned();
+    // The name becomes part of a program name looked up on PATH.
+    if name.is_empty() || name.starts_with('-') || name.contains(['/', '\\']) {
+        return Err(UnknownCommand(name).into());
+    }
+
+    let handshake = handshake(format, verbose)?;
+    let mut command = tokio::process::Command::new(format!("{}{}", PREFIX, name));
+    command
+        .args(args)
+        .env(
+            "ATPROTO_PLUGIN_HANDSHAKE",
+            serde_json::to_string(&handshake)?,
+        )
+        .env("ATPROTO_OUTPUT", &handshake.output)
+        .env("ATPROTO_VERBOSE", handshake.verbose.to_string());
+    if let Some(cli) = &handshake.cli {
+        command.env("ATPROTO_CLI", cli);
+    }
+    if let Some(session) = &handshake.session {
+        command
+            .env("ATPROTO_DID", &session.did)
+            .env("ATPROTO_PDS", &session.pds)
+            .env("ATPROTO_SESSION_FILE", &session.session_file);
+    }
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(UnknownCommand(name).into());
+        }
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to run plugin {}{}", PREFIX, name));
+        }
+    };
+
+    // Ctrl+C reaches the plugin too; let it decide when to stop, and wait.
+    tokio::spawn(async { while tokio::signal::ctrl_c().await.is_ok() {} });
+
+    let status = child
+        .wait()
+        .await
+        .with_context(|| format!("Failed to wait for plugin {}{}", PREFIX, name))?;
+    if status.success() {
+        return Ok(());
+    }
+    // A plugin killed by a signal has no code.
+    let code = status
+        .code()
+        .and_then(|code| u8::try_from(code).ok())
+        .unwrap_or(crate::exit::FAILURE);
+    Err(PluginExit(code).into())
+}
+
+fn handshake(format: Format, verbose: u8) -> Result<Handshake> {
+    let output = format
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default();
+
+    let session = match storage::read_stored_session()? {
+        Some(stored) => Some(SessionContext {
+            did: stored.did,
+            pds: stored.pds,
+            session_file: storage::session_path()?,
+        }),
+        None => None,
+    };
+
+    Ok(Handshake {
+        protocol: PROTOCOL,
+        cli_version: env!("ATPROTO_VERSION"),
+        cli: std::env::current_exe().ok(),
+        output,
+        verbose,
+        session,
+    })
+}
@@ -0,0 +1,153 @@
+//! Serve command implementation.
+//!
+//! Serves a local filesystem-backed PDS over XRPC so any AT Protocol client
+//! can be pointed at it. Requests are logged until the server is stopped with
+//! Ctrl-C. Records can be checked against lexicons, as a network PDS does.
+
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use clap::Args;
+use colored::Colorize;
+use serde::Serialize;
+use tokio::net::TcpListener;
+
+use muat_core::{Nsid, PdsUrl, RecordValue};
+use muat_file::FilePds;
+use muat_serve::FileServer;
+
+use crate::lexicon::Lexicon;
+use crate::output::{self, Format, Report};
+
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Local PDS directory
+    #[arg(long, default_value = "./pds")]
+    pub root: PathBuf,
+
+    /// Port to listen on (0 picks a free port)
+    #[arg(long, default_value_t = 2583)]
+    pub port: u16,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: IpAddr,
+
+    /// Only create accounts with handles under this domain (e.g.
+    /// .pds.example.com); repeat for several. Advertised in describeServer
+    #[arg(long = "user-domain", value_name = "DOMAIN")]
+    pub user_domains: Vec<String>,
+
+    /// Reject records that do not match this record lexicon (path or URL),
+    /// as a network PDS does; repeat for several
+    #[arg(long = "lexicon", value_name = "LEXICON")]
+    pub lexicons: Vec<String>,
+}
+
+/// Where the server is listening, printed before it starts serving.
+#[derive(Serialize)]
+struct ServeInfo {
+    root: String,
+    accounts: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    lexicons: Vec<String>,
+    url: String,
+}
+
+impl Report for ServeInfo {
+    fn print_text(&self) {
+        output::field("Root", &self.root);
+        output::field("Accounts", &self.accounts.to_string());
+        if !self.lexicons.is_empty() {
+            output::field("Lexicons", &self.lexicons.join(", "));
+        }
+        output::field("URL", &self.url);
+    }
+}
+
+pub async fn run(args: ServeArgs, format: Format) -> Result<()> {
+    let pds_url =
+        PdsUrl::new(format!("file://{}", args.root.display())).context("Invalid PDS root")?;
+    let mut pds = FilePds::new(&args.root, pds_url).with_user_domains(args.user_domains);
+    let mut lexicons = Vec::new();
+    for source in &args.lexicons {
+        let lexicon = Lexicon::load(source)
+            .await
+            .with_context(|| format!("Failed to load lexicon {}", source))?;
+        lexicon
+            .record_def()
+            .with_context(|| format!("Lexicon {} is not a record lexicon", source))?;
+        lexicons.push(lexicon.id().to_string());
+        pds = pds.with_validator(move |collection: &Nsid, record: &RecordValue| {
+            if collection.as_str() != lexicon.id() {
+                return Ok(());
+            }
+            match lexicon.validate_record(record.as_value()) {
+                Ok(errors) if errors.is_empty() => Ok(()),
+                Ok(errors) => Err(errors
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("; ")),
+                Err(e) => Err(e.to_string()),
+            }
+        });
+    }
+
+    if !pds.is_writable() {
+        bail!("PDS root {} is not writable", args.root.display());
+    }
+
+    let listener = TcpListener::bind((args.host, args.port))
+        .await
+        .with_context(|| format!("Failed to listen on {}:{}", args.host, args.port))?;
+    let addr = listener
+        .local_addr()
+        .context("Failed to read bound address")?;
+    // Clients only allow plain HTTP for localhost.
+    let url = if addr.ip().is_unspecified() {
+        format!("http://localhost:{}", addr.port())
+    } else {
+        format!("http://{}", addr)
+    };
+
+    let accounts = pds.account_count().context("Failed to read accounts")?;
+    output::report(
+        format,
+        &ServeInfo {
+            root: args.root.display().to_string(),
+            accounts,
+            lexicons,
+            url: url.clone(),
+        },
+    )?;
+
+    if !args.host.is_loopback() {
+        eprintln!(
+            "{} Listening on a non-loopback address; local PDS tokens contain password hashes",
+            "Warning:".yellow()
+        );
+    }
+    eprintln!(
+        "{}",
+        format!(
+            "Log in with: atproto pds login --pds {} --identifier <handle> --password <password>",
+            url
+        )
+        .dimmed()
+    );
+    eprintln!("{}", "Press Ctrl-C to stop.".dimmed());
+
+    FileServer::new(pds)
+        .serve_with_shutdown(listener, async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await
+        .context("Server failed")?;
+
+    if format == Format::Text {
+        output::success("Server stopped");
+    }
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! Process exit codes.
+//!
+//! These are stable so scripts can branch on them. Invalid command-line
+//! usage exits with 2, as reported by the argument parser, and so does an
+//! unknown command with no plugin.
+
+use muat_core::Error;
+
+use crate::commands::pds::get_record::RecordDeleted;
+use crate::commands::plugin::UnknownCommand;
+use crate::session::storage::NoSession;
+
+/// Any failure not covered below.
+pub const FAILURE: u8 = 1;
+/// The command line was invalid.
+pub const USAGE: u8 = 2;
+/// There is no session, or the PDS rejected the credentials.
+pub const AUTH: u8 = 3;
+/// The requested record, account or resource does not exist.
+pub const NOT_FOUND: u8 = 4;
+/// The PDS could not be reached or read.
+pub const TRANSPORT: u8 = 5;
+/// An identifier, payload or request was rejected as invalid.
+pub const INVALID_INPUT: u8 = 6;
+/// A record followed with `get-record --watch` was deleted.
+pub const DELETED: u8 = 7;
+
+/// Exit code for an error, from the first library error in its chain.
+pub fn code_for(error: &anyhow::Error) -> u8 {
+    for cause in error.chain() {
+        if cause.is::<NoSession>() {
+            return AUTH;
+        }
+        if cause.is::<UnknownCommand>() {
+            return USAGE;
+        }
+        if cause.is::<RecordDeleted>() {
+            return DELETED;
+        }
+        if let Some(error) = cause.downcast_ref::<Error>() {
+            return match error {
+                Error::Auth(_) => AUTH,
+                Error::Transport(_) | Error::Firehose(_) => TRANSPORT,
+                Error::InvalidInput(_) => INVALID_INPUT,
+                Error::Protocol(e) => match e.status {
+                    401 | 403 => AUTH,
+                    404 => NOT_FOUND,
+                    400..=499 => INVALID_INPUT,
+                    _ => FAILURE,
+                },
+            };
+        }
+    }
+    FAILURE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+    use muat_core::error::ProtocolError;
+
+    #[test]
+    fn codes_follow_the_error_chain() {
+        let not_found: Result<(), Error> = Err(Error::Protocol(ProtocolError::new(
+            404,
+            Some("RecordNotFound".to_string()),
+            None,
+        )));
+        let error = not_found.context("Failed to get record").unwrap_err();
+        assert_eq!(code_for(&error), NOT_FOUND);
+
+        let error = anyhow::Error::new(NoSession).context("Failed to load session");
+        assert_eq!(code_for(&error), AUTH);
+
+        let error = anyhow::Error::new(UnknownCommand("nope".to_string()));
+        assert_eq!(code_for(&error), USAGE);
+
+        let uri =
+            muat_core::AtUri::new("at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.feed.post/3k2a")
+                .unwrap();
+        let error = anyhow::Error::new(RecordDeleted(uri));
+        assert_eq!(code_for(&error), DELETED);
+
+        assert_eq!(code_for(&anyhow::anyhow!("other")), FAILURE);
+    }
+}
@@ -0,0 +1,337 @@
+//! Interactive record builder driven by a lexicon schema.
+//!
+//! Prompts for each property of the record definition, showing its type,
+//! constraints and default, and checks every answer against the lexicon
+//! before moving on. Values the form cannot ask for field by field (unions,
+//! blobs, `unknown`, external refs) are entered as JSON.
+
+use std::io::{BufRead, Write};
+
+use anyhow::{Result, bail};
+use serde_json::{Map, Value};
+
+use crate::lexicon::{Lexicon, child, string_list};
+
+/// Builds a record by prompting for each field of a lexicon.
+pub struct RecordForm<'a, R, W> {
+    lexicon: &'a Lexicon,
+    input: R,
+    output: W,
+}
+
+impl<'a, R: BufRead, W: Write> RecordForm<'a, R, W> {
+    /// Create a form reading answers from `input` and writing prompts to `output`.
+    pub fn new(lexicon: &'a Lexicon, input: R, output: W) -> Self {
+        Self {
+            lexicon,
+            input,
+            output,
+        }
+    }
+
+    /// Prompt for every field of the lexicon's record definition.
+    pub fn prompt_record(&mut self) -> Result<Value> {
+        let lexicon = self.lexicon;
+        self.prompt_object(lexicon.record_def()?, "")
+    }
+
+    fn prompt_object(&mut self, def: &'a Value, pointer: &str) -> Result<Value> {
+        let mut obj = Map::new();
+        let Some(properties) = def.get("properties").and_then(Value::as_object) else {
+            return Ok(Value::Object(obj));
+        };
+
+        // Required fields first, in the order the lexicon lists them.
+        let required = string_list(def, "required");
+        let mut names: Vec<&str> = required
+            .iter()
+            .copied()
+            .filter(|name| properties.contains_key(*name))
+            .collect();
+        names.extend(
+            properties
+                .keys()
+                .map(String::as_str)
+                .filter(|name| !required.contains(name)),
+        );
+
+        for name in names {
+            let pointer = child(pointer, name);
+            let is_required = required.contains(&name);
+            if let Some(value) = self.prompt_field(&properties[name], &pointer, is_required)? {
+                obj.insert(name.to_string(), value);
+            }
+        }
+
+        Ok(Value::Object(obj))
+    }
+
+    fn prompt_field(
+        &mut self,
+        def: &'a Value,
+        pointer: &str,
+        required: bool,
+    ) -> Result<Option<Value>> {
+        let def = self.deref(def);
+        let label = pointer.trim_start_matches('/');
+
+        if def_type(def) == "object" {
+            if !required && !self.confirm(&format!("Add {}?", label))? {
+                return Ok(None);
+            }
+            return self.prompt_object(def, pointer).map(Some);
+        }
+
+        let default = default_value(def);
+        let prompt = match &default {
+            Some(value) => format!(
+                "{} ({}) [{}]: ",
+                label,
+                self.describe(def, required),
+                display(value)
+            ),
+            None => format!("{} ({}): ", label, self.describe(def, required)),
+        };
+
+        loop {
+            let line = self.ask(&prompt)?;
+            let value = if line.trim().is_empty() {
+                match &default {
+                    Some(value) => value.clone(),
+                    None if !required => return Ok(None),
+                    None => {
+                        writeln!(self.output, "  {} is required", label)?;
+                        continue;
+                    }
+                }
+            } else {
+                match self.parse(def, &line) {
+                    Ok(value) => value,
+                    Err(message) => {
+                        writeln!(self.output, "  {}", message)?;
+                        continue;
+                    }
+                }
+            };
+
+            let errors = self.lexicon.validate_value(def, &value, pointer);
+            if errors.is_empty() {
+                return Ok(Some(value));
+            }
+            for error in errors {
+                writeln!(self.output, "  {}", error.message)?;
+            }
+        }
+    }
+
+    /// Follow local refs so prompts describe the target definition.
+    fn deref(&self, def: &'a Value) -> &'a Value {
+        let lexicon = self.lexicon;
+        match def.get("ref").and_then(Value::as_str) {
+            Some(reference) if def_type(def) == "ref" => match lexicon.resolve(reference) {
+                Some(target) => self.deref(target),
+                None => def,
+            },
+            _ => def,
+        }
+    }
+
+    /// Parse one line of input for a definition.
+    fn parse(&self, def: &Value, line: &str) -> Result<Value, String> {
+        let line = line.trim();
+        match def_type(def) {
+            "string" => Ok(Value::String(line.to_string())),
+            "integer" => line
+                .parse::<i64>()
+                .map(Value::from)
+                .map_err(|_| format!("'{}' is not an integer", line)),
+            "boolean" => match line.to_ascii_lowercase().as_str() {
+                "y" | "yes" | "true" => Ok(Value::Bool(true)),
+                "n" | "no" | "false" => Ok(Value::Bool(false)),
+                _ => Err(format!("'{}' is not yes or no", line)),
+            },
+            "array" => match def.get("items").map(|items| self.deref(items)) {
+                Some(items) if is_primitive(items) => line
+                    .split(',')
+                    .map(|item| self.parse(items, item))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(Value::Array),
+                _ => parse_json(line),
+            },
+            _ => parse_json(line),
+        }
+    }
+
+    /// Short description of a definition's type and constraints.
+    fn describe(&self, def: &Value, required: bool) -> String {
+        let kind = def_type(def);
+        let mut parts = vec![match kind {
+            "string" => match def.get("format").and_then(Value::as_str) {
+                Some(format) => format!("string, {}", format),
+                None => "string".to_string(),
+            },
+            "integer" => "integer".to_string(),
+            "boolean" => "yes/no".to_string(),
+            "array" => match def.get("items").map(|items| self.deref(items)) {
+                Some(items) if is_primitive(items) => {
+                    format!("{}s, comma-separated", def_type(items))
+                }
+                _ => "array, JSON".to_string(),
+            },
+            other => format!("{}, JSON", other),
+        }];
+
+        parts.push(if required { "required" } else { "optional" }.to_string());
+
+        for (key, name) in [
+            ("maxLength", "max length"),
+            ("maxGraphemes", "max graphemes"),
+            ("minimum", "min"),
+            ("maximum", "max"),
+        ] {
+            if let Some(limit) = def.get(key).and_then(Value::as_i64) {
+                parts.push(format!("{} {}", name, limit));
+            }
+        }
+
+        let choices = string_list(def, "enum");
+        let known = string_list(def, "knownValues");
+        if !choices.is_empty() {
+            parts.push(format!("one of {}", choices.join("|")));
+        } else if !known.is_empty() {
+            parts.push(format!("e.g. {}", known.join("|")));
+        }
+
+        parts.join(", ")
+    }
+
+    fn confirm(&mut self, question: &str) -> Result<bool> {
+        let answer = self.ask(&format!("{} [y/N]: ", question))?;
+        Ok(matches!(
+            answer.trim().to_ascii_lowercase().as_str(),
+            "y" | "yes"
+        ))
+    }
+
+    fn ask(&mut self, prompt: &str) -> Result<String> {
+        write!(self.output, "{}", prompt)?;
+        self.output.flush()?;
+
+        let mut line = String::new();
+        if self.input.read_line(&mut line)? == 0 {
+            bail!("Input ended before the record was complete");
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+}
+
+fn def_type(def: &Value) -> &str {
+    def.get("type").and_then(Value::as_str).unwrap_or("unknown")
+}
+
+fn is_primitive(def: &Value) -> bool {
+    matches!(def_type(def), "string" | "integer" | "boolean")
+}
+
+/// The value used when a field is left empty.
+fn default_value(def: &Value) -> Option<Value> {
+    if let Some(value) = def.get("const").or_else(|| def.get("default")) {
+        return Some(value.clone());
+    }
+    if def_type(def) == "string" && def.get("format").and_then(Value::as_str) == Some("datetime") {
+        let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        return Some(Value::String(now));
+    }
+    None
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn parse_json(line: &str) -> Result<Value, String> {
+    serde_json::from_str(line).map_err(|e| format!("invalid JSON: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn lexicon() -> Lexicon {
+        Lexicon::from_value(json!({
+            "lexicon": 1,
+            "id": "org.example.note",
+            "defs": {
+                "main": {
+                    "type": "record",
+                    "key": "tid",
+                    "record": {
+                        "type": "object",
+                        "required": ["text", "createdAt"],
+                        "properties": {
+                            "text": {"type": "string", "maxLength": 10},
+                            "createdAt": {"type": "string", "format": "datetime"},
+                            "pinned": {"type": "boolean", "default": false},
+                            "tags": {"type": "array", "items": {"type": "string"}},
+                            "meta": {"type": "ref", "ref": "#meta"}
+                        }
+                    }
+                },
+                "meta": {
+                    "type": "object",
+                    "properties": {"score": {"type": "integer", "minimum": 0}}
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    fn fill(answers: &str) -> (Result<Value>, String) {
+        let lexicon = lexicon();
+        let mut output = Vec::new();
+        let result = RecordForm::new(&lexicon, answers.as_bytes(), &mut output).prompt_record();
+        (result, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn builds_record_from_answers() {
+        // text, createdAt, then optional fields in name order: meta, pinned, tags.
+        let (record, _) = fill("hello\n2024-01-01T00:00:00Z\ny\n3\n\na, b\n");
+        assert_eq!(
+            record.unwrap(),
+            json!({
+                "text": "hello",
+                "createdAt": "2024-01-01T00:00:00Z",
+                "meta": {"score": 3},
+                "pinned": false,
+                "tags": ["a", "b"]
+            })
+        );
+    }
+
+    #[test]
+    fn reprompts_until_valid() {
+        let (record, output) = fill("\nfar too long text\nok\nyesterday\n\nn\n\n\n");
+        let record = record.unwrap();
+
+        assert_eq!(record["text"], "ok");
+        assert!(record.get("meta").is_none());
+        assert!(record.get("tags").is_none());
+        assert!(
+            chrono::DateTime::parse_from_rfc3339(record["createdAt"].as_str().unwrap()).is_ok()
+        );
+        assert!(output.contains("text is required"));
+        assert!(output.contains("maximum is 10"));
+        assert!(output.contains("not an RFC 3339 datetime"));
+    }
+
+    #[test]
+    fn fails_when_input_ends() {
+        let (record, _) = fill("hello\n");
+        assert!(record.is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! Command history.
+//!
+//! When `ATPROTO_HISTORY` is set, each invocation is appended to a JSON
+//! Lines file: its arguments with secrets redacted, working directory, how
+//! long it took, its exit code and any AT URIs in its result. Set it to `1`
+//! to use `history.jsonl` in the data directory, or to a file path.
+//! `atproto history` lists and reruns the entries.
+
+use std::cell::RefCell;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::session::storage;
+
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
+/// Environment variable that turns history on.
+pub const ENV: &str = "ATPROTO_HISTORY";
+
+/// Stands in for a redacted argument.
+pub const REDACTED: &str = "<redacted>";
+
+/// Flag names containing any of these have their values redacted.
+const SECRET_FLAGS: &[&str] = &["password", "token", "secret"];
+
+tokio::task_local! {
+    /// AT URIs reported by the running command.
+    static RESULT_URIS: RefCell<Vec<String>>;
+}
+
+/// One recorded invocation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub started_at: DateTime<Utc>,
+    /// Arguments after the program name, with secret values redacted.
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    pub duration_ms: u64,
+    pub exit_code: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub uris: Vec<String>,
+}
+
+impl Entry {
+    /// Returns true if the command succeeded.
+    pub fn ok(&self) -> bool {
+        self.exit_code == 0
+    }
+
+    /// Returns true if any argument was redacted, so the entry cannot be
+    /// rerun as recorded.
+    pub fn redacted(&self) -> bool {
+        self.args.iter().any(|arg| arg.ends_with(REDACTED))
+    }
+}
+
+/// The history file, or `None` if `ATPROTO_HISTORY` is unset, empty or `0`.
+pub fn enabled_path() -> Result<Option<PathBuf>> {
+    match std::env::var_os(ENV) {
+        None => Ok(None),
+        Some(value) if value.is_empty() || value == "0" => Ok(None),
+        Some(value) if value == "1" => Ok(Some(default_path()?)),
+        Some(value) => Ok(Some(PathBuf::from(value))),
+    }
+}
+
+/// The history file to read: the enabled one, or the default location.
+pub fn path() -> Result<PathBuf> {
+    match enabled_path()? {
+        Some(path) => Ok(path),
+        None => default_path(),
+    }
+}
+
+fn default_path() -> Result<PathBuf> {
+    Ok(storage::data_dir()?.join("history.jsonl"))
+}
+
+/// Read every entry, oldest first, skipping lines that do not parse.
+pub fn read() -> Result<Vec<Entry>> {
+    let path = path()?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to read {}", path.display()));
+        }
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Records the running invocation.
+pub struct Recorder {
+    path: PathBuf,
+    started_at: DateTime<Utc>,
+    start: Instant,
+    args: Vec<String>,
+}
+
+impl Recorder {
+    /// Start recording this process's invocation, if history is on.
+    pub fn start() -> Option<Self> {
+        let path = match enabled_path() {
+            Ok(path) => path?,
+            Err(e) => {
+                tracing::warn!("Not recording history: {:#}", e);
+                return None;
+            }
+        };
+        let args = std::env::args_os()
+            .skip(1)
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        Some(Self {
+            path,
+            started_at: Utc::now(),
+            start: Instant::now(),
+            args: redact(&args),
+        })
+    }
+
+    /// Run `command`, collecting the AT URIs it reports.
+    pub async fn run<F: Future>(&self, command: F) -> (F::Output, Vec<String>) {
+        RESULT_URIS
+            .scope(RefCell::new(Vec::new()), async {
+                let output = command.await;
+                (output, RESULT_URIS.with(|uris| uris.take()))
+            })
+            .await
+    }
+
+    /// Append the finished invocation to the history file. Failing to write
+    /// it is logged and does not fail the command.
+    pub fn finish(self, exit_code: u8, error: Option<&anyhow::Error>, uris: Vec<String>) {
+        let entry = Entry {
+            started_at: self.started_at,
+            args: self.args,
+            cwd: std::env::current_dir().ok(),
+            duration_ms: u64::try_from(self.start.elapsed().as_millis()).unwrap_or(u64::MAX),
+            exit_code,
+            error: error.map(|e| format!("{:#}", e)),
+            uris,
+        };
+        if let Err(e) = append(&self.path, &entry) {
+            tracing::warn!(
+                "Failed to record history in {}: {:#}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Note the AT URIs in a command result, if history is being recorded.
+pub fn note_uris<T: Serialize>(result: &T) {
+    let _ = RESULT_URIS.try_with(|uris| {
+        if let Ok(value) = serde_json::to_value(result) {
+            collect_uris(&value, &mut uris.borrow_mut());
+        }
+    });
+}
+
+fn collect_uris(value: &Value, uris: &mut Vec<String>) {
+    match value {
+        Value::String(s) if s.starts_with("at://") && !uris.contains(s) => uris.push(s.clone()),
+        Value::Array(items) => items.iter().for_each(|item| collect_uris(item, uris)),
+        Value::Object(fields) => fields.values().for_each(|field| collect_uris(field, uris)),
+        _ => {}
+    }
+}
+
+fn append(path: &std::path::Path, entry: &Entry) -> Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+
+    // Arguments can still be sensitive, so keep the file private.
+    let mut options = fs::OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path)?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Replace the values of secret-looking flags, given either as
+/// `--password VALUE` or `--password=VALUE`.
+fn redact(args: &[String]) -> Vec<String> {
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut secret_next = false;
+    for arg in args {
+        if secret_next {
+            redacted.push(REDACTED.to_string());
+            secret_next = false;
+            continue;
+        }
+        match arg.strip_prefix("--").map(|flag| flag.split_once('=')) {
+            Some(Some((name, _))) if is_secret(name) => {
+                redacted.push(format!("--{}={}", name, REDACTED));
+            }
+            Some(None) if is_secret(&arg[2..]) => {
+                secret_next = true;
+                redacted.push(arg.clone());
+            }
+            _ => redacted.push(arg.clone()),
+        }
+    }
+    redacted
+}
+
+fn is_secret(flag: &str) -> bool {
+    let flag = flag.to_ascii_lowercase();
+    SECRET_FLAGS.iter().any(|secret| flag.contains(secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn secret_flag_values_are_redacted() {
+        let redacted = redact(&args(&[
+            "pds",
+            "login",
+            "--identifier",
+            "alice.test",
+            "--password",
+            "hunter2",
+            "--token=ABCDE-FGHIJ",
+            "--app-secret",
+            "s3cret",
+        ]));
+        assert_eq!(
+            redacted,
+            args(&[
+                "pds",
+                "login",
+                "--identifier",
+                "alice.test",
+                "--password",
+                REDACTED,
+                "--token=<redacted>",
+                "--app-secret",
+                REDACTED,
+            ])
+        );
+        assert_eq!(
+            redact(&args(&["pds", "refresh-token"])),
+            args(&["pds", "refresh-token"])
+        );
+    }
+
+    #[test]
+    fn uris_are_collected_once() {
+        let value = serde_json::json!({
+            "uri": "at://did:plc:abc/app.bsky.feed.post/1",
+            "records": [
+                { "uri": "at://did:plc:abc/app.bsky.feed.post/1" },
+                { "uri": "at://did:plc:abc/app.bsky.feed.post/2", "text": "hi" },
+            ],
+        });
+        let mut uris = Vec::new();
+        collect_uris(&value, &mut uris);
+        assert_eq!(
+            uris,
+            vec![
+                "at://did:plc:abc/app.bsky.feed.post/1",
+                "at://did:plc:abc/app.bsky.feed.post/2",
+            ]
+        );
+    }
+}
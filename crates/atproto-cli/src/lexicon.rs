@@ -0,0 +1,514 @@
+//! Lexicon schema validation for record payloads.
+//!
+//! This is a client-side sanity check, not a full lexicon implementation.
+//! It understands the record, object, primitive, array, ref and union
+//! definitions found in typical record lexicons and reports every violation
+//! with a JSON pointer into the payload.
+
+use std::fmt;
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use unicode_segmentation::UnicodeSegmentation;
+
+use muat_core::{BlobRef, Cid};
+use muat_xrpc::{HttpMethod, HttpRequest, HttpTransport, ReqwestTransport};
+
+/// A single validation failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    /// JSON pointer (RFC 6901) to the offending value.
+    pub pointer: String,
+    /// What is wrong with the value.
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pointer = if self.pointer.is_empty() {
+            "/"
+        } else {
+            &self.pointer
+        };
+        write!(f, "{}: {}", pointer, self.message)
+    }
+}
+
+/// A parsed lexicon document.
+#[derive(Debug, Clone)]
+pub struct Lexicon {
+    id: String,
+    defs: Map<String, Value>,
+}
+
+impl Lexicon {
+    /// Parse a lexicon document from JSON.
+    pub fn from_value(doc: Value) -> Result<Self> {
+        let id = doc
+            .get("id")
+            .and_then(Value::as_str)
+            .context("Lexicon is missing 'id'")?
+            .to_string();
+        let defs = doc
+            .get("defs")
+            .and_then(Value::as_object)
+            .context("Lexicon is missing 'defs'")?
+            .clone();
+
+        Ok(Self { id, defs })
+    }
+
+    /// Load a lexicon from a local path or an `https://` URL.
+    pub async fn load(source: &str) -> Result<Self> {
+        let content = if source.starts_with("https://") || source.starts_with("http://") {
+            let transport = ReqwestTransport::new();
+            let response = transport
+                .send(HttpRequest::new(HttpMethod::Get, source))
+                .await
+                .context("Failed to fetch lexicon")?;
+            if !response.is_success() {
+                bail!("Failed to fetch lexicon: HTTP {}", response.status);
+            }
+            String::from_utf8(response.body).context("Lexicon is not valid UTF-8")?
+        } else {
+            std::fs::read_to_string(source).context("Failed to read lexicon file")?
+        };
+
+        let doc: Value = serde_json::from_str(&content).context("Invalid lexicon JSON")?;
+        Self::from_value(doc)
+    }
+
+    /// Returns the lexicon NSID.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the object definition of the `main` record.
+    pub fn record_def(&self) -> Result<&Value> {
+        let main = self
+            .defs
+            .get("main")
+            .context("Lexicon has no 'main' definition")?;
+
+        if main.get("type").and_then(Value::as_str) != Some("record") {
+            bail!("Lexicon 'main' definition is not a record");
+        }
+
+        main.get("record")
+            .context("Record definition is missing 'record'")
+    }
+
+    /// Validate a record payload against the `main` record definition.
+    pub fn validate_record(&self, value: &Value) -> Result<Vec<ValidationError>> {
+        let record = self.record_def()?;
+        let mut errors = Vec::new();
+
+        if let Some(record_type) = value.get("$type").and_then(Value::as_str)
+            && record_type != self.id
+        {
+            errors.push(ValidationError {
+                pointer: "/$type".to_string(),
+                message: format!("expected '{}', found '{}'", self.id, record_type),
+            });
+        }
+
+        self.validate(record, value, "", &mut errors);
+        Ok(errors)
+    }
+
+    /// Validate a value against a single definition from this lexicon.
+    pub fn validate_value(
+        &self,
+        def: &Value,
+        value: &Value,
+        pointer: &str,
+    ) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        self.validate(def, value, pointer, &mut errors);
+        errors
+    }
+
+    fn validate(
+        &self,
+        def: &Value,
+        value: &Value,
+        pointer: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let def_type = def.get("type").and_then(Value::as_str).unwrap_or("unknown");
+
+        let fail = |errors: &mut Vec<ValidationError>, message: String| {
+            errors.push(ValidationError {
+                pointer: pointer.to_string(),
+                message,
+            })
+        };
+
+        match def_type {
+            "object" => match value.as_object() {
+                Some(obj) => self.validate_object(def, obj, pointer, errors),
+                None => fail(errors, format!("expected object, found {}", kind(value))),
+            },
+            "string" => match value.as_str() {
+                Some(s) => validate_string(def, s, &mut |m| fail(errors, m)),
+                None => fail(errors, format!("expected string, found {}", kind(value))),
+            },
+            "integer" => match value.as_i64() {
+                Some(n) => validate_integer(def, n, &mut |m| fail(errors, m)),
+                None => fail(errors, format!("expected integer, found {}", kind(value))),
+            },
+            "boolean" if !value.is_boolean() => {
+                fail(errors, format!("expected boolean, found {}", kind(value)))
+            }
+            "array" => match value.as_array() {
+                Some(items) => self.validate_array(def, items, pointer, errors),
+                None => fail(errors, format!("expected array, found {}", kind(value))),
+            },
+            "ref" => match def.get("ref").and_then(Value::as_str) {
+                Some(reference) => {
+                    // External refs cannot be checked offline.
+                    if let Some(target) = self.resolve(reference) {
+                        self.validate(target, value, pointer, errors);
+                    }
+                }
+                None => fail(errors, "ref definition is missing 'ref'".to_string()),
+            },
+            "union" => self.validate_union(def, value, pointer, errors),
+            "blob" => {
+                if let Err(e) = BlobRef::deserialize(value) {
+                    fail(errors, format!("expected blob reference: {}", e))
+                }
+            }
+            "cid-link" => match value.get("$link").and_then(Value::as_str) {
+                Some(link) => {
+                    if let Err(e) = Cid::new(link) {
+                        fail(errors, e.to_string())
+                    }
+                }
+                None => fail(errors, "expected CID link object with '$link'".to_string()),
+            },
+            "bytes" if value.get("$bytes").and_then(Value::as_str).is_none() => {
+                fail(errors, "expected bytes object with '$bytes'".to_string())
+            }
+            "null" if !value.is_null() => {
+                fail(errors, format!("expected null, found {}", kind(value)))
+            }
+            // Valid values, "unknown", and anything newer than this validator.
+            _ => {}
+        }
+    }
+
+    fn validate_object(
+        &self,
+        def: &Value,
+        obj: &Map<String, Value>,
+        pointer: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let nullable = string_list(def, "nullable");
+
+        for required in string_list(def, "required") {
+            if !obj.contains_key(required) {
+                errors.push(ValidationError {
+                    pointer: child(pointer, required),
+                    message: "required field is missing".to_string(),
+                });
+            }
+        }
+
+        let Some(properties) = def.get("properties").and_then(Value::as_object) else {
+            return;
+        };
+
+        for (name, property) in properties {
+            let Some(field) = obj.get(name) else {
+                continue;
+            };
+            if field.is_null() && nullable.contains(&name.as_str()) {
+                continue;
+            }
+            self.validate(property, field, &child(pointer, name), errors);
+        }
+    }
+
+    fn validate_array(
+        &self,
+        def: &Value,
+        items: &[Value],
+        pointer: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let len = items.len() as u64;
+        if let Some(min) = def.get("minLength").and_then(Value::as_u64)
+            && len < min
+        {
+            errors.push(ValidationError {
+                pointer: pointer.to_string(),
+                message: format!("array has {} items, minimum is {}", len, min),
+            });
+        }
+        if let Some(max) = def.get("maxLength").and_then(Value::as_u64)
+            && len > max
+        {
+            errors.push(ValidationError {
+                pointer: pointer.to_string(),
+                message: format!("array has {} items, maximum is {}", len, max),
+            });
+        }
+
+        if let Some(item_def) = def.get("items") {
+            for (i, item) in items.iter().enumerate() {
+                self.validate(item_def, item, &child(pointer, &i.to_string()), errors);
+            }
+        }
+    }
+
+    fn validate_union(
+        &self,
+        def: &Value,
+        value: &Value,
+        pointer: &str,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        let Some(value_type) = value.get("$type").and_then(Value::as_str) else {
+            errors.push(ValidationError {
+                pointer: pointer.to_string(),
+                message: "union member must have a '$type'".to_string(),
+            });
+            return;
+        };
+
+        let refs = string_list(def, "refs");
+        let matching = refs
+            .iter()
+            .find(|r| self.qualify(r) == value_type || **r == value_type);
+
+        match matching {
+            Some(reference) => {
+                if let Some(target) = self.resolve(reference) {
+                    self.validate(target, value, pointer, errors);
+                }
+            }
+            None if def.get("closed").and_then(Value::as_bool) == Some(true) => {
+                errors.push(ValidationError {
+                    pointer: child(pointer, "$type"),
+                    message: format!("'{}' is not one of {}", value_type, refs.join(", ")),
+                });
+            }
+            None => {}
+        }
+    }
+
+    /// Resolve a ref to a definition in this lexicon.
+    pub fn resolve(&self, reference: &str) -> Option<&Value> {
+        let (nsid, name) = match reference.split_once('#') {
+            Some((nsid, name)) => (nsid, name),
+            None => (reference, "main"),
+        };
+
+        if nsid.is_empty() || nsid == self.id {
+            self.defs.get(name)
+        } else {
+            None
+        }
+    }
+
+    /// Expand a local `#name` ref to its fully-qualified `$type` form.
+    fn qualify(&self, reference: &str) -> String {
+        match reference.strip_prefix('#') {
+            Some(name) => format!("{}#{}", self.id, name),
+            None => reference.to_string(),
+        }
+    }
+}
+
+fn validate_string(def: &Value, s: &str, fail: &mut impl FnMut(String)) {
+    let len = s.len() as u64;
+    if let Some(max) = def.get("maxLength").and_then(Value::as_u64)
+        && len > max
+    {
+        fail(format!("string is {} bytes, maximum is {}", len, max));
+    }
+    if let Some(min) = def.get("minLength").and_then(Value::as_u64)
+        && len < min
+    {
+        fail(format!("string is {} bytes, minimum is {}", len, min));
+    }
+
+    let graphemes = s.graphemes(true).count() as u64;
+    if let Some(max) = def.get("maxGraphemes").and_then(Value::as_u64)
+        && graphemes > max
+    {
+        fail(format!(
+            "string is {} graphemes, maximum is {}",
+            graphemes, max
+        ));
+    }
+    if let Some(min) = def.get("minGraphemes").and_then(Value::as_u64)
+        && graphemes < min
+    {
+        fail(format!(
+            "string is {} graphemes, minimum is {}",
+            graphemes, min
+        ));
+    }
+
+    if let Some(allowed) = def.get("enum").and_then(Value::as_array)
+        && !allowed.iter().any(|v| v.as_str() == Some(s))
+    {
+        fail(format!("'{}' is not an allowed value", s));
+    }
+    if let Some(expected) = def.get("const").and_then(Value::as_str)
+        && expected != s
+    {
+        fail(format!("expected constant '{}'", expected));
+    }
+
+    if def.get("format").and_then(Value::as_str) == Some("datetime")
+        && chrono::DateTime::parse_from_rfc3339(s).is_err()
+    {
+        fail(format!("'{}' is not an RFC 3339 datetime", s));
+    }
+}
+
+fn validate_integer(def: &Value, n: i64, fail: &mut impl FnMut(String)) {
+    if let Some(min) = def.get("minimum").and_then(Value::as_i64)
+        && n < min
+    {
+        fail(format!("{} is less than minimum {}", n, min));
+    }
+    if let Some(max) = def.get("maximum").and_then(Value::as_i64)
+        && n > max
+    {
+        fail(format!("{} is greater than maximum {}", n, max));
+    }
+    if let Some(allowed) = def.get("enum").and_then(Value::as_array)
+        && !allowed.iter().any(|v| v.as_i64() == Some(n))
+    {
+        fail(format!("{} is not an allowed value", n));
+    }
+}
+
+pub(crate) fn string_list<'a>(def: &'a Value, key: &str) -> Vec<&'a str> {
+    def.get(key)
+        .and_then(Value::as_array)
+        .map(|items| items.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default()
+}
+
+/// Append a reference token to a JSON pointer, escaping per RFC 6901.
+pub(crate) fn child(pointer: &str, token: &str) -> String {
+    format!(
+        "{}/{}",
+        pointer,
+        token.replace('~', "~0").replace('/', "~1")
+    )
+}
+
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn lexicon() -> Lexicon {
+        Lexicon::from_value(json!({
+            "lexicon": 1,
+            "id": "org.example.note",
+            "defs": {
+                "main": {
+                    "type": "record",
+                    "key": "tid",
+                    "record": {
+                        "type": "object",
+                        "required": ["text", "createdAt"],
+                        "properties": {
+                            "text": {"type": "string", "maxLength": 10},
+                            "createdAt": {"type": "string", "format": "datetime"},
+                            "tags": {"type": "array", "items": {"type": "string"}, "maxLength": 2},
+                            "meta": {"type": "ref", "ref": "#meta"}
+                        }
+                    }
+                },
+                "meta": {
+                    "type": "object",
+                    "properties": {"score": {"type": "integer", "minimum": 0}}
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn valid_record_has_no_errors() {
+        let value = json!({
+            "$type": "org.example.note",
+            "text": "hello",
+            "createdAt": "2024-01-01T00:00:00Z"
+        });
+        assert!(lexicon().validate_record(&value).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_json_pointers() {
+        let value = json!({
+            "$type": "org.example.note",
+            "text": "this is far too long",
+            "tags": ["a", 1, "c"],
+            "meta": {"score": -1}
+        });
+        let pointers: Vec<String> = lexicon()
+            .validate_record(&value)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.pointer)
+            .collect();
+
+        assert_eq!(
+            pointers,
+            vec!["/createdAt", "/meta/score", "/tags", "/tags/1", "/text"]
+        );
+    }
+
+    #[test]
+    fn grapheme_limits_count_clusters() {
+        let lexicon = Lexicon::from_value(json!({
+            "lexicon": 1,
+            "id": "org.example.status",
+            "defs": {
+                "main": {
+                    "type": "record",
+                    "key": "tid",
+                    "record": {
+                        "type": "object",
+                        "properties": {"text": {"type": "string", "maxGraphemes": 3}}
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        let status = |text: &str| json!({"$type": "org.example.status", "text": text});
+
+        // A decomposed "é" and a family emoji are one grapheme each.
+        let three = "e\u{301}\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}!";
+        assert!(lexicon.validate_record(&status(three)).unwrap().is_empty());
+        assert_eq!(lexicon.validate_record(&status("abcd")).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn pointer_tokens_are_escaped() {
+        assert_eq!(child("", "a/b~c"), "/a~1b~0c");
+    }
+}
@@ -5,26 +5,72 @@
 
 mod cli;
 mod commands;
+mod exit;
+mod form;
+mod history;
+mod lexicon;
 mod output;
 mod session;
 
-use anyhow::Result;
+use std::process::ExitCode;
+
 use clap::Parser;
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
 use cli::{Cli, Commands};
-use commands::pds;
+use commands::history as history_cmd;
+use commands::{batch, bsky, doctor, mirror, pds, plugin, serve};
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     let cli = Cli::parse();
 
-    // Initialize logging
-    init_logging(cli.verbose, cli.json_logs);
+    // Initialize logging; the server logs each request at info level.
+    let verbose = match cli.command {
+        Commands::Serve(_) => cli.verbose.max(1),
+        _ => cli.verbose,
+    };
+    init_logging(verbose, cli.json_logs);
+
+    // Listing or rerunning history is not itself recorded.
+    let recorder = match cli.command {
+        Commands::History(_) => None,
+        _ => history::Recorder::start(),
+    };
+
+    let format = cli.output;
+    let run = async {
+        match cli.command {
+            Commands::Pds(pds_cmd) => pds::handle(pds_cmd, format).await,
+            Commands::Bsky(bsky_cmd) => bsky::handle(bsky_cmd, format).await,
+            Commands::Batch(args) => batch::run(args).await,
+            Commands::Doctor(args) => doctor::run(args, format).await,
+            Commands::Serve(args) => serve::run(args, format).await,
+            Commands::Mirror(cmd) => mirror::handle(cmd, format).await,
+            Commands::History(cmd) => history_cmd::handle(cmd, format).await,
+            Commands::External(args) => plugin::run(args, format, cli.verbose).await,
+        }
+    };
+    let (result, uris) = match &recorder {
+        Some(recorder) => recorder.run(run).await,
+        None => (run.await, Vec::new()),
+    };
 
-    match cli.command {
-        Commands::Pds(pds_cmd) => pds::handle(pds_cmd).await,
+    let code = match &result {
+        Ok(()) => 0,
+        // The plugin has already reported its own failure.
+        Err(e) => match e.downcast_ref() {
+            Some(plugin::PluginExit(code)) => *code,
+            None => {
+                eprintln!("Error: {:?}", e);
+                exit::code_for(e)
+            }
+        },
+    };
+    if let Some(recorder) = recorder {
+        recorder.finish(code, result.as_ref().err(), uris);
     }
+    ExitCode::from(code)
 }
 
 fn init_logging(verbosity: u8, json: bool) {
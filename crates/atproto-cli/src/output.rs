@@ -1,8 +1,54 @@
 //! Output formatting helpers.
+//!
+//! Commands build a serializable result and hand it to [`report`], which
+//! renders it in the format chosen with the global `--output` flag.
 
 use anyhow::Result;
+use clap::ValueEnum;
 use colored::Colorize;
 use serde::Serialize;
+use serde_json::Value;
+
+use crate::history;
+
+/// Output format selected with the global `--output` flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Format {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// Pretty-printed JSON
+    Json,
+    /// YAML
+    Yaml,
+    /// Aligned columns
+    Table,
+}
+
+/// A command result with a stable, serializable shape.
+///
+/// Field names are part of the CLI's scripting interface.
+pub trait Report: Serialize {
+    /// Print the human-readable form used by `--output text`.
+    fn print_text(&self);
+}
+
+/// Print a command result in the selected format.
+pub fn report<T: Report>(format: Format, result: &T) -> Result<()> {
+    history::note_uris(result);
+    match format {
+        Format::Text => {
+            result.print_text();
+            Ok(())
+        }
+        Format::Json => json_pretty(result),
+        Format::Yaml => yaml(result),
+        Format::Table => {
+            table(&serde_json::to_value(result)?);
+            Ok(())
+        }
+    }
+}
 
 /// Print a success message.
 pub fn success(msg: &str) {
@@ -33,3 +79,115 @@ pub fn json_pretty<T: Serialize>(value: &T) -> Result<()> {
     println!("{}", json);
     Ok(())
 }
+
+/// Print a value as a YAML document.
+pub fn yaml<T: Serialize>(value: &T) -> Result<()> {
+    print!("{}", serde_yaml::to_string(value)?);
+    Ok(())
+}
+
+/// Print a value as aligned columns.
+///
+/// Arrays become one row per element. Objects become FIELD/VALUE rows,
+/// followed by a table for each field holding an array of objects.
+pub fn table(value: &Value) {
+    match value {
+        Value::Array(items) => print_rows(items),
+        Value::Object(fields) => {
+            let mut rows = Vec::new();
+            let mut lists = Vec::new();
+            for (name, field) in fields {
+                match field {
+                    Value::Array(items)
+                        if items.iter().all(Value::is_object) && !items.is_empty() =>
+                    {
+                        lists.push((name, items))
+                    }
+                    Value::Null => {}
+                    other => rows.push(vec![name.clone(), cell(other)]),
+                }
+            }
+            if !rows.is_empty() {
+                print_columns(&["FIELD".to_string(), "VALUE".to_string()], &rows);
+            }
+            for (name, items) in lists {
+                if !rows.is_empty() {
+                    println!();
+                }
+                println!("{}", name.to_uppercase().dimmed());
+                print_rows(items);
+            }
+        }
+        other => println!("{}", cell(other)),
+    }
+}
+
+/// Print array elements as rows, with a column per object key.
+fn print_rows(items: &[Value]) {
+    let mut columns: Vec<&str> = Vec::new();
+    for item in items {
+        if let Value::Object(fields) = item {
+            for name in fields.keys() {
+                if !columns.contains(&name.as_str()) {
+                    columns.push(name);
+                }
+            }
+        }
+    }
+
+    if columns.is_empty() {
+        let rows: Vec<Vec<String>> = items.iter().map(|item| vec![cell(item)]).collect();
+        print_columns(&["VALUE".to_string()], &rows);
+        return;
+    }
+
+    let headers: Vec<String> = columns.iter().map(|c| c.to_uppercase()).collect();
+    let rows: Vec<Vec<String>> = items
+        .iter()
+        .map(|item| {
+            columns
+                .iter()
+                .map(|c| item.get(c).map_or_else(String::new, cell))
+                .collect()
+        })
+        .collect();
+    print_columns(&headers, &rows);
+}
+
+fn print_columns(headers: &[String], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+
+    let line = |values: &[String]| {
+        let last = values.len().saturating_sub(1);
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                if i == last {
+                    v.clone()
+                } else {
+                    format!("{:width$}", v, width = widths[i])
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+
+    println!("{}", line(headers).dimmed());
+    for row in rows {
+        println!("{}", line(row));
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
@@ -0,0 +1,48 @@
+//! Token expiry for display.
+//!
+//! The library treats tokens as opaque. The CLI only peeks at the JWT `exp`
+//! claim to tell the user when a token expires; nothing else is read.
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Claims {
+    exp: Option<i64>,
+}
+
+/// Returns the expiry of a JWT, or `None` if the token is not a JWT or has
+/// no `exp` claim. The signature is not verified.
+pub fn jwt_expiry(token: &str) -> Option<DateTime<Utc>> {
+    let payload = token.split('.').nth(1)?;
+    let bytes = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    let claims: Claims = serde_json::from_slice(&bytes).ok()?;
+    DateTime::from_timestamp(claims.exp?, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jwt(claims: &str) -> String {
+        format!(
+            "eyJhbGciOiJIUzI1NiJ9.{}.sig",
+            URL_SAFE_NO_PAD.encode(claims)
+        )
+    }
+
+    #[test]
+    fn reads_exp_claim() {
+        let expiry = jwt_expiry(&jwt(r#"{"sub":"did:plc:abc","exp":1893456000}"#)).unwrap();
+        assert_eq!(expiry.to_rfc3339(), "2030-01-01T00:00:00+00:00");
+    }
+
+    #[test]
+    fn missing_or_opaque_tokens_have_no_expiry() {
+        assert!(jwt_expiry(&jwt(r#"{"sub":"did:plc:abc"}"#)).is_none());
+        assert!(jwt_expiry("opaque-token").is_none());
+        assert!(jwt_expiry("a.!!!.c").is_none());
+    }
+}
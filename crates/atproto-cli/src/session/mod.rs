@@ -1,5 +1,7 @@
 //! Session management for the CLI.
 
+pub mod expiry;
+pub mod reader;
 pub mod storage;
 mod types;
 
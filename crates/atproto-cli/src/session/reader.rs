@@ -0,0 +1,156 @@
+//! Record reads with or without a session.
+//!
+//! Repository records are public, so read commands fall back to anonymous
+//! reads when no session is stored.
+
+use anyhow::{Context, Result};
+use futures_util::{Stream, TryStreamExt, stream};
+
+use muat_core::repo::{ListRecordsOptions, ListRecordsOutput, Record};
+use muat_core::traits::{Pds, Session};
+use muat_core::{AtUri, Did, Nsid, PdsUrl};
+use muat_file::FilePds;
+use muat_xrpc::XrpcPds;
+
+use super::CliSession;
+use super::storage;
+
+/// PDS used for anonymous reads when neither a session nor `--pds` is given.
+pub const DEFAULT_PDS: &str = "https://bsky.social";
+
+/// Reads records through the active session, or anonymously from a PDS.
+pub enum RecordReader {
+    Session(CliSession),
+    Xrpc(XrpcPds),
+    File(FilePds),
+}
+
+impl RecordReader {
+    /// Read from `pds` anonymously if given, else through the stored
+    /// session, else anonymously from [`DEFAULT_PDS`].
+    pub async fn open(pds: Option<&str>) -> Result<Self> {
+        if let Some(pds) = pds {
+            return Self::public(pds);
+        }
+        match storage::load_session()
+            .await
+            .context("Failed to load session")?
+        {
+            Some(session) => Ok(Self::Session(session)),
+            None => Self::public(DEFAULT_PDS),
+        }
+    }
+
+    fn public(pds: &str) -> Result<Self> {
+        let pds_url = PdsUrl::new(pds).context("Invalid PDS URL")?;
+        if pds_url.is_local() {
+            let path = pds_url
+                .to_file_path()
+                .context("Failed to convert file:// URL to path")?;
+            return Ok(Self::File(FilePds::new(&path, pds_url)));
+        }
+        Ok(Self::Xrpc(XrpcPds::new(pds_url)))
+    }
+
+    /// The session's DID, used when no repo is given.
+    pub fn default_repo(&self) -> Result<Did> {
+        match self {
+            Self::Session(session) => Ok(session.did().clone()),
+            Self::Xrpc(_) | Self::File(_) => {
+                Err(storage::NoSession).context("Pass --repo to read without a session")
+            }
+        }
+    }
+
+    /// Resolve a `--repo` value, which may be a DID or a handle.
+    pub async fn resolve_repo(&self, repo: &str) -> Result<Did> {
+        if repo.starts_with("did:") {
+            return Did::new(repo).context("Invalid repo DID");
+        }
+        let did = match self {
+            Self::Session(CliSession::File(session)) => session
+                .file_pds()
+                .resolve_handle(repo)?
+                .with_context(|| format!("No local account with handle {}", repo))?,
+            Self::Session(CliSession::Xrpc(session)) => XrpcPds::new(session.pds().clone())
+                .resolve_handle(repo)
+                .await
+                .with_context(|| format!("Failed to resolve handle {}", repo))?,
+            Self::Xrpc(pds) => pds
+                .resolve_handle(repo)
+                .await
+                .with_context(|| format!("Failed to resolve handle {}", repo))?,
+            Self::File(pds) => pds
+                .resolve_handle(repo)?
+                .with_context(|| format!("No local account with handle {}", repo))?,
+        };
+        Ok(did)
+    }
+
+    /// Fetch a single record.
+    pub async fn get_record(&self, uri: &AtUri) -> muat_core::Result<Record> {
+        match self {
+            Self::Session(session) => session.get_record(uri).await,
+            Self::Xrpc(pds) => pds.get_record_public(uri).await,
+            Self::File(pds) => pds.get_record_public(uri).await,
+        }
+    }
+
+    /// Fetch the version of a record with the given CID. Only a local PDS
+    /// keeps earlier versions.
+    pub async fn get_record_at(&self, uri: &AtUri, cid: &str) -> muat_core::Result<Record> {
+        match self {
+            Self::Session(session) => session.get_record_at(uri, cid).await,
+            Self::Xrpc(pds) => pds.get_record_at(uri, cid).await,
+            Self::File(pds) => pds.get_record_at(uri, cid).await,
+        }
+    }
+
+    /// List the collections of a repo that hold records.
+    pub async fn list_collections(&self, repo: &Did) -> muat_core::Result<Vec<Nsid>> {
+        match self {
+            Self::Session(session) => session.list_collections(repo).await,
+            Self::Xrpc(pds) => pds.list_collections_public(repo).await,
+            Self::File(pds) => pds.list_collections_public(repo).await,
+        }
+    }
+
+    /// List one page of records in a collection.
+    pub async fn list_records_with(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        options: &ListRecordsOptions,
+    ) -> muat_core::Result<ListRecordsOutput> {
+        match self {
+            Self::Session(session) => session.list_records_with(repo, collection, options).await,
+            Self::Xrpc(pds) => pds.list_records_public(repo, collection, options).await,
+            Self::File(pds) => pds.list_records_public(repo, collection, options).await,
+        }
+    }
+
+    /// Stream records from `options`' cursor onwards, fetching further
+    /// pages only as the stream is read.
+    pub fn records<'a>(
+        &'a self,
+        repo: &'a Did,
+        collection: &'a Nsid,
+        options: ListRecordsOptions,
+    ) -> impl Stream<Item = muat_core::Result<Record>> + 'a {
+        stream::try_unfold(Some(options), move |options| async move {
+            let Some(options) = options else {
+                return Ok::<_, muat_core::Error>(None);
+            };
+            let page = self.list_records_with(repo, collection, &options).await?;
+            // A cursor that does not move would repeat the same page forever.
+            let next = match page.cursor {
+                Some(cursor) if options.get_cursor() != Some(cursor.as_str()) => {
+                    Some(options.cursor(cursor))
+                }
+                _ => None,
+            };
+            Ok(Some((stream::iter(page.records.into_iter().map(Ok)), next)))
+        })
+        .try_flatten()
+    }
+}
@@ -1,9 +1,13 @@
 //! Session storage for persisting login state.
 
 use std::fs;
-use std::path::PathBuf;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use colored::Colorize;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 
@@ -13,80 +17,183 @@ use muat_file::{FilePds, FileSession};
 use muat_xrpc::XrpcSession;
 
 use super::CliSession;
+use super::expiry::jwt_expiry;
 
 #[cfg(unix)]
-use std::os::unix::fs::PermissionsExt;
+use std::os::unix::fs::OpenOptionsExt;
+
+/// Returned when a command needs a session and none is stored.
+#[derive(Debug)]
+pub struct NoSession;
+
+impl std::fmt::Display for NoSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("No active session. Run 'atproto pds login' first.")
+    }
+}
+
+impl std::error::Error for NoSession {}
 
 /// Stored session data.
 #[derive(Debug, Serialize, Deserialize)]
-struct StoredSession {
-    did: String,
-    pds: String,
-    access_token: String,
-    refresh_token: Option<String>,
+pub struct StoredSession {
+    pub did: String,
+    pub pds: String,
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    /// When the session was last loaded or saved. Only tracked with a
+    /// `max_idle_secs` limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used: Option<DateTime<Utc>>,
+    /// How long the session may go unused before it is pruned.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_idle_secs: Option<u64>,
 }
 
-/// Get the session file path.
-fn session_path() -> Result<PathBuf> {
-    if let Some(dir) = std::env::var_os("ATPROTO_DATA_DIR") {
-        let data_dir = PathBuf::from(dir);
-        fs::create_dir_all(&data_dir).context("Failed to create data directory")?;
-        return Ok(data_dir.join("session.json"));
-    }
+/// Why a stored session can provably no longer be used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Staleness {
+    /// The refresh token's `exp` claim has passed.
+    RefreshExpired(DateTime<Utc>),
+    /// There is no refresh token and the access token's `exp` has passed.
+    AccessExpired(DateTime<Utc>),
+    /// The session went unused for longer than its idle limit.
+    Idle {
+        last_used: DateTime<Utc>,
+        max_idle: Duration,
+    },
+}
 
-    if let Some(dir) = std::env::var_os("XDG_DATA_HOME") {
-        let data_dir = PathBuf::from(dir).join("atproto");
-        fs::create_dir_all(&data_dir).context("Failed to create data directory")?;
-        return Ok(data_dir.join("session.json"));
+impl std::fmt::Display for Staleness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RefreshExpired(at) => write!(f, "refresh token expired {}", at.to_rfc3339()),
+            Self::AccessExpired(at) => write!(f, "access token expired {}", at.to_rfc3339()),
+            Self::Idle {
+                last_used,
+                max_idle,
+            } => write!(
+                f,
+                "unused since {}, longer than its {}s idle limit",
+                last_used.to_rfc3339(),
+                max_idle.as_secs()
+            ),
+        }
     }
+}
+
+impl StoredSession {
+    /// Returns why the session can no longer be used at `now`, or `None` if
+    /// it may still work. Tokens without an `exp` claim never count as
+    /// expired, so only provably dead sessions are reported.
+    pub fn staleness(&self, now: DateTime<Utc>) -> Option<Staleness> {
+        match &self.refresh_token {
+            Some(refresh) => {
+                if let Some(at) = jwt_expiry(refresh).filter(|at| *at <= now) {
+                    return Some(Staleness::RefreshExpired(at));
+                }
+            }
+            None => {
+                if let Some(at) = jwt_expiry(&self.access_token).filter(|at| *at <= now) {
+                    return Some(Staleness::AccessExpired(at));
+                }
+            }
+        }
 
-    if let Some(dirs) = ProjectDirs::from("", "", "atproto") {
-        let data_dir = dirs.data_dir();
-        fs::create_dir_all(data_dir).context("Failed to create data directory")?;
-        return Ok(data_dir.join("session.json"));
+        let max_idle = Duration::from_secs(self.max_idle_secs?);
+        let last_used = self.last_used?;
+        let idle = (now - last_used).to_std().unwrap_or_default();
+        (idle > max_idle).then_some(Staleness::Idle {
+            last_used,
+            max_idle,
+        })
     }
+}
+
+/// Get the session file path.
+pub fn session_path() -> Result<PathBuf> {
+    Ok(data_dir()?.join("session.json"))
+}
 
-    if let Some(home) = std::env::var_os("HOME") {
-        let data_dir = PathBuf::from(home)
+/// Get the directory holding the session and other CLI state, creating it
+/// if needed.
+pub fn data_dir() -> Result<PathBuf> {
+    let data_dir = if let Some(dir) = std::env::var_os("ATPROTO_DATA_DIR") {
+        PathBuf::from(dir)
+    } else if let Some(dir) = std::env::var_os("XDG_DATA_HOME") {
+        PathBuf::from(dir).join("atproto")
+    } else if let Some(dirs) = ProjectDirs::from("", "", "atproto") {
+        dirs.data_dir().to_path_buf()
+    } else if let Some(home) = std::env::var_os("HOME") {
+        PathBuf::from(home)
             .join(".local")
             .join("share")
-            .join("atproto");
-        fs::create_dir_all(&data_dir).context("Failed to create data directory")?;
-        return Ok(data_dir.join("session.json"));
-    }
-
-    anyhow::bail!("Could not determine config directory");
+            .join("atproto")
+    } else {
+        anyhow::bail!("Could not determine config directory");
+    };
+    fs::create_dir_all(&data_dir).context("Failed to create data directory")?;
+    Ok(data_dir)
 }
 
-/// Save a session to disk.
+/// Save a session to disk, keeping the idle limit of the stored session
+/// it replaces if that was the same account.
 pub async fn save_session(session: &CliSession) -> Result<()> {
-    let access_token = session.access_token();
+    let max_idle_secs = read_stored_session()
+        .ok()
+        .flatten()
+        .filter(|stored| stored.did == session.did().as_str())
+        .and_then(|stored| stored.max_idle_secs);
+    write_session(&stored_session(session, max_idle_secs))
+}
 
-    let stored = StoredSession {
+/// Save a newly logged-in session, pruned once unused for `max_idle`.
+pub async fn save_new_session(session: &CliSession, max_idle: Option<Duration>) -> Result<()> {
+    write_session(&stored_session(
+        session,
+        max_idle.map(|max_idle| max_idle.as_secs()),
+    ))
+}
+
+fn stored_session(session: &CliSession, max_idle_secs: Option<u64>) -> StoredSession {
+    StoredSession {
         did: session.did().to_string(),
         pds: session.pds().to_string(),
-        access_token: access_token.as_str().to_string(),
+        access_token: session.access_token().as_str().to_string(),
         refresh_token: session.refresh_token().map(|t| t.as_str().to_string()),
-    };
+        last_used: max_idle_secs.map(|_| Utc::now()),
+        max_idle_secs,
+    }
+}
 
+fn write_session(stored: &StoredSession) -> Result<()> {
     let path = session_path()?;
     let json = serde_json::to_string_pretty(&stored)?;
 
-    fs::write(&path, &json).context("Failed to write session file")?;
+    // Write to a temporary file and rename it over the old one, so the stored
+    // session is either entirely old or entirely new.
+    let tmp_path = path.with_extension("json.tmp");
+    let _ = fs::remove_file(&tmp_path);
+    write_private(&tmp_path, json.as_bytes()).context("Failed to write session file")?;
+    fs::rename(&tmp_path, &path).context("Failed to replace session file")?;
+
+    Ok(())
+}
 
-    // Set restrictive permissions (Unix only)
+/// Write a file readable only by the current user (Unix only).
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
     #[cfg(unix)]
-    {
-        let mut perms = fs::metadata(&path)?.permissions();
-        perms.set_mode(0o600);
-        fs::set_permissions(&path, perms)?;
-    }
+    options.mode(0o600);
 
-    Ok(())
+    let mut file = options.open(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
 }
 
-/// Load a session from disk.
-pub async fn load_session() -> Result<Option<CliSession>> {
+/// Read the stored session without contacting the PDS.
+pub fn read_stored_session() -> Result<Option<StoredSession>> {
     let path = session_path()?;
 
     if !path.exists() {
@@ -94,7 +201,35 @@ pub async fn load_session() -> Result<Option<CliSession>> {
     }
 
     let json = fs::read_to_string(&path).context("Failed to read session file")?;
-    let stored: StoredSession = serde_json::from_str(&json).context("Invalid session file")?;
+    let stored = serde_json::from_str(&json).context("Invalid session file")?;
+    Ok(Some(stored))
+}
+
+/// Load a session from disk.
+///
+/// A session that is provably unusable (see [`StoredSession::staleness`])
+/// is removed with a warning, and treated as no session.
+pub async fn load_session() -> Result<Option<CliSession>> {
+    let Some(mut stored) = read_stored_session()? else {
+        return Ok(None);
+    };
+
+    if let Some(staleness) = stored.staleness(Utc::now()) {
+        clear_session().await?;
+        eprintln!(
+            "{} Removed the stored session for {}: {}",
+            "Warning:".yellow(),
+            stored.did,
+            staleness
+        );
+        return Ok(None);
+    }
+    if stored.max_idle_secs.is_some() {
+        stored.last_used = Some(Utc::now());
+        if let Err(e) = write_session(&stored) {
+            tracing::warn!(error = %e, "Failed to record session use");
+        }
+    }
 
     let pds = PdsUrl::new(&stored.pds).context("Invalid PDS URL in session")?;
     let did = Did::new(&stored.did).context("Invalid DID in session")?;
@@ -108,18 +243,36 @@ pub async fn load_session() -> Result<Option<CliSession>> {
             .context("Failed to convert file:// URL to path")?;
         let file_pds = FilePds::new(&path, pds);
         let session = FileSession::from_persisted(file_pds, access_token)?;
-        Ok(Some(CliSession::File(session)))
+        Ok(Some(CliSession::File(Box::new(session))))
     } else {
-        let session = XrpcSession::from_persisted(pds.clone(), did, access_token, refresh_token);
-        if let Err(e) = session.refresh().await {
-            tracing::warn!(error = %e, "Failed to refresh session, using existing tokens");
+        let session = XrpcSession::from_persisted(pds.clone(), did, access_token, refresh_token)
+            .context("Stored session is corrupted; log in again")?;
+        let refreshed = match session.refresh().await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to refresh session, using existing tokens");
+                false
+            }
+        };
+
+        let session = CliSession::Xrpc(session);
+        // The PDS rotates the refresh token, so the stored one is now spent.
+        if refreshed && let Err(e) = save_session(&session).await {
+            tracing::warn!(error = %e, "Failed to save refreshed session");
         }
-        Ok(Some(CliSession::Xrpc(session)))
+        Ok(Some(session))
     }
 }
 
+/// Load the stored session, failing with [`NoSession`] if there is none.
+pub async fn require_session() -> Result<CliSession> {
+    load_session()
+        .await
+        .context("Failed to load session")?
+        .ok_or_else(|| NoSession.into())
+}
+
 /// Clear the stored session.
-#[allow(dead_code)]
 pub async fn clear_session() -> Result<()> {
     let path = session_path()?;
 
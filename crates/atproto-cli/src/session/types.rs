@@ -1,18 +1,19 @@
 //! CLI session wrapper.
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 
-use muat_core::repo::{ListRecordsOutput, Record, RecordValue};
-use muat_core::traits::Session;
-use muat_core::types::{AtUri, Did, Nsid, PdsUrl};
-use muat_core::{AccessToken, RefreshToken, Result};
+use muat_core::repo::{BulkReport, ListRecordsOptions, ListRecordsOutput, Record, RecordValue};
+use muat_core::traits::{AccountStatus, BlobStore, Session};
+use muat_core::types::{AtUri, Did, Nsid, PdsUrl, Rkey};
+use muat_core::{AccessToken, CancellationToken, RefreshToken, Result};
 use muat_file::FileSession;
 use muat_xrpc::XrpcSession;
 
 /// Session wrapper for CLI use.
 #[derive(Debug)]
 pub enum CliSession {
-    File(FileSession),
+    File(Box<FileSession>),
     Xrpc(XrpcSession),
 }
 
@@ -51,6 +52,13 @@ impl CliSession {
             _ => None,
         }
     }
+
+    pub fn as_file(&self) -> Option<&FileSession> {
+        match self {
+            CliSession::File(session) => Some(session),
+            _ => None,
+        }
+    }
 }
 
 #[async_trait]
@@ -83,20 +91,22 @@ impl Session for CliSession {
         }
     }
 
-    async fn list_records(
+    async fn list_records_with(
         &self,
         repo: &Did,
         collection: &Nsid,
-        limit: Option<u32>,
-        cursor: Option<&str>,
+        options: &ListRecordsOptions,
     ) -> Result<ListRecordsOutput> {
         match self {
-            CliSession::File(session) => {
-                session.list_records(repo, collection, limit, cursor).await
-            }
-            CliSession::Xrpc(session) => {
-                session.list_records(repo, collection, limit, cursor).await
-            }
+            CliSession::File(session) => session.list_records_with(repo, collection, options).await,
+            CliSession::Xrpc(session) => session.list_records_with(repo, collection, options).await,
+        }
+    }
+
+    async fn list_collections(&self, repo: &Did) -> Result<Vec<Nsid>> {
+        match self {
+            CliSession::File(session) => session.list_collections(repo).await,
+            CliSession::Xrpc(session) => session.list_collections(repo).await,
         }
     }
 
@@ -107,6 +117,13 @@ impl Session for CliSession {
         }
     }
 
+    async fn get_record_at(&self, uri: &AtUri, cid: &str) -> Result<Record> {
+        match self {
+            CliSession::File(session) => session.get_record_at(uri, cid).await,
+            CliSession::Xrpc(session) => session.get_record_at(uri, cid).await,
+        }
+    }
+
     async fn create_record(&self, collection: &Nsid, value: &RecordValue) -> Result<AtUri> {
         match self {
             CliSession::File(session) => session.create_record(collection, value).await,
@@ -114,10 +131,106 @@ impl Session for CliSession {
         }
     }
 
+    async fn create_records_bulk(
+        &self,
+        collection: &Nsid,
+        values: Vec<RecordValue>,
+        concurrency: usize,
+        cancel: Option<&CancellationToken>,
+    ) -> BulkReport<RecordValue, AtUri> {
+        match self {
+            CliSession::File(session) => {
+                session
+                    .create_records_bulk(collection, values, concurrency, cancel)
+                    .await
+            }
+            CliSession::Xrpc(session) => {
+                session
+                    .create_records_bulk(collection, values, concurrency, cancel)
+                    .await
+            }
+        }
+    }
+
+    async fn put_record(
+        &self,
+        collection: &Nsid,
+        rkey: &Rkey,
+        value: &RecordValue,
+        swap_cid: Option<&str>,
+    ) -> Result<AtUri> {
+        match self {
+            CliSession::File(session) => {
+                session.put_record(collection, rkey, value, swap_cid).await
+            }
+            CliSession::Xrpc(session) => {
+                session.put_record(collection, rkey, value, swap_cid).await
+            }
+        }
+    }
+
     async fn delete_record(&self, uri: &AtUri) -> Result<()> {
         match self {
             CliSession::File(session) => session.delete_record(uri).await,
             CliSession::Xrpc(session) => session.delete_record(uri).await,
         }
     }
+
+    async fn delete_records_bulk(
+        &self,
+        uris: Vec<AtUri>,
+        concurrency: usize,
+        cancel: Option<&CancellationToken>,
+    ) -> BulkReport<AtUri, AtUri> {
+        match self {
+            CliSession::File(session) => {
+                session.delete_records_bulk(uris, concurrency, cancel).await
+            }
+            CliSession::Xrpc(session) => {
+                session.delete_records_bulk(uris, concurrency, cancel).await
+            }
+        }
+    }
+
+    async fn request_email_confirmation(&self) -> Result<()> {
+        match self {
+            CliSession::File(session) => session.request_email_confirmation().await,
+            CliSession::Xrpc(session) => session.request_email_confirmation().await,
+        }
+    }
+
+    async fn confirm_email(&self, email: &str, token: &str) -> Result<()> {
+        match self {
+            CliSession::File(session) => session.confirm_email(email, token).await,
+            CliSession::Xrpc(session) => session.confirm_email(email, token).await,
+        }
+    }
+
+    async fn check_account_status(&self) -> Result<AccountStatus> {
+        match self {
+            CliSession::File(session) => session.check_account_status().await,
+            CliSession::Xrpc(session) => session.check_account_status().await,
+        }
+    }
+
+    async fn activate_account(&self) -> Result<()> {
+        match self {
+            CliSession::File(session) => session.activate_account().await,
+            CliSession::Xrpc(session) => session.activate_account().await,
+        }
+    }
+
+    async fn deactivate_account(&self, delete_after: Option<DateTime<Utc>>) -> Result<()> {
+        match self {
+            CliSession::File(session) => session.deactivate_account(delete_after).await,
+            CliSession::Xrpc(session) => session.deactivate_account(delete_after).await,
+        }
+    }
+
+    fn blobs(&self) -> &dyn BlobStore {
+        match self {
+            CliSession::File(session) => session.blobs(),
+            CliSession::Xrpc(session) => session.blobs(),
+        }
+    }
 }
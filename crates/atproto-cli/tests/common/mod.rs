@@ -79,18 +79,6 @@ pub fn apply_home_env(cmd: &mut Command, home: &Path) {
 /// Delete all test records (cleanup helper).
 #[allow(dead_code)]
 pub fn cleanup_test_records() {
-    // List and delete any existing test records
-    let output = run_cli(&["pds", "list-records", TEST_COLLECTION]);
-    if !output.status.success() {
-        return; // No session or collection doesn't exist
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    for line in stdout.lines() {
-        if let Ok(record) = serde_json::from_str::<serde_json::Value>(line)
-            && let Some(uri) = record["uri"].as_str()
-        {
-            let _ = run_cli(&["pds", "delete-record", uri]);
-        }
-    }
+    // Fails harmlessly when there is no session
+    let _ = run_cli(&["pds", "purge-collection", TEST_COLLECTION, "--yes"]);
 }
@@ -300,6 +300,69 @@ fn test_file_pds_whoami() {
     // Whoami
     let stdout = run_cli_with_env_success(&["pds", "whoami"], &home, &pds_url);
     assert!(stdout.contains("did:"), "Expected DID in whoami output");
+
+    // File sessions also describe the local PDS root.
+    let root = std::fs::canonicalize(&pds_path).unwrap();
+    let field = |label: &str| {
+        stdout
+            .lines()
+            .find(|line| line.contains(label))
+            .and_then(|line| line.split_once(": "))
+            .map(|(_, value)| value.to_string())
+    };
+    assert_eq!(field("Root"), Some(root.display().to_string()));
+    assert_eq!(field("Accounts"), Some("1".to_string()));
+    assert_eq!(field("Writable"), Some("yes".to_string()));
+}
+
+#[test]
+fn test_whoami_output_formats() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let password = "test-password";
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            password,
+            "frank.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "frank.local",
+            "--password",
+            password,
+        ],
+        &home,
+        &pds_url,
+    );
+
+    let stdout = run_cli_with_env_success(&["--output", "json", "pds", "whoami"], &home, &pds_url);
+    let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert!(json["did"].as_str().unwrap().starts_with("did:"));
+    assert_eq!(json["local"]["accounts"], 1);
+    assert_eq!(json["local"]["writable"], true);
+
+    let stdout = run_cli_with_env_success(&["pds", "whoami", "-o", "yaml"], &home, &pds_url);
+    assert!(stdout.contains(&format!("did: {}", json["did"].as_str().unwrap())));
+
+    let stdout = run_cli_with_env_success(&["pds", "whoami", "-o", "table"], &home, &pds_url);
+    assert!(stdout.lines().next().unwrap().contains("FIELD"));
 }
 
 #[test]
@@ -349,6 +412,259 @@ fn test_list_records_positional() {
     );
 }
 
+#[test]
+fn test_list_records_head_tail_sample() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let password = "test-password";
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            password,
+            "grace.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "grace.local",
+            "--password",
+            password,
+        ],
+        &home,
+        &pds_url,
+    );
+    for _ in 0..5 {
+        run_cli_with_env_success(
+            &[
+                "pds",
+                "create-record",
+                TEST_COLLECTION,
+                "--type",
+                TEST_COLLECTION,
+            ],
+            &home,
+            &pds_url,
+        );
+    }
+
+    // Small pages make every option follow the cursor.
+    let uris = |args: &[&str]| -> Vec<String> {
+        let mut full = vec![
+            "-o",
+            "json",
+            "pds",
+            "list-records",
+            TEST_COLLECTION,
+            "--limit",
+            "2",
+        ];
+        full.extend_from_slice(args);
+        let stdout = run_cli_with_env_success(&full, &home, &pds_url);
+        let json: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        json["records"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|record| record["uri"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let all = uris(&["--head", "10"]);
+    assert_eq!(all.len(), 5);
+    assert_eq!(uris(&["--head", "3"]), all[..3]);
+    assert_eq!(uris(&["--tail", "3"]), all[2..]);
+    assert_eq!(
+        uris(&["--tail", "2", "--reverse"]),
+        vec![all[1].clone(), all[0].clone()]
+    );
+
+    let sample = uris(&["--sample", "3"]);
+    assert_eq!(sample.len(), 3);
+    let positions: Vec<usize> = sample
+        .iter()
+        .map(|uri| all.iter().position(|u| u == uri).unwrap())
+        .collect();
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_read_local_pds_without_session() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    let reader_home = temp_dir.path().join("reader");
+    std::fs::create_dir_all(&home).unwrap();
+    std::fs::create_dir_all(&reader_home).unwrap();
+    let password = "test-password";
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            password,
+            "heidi.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "heidi.local",
+            "--password",
+            password,
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-record",
+            TEST_COLLECTION,
+            "--type",
+            TEST_COLLECTION,
+        ],
+        &home,
+        &pds_url,
+    );
+
+    // A home with no stored session reads through --pds alone.
+    let output = run_cli_with_env_success(
+        &[
+            "-o",
+            "json",
+            "pds",
+            "list-records",
+            TEST_COLLECTION,
+            "--repo",
+            "heidi.local",
+            "--pds",
+            &pds_url,
+        ],
+        &reader_home,
+        &pds_url,
+    );
+    let page: serde_json::Value = serde_json::from_str(&output).unwrap();
+    let uri = page["records"][0]["uri"].as_str().unwrap().to_string();
+
+    let output = run_cli_with_env_success(
+        &["pds", "get-record", &uri, "--pds", &pds_url],
+        &reader_home,
+        &pds_url,
+    );
+    assert!(output.contains(TEST_COLLECTION));
+}
+
+#[test]
+fn test_get_record_watch_exits_when_record_is_deleted() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::time::{Duration, Instant};
+
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let password = "test-password";
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            password,
+            "judy.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "judy.local",
+            "--password",
+            password,
+        ],
+        &home,
+        &pds_url,
+    );
+    let stdout = run_cli_with_env_success(
+        &[
+            "pds",
+            "create-record",
+            TEST_COLLECTION,
+            "--type",
+            TEST_COLLECTION,
+        ],
+        &home,
+        &pds_url,
+    );
+    let uri = stdout
+        .lines()
+        .find(|line| line.starts_with("at://"))
+        .unwrap()
+        .trim()
+        .to_string();
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_atproto"));
+    cmd.args(["-o", "json", "pds", "get-record", &uri, "--watch"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    apply_home_env(&mut cmd, &home);
+    cmd.env("ATPROTO_PDS", &pds_url);
+    let mut watcher = ServerGuard(cmd.spawn().unwrap());
+
+    // The first version is printed once the watch has started.
+    let stdout = watcher.0.stdout.take().unwrap();
+    let mut lines = BufReader::new(stdout).lines();
+    let first: serde_json::Value =
+        serde_json::from_str(&lines.next().expect("watch exited early").unwrap()).unwrap();
+    assert_eq!(first["uri"], uri.as_str());
+
+    run_cli_with_env_success(&["pds", "delete-record", &uri], &home, &pds_url);
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+    let status = loop {
+        if let Some(status) = watcher.0.try_wait().unwrap() {
+            break status;
+        }
+        assert!(Instant::now() < deadline, "watch did not notice the delete");
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    assert_eq!(status.code(), Some(7));
+}
+
 #[test]
 fn test_no_session_error() {
     // Clear any existing session by using a temp home
@@ -358,7 +674,7 @@ fn test_no_session_error() {
     apply_home_env(&mut cmd, temp_dir.path());
 
     let output = cmd.output().expect("Failed to execute CLI");
-    assert!(!output.status.success());
+    assert_eq!(output.status.code(), Some(3), "Expected the auth exit code");
 
     let stderr = String::from_utf8_lossy(&output.stderr);
     assert!(
@@ -367,3 +683,1573 @@ fn test_no_session_error() {
         stderr
     );
 }
+
+#[test]
+fn test_create_record_lexicon_validation() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let password = "test-password";
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            password,
+            "frank.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "frank.local",
+            "--password",
+            password,
+        ],
+        &home,
+        &pds_url,
+    );
+
+    let lexicon_path = temp_dir.path().join("lexicon.json");
+    let lexicon = serde_json::json!({
+        "lexicon": 1,
+        "id": TEST_COLLECTION,
+        "defs": {
+            "main": {
+                "type": "record",
+                "record": {
+                    "type": "object",
+                    "required": ["text"],
+                    "properties": {"text": {"type": "string", "maxLength": 5}}
+                }
+            }
+        }
+    });
+    std::fs::write(&lexicon_path, lexicon.to_string()).unwrap();
+
+    let record_path = temp_dir.path().join("record.json");
+    std::fs::write(&record_path, r#"{"text": "far too long"}"#).unwrap();
+
+    let output = run_cli_with_env(
+        &[
+            "pds",
+            "create-record",
+            TEST_COLLECTION,
+            "--type",
+            TEST_COLLECTION,
+            "--json",
+            record_path.to_str().unwrap(),
+            "--lexicon",
+            lexicon_path.to_str().unwrap(),
+        ],
+        &home,
+        &pds_url,
+    );
+    assert!(!output.status.success(), "Invalid record was accepted");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("/text"),
+        "Expected pointer, got: {}",
+        stderr
+    );
+
+    // Nothing should have been written
+    let stdout =
+        run_cli_with_env_success(&["pds", "list-records", TEST_COLLECTION], &home, &pds_url);
+    assert_eq!(stdout.lines().filter(|l| l.starts_with('{')).count(), 0);
+}
+
+/// Run `edit-record` with `editor` as `$EDITOR` and scratch files in `tmp`.
+#[cfg(unix)]
+fn edit_record(uri: &str, editor: &str, home: &Path, tmp: &Path) -> std::process::Output {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_atproto"));
+    cmd.args(["pds", "edit-record", uri]);
+    apply_home_env(&mut cmd, home);
+    cmd.env_remove("VISUAL");
+    cmd.env("EDITOR", editor);
+    cmd.env("TMPDIR", tmp);
+    cmd.output().expect("Failed to execute CLI")
+}
+
+#[cfg(unix)]
+#[test]
+fn test_edit_record_round_trips_through_editor() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    let tmp = temp_dir.path().join("tmp");
+    std::fs::create_dir_all(&home).unwrap();
+    std::fs::create_dir_all(&tmp).unwrap();
+    let password = "test-password";
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            password,
+            "ivy.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "ivy.local",
+            "--password",
+            password,
+        ],
+        &home,
+        &pds_url,
+    );
+
+    let record_path = temp_dir.path().join("record.json");
+    std::fs::write(&record_path, r#"{"text": "hello"}"#).unwrap();
+    let stdout = run_cli_with_env_success(
+        &[
+            "pds",
+            "create-record",
+            TEST_COLLECTION,
+            "--type",
+            TEST_COLLECTION,
+            "--json",
+            record_path.to_str().unwrap(),
+        ],
+        &home,
+        &pds_url,
+    );
+    let uri = stdout.lines().next().unwrap().trim().to_string();
+    let stdout =
+        run_cli_with_env_success(&["-o", "json", "pds", "get-record", &uri], &home, &pds_url);
+    let original: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    let original_cid = original["cid"].as_str().unwrap().to_string();
+
+    // An editor that changes nothing writes nothing.
+    let output = edit_record(&uri, "true", &home, &tmp);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("No changes"));
+
+    let output = edit_record(&uri, "sed -i s/hello/world/", &home, &tmp);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = run_cli_with_env_success(&["pds", "get-record", &uri], &home, &pds_url);
+    assert!(stdout.contains("world"), "{}", stdout);
+    assert_eq!(std::fs::read_dir(&tmp).unwrap().count(), 0);
+
+    // The version before the edit can still be read by its CID.
+    let stdout = run_cli_with_env_success(
+        &["pds", "get-record", &uri, "--cid", &original_cid],
+        &home,
+        &pds_url,
+    );
+    assert!(stdout.contains("hello"), "{}", stdout);
+
+    // Invalid JSON is not written, and the edit is kept for recovery.
+    let output = edit_record(&uri, "sed -i s/}/,/", &home, &tmp);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not valid JSON"));
+    let kept: Vec<_> = std::fs::read_dir(&tmp).unwrap().collect();
+    assert_eq!(kept.len(), 1);
+    let stdout = run_cli_with_env_success(&["pds", "get-record", &uri], &home, &pds_url);
+    assert!(stdout.contains("world"), "{}", stdout);
+    for entry in kept {
+        std::fs::remove_file(entry.unwrap().path()).unwrap();
+    }
+
+    // A concurrent edit while the editor is open makes the swap fail.
+    let script = temp_dir.path().join("racing-editor.sh");
+    std::fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\nEDITOR='sed -i s/world/theirs/' '{}' pds edit-record '{}' >/dev/null\nsed -i s/world/mine/ \"$1\"\n",
+            env!("CARGO_BIN_EXE_atproto"),
+            uri
+        ),
+    )
+    .unwrap();
+    let output = edit_record(&uri, &format!("sh {}", script.display()), &home, &tmp);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("changed while you were editing"),
+        "{:?}",
+        output
+    );
+    let stdout = run_cli_with_env_success(&["pds", "get-record", &uri], &home, &pds_url);
+    assert!(stdout.contains("theirs"), "{}", stdout);
+}
+
+#[test]
+fn test_bulk_create_and_delete_records() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let password = "test-password";
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            password,
+            "grace.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "grace.local",
+            "--password",
+            password,
+        ],
+        &home,
+        &pds_url,
+    );
+
+    let jsonl_path = temp_dir.path().join("records.jsonl");
+    std::fs::write(&jsonl_path, "{\"n\": 1}\n\n{\"n\": 2}\n{\"n\": 3}\n").unwrap();
+    let stdout = run_cli_with_env_success(
+        &[
+            "pds",
+            "create-records",
+            TEST_COLLECTION,
+            "--type",
+            TEST_COLLECTION,
+            "--jsonl",
+            jsonl_path.to_str().unwrap(),
+        ],
+        &home,
+        &pds_url,
+    );
+    let uris: Vec<&str> = stdout.lines().filter(|l| l.starts_with("at://")).collect();
+    assert_eq!(uris.len(), 3, "Expected one URI per record: {}", stdout);
+    assert!(stdout.contains("Created 3 of 3 records"));
+
+    // A record in another repo cannot be deleted, so one item fails.
+    let foreign = format!(
+        "at://did:plc:bbbbbbbbbbbbbbbbbbbbbbbb/{}/x",
+        TEST_COLLECTION
+    );
+    let output = run_cli_with_env(
+        &["pds", "delete-records", uris[0], &foreign],
+        &home,
+        &pds_url,
+    );
+    assert!(
+        !output.status.success(),
+        "A failed item must fail by default"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&foreign),
+        "Expected the failed URI: {}",
+        stderr
+    );
+    assert!(stderr.contains("1 of 2 records failed"), "{}", stderr);
+
+    let uri_path = temp_dir.path().join("uris.txt");
+    std::fs::write(&uri_path, format!("{}\n{}\n", uris[1], foreign)).unwrap();
+    let output = run_cli_with_env(
+        &[
+            "--output",
+            "json",
+            "pds",
+            "delete-records",
+            uris[2],
+            "--from",
+            uri_path.to_str().unwrap(),
+            "--max-failures",
+            "1",
+        ],
+        &home,
+        &pds_url,
+    );
+    assert!(
+        output.status.success(),
+        "Failures within --max-failures must succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["succeeded"], serde_json::json!([uris[2], uris[1]]));
+    assert_eq!(report["failed"][0]["item"], foreign.as_str());
+    assert_eq!(report["skipped"], 0);
+
+    let stdout =
+        run_cli_with_env_success(&["pds", "list-records", TEST_COLLECTION], &home, &pds_url);
+    assert_eq!(stdout.lines().filter(|l| l.starts_with('{')).count(), 0);
+}
+
+#[test]
+fn test_purge_collection() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let password = "test-password";
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            password,
+            "grace.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "grace.local",
+            "--password",
+            password,
+        ],
+        &home,
+        &pds_url,
+    );
+
+    let jsonl_path = temp_dir.path().join("records.jsonl");
+    std::fs::write(&jsonl_path, "{\"n\": 1}\n{\"n\": 2}\n{\"n\": 3}\n").unwrap();
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-records",
+            TEST_COLLECTION,
+            "--type",
+            TEST_COLLECTION,
+            "--jsonl",
+            jsonl_path.to_str().unwrap(),
+        ],
+        &home,
+        &pds_url,
+    );
+
+    let stdout = run_cli_with_env_success(
+        &["pds", "purge-collection", TEST_COLLECTION, "--dry-run"],
+        &home,
+        &pds_url,
+    );
+    assert!(stdout.contains("Would delete 3 records"), "{}", stdout);
+
+    // Without --yes the prompt reads an empty stdin and aborts.
+    let output = run_cli_with_env(
+        &["pds", "purge-collection", TEST_COLLECTION],
+        &home,
+        &pds_url,
+    );
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("This will delete 3 records"), "{}", stderr);
+    assert!(stderr.contains("Aborted."), "{}", stderr);
+
+    let stdout =
+        run_cli_with_env_success(&["pds", "list-records", TEST_COLLECTION], &home, &pds_url);
+    assert_eq!(stdout.lines().filter(|l| l.starts_with('{')).count(), 3);
+
+    let stdout = run_cli_with_env_success(
+        &[
+            "--output",
+            "json",
+            "pds",
+            "purge-collection",
+            TEST_COLLECTION,
+            "--yes",
+            "--page-size",
+            "2",
+        ],
+        &home,
+        &pds_url,
+    );
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["scanned"], 3);
+    assert_eq!(report["deleted"], 3);
+    assert_eq!(report["failed"], serde_json::json!([]));
+
+    let stdout =
+        run_cli_with_env_success(&["pds", "list-records", TEST_COLLECTION], &home, &pds_url);
+    assert_eq!(stdout.lines().filter(|l| l.starts_with('{')).count(), 0);
+}
+
+#[test]
+fn test_export_and_import_between_accounts() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let password = "test-password";
+
+    for handle in ["grace.local", "heidi.local"] {
+        run_cli_with_env_success(
+            &[
+                "pds",
+                "create-account",
+                "--pds",
+                &pds_url,
+                "--password",
+                password,
+                handle,
+            ],
+            &home,
+            &pds_url,
+        );
+    }
+    let login = |handle: &str| {
+        run_cli_with_env_success(
+            &[
+                "pds",
+                "login",
+                "--pds",
+                &pds_url,
+                "--identifier",
+                handle,
+                "--password",
+                password,
+            ],
+            &home,
+            &pds_url,
+        );
+    };
+
+    login("grace.local");
+    let jsonl_path = temp_dir.path().join("records.jsonl");
+    std::fs::write(&jsonl_path, "{\"n\": 1}\n{\"n\": 2}\n{\"n\": 3}\n").unwrap();
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-records",
+            TEST_COLLECTION,
+            "--type",
+            TEST_COLLECTION,
+            "--jsonl",
+            jsonl_path.to_str().unwrap(),
+        ],
+        &home,
+        &pds_url,
+    );
+
+    let export_path = temp_dir.path().join("export.jsonl");
+    let stdout = run_cli_with_env_success(
+        &[
+            "pds",
+            "export",
+            "--collection",
+            TEST_COLLECTION,
+            "--out",
+            export_path.to_str().unwrap(),
+        ],
+        &home,
+        &pds_url,
+    );
+    assert!(stdout.contains("Exported 3 records"), "{}", stdout);
+    let exported = std::fs::read_to_string(&export_path).unwrap();
+    let values: Vec<serde_json::Value> = exported
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["value"]["n"].clone())
+        .collect();
+    assert_eq!(values, vec![1, 2, 3]);
+
+    // Exporting to stdout writes only the records.
+    let stdout = run_cli_with_env_success(
+        &[
+            "pds",
+            "export",
+            "--collection",
+            TEST_COLLECTION,
+            "--out",
+            "-",
+        ],
+        &home,
+        &pds_url,
+    );
+    assert_eq!(stdout, exported);
+
+    login("heidi.local");
+    let stdout = run_cli_with_env_success(
+        &["pds", "import", export_path.to_str().unwrap()],
+        &home,
+        &pds_url,
+    );
+    assert!(stdout.contains("Imported 3 of 3 records"), "{}", stdout);
+
+    let other = "org.example.copy";
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "import",
+            export_path.to_str().unwrap(),
+            "--collection",
+            other,
+        ],
+        &home,
+        &pds_url,
+    );
+
+    for collection in [TEST_COLLECTION, other] {
+        let stdout = run_cli_with_env_success(
+            &["-o", "json", "pds", "list-records", collection],
+            &home,
+            &pds_url,
+        );
+        let page: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        let records = page["records"].as_array().unwrap();
+        assert_eq!(records.len(), 3, "{}", stdout);
+        for record in records {
+            assert_eq!(record["value"]["$type"], TEST_COLLECTION);
+        }
+    }
+
+    // --all exports every collection in the repo.
+    let all_path = temp_dir.path().join("all.jsonl");
+    let stdout = run_cli_with_env_success(
+        &[
+            "-o",
+            "json",
+            "pds",
+            "export",
+            "--all",
+            "--out",
+            all_path.to_str().unwrap(),
+        ],
+        &home,
+        &pds_url,
+    );
+    let summary: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(summary["exported"], 6, "{}", stdout);
+    assert_eq!(
+        summary["collections"],
+        serde_json::json!([other, TEST_COLLECTION])
+    );
+    assert_eq!(
+        std::fs::read_to_string(&all_path).unwrap().lines().count(),
+        6
+    );
+
+    // Nothing is written if any line is invalid.
+    let bad_path = temp_dir.path().join("bad.jsonl");
+    std::fs::write(&bad_path, format!("{}{{\"n\": 4}}\n", exported)).unwrap();
+    let output = run_cli_with_env(
+        &["pds", "import", bad_path.to_str().unwrap()],
+        &home,
+        &pds_url,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("line 4"), "{}", stderr);
+}
+
+#[test]
+fn test_batch_runs_script_and_reports_each_operation() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let password = "test-password";
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            password,
+            "ivan.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "ivan.local",
+            "--password",
+            password,
+        ],
+        &home,
+        &pds_url,
+    );
+
+    let create = |id: &str, n: u32| {
+        serde_json::json!({
+            "op": "createRecord",
+            "id": id,
+            "params": {
+                "collection": TEST_COLLECTION,
+                "record": { "$type": TEST_COLLECTION, "n": n }
+            }
+        })
+    };
+    let script_path = temp_dir.path().join("create.ndjson");
+    std::fs::write(
+        &script_path,
+        format!(
+            "{}\n\n{}\n{{\"op\": \"putRecord\"}}\n",
+            create("first", 1),
+            create("second", 2)
+        ),
+    )
+    .unwrap();
+
+    // One operation fails, so the batch fails, but every result is written.
+    let output = run_cli_with_env(
+        &["batch", "--file", script_path.to_str().unwrap()],
+        &home,
+        &pds_url,
+    );
+    assert!(!output.status.success());
+    let results: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["id"], "first");
+    assert_eq!(results[0]["ok"], true);
+    assert_eq!(results[1]["line"], 3);
+    assert_eq!(results[2]["ok"], false);
+    assert!(
+        results[2]["error"]
+            .as_str()
+            .unwrap()
+            .contains("Unknown op 'putRecord'")
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 of 3 operations failed"), "{}", stderr);
+
+    let uris: Vec<&str> = results[..2]
+        .iter()
+        .map(|result| result["result"]["uri"].as_str().unwrap())
+        .collect();
+    let script = uris
+        .iter()
+        .map(|uri| {
+            format!(
+                "{}\n{}\n",
+                serde_json::json!({ "op": "getRecord", "params": { "uri": uri } }),
+                serde_json::json!({ "op": "deleteRecord", "params": { "uri": uri } })
+            )
+        })
+        .collect::<String>();
+    let script_path = temp_dir.path().join("read.ndjson");
+    std::fs::write(&script_path, script).unwrap();
+
+    let stdout = run_cli_with_env_success(
+        &[
+            "batch",
+            "-f",
+            script_path.to_str().unwrap(),
+            "--concurrency",
+            "2",
+        ],
+        &home,
+        &pds_url,
+    );
+    let results: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(results.len(), 4);
+    assert!(results.iter().all(|result| result["ok"] == true));
+    assert_eq!(results[0]["result"]["value"]["n"], 1);
+    assert_eq!(results[2]["result"]["value"]["n"], 2);
+    assert_eq!(results[3]["result"]["uri"], uris[1]);
+
+    let stdout = run_cli_with_env_success(
+        &["-o", "json", "pds", "list-records", TEST_COLLECTION],
+        &home,
+        &pds_url,
+    );
+    let page: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert!(page["records"].as_array().unwrap().is_empty(), "{}", stdout);
+}
+
+/// Run the CLI with `bin` at the front of `PATH`, so its plugins are found.
+#[cfg(unix)]
+fn run_with_plugins(args: &[&str], home: &Path, bin: &Path) -> std::process::Output {
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let mut paths = vec![bin.to_path_buf()];
+    paths.extend(std::env::split_paths(&path));
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_atproto"));
+    cmd.args(args);
+    apply_home_env(&mut cmd, home);
+    cmd.env("PATH", std::env::join_paths(paths).unwrap());
+    cmd.output().expect("Failed to execute CLI")
+}
+
+#[cfg(unix)]
+#[test]
+fn test_external_subcommands_run_plugins_with_context() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let bin = temp_dir.path().join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+
+    let plugin = bin.join("atproto-hello");
+    std::fs::write(
+        &plugin,
+        "#!/bin/sh\n\
+         echo \"args: $*\"\n\
+         echo \"did: $ATPROTO_DID\"\n\
+         echo \"output: $ATPROTO_OUTPUT\"\n\
+         echo \"$ATPROTO_PLUGIN_HANDSHAKE\"\n\
+         [ \"$1\" = fail ] && exit 7\n\
+         exit 0\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&plugin, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    // Without a session the plugin still runs, with no session context.
+    let output = run_with_plugins(&["hello", "a", "--flag"], &home, &bin);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("args: a --flag"), "{}", stdout);
+    assert!(stdout.contains("\"session\":null"), "{}", stdout);
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            "test-password",
+            "plugin.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    let login = run_cli_with_env_success(
+        &[
+            "-o",
+            "json",
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "plugin.local",
+            "--password",
+            "test-password",
+        ],
+        &home,
+        &pds_url,
+    );
+    let login: serde_json::Value = serde_json::from_str(&login).unwrap();
+    let did = login["did"].as_str().unwrap();
+
+    let output = run_with_plugins(&["-o", "json", "hello"], &home, &bin);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("did: {}", did)), "{}", stdout);
+    assert!(stdout.contains("output: json"), "{}", stdout);
+    let handshake: serde_json::Value = stdout
+        .lines()
+        .find_map(|line| serde_json::from_str(line).ok())
+        .unwrap();
+    assert_eq!(handshake["protocol"], 1);
+    assert_eq!(handshake["session"]["did"], did);
+    assert!(
+        !stdout.contains("access"),
+        "Tokens must not be passed in the environment: {}",
+        stdout
+    );
+    let session_file = handshake["session"]["sessionFile"].as_str().unwrap();
+    assert!(Path::new(session_file).exists());
+
+    // The plugin's exit code is the CLI's, with nothing added.
+    let output = run_with_plugins(&["hello", "fail"], &home, &bin);
+    assert_eq!(output.status.code(), Some(7));
+    assert!(
+        output.stderr.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = run_with_plugins(&["nonexistent"], &home, &bin);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("atproto-nonexistent"));
+}
+
+#[test]
+fn test_compact_round_trip() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let password = "test-password";
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            password,
+            "grace.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "grace.local",
+            "--password",
+            password,
+        ],
+        &home,
+        &pds_url,
+    );
+
+    let record_path = temp_dir.path().join("record.json");
+    std::fs::write(&record_path, r#"{"text": "compress me"}"#).unwrap();
+    let stdout = run_cli_with_env_success(
+        &[
+            "pds",
+            "create-record",
+            TEST_COLLECTION,
+            "--type",
+            TEST_COLLECTION,
+            "--json",
+            record_path.to_str().unwrap(),
+        ],
+        &home,
+        &pds_url,
+    );
+    let uri = stdout
+        .lines()
+        .find(|line| line.starts_with("at://"))
+        .expect("Could not find AT URI in output")
+        .trim()
+        .to_string();
+
+    let stdout = run_cli_with_env_success(&["pds", "compact", "--pds", &pds_url], &home, &pds_url);
+    assert!(
+        stdout.contains("Rewritten"),
+        "Unexpected output: {}",
+        stdout
+    );
+
+    let stdout = run_cli_with_env_success(&["pds", "get-record", &uri], &home, &pds_url);
+    assert!(
+        stdout.contains("compress me"),
+        "Compressed record unreadable"
+    );
+
+    // Compacting again is a no-op
+    let stdout = run_cli_with_env_success(&["pds", "compact", "--pds", &pds_url], &home, &pds_url);
+    assert!(
+        stdout.contains("Rewritten: 0"),
+        "Unexpected output: {}",
+        stdout
+    );
+
+    run_cli_with_env_success(
+        &["pds", "compact", "--decompress", "--pds", &pds_url],
+        &home,
+        &pds_url,
+    );
+    let stdout =
+        run_cli_with_env_success(&["pds", "list-records", TEST_COLLECTION], &home, &pds_url);
+    assert_eq!(stdout.lines().filter(|l| l.starts_with('{')).count(), 1);
+}
+
+#[test]
+fn test_bsky_commands_require_network_session() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let password = "test-password";
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            password,
+            "heidi.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "heidi.local",
+            "--password",
+            password,
+        ],
+        &home,
+        &pds_url,
+    );
+
+    let output = run_cli_with_env(&["bsky", "post", "hello"], &home, &pds_url);
+    assert!(!output.status.success(), "Post on a file PDS should fail");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("network PDS"),
+        "Unexpected error: {}",
+        stderr
+    );
+}
+
+#[test]
+fn test_doctor_reports_local_setup() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let password = "test-password";
+
+    // Without a session, doctor warns but does not fail.
+    let stdout = run_cli_with_env_success(&["doctor"], &home, &pds_url);
+    assert!(stdout.contains("Session file: not found"), "{}", stdout);
+    assert!(stdout.contains("atproto pds login"), "{}", stdout);
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            password,
+            "erin.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "erin.local",
+            "--password",
+            password,
+        ],
+        &home,
+        &pds_url,
+    );
+
+    let stdout = run_cli_with_env_success(&["doctor"], &home, &pds_url);
+    assert!(stdout.contains("with 1 account(s)"), "{}", stdout);
+    assert!(stdout.contains("Session: valid for did:"), "{}", stdout);
+    assert!(stdout.contains("Firehose: connected"), "{}", stdout);
+
+    // A corrupt session file is reported with a fix.
+    let session_file = home.join("data").join("atproto").join("session.json");
+    std::fs::write(&session_file, "not json").unwrap();
+    let output = run_cli_with_env(&["doctor"], &home, &pds_url);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Invalid session file"), "{}", stdout);
+    assert!(stdout.contains("Delete"), "{}", stdout);
+}
+
+#[test]
+fn test_login_max_idle_is_stored_and_kept_across_commands() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let password = "test-password";
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            password,
+            "fay.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    let login = |max_idle: &str| {
+        run_cli_with_env(
+            &[
+                "pds",
+                "login",
+                "--pds",
+                &pds_url,
+                "--identifier",
+                "fay.local",
+                "--password",
+                password,
+                "--max-idle",
+                max_idle,
+            ],
+            &home,
+            &pds_url,
+        )
+    };
+
+    assert_eq!(login("30x").status.code(), Some(2));
+    assert_eq!(login("0d").status.code(), Some(2));
+    assert!(login("30d").status.success());
+
+    let session_file = home.join("data").join("atproto").join("session.json");
+    let read = || -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(&session_file).unwrap()).unwrap()
+    };
+    let stored = read();
+    assert_eq!(stored["max_idle_secs"], 30 * 24 * 60 * 60);
+    let first_use = stored["last_used"].as_str().unwrap().to_string();
+
+    run_cli_with_env_success(&["pds", "whoami"], &home, &pds_url);
+    let stored = read();
+    assert_eq!(stored["max_idle_secs"], 30 * 24 * 60 * 60);
+    assert_ne!(stored["last_used"].as_str().unwrap(), first_use);
+}
+
+/// Kills a spawned server when the test ends, even on failure.
+struct ServerGuard(std::process::Child);
+
+impl Drop for ServerGuard {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+#[test]
+fn test_serve_local_pds_over_xrpc() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let password = "test-password";
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            password,
+            "frank.local",
+        ],
+        &home,
+        &pds_url,
+    );
+
+    let lexicon_file = temp_dir.path().join("lexicon.json");
+    let lexicon = serde_json::json!({
+        "lexicon": 1,
+        "id": TEST_COLLECTION,
+        "defs": {
+            "main": {
+                "type": "record",
+                "record": {
+                    "type": "object",
+                    "required": ["text"],
+                    "properties": { "text": { "type": "string" } }
+                }
+            }
+        }
+    });
+    std::fs::write(&lexicon_file, lexicon.to_string()).unwrap();
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_atproto"));
+    cmd.args([
+        "serve",
+        "--root",
+        pds_path.to_str().unwrap(),
+        "--port",
+        "0",
+        "--lexicon",
+        lexicon_file.to_str().unwrap(),
+    ])
+    .stdout(Stdio::piped())
+    .stderr(Stdio::null());
+    apply_home_env(&mut cmd, &home);
+    let mut server = ServerGuard(cmd.spawn().unwrap());
+
+    let stdout = server.0.stdout.take().unwrap();
+    let mut lines = BufReader::new(stdout).lines();
+    let mut accounts_line = None;
+    let url = loop {
+        let line = lines.next().expect("server exited early").unwrap();
+        if line.contains("Accounts") {
+            accounts_line = Some(line.clone());
+        }
+        if let Some(start) = line.find("http://") {
+            break line[start..].trim().to_string();
+        }
+    };
+    assert!(accounts_line.unwrap().ends_with('1'));
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &url,
+            "--identifier",
+            "frank.local",
+            "--password",
+            password,
+        ],
+        &home,
+        &url,
+    );
+    let stdout = run_cli_with_env_success(&["pds", "whoami"], &home, &url);
+    assert!(stdout.contains("did:plc:"), "{}", stdout);
+    assert!(stdout.contains(&url), "{}", stdout);
+
+    let record_file = temp_dir.path().join("record.json");
+    std::fs::write(&record_file, r#"{"text":"served"}"#).unwrap();
+    let stdout = run_cli_with_env_success(
+        &[
+            "pds",
+            "create-record",
+            TEST_COLLECTION,
+            "--type",
+            TEST_COLLECTION,
+            "--json",
+            record_file.to_str().unwrap(),
+        ],
+        &home,
+        &url,
+    );
+    assert!(stdout.contains("at://"), "{}", stdout);
+
+    let stdout = run_cli_with_env_success(&["pds", "list-records", TEST_COLLECTION], &home, &url);
+    assert!(stdout.contains("served"), "{}", stdout);
+
+    // The server checks records against the lexicon it was given.
+    std::fs::write(&record_file, r#"{"text":3}"#).unwrap();
+    let output = run_cli_with_env(
+        &[
+            "pds",
+            "create-record",
+            TEST_COLLECTION,
+            "--type",
+            TEST_COLLECTION,
+            "--json",
+            record_file.to_str().unwrap(),
+        ],
+        &home,
+        &url,
+    );
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("InvalidRecord"), "{}", stderr);
+    assert!(stderr.contains("/text"), "{}", stderr);
+}
+
+#[test]
+fn test_file_pds_email_confirmation_and_password_reset() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let outbox = pds_path
+        .join("pds")
+        .join("outbox")
+        .join("alice@example.com.json");
+    let sent_token = || {
+        let mail: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&outbox).unwrap()).unwrap();
+        mail["token"].as_str().unwrap().to_string()
+    };
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            "old-password",
+            "--email",
+            "alice@example.com",
+            "alice.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "alice.local",
+            "--password",
+            "old-password",
+        ],
+        &home,
+        &pds_url,
+    );
+
+    run_cli_with_env_success(&["pds", "request-email-confirmation"], &home, &pds_url);
+    let output = run_cli_with_env(
+        &[
+            "pds",
+            "confirm-email",
+            "--email",
+            "alice@example.com",
+            "--token",
+            "AAAAA-AAAAA",
+        ],
+        &home,
+        &pds_url,
+    );
+    assert_eq!(output.status.code(), Some(6));
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "confirm-email",
+            "--email",
+            "alice@example.com",
+            "--token",
+            &sent_token(),
+        ],
+        &home,
+        &pds_url,
+    );
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "request-password-reset",
+            "--pds",
+            &pds_url,
+            "--email",
+            "alice@example.com",
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "reset-password",
+            "--pds",
+            &pds_url,
+            "--token",
+            &sent_token(),
+            "--password",
+            "new-password",
+        ],
+        &home,
+        &pds_url,
+    );
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "alice.local",
+            "--password",
+            "new-password",
+        ],
+        &home,
+        &pds_url,
+    );
+}
+
+#[test]
+fn test_file_pds_deactivate_and_activate_account() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            "password",
+            "ivy.local",
+        ],
+        &home,
+        &pds_url,
+    );
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "login",
+            "--pds",
+            &pds_url,
+            "--identifier",
+            "ivy.local",
+            "--password",
+            "password",
+        ],
+        &home,
+        &pds_url,
+    );
+    let create = [
+        "pds",
+        "create-record",
+        TEST_COLLECTION,
+        "--type",
+        TEST_COLLECTION,
+    ];
+    run_cli_with_env_success(&create, &home, &pds_url);
+
+    let status = || -> serde_json::Value {
+        let stdout =
+            run_cli_with_env_success(&["-o", "json", "pds", "account-status"], &home, &pds_url);
+        serde_json::from_str(&stdout).unwrap()
+    };
+    let active = status();
+    assert_eq!(active["activated"], true);
+    assert_eq!(active["indexedRecords"], 1);
+
+    run_cli_with_env_success(&["pds", "deactivate-account"], &home, &pds_url);
+    assert_eq!(status()["activated"], false);
+    let output = run_cli_with_env(&create, &home, &pds_url);
+    assert_eq!(output.status.code(), Some(3));
+
+    run_cli_with_env_success(&["pds", "activate-account"], &home, &pds_url);
+    assert_eq!(status()["activated"], true);
+    run_cli_with_env_success(&create, &home, &pds_url);
+}
+
+#[test]
+fn test_history_records_and_reruns_commands() {
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+    let run = |args: &[&str]| {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_atproto"));
+        cmd.args(args);
+        apply_home_env(&mut cmd, &home);
+        cmd.env("ATPROTO_PDS", &pds_url).env("ATPROTO_HISTORY", "1");
+        cmd.output().unwrap()
+    };
+    let history = || -> Vec<serde_json::Value> {
+        let output = run(&["-o", "json", "history", "list"]);
+        assert!(output.status.success());
+        let list: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(list["recording"], true);
+        list["entries"].as_array().unwrap().clone()
+    };
+
+    let args = [
+        "pds",
+        "create-account",
+        "--pds",
+        &pds_url,
+        "--password",
+        "hunter2",
+        "judy.local",
+    ];
+    assert!(run(&args).status.success());
+    let args = [
+        "pds",
+        "login",
+        "--pds",
+        &pds_url,
+        "--identifier",
+        "judy.local",
+        "--password=hunter2",
+    ];
+    assert!(run(&args).status.success());
+    let created = run(&[
+        "-o",
+        "json",
+        "pds",
+        "create-record",
+        TEST_COLLECTION,
+        "--type",
+        TEST_COLLECTION,
+    ]);
+    assert!(created.status.success());
+    let created: serde_json::Value = serde_json::from_slice(&created.stdout).unwrap();
+    assert_eq!(
+        run(&["pds", "get-record", "at://nope"]).status.code(),
+        Some(6)
+    );
+
+    let entries = history();
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[0]["n"], 1);
+    assert_eq!(entries[0]["args"][5], "<redacted>");
+    assert_eq!(entries[1]["args"][6], "--password=<redacted>");
+    let log = std::fs::read_to_string(home.join("data/atproto/history.jsonl")).unwrap();
+    assert!(!log.contains("hunter2"));
+    assert_eq!(entries[2]["exitCode"], 0);
+    assert_eq!(entries[2]["uris"][0], created["uri"]);
+    assert_eq!(entries[3]["exitCode"], 6);
+    assert!(entries[3]["error"].is_string());
+
+    // A redacted entry cannot be rerun; the others run again and are
+    // recorded as new entries.
+    assert!(!run(&["history", "rerun", "2"]).status.success());
+    assert!(run(&["history", "rerun", "3"]).status.success());
+    assert_eq!(run(&["history", "rerun", "4"]).status.code(), Some(6));
+    let entries = history();
+    assert_eq!(entries.len(), 6);
+    assert_eq!(entries[4]["args"], entries[2]["args"]);
+    assert_ne!(entries[4]["uris"][0], created["uri"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_subscribe_prints_summary_when_stopped() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let temp_dir = TempDir::new().unwrap();
+    let pds_path = temp_dir.path().join("pds");
+    let pds_url = file_pds_url(&pds_path);
+    let home = temp_dir.path().join("home");
+    std::fs::create_dir_all(&home).unwrap();
+
+    run_cli_with_env_success(
+        &[
+            "pds",
+            "create-account",
+            "--pds",
+            &pds_url,
+            "--password",
+            "test-password",
+            "gus.local",
+        ],
+        &home,
+        &pds_url,
+    );
+
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_atproto"));
+    cmd.args([
+        "pds",
+        "subscribe",
+        "--pds",
+        &pds_url,
+        "--cursor",
+        "0",
+        "--json",
+        "--summary-json",
+    ])
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped());
+    apply_home_env(&mut cmd, &home);
+    let mut subscriber = ServerGuard(cmd.spawn().unwrap());
+    let stderr = subscriber.0.stderr.take().unwrap();
+    let (sender, lines) = mpsc::channel();
+    let stdout = BufReader::new(subscriber.0.stdout.take().unwrap());
+    std::thread::spawn(move || {
+        for line in stdout.lines() {
+            let _ = sender.send(line.unwrap());
+        }
+    });
+
+    let first: serde_json::Value =
+        serde_json::from_str(&lines.recv_timeout(Duration::from_secs(30)).unwrap()).unwrap();
+    let seq = first["seq"].as_i64().unwrap();
+
+    // A supervisor stopping the process still gets the summary.
+    let killed = Command::new("kill")
+        .args(["-TERM", &subscriber.0.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+
+    let last = lines.recv_timeout(Duration::from_secs(30)).unwrap();
+    let summary: serde_json::Value = serde_json::from_str(&last).unwrap();
+    let summary = &summary["summary"];
+    assert_eq!(summary["events"], 1, "{}", summary);
+    assert_eq!(summary["errors"], 0, "{}", summary);
+    assert_eq!(summary["lastCursor"], seq, "{}", summary);
+    assert_eq!(summary["reason"], "interrupted", "{}", summary);
+    assert!(summary["durationSecs"].as_f64().unwrap() > 0.0);
+
+    assert!(subscriber.0.wait().unwrap().success());
+    let mut report = String::new();
+    std::io::Read::read_to_string(&mut BufReader::new(stderr), &mut report).unwrap();
+    assert!(report.contains("Stopped:"), "{}", report);
+    assert!(
+        report.contains(&format!("Last cursor: {}", seq)),
+        "{}",
+        report
+    );
+}
@@ -0,0 +1,454 @@
+//! CLI tests against a mock network PDS.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde_json::{Value, json};
+use wiremock::matchers::{body_json, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use common::apply_home_env;
+
+fn jwt(exp: i64) -> String {
+    let claims = URL_SAFE_NO_PAD
+        .encode(json!({"sub": "did:plc:test234aaaaaaaaaaaaaaaaa", "exp": exp}).to_string());
+    format!("eyJhbGciOiJIUzI1NiJ9.{}.sig", claims)
+}
+
+fn session_file(home: &Path) -> PathBuf {
+    home.join("data").join("atproto").join("session.json")
+}
+
+fn write_session(home: &Path, server: &MockServer, refresh_token: &str) {
+    let file = session_file(home);
+    std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+    let session = json!({
+        "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+        "pds": format!("http://127.0.0.1:{}", server.address().port()),
+        "access_token": "access-old",
+        "refresh_token": refresh_token
+    });
+    std::fs::write(file, session.to_string()).unwrap();
+}
+
+fn read_session(home: &Path) -> Value {
+    serde_json::from_str(&std::fs::read_to_string(session_file(home)).unwrap()).unwrap()
+}
+
+async fn run_cli_in(home: &Path, args: &[&str]) -> Output {
+    let home = home.to_path_buf();
+    let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+    tokio::task::spawn_blocking(move || {
+        let mut cmd = std::process::Command::new(env!("CARGO_BIN_EXE_atproto"));
+        cmd.args(&args);
+        apply_home_env(&mut cmd, &home);
+        cmd.output().expect("Failed to execute CLI")
+    })
+    .await
+    .unwrap()
+}
+
+async fn mount_refresh(server: &MockServer, presented: &str, access: &str, refresh: &str) {
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.refreshSession"))
+        .and(header("authorization", format!("Bearer {}", presented)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": access,
+            "refreshJwt": refresh
+        })))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_refresh_token_persists_rotated_tokens() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+
+    // Each refresh rotates the refresh token; only the latest is accepted.
+    let (access1, refresh1) = (jwt(1_893_456_000), jwt(1_900_000_000));
+    let (access2, refresh2) = (jwt(1_893_456_100), jwt(1_900_000_100));
+    mount_refresh(&server, "refresh-old", &access1, &refresh1).await;
+    mount_refresh(&server, &refresh1, &access2, &refresh2).await;
+    write_session(home.path(), &server, "refresh-old");
+
+    let output = run_cli_in(home.path(), &["pds", "refresh-token", "--json"]).await;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let result: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["did"], "did:plc:test234aaaaaaaaaaaaaaaaa");
+    assert_eq!(result["accessExpiresAt"], "2030-01-01T00:01:40Z");
+    assert_eq!(result["persisted"], true);
+
+    let stored = read_session(home.path());
+    assert_eq!(stored["access_token"], access2.as_str());
+    assert_eq!(stored["refresh_token"], refresh2.as_str());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_failed_refresh_leaves_stored_session_unchanged() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.refreshSession"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "error": "ExpiredToken",
+            "message": "Token has expired"
+        })))
+        .mount(&server)
+        .await;
+    write_session(home.path(), &server, "refresh-old");
+
+    let output = run_cli_in(home.path(), &["pds", "refresh-token"]).await;
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("stored credentials were not changed")
+    );
+
+    let stored = read_session(home.path());
+    assert_eq!(stored["access_token"], "access-old");
+    assert_eq!(stored["refresh_token"], "refresh-old");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_malformed_stored_token_is_an_auth_error() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    write_session(home.path(), &server, "refresh\nold");
+
+    let output = run_cli_in(home.path(), &["pds", "refresh-token"]).await;
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("log in again"), "{}", stderr);
+    assert!(stderr.contains("malformed token"), "{}", stderr);
+    assert!(!stderr.contains("refresh\nold"), "{}", stderr);
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_expired_refresh_token_prunes_session() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    write_session(home.path(), &server, &jwt(1_000_000_000));
+
+    // Doctor reports the stale session but leaves it in place.
+    let output = run_cli_in(home.path(), &["doctor"]).await;
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("stale: refresh token expired 2001-09-09"),
+        "{}",
+        stdout
+    );
+    assert!(session_file(home.path()).exists());
+
+    let output = run_cli_in(home.path(), &["pds", "whoami"]).await;
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Removed the stored session"), "{}", stderr);
+    assert!(stderr.contains("No active session"), "{}", stderr);
+    assert!(!session_file(home.path()).exists());
+
+    let refreshes = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|request| request.url.path().ends_with("refreshSession"))
+        .count();
+    assert_eq!(refreshes, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_idle_session_limit() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    let (access, refresh) = (jwt(1_893_456_000), jwt(1_900_000_000));
+    mount_refresh(&server, "refresh-old", &access, &refresh).await;
+
+    let set_last_used = |last_used: &str| {
+        write_session(home.path(), &server, "refresh-old");
+        let mut stored = read_session(home.path());
+        stored["last_used"] = json!(last_used);
+        stored["max_idle_secs"] = json!(3600);
+        std::fs::write(session_file(home.path()), stored.to_string()).unwrap();
+    };
+
+    // Used within the limit: the session works, keeps its limit and is
+    // marked as used.
+    let recent = chrono::Utc::now() - chrono::Duration::minutes(30);
+    set_last_used(&recent.to_rfc3339());
+    let output = run_cli_in(home.path(), &["pds", "whoami"]).await;
+    assert!(output.status.success());
+    let stored = read_session(home.path());
+    assert_eq!(stored["refresh_token"], refresh.as_str());
+    assert_eq!(stored["max_idle_secs"], 3600);
+    let last_used: chrono::DateTime<chrono::Utc> =
+        serde_json::from_value(stored["last_used"].clone()).unwrap();
+    assert!(last_used > recent);
+
+    set_last_used("2020-01-01T00:00:00Z");
+    let output = run_cli_in(home.path(), &["pds", "whoami"]).await;
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("3600s idle limit"), "{}", stderr);
+    assert!(!session_file(home.path()).exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_login_with_email() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    let pds = format!("http://127.0.0.1:{}", server.address().port());
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .and(body_json(json!({
+            "identifier": "alice@example.com",
+            "password": "secret"
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access",
+            "refreshJwt": "refresh"
+        })))
+        .mount(&server)
+        .await;
+
+    let output = run_cli_in(
+        home.path(),
+        &[
+            "pds",
+            "login",
+            "--identifier",
+            "alice@example.com",
+            "--password",
+            "secret",
+            "--pds",
+            &pds,
+        ],
+    )
+    .await;
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        read_session(home.path())["did"],
+        "did:plc:test234aaaaaaaaaaaaaaaaa"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_login_explains_account_takedown() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    let pds = format!("http://127.0.0.1:{}", server.address().port());
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "error": "AccountTakedown",
+            "message": "Account has been taken down"
+        })))
+        .mount(&server)
+        .await;
+
+    let output = run_cli_in(
+        home.path(),
+        &[
+            "pds",
+            "login",
+            "--identifier",
+            "alice.test",
+            "--password",
+            "secret",
+            "--pds",
+            &pds,
+        ],
+    )
+    .await;
+
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("taken down by the PDS operator"),
+        "{}",
+        stderr
+    );
+    assert!(!session_file(home.path()).exists());
+}
+
+async fn mount_rate_limited_login(server: &MockServer, times: u64) {
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("retry-after", "0")
+                .set_body_json(json!({
+                    "error": "RateLimitExceeded",
+                    "message": "Rate Limit Exceeded"
+                })),
+        )
+        .up_to_n_times(times)
+        .with_priority(1)
+        .mount(server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access",
+            "refreshJwt": "refresh"
+        })))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_login_reports_rate_limit() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    let pds = format!("http://127.0.0.1:{}", server.address().port());
+    mount_rate_limited_login(&server, 1).await;
+
+    let output = run_cli_in(
+        home.path(),
+        &[
+            "pds",
+            "login",
+            "--identifier",
+            "alice.test",
+            "--password",
+            "secret",
+            "--pds",
+            &pds,
+        ],
+    )
+    .await;
+
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Too many login attempts"), "{}", stderr);
+    assert!(!session_file(home.path()).exists());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_login_retry_waits_out_rate_limit() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    let pds = format!("http://127.0.0.1:{}", server.address().port());
+    mount_rate_limited_login(&server, 2).await;
+
+    let output = run_cli_in(
+        home.path(),
+        &[
+            "pds",
+            "login",
+            "--identifier",
+            "alice.test",
+            "--password",
+            "secret",
+            "--pds",
+            &pds,
+            "--retry",
+        ],
+    )
+    .await;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "stderr: {}", stderr);
+    assert!(stderr.contains("retrying in 0s (2/3)"), "{}", stderr);
+    assert_eq!(
+        read_session(home.path())["did"],
+        "did:plc:test234aaaaaaaaaaaaaaaaa"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_public_reads_without_session() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    let pds = format!("http://127.0.0.1:{}", server.address().port());
+    let record = json!({
+        "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/abc123",
+        "cid": "bafytest1",
+        "value": {"$type": "org.test.record", "text": "public"}
+    });
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.identity.resolveHandle"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"did": "did:plc:test234aaaaaaaaaaaaaaaaa"})),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.repo.listRecords"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"records": [record]})))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.repo.getRecord"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(record.clone()))
+        .mount(&server)
+        .await;
+
+    let output = run_cli_in(
+        home.path(),
+        &[
+            "-o",
+            "json",
+            "pds",
+            "list-records",
+            "org.test.record",
+            "--repo",
+            "alice.test",
+            "--pds",
+            &pds,
+        ],
+    )
+    .await;
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let result: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result["records"][0]["uri"], record["uri"]);
+
+    let uri = record["uri"].as_str().unwrap();
+    let output = run_cli_in(home.path(), &["pds", "get-record", uri, "--pds", &pds]).await;
+    assert!(output.status.success());
+    let value: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(value["text"], "public");
+
+    let requests = server.received_requests().await.unwrap();
+    assert!(
+        requests
+            .iter()
+            .all(|request| !request.headers.contains_key("authorization"))
+    );
+
+    // Without a session there is no default repo.
+    let output = run_cli_in(
+        home.path(),
+        &["pds", "list-records", "org.test.record", "--pds", &pds],
+    )
+    .await;
+    assert_eq!(output.status.code(), Some(3));
+}
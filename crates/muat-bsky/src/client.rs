@@ -0,0 +1,166 @@
+//! Bluesky helper client.
+
+use chrono::{SecondsFormat, Utc};
+use serde_json::{Value, json};
+use tracing::{debug, instrument};
+
+use muat_core::traits::Session;
+use muat_core::types::{AtUri, Did, Nsid};
+use muat_core::{RecordValue, Result};
+use muat_xrpc::XrpcSession;
+
+use crate::preferences::Preferences;
+use crate::types::{Profile, Timeline};
+
+/// Collection for posts.
+const POST: &str = "app.bsky.feed.post";
+
+/// Collection for likes.
+const LIKE: &str = "app.bsky.feed.like";
+
+/// Collection for follows.
+const FOLLOW: &str = "app.bsky.graph.follow";
+
+/// App view endpoint for a single profile.
+const GET_PROFILE: &str = "app.bsky.actor.getProfile";
+
+/// App view endpoint for the home timeline.
+const GET_TIMELINE: &str = "app.bsky.feed.getTimeline";
+
+/// Endpoint for reading account preferences.
+const GET_PREFERENCES: &str = "app.bsky.actor.getPreferences";
+
+/// Endpoint for replacing account preferences.
+const PUT_PREFERENCES: &str = "app.bsky.actor.putPreferences";
+
+/// Bluesky helpers over an authenticated session.
+///
+/// # Example
+///
+/// ```no_run
+/// use muat_bsky::Bsky;
+/// use muat_core::traits::Pds;
+/// use muat_core::{Credentials, PdsUrl};
+/// use muat_xrpc::XrpcPds;
+///
+/// # async fn example() -> Result<(), muat_core::Error> {
+/// let pds = XrpcPds::new(PdsUrl::new("https://bsky.social")?);
+/// let session = pds.login(Credentials::new("alice.bsky.social", "app-password")).await?;
+///
+/// let bsky = Bsky::new(session);
+/// let uri = bsky.post("Hello from muat!").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Bsky {
+    session: XrpcSession,
+}
+
+impl Bsky {
+    /// Wrap an authenticated session.
+    pub fn new(session: XrpcSession) -> Self {
+        Self { session }
+    }
+
+    /// Returns the underlying session.
+    pub fn session(&self) -> &XrpcSession {
+        &self.session
+    }
+
+    /// Publish a text post.
+    #[instrument(skip(self, text))]
+    pub async fn post(&self, text: &str) -> Result<AtUri> {
+        debug!("Creating post");
+        self.create(
+            POST,
+            json!({
+                "text": text,
+                "createdAt": now(),
+            }),
+        )
+        .await
+    }
+
+    /// Like a post, identified by its URI and CID.
+    #[instrument(skip(self), fields(%uri))]
+    pub async fn like(&self, uri: &AtUri, cid: &str) -> Result<AtUri> {
+        debug!("Liking post");
+        self.create(
+            LIKE,
+            json!({
+                "subject": { "uri": uri.to_string(), "cid": cid },
+                "createdAt": now(),
+            }),
+        )
+        .await
+    }
+
+    /// Follow an account.
+    #[instrument(skip(self), fields(%did))]
+    pub async fn follow(&self, did: &Did) -> Result<AtUri> {
+        debug!("Following account");
+        self.create(
+            FOLLOW,
+            json!({
+                "subject": did.as_str(),
+                "createdAt": now(),
+            }),
+        )
+        .await
+    }
+
+    /// Fetch a profile by handle or DID from the app view.
+    #[instrument(skip(self))]
+    pub async fn get_profile(&self, actor: &str) -> Result<Profile> {
+        let method = Nsid::new(GET_PROFILE)?;
+        self.session.xrpc_query(&method, &[("actor", actor)]).await
+    }
+
+    /// Fetch a page of the home timeline from the app view.
+    #[instrument(skip(self))]
+    pub async fn get_timeline(&self, limit: Option<u32>, cursor: Option<&str>) -> Result<Timeline> {
+        let method = Nsid::new(GET_TIMELINE)?;
+
+        let mut params: Vec<(&str, String)> = Vec::new();
+        if let Some(limit) = limit {
+            params.push(("limit", limit.to_string()));
+        }
+        if let Some(cursor) = cursor {
+            params.push(("cursor", cursor.to_string()));
+        }
+
+        self.session.xrpc_query(&method, &params).await
+    }
+
+    /// Fetch the account's preferences.
+    #[instrument(skip(self))]
+    pub async fn get_preferences(&self) -> Result<Preferences> {
+        let method = Nsid::new(GET_PREFERENCES)?;
+        self.session.xrpc_query(&method, &()).await
+    }
+
+    /// Replace the account's preferences.
+    ///
+    /// This overwrites every preference, so read the current set with
+    /// [`get_preferences`](Self::get_preferences) and modify it rather than
+    /// building one from scratch.
+    #[instrument(skip(self, preferences))]
+    pub async fn put_preferences(&self, preferences: &Preferences) -> Result<()> {
+        debug!(count = preferences.preferences.len(), "Putting preferences");
+        let method = Nsid::new(PUT_PREFERENCES)?;
+        self.session
+            .xrpc_procedure_no_response(&method, preferences)
+            .await
+    }
+
+    async fn create(&self, collection: &str, value: Value) -> Result<AtUri> {
+        let collection = Nsid::new(collection)?;
+        let record = RecordValue::with_type(collection.as_str(), value)?;
+        self.session.create_record(&collection, &record).await
+    }
+}
+
+fn now() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
@@ -0,0 +1,20 @@
+//! muat-bsky - Bluesky (`app.bsky`) convenience helpers.
+//!
+//! This crate layers typed helpers for common Bluesky actions over an
+//! [`XrpcSession`](muat_xrpc::XrpcSession). Writes are ordinary record
+//! operations on the user's repository; reads go to the app view through the
+//! PDS.
+//!
+//! Lexicon-specific types live here rather than in `muat-core`, which stays
+//! schema-agnostic.
+
+mod client;
+mod preferences;
+mod types;
+
+pub use client::Bsky;
+pub use preferences::{
+    AdultContentPref, ContentLabelPref, PersonalDetailsPref, Preference, Preferences, SavedFeed,
+    SavedFeedsPref,
+};
+pub use types::{FeedViewPost, Label, PostView, Profile, ProfileViewBasic, Timeline};
@@ -0,0 +1,196 @@
+//! Account preferences (`app.bsky.actor.defs#preferences`).
+//!
+//! Commonly used preference kinds are typed. Anything else is kept as raw
+//! JSON, and typed preferences keep fields they do not model, so a value
+//! read with `get_preferences` can be written back without losing data.
+
+use serde::de::Error as _;
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+const ADULT_CONTENT: &str = "app.bsky.actor.defs#adultContentPref";
+const CONTENT_LABEL: &str = "app.bsky.actor.defs#contentLabelPref";
+const SAVED_FEEDS: &str = "app.bsky.actor.defs#savedFeedsPrefV2";
+const PERSONAL_DETAILS: &str = "app.bsky.actor.defs#personalDetailsPref";
+
+/// An account's preferences, as read from and written to the PDS.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Preferences {
+    /// Preferences in the order the PDS returned them.
+    pub preferences: Vec<Preference>,
+}
+
+impl Preferences {
+    /// Returns the adult content preference, if set.
+    pub fn adult_content(&self) -> Option<&AdultContentPref> {
+        self.preferences.iter().find_map(|p| match p {
+            Preference::AdultContent(pref) => Some(pref),
+            _ => None,
+        })
+    }
+
+    /// Returns the saved feeds preference, if set.
+    pub fn saved_feeds(&self) -> Option<&SavedFeedsPref> {
+        self.preferences.iter().find_map(|p| match p {
+            Preference::SavedFeeds(pref) => Some(pref),
+            _ => None,
+        })
+    }
+
+    /// Replace the preference of the same kind, or append it if absent.
+    ///
+    /// Raw preferences are never replaced, since their kind is not known.
+    pub fn set(&mut self, preference: Preference) {
+        let existing = self
+            .preferences
+            .iter_mut()
+            .find(|p| p.type_id().is_some() && p.type_id() == preference.type_id());
+        match existing {
+            Some(slot) => *slot = preference,
+            None => self.preferences.push(preference),
+        }
+    }
+}
+
+/// A single preference, identified on the wire by its `$type`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Preference {
+    /// `app.bsky.actor.defs#adultContentPref`
+    AdultContent(AdultContentPref),
+    /// `app.bsky.actor.defs#contentLabelPref`
+    ContentLabel(ContentLabelPref),
+    /// `app.bsky.actor.defs#savedFeedsPrefV2`
+    SavedFeeds(SavedFeedsPref),
+    /// `app.bsky.actor.defs#personalDetailsPref`
+    PersonalDetails(PersonalDetailsPref),
+    /// Any other preference, including its `$type`.
+    Raw(Value),
+}
+
+impl Preference {
+    /// The `$type` of a typed preference; `None` for raw preferences.
+    fn type_id(&self) -> Option<&'static str> {
+        match self {
+            Self::AdultContent(_) => Some(ADULT_CONTENT),
+            Self::ContentLabel(_) => Some(CONTENT_LABEL),
+            Self::SavedFeeds(_) => Some(SAVED_FEEDS),
+            Self::PersonalDetails(_) => Some(PERSONAL_DETAILS),
+            Self::Raw(_) => None,
+        }
+    }
+}
+
+impl Serialize for Preference {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = match self {
+            Self::AdultContent(pref) => serde_json::to_value(pref),
+            Self::ContentLabel(pref) => serde_json::to_value(pref),
+            Self::SavedFeeds(pref) => serde_json::to_value(pref),
+            Self::PersonalDetails(pref) => serde_json::to_value(pref),
+            Self::Raw(value) => return value.serialize(serializer),
+        }
+        .map_err(S::Error::custom)?;
+
+        let Value::Object(fields) = value else {
+            return Err(S::Error::custom(
+                "preference did not serialize to an object",
+            ));
+        };
+        let mut object = Map::with_capacity(fields.len() + 1);
+        if let Some(type_id) = self.type_id() {
+            object.insert("$type".to_string(), Value::String(type_id.to_string()));
+        }
+        object.extend(fields);
+        Value::Object(object).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Preference {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        let Value::Object(fields) = value else {
+            return Err(D::Error::custom("preference must be an object"));
+        };
+
+        let mut body = fields.clone();
+        let type_id = body.remove("$type");
+        let body = Value::Object(body);
+
+        // A known kind that fails to parse is kept raw rather than rejected.
+        let preference = match type_id.as_ref().and_then(Value::as_str) {
+            Some(ADULT_CONTENT) => serde_json::from_value(body).ok().map(Self::AdultContent),
+            Some(CONTENT_LABEL) => serde_json::from_value(body).ok().map(Self::ContentLabel),
+            Some(SAVED_FEEDS) => serde_json::from_value(body).ok().map(Self::SavedFeeds),
+            Some(PERSONAL_DETAILS) => serde_json::from_value(body).ok().map(Self::PersonalDetails),
+            _ => None,
+        };
+
+        Ok(preference.unwrap_or(Self::Raw(Value::Object(fields))))
+    }
+}
+
+/// Whether adult content is shown.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdultContentPref {
+    /// Whether adult content is enabled.
+    pub enabled: bool,
+    /// Fields not modelled here, preserved on round trip.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Visibility of content carrying a label.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContentLabelPref {
+    /// The labeler this applies to; global when absent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub labeler_did: Option<String>,
+    /// The label value.
+    pub label: String,
+    /// `ignore`, `show`, `warn` or `hide`.
+    pub visibility: String,
+    /// Fields not modelled here, preserved on round trip.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// Saved and pinned feeds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedFeedsPref {
+    /// Saved feeds, in display order.
+    pub items: Vec<SavedFeed>,
+    /// Fields not modelled here, preserved on round trip.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A saved feed entry (`app.bsky.actor.defs#savedFeed`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedFeed {
+    /// Client-generated identifier for the entry.
+    pub id: String,
+    /// `feed`, `list` or `timeline`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The feed or list AT URI, or `following` for the timeline.
+    pub value: String,
+    /// Whether the feed is pinned.
+    pub pinned: bool,
+}
+
+/// Personal details.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonalDetailsPref {
+    /// Birth date, as an RFC 3339 datetime.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub birth_date: Option<String>,
+    /// Fields not modelled here, preserved on round trip.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
@@ -0,0 +1,137 @@
+//! App view response types.
+//!
+//! Only the commonly used fields are typed. Embeds and other nested views
+//! are kept as raw JSON.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A moderation label (`com.atproto.label.defs#label`).
+///
+/// App views return labels from the labelers a session accepts; see
+/// [`XrpcSession::with_accept_labelers`](muat_xrpc::XrpcSession::with_accept_labelers).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Label {
+    /// Label format version.
+    pub ver: Option<u64>,
+    /// DID of the labeler that created the label.
+    pub src: String,
+    /// AT URI of the labelled record, or DID of the labelled account.
+    pub uri: String,
+    /// CID of the labelled record version, if the label is that specific.
+    pub cid: Option<String>,
+    /// The label value, such as `porn` or `!warn`.
+    pub val: String,
+    /// Whether this label negates (removes) an earlier one.
+    #[serde(default)]
+    pub neg: bool,
+    /// When the label was created.
+    pub cts: String,
+    /// When the label expires, if ever.
+    pub exp: Option<String>,
+    /// Signature over the label, as encoded by the app view.
+    pub sig: Option<Value>,
+}
+
+impl Label {
+    /// Whether the label applies: not a negation, and not expired at `now`.
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        if self.neg {
+            return false;
+        }
+        match self.exp.as_deref().map(DateTime::parse_from_rfc3339) {
+            Some(Ok(exp)) => exp > now,
+            // An unreadable expiry is treated as no expiry.
+            Some(Err(_)) | None => true,
+        }
+    }
+}
+
+/// A detailed profile (`app.bsky.actor.defs#profileViewDetailed`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    /// The account DID.
+    pub did: String,
+    /// The account handle.
+    pub handle: String,
+    /// Display name, if set.
+    pub display_name: Option<String>,
+    /// Profile description, if set.
+    pub description: Option<String>,
+    /// Avatar image URL, if set.
+    pub avatar: Option<String>,
+    /// Number of followers.
+    pub followers_count: Option<u64>,
+    /// Number of accounts followed.
+    pub follows_count: Option<u64>,
+    /// Number of posts.
+    pub posts_count: Option<u64>,
+    /// Labels on the account and its profile record.
+    #[serde(default)]
+    pub labels: Vec<Label>,
+}
+
+/// A minimal profile (`app.bsky.actor.defs#profileViewBasic`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileViewBasic {
+    /// The account DID.
+    pub did: String,
+    /// The account handle.
+    pub handle: String,
+    /// Display name, if set.
+    pub display_name: Option<String>,
+    /// Avatar image URL, if set.
+    pub avatar: Option<String>,
+    /// Labels on the account and its profile record.
+    #[serde(default)]
+    pub labels: Vec<Label>,
+}
+
+/// A post as seen by the app view (`app.bsky.feed.defs#postView`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostView {
+    /// The post AT URI.
+    pub uri: String,
+    /// The post record CID.
+    pub cid: String,
+    /// The post author.
+    pub author: ProfileViewBasic,
+    /// The raw post record.
+    pub record: Value,
+    /// When the app view indexed the post.
+    pub indexed_at: String,
+    /// Number of replies.
+    pub reply_count: Option<u64>,
+    /// Number of reposts.
+    pub repost_count: Option<u64>,
+    /// Number of likes.
+    pub like_count: Option<u64>,
+    /// Labels on the post.
+    #[serde(default)]
+    pub labels: Vec<Label>,
+}
+
+/// A timeline entry (`app.bsky.feed.defs#feedViewPost`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeedViewPost {
+    /// The post.
+    pub post: PostView,
+    /// Reply context, if the post is a reply.
+    pub reply: Option<Value>,
+    /// Why the post is in the feed (e.g. a repost).
+    pub reason: Option<Value>,
+}
+
+/// Output from `app.bsky.feed.getTimeline`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Timeline {
+    /// Timeline entries, newest first.
+    pub feed: Vec<FeedViewPost>,
+    /// Cursor for the next page, if any.
+    pub cursor: Option<String>,
+}
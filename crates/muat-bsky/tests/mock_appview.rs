@@ -0,0 +1,262 @@
+//! Mock PDS tests for the Bluesky helpers.
+
+use muat_bsky::{AdultContentPref, Bsky, Preference};
+use muat_core::{AtUri, Credentials, Did, Pds, PdsUrl};
+use muat_xrpc::XrpcPds;
+use serde_json::json;
+use wiremock::matchers::{body_json, body_partial_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+async fn login(server: &MockServer) -> Bsky {
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(server)
+        .await;
+
+    let pds_url = PdsUrl::new(format!("http://127.0.0.1:{}", server.address().port())).unwrap();
+    let session = XrpcPds::new(pds_url)
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+
+    Bsky::new(session)
+}
+
+#[tokio::test]
+async fn test_post_creates_feed_post_record() {
+    let server = MockServer::start().await;
+    let bsky = login(&server).await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.createRecord"))
+        .and(body_partial_json(json!({
+            "collection": "app.bsky.feed.post",
+            "record": { "$type": "app.bsky.feed.post", "text": "hello" }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/app.bsky.feed.post/3kabc",
+            "cid": "bafypost"
+        })))
+        .mount(&server)
+        .await;
+
+    let uri = bsky.post("hello").await.unwrap();
+    assert_eq!(uri.rkey().unwrap().as_str(), "3kabc");
+}
+
+#[tokio::test]
+async fn test_like_and_follow_use_subjects() {
+    let server = MockServer::start().await;
+    let bsky = login(&server).await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.createRecord"))
+        .and(body_partial_json(json!({
+            "collection": "app.bsky.feed.like",
+            "record": { "subject": { "uri": "at://did:plc:otheraaaaaaaaaaaaaaaaaaa/app.bsky.feed.post/1", "cid": "bafy" } }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/app.bsky.feed.like/like1",
+            "cid": "bafylike"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.createRecord"))
+        .and(body_partial_json(json!({
+            "collection": "app.bsky.graph.follow",
+            "record": { "subject": "did:plc:otheraaaaaaaaaaaaaaaaaaa" }
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/app.bsky.graph.follow/follow1",
+            "cid": "bafyfollow"
+        })))
+        .mount(&server)
+        .await;
+
+    let post = AtUri::new("at://did:plc:otheraaaaaaaaaaaaaaaaaaa/app.bsky.feed.post/1").unwrap();
+    let like = bsky.like(&post, "bafy").await.unwrap();
+    assert_eq!(like.rkey().unwrap().as_str(), "like1");
+
+    let follow = bsky
+        .follow(&Did::new("did:plc:otheraaaaaaaaaaaaaaaaaaa").unwrap())
+        .await
+        .unwrap();
+    assert_eq!(follow.rkey().unwrap().as_str(), "follow1");
+}
+
+#[tokio::test]
+async fn test_get_profile_and_timeline() {
+    let server = MockServer::start().await;
+    let bsky = login(&server).await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/app.bsky.actor.getProfile"))
+        .and(query_param("actor", "bob.test"))
+        .and(header("authorization", "Bearer access-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:bobaaaaaaaaaaaaaaaaaaaaa",
+            "handle": "bob.test",
+            "displayName": "Bob",
+            "followersCount": 3
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/app.bsky.feed.getTimeline"))
+        .and(query_param("limit", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "cursor": "next",
+            "feed": [{
+                "post": {
+                    "uri": "at://did:plc:bobaaaaaaaaaaaaaaaaaaaaa/app.bsky.feed.post/1",
+                    "cid": "bafy",
+                    "author": { "did": "did:plc:bobaaaaaaaaaaaaaaaaaaaaa", "handle": "bob.test" },
+                    "record": { "$type": "app.bsky.feed.post", "text": "hi" },
+                    "indexedAt": "2024-01-01T00:00:00Z",
+                    "likeCount": 2
+                }
+            }]
+        })))
+        .mount(&server)
+        .await;
+
+    let profile = bsky.get_profile("bob.test").await.unwrap();
+    assert_eq!(profile.display_name.as_deref(), Some("Bob"));
+    assert_eq!(profile.followers_count, Some(3));
+
+    let timeline = bsky.get_timeline(Some(1), None).await.unwrap();
+    assert_eq!(timeline.cursor.as_deref(), Some("next"));
+    assert_eq!(timeline.feed[0].post.author.handle, "bob.test");
+    assert_eq!(timeline.feed[0].post.like_count, Some(2));
+}
+
+#[tokio::test]
+async fn test_reads_carry_accepted_labelers_and_return_labels() {
+    let server = MockServer::start().await;
+    let bsky = login(&server).await;
+    let bsky = Bsky::new(
+        bsky.session()
+            .with_accept_labelers(&["did:plc:labelerbbbbbbbbbbbbbbbbb"])
+            .unwrap(),
+    );
+
+    let label = |val: &str, neg: bool, exp: Option<&str>| {
+        json!({
+            "src": "did:plc:labelerbbbbbbbbbbbbbbbbb",
+            "uri": "did:plc:bobaaaaaaaaaaaaaaaaaaaaa",
+            "val": val,
+            "neg": neg,
+            "cts": "2024-01-01T00:00:00Z",
+            "exp": exp,
+        })
+    };
+    Mock::given(method("GET"))
+        .and(path("/xrpc/app.bsky.actor.getProfile"))
+        .and(header(
+            "atproto-accept-labelers",
+            "did:plc:labelerbbbbbbbbbbbbbbbbb",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:bobaaaaaaaaaaaaaaaaaaaaa",
+            "handle": "bob.test",
+            "labels": [
+                label("!warn", false, None),
+                label("spam", true, None),
+                label("rude", false, Some("2024-02-01T00:00:00Z")),
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    let profile = bsky.get_profile("bob.test").await.unwrap();
+    assert_eq!(profile.labels.len(), 3);
+    assert_eq!(profile.labels[0].src, "did:plc:labelerbbbbbbbbbbbbbbbbb");
+
+    let now = "2024-01-15T00:00:00Z".parse().unwrap();
+    let active: Vec<&str> = profile
+        .labels
+        .iter()
+        .filter(|label| label.is_active(now))
+        .map(|label| label.val.as_str())
+        .collect();
+    assert_eq!(active, ["!warn", "rude"]);
+    let later = "2024-03-01T00:00:00Z".parse().unwrap();
+    assert!(!profile.labels[2].is_active(later));
+}
+
+#[tokio::test]
+async fn test_preferences_round_trip_keeps_unknown_data() {
+    let server = MockServer::start().await;
+    let bsky = login(&server).await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/app.bsky.actor.getPreferences"))
+        .and(header("authorization", "Bearer access-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "preferences": [
+                {
+                    "$type": "app.bsky.actor.defs#adultContentPref",
+                    "enabled": false
+                },
+                {
+                    "$type": "app.bsky.actor.defs#savedFeedsPrefV2",
+                    "items": [
+                        { "id": "1", "type": "timeline", "value": "following", "pinned": true }
+                    ]
+                },
+                {
+                    "$type": "app.bsky.actor.defs#threadViewPref",
+                    "sort": "oldest"
+                }
+            ]
+        })))
+        .mount(&server)
+        .await;
+
+    let mut prefs = bsky.get_preferences().await.unwrap();
+    assert_eq!(prefs.preferences.len(), 3);
+    assert!(!prefs.adult_content().unwrap().enabled);
+    assert_eq!(prefs.saved_feeds().unwrap().items[0].value, "following");
+    assert!(matches!(&prefs.preferences[2], Preference::Raw(_)));
+
+    prefs.set(Preference::AdultContent(AdultContentPref {
+        enabled: true,
+        ..Default::default()
+    }));
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/app.bsky.actor.putPreferences"))
+        .and(body_json(json!({
+            "preferences": [
+                {
+                    "$type": "app.bsky.actor.defs#adultContentPref",
+                    "enabled": true
+                },
+                {
+                    "$type": "app.bsky.actor.defs#savedFeedsPrefV2",
+                    "items": [
+                        { "id": "1", "type": "timeline", "value": "following", "pinned": true }
+                    ]
+                },
+                {
+                    "$type": "app.bsky.actor.defs#threadViewPref",
+                    "sort": "oldest"
+                }
+            ]
+        })))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    bsky.put_preferences(&prefs).await.unwrap();
+}
@@ -0,0 +1,433 @@
+//! Rust source for lexicon definitions.
+//!
+//! Each lexicon becomes a module named after its NSID (`com.example.feed.post`
+//! is `com::example::feed::post`), holding one item per definition:
+//!
+//! - records and objects become structs implementing `muat_core::Lexicon`;
+//! - unions become enums tagged by `$type`, with an `Unknown` variant unless
+//!   the union is closed;
+//! - tokens become string constants, and other plain types aliases;
+//! - XRPC methods are skipped.
+//!
+//! References between lexicons are relative paths, so the output works
+//! wherever it is included. A reference to a lexicon that was not given is
+//! typed as `serde_json::Value`.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::schema::{Def, LexiconDoc, Object};
+
+const HEADER: &str = "// @generated by muat-codegen from lexicon schemas. Do not edit.\n";
+
+const DERIVE: &str =
+    "#[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]\n";
+
+const VALUE: &str = "::serde_json::Value";
+
+/// Generate the source for every lexicon in `docs`, keyed by NSID.
+pub(crate) fn generate(docs: &BTreeMap<String, LexiconDoc>) -> String {
+    let mut root = Module::default();
+    for doc in docs.values() {
+        let module = module_path(&doc.id);
+        let items = DocGen {
+            docs,
+            doc,
+            module: &module,
+            items: Vec::new(),
+        }
+        .generate();
+        // Lexicons of only XRPC methods get no module.
+        if !items.is_empty() {
+            root.insert(&module, items);
+        }
+    }
+
+    let mut out = format!("{}\n", HEADER);
+    root.write(&mut out, 0);
+    out
+}
+
+/// A module of the output: items from the lexicon at this path, if any,
+/// and the modules below it.
+#[derive(Default)]
+struct Module {
+    items: Vec<String>,
+    children: BTreeMap<String, Module>,
+}
+
+impl Module {
+    fn insert(&mut self, path: &[String], items: Vec<String>) {
+        match path.split_first() {
+            None => self.items.extend(items),
+            Some((name, rest)) => self
+                .children
+                .entry(name.clone())
+                .or_default()
+                .insert(rest, items),
+        }
+    }
+
+    fn write(&self, out: &mut String, depth: usize) {
+        let indent = "    ".repeat(depth);
+        let mut first = true;
+        let mut separate = |out: &mut String| {
+            if !first {
+                out.push('\n');
+            }
+            first = false;
+        };
+        for item in &self.items {
+            separate(out);
+            for line in item.lines() {
+                if line.is_empty() {
+                    out.push('\n');
+                } else {
+                    let _ = writeln!(out, "{}{}", indent, line);
+                }
+            }
+        }
+        for (name, child) in &self.children {
+            separate(out);
+            let _ = writeln!(out, "{}pub mod {} {{", indent, name);
+            child.write(out, depth + 1);
+            let _ = writeln!(out, "{}}}", indent);
+        }
+    }
+}
+
+/// Generates the items of one lexicon.
+struct DocGen<'a> {
+    docs: &'a BTreeMap<String, LexiconDoc>,
+    doc: &'a LexiconDoc,
+    module: &'a [String],
+    items: Vec<String>,
+}
+
+impl DocGen<'_> {
+    fn generate(mut self) -> Vec<String> {
+        // `main` first, then the rest by name.
+        let mut defs: Vec<_> = self.doc.defs.iter().collect();
+        defs.sort_by_key(|(name, _)| (*name != "main", *name));
+
+        for (name, def) in defs {
+            let type_name = type_name(&self.doc.id, name);
+            let tag = type_tag(&self.doc.id, name);
+            match def {
+                Def::Record(record) => {
+                    let description = record
+                        .description
+                        .as_deref()
+                        .or(record.record.description.as_deref());
+                    self.structure(&type_name, description, &record.record, &tag);
+                }
+                Def::Object(object) => {
+                    self.structure(&type_name, object.description.as_deref(), object, &tag);
+                }
+                Def::Token(token) => {
+                    let mut item = doc_comment(token.description.as_deref(), "");
+                    let _ = writeln!(item, "pub const {}: &str = {:?};", constant_name(name), tag);
+                    self.items.push(item);
+                }
+                Def::Union {
+                    description,
+                    refs,
+                    closed,
+                } => self.union(&type_name, description.as_deref(), refs, *closed, None),
+                Def::Other => {}
+                field => {
+                    let at = self.items.len();
+                    let ty = self.rust_type(field, &type_name, "Item", None);
+                    let mut item = doc_comment(field.description(), "");
+                    let _ = writeln!(item, "pub type {} = {};", type_name, ty);
+                    self.items.insert(at, item);
+                }
+            }
+        }
+        self.items
+    }
+
+    /// A struct for an object, and the enums for its unions.
+    fn structure(&mut self, name: &str, description: Option<&str>, object: &Object, tag: &str) {
+        let at = self.items.len();
+        let mut fields = String::new();
+        for (property, def) in &object.properties {
+            let field = field_name(property);
+            let ty = self.rust_type(def, name, &pascal_case(property), Some(name));
+            let optional =
+                !object.required.contains(property) || object.nullable.contains(property);
+
+            fields.push_str(&doc_comment(def.description(), "    "));
+            let mut attrs = Vec::new();
+            if unescaped(&field) != property {
+                attrs.push(format!("rename = {:?}", property));
+            }
+            if optional {
+                attrs.push("default, skip_serializing_if = \"Option::is_none\"".to_string());
+            }
+            if !attrs.is_empty() {
+                let _ = writeln!(fields, "    #[serde({})]", attrs.join(", "));
+            }
+            if optional {
+                let _ = writeln!(fields, "    pub {}: Option<{}>,", field, ty);
+            } else {
+                let _ = writeln!(fields, "    pub {}: {},", field, ty);
+            }
+        }
+
+        let mut item = doc_comment(description, "");
+        item.push_str(DERIVE);
+        if fields.is_empty() {
+            let _ = writeln!(item, "pub struct {} {{}}", name);
+        } else {
+            let _ = write!(item, "pub struct {} {{\n{}}}\n", name, fields);
+        }
+        let _ = writeln!(item);
+        let _ = writeln!(item, "impl ::muat_core::Lexicon for {} {{", name);
+        let _ = writeln!(item, "    const NSID: &'static str = {:?};", self.doc.id);
+        if tag != self.doc.id {
+            let _ = writeln!(item, "    const TYPE: &'static str = {:?};", tag);
+        }
+        item.push_str("}\n");
+        self.items.insert(at, item);
+    }
+
+    /// An enum for a union, tagged by `$type`.
+    ///
+    /// `owner` is the struct the union is a field of; a variant holding that
+    /// struct is boxed.
+    fn union(
+        &mut self,
+        name: &str,
+        description: Option<&str>,
+        refs: &[String],
+        closed: bool,
+        owner: Option<&str>,
+    ) {
+        let mut variants = String::new();
+        let mut used = Vec::new();
+        for target in refs {
+            let (nsid, def_name) = self.split_ref(target);
+            let mut variant = type_name(nsid, def_name);
+            if used.contains(&variant) || variant == "Unknown" {
+                variant = pascal_case(&format!("{}.{}", nsid, def_name));
+            }
+            let ty = match self.resolve(target) {
+                Some(path) if Some(path.as_str()) == owner => format!("Box<{}>", path),
+                Some(path) => path,
+                None => VALUE.to_string(),
+            };
+            let _ = writeln!(
+                variants,
+                "    #[serde(rename = {:?})]\n    {}({}),",
+                type_tag(nsid, def_name),
+                variant,
+                ty
+            );
+            used.push(variant);
+        }
+        if !closed {
+            variants.push_str("    /// A value of a type the union does not list.\n");
+            variants.push_str("    #[serde(untagged)]\n");
+            let _ = writeln!(variants, "    Unknown({}),", VALUE);
+        }
+
+        let mut item = doc_comment(description, "");
+        item.push_str(DERIVE);
+        item.push_str("#[allow(clippy::enum_variant_names, clippy::large_enum_variant)]\n");
+        item.push_str("#[serde(tag = \"$type\")]\n");
+        let _ = write!(item, "pub enum {} {{\n{}}}\n", name, variants);
+        self.items.push(item);
+    }
+
+    /// The Rust type of a property or array item of `owner`, generating an
+    /// enum named after both for a union.
+    ///
+    /// `direct` is the owner's name when a reference to the owner needs
+    /// boxing, and `None` inside a `Vec`.
+    fn rust_type(&mut self, def: &Def, owner: &str, name: &str, direct: Option<&str>) -> String {
+        match def {
+            Def::Boolean { .. } => "bool".to_string(),
+            Def::Integer { .. } => "i64".to_string(),
+            Def::String { .. } => "String".to_string(),
+            Def::Blob { .. } => "::muat_core::BlobRef".to_string(),
+            Def::Array { items, .. } => {
+                format!("Vec<{}>", self.rust_type(items, owner, name, None))
+            }
+            Def::Ref { target, .. } => match self.resolve(target) {
+                Some(path) if Some(path.as_str()) == direct => format!("Box<{}>", path),
+                Some(path) => path,
+                None => VALUE.to_string(),
+            },
+            Def::Union {
+                description,
+                refs,
+                closed,
+            } => {
+                let enum_name = format!("{}{}", owner, name);
+                self.union(&enum_name, description.as_deref(), refs, *closed, direct);
+                enum_name
+            }
+            Def::Bytes { .. }
+            | Def::CidLink { .. }
+            | Def::Unknown { .. }
+            | Def::Object(_)
+            | Def::Record(_)
+            | Def::Token(_)
+            | Def::Other => VALUE.to_string(),
+        }
+    }
+
+    /// Split a reference into its NSID and definition name.
+    fn split_ref<'r>(&'r self, target: &'r str) -> (&'r str, &'r str) {
+        match target.split_once('#') {
+            Some(("", name)) => (&self.doc.id, name),
+            Some((nsid, name)) => (nsid, name),
+            None => (target, "main"),
+        }
+    }
+
+    /// The path to the type a reference names, relative to this lexicon's
+    /// module, or `None` if it names nothing with a type.
+    fn resolve(&self, target: &str) -> Option<String> {
+        let (nsid, name) = self.split_ref(target);
+        match self.docs.get(nsid)?.defs.get(name)? {
+            Def::Token(_) => return Some("String".to_string()),
+            Def::Other => return None,
+            _ => {}
+        }
+
+        let to = module_path(nsid);
+        let common = self
+            .module
+            .iter()
+            .zip(&to)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let mut path = "super::".repeat(self.module.len() - common);
+        for segment in &to[common..] {
+            path.push_str(segment);
+            path.push_str("::");
+        }
+        path.push_str(&type_name(nsid, name));
+        Some(path)
+    }
+}
+
+/// The module path of a lexicon: its NSID's segments as snake case.
+fn module_path(nsid: &str) -> Vec<String> {
+    nsid.split('.').map(field_name).collect()
+}
+
+/// The `$type` of a definition.
+fn type_tag(nsid: &str, name: &str) -> String {
+    if name == "main" {
+        nsid.to_string()
+    } else {
+        format!("{}#{}", nsid, name)
+    }
+}
+
+/// The Rust type name of a definition: the last NSID segment for `main`,
+/// otherwise the definition name, in Pascal case.
+fn type_name(nsid: &str, name: &str) -> String {
+    if name == "main" {
+        pascal_case(nsid.rsplit('.').next().unwrap_or(nsid))
+    } else {
+        pascal_case(name)
+    }
+}
+
+fn pascal_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    for word in name.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            out.push(first.to_ascii_uppercase());
+            out.extend(chars);
+        }
+    }
+    if out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert(0, 'T');
+    }
+    out
+}
+
+fn snake_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len() + 4);
+    let mut previous: Option<char> = None;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            out.push('_');
+        } else if c.is_ascii_uppercase() {
+            if previous.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit()) {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+        previous = Some(c);
+    }
+    if out.is_empty() || out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert(0, '_');
+    }
+    out
+}
+
+fn constant_name(name: &str) -> String {
+    snake_case(name).to_ascii_uppercase()
+}
+
+/// A field or module name: snake case, escaped if it is a keyword.
+fn field_name(name: &str) -> String {
+    let name = snake_case(name);
+    match name.as_str() {
+        // Keywords that cannot be raw identifiers.
+        "crate" | "self" | "super" => format!("{}_", name),
+        "abstract" | "as" | "async" | "await" | "become" | "box" | "break" | "const"
+        | "continue" | "do" | "dyn" | "else" | "enum" | "extern" | "false" | "final" | "fn"
+        | "for" | "gen" | "if" | "impl" | "in" | "let" | "loop" | "macro" | "match" | "mod"
+        | "move" | "mut" | "override" | "priv" | "pub" | "ref" | "return" | "static" | "struct"
+        | "trait" | "true" | "try" | "type" | "typeof" | "unsafe" | "unsized" | "use"
+        | "virtual" | "where" | "while" | "yield" => format!("r#{}", name),
+        _ => name,
+    }
+}
+
+/// A field name as serde sees it, without the raw identifier prefix.
+fn unescaped(field: &str) -> &str {
+    field.strip_prefix("r#").unwrap_or(field)
+}
+
+fn doc_comment(description: Option<&str>, indent: &str) -> String {
+    let mut out = String::new();
+    for line in description.unwrap_or_default().lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            let _ = writeln!(out, "{}///", indent);
+        } else {
+            let _ = writeln!(out, "{}/// {}", indent, line);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_follow_rust_conventions() {
+        assert_eq!(pascal_case("strongRef"), "StrongRef");
+        assert_eq!(type_name("com.example.feed.post", "main"), "Post");
+        assert_eq!(field_name("createdAt"), "created_at");
+        assert_eq!(field_name("type"), "r#type");
+        assert_eq!(field_name("self"), "self_");
+        assert_eq!(constant_name("clickthroughItem"), "CLICKTHROUGH_ITEM");
+        assert_eq!(
+            module_path("com.example.getTimeline"),
+            ["com", "example", "get_timeline"]
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! muat-codegen - Rust types from AT Protocol lexicons.
+//!
+//! Reads lexicon JSON schemas and generates Rust structs with serde impls
+//! for their records and objects, each implementing
+//! [`muat_core::Lexicon`], so apps can use typed records for their own
+//! NSIDs with [`RecordValue::from_lexicon`](muat_core::RecordValue::from_lexicon)
+//! and [`to_lexicon`](muat_core::RecordValue::to_lexicon).
+//!
+//! Use it from a build script:
+//!
+//! ```no_run
+//! // build.rs
+//! let out = std::path::Path::new(&std::env::var("OUT_DIR").unwrap()).join("lexicons.rs");
+//! println!("cargo:rerun-if-changed=lexicons");
+//! muat_codegen::compile("lexicons", &out).unwrap();
+//! ```
+//!
+//! and include the output with
+//! `include!(concat!(env!("OUT_DIR"), "/lexicons.rs"));`, or run the
+//! `muat-codegen` binary and commit what it writes. The generated code
+//! needs `muat-core`, `serde` and `serde_json` as dependencies.
+
+mod generate;
+mod schema;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use muat_core::Result;
+use muat_core::error::{Error, InvalidInputError, TransportError};
+
+use crate::schema::LexiconDoc;
+
+/// A set of lexicons to generate code for.
+///
+/// # Example
+///
+/// ```
+/// use muat_codegen::Codegen;
+///
+/// let mut codegen = Codegen::new();
+/// codegen.add_json(r#"{
+///     "lexicon": 1,
+///     "id": "org.example.note",
+///     "defs": {
+///         "main": {
+///             "type": "record",
+///             "key": "tid",
+///             "record": {
+///                 "type": "object",
+///                 "required": ["text"],
+///                 "properties": { "text": { "type": "string" } }
+///             }
+///         }
+///     }
+/// }"#).unwrap();
+///
+/// let code = codegen.generate();
+/// assert!(code.contains("pub struct Note {"));
+/// ```
+#[derive(Debug, Default)]
+pub struct Codegen {
+    docs: BTreeMap<String, LexiconDoc>,
+}
+
+impl Codegen {
+    /// Create an empty set of lexicons.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a lexicon from its JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the JSON is not a version 1 lexicon, or its NSID
+    /// has already been added.
+    pub fn add_json(&mut self, json: &str) -> Result<()> {
+        let doc: LexiconDoc = serde_json::from_str(json).map_err(|e| invalid(e.to_string()))?;
+        if doc.lexicon != 1 {
+            return Err(invalid(format!(
+                "{}: unsupported lexicon version {}",
+                doc.id, doc.lexicon
+            )));
+        }
+        if self.docs.contains_key(&doc.id) {
+            return Err(invalid(format!("{}: lexicon added twice", doc.id)));
+        }
+        self.docs.insert(doc.id.clone(), doc);
+        Ok(())
+    }
+
+    /// Add the lexicon in a JSON file, or every `.json` file under a
+    /// directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a file cannot be read, or as
+    /// [`add_json`](Self::add_json) does, naming the file.
+    pub fn add_path(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)
+                .map_err(|e| io_error(path, e))?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<std::io::Result<Vec<_>>>()
+                .map_err(|e| io_error(path, e))?;
+            entries.sort();
+            for entry in entries {
+                if entry.is_dir() || entry.extension().is_some_and(|ext| ext == "json") {
+                    self.add_path(&entry)?;
+                }
+            }
+            return Ok(());
+        }
+
+        let json = fs::read_to_string(path).map_err(|e| io_error(path, e))?;
+        self.add_json(&json).map_err(|e| match e {
+            Error::InvalidInput(e) => invalid(format!("{}: {}", path.display(), e.reason())),
+            e => e,
+        })
+    }
+
+    /// The NSIDs of the lexicons added, in order.
+    pub fn nsids(&self) -> impl Iterator<Item = &str> {
+        self.docs.keys().map(String::as_str)
+    }
+
+    /// Generate Rust source for the lexicons added.
+    pub fn generate(&self) -> String {
+        generate::generate(&self.docs)
+    }
+}
+
+/// Generate Rust source for the lexicons at `lexicons` (a file or a
+/// directory) and write it to `out`, for use from a build script.
+///
+/// # Errors
+///
+/// Returns an error if a lexicon is invalid, or a file cannot be read or
+/// written.
+pub fn compile(lexicons: impl AsRef<Path>, out: impl AsRef<Path>) -> Result<()> {
+    let mut codegen = Codegen::new();
+    codegen.add_path(lexicons)?;
+    let out = out.as_ref();
+    fs::write(out, codegen.generate()).map_err(|e| io_error(out, e))
+}
+
+fn invalid(message: String) -> Error {
+    Error::InvalidInput(InvalidInputError::Other { message })
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> Error {
+    Error::Transport(TransportError::Http {
+        message: format!("IO error: {}: {}", path.display(), err),
+    })
+}
@@ -0,0 +1,47 @@
+//! muat-codegen - generate Rust types from lexicon JSON files.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::Parser;
+
+use muat_codegen::Codegen;
+
+/// Generate Rust types for AT Protocol lexicons
+#[derive(Parser)]
+#[command(name = "muat-codegen", version)]
+struct Args {
+    /// Lexicon JSON files, or directories to search for them
+    #[arg(required = true)]
+    lexicons: Vec<PathBuf>,
+
+    /// Write the generated code here instead of to stdout
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let mut codegen = Codegen::new();
+    for path in &args.lexicons {
+        if let Err(e) = codegen.add_path(path) {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+    let code = codegen.generate();
+
+    let written = match &args.out {
+        Some(out) => std::fs::write(out, code),
+        None => std::io::stdout().write_all(code.as_bytes()),
+    };
+    match written {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! The parts of the lexicon schema language that code is generated from.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::de::{MapAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+
+/// A lexicon document: one NSID and its definitions.
+#[derive(Debug, Deserialize)]
+pub(crate) struct LexiconDoc {
+    pub lexicon: u32,
+    pub id: String,
+    pub defs: BTreeMap<String, Def>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Record {
+    #[serde(default)]
+    pub description: Option<String>,
+    pub record: Object,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Object {
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: Vec<String>,
+    #[serde(default)]
+    pub nullable: Vec<String>,
+    #[serde(default, deserialize_with = "in_order")]
+    pub properties: Vec<(String, Def)>,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Token {
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// A definition in a lexicon, or the type of a property or array item:
+/// the schema language uses the same shapes for both. Records and tokens
+/// only appear as definitions.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub(crate) enum Def {
+    Record(Record),
+    Token(Token),
+    Boolean {
+        #[serde(default)]
+        description: Option<String>,
+    },
+    Integer {
+        #[serde(default)]
+        description: Option<String>,
+    },
+    String {
+        #[serde(default)]
+        description: Option<String>,
+    },
+    Bytes {
+        #[serde(default)]
+        description: Option<String>,
+    },
+    CidLink {
+        #[serde(default)]
+        description: Option<String>,
+    },
+    Blob {
+        #[serde(default)]
+        description: Option<String>,
+    },
+    Unknown {
+        #[serde(default)]
+        description: Option<String>,
+    },
+    Array {
+        #[serde(default)]
+        description: Option<String>,
+        items: Box<Def>,
+    },
+    Object(Object),
+    Ref {
+        #[serde(default)]
+        description: Option<String>,
+        #[serde(rename = "ref")]
+        target: String,
+    },
+    Union {
+        #[serde(default)]
+        description: Option<String>,
+        refs: Vec<String>,
+        #[serde(default)]
+        closed: bool,
+    },
+    /// An XRPC method, `null`, or another definition with no Rust type.
+    #[serde(other)]
+    Other,
+}
+
+impl Def {
+    pub fn description(&self) -> Option<&str> {
+        match self {
+            Def::Boolean { description }
+            | Def::Integer { description }
+            | Def::String { description }
+            | Def::Bytes { description }
+            | Def::CidLink { description }
+            | Def::Blob { description }
+            | Def::Unknown { description }
+            | Def::Array { description, .. }
+            | Def::Ref { description, .. }
+            | Def::Union { description, .. } => description.as_deref(),
+            Def::Record(Record { description, .. }) | Def::Token(Token { description }) => {
+                description.as_deref()
+            }
+            Def::Object(object) => object.description.as_deref(),
+            Def::Other => None,
+        }
+    }
+}
+
+/// Read a map as a list of entries, keeping the order properties are
+/// written in so generated fields follow the schema.
+fn in_order<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<(String, Def)>, D::Error> {
+    struct Entries;
+
+    impl<'de> Visitor<'de> for Entries {
+        type Value = Vec<(String, Def)>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a map of properties")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+            while let Some(entry) = map.next_entry()? {
+                entries.push(entry);
+            }
+            Ok(entries)
+        }
+    }
+
+    deserializer.deserialize_map(Entries)
+}
@@ -0,0 +1,122 @@
+//! Code generation from the lexicons in `tests/lexicons`.
+//!
+//! `tests/generated/lexicons.rs` is the expected output, compiled below so
+//! the generated types are checked too. After changing the generator,
+//! regenerate it with
+//! `cargo run -p muat-codegen -- tests/lexicons -o tests/generated/lexicons.rs`.
+
+use muat_codegen::Codegen;
+use muat_core::{Cid, Lexicon, RecordValue};
+use serde_json::json;
+
+#[allow(dead_code)]
+mod generated {
+    include!("generated/lexicons.rs");
+}
+
+use generated::com::example::defs::{StrongRef, Thread, ThreadNext};
+use generated::com::example::feed::post::{self, Post, PostEmbed};
+
+const LEXICONS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/lexicons");
+
+#[test]
+fn test_generated_code_matches_the_expected_output() {
+    let mut codegen = Codegen::new();
+    codegen.add_path(LEXICONS).unwrap();
+    assert_eq!(
+        codegen.nsids().collect::<Vec<_>>(),
+        [
+            "com.example.defs",
+            "com.example.embed.images",
+            "com.example.feed.getPosts",
+            "com.example.feed.post",
+        ]
+    );
+    assert_eq!(
+        codegen.generate(),
+        include_str!("generated/lexicons.rs"),
+        "regenerate tests/generated/lexicons.rs"
+    );
+}
+
+#[test]
+fn test_records_round_trip_through_record_values() {
+    let cid = Cid::v1(Cid::DAG_CBOR, Cid::SHA2_256, &[1; 32]);
+    let value = RecordValue::new(json!({
+        "$type": "com.example.feed.post",
+        "text": "hello",
+        "createdAt": "2024-01-01T00:00:00Z",
+        "visibility": "public",
+        "embed": {
+            "$type": "com.example.feed.post#quote",
+            "post": { "uri": "at://did:plc:alice/com.example.feed.post/1", "cid": cid.as_str() },
+        },
+        "type": "note",
+    }))
+    .unwrap();
+
+    let post: Post = value.to_lexicon().unwrap();
+    assert_eq!(post.created_at, "2024-01-01T00:00:00Z");
+    assert_eq!(post.visibility.as_deref(), Some("public"));
+    assert_eq!(post.r#type.as_deref(), Some("note"));
+    let Some(PostEmbed::Quote(quote)) = &post.embed else {
+        panic!("expected a quote, got {:?}", post.embed);
+    };
+    assert_eq!(quote.post.cid, cid.as_str());
+
+    assert_eq!(RecordValue::from_lexicon(&post).unwrap(), value);
+    assert_eq!(Post::NSID, "com.example.feed.post");
+    assert_eq!(post::Quote::TYPE, "com.example.feed.post#quote");
+    assert_eq!(post::PINNED, "com.example.feed.post#pinned");
+}
+
+#[test]
+fn test_open_unions_keep_unknown_types() {
+    let embed = json!({ "$type": "org.example.video", "url": "https://example.com" });
+    let post: Post = serde_json::from_value(json!({
+        "text": "hello",
+        "createdAt": "2024-01-01T00:00:00Z",
+        "embed": embed,
+    }))
+    .unwrap();
+    assert_eq!(post.embed, Some(PostEmbed::Unknown(embed.clone())));
+    assert_eq!(serde_json::to_value(&post).unwrap()["embed"], embed);
+}
+
+#[test]
+fn test_recursive_objects_are_boxed() {
+    let post = StrongRef {
+        uri: "at://did:plc:alice/com.example.feed.post/1".to_string(),
+        cid: "bafyrei".to_string(),
+    };
+    let thread = Thread {
+        post: post.clone(),
+        parent: None,
+        replies: Some(vec![]),
+        next: Some(ThreadNext::Thread(Box::new(Thread {
+            post,
+            parent: None,
+            replies: None,
+            next: None,
+        }))),
+    };
+    let json = serde_json::to_value(&thread).unwrap();
+    assert_eq!(json["next"]["$type"], "com.example.defs#thread");
+    assert_eq!(serde_json::from_value::<Thread>(json).unwrap(), thread);
+}
+
+#[test]
+fn test_invalid_lexicons_are_rejected() {
+    let mut codegen = Codegen::new();
+    assert!(codegen.add_json("{}").is_err());
+    assert!(
+        codegen
+            .add_json(r#"{ "lexicon": 2, "id": "org.example.note", "defs": {} }"#)
+            .is_err()
+    );
+
+    let note = r#"{ "lexicon": 1, "id": "org.example.note", "defs": {} }"#;
+    codegen.add_json(note).unwrap();
+    let error = codegen.add_json(note).unwrap_err();
+    assert!(error.to_string().contains("added twice"), "{error}");
+}
@@ -0,0 +1,143 @@
+// @generated by muat-codegen from lexicon schemas. Do not edit.
+
+pub mod com {
+    pub mod example {
+        pub mod defs {
+            /// A reference to a specific version of a record.
+            #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+            pub struct StrongRef {
+                pub uri: String,
+                pub cid: String,
+            }
+
+            impl ::muat_core::Lexicon for StrongRef {
+                const NSID: &'static str = "com.example.defs";
+                const TYPE: &'static str = "com.example.defs#strongRef";
+            }
+
+            /// A post and the replies to it.
+            #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+            pub struct Thread {
+                pub post: StrongRef,
+                #[serde(default, skip_serializing_if = "Option::is_none")]
+                pub parent: Option<Box<Thread>>,
+                #[serde(default, skip_serializing_if = "Option::is_none")]
+                pub replies: Option<Vec<Thread>>,
+                #[serde(default, skip_serializing_if = "Option::is_none")]
+                pub next: Option<ThreadNext>,
+            }
+
+            impl ::muat_core::Lexicon for Thread {
+                const NSID: &'static str = "com.example.defs";
+                const TYPE: &'static str = "com.example.defs#thread";
+            }
+
+            #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+            #[allow(clippy::enum_variant_names, clippy::large_enum_variant)]
+            #[serde(tag = "$type")]
+            pub enum ThreadNext {
+                #[serde(rename = "com.example.defs#thread")]
+                Thread(Box<Thread>),
+                #[serde(rename = "com.example.defs#strongRef")]
+                StrongRef(StrongRef),
+            }
+
+            pub type Visibility = String;
+        }
+
+        pub mod embed {
+            pub mod images {
+                #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+                pub struct Images {
+                    pub images: Vec<Image>,
+                }
+
+                impl ::muat_core::Lexicon for Images {
+                    const NSID: &'static str = "com.example.embed.images";
+                }
+
+                #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+                pub struct Image {
+                    pub image: ::muat_core::BlobRef,
+                    /// Alt text.
+                    ///
+                    /// Describes the image for screen readers.
+                    #[serde(default, skip_serializing_if = "Option::is_none")]
+                    pub alt: Option<String>,
+                }
+
+                impl ::muat_core::Lexicon for Image {
+                    const NSID: &'static str = "com.example.embed.images";
+                    const TYPE: &'static str = "com.example.embed.images#image";
+                }
+            }
+        }
+
+        pub mod feed {
+            pub mod post {
+                /// A short post.
+                #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+                pub struct Post {
+                    /// The post's text.
+                    pub text: String,
+                    #[serde(rename = "createdAt")]
+                    pub created_at: String,
+                    #[serde(default, skip_serializing_if = "Option::is_none")]
+                    pub langs: Option<Vec<String>>,
+                    #[serde(default, skip_serializing_if = "Option::is_none")]
+                    pub visibility: Option<super::super::defs::Visibility>,
+                    #[serde(default, skip_serializing_if = "Option::is_none")]
+                    pub reply: Option<ReplyRef>,
+                    #[serde(default, skip_serializing_if = "Option::is_none")]
+                    pub embed: Option<PostEmbed>,
+                    #[serde(default, skip_serializing_if = "Option::is_none")]
+                    pub r#type: Option<String>,
+                }
+
+                impl ::muat_core::Lexicon for Post {
+                    const NSID: &'static str = "com.example.feed.post";
+                }
+
+                #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+                #[allow(clippy::enum_variant_names, clippy::large_enum_variant)]
+                #[serde(tag = "$type")]
+                pub enum PostEmbed {
+                    #[serde(rename = "com.example.embed.images")]
+                    Images(super::super::embed::images::Images),
+                    #[serde(rename = "com.example.feed.post#quote")]
+                    Quote(Quote),
+                    /// A value of a type the union does not list.
+                    #[serde(untagged)]
+                    Unknown(::serde_json::Value),
+                }
+
+                /// Marks a post the author pinned.
+                pub const PINNED: &str = "com.example.feed.post#pinned";
+
+                /// Another post, quoted.
+                #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+                pub struct Quote {
+                    pub post: super::super::defs::StrongRef,
+                    #[serde(default, skip_serializing_if = "Option::is_none")]
+                    pub labels: Option<::serde_json::Value>,
+                }
+
+                impl ::muat_core::Lexicon for Quote {
+                    const NSID: &'static str = "com.example.feed.post";
+                    const TYPE: &'static str = "com.example.feed.post#quote";
+                }
+
+                #[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+                pub struct ReplyRef {
+                    pub root: super::super::defs::StrongRef,
+                    pub parent: super::super::defs::StrongRef,
+                }
+
+                impl ::muat_core::Lexicon for ReplyRef {
+                    const NSID: &'static str = "com.example.feed.post";
+                    const TYPE: &'static str = "com.example.feed.post#replyRef";
+                }
+            }
+        }
+    }
+}
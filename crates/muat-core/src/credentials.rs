@@ -4,8 +4,9 @@ use std::fmt;
 
 /// Login credentials for AT Protocol authentication.
 ///
-/// This type holds the identifier (handle or DID) and secret (password or app password)
-/// required to authenticate with a PDS.
+/// This type holds the identifier (handle, DID or account email) and secret (password or
+/// app password) required to authenticate with a PDS. The identifier is passed to the PDS
+/// unchanged; it is not validated, since PDSes accept emails as well as handles and DIDs.
 ///
 /// # Security
 ///
@@ -29,7 +30,7 @@ impl Credentials {
     ///
     /// # Arguments
     ///
-    /// * `identifier` - A handle (e.g., "alice.bsky.social") or DID
+    /// * `identifier` - A handle (e.g., "alice.bsky.social"), DID or account email
     /// * `password` - The account password or an app password
     pub fn new(identifier: impl Into<String>, password: impl Into<String>) -> Self {
         Self {
@@ -38,7 +39,7 @@ impl Credentials {
         }
     }
 
-    /// Returns the identifier (handle or DID).
+    /// Returns the identifier (handle, DID or email).
     pub fn identifier(&self) -> &str {
         &self.identifier
     }
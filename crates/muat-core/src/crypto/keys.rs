@@ -0,0 +1,370 @@
+//! Public keys, `did:key` and DID document verification methods.
+//!
+//! AT Protocol uses secp256k1 (K-256) and NIST P-256 keys. They are written
+//! as multibase strings (base58btc, `z` prefix), either with a multicodec
+//! prefix that names the curve (`did:key` and `Multikey`) or, in legacy DID
+//! documents, as a bare uncompressed key whose curve is given by the
+//! verification method type.
+//!
+//! # Example
+//!
+//! ```
+//! use muat_core::crypto::keys::{KeyAlgorithm, PublicKey};
+//!
+//! let key = PublicKey::from_did_key(
+//!     "did:key:zQ3shqwJEJyMBsBXCWyCBpUBMqxcon9oHB7mCvx4sSpMdLJwc",
+//! )
+//! .unwrap();
+//! assert_eq!(key.algorithm(), KeyAlgorithm::Secp256k1);
+//! assert_eq!(key.algorithm().jwt_alg(), "ES256K");
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Error, InvalidInputError};
+
+/// Verification method type for multicodec-prefixed multibase keys.
+pub const MULTIKEY_TYPE: &str = "Multikey";
+
+/// Legacy verification method type for uncompressed secp256k1 keys.
+pub const LEGACY_SECP256K1_TYPE: &str = "EcdsaSecp256k1VerificationKey2019";
+
+/// Legacy verification method type for uncompressed P-256 keys.
+pub const LEGACY_P256_TYPE: &str = "EcdsaSecp256r1VerificationKey2019";
+
+/// Length of a compressed SEC1 public key.
+const COMPRESSED_LEN: usize = 33;
+
+/// Length of an uncompressed SEC1 public key.
+const UNCOMPRESSED_LEN: usize = 65;
+
+/// The curve and signature algorithm of a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KeyAlgorithm {
+    /// secp256k1 (K-256), the usual choice for atproto repo signing keys.
+    Secp256k1,
+    /// NIST P-256.
+    P256,
+}
+
+impl KeyAlgorithm {
+    /// Returns the JWT `alg` name (`ES256K` or `ES256`).
+    pub fn jwt_alg(self) -> &'static str {
+        match self {
+            Self::Secp256k1 => "ES256K",
+            Self::P256 => "ES256",
+        }
+    }
+
+    /// Returns the varint-encoded multicodec prefix for a compressed key.
+    pub fn multicodec_prefix(self) -> [u8; 2] {
+        match self {
+            // secp256k1-pub (0xe7)
+            Self::Secp256k1 => [0xe7, 0x01],
+            // p256-pub (0x1200)
+            Self::P256 => [0x80, 0x24],
+        }
+    }
+
+    fn from_multicodec_prefix(prefix: [u8; 2]) -> Option<Self> {
+        [Self::Secp256k1, Self::P256]
+            .into_iter()
+            .find(|algorithm| algorithm.multicodec_prefix() == prefix)
+    }
+}
+
+/// A public key with a known algorithm, stored in compressed SEC1 form.
+///
+/// Parsing checks the encoding and point format but not that the point lies
+/// on the curve; signature verification does that.
+///
+/// Serializes as a `did:key` string.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PublicKey {
+    algorithm: KeyAlgorithm,
+    bytes: [u8; COMPRESSED_LEN],
+}
+
+impl PublicKey {
+    /// Create a key from SEC1 bytes, compressed (33 bytes) or uncompressed
+    /// (65 bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not a SEC1 point encoding.
+    pub fn from_bytes(algorithm: KeyAlgorithm, bytes: &[u8]) -> Result<Self, Error> {
+        let invalid = |reason: &str| invalid(&format!("{} bytes", bytes.len()), reason);
+
+        let mut compressed = [0u8; COMPRESSED_LEN];
+        match (bytes.len(), bytes.first()) {
+            (COMPRESSED_LEN, Some(0x02 | 0x03)) => compressed.copy_from_slice(bytes),
+            (UNCOMPRESSED_LEN, Some(0x04)) => {
+                // The compressed form keeps x and the parity of y.
+                compressed[0] = 0x02 | (bytes[UNCOMPRESSED_LEN - 1] & 1);
+                compressed[1..].copy_from_slice(&bytes[1..COMPRESSED_LEN]);
+            }
+            (COMPRESSED_LEN | UNCOMPRESSED_LEN, _) => {
+                return Err(invalid("unknown SEC1 point prefix"));
+            }
+            _ => {
+                return Err(invalid("expected a 33 or 65 byte SEC1 public key"));
+            }
+        }
+
+        Ok(Self {
+            algorithm,
+            bytes: compressed,
+        })
+    }
+
+    /// Parse a `did:key` (e.g. `did:key:zQ3sh...`).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the string is not a `did:key` for a supported
+    /// curve.
+    pub fn from_did_key(s: &str) -> Result<Self, Error> {
+        let multikey = s
+            .strip_prefix("did:key:")
+            .ok_or_else(|| invalid(s, "must start with 'did:key:'"))?;
+        Self::parse_multikey(s, multikey)
+    }
+
+    /// Parse a multicodec-prefixed multibase key, as found in `did:key`
+    /// identifiers and `Multikey` verification methods.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the string is not a base58btc multikey for a
+    /// supported curve.
+    pub fn from_multikey(s: &str) -> Result<Self, Error> {
+        Self::parse_multikey(s, s)
+    }
+
+    fn parse_multikey(value: &str, multikey: &str) -> Result<Self, Error> {
+        let bytes = decode_base58btc(value, multikey)?;
+        let (prefix, key) = match bytes.as_slice() {
+            [a, b, key @ ..] => ([*a, *b], key),
+            _ => return Err(invalid(value, "too short")),
+        };
+        let algorithm = KeyAlgorithm::from_multicodec_prefix(prefix)
+            .ok_or_else(|| invalid(value, "unsupported key type"))?;
+
+        if key.len() != COMPRESSED_LEN {
+            return Err(invalid(value, "expected a compressed public key"));
+        }
+        Self::from_bytes(algorithm, key)
+    }
+
+    /// Returns the key's algorithm.
+    pub fn algorithm(&self) -> KeyAlgorithm {
+        self.algorithm
+    }
+
+    /// Returns the compressed SEC1 encoding of the key.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the key as a multicodec-prefixed multibase string.
+    pub fn to_multikey(&self) -> String {
+        let mut bytes = self.algorithm.multicodec_prefix().to_vec();
+        bytes.extend_from_slice(&self.bytes);
+        format!("z{}", bs58::encode(bytes).into_string())
+    }
+
+    /// Returns the key as a `did:key`.
+    pub fn to_did_key(&self) -> String {
+        format!("did:key:{}", self.to_multikey())
+    }
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_did_key())
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_did_key(s)
+    }
+}
+
+impl TryFrom<String> for PublicKey {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::from_did_key(&s)
+    }
+}
+
+impl From<PublicKey> for String {
+    fn from(key: PublicKey) -> Self {
+        key.to_did_key()
+    }
+}
+
+/// A `verificationMethod` entry from a DID document.
+///
+/// # Example
+///
+/// ```
+/// use muat_core::crypto::keys::VerificationMethod;
+///
+/// let method: VerificationMethod = serde_json::from_value(serde_json::json!({
+///     "id": "did:plc:ewvi7nxzyoun6zhxrhs64oiz#atproto",
+///     "type": "Multikey",
+///     "controller": "did:plc:ewvi7nxzyoun6zhxrhs64oiz",
+///     "publicKeyMultibase": "zQ3shqwJEJyMBsBXCWyCBpUBMqxcon9oHB7mCvx4sSpMdLJwc"
+/// }))
+/// .unwrap();
+///
+/// assert_eq!(method.fragment(), Some("atproto"));
+/// assert!(method.public_key().is_ok());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationMethod {
+    /// Method ID, usually `<did>#<fragment>`.
+    pub id: String,
+    /// Method type, e.g. `Multikey`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// DID that controls the key.
+    pub controller: String,
+    /// The key as a multibase string.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key_multibase: Option<String>,
+}
+
+impl VerificationMethod {
+    /// Returns the part of the ID after `#`, e.g. `atproto`.
+    pub fn fragment(&self) -> Option<&str> {
+        self.id.split_once('#').map(|(_, fragment)| fragment)
+    }
+
+    /// Parse the method's public key, detecting the algorithm from the
+    /// multicodec prefix (`Multikey`) or the legacy method type.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the method has no `publicKeyMultibase`, has an
+    /// unsupported type, or the key does not parse.
+    pub fn public_key(&self) -> Result<PublicKey, Error> {
+        let multibase = self
+            .public_key_multibase
+            .as_deref()
+            .ok_or_else(|| invalid(&self.id, "missing publicKeyMultibase"))?;
+
+        let legacy = match self.kind.as_str() {
+            MULTIKEY_TYPE => return PublicKey::from_multikey(multibase),
+            LEGACY_SECP256K1_TYPE => KeyAlgorithm::Secp256k1,
+            LEGACY_P256_TYPE => KeyAlgorithm::P256,
+            other => {
+                return Err(invalid(
+                    &self.id,
+                    &format!("unsupported verification method type '{}'", other),
+                ));
+            }
+        };
+        PublicKey::from_bytes(legacy, &decode_base58btc(multibase, multibase)?)
+    }
+}
+
+fn decode_base58btc(value: &str, multibase: &str) -> Result<Vec<u8>, Error> {
+    let encoded = multibase
+        .strip_prefix('z')
+        .ok_or_else(|| invalid(value, "expected base58btc multibase ('z' prefix)"))?;
+    bs58::decode(encoded)
+        .into_vec()
+        .map_err(|_| invalid(value, "not valid base58btc"))
+}
+
+fn invalid(value: &str, reason: &str) -> Error {
+    InvalidInputError::Key {
+        value: value.to_string(),
+        reason: reason.to_string(),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const K256_DID_KEY: &str = "did:key:zQ3shqwJEJyMBsBXCWyCBpUBMqxcon9oHB7mCvx4sSpMdLJwc";
+    const P256_DID_KEY: &str = "did:key:zDnaembgSGUhZULN2Caob4HLJPaxBh92N7rtH21TErzqf8HQo";
+
+    #[test]
+    fn did_key_detects_algorithm_and_round_trips() {
+        let k256 = PublicKey::from_did_key(K256_DID_KEY).unwrap();
+        assert_eq!(k256.algorithm(), KeyAlgorithm::Secp256k1);
+        assert_eq!(k256.to_did_key(), K256_DID_KEY);
+
+        let p256: PublicKey = P256_DID_KEY.parse().unwrap();
+        assert_eq!(p256.algorithm(), KeyAlgorithm::P256);
+        assert_eq!(p256.as_bytes().len(), 33);
+        assert_eq!(p256.to_string(), P256_DID_KEY);
+    }
+
+    #[test]
+    fn invalid_did_keys() {
+        assert!(PublicKey::from_did_key("did:plc:abcaaaaaaaaaaaaaaaaaaaaa").is_err());
+        assert!(PublicKey::from_did_key("did:key:Q3shqwJEJyMB").is_err());
+        assert!(PublicKey::from_did_key("did:key:z0OIl").is_err());
+        // ed25519 (0xed) is not an atproto key type
+        assert!(
+            PublicKey::from_did_key("did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn uncompressed_bytes_are_compressed() {
+        let compressed = PublicKey::from_did_key(K256_DID_KEY).unwrap();
+        let mut uncompressed = vec![0x04];
+        uncompressed.extend_from_slice(&compressed.as_bytes()[1..]);
+        // Only the parity of y matters for compression.
+        let odd = compressed.as_bytes()[0] == 0x03;
+        uncompressed.extend(std::iter::repeat_n(0, 31));
+        uncompressed.push(u8::from(odd));
+
+        let parsed = PublicKey::from_bytes(KeyAlgorithm::Secp256k1, &uncompressed).unwrap();
+        assert_eq!(parsed, compressed);
+        assert!(PublicKey::from_bytes(KeyAlgorithm::P256, &[0x05; 33]).is_err());
+    }
+
+    #[test]
+    fn verification_method_types() {
+        let key = PublicKey::from_did_key(P256_DID_KEY).unwrap();
+        let mut method = VerificationMethod {
+            id: "did:plc:abcaaaaaaaaaaaaaaaaaaaaa#atproto".to_string(),
+            kind: MULTIKEY_TYPE.to_string(),
+            controller: "did:plc:abcaaaaaaaaaaaaaaaaaaaaa".to_string(),
+            public_key_multibase: Some(key.to_multikey()),
+        };
+        assert_eq!(method.public_key().unwrap(), key);
+
+        method.kind = LEGACY_P256_TYPE.to_string();
+        method.public_key_multibase =
+            Some(format!("z{}", bs58::encode(key.as_bytes()).into_string()));
+        assert_eq!(method.public_key().unwrap(), key);
+
+        method.kind = "JsonWebKey2020".to_string();
+        assert!(method.public_key().is_err());
+    }
+
+    #[test]
+    fn serializes_as_did_key() {
+        let key = PublicKey::from_did_key(K256_DID_KEY).unwrap();
+        let json = serde_json::to_string(&key).unwrap();
+        assert_eq!(json, format!("\"{}\"", K256_DID_KEY));
+        assert_eq!(serde_json::from_str::<PublicKey>(&json).unwrap(), key);
+    }
+}
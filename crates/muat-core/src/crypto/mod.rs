@@ -0,0 +1,6 @@
+//! Cryptographic key types.
+//!
+//! This module parses and encodes public keys. It does not sign or verify;
+//! that is left to crates that depend on curve implementations.
+
+pub mod keys;
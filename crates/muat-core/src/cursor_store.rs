@@ -0,0 +1,40 @@
+//! In-memory [`CursorStore`].
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::Result;
+use crate::traits::CursorStore;
+
+/// A [`CursorStore`] that keeps cursors in memory, for tests and
+/// short-lived processes. Clones share the same cursors.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryCursorStore {
+    cursors: Arc<Mutex<HashMap<String, i64>>>,
+}
+
+impl MemoryCursorStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn cursors(&self) -> std::sync::MutexGuard<'_, HashMap<String, i64>> {
+        // A panic while holding the lock cannot leave the map half-updated.
+        self.cursors.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl CursorStore for MemoryCursorStore {
+    async fn load(&self, host: &str) -> Result<Option<i64>> {
+        Ok(self.cursors().get(host).copied())
+    }
+
+    async fn save(&self, host: &str, cursor: i64) -> Result<()> {
+        self.cursors().insert(host.to_string(), cursor);
+        Ok(())
+    }
+}
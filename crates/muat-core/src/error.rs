@@ -4,13 +4,14 @@
 //! transport, authentication, protocol, and input validation errors.
 
 use std::fmt;
+use std::time::Duration;
 use thiserror::Error;
 
 /// The unified error type for muat operations.
 ///
 /// This error type covers all possible failure modes in the library,
 /// with explicit variants to allow callers to handle specific cases.
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum Error {
     /// Network transport errors (DNS, TLS, connection, timeout).
     #[error("transport error: {0}")]
@@ -27,10 +28,15 @@ pub enum Error {
     /// Input validation errors (invalid DID, NSID, URI format).
     #[error("invalid input: {0}")]
     InvalidInput(#[from] InvalidInputError),
+
+    /// Firehose stream errors (connecting, disconnects, bad frames, errors
+    /// sent by the server).
+    #[error("firehose error: {0}")]
+    Firehose(#[from] FirehoseError),
 }
 
 /// Transport-level errors.
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum TransportError {
     /// Network connection failed.
     #[error("connection failed: {message}")]
@@ -51,10 +57,100 @@ pub enum TransportError {
     /// Generic HTTP error.
     #[error("HTTP error: {message}")]
     Http { message: String },
+
+    /// A firehose consumer fell behind and its event buffer overflowed.
+    #[error("firehose consumer fell behind; buffer of {capacity} events overflowed")]
+    Overflow { capacity: usize },
+}
+
+/// Errors from a firehose connection, split so consumers can decide whether
+/// to reconnect, resume from their cursor, or backfill.
+#[derive(Debug, Clone, Error)]
+pub enum FirehoseError {
+    /// The connection could not be opened, or the handshake timed out.
+    #[error("could not connect: {message}")]
+    ConnectFailed {
+        message: String,
+        /// HTTP status of a rejected WebSocket upgrade, if there was one.
+        status: Option<u16>,
+    },
+
+    /// An open connection failed.
+    #[error("disconnected after {}s: {message}", after.as_secs())]
+    Disconnected {
+        /// How long the connection had been open.
+        after: Duration,
+        message: String,
+    },
+
+    /// A frame could not be decoded. The stream carries on with the next
+    /// frame.
+    #[error("could not decode {frame_kind} frame: {message}")]
+    DecodeError {
+        /// The frame's type (such as `#commit`), or its WebSocket message
+        /// kind (`binary`, `text`) when the type is unknown.
+        frame_kind: String,
+        message: String,
+    },
+
+    /// The server no longer has events back to the requested cursor (an
+    /// `OutdatedCursor` info message). The stream carries on from the
+    /// oldest event the server has, so the events before it need a
+    /// backfill; reconnecting with the same cursor will not help.
+    #[error("cursor too old{}", detail(.message))]
+    CursorTooOld { message: Option<String> },
+
+    /// A message or frame was larger than the connection's size limit. The
+    /// connection is closed; resuming from the same cursor meets the same
+    /// message, so only a higher limit gets past it.
+    #[error("message of {size} bytes exceeds the {limit} byte limit")]
+    MessageTooLarge { size: usize, limit: usize },
+
+    /// Any other error frame sent by the server, such as `FutureCursor` or
+    /// `ConsumerTooSlow`. The server closes the connection after it.
+    #[error("server error {name}{}", detail(.message))]
+    ServerError {
+        name: String,
+        message: Option<String>,
+    },
+}
+
+fn detail(message: &Option<String>) -> String {
+    match message {
+        Some(message) => format!(": {}", message),
+        None => String::new(),
+    }
+}
+
+impl FirehoseError {
+    /// Whether reconnecting, resuming from the last cursor, may succeed.
+    ///
+    /// False when the same request would fail again: a rejected upgrade
+    /// (other than 408 and 429, or a 5xx), a cursor the server cannot serve,
+    /// a message over the size limit, or a `FutureCursor` error. Decode
+    /// errors are not about the connection, which stays open.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::ConnectFailed { status, .. } => match status {
+                Some(status) => matches!(status, 408 | 429 | 500..=599),
+                None => true,
+            },
+            Self::Disconnected { .. } => true,
+            Self::DecodeError { .. } | Self::CursorTooOld { .. } | Self::MessageTooLarge { .. } => {
+                false
+            }
+            Self::ServerError { name, .. } => name != "FutureCursor",
+        }
+    }
+
+    /// Whether events were lost and must be fetched another way.
+    pub fn needs_backfill(&self) -> bool {
+        matches!(self, Self::CursorTooOld { .. })
+    }
 }
 
 /// Authentication-related errors.
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum AuthError {
     /// Invalid credentials provided.
     #[error("invalid credentials: {0}")]
@@ -71,10 +167,38 @@ pub enum AuthError {
     /// Account is suspended or deactivated.
     #[error("account unavailable: {reason}")]
     AccountUnavailable { reason: String },
+
+    /// Account is temporarily suspended by the PDS operator.
+    #[error("account suspended: {0}")]
+    AccountSuspended(String),
+
+    /// Account has been taken down by the PDS operator.
+    #[error("account taken down: {0}")]
+    AccountTakendown(String),
+
+    /// A token cannot be sent, e.g. a corrupted persisted session holds
+    /// characters not allowed in an HTTP header. The token itself is never
+    /// included.
+    #[error("malformed token: {0}")]
+    MalformedToken(String),
+
+    /// Too many attempts; the PDS refuses further logins for a while.
+    #[error("rate limited{}", retry_hint(.retry_after))]
+    RateLimited {
+        /// How long the PDS asked callers to wait, if it said.
+        retry_after: Option<Duration>,
+    },
+}
+
+fn retry_hint(retry_after: &Option<Duration>) -> String {
+    match retry_after {
+        Some(wait) => format!(", retry after {}s", wait.as_secs()),
+        None => String::new(),
+    }
 }
 
 /// Protocol-level errors from XRPC responses.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ProtocolError {
     /// HTTP status code.
     pub status: u16,
@@ -82,6 +206,8 @@ pub struct ProtocolError {
     pub error: Option<String>,
     /// Error message from the server.
     pub message: Option<String>,
+    /// Delay requested by a `Retry-After` header (if present).
+    pub retry_after: Option<Duration>,
 }
 
 impl fmt::Display for ProtocolError {
@@ -106,9 +232,32 @@ impl ProtocolError {
             status,
             error,
             message,
+            retry_after: None,
         }
     }
 
+    /// Attach the delay from a `Retry-After` header.
+    pub fn with_retry_after(mut self, retry_after: Option<Duration>) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// Check if the server is rate limiting requests (HTTP 429).
+    pub fn is_rate_limited(&self) -> bool {
+        self.status == 429
+    }
+
+    /// Check if a swap CID did not match the record's current CID.
+    pub fn is_invalid_swap(&self) -> bool {
+        self.error.as_deref() == Some("InvalidSwap")
+    }
+
+    /// Check if the requested record or repo does not exist: a 404, or a
+    /// `RecordNotFound` error, which the reference PDS sends with a 400.
+    pub fn is_not_found(&self) -> bool {
+        self.status == 404 || self.error.as_deref() == Some("RecordNotFound")
+    }
+
     /// Check if this is an authentication error.
     pub fn is_auth_error(&self) -> bool {
         self.status == 401
@@ -119,12 +268,16 @@ impl ProtocolError {
 }
 
 /// Input validation errors.
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum InvalidInputError {
     /// Invalid DID format.
     #[error("invalid DID '{value}': {reason}")]
     Did { value: String, reason: String },
 
+    /// Invalid handle format, or a handle outside a PDS's domains.
+    #[error("invalid handle '{value}': {reason}")]
+    Handle { value: String, reason: String },
+
     /// Invalid NSID format.
     #[error("invalid NSID '{value}': {reason}")]
     Nsid { value: String, reason: String },
@@ -141,15 +294,47 @@ pub enum InvalidInputError {
     #[error("invalid rkey '{value}': {reason}")]
     Rkey { value: String, reason: String },
 
+    /// Invalid TID format.
+    #[error("invalid TID '{value}': {reason}")]
+    Tid { value: String, reason: String },
+
     /// Invalid CID format.
     #[error("invalid CID '{value}': {reason}")]
     Cid { value: String, reason: String },
 
+    /// Invalid public key or `did:key`.
+    #[error("invalid key '{value}': {reason}")]
+    Key { value: String, reason: String },
+
     /// Invalid record value (missing $type, wrong type, etc.)
     #[error("invalid record value: {reason}")]
     RecordValue { reason: String },
 
+    /// Malformed CAR data, or a block that does not match its CID.
+    #[error("invalid CAR: {reason}")]
+    Car { reason: String },
+
     /// Generic invalid input.
     #[error("invalid input: {message}")]
     Other { message: String },
 }
+
+impl InvalidInputError {
+    /// Returns the reason text, without the offending value.
+    pub fn reason(&self) -> &str {
+        match self {
+            Self::Did { reason, .. }
+            | Self::Handle { reason, .. }
+            | Self::Nsid { reason, .. }
+            | Self::AtUri { reason, .. }
+            | Self::PdsUrl { reason, .. }
+            | Self::Rkey { reason, .. }
+            | Self::Tid { reason, .. }
+            | Self::Cid { reason, .. }
+            | Self::Key { reason, .. }
+            | Self::RecordValue { reason }
+            | Self::Car { reason } => reason,
+            Self::Other { message } => message,
+        }
+    }
+}
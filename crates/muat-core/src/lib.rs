@@ -1,21 +1,36 @@
 //! muat-core - Core AT Protocol types and traits.
 
 pub mod credentials;
+pub mod crypto;
+pub mod cursor_store;
 pub mod error;
 pub mod repo;
+pub mod session_store;
+pub mod sync;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod tokens;
 pub mod traits;
 pub mod types;
 
 pub use credentials::Credentials;
+pub use cursor_store::MemoryCursorStore;
 pub use error::Error;
 pub use repo::{
-    CommitEvent, CommitOperation, HandleEvent, IdentityEvent, InfoEvent, Record, RecordValue,
-    RepoEvent,
+    Backfiller, BlobRef, BulkReport, ByteRange, CommitEvent, CommitOperation,
+    DeleteCollectionOptions, DeletionReport, EventStats, FirehoseProcessor, FirehoseStats,
+    GapDetected, HandleEvent, IdentityEvent, InfoEvent, LabelEvent, ListRecordsOptions,
+    MigrateOptions, MigrationReport, Record, RecordOrder, RecordValue, RepoEvent, TopicMapping,
 };
+pub use session_store::MemorySessionStore;
 pub use tokens::{AccessToken, RefreshToken};
-pub use traits::{CreateAccountOutput, Firehose, Pds, Session};
-pub use types::{AtUri, Did, Nsid, PdsUrl, Rkey};
+pub use tokio_util::sync::CancellationToken;
+pub use traits::{
+    AccountStatus, BlobStore, Cancellable, CreateAccountOutput, CursorStore, EventSink, Firehose,
+    FirehoseExt, LabelStream, Lexicon, Pds, Sequenced, ServerDescription, Session, SessionStore,
+    StoredSession,
+};
+pub use types::{AtUri, Cid, Did, Handle, Nsid, PdsUrl, Rkey, Tid, TidGenerator};
 
 /// Result type alias using the crate's Error type.
 pub type Result<T> = std::result::Result<T, Error>;
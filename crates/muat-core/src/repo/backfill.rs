@@ -0,0 +1,178 @@
+//! Backfilling repos as synthetic firehose events.
+
+use std::collections::VecDeque;
+
+use chrono::Utc;
+use futures_core::Stream;
+use futures_util::stream;
+
+use crate::Result;
+use crate::repo::{CommitEvent, CommitOperation, ListRecordsOptions, RepoEvent};
+use crate::traits::Session;
+use crate::types::{Did, Nsid, Tid, TidGenerator};
+
+/// Reads the records already in repos and emits them as firehose commits,
+/// so an indexer can load history through the same code that handles live
+/// events.
+///
+/// Records are read with `listRecords`, one collection at a time, and each
+/// becomes a [`CommitEvent`] with a single `create` operation. The
+/// collections to read are given up front; use
+/// [`Session::list_collections`] to find a repo's.
+///
+/// Synthetic commits are told apart from live ones by their sequence number,
+/// which is always 0, so they never move a saved cursor. Their `rev` is a
+/// TID taken when the page was read, later than the repo revision the record
+/// came from, and their `since` is the `since_rev` passed to
+/// [`run`](Self::run).
+///
+/// # Example
+///
+/// ```ignore
+/// use futures_util::StreamExt;
+///
+/// let backfill = Backfiller::new(&session, &[Nsid::new("app.bsky.feed.post")?]);
+/// // History first, then live events, through one handler.
+/// let mut events = backfill.run(&dids, None).chain(pds.firehose_from(cursor)?);
+/// while let Some(event) = events.next().await {
+///     index(event?).await;
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Backfiller<'a, S: ?Sized> {
+    session: &'a S,
+    collections: Vec<Nsid>,
+    page_size: Option<u32>,
+}
+
+impl<'a, S: Session + ?Sized> Backfiller<'a, S> {
+    /// Backfill `collections` using `session` to read repos.
+    pub fn new(session: &'a S, collections: &[Nsid]) -> Self {
+        Self {
+            session,
+            collections: collections.to_vec(),
+            page_size: None,
+        }
+    }
+
+    /// Set the number of records fetched per page.
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+
+    /// Stream the records of each repo in `dids`, in order, as commits.
+    ///
+    /// With `since_rev`, only records whose TID record key is at or after
+    /// that revision's time are read: those created since then. Records
+    /// with other keys, and updates to older records, are not visible this
+    /// way.
+    ///
+    /// A repo stops at its first error, which is yielded, and the stream
+    /// moves on to the next repo.
+    pub fn run(
+        &self,
+        dids: &[Did],
+        since_rev: Option<&Tid>,
+    ) -> impl Stream<Item = Result<RepoEvent>> + Send + 'a {
+        let mut options = ListRecordsOptions::new();
+        if let Some(page_size) = self.page_size {
+            options = options.limit(page_size);
+        }
+        if let Some(rev) = since_rev {
+            options = options.since(rev.timestamp());
+        }
+        let jobs = dids
+            .iter()
+            .flat_map(|did| {
+                self.collections
+                    .iter()
+                    .map(move |collection| (did.clone(), collection.clone()))
+            })
+            .collect();
+        let progress = Progress {
+            session: self.session,
+            jobs,
+            options,
+            cursor: None,
+            since: since_rev.map(ToString::to_string),
+            revs: TidGenerator::new(),
+            ready: VecDeque::new(),
+        };
+        stream::unfold(progress, |mut progress| async move {
+            let event = progress.next().await?;
+            Some((event, progress))
+        })
+    }
+}
+
+/// Where a backfill has got to.
+struct Progress<'a, S: ?Sized> {
+    session: &'a S,
+    /// Collections still to read, each with its repo.
+    jobs: VecDeque<(Did, Nsid)>,
+    options: ListRecordsOptions,
+    /// Cursor into the first job's collection.
+    cursor: Option<String>,
+    since: Option<String>,
+    revs: TidGenerator,
+    /// Events read but not yet yielded.
+    ready: VecDeque<Result<RepoEvent>>,
+}
+
+impl<S: Session + ?Sized> Progress<'_, S> {
+    async fn next(&mut self) -> Option<Result<RepoEvent>> {
+        loop {
+            if let Some(event) = self.ready.pop_front() {
+                return Some(event);
+            }
+            let (did, collection) = self.jobs.front()?.clone();
+            let mut options = self.options.clone();
+            if let Some(cursor) = &self.cursor {
+                options = options.cursor(cursor.clone());
+            }
+            let page = match self
+                .session
+                .list_records_with(&did, &collection, &options)
+                .await
+            {
+                Ok(page) => page,
+                Err(e) => {
+                    self.jobs.retain(|(repo, _)| *repo != did);
+                    self.cursor = None;
+                    return Some(Err(e));
+                }
+            };
+
+            let rev = self.revs.next_tid().to_string();
+            let time = Utc::now();
+            let done =
+                page.records.is_empty() || page.cursor.is_none() || page.cursor == self.cursor;
+            for record in page.records {
+                let Ok((_, rkey)) = record.uri.record_path() else {
+                    continue;
+                };
+                self.ready.push_back(Ok(RepoEvent::Commit(CommitEvent {
+                    repo: did.to_string(),
+                    rev: rev.clone(),
+                    since: self.since.clone(),
+                    seq: 0,
+                    time,
+                    ops: vec![CommitOperation {
+                        path: format!("{}/{}", collection, rkey),
+                        action: "create".to_string(),
+                        cid: Some(record.cid),
+                    }],
+                    blocks: Vec::new(),
+                    blobs: Vec::new(),
+                })));
+            }
+            if done {
+                self.jobs.pop_front();
+                self.cursor = None;
+            } else {
+                self.cursor = page.cursor;
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Blob listing and range types.
+
+use crate::Result;
+use crate::error::ProtocolError;
+
+/// Output from listing blobs.
+#[derive(Debug, Clone)]
+pub struct ListBlobsOutput {
+    /// Blob CIDs in this page.
+    pub cids: Vec<String>,
+
+    /// Cursor for the next page, if more blobs exist.
+    pub cursor: Option<String>,
+}
+
+/// A byte range within a blob, for resuming interrupted downloads.
+///
+/// Reads `length` bytes from `offset`, or to the end of the blob when
+/// `length` is `None`. A range that runs past the end is cut short.
+///
+/// # Example
+///
+/// ```
+/// use muat_core::repo::ByteRange;
+///
+/// let range = ByteRange::new(2, Some(3));
+/// assert_eq!(range.slice(b"abcdefg".to_vec()).unwrap(), b"cde");
+/// assert_eq!(ByteRange::from_offset(5).slice(b"abcdefg".to_vec()).unwrap(), b"fg");
+/// assert!(ByteRange::from_offset(8).slice(b"abcdefg".to_vec()).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ByteRange {
+    /// First byte to read.
+    pub offset: u64,
+    /// Number of bytes to read, or `None` for the rest of the blob.
+    pub length: Option<u64>,
+}
+
+impl ByteRange {
+    /// Create a range of `length` bytes starting at `offset`.
+    pub fn new(offset: u64, length: Option<u64>) -> Self {
+        Self { offset, length }
+    }
+
+    /// Create a range from `offset` to the end of the blob.
+    pub fn from_offset(offset: u64) -> Self {
+        Self::new(offset, None)
+    }
+
+    /// Returns the start and end (exclusive) of this range in a blob of
+    /// `size` bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns a 416 `InvalidRange` protocol error if `offset` is past the
+    /// end. An offset equal to `size` is an empty range, so a download that
+    /// already finished resumes cleanly.
+    pub fn bounds(&self, size: u64) -> Result<(u64, u64)> {
+        if self.offset > size {
+            return Err(ProtocolError::new(
+                416,
+                Some("InvalidRange".to_string()),
+                Some(format!(
+                    "offset {} is past the end of a {} byte blob",
+                    self.offset, size
+                )),
+            )
+            .into());
+        }
+        let end = match self.length {
+            Some(length) => self.offset.saturating_add(length).min(size),
+            None => size,
+        };
+        Ok((self.offset, end))
+    }
+
+    /// Cut this range out of a whole blob.
+    ///
+    /// # Errors
+    ///
+    /// As for [`bounds`](Self::bounds).
+    pub fn slice(&self, mut data: Vec<u8>) -> Result<Vec<u8>> {
+        let (start, end) = self.bounds(data.len() as u64)?;
+        data.truncate(end as usize);
+        data.drain(..start as usize);
+        Ok(data)
+    }
+}
@@ -0,0 +1,308 @@
+//! Buffering between a firehose reader and its consumer.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use futures_core::Stream;
+use futures_util::stream;
+use tokio::sync::Notify;
+
+use super::events::RepoEvent;
+use super::stats::FirehoseStats;
+use crate::Result;
+use crate::error::{Error, TransportError};
+
+/// What a firehose does when its consumer falls behind and the buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Stop reading from the source until the consumer catches up. Nothing
+    /// is lost, but a server may disconnect a reader that stalls for long.
+    #[default]
+    Block,
+    /// Discard the oldest buffered event to make room. Discarded events are
+    /// counted in [`EventStats::dropped_events`](super::EventStats::dropped_events).
+    DropOldest,
+    /// Deliver what is buffered, then end the stream with
+    /// [`TransportError::Overflow`].
+    Error,
+}
+
+/// Capacity and overflow policy of a firehose's event buffer.
+///
+/// # Example
+///
+/// ```
+/// use muat_core::repo::{FirehoseBuffer, OverflowPolicy};
+///
+/// let buffer = FirehoseBuffer::new(10_000).overflow(OverflowPolicy::DropOldest);
+/// assert_eq!(buffer.capacity(), 10_000);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FirehoseBuffer {
+    capacity: usize,
+    overflow: OverflowPolicy,
+}
+
+impl Default for FirehoseBuffer {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+impl FirehoseBuffer {
+    /// Events buffered by default.
+    pub const DEFAULT_CAPACITY: usize = 100;
+
+    /// A buffer of `capacity` events (at least 1) that blocks when full.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            overflow: OverflowPolicy::default(),
+        }
+    }
+
+    /// Set what happens when the buffer is full.
+    pub fn overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Maximum number of buffered events.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// What happens when the buffer is full.
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow
+    }
+
+    /// Create a channel with this buffer, for firehose backends.
+    ///
+    /// The channel keeps the lag and dropped-event figures in `stats`.
+    pub fn channel(&self, stats: FirehoseStats) -> (EventSender, EventReceiver) {
+        let shared = Arc::new(Shared {
+            buffer: *self,
+            stats,
+            state: Mutex::new(State::default()),
+            items: Notify::new(),
+            space: Notify::new(),
+        });
+        (
+            EventSender {
+                shared: shared.clone(),
+            },
+            EventReceiver { shared },
+        )
+    }
+}
+
+struct Shared {
+    buffer: FirehoseBuffer,
+    stats: FirehoseStats,
+    state: Mutex<State>,
+    /// Wakes the receiver when an item arrives or the sender goes away.
+    items: Notify,
+    /// Wakes the sender when space frees up or the receiver goes away.
+    space: Notify,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[derive(Default)]
+struct State {
+    queue: VecDeque<Result<RepoEvent>>,
+    sender_gone: bool,
+    receiver_gone: bool,
+    overflowed: bool,
+    finished: bool,
+}
+
+/// Sending half of a [`FirehoseBuffer::channel`], held by the reader task.
+pub struct EventSender {
+    shared: Arc<Shared>,
+}
+
+impl EventSender {
+    /// Buffer an event, applying the overflow policy when full.
+    ///
+    /// Returns `false` once the stream has ended (the consumer dropped it,
+    /// or it overflowed under [`OverflowPolicy::Error`]), after which the
+    /// reader should stop.
+    pub async fn send(&self, event: Result<RepoEvent>) -> bool {
+        let shared = &self.shared;
+        let mut event = Some(event);
+        loop {
+            let space = shared.space.notified();
+            {
+                let mut state = shared.lock();
+                if state.receiver_gone || state.overflowed {
+                    return false;
+                }
+                if state.queue.len() >= shared.buffer.capacity {
+                    match shared.buffer.overflow {
+                        OverflowPolicy::Block => {}
+                        OverflowPolicy::DropOldest => {
+                            state.queue.pop_front();
+                            shared.stats.add_dropped(1);
+                        }
+                        OverflowPolicy::Error => {
+                            state.overflowed = true;
+                            drop(state);
+                            shared.items.notify_one();
+                            return false;
+                        }
+                    }
+                }
+                if state.queue.len() < shared.buffer.capacity
+                    && let Some(event) = event.take()
+                {
+                    state.queue.push_back(event);
+                    shared.stats.set_lag(state.queue.len());
+                    drop(state);
+                    shared.items.notify_one();
+                    return true;
+                }
+            }
+            space.await;
+        }
+    }
+}
+
+impl Drop for EventSender {
+    fn drop(&mut self) {
+        self.shared.lock().sender_gone = true;
+        self.shared.items.notify_one();
+    }
+}
+
+/// Receiving half of a [`FirehoseBuffer::channel`], read by the consumer.
+pub struct EventReceiver {
+    shared: Arc<Shared>,
+}
+
+impl EventReceiver {
+    /// Take the next event, or `None` once the stream has ended.
+    pub async fn recv(&mut self) -> Option<Result<RepoEvent>> {
+        let shared = &self.shared;
+        loop {
+            let items = shared.items.notified();
+            {
+                let mut state = shared.lock();
+                if let Some(event) = state.queue.pop_front() {
+                    shared.stats.set_lag(state.queue.len());
+                    drop(state);
+                    shared.space.notify_one();
+                    return Some(event);
+                }
+                if state.finished {
+                    return None;
+                }
+                if state.overflowed {
+                    state.finished = true;
+                    return Some(Err(Error::Transport(TransportError::Overflow {
+                        capacity: shared.buffer.capacity,
+                    })));
+                }
+                if state.sender_gone {
+                    return None;
+                }
+            }
+            items.await;
+        }
+    }
+
+    /// Turn the receiver into a stream of events.
+    pub fn into_stream(self) -> impl Stream<Item = Result<RepoEvent>> + Send + 'static {
+        stream::unfold(self, |mut rx| async move {
+            rx.recv().await.map(|event| (event, rx))
+        })
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        self.shared.lock().receiver_gone = true;
+        self.shared.space.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::InfoEvent;
+
+    fn info(name: &str) -> Result<RepoEvent> {
+        Ok(RepoEvent::Info(InfoEvent {
+            name: name.to_string(),
+            message: None,
+        }))
+    }
+
+    fn name(event: Option<Result<RepoEvent>>) -> String {
+        match event {
+            Some(Ok(RepoEvent::Info(info))) => info.name,
+            other => panic!("expected an info event, got {:?}", other),
+        }
+    }
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let mut cx = std::task::Context::from_waker(std::task::Waker::noop());
+        loop {
+            if let std::task::Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn drop_oldest_keeps_newest_events() {
+        let stats = FirehoseStats::new();
+        let buffer = FirehoseBuffer::new(2).overflow(OverflowPolicy::DropOldest);
+        let (tx, mut rx) = buffer.channel(stats.clone());
+
+        for n in ["a", "b", "c"] {
+            assert!(block_on(tx.send(info(n))));
+        }
+        assert_eq!(stats.lag(), 2);
+        assert_eq!(stats.dropped_events(), 1);
+
+        assert_eq!(name(block_on(rx.recv())), "b");
+        assert_eq!(name(block_on(rx.recv())), "c");
+        assert_eq!(stats.lag(), 0);
+        drop(tx);
+        assert!(block_on(rx.recv()).is_none());
+    }
+
+    #[test]
+    fn error_policy_ends_the_stream() {
+        let stats = FirehoseStats::new();
+        let buffer = FirehoseBuffer::new(1).overflow(OverflowPolicy::Error);
+        let (tx, mut rx) = buffer.channel(stats);
+
+        assert!(block_on(tx.send(info("a"))));
+        assert!(!block_on(tx.send(info("b"))));
+
+        assert_eq!(name(block_on(rx.recv())), "a");
+        assert!(matches!(
+            block_on(rx.recv()),
+            Some(Err(Error::Transport(TransportError::Overflow {
+                capacity: 1
+            })))
+        ));
+        assert!(block_on(rx.recv()).is_none());
+    }
+
+    #[test]
+    fn send_stops_when_receiver_is_dropped() {
+        let (tx, rx) = FirehoseBuffer::new(1).channel(FirehoseStats::new());
+        assert!(block_on(tx.send(info("a"))));
+        drop(rx);
+        assert!(!block_on(tx.send(info("b"))));
+    }
+}
@@ -0,0 +1,100 @@
+//! Bulk operation results.
+
+use crate::Result;
+use crate::error::Error;
+
+/// Outcome of an operation applied to many inputs.
+///
+/// Items are independent: a failed item is recorded with its input and the
+/// rest still run. Each list keeps input order.
+#[derive(Debug, Clone)]
+pub struct BulkReport<I, O> {
+    /// Outputs of the items that succeeded.
+    pub succeeded: Vec<O>,
+    /// Inputs whose operation failed, with the error.
+    pub failed: Vec<(I, Error)>,
+    /// Inputs never attempted because the operation was cancelled.
+    pub skipped: Vec<I>,
+}
+
+impl<I, O> BulkReport<I, O> {
+    /// Create an empty report.
+    pub fn new() -> Self {
+        Self {
+            succeeded: Vec::new(),
+            failed: Vec::new(),
+            skipped: Vec::new(),
+        }
+    }
+
+    /// Pair inputs with their results, in order.
+    ///
+    /// Inputs beyond the end of `results` are recorded as skipped.
+    pub fn from_results(
+        inputs: impl IntoIterator<Item = I>,
+        results: impl IntoIterator<Item = Result<O>>,
+    ) -> Self {
+        let mut report = Self::new();
+        let mut results = results.into_iter();
+        for input in inputs {
+            match results.next() {
+                Some(Ok(output)) => report.succeeded.push(output),
+                Some(Err(e)) => report.failed.push((input, e)),
+                None => report.skipped.push(input),
+            }
+        }
+        report
+    }
+
+    /// Total number of inputs.
+    pub fn len(&self) -> usize {
+        self.succeeded.len() + self.failed.len() + self.skipped.len()
+    }
+
+    /// Returns true if there were no inputs.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if every input was attempted and succeeded.
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty() && self.skipped.is_empty()
+    }
+}
+
+impl<I, O> Default for BulkReport<I, O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::InvalidInputError;
+
+    fn failure() -> Error {
+        InvalidInputError::Other {
+            message: "bad".to_string(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn pairs_inputs_with_results() {
+        let report = BulkReport::from_results(["a", "b", "c", "d"], [Ok(1), Err(failure()), Ok(3)]);
+        assert_eq!(report.succeeded, [1, 3]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "b");
+        assert_eq!(report.skipped, ["d"]);
+        assert_eq!(report.len(), 4);
+        assert!(!report.is_complete());
+    }
+
+    #[test]
+    fn empty_report_is_complete() {
+        let report: BulkReport<&str, ()> = BulkReport::from_results([], []);
+        assert!(report.is_empty());
+        assert!(report.is_complete());
+    }
+}
@@ -0,0 +1,278 @@
+//! CAR (content-addressed archive) files, as the sync endpoints return
+//! repos and blocks.
+//!
+//! A CARv1 file is a varint-prefixed DAG-CBOR header naming its root CIDs,
+//! followed by sections of a varint length, a binary CID and the block's
+//! bytes.
+
+use std::fmt;
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::Result;
+use crate::error::{Error, InvalidInputError};
+use crate::repo::from_dag_cbor;
+use crate::types::{Cid, read_varint};
+
+/// A CARv1 file: root CIDs and the blocks that follow them.
+///
+/// The header is checked when the file is parsed; blocks are read lazily by
+/// [`blocks`](Self::blocks), which checks each one against its CID.
+///
+/// # Example
+///
+/// ```ignore
+/// let car = pds.get_repo_since(&did, Some(&rev)).await?;
+/// for block in car.blocks() {
+///     let block = block?;
+///     println!("{} {}", block.cid, block.decode()?);
+/// }
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Car {
+    bytes: Vec<u8>,
+    roots: Vec<Cid>,
+    /// Offset of the first block section.
+    body: usize,
+}
+
+impl fmt::Debug for Car {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Car")
+            .field("roots", &self.roots)
+            .field("len", &self.bytes.len())
+            .finish()
+    }
+}
+
+/// A block in a [`Car`]: content and the CID it is stored under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CarBlock<'a> {
+    /// The CID of the block, checked against its content.
+    pub cid: Cid,
+    /// The block's bytes; DAG-CBOR for records, commits and MST nodes.
+    pub data: &'a [u8],
+}
+
+impl Car {
+    /// Parse a CARv1 file, reading its header.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the header is truncated, is not DAG-CBOR, or is not a
+    /// version 1 header with a list of root CIDs.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self> {
+        let mut rest = bytes.as_slice();
+        let header = read_section(&mut rest)?.ok_or_else(|| invalid("empty CAR file"))?;
+        let header = from_dag_cbor(header)?;
+
+        if header.get("version").and_then(Value::as_u64) != Some(1) {
+            return Err(invalid("only CAR version 1 is supported"));
+        }
+        let roots = header
+            .get("roots")
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid("header has no roots"))?
+            .iter()
+            .map(|root| match root.get("$link").and_then(Value::as_str) {
+                Some(link) => Cid::new(link),
+                None => Err(invalid("roots must be CID links")),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let body = bytes.len() - rest.len();
+        Ok(Self { bytes, roots, body })
+    }
+
+    /// Returns the root CIDs named in the header, such as a repo's latest
+    /// commit.
+    pub fn roots(&self) -> &[Cid] {
+        &self.roots
+    }
+
+    /// Iterate over the blocks, in the order they are stored.
+    ///
+    /// Each block's SHA-256 digest is checked against its CID. The iterator
+    /// ends after the first error.
+    pub fn blocks(&self) -> Blocks<'_> {
+        Blocks {
+            rest: &self.bytes[self.body..],
+        }
+    }
+
+    /// Find the block stored under `cid`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a block before it is malformed or does not match its CID.
+    pub fn get(&self, cid: &Cid) -> Result<Option<CarBlock<'_>>> {
+        for block in self.blocks() {
+            let block = block?;
+            if &block.cid == cid {
+                return Ok(Some(block));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the file's bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the file's bytes, e.g. to save it.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl TryFrom<Vec<u8>> for Car {
+    type Error = Error;
+
+    fn try_from(bytes: Vec<u8>) -> Result<Self> {
+        Self::from_bytes(bytes)
+    }
+}
+
+impl CarBlock<'_> {
+    /// Decode the block as DAG-CBOR.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the block is not DAG-CBOR, such as a raw blob.
+    pub fn decode(&self) -> Result<Value> {
+        if self.cid.codec() != Cid::DAG_CBOR {
+            return Err(invalid(format!("block {} is not DAG-CBOR", self.cid)));
+        }
+        from_dag_cbor(self.data)
+    }
+}
+
+/// Iterator over the blocks of a [`Car`], from [`Car::blocks`].
+#[derive(Debug, Clone)]
+pub struct Blocks<'a> {
+    rest: &'a [u8],
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = Result<CarBlock<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let block = read_block(&mut self.rest);
+        if block.is_err() {
+            self.rest = &[];
+        }
+        block.transpose()
+    }
+}
+
+fn read_block<'a>(rest: &mut &'a [u8]) -> Result<Option<CarBlock<'a>>> {
+    let Some(mut section) = read_section(rest)? else {
+        return Ok(None);
+    };
+    let cid = Cid::read_prefix(&mut section)?;
+    if cid.hash_code() != Cid::SHA2_256 {
+        return Err(invalid(format!("block {} is not hashed with SHA-256", cid)));
+    }
+    if !cid.to_bytes().ends_with(&Sha256::digest(section)) {
+        return Err(invalid(format!("block {} does not match its CID", cid)));
+    }
+    Ok(Some(CarBlock { cid, data: section }))
+}
+
+/// Read a varint-prefixed section, or `None` at the end of the file.
+fn read_section<'a>(rest: &mut &'a [u8]) -> Result<Option<&'a [u8]>> {
+    if rest.is_empty() {
+        return Ok(None);
+    }
+    let len = read_varint(rest).ok_or_else(|| invalid("truncated section length"))?;
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|len| *len <= rest.len())
+        .ok_or_else(|| invalid("truncated section"))?;
+    let (section, remainder) = rest.split_at(len);
+    *rest = remainder;
+    Ok(Some(section))
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    InvalidInputError::Car {
+        reason: reason.into(),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::repo::{dag_cbor_cid, to_dag_cbor};
+
+    fn section(out: &mut Vec<u8>, parts: &[&[u8]]) {
+        let len: usize = parts.iter().map(|part| part.len()).sum();
+        out.push(len as u8);
+        for part in parts {
+            out.extend_from_slice(part);
+        }
+    }
+
+    fn car(roots: &[&Cid], blocks: &[(&Cid, &[u8])]) -> Vec<u8> {
+        let roots: Vec<_> = roots.iter().map(|cid| json!({ "$link": cid })).collect();
+        let header = to_dag_cbor(&json!({ "version": 1, "roots": roots })).unwrap();
+        let mut out = Vec::new();
+        section(&mut out, &[&header]);
+        for (cid, data) in blocks {
+            section(&mut out, &[&cid.to_bytes(), data]);
+        }
+        out
+    }
+
+    #[test]
+    fn reads_roots_and_blocks() {
+        let record = to_dag_cbor(&json!({ "text": "hello" })).unwrap();
+        let cid = dag_cbor_cid(&record);
+        let blob = b"raw bytes";
+        let blob_cid = Cid::v1(Cid::RAW, Cid::SHA2_256, &Sha256::digest(blob));
+
+        let car = Car::from_bytes(car(&[&cid], &[(&cid, &record), (&blob_cid, blob)])).unwrap();
+        assert_eq!(car.roots(), std::slice::from_ref(&cid));
+
+        let blocks: Vec<_> = car.blocks().collect::<Result<_>>().unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].decode().unwrap(), json!({ "text": "hello" }));
+        assert_eq!(blocks[1].data, blob);
+        assert!(blocks[1].decode().is_err());
+
+        assert_eq!(car.get(&blob_cid).unwrap().unwrap().cid, blob_cid);
+        let missing = dag_cbor_cid(b"other");
+        assert!(car.get(&missing).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_blocks_that_do_not_match_their_cid() {
+        let record = to_dag_cbor(&json!({ "text": "hello" })).unwrap();
+        let wrong = dag_cbor_cid(b"something else");
+        let car = Car::from_bytes(car(&[], &[(&wrong, &record), (&wrong, &record)])).unwrap();
+
+        let results: Vec<_> = car.blocks().collect();
+        assert_eq!(results.len(), 1);
+        let err = results[0].as_ref().unwrap_err();
+        assert!(err.to_string().contains("does not match"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        assert!(Car::from_bytes(Vec::new()).is_err());
+        let mut bytes = Vec::new();
+        let header = to_dag_cbor(&json!({ "version": 2, "roots": [] })).unwrap();
+        section(&mut bytes, &[&header]);
+        let err = Car::from_bytes(bytes).unwrap_err();
+        assert!(err.to_string().contains("version 1"), "{}", err);
+
+        let mut truncated = car(&[], &[]);
+        truncated.pop();
+        assert!(Car::from_bytes(truncated).is_err());
+    }
+}
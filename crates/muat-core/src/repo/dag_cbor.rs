@@ -0,0 +1,618 @@
+//! Canonical DAG-CBOR encoding, for record CIDs and signatures, and
+//! decoding, for blocks fetched from sync endpoints.
+//!
+//! Values are JSON in the AT Protocol data model. The encoding follows the
+//! DAG-CBOR rules atproto requires: map keys sorted shorter first, then
+//! bytewise; integers and lengths in their shortest form; no floats.
+//! `{"$link": cid}` objects become CID links (tag 42) and
+//! `{"$bytes": base64}` objects become byte strings, and back again when
+//! decoding.
+
+use std::cmp::Ordering;
+
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, InvalidInputError};
+use crate::types::Cid;
+
+/// CBOR tag for a CID link.
+const CID_TAG: u64 = 42;
+
+/// How deeply decoded values may nest, so hostile input cannot exhaust the
+/// stack.
+const MAX_DEPTH: usize = 128;
+
+/// Standard base64 alphabet.
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Order of map keys when a record is written out as JSON.
+///
+/// CIDs do not depend on it: they hash the DAG-CBOR encoding, which always
+/// uses the canonical order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyOrder {
+    /// Keys sorted bytewise, as `serde_json` writes them.
+    #[default]
+    Sorted,
+    /// Shorter keys first, then bytewise: the DAG-CBOR order.
+    Canonical,
+}
+
+/// Encode a JSON value in the atproto data model as canonical DAG-CBOR.
+///
+/// # Errors
+///
+/// Fails on floats, which the data model does not allow, and on `$link` or
+/// `$bytes` objects whose value is not a CID or base64 string.
+pub fn to_dag_cbor(value: &Value) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::new();
+    encode(value, &mut out)?;
+    Ok(out)
+}
+
+/// Decode DAG-CBOR into a JSON value in the atproto data model, the
+/// inverse of [`to_dag_cbor`].
+///
+/// CID links become `{"$link": cid}` objects and byte strings become
+/// `{"$bytes": base64}` objects, unpadded as atproto writes them.
+///
+/// # Errors
+///
+/// Fails on truncated input or bytes after the value, and on CBOR that
+/// DAG-CBOR does not allow: floats, indefinite lengths, tags other than
+/// CID links, and map keys that are not strings or repeat.
+pub fn from_dag_cbor(bytes: &[u8]) -> Result<Value, Error> {
+    let mut rest = bytes;
+    let value = read_dag_cbor(&mut rest)?;
+    if !rest.is_empty() {
+        return Err(invalid(format!(
+            "{} bytes after the DAG-CBOR value",
+            rest.len()
+        )));
+    }
+    Ok(value)
+}
+
+/// Decode the DAG-CBOR value at the front of `bytes`, leaving `bytes` at
+/// what follows it.
+///
+/// Event stream frames are two values back to back, a header and a body;
+/// this reads the header. Values are decoded as by [`from_dag_cbor`].
+///
+/// # Errors
+///
+/// Fails as [`from_dag_cbor`] does, except that bytes after the value are
+/// left to the caller.
+pub fn read_dag_cbor(bytes: &mut &[u8]) -> Result<Value, Error> {
+    decode(bytes, 0, &mut |data| {
+        let mut map = Map::new();
+        map.insert("$bytes".to_string(), Value::String(encode_base64(data)));
+        Value::Object(map)
+    })
+}
+
+/// Decode the DAG-CBOR value at the front of `bytes` as [`read_dag_cbor`]
+/// does, but leave byte strings as they are rather than base64-encoding
+/// them.
+///
+/// Each byte string becomes `{"$bytes": n}`, where `n` is its index in the
+/// returned slices, which borrow from `bytes`. Event streams read frame
+/// bodies this way, so a commit's `blocks` are never copied into base64 and
+/// back.
+///
+/// # Errors
+///
+/// Fails as [`read_dag_cbor`] does.
+pub fn read_dag_cbor_raw<'a>(bytes: &mut &'a [u8]) -> Result<(Value, Vec<&'a [u8]>), Error> {
+    let mut slices = Vec::new();
+    let value = decode(bytes, 0, &mut |data| {
+        let mut map = Map::new();
+        map.insert("$bytes".to_string(), Value::from(slices.len()));
+        slices.push(data);
+        Value::Object(map)
+    })?;
+    Ok((value, slices))
+}
+
+/// The bytes of a `{"$bytes": base64}` object, as [`from_dag_cbor`]
+/// decodes byte strings.
+pub fn dag_cbor_bytes(value: &Value) -> Option<Vec<u8>> {
+    decode_base64(only_key(value.as_object()?, "$bytes")?.as_str()?)
+}
+
+/// The CID of DAG-CBOR bytes: CIDv1 with the `dag-cbor` codec and a SHA-256
+/// multihash, as atproto uses for records and commits.
+pub fn dag_cbor_cid(bytes: &[u8]) -> Cid {
+    Cid::v1(Cid::DAG_CBOR, Cid::SHA2_256, &Sha256::digest(bytes))
+}
+
+/// Compare map keys in DAG-CBOR order.
+fn canonical_cmp(a: &str, b: &str) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+fn sorted_entries(map: &Map<String, Value>) -> Vec<(&String, &Value)> {
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by(|(a, _), (b, _)| canonical_cmp(a, b));
+    entries
+}
+
+fn encode(value: &Value, out: &mut Vec<u8>) -> Result<(), Error> {
+    match value {
+        Value::Null => out.push(0xf6),
+        Value::Bool(false) => out.push(0xf4),
+        Value::Bool(true) => out.push(0xf5),
+        Value::Number(n) => match (n.as_u64(), n.as_i64()) {
+            (Some(u), _) => header(0, u, out),
+            (None, Some(i)) => header(1, (-1 - i) as u64, out),
+            _ => return Err(invalid(format!("floats are not allowed: {}", n))),
+        },
+        Value::String(s) => {
+            header(3, s.len() as u64, out);
+            out.extend_from_slice(s.as_bytes());
+        }
+        Value::Array(items) => {
+            header(4, items.len() as u64, out);
+            for item in items {
+                encode(item, out)?;
+            }
+        }
+        Value::Object(map) => {
+            if let Some(link) = only_key(map, "$link") {
+                let cid = match link {
+                    Value::String(s) => Cid::new(s.as_str())?,
+                    _ => return Err(invalid("$link must be a CID string".to_string())),
+                };
+                let bytes = cid.to_bytes();
+                header(6, CID_TAG, out);
+                // The multibase prefix for binary CIDs.
+                header(2, bytes.len() as u64 + 1, out);
+                out.push(0x00);
+                out.extend_from_slice(&bytes);
+                return Ok(());
+            }
+            if let Some(bytes) = only_key(map, "$bytes") {
+                let bytes = bytes
+                    .as_str()
+                    .and_then(decode_base64)
+                    .ok_or_else(|| invalid("$bytes must be a base64 string".to_string()))?;
+                header(2, bytes.len() as u64, out);
+                out.extend_from_slice(&bytes);
+                return Ok(());
+            }
+
+            header(5, map.len() as u64, out);
+            for (key, item) in sorted_entries(map) {
+                header(3, key.len() as u64, out);
+                out.extend_from_slice(key.as_bytes());
+                encode(item, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decode one value, turning byte strings into values with `byte_string`.
+fn decode<'a>(
+    bytes: &mut &'a [u8],
+    depth: usize,
+    byte_string: &mut impl FnMut(&'a [u8]) -> Value,
+) -> Result<Value, Error> {
+    if depth > MAX_DEPTH {
+        return Err(invalid("DAG-CBOR value nests too deeply".to_string()));
+    }
+    match bytes.first() {
+        Some(0xf4) => return skip(bytes, Value::Bool(false)),
+        Some(0xf5) => return skip(bytes, Value::Bool(true)),
+        Some(0xf6) => return skip(bytes, Value::Null),
+        Some(0xf9..=0xfb) => return Err(invalid("floats are not allowed".to_string())),
+        Some(byte) if byte >> 5 == 7 => {
+            return Err(invalid(format!(
+                "unsupported CBOR simple value {:#x}",
+                byte
+            )));
+        }
+        _ => {}
+    }
+
+    let (major, arg) = read_header(bytes)?;
+    let value = match major {
+        0 => Value::from(arg),
+        1 => {
+            let n = i64::try_from(arg)
+                .map_err(|_| invalid("negative integer out of range".to_string()))?;
+            Value::from(-1 - n)
+        }
+        2 => byte_string(take(bytes, arg)?),
+        3 => Value::String(read_text(bytes, arg)?),
+        4 => {
+            let mut items = Vec::new();
+            for _ in 0..arg {
+                items.push(decode(bytes, depth + 1, byte_string)?);
+            }
+            Value::Array(items)
+        }
+        5 => {
+            let mut map = Map::new();
+            for _ in 0..arg {
+                let (major, len) = read_header(bytes)?;
+                if major != 3 {
+                    return Err(invalid("map keys must be strings".to_string()));
+                }
+                let key = read_text(bytes, len)?;
+                let item = decode(bytes, depth + 1, byte_string)?;
+                if map.insert(key.clone(), item).is_some() {
+                    return Err(invalid(format!("duplicate map key '{}'", key)));
+                }
+            }
+            Value::Object(map)
+        }
+        6 if arg == CID_TAG => {
+            let (major, len) = read_header(bytes)?;
+            let data = take(bytes, len)?;
+            let cid = match data.split_first() {
+                Some((0x00, cid)) if major == 2 => Cid::from_bytes(cid)?,
+                _ => return Err(invalid("CID links must be prefixed bytes".to_string())),
+            };
+            let mut map = Map::new();
+            map.insert("$link".to_string(), Value::String(cid.into()));
+            Value::Object(map)
+        }
+        _ => return Err(invalid(format!("unsupported CBOR tag {}", arg))),
+    };
+    Ok(value)
+}
+
+fn skip(bytes: &mut &[u8], value: Value) -> Result<Value, Error> {
+    *bytes = &bytes[1..];
+    Ok(value)
+}
+
+/// Read a CBOR major type and argument.
+fn read_header(bytes: &mut &[u8]) -> Result<(u8, u64), Error> {
+    let first = take(bytes, 1)?[0];
+    let arg = match first & 0x1f {
+        info @ 0..=23 => u64::from(info),
+        24 => u64::from(take(bytes, 1)?[0]),
+        25 => u64::from(u16::from_be_bytes(
+            take(bytes, 2)?.try_into().unwrap_or_default(),
+        )),
+        26 => u64::from(u32::from_be_bytes(
+            take(bytes, 4)?.try_into().unwrap_or_default(),
+        )),
+        27 => u64::from_be_bytes(take(bytes, 8)?.try_into().unwrap_or_default()),
+        _ => return Err(invalid("indefinite lengths are not allowed".to_string())),
+    };
+    Ok((first >> 5, arg))
+}
+
+fn read_text(bytes: &mut &[u8], len: u64) -> Result<String, Error> {
+    let data = take(bytes, len)?;
+    String::from_utf8(data.to_vec()).map_err(|_| invalid("strings must be UTF-8".to_string()))
+}
+
+/// Split `len` bytes off the front of `bytes`.
+fn take<'a>(bytes: &mut &'a [u8], len: u64) -> Result<&'a [u8], Error> {
+    let len = usize::try_from(len)
+        .ok()
+        .filter(|len| *len <= bytes.len())
+        .ok_or_else(|| invalid("truncated DAG-CBOR".to_string()))?;
+    let (data, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(data)
+}
+
+/// The value of a map whose only key is `key`.
+fn only_key<'a>(map: &'a Map<String, Value>, key: &str) -> Option<&'a Value> {
+    if map.len() == 1 { map.get(key) } else { None }
+}
+
+fn invalid(reason: String) -> Error {
+    InvalidInputError::RecordValue { reason }.into()
+}
+
+/// Write a CBOR major type and argument in its shortest form.
+fn header(major: u8, arg: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+    match arg {
+        0..=23 => out.push(major | arg as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, arg as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(arg as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(arg as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&arg.to_be_bytes());
+        }
+    }
+}
+
+/// Decode RFC 4648 base64, with or without padding, as atproto writes
+/// `$bytes`.
+pub(crate) fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim_end_matches('=');
+    let mut out = Vec::with_capacity(s.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in s.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    // A single leftover character cannot hold a whole byte.
+    if bits >= 6 {
+        return None;
+    }
+    Some(out)
+}
+
+/// Encode RFC 4648 base64 without padding, as atproto writes `$bytes`.
+pub(crate) fn encode_base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let buffer = chunk.iter().enumerate().fold(0u32, |buffer, (i, &byte)| {
+            buffer | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..=chunk.len() {
+            out.push(BASE64[(buffer >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    out
+}
+
+/// A JSON value that serializes its maps with keys in a [`KeyOrder`].
+pub(crate) struct Ordered<'a> {
+    pub(crate) value: &'a Value,
+    pub(crate) order: KeyOrder,
+}
+
+impl Serialize for Ordered<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let order = self.order;
+        match (self.value, order) {
+            (Value::Object(map), KeyOrder::Canonical) => {
+                let mut out = serializer.serialize_map(Some(map.len()))?;
+                for (key, value) in sorted_entries(map) {
+                    out.serialize_entry(key, &Ordered { value, order })?;
+                }
+                out.end()
+            }
+            (Value::Array(items), KeyOrder::Canonical) => {
+                let mut out = serializer.serialize_seq(Some(items.len()))?;
+                for value in items {
+                    out.serialize_element(&Ordered { value, order })?;
+                }
+                out.end()
+            }
+            (value, _) => value.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn map_keys_sort_by_length_then_bytes() {
+        let bytes = to_dag_cbor(&json!({"bb": 1, "a": null, "ab": true})).unwrap();
+        assert_eq!(
+            bytes,
+            [
+                0xa3, // map(3)
+                0x61, b'a', 0xf6, // "a": null
+                0x62, b'a', b'b', 0xf5, // "ab": true
+                0x62, b'b', b'b', 0x01, // "bb": 1
+            ]
+        );
+    }
+
+    #[test]
+    fn integers_and_lengths_use_shortest_header() {
+        let bytes = to_dag_cbor(&json!("x".repeat(24))).unwrap();
+        assert_eq!(&bytes[..2], &[0x78, 24]);
+        assert_eq!(to_dag_cbor(&json!(-1)).unwrap(), [0x20]);
+        assert_eq!(to_dag_cbor(&json!(-25)).unwrap(), [0x38, 24]);
+        assert_eq!(
+            to_dag_cbor(&json!(1_000_000)).unwrap(),
+            [0x1a, 0x00, 0x0f, 0x42, 0x40]
+        );
+        assert_eq!(
+            to_dag_cbor(&json!(i64::MIN)).unwrap(),
+            [0x3b, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+        );
+    }
+
+    #[test]
+    fn floats_are_rejected() {
+        let err = to_dag_cbor(&json!({ "n": 1.5 })).unwrap_err();
+        assert!(err.to_string().contains("floats"), "{}", err);
+    }
+
+    #[test]
+    fn links_and_bytes_use_their_cbor_types() {
+        let cid = Cid::v1(Cid::DAG_CBOR, Cid::SHA2_256, &[0xab; 32]);
+        let bytes = to_dag_cbor(&json!({ "$link": cid.as_str() })).unwrap();
+        let mut expected = vec![0xd8, 0x2a, 0x58, 0x25, 0x00, 0x01, 0x71, 0x12, 0x20];
+        expected.extend([0xab; 32]);
+        assert_eq!(bytes, expected);
+
+        let bytes = to_dag_cbor(&json!({ "$bytes": "aGVsbG8" })).unwrap();
+        assert_eq!(bytes, [0x45, b'h', b'e', b'l', b'l', b'o']);
+        assert_eq!(
+            to_dag_cbor(&json!({ "$bytes": "aGVsbG8=" })).unwrap(),
+            bytes
+        );
+
+        // Other keys beside them make an ordinary map.
+        let bytes = to_dag_cbor(&json!({ "$link": "x", "a": 1 })).unwrap();
+        assert_eq!(bytes[0], 0xa2);
+
+        assert!(to_dag_cbor(&json!({ "$link": "not-a-cid" })).is_err());
+        assert!(to_dag_cbor(&json!({ "$bytes": "a" })).is_err());
+        assert!(to_dag_cbor(&json!({ "$bytes": 1 })).is_err());
+    }
+
+    /// Checked against `serde_ipld_dagcbor`, an independent encoder.
+    #[test]
+    fn matches_reference_encoder() {
+        use ipld_core::cid::Cid as IpldCid;
+        use ipld_core::ipld::Ipld;
+
+        let post = json!({
+            "$type": "app.bsky.feed.post",
+            "text": "Hello, world! a~öñ©⽘☎𓋓😀",
+            "createdAt": "2024-01-01T00:00:00.000Z",
+            "langs": ["en"],
+            "facets": [{ "index": { "byteStart": 0, "byteEnd": 5 }, "features": [] }],
+            "count": -300,
+            "big": u64::MAX,
+            "flag": false,
+            "none": null,
+        });
+        assert_eq!(
+            to_dag_cbor(&post).unwrap(),
+            serde_ipld_dagcbor::to_vec(&post).unwrap()
+        );
+
+        let link = "bafyreibvjvcv745gig4mvqs4hctx4zfkono4rjejm2ta6gtyzkqxfjeily";
+        let blob = json!({
+            "$type": "blob",
+            "ref": { "$link": link },
+            "mimeType": "image/png",
+            "size": 1000,
+            "raw": { "$bytes": "AAEC/w" },
+        });
+        let reference = Ipld::Map(
+            [
+                ("$type".to_string(), Ipld::String("blob".to_string())),
+                (
+                    "ref".to_string(),
+                    Ipld::Link(IpldCid::try_from(link).unwrap()),
+                ),
+                (
+                    "mimeType".to_string(),
+                    Ipld::String("image/png".to_string()),
+                ),
+                ("size".to_string(), Ipld::Integer(1000)),
+                ("raw".to_string(), Ipld::Bytes(vec![0x00, 0x01, 0x02, 0xff])),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(
+            to_dag_cbor(&blob).unwrap(),
+            serde_ipld_dagcbor::to_vec(&reference).unwrap()
+        );
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let cid = Cid::v1(Cid::DAG_CBOR, Cid::SHA2_256, &[0xab; 32]);
+        let value = json!({
+            "$type": "app.bsky.feed.post",
+            "text": "Hello, world! ☎😀",
+            "langs": ["en"],
+            "count": -300,
+            "big": u64::MAX,
+            "small": i64::MIN,
+            "flag": true,
+            "none": null,
+            "ref": { "$link": cid.as_str() },
+            "raw": [{ "$bytes": "" }, { "$bytes": "AA" }, { "$bytes": "AAE" }, { "$bytes": "AAEC/w" }],
+        });
+        let bytes = to_dag_cbor(&value).unwrap();
+        assert_eq!(from_dag_cbor(&bytes).unwrap(), value);
+    }
+
+    #[test]
+    fn reads_values_back_to_back() {
+        let header = json!({ "t": "#commit", "op": 1 });
+        let body = json!({ "blocks": { "$bytes": "AAEC" } });
+        let frame = [to_dag_cbor(&header).unwrap(), to_dag_cbor(&body).unwrap()].concat();
+
+        let mut rest = frame.as_slice();
+        assert_eq!(read_dag_cbor(&mut rest).unwrap(), header);
+        let body = from_dag_cbor(rest).unwrap();
+        assert_eq!(dag_cbor_bytes(&body["blocks"]), Some(vec![0, 1, 2]));
+        assert_eq!(dag_cbor_bytes(&body), None);
+    }
+
+    #[test]
+    fn reads_byte_strings_raw() {
+        let body = json!({
+            "blocks": { "$bytes": "AAEC" },
+            "ops": [{ "path": "a/b", "k": { "$bytes": "" } }],
+        });
+        let encoded = to_dag_cbor(&body).unwrap();
+
+        let mut rest = encoded.as_slice();
+        let (value, slices) = read_dag_cbor_raw(&mut rest).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(
+            value,
+            json!({
+                "blocks": { "$bytes": 1 },
+                "ops": [{ "path": "a/b", "k": { "$bytes": 0 } }],
+            })
+        );
+        // In encoding order: shorter keys first.
+        assert_eq!(slices, [&[][..], &[0u8, 1, 2][..]]);
+    }
+
+    #[test]
+    fn decoding_rejects_what_dag_cbor_forbids() {
+        for (bytes, reason) in [
+            (&[0xa1, 0x61, b'a'][..], "truncated"),
+            (&[0x01, 0x02], "after the DAG-CBOR value"),
+            (&[0xfb, 0, 0, 0, 0, 0, 0, 0, 0], "floats"),
+            (&[0x9f, 0xff], "indefinite"),
+            (&[0xa1, 0x01, 0x02], "keys must be strings"),
+            (&[0xa2, 0x61, b'a', 0x01, 0x61, b'a', 0x02], "duplicate"),
+            (&[0xc1, 0x01], "tag"),
+            (&[0xd8, 0x2a, 0x41, 0x01], "CID links"),
+            (&[0x3b, 0x80, 0, 0, 0, 0, 0, 0, 0], "out of range"),
+        ] {
+            let err = from_dag_cbor(bytes).unwrap_err();
+            assert!(err.to_string().contains(reason), "{:?}: {}", bytes, err);
+        }
+        let deep = [vec![0x81; MAX_DEPTH + 2], vec![0xf6]].concat();
+        assert!(from_dag_cbor(&deep).is_err());
+    }
+
+    #[test]
+    fn canonical_json_key_order() {
+        let value = json!({ "bb": [{ "ccc": 1, "d": 2 }], "a": 1, "ab": 2 });
+        let sorted = serde_json::to_string(&Ordered {
+            value: &value,
+            order: KeyOrder::Sorted,
+        })
+        .unwrap();
+        assert_eq!(sorted, r#"{"a":1,"ab":2,"bb":[{"ccc":1,"d":2}]}"#);
+        let canonical = serde_json::to_string(&Ordered {
+            value: &value,
+            order: KeyOrder::Canonical,
+        })
+        .unwrap();
+        assert_eq!(canonical, r#"{"a":1,"ab":2,"bb":[{"d":2,"ccc":1}]}"#);
+    }
+}
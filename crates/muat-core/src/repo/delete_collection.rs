@@ -0,0 +1,177 @@
+//! Deleting every record in a collection.
+
+use std::fmt;
+use std::sync::Arc;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::Result;
+use crate::error::Error;
+use crate::repo::ListRecordsOptions;
+use crate::traits::Session;
+use crate::types::{AtUri, Did, Nsid};
+
+/// Callback invoked with the running totals after each page.
+type ProgressFn = Arc<dyn Fn(&DeletionReport) + Send + Sync>;
+
+/// Options for [`Session::delete_collection`].
+#[derive(Clone)]
+pub struct DeleteCollectionOptions {
+    dry_run: bool,
+    page_size: Option<u32>,
+    concurrency: usize,
+    progress: Option<ProgressFn>,
+    cancel: Option<CancellationToken>,
+}
+
+impl DeleteCollectionOptions {
+    /// Requests in flight per page unless set with
+    /// [`concurrency`](Self::concurrency).
+    pub const DEFAULT_CONCURRENCY: usize = 4;
+
+    /// Create options that delete records, using the backend's default page
+    /// size.
+    pub fn new() -> Self {
+        Self {
+            dry_run: false,
+            page_size: None,
+            concurrency: Self::DEFAULT_CONCURRENCY,
+            progress: None,
+            cancel: None,
+        }
+    }
+
+    /// Count the records that would be deleted without deleting anything.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Set the number of records fetched, and deleted, per page.
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+
+    /// Set how many delete requests may be in flight at once (at least 1).
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Call `progress` with the running totals after each page.
+    pub fn on_progress(
+        mut self,
+        progress: impl Fn(&DeletionReport) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Stop starting deletes once `cancel` is cancelled.
+    pub fn cancel(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Returns whether this is a dry run.
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Returns the page size, if set.
+    pub fn get_page_size(&self) -> Option<u32> {
+        self.page_size
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+}
+
+impl Default for DeleteCollectionOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for DeleteCollectionOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeleteCollectionOptions")
+            .field("dry_run", &self.dry_run)
+            .field("page_size", &self.page_size)
+            .field("concurrency", &self.concurrency)
+            .field("progress", &self.progress.is_some())
+            .field("cancel", &self.cancel)
+            .finish()
+    }
+}
+
+/// Outcome of [`Session::delete_collection`].
+#[derive(Debug, Clone, Default)]
+pub struct DeletionReport {
+    /// Records read from the collection.
+    pub scanned: usize,
+    /// Records deleted, or that would be in a dry run.
+    pub deleted: usize,
+    /// Records whose delete failed.
+    pub failed: Vec<(AtUri, Error)>,
+    /// Whether the run stopped early because it was cancelled.
+    pub cancelled: bool,
+}
+
+impl DeletionReport {
+    /// Returns true if every scanned record was deleted.
+    pub fn is_complete(&self) -> bool {
+        !self.cancelled && self.failed.is_empty()
+    }
+}
+
+/// The default [`Session::delete_collection`].
+pub(crate) async fn delete_collection_paged<S: Session + ?Sized>(
+    session: &S,
+    repo: &Did,
+    collection: &Nsid,
+    options: &DeleteCollectionOptions,
+) -> Result<DeletionReport> {
+    let mut report = DeletionReport::default();
+    let mut list = ListRecordsOptions::new();
+    if let Some(page_size) = options.page_size {
+        list = list.limit(page_size);
+    }
+
+    loop {
+        if options.is_cancelled() {
+            report.cancelled = true;
+            return Ok(report);
+        }
+        let page = session.list_records_with(repo, collection, &list).await?;
+        let empty = page.records.is_empty();
+        let uris: Vec<AtUri> = page.records.into_iter().map(|record| record.uri).collect();
+        report.scanned += uris.len();
+
+        if options.dry_run {
+            report.deleted += uris.len();
+        } else {
+            let bulk = session
+                .delete_records_bulk(uris, options.concurrency, options.cancel.as_ref())
+                .await;
+            report.deleted += bulk.succeeded.len();
+            report.failed.extend(bulk.failed);
+            report.cancelled = !bulk.skipped.is_empty();
+        }
+
+        if let Some(progress) = &options.progress {
+            progress(&report);
+        }
+
+        // The cursor is the page's last rkey, so deleting the page does not
+        // move the next one.
+        match page.cursor {
+            Some(cursor) if !empty && !report.cancelled => list = list.cursor(cursor),
+            _ => return Ok(report),
+        }
+    }
+}
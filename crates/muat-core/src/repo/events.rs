@@ -1,9 +1,14 @@
 //! Repository event types for the firehose stream.
 
+use chrono::{DateTime, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
 
 /// A repository event from the subscription stream.
-#[derive(Debug, Clone)]
+///
+/// Serializes as the event's own fields tagged with an `event` field naming
+/// its kind, such as `{"event": "commit", "repo": ...}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
 pub enum RepoEvent {
     /// A commit event containing repository changes.
     Commit(CommitEvent),
@@ -21,24 +26,86 @@ pub enum RepoEvent {
     Unknown { kind: String },
 }
 
+impl RepoEvent {
+    /// The event timestamp, if this event type carries one.
+    pub fn time(&self) -> Option<DateTime<Utc>> {
+        match self {
+            RepoEvent::Commit(e) => Some(e.time),
+            RepoEvent::Identity(e) => Some(e.time),
+            RepoEvent::Handle(e) => Some(e.time),
+            RepoEvent::Info(_) | RepoEvent::Unknown { .. } => None,
+        }
+    }
+
+    /// How long ago the event was timestamped, if it carries a timestamp.
+    ///
+    /// Useful for measuring how far a consumer is lagging behind the stream.
+    pub fn age(&self) -> Option<TimeDelta> {
+        self.time().map(age_of)
+    }
+
+    /// The DID of the repository the event is about, if it names one.
+    pub fn repo(&self) -> Option<&str> {
+        match self {
+            RepoEvent::Commit(e) => Some(&e.repo),
+            RepoEvent::Identity(e) => Some(&e.did),
+            RepoEvent::Handle(e) => Some(&e.did),
+            RepoEvent::Info(_) | RepoEvent::Unknown { .. } => None,
+        }
+    }
+
+    /// The event's sequence number, if this event type carries one.
+    pub fn seq(&self) -> Option<i64> {
+        match self {
+            RepoEvent::Commit(e) => Some(e.seq),
+            RepoEvent::Identity(e) => Some(e.seq),
+            RepoEvent::Handle(e) => Some(e.seq),
+            RepoEvent::Info(_) | RepoEvent::Unknown { .. } => None,
+        }
+    }
+}
+
 /// A commit event from the repository.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommitEvent {
     /// The repository DID.
     pub repo: String,
 
-    /// The commit revision.
+    /// The commit revision, a TID that increases with each commit to the repo.
     pub rev: String,
 
+    /// The revision of the repo's previous commit, if the source reports it.
+    #[serde(default)]
+    pub since: Option<String>,
+
     /// Sequence number.
     pub seq: i64,
 
     /// Timestamp of the commit.
-    pub time: String,
+    #[serde(with = "rfc3339")]
+    pub time: DateTime<Utc>,
 
     /// Operations in this commit.
     #[serde(default)]
     pub ops: Vec<CommitOperation>,
+
+    /// CAR-encoded blocks of the commit diff; empty when the source does
+    /// not carry them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocks: Vec<u8>,
+
+    /// CIDs of blobs referenced by the commit.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blobs: Vec<String>,
+}
+
+impl CommitEvent {
+    /// How long ago the event was timestamped.
+    ///
+    /// Negative if the timestamp is ahead of the local clock.
+    pub fn age(&self) -> TimeDelta {
+        age_of(self.time)
+    }
 }
 
 /// An operation within a commit.
@@ -64,7 +131,17 @@ pub struct IdentityEvent {
     pub seq: i64,
 
     /// Timestamp.
-    pub time: String,
+    #[serde(with = "rfc3339")]
+    pub time: DateTime<Utc>,
+}
+
+impl IdentityEvent {
+    /// How long ago the event was timestamped.
+    ///
+    /// Negative if the timestamp is ahead of the local clock.
+    pub fn age(&self) -> TimeDelta {
+        age_of(self.time)
+    }
 }
 
 /// A handle update event.
@@ -80,7 +157,17 @@ pub struct HandleEvent {
     pub seq: i64,
 
     /// Timestamp.
-    pub time: String,
+    #[serde(with = "rfc3339")]
+    pub time: DateTime<Utc>,
+}
+
+impl HandleEvent {
+    /// How long ago the event was timestamped.
+    ///
+    /// Negative if the timestamp is ahead of the local clock.
+    pub fn age(&self) -> TimeDelta {
+        age_of(self.time)
+    }
 }
 
 /// Stream info event.
@@ -92,3 +179,232 @@ pub struct InfoEvent {
     /// Optional message.
     pub message: Option<String>,
 }
+
+impl InfoEvent {
+    /// The gap this event reports, if it is a [`GapDetected`] event.
+    pub fn gap(&self) -> Option<GapDetected> {
+        if self.name != GapDetected::NAME {
+            return None;
+        }
+        let message = self.message.as_deref()?;
+        let (from, to) = message
+            .strip_prefix("sequence jumped from ")?
+            .split_once(" to ")?;
+        Some(GapDetected {
+            from: from.parse().ok()?,
+            to: to.parse().ok()?,
+        })
+    }
+}
+
+/// Events between two sequence numbers never arrived.
+///
+/// Reported by [`FirehoseExt::check_sequence`](crate::FirehoseExt::check_sequence)
+/// as an info event named [`GapDetected::NAME`]; read it back with
+/// [`InfoEvent::gap`]. The missed events are those with sequence numbers
+/// strictly between `from` and `to`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GapDetected {
+    /// The last sequence number received before the gap.
+    pub from: i64,
+    /// The first sequence number received after the gap.
+    pub to: i64,
+}
+
+impl GapDetected {
+    /// Name of the info event reporting a gap.
+    pub const NAME: &str = "GapDetected";
+
+    /// How many sequence numbers were skipped.
+    pub fn missed(&self) -> u64 {
+        self.to.saturating_sub(self.from).saturating_sub(1).max(0) as u64
+    }
+}
+
+impl From<GapDetected> for InfoEvent {
+    fn from(gap: GapDetected) -> Self {
+        InfoEvent {
+            name: GapDetected::NAME.to_string(),
+            message: Some(format!("sequence jumped from {} to {}", gap.from, gap.to)),
+        }
+    }
+}
+
+/// A label from `com.atproto.label.subscribeLabels` or `queryLabels`.
+///
+/// A label is a moderation assertion by `src` about a repo or record. A
+/// negation (`neg`) retracts an earlier label with the same `src`, `uri`
+/// and `val`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LabelEvent {
+    /// Sequence number of the message carrying the label; 0 for labels from
+    /// `queryLabels`, which are not sequenced.
+    #[serde(default)]
+    pub seq: i64,
+
+    /// DID of the labeler that created the label.
+    pub src: String,
+
+    /// AT URI of the labelled record, or a DID for a whole account.
+    pub uri: String,
+
+    /// CID of the specific record version labelled, if the label is pinned
+    /// to one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cid: Option<String>,
+
+    /// The label value, such as `spam` or `!hide`.
+    pub val: String,
+
+    /// Whether this retracts the label instead of applying it.
+    #[serde(default)]
+    pub neg: bool,
+
+    /// When the label was created.
+    #[serde(with = "rfc3339")]
+    pub cts: DateTime<Utc>,
+
+    /// When the label stops applying, if it expires.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "rfc3339::option"
+    )]
+    pub exp: Option<DateTime<Utc>>,
+}
+
+impl LabelEvent {
+    /// Returns true if the label has an expiry that has passed.
+    pub fn is_expired(&self) -> bool {
+        self.exp.is_some_and(|exp| exp <= Utc::now())
+    }
+}
+
+/// A page of labels from `queryLabels`.
+#[derive(Debug, Clone, Default)]
+pub struct QueryLabelsOutput {
+    /// The labels on this page.
+    pub labels: Vec<LabelEvent>,
+    /// Cursor for the next page, if there are more labels.
+    pub cursor: Option<String>,
+}
+
+fn age_of(time: DateTime<Utc>) -> TimeDelta {
+    Utc::now() - time
+}
+
+/// Serde adapter keeping event timestamps in the RFC 3339 form used on the
+/// wire (`2024-01-01T00:00:00.123Z`), whatever offset they arrive with.
+mod rfc3339 {
+    use chrono::{DateTime, SecondsFormat, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        time: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        DateTime::parse_from_rfc3339(&value)
+            .map(|time| time.with_timezone(&Utc))
+            .map_err(serde::de::Error::custom)
+    }
+
+    /// The same, for optional timestamps.
+    pub mod option {
+        use chrono::{DateTime, Utc};
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            time: &Option<DateTime<Utc>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match time {
+                Some(time) => super::serialize(time, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<DateTime<Utc>>, D::Error> {
+            Option::<String>::deserialize(deserializer)?
+                .map(|value| {
+                    DateTime::parse_from_rfc3339(&value)
+                        .map(|time| time.with_timezone(&Utc))
+                        .map_err(serde::de::Error::custom)
+                })
+                .transpose()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_round_trips_in_wire_format() {
+        let json = r#"{"did":"did:plc:abcaaaaaaaaaaaaaaaaaaaaa","seq":7,"time":"2024-09-09T19:46:02.329308Z"}"#;
+        let event: IdentityEvent = serde_json::from_str(json).unwrap();
+        assert_eq!(event.time.timestamp_micros(), 1_725_911_162_329_308);
+        assert_eq!(serde_json::to_string(&event).unwrap(), json);
+    }
+
+    #[test]
+    fn time_accepts_offsets() {
+        let json = r#"{"did":"did:plc:abcaaaaaaaaaaaaaaaaaaaaa","seq":7,"time":"2024-01-01T01:00:00+01:00"}"#;
+        let event: IdentityEvent = serde_json::from_str(json).unwrap();
+        assert_eq!(event.time.to_rfc3339(), "2024-01-01T00:00:00+00:00");
+        assert!(event.age() > TimeDelta::zero());
+    }
+
+    #[test]
+    fn commit_fields_added_later_are_optional() {
+        let json = r#"{"repo":"did:plc:abcaaaaaaaaaaaaaaaaaaaaa","rev":"3l3qo2vutsw2b","seq":7,"time":"2024-09-09T19:46:02.329308Z"}"#;
+        let event: CommitEvent = serde_json::from_str(json).unwrap();
+        assert!(event.since.is_none());
+        assert!(event.ops.is_empty() && event.blocks.is_empty() && event.blobs.is_empty());
+    }
+
+    #[test]
+    fn gap_round_trips_through_info_event() {
+        let gap = GapDetected { from: 100, to: 150 };
+        let info = InfoEvent::from(gap);
+        assert_eq!(info.name, "GapDetected");
+        assert_eq!(info.gap(), Some(gap));
+        assert_eq!(gap.missed(), 49);
+
+        let other = InfoEvent {
+            name: "OutdatedCursor".to_string(),
+            message: info.message,
+        };
+        assert!(other.gap().is_none());
+    }
+
+    #[test]
+    fn label_fields_follow_the_lexicon() {
+        let json = r#"{"src":"did:plc:labelaaaaaaaaaaaaaaaaaaa","uri":"at://did:plc:abcaaaaaaaaaaaaaaaaaaaaa/app.bsky.feed.post/3k2a","val":"spam","cts":"2024-09-09T19:46:02.329Z","exp":"2000-01-01T00:00:00Z"}"#;
+        let label: LabelEvent = serde_json::from_str(json).unwrap();
+        assert_eq!(label.seq, 0);
+        assert!(!label.neg && label.cid.is_none());
+        assert!(label.is_expired());
+        let back: LabelEvent =
+            serde_json::from_str(&serde_json::to_string(&label).unwrap()).unwrap();
+        assert_eq!(back, label);
+    }
+
+    #[test]
+    fn age_is_none_without_time() {
+        let event = RepoEvent::Unknown {
+            kind: "#sync".to_string(),
+        };
+        assert!(event.time().is_none());
+        assert!(event.age().is_none());
+    }
+}
@@ -0,0 +1,59 @@
+//! Fetching the records a commit touched.
+
+use futures_util::{StreamExt, stream};
+
+use crate::Result;
+use crate::error::Error;
+use crate::repo::{CommitEvent, CommitOperation, Record};
+use crate::traits::Session;
+use crate::types::{AtUri, Did};
+
+/// Fetch the current value of each record a commit created or updated,
+/// with up to `concurrency` reads at a time.
+///
+/// Returns the commit's operations in order, each paired with its record.
+/// Deletes, and records deleted again before they could be read, are paired
+/// with `None`. A record updated since the commit is returned as it is now,
+/// so its CID may differ from the operation's.
+///
+/// Records are always read from `session`; blocks embedded in the commit are
+/// not decoded.
+///
+/// # Errors
+///
+/// Fails on the first read that fails for another reason than the record
+/// being gone, or if the commit names an invalid repo or path.
+pub async fn hydrate_ops<S: Session + ?Sized>(
+    session: &S,
+    commit: &CommitEvent,
+    concurrency: usize,
+) -> Result<Vec<(CommitOperation, Option<Record>)>> {
+    let repo = Did::new(&commit.repo)?;
+    let reads: Vec<_> = commit
+        .ops
+        .iter()
+        .map(|op| {
+            let uri = match op.action.as_str() {
+                "delete" => None,
+                _ => Some(AtUri::new(format!("at://{}/{}", repo, op.path))),
+            };
+            async move {
+                let record = match uri {
+                    None => None,
+                    Some(uri) => match session.get_record(&uri?).await {
+                        Ok(record) => Some(record),
+                        Err(Error::Protocol(e)) if e.is_not_found() => None,
+                        Err(e) => return Err(e),
+                    },
+                };
+                Ok((op.clone(), record))
+            }
+        })
+        .collect();
+    stream::iter(reads)
+        .buffered(concurrency.max(1))
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect()
+}
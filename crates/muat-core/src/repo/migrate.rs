@@ -0,0 +1,219 @@
+//! Record migration over a collection.
+
+use std::fmt;
+use std::sync::Arc;
+
+use tokio_util::sync::CancellationToken;
+
+use crate::Result;
+use crate::error::Error;
+use crate::repo::{ListRecordsOptions, RecordValue};
+use crate::traits::Session;
+use crate::types::{AtUri, Nsid};
+
+/// Callback invoked with the running totals after each page.
+type ProgressFn = Arc<dyn Fn(&MigrationReport) + Send + Sync>;
+
+/// Options for [`migrate_collection_with`].
+#[derive(Clone, Default)]
+pub struct MigrateOptions {
+    dry_run: bool,
+    page_size: Option<u32>,
+    progress: Option<ProgressFn>,
+    cancel: Option<CancellationToken>,
+}
+
+impl MigrateOptions {
+    /// Create options that write changes back, using the backend's default
+    /// page size.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the records that would change without writing anything.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Set the number of records fetched per page.
+    pub fn page_size(mut self, page_size: u32) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+
+    /// Call `progress` with the running totals after each page.
+    pub fn on_progress(
+        mut self,
+        progress: impl Fn(&MigrationReport) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Stop before the next record once `cancel` is cancelled.
+    pub fn cancel(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Returns whether this is a dry run.
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Returns the page size, if set.
+    pub fn get_page_size(&self) -> Option<u32> {
+        self.page_size
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+    }
+}
+
+impl fmt::Debug for MigrateOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MigrateOptions")
+            .field("dry_run", &self.dry_run)
+            .field("page_size", &self.page_size)
+            .field("progress", &self.progress.is_some())
+            .field("cancel", &self.cancel)
+            .finish()
+    }
+}
+
+/// Outcome of a collection migration.
+#[derive(Debug, Clone, Default)]
+pub struct MigrationReport {
+    /// Records read from the collection.
+    pub scanned: usize,
+    /// Records rewritten, or that would be in a dry run.
+    pub changed: usize,
+    /// Records the transform left as they were.
+    pub unchanged: usize,
+    /// Records that changed between being read and written, and were skipped.
+    pub conflicts: Vec<AtUri>,
+    /// Records whose write failed for another reason.
+    pub failed: Vec<(AtUri, Error)>,
+    /// Whether the migration stopped early because it was cancelled.
+    pub cancelled: bool,
+}
+
+impl MigrationReport {
+    /// Returns true if every scanned record was migrated or left unchanged.
+    pub fn is_complete(&self) -> bool {
+        !self.cancelled && self.conflicts.is_empty() && self.failed.is_empty()
+    }
+}
+
+/// Rewrite every record in one of the session's collections.
+///
+/// Shorthand for [`migrate_collection_with`] with default options.
+pub async fn migrate_collection<S, F>(
+    session: &S,
+    collection: &Nsid,
+    transform: F,
+) -> Result<MigrationReport>
+where
+    S: Session + ?Sized,
+    F: FnMut(RecordValue) -> Option<RecordValue>,
+{
+    migrate_collection_with(session, collection, &MigrateOptions::new(), transform).await
+}
+
+/// Rewrite every record in one of the session's collections.
+///
+/// Pages through the collection in ascending record key order and passes
+/// each value to `transform`. Returning `None`, or a value equal to the
+/// input, leaves the record alone; any other value is written back with
+/// [`Session::put_record`], swapping on the CID that was read. A record
+/// changed by someone else in the meantime is not overwritten and is listed
+/// in [`MigrationReport::conflicts`]; rerunning the migration picks it up.
+///
+/// Failed writes are collected in the report rather than stopping the
+/// migration. Errors listing the collection are returned.
+///
+/// # Example
+///
+/// ```no_run
+/// # use muat_core::{Nsid, Session, RecordValue};
+/// # use muat_core::repo::{MigrateOptions, migrate_collection_with};
+/// # async fn example(session: &impl Session) -> muat_core::Result<()> {
+/// let collection = Nsid::new("org.example.post")?;
+/// let options = MigrateOptions::new().dry_run(true);
+/// let report = migrate_collection_with(session, &collection, &options, |value| {
+///     let mut json = value.as_value().clone();
+///     let title = json.as_object_mut()?.remove("title")?;
+///     json["name"] = title;
+///     RecordValue::new(json).ok()
+/// })
+/// .await?;
+/// println!("{} of {} records would change", report.changed, report.scanned);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn migrate_collection_with<S, F>(
+    session: &S,
+    collection: &Nsid,
+    options: &MigrateOptions,
+    mut transform: F,
+) -> Result<MigrationReport>
+where
+    S: Session + ?Sized,
+    F: FnMut(RecordValue) -> Option<RecordValue>,
+{
+    let mut report = MigrationReport::default();
+    let mut list = ListRecordsOptions::new();
+    if let Some(page_size) = options.page_size {
+        list = list.limit(page_size);
+    }
+
+    loop {
+        let page = session
+            .list_records_with(session.did(), collection, &list)
+            .await?;
+        let empty = page.records.is_empty();
+
+        for record in page.records {
+            if options.is_cancelled() {
+                report.cancelled = true;
+                return Ok(report);
+            }
+            report.scanned += 1;
+
+            let value = match transform(record.value.clone()) {
+                Some(value) if value != record.value => value,
+                _ => {
+                    report.unchanged += 1;
+                    continue;
+                }
+            };
+            if options.dry_run {
+                report.changed += 1;
+                continue;
+            }
+
+            let (_, rkey) = record.uri.record_path()?;
+            match session
+                .put_record(collection, rkey, &value, Some(&record.cid))
+                .await
+            {
+                Ok(_) => report.changed += 1,
+                Err(Error::Protocol(e)) if e.is_invalid_swap() => report.conflicts.push(record.uri),
+                Err(e) => report.failed.push((record.uri, e)),
+            }
+        }
+
+        if let Some(progress) = &options.progress {
+            progress(&report);
+        }
+
+        match page.cursor {
+            Some(cursor) if !empty => list = list.cursor(cursor),
+            _ => return Ok(report),
+        }
+    }
+}
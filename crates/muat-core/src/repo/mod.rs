@@ -3,10 +3,44 @@
 //! This module defines the types used for repository operations.
 //! The actual operations are methods on [`Session`](crate::Session).
 
+mod backfill;
+mod blob;
+mod buffer;
+mod bulk;
+mod car;
+mod dag_cbor;
+mod delete_collection;
 mod events;
+mod hydrate;
+mod migrate;
+mod processor;
+mod proof;
 mod record_value;
+mod stats;
+mod topics;
 mod types;
 
-pub use events::{CommitEvent, CommitOperation, HandleEvent, IdentityEvent, InfoEvent, RepoEvent};
+pub use crate::types::BlobRef;
+pub use backfill::Backfiller;
+pub use blob::{ByteRange, ListBlobsOutput};
+pub use buffer::{EventReceiver, EventSender, FirehoseBuffer, OverflowPolicy};
+pub use bulk::BulkReport;
+pub use car::{Blocks, Car, CarBlock};
+pub use dag_cbor::{
+    KeyOrder, dag_cbor_bytes, dag_cbor_cid, from_dag_cbor, read_dag_cbor, read_dag_cbor_raw,
+    to_dag_cbor,
+};
+pub(crate) use delete_collection::delete_collection_paged;
+pub use delete_collection::{DeleteCollectionOptions, DeletionReport};
+pub use events::{
+    CommitEvent, CommitOperation, GapDetected, HandleEvent, IdentityEvent, InfoEvent, LabelEvent,
+    QueryLabelsOutput, RepoEvent,
+};
+pub use hydrate::hydrate_ops;
+pub use migrate::{MigrateOptions, MigrationReport, migrate_collection, migrate_collection_with};
+pub use processor::{FirehoseProcessor, ProcessError, ProcessReport};
+pub use proof::{Commit, RecordProof, verify_inclusion};
 pub use record_value::RecordValue;
-pub use types::{ListRecordsOutput, Record};
+pub use stats::{EventStats, FirehoseStats};
+pub use topics::{SinkMessage, TopicMapping};
+pub use types::{ListRecordsOptions, ListRecordsOutput, Record, RecordOrder};
@@ -0,0 +1,378 @@
+//! Consuming a firehose with a pool of workers.
+
+use std::collections::BTreeMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use futures_util::StreamExt;
+use futures_util::future::{try_join, try_join_all};
+use tokio::sync::mpsc;
+
+use crate::Error;
+use crate::repo::RepoEvent;
+use crate::traits::Firehose;
+
+/// Callback invoked with each new checkpoint.
+type CheckpointFn = Arc<dyn Fn(i64) + Send + Sync>;
+
+/// Runs a handler over a firehose on several workers at once, so slow
+/// handling does not hold up reading the stream.
+///
+/// Events for the same repository always go to the same worker, so each
+/// repo's events are handled one at a time, in stream order. Events without
+/// a repository (info, unknown) go to the first worker.
+///
+/// Delivery is at least once. The [`on_complete`](Self::on_complete)
+/// callback is given a cursor every event up to which has been handled;
+/// saving it and resuming the firehose from it after a crash replays at
+/// most the events that were in flight. Synthetic events with sequence
+/// number 0, such as those from a [`Backfiller`](crate::repo::Backfiller),
+/// are handled but never move the cursor.
+///
+/// The workers are futures polled by [`run`](Self::run), not spawned tasks,
+/// so the processor needs no particular runtime. Handlers run concurrently
+/// with each other; spawn `run` itself onto its own task to keep it off the
+/// caller's, and have a handler spawn CPU-heavy work if it needs parallelism.
+///
+/// # Example
+///
+/// ```ignore
+/// let report = FirehoseProcessor::new(8, |event| async move { index(event).await })
+///     .on_complete(move |cursor| cursor_tx.send_replace(Some(cursor)))
+///     .run(pds.firehose_from(saved_cursor)?)
+///     .await?;
+/// ```
+pub struct FirehoseProcessor<H> {
+    workers: usize,
+    queue: usize,
+    handler: H,
+    on_complete: Option<CheckpointFn>,
+}
+
+impl<H> fmt::Debug for FirehoseProcessor<H> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FirehoseProcessor")
+            .field("workers", &self.workers)
+            .field("queue", &self.queue)
+            .field("on_complete", &self.on_complete.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<H, Fut, E> FirehoseProcessor<H>
+where
+    H: Fn(RepoEvent) -> Fut + Send + Sync,
+    Fut: Future<Output = std::result::Result<(), E>> + Send,
+    E: Send,
+{
+    /// Events queued per worker before reading the stream pauses.
+    pub const DEFAULT_QUEUE: usize = 16;
+
+    /// Handle events with `handler` on `workers` workers (at least one).
+    pub fn new(workers: usize, handler: H) -> Self {
+        Self {
+            workers: workers.max(1),
+            queue: Self::DEFAULT_QUEUE,
+            handler,
+            on_complete: None,
+        }
+    }
+
+    /// Set how many events may wait for each worker. When a worker's queue
+    /// is full, the stream is not read until it has room.
+    pub fn queue(mut self, queue: usize) -> Self {
+        self.queue = queue.max(1);
+        self
+    }
+
+    /// Call `on_complete` with the new cursor each time every event up to a
+    /// later sequence number has been handled.
+    ///
+    /// Cursors are passed in increasing order. The callback runs on a
+    /// worker, so it should hand off slow work such as a database write
+    /// rather than block.
+    pub fn on_complete(mut self, on_complete: impl Fn(i64) + Send + Sync + 'static) -> Self {
+        self.on_complete = Some(Arc::new(on_complete));
+        self
+    }
+
+    /// Handle every event of `firehose` until it ends or fails.
+    ///
+    /// Ends with the first stream error or handler error. Events after the
+    /// failing one may already have been handled by other workers, and are
+    /// replayed when resuming from the last checkpoint.
+    pub async fn run<F: Firehose>(
+        self,
+        firehose: F,
+    ) -> std::result::Result<ProcessReport, ProcessError<E>> {
+        let checkpoints = Mutex::new(Checkpoints::default());
+        let (senders, receivers): (Vec<_>, Vec<_>) =
+            (0..self.workers).map(|_| mpsc::channel(self.queue)).unzip();
+
+        let dispatch = async {
+            let mut firehose = std::pin::pin!(firehose);
+            while let Some(event) = firehose.next().await {
+                let event = event.map_err(ProcessError::Firehose)?;
+                if let Some(seq) = sequenced(&event) {
+                    lock(&checkpoints).start(seq);
+                }
+                let worker = worker_for(&event, senders.len());
+                if senders[worker].send(event).await.is_err() {
+                    // The worker failed; its error ends the run.
+                    break;
+                }
+            }
+            drop(senders);
+            Ok(())
+        };
+
+        let workers = receivers.into_iter().map(|mut receiver| {
+            let handler = &self.handler;
+            let on_complete = self.on_complete.as_deref();
+            let checkpoints = &checkpoints;
+            async move {
+                while let Some(event) = receiver.recv().await {
+                    let seq = sequenced(&event);
+                    let repo = event.repo().map(str::to_string);
+                    if let Err(error) = handler(event).await {
+                        return Err(ProcessError::Handler { seq, repo, error });
+                    }
+                    let mut checkpoints = lock(checkpoints);
+                    checkpoints.handled += 1;
+                    if let Some(cursor) = seq.and_then(|seq| checkpoints.finish(seq))
+                        && let Some(on_complete) = on_complete
+                    {
+                        on_complete(cursor);
+                    }
+                }
+                Ok(())
+            }
+        });
+
+        try_join(dispatch, try_join_all(workers)).await?;
+        let checkpoints = checkpoints
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        Ok(ProcessReport {
+            events: checkpoints.handled,
+            cursor: checkpoints.reported,
+        })
+    }
+}
+
+/// How a [`FirehoseProcessor`] run ended without error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessReport {
+    /// Events handled.
+    pub events: u64,
+    /// The last cursor passed to `on_complete`, if any.
+    pub cursor: Option<i64>,
+}
+
+/// Why a [`FirehoseProcessor`] run stopped.
+#[derive(Debug, thiserror::Error)]
+pub enum ProcessError<E> {
+    /// The firehose yielded an error.
+    #[error("firehose failed: {0}")]
+    Firehose(#[source] Error),
+
+    /// The handler failed on an event.
+    #[error(
+        "handler failed{}{}",
+        seq.map(|seq| format!(" at seq {}", seq)).unwrap_or_default(),
+        repo.as_deref().map(|repo| format!(" for {}", repo)).unwrap_or_default()
+    )]
+    Handler {
+        /// The event's sequence number, if it has one.
+        seq: Option<i64>,
+        /// The event's repository, if it names one.
+        repo: Option<String>,
+        /// The handler's error.
+        #[source]
+        error: E,
+    },
+}
+
+/// Sequence numbers handed to workers but not yet handled, and the cursor
+/// reported so far.
+#[derive(Debug, Default)]
+struct Checkpoints {
+    /// In-flight sequence numbers, with a count in case one is replayed.
+    in_flight: BTreeMap<i64, usize>,
+    /// The first sequence number seen; no cursor before it is reported.
+    first: Option<i64>,
+    /// The highest sequence number seen.
+    highest: Option<i64>,
+    reported: Option<i64>,
+    handled: u64,
+}
+
+impl Checkpoints {
+    fn start(&mut self, seq: i64) {
+        *self.in_flight.entry(seq).or_default() += 1;
+        self.first.get_or_insert(seq);
+        self.highest = self.highest.max(Some(seq));
+    }
+
+    /// Record that `seq` was handled, returning the new cursor if it moved.
+    fn finish(&mut self, seq: i64) -> Option<i64> {
+        if let Some(count) = self.in_flight.get_mut(&seq) {
+            *count -= 1;
+            if *count == 0 {
+                self.in_flight.remove(&seq);
+            }
+        }
+        let cursor = match self.in_flight.keys().next() {
+            Some(&oldest) => oldest - 1,
+            None => self.highest?,
+        };
+        if cursor < self.first? || self.reported.is_some_and(|reported| cursor <= reported) {
+            return None;
+        }
+        self.reported = Some(cursor);
+        Some(cursor)
+    }
+}
+
+/// The sequence number that moves the cursor, if the event has one.
+fn sequenced(event: &RepoEvent) -> Option<i64> {
+    event.seq().filter(|&seq| seq > 0)
+}
+
+fn worker_for(event: &RepoEvent, workers: usize) -> usize {
+    let Some(repo) = event.repo() else {
+        return 0;
+    };
+    let mut hasher = DefaultHasher::new();
+    repo.hash(&mut hasher);
+    (hasher.finish() % workers as u64) as usize
+}
+
+fn lock(checkpoints: &Mutex<Checkpoints>) -> std::sync::MutexGuard<'_, Checkpoints> {
+    checkpoints.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use futures_util::stream;
+
+    use super::*;
+    use crate::repo::{CommitEvent, InfoEvent};
+
+    fn commit(repo: usize, seq: i64) -> crate::Result<RepoEvent> {
+        Ok(RepoEvent::Commit(CommitEvent {
+            repo: format!("did:plc:repo{}", repo),
+            rev: "rev".to_string(),
+            since: None,
+            seq,
+            time: "2024-01-01T00:00:00Z".parse().unwrap(),
+            ops: Vec::new(),
+            blocks: Vec::new(),
+            blobs: Vec::new(),
+        }))
+    }
+
+    /// Yield a few times, more for some repos, so workers finish out of
+    /// stream order.
+    async fn work(seq: i64) {
+        for _ in 0..(seq % 5) * 3 {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn repos_keep_their_order_and_cursors_only_advance() {
+        let mut events: Vec<_> = (1..=60).map(|seq| commit(seq as usize % 7, seq)).collect();
+        events.insert(
+            10,
+            Ok(RepoEvent::Info(InfoEvent {
+                name: "hello".to_string(),
+                message: None,
+            })),
+        );
+        let seen = Arc::new(Mutex::new(HashMap::<String, Vec<i64>>::new()));
+        let cursors = Arc::new(Mutex::new(Vec::new()));
+
+        let handled = seen.clone();
+        let reported = cursors.clone();
+        let report = FirehoseProcessor::new(4, move |event: RepoEvent| {
+            let handled = handled.clone();
+            async move {
+                let seq = event.seq().unwrap_or_default();
+                work(seq).await;
+                if let Some(repo) = event.repo() {
+                    handled
+                        .lock()
+                        .unwrap()
+                        .entry(repo.to_string())
+                        .or_default()
+                        .push(seq);
+                }
+                Ok::<_, std::convert::Infallible>(())
+            }
+        })
+        .queue(2)
+        .on_complete(move |cursor| reported.lock().unwrap().push(cursor))
+        .run(stream::iter(events))
+        .await
+        .unwrap();
+
+        assert_eq!(report.events, 61);
+        assert_eq!(report.cursor, Some(60));
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.values().map(Vec::len).sum::<usize>(), 60);
+        for seqs in seen.values() {
+            assert!(seqs.is_sorted(), "repo events out of order: {seqs:?}");
+        }
+        let cursors = cursors.lock().unwrap();
+        assert!(cursors.is_sorted() && cursors.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(cursors.last(), Some(&60));
+    }
+
+    #[tokio::test]
+    async fn a_failed_event_is_never_passed_by_the_cursor() {
+        let events: Vec<_> = (1..=20).map(|seq| commit(seq as usize % 3, seq)).collect();
+        let cursors = Arc::new(Mutex::new(Vec::new()));
+
+        let reported = cursors.clone();
+        let result = FirehoseProcessor::new(3, |event: RepoEvent| async move {
+            let seq = event.seq().unwrap_or_default();
+            work(seq).await;
+            if seq == 8 { Err("boom") } else { Ok(()) }
+        })
+        .on_complete(move |cursor| reported.lock().unwrap().push(cursor))
+        .run(stream::iter(events))
+        .await;
+
+        let Err(ProcessError::Handler { seq, repo, error }) = result else {
+            panic!("expected the handler error");
+        };
+        assert_eq!(
+            (seq, repo.as_deref(), error),
+            (Some(8), Some("did:plc:repo2"), "boom")
+        );
+        assert!(cursors.lock().unwrap().iter().all(|&cursor| cursor < 8));
+    }
+
+    #[tokio::test]
+    async fn stream_errors_end_the_run() {
+        let events = vec![
+            commit(1, 1),
+            Err(Error::Firehose(crate::error::FirehoseError::CursorTooOld {
+                message: None,
+            })),
+            commit(1, 2),
+        ];
+        let result = FirehoseProcessor::new(2, |_event: RepoEvent| async {
+            Ok::<_, std::convert::Infallible>(())
+        })
+        .run(stream::iter(events))
+        .await;
+        assert!(matches!(result, Err(ProcessError::Firehose(_))));
+    }
+}
@@ -0,0 +1,486 @@
+//! Proving a record is in a signed commit, from CAR blocks.
+//!
+//! A repo commit signs the root of a Merkle search tree (MST) whose keys
+//! are `collection/rkey` paths and whose values are record CIDs. Blocks are
+//! addressed by their hashes, so the commit and the tree nodes on the path
+//! to a key are enough to show what the repo held at that commit, whoever
+//! served them.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::Result;
+use crate::error::{Error, InvalidInputError};
+use crate::repo::{Car, Record, RecordValue, dag_cbor_bytes, from_dag_cbor, to_dag_cbor};
+use crate::types::{AtUri, Cid, Did};
+
+/// A signed repo commit, decoded from its block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Commit {
+    /// The repo's DID.
+    pub did: Did,
+    /// The repo format version, 2 or 3.
+    pub version: u64,
+    /// The root of the repo's MST.
+    pub data: Cid,
+    /// The commit's revision, a TID.
+    pub rev: String,
+    /// The previous commit, if the repo recorded it.
+    pub prev: Option<Cid>,
+    /// The signature over [`unsigned_bytes`](Self::unsigned_bytes).
+    pub sig: Vec<u8>,
+    unsigned: Vec<u8>,
+}
+
+impl Commit {
+    /// Decode a commit block.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the block is not DAG-CBOR or lacks a commit's fields.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut value = from_dag_cbor(bytes)?;
+        let map = value
+            .as_object_mut()
+            .ok_or_else(|| invalid("commit is not a map"))?;
+        let sig = map
+            .remove("sig")
+            .as_ref()
+            .and_then(dag_cbor_bytes)
+            .ok_or_else(|| invalid("commit has no signature"))?;
+        let unsigned = to_dag_cbor(&value)?;
+
+        let did = value
+            .get("did")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid("commit has no DID"))?;
+        let version = value.get("version").and_then(Value::as_u64);
+        let version = match version {
+            Some(version @ (2 | 3)) => version,
+            _ => return Err(invalid("unsupported commit version")),
+        };
+        let data = value
+            .get("data")
+            .and_then(link)
+            .ok_or_else(|| invalid("commit has no data link"))?;
+        let rev = value
+            .get("rev")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid("commit has no rev"))?;
+        let prev = match value.get("prev") {
+            None | Some(Value::Null) => None,
+            Some(prev) => Some(link(prev).ok_or_else(|| invalid("commit prev is not a link"))?),
+        };
+
+        Ok(Self {
+            did: Did::new(did)?,
+            version,
+            data,
+            rev: rev.to_string(),
+            prev,
+            sig,
+            unsigned,
+        })
+    }
+
+    /// Returns the DAG-CBOR encoding of the commit without its signature:
+    /// the bytes the repo's signing key signed.
+    pub fn unsigned_bytes(&self) -> &[u8] {
+        &self.unsigned
+    }
+}
+
+/// What [`verify_inclusion`] proved about a record.
+#[derive(Debug, Clone)]
+pub struct RecordProof {
+    /// The commit the proof is rooted in. Its signature is not checked
+    /// here; see [`Commit::unsigned_bytes`].
+    pub commit: Commit,
+    /// The CID of the commit block.
+    pub commit_cid: Cid,
+    /// The record's CID in the commit's tree, or `None` if the tree shows
+    /// the repo did not hold the record.
+    pub cid: Option<Cid>,
+    /// The record, if its block was in the CAR too.
+    pub record: Option<Record>,
+}
+
+/// Check that the CAR proves what the record at `uri` was at a commit.
+///
+/// The CAR's root must be a commit of the record's repo, as
+/// `com.atproto.sync.getRecord` returns. The MST is walked from the
+/// commit's data root to the record's key; every block on the way must be
+/// in the CAR and match its CID, and each node's keys must be sorted and
+/// on a lower layer than its parent's. Blocks not on the path are ignored.
+///
+/// This proves the record against the commit, not that the commit is
+/// genuine: check [`Commit::sig`] with the repo's signing key too, such as
+/// with `muat_plc::verify_commit`.
+///
+/// # Errors
+///
+/// Fails if `uri` is not a record URI, or the CAR is malformed, is missing
+/// a block on the path, or has a root that is not a commit of the repo.
+pub fn verify_inclusion(car: &Car, uri: &AtUri) -> Result<RecordProof> {
+    let (collection, rkey) = uri.record_path()?;
+    let [commit_cid] = car.roots() else {
+        return Err(invalid("expected the commit as the only root"));
+    };
+    let blocks = car
+        .blocks()
+        .map(|block| block.map(|block| (block.cid, block.data)))
+        .collect::<Result<HashMap<_, _>>>()?;
+
+    let commit = Commit::decode(block(&blocks, commit_cid)?)?;
+    if &commit.did != uri.repo() {
+        return Err(invalid(format!(
+            "commit is for {}, not {}",
+            commit.did,
+            uri.repo()
+        )));
+    }
+
+    let key = format!("{}/{}", collection, rkey);
+    let cid = find(&blocks, &commit.data, key.as_bytes())?;
+    let record = match cid
+        .as_ref()
+        .and_then(|cid| blocks.get(cid).map(|data| (cid, data)))
+    {
+        Some((cid, data)) => Some(Record {
+            uri: uri.clone(),
+            cid: cid.to_string(),
+            value: RecordValue::new(from_dag_cbor(data)?)?,
+        }),
+        None => None,
+    };
+
+    Ok(RecordProof {
+        commit_cid: commit_cid.clone(),
+        commit,
+        cid,
+        record,
+    })
+}
+
+/// A decoded MST node.
+struct Node {
+    left: Option<Cid>,
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    key: Vec<u8>,
+    value: Cid,
+    right: Option<Cid>,
+}
+
+impl Node {
+    fn decode(bytes: &[u8]) -> Result<Self> {
+        let value = from_dag_cbor(bytes)?;
+        let left = optional_link(value.get("l"))?;
+        let mut entries: Vec<Entry> = Vec::new();
+        for entry in value
+            .get("e")
+            .and_then(Value::as_array)
+            .ok_or_else(|| invalid("MST node has no entries list"))?
+        {
+            let prefix = entry.get("p").and_then(Value::as_u64);
+            let suffix = entry.get("k").and_then(dag_cbor_bytes);
+            let (Some(prefix), Some(suffix)) = (prefix, suffix) else {
+                return Err(invalid("MST entry has no key"));
+            };
+            let previous = entries.last().map_or(&[][..], |entry| &entry.key);
+            let prefix = usize::try_from(prefix)
+                .ok()
+                .filter(|prefix| *prefix <= previous.len())
+                .ok_or_else(|| invalid("MST key prefix is longer than the previous key"))?;
+            let key = [&previous[..prefix], &suffix].concat();
+            if !entries.is_empty() && key.as_slice() <= previous {
+                return Err(invalid("MST keys are not sorted"));
+            }
+            entries.push(Entry {
+                key,
+                value: entry
+                    .get("v")
+                    .and_then(link)
+                    .ok_or_else(|| invalid("MST entry has no value link"))?,
+                right: optional_link(entry.get("t"))?,
+            });
+        }
+        Ok(Self { left, entries })
+    }
+
+    /// The layer all of the node's keys are on, or `None` if it has none.
+    fn layer(&self) -> Result<Option<u32>> {
+        let mut layers = self.entries.iter().map(|entry| key_layer(&entry.key));
+        let first = layers.next();
+        if layers.any(|layer| Some(layer) != first) {
+            return Err(invalid("MST node mixes keys from different layers"));
+        }
+        Ok(first)
+    }
+}
+
+/// Walk the MST from `root` to `key`, returning the value stored there.
+fn find(blocks: &HashMap<Cid, &[u8]>, root: &Cid, key: &[u8]) -> Result<Option<Cid>> {
+    let mut cid = root.clone();
+    let mut above = None;
+    loop {
+        let node = Node::decode(block(blocks, &cid)?)?;
+        let layer = node.layer()?;
+        if let (Some(layer), Some(above)) = (layer, above)
+            && layer >= above
+        {
+            return Err(invalid("MST node is not below its parent"));
+        }
+
+        let mut next = node.left;
+        for entry in node.entries {
+            match entry.key.as_slice().cmp(key) {
+                Ordering::Equal => return Ok(Some(entry.value)),
+                Ordering::Less => next = entry.right,
+                Ordering::Greater => break,
+            }
+        }
+        match next {
+            Some(child) => cid = child,
+            None => return Ok(None),
+        }
+        above = layer.or(above);
+    }
+}
+
+/// The MST layer of a key: the leading zero bits of its SHA-256 hash,
+/// counted in pairs, so each layer has about a quarter of the keys of the
+/// one below.
+fn key_layer(key: &[u8]) -> u32 {
+    let mut zeros = 0;
+    for byte in Sha256::digest(key) {
+        zeros += byte.leading_zeros();
+        if byte != 0 {
+            break;
+        }
+    }
+    zeros / 2
+}
+
+fn block<'a>(blocks: &HashMap<Cid, &'a [u8]>, cid: &Cid) -> Result<&'a [u8]> {
+    blocks
+        .get(cid)
+        .copied()
+        .ok_or_else(|| invalid(format!("missing block {}", cid)))
+}
+
+fn link(value: &Value) -> Option<Cid> {
+    Cid::new(value.get("$link")?.as_str()?).ok()
+}
+
+fn optional_link(value: Option<&Value>) -> Result<Option<Cid>> {
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => link(value)
+            .map(Some)
+            .ok_or_else(|| invalid("MST subtree is not a link")),
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> Error {
+    InvalidInputError::Car {
+        reason: reason.into(),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::super::dag_cbor::encode_base64 as base64;
+    use super::*;
+    use crate::repo::dag_cbor_cid;
+
+    const DID: &str = "did:plc:test234aaaaaaaaaaaaaaaaa";
+
+    /// Blocks for a CAR, keyed by CID.
+    #[derive(Default)]
+    struct Blocks(Vec<(Cid, Vec<u8>)>);
+
+    impl Blocks {
+        fn put(&mut self, value: Value) -> Cid {
+            let bytes = to_dag_cbor(&value).unwrap();
+            let cid = dag_cbor_cid(&bytes);
+            self.0.push((cid.clone(), bytes));
+            cid
+        }
+
+        /// A node holding sorted `keys`, each with the given subtree to its
+        /// right, without prefix compression.
+        fn node(&mut self, left: Option<&Cid>, entries: &[(&str, &Cid, Option<&Cid>)]) -> Cid {
+            let entries: Vec<_> = entries
+                .iter()
+                .map(|(key, value, right)| {
+                    json!({
+                        "p": 0,
+                        "k": { "$bytes": base64(key.as_bytes()) },
+                        "v": { "$link": value },
+                        "t": right.map(|cid| json!({ "$link": cid })),
+                    })
+                })
+                .collect();
+            self.put(json!({ "l": left.map(|cid| json!({ "$link": cid })), "e": entries }))
+        }
+
+        fn car(&self, root: &Cid) -> Car {
+            let header = to_dag_cbor(&json!({ "version": 1, "roots": [{ "$link": root }] }));
+            let mut out = Vec::new();
+            for section in std::iter::once(header.unwrap()).chain(
+                self.0
+                    .iter()
+                    .map(|(cid, data)| [cid.to_bytes(), data.clone()].concat()),
+            ) {
+                let mut len = section.len();
+                while len >= 0x80 {
+                    out.push((len as u8 & 0x7f) | 0x80);
+                    len >>= 7;
+                }
+                out.push(len as u8);
+                out.extend(section);
+            }
+            Car::from_bytes(out).unwrap()
+        }
+    }
+
+    fn commit(blocks: &mut Blocks, data: &Cid) -> Cid {
+        blocks.put(json!({
+            "did": DID,
+            "version": 3,
+            "data": { "$link": data },
+            "rev": "3k2a",
+            "prev": null,
+            "sig": { "$bytes": base64(&[7; 64]) },
+        }))
+    }
+
+    fn uri(key: &str) -> AtUri {
+        AtUri::new(format!("at://{}/{}", DID, key)).unwrap()
+    }
+
+    /// The first `count` post keys on `layer`, in order.
+    fn keys_on_layer(layer: u32, count: usize) -> Vec<String> {
+        (0..)
+            .map(|i| format!("app.bsky.feed.post/{:06}", i))
+            .filter(|key| key_layer(key.as_bytes()) == layer)
+            .take(count)
+            .collect()
+    }
+
+    #[test]
+    fn proves_records_in_a_single_node() {
+        let mut blocks = Blocks::default();
+        let keys = keys_on_layer(0, 3);
+        let record = blocks.put(json!({ "$type": "app.bsky.feed.post", "text": "hi" }));
+        let other = dag_cbor_cid(b"not in the CAR");
+        let root = blocks.node(
+            None,
+            &[
+                (&keys[0], &record, None),
+                (&keys[1], &other, None),
+                (&keys[2], &other, None),
+            ],
+        );
+        let commit_cid = commit(&mut blocks, &root);
+        let car = blocks.car(&commit_cid);
+
+        let proof = verify_inclusion(&car, &uri(&keys[0])).unwrap();
+        assert_eq!(proof.commit_cid, commit_cid);
+        assert_eq!(proof.commit.data, root);
+        assert_eq!(proof.commit.sig, [7; 64]);
+        assert_eq!(proof.cid.as_ref(), Some(&record));
+        assert_eq!(proof.record.unwrap().value.get("text"), Some(&json!("hi")));
+
+        // The record's block need not be there to prove its CID.
+        let proof = verify_inclusion(&car, &uri(&keys[1])).unwrap();
+        assert_eq!(proof.cid, Some(other));
+        assert!(proof.record.is_none());
+
+        let proof = verify_inclusion(&car, &uri("app.bsky.feed.post/zzz")).unwrap();
+        assert!(proof.cid.is_none());
+    }
+
+    #[test]
+    fn walks_subtrees() {
+        let mut blocks = Blocks::default();
+        let top = &keys_on_layer(1, 1)[0];
+        let low = keys_on_layer(0, 40);
+        let (before, after): (Vec<_>, Vec<_>) = low.iter().partition(|key| *key < top);
+        let (record_a, record_b) = (dag_cbor_cid(b"a"), dag_cbor_cid(b"b"));
+        let left = blocks.node(None, &[(before[0], &record_a, None)]);
+        let right = blocks.node(None, &[(after[0], &record_b, None)]);
+        let root = blocks.node(Some(&left), &[(top, &record_a, Some(&right))]);
+        let commit_cid = commit(&mut blocks, &root);
+        let car = blocks.car(&commit_cid);
+
+        assert_eq!(
+            verify_inclusion(&car, &uri(top)).unwrap().cid,
+            Some(record_a.clone())
+        );
+        assert_eq!(
+            verify_inclusion(&car, &uri(before[0])).unwrap().cid,
+            Some(record_a)
+        );
+        assert_eq!(
+            verify_inclusion(&car, &uri(after[0])).unwrap().cid,
+            Some(record_b)
+        );
+        assert!(
+            verify_inclusion(&car, &uri(after[1]))
+                .unwrap()
+                .cid
+                .is_none()
+        );
+
+        // A subtree on the same layer as its parent is not a valid MST.
+        let mut blocks = Blocks::default();
+        let child = blocks.node(None, &[(after[0], &dag_cbor_cid(b"b"), None)]);
+        let root = blocks.node(None, &[(before[0], &dag_cbor_cid(b"a"), Some(&child))]);
+        let commit_cid = commit(&mut blocks, &root);
+        let err = verify_inclusion(&blocks.car(&commit_cid), &uri(after[0])).unwrap_err();
+        assert!(err.to_string().contains("below its parent"), "{}", err);
+    }
+
+    #[test]
+    fn rejects_incomplete_or_foreign_proofs() {
+        let mut blocks = Blocks::default();
+        let keys = keys_on_layer(0, 1);
+        let missing = dag_cbor_cid(b"missing node");
+        let commit_cid = commit(&mut blocks, &missing);
+        let err = verify_inclusion(&blocks.car(&commit_cid), &uri(&keys[0])).unwrap_err();
+        assert!(err.to_string().contains("missing block"), "{}", err);
+
+        let other = AtUri::new(format!("at://did:plc:other234aaaaaaaaaaaaaaaa/{}", keys[0]));
+        let err = verify_inclusion(&blocks.car(&commit_cid), &other.unwrap()).unwrap_err();
+        assert!(err.to_string().contains("commit is for"), "{}", err);
+    }
+
+    #[test]
+    fn unsigned_bytes_drop_the_signature() {
+        let data = dag_cbor_cid(b"root");
+        let unsigned = json!({
+            "did": DID,
+            "version": 3,
+            "data": { "$link": data },
+            "rev": "3k2a",
+            "prev": null,
+        });
+        let mut signed = unsigned.clone();
+        signed["sig"] = json!({ "$bytes": base64(&[1, 2, 3]) });
+
+        let commit = Commit::decode(&to_dag_cbor(&signed).unwrap()).unwrap();
+        assert_eq!(commit.unsigned_bytes(), to_dag_cbor(&unsigned).unwrap());
+        assert_eq!(commit.sig, [1, 2, 3]);
+        assert!(Commit::decode(&to_dag_cbor(&unsigned).unwrap()).is_err());
+    }
+}
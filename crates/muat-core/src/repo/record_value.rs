@@ -3,10 +3,14 @@
 //! This module provides [`RecordValue`], a type that guarantees the value
 //! is a valid AT Protocol record payload (a JSON object with a `$type` field).
 
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 
+use super::dag_cbor::{KeyOrder, Ordered, dag_cbor_cid, to_dag_cbor};
 use crate::error::{Error, InvalidInputError};
+use crate::traits::Lexicon;
+use crate::types::{BlobRef, Cid};
 
 /// A validated AT Protocol record value.
 ///
@@ -81,6 +85,62 @@ impl RecordValue {
         Self::new(value)
     }
 
+    /// Create a record from a typed [`Lexicon`] value, setting `$type` to
+    /// its [`TYPE`](Lexicon::TYPE).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value does not serialize to a JSON object.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use muat_core::{Lexicon, RecordValue};
+    /// use serde::{Deserialize, Serialize};
+    ///
+    /// #[derive(Serialize, Deserialize)]
+    /// struct Note {
+    ///     text: String,
+    /// }
+    ///
+    /// impl Lexicon for Note {
+    ///     const NSID: &'static str = "org.example.note";
+    /// }
+    ///
+    /// let value = RecordValue::from_lexicon(&Note { text: "hi".into() }).unwrap();
+    /// assert_eq!(value.record_type(), "org.example.note");
+    /// let note: Note = value.to_lexicon().unwrap();
+    /// assert_eq!(note.text, "hi");
+    /// ```
+    pub fn from_lexicon<T: Lexicon + Serialize>(record: &T) -> Result<Self, Error> {
+        let value = serde_json::to_value(record).map_err(|e| {
+            Error::InvalidInput(InvalidInputError::RecordValue {
+                reason: e.to_string(),
+            })
+        })?;
+        Self::with_type(T::TYPE, value)
+    }
+
+    /// Deserialize the record into a typed [`Lexicon`] value, checking that
+    /// its `$type` is the type's [`TYPE`](Lexicon::TYPE).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `$type` differs, or as
+    /// [`deserialize_as`](Self::deserialize_as) does.
+    pub fn to_lexicon<T: Lexicon + DeserializeOwned>(&self) -> Result<T, Error> {
+        if self.record_type() != T::TYPE {
+            return Err(Error::InvalidInput(InvalidInputError::RecordValue {
+                reason: format!(
+                    "expected a {} record, found {}",
+                    T::TYPE,
+                    self.record_type()
+                ),
+            }));
+        }
+        self.deserialize_as()
+    }
+
     /// Get the `$type` field value.
     ///
     /// This is guaranteed to return a valid string due to construction invariants.
@@ -104,6 +164,127 @@ impl RecordValue {
         self.0.get(key)
     }
 
+    /// Deserialize the record into a typed struct.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the path of the offending field and what was
+    /// expected there, such as
+    /// ``at `embed.images[0].alt`: invalid type: integer `3`, expected a string``.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use muat_core::repo::RecordValue;
+    /// use serde::Deserialize;
+    /// use serde_json::json;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Post {
+    ///     text: String,
+    /// }
+    ///
+    /// let value = RecordValue::new(json!({
+    ///     "$type": "app.bsky.feed.post",
+    ///     "text": "Hello, world!"
+    /// })).unwrap();
+    ///
+    /// let post: Post = value.deserialize_as().unwrap();
+    /// assert_eq!(post.text, "Hello, world!");
+    /// ```
+    pub fn deserialize_as<T: DeserializeOwned>(&self) -> Result<T, Error> {
+        serde_path_to_error::deserialize(&self.0).map_err(|e| {
+            let path = e.path().to_string();
+            let reason = if path == "." {
+                e.inner().to_string()
+            } else {
+                format!("at `{}`: {}", path, e.inner())
+            };
+            Error::InvalidInput(InvalidInputError::RecordValue { reason })
+        })
+    }
+
+    /// Collect the blobs embedded anywhere in the record.
+    ///
+    /// Any object with `"$type": "blob"` is parsed as a [`BlobRef`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an embedded blob has an invalid CID, MIME type
+    /// or size.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use muat_core::repo::RecordValue;
+    /// use serde_json::json;
+    ///
+    /// let value = RecordValue::new(json!({
+    ///     "$type": "org.example.photo",
+    ///     "image": {
+    ///         "$type": "blob",
+    ///         "ref": {"$link": "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e"},
+    ///         "mimeType": "image/png",
+    ///         "size": 1024
+    ///     }
+    /// })).unwrap();
+    ///
+    /// let blobs = value.blob_refs().unwrap();
+    /// assert_eq!(blobs[0].mime_type, "image/png");
+    /// ```
+    pub fn blob_refs(&self) -> Result<Vec<BlobRef>, Error> {
+        let mut blobs = Vec::new();
+        collect_blobs(&self.0, &mut blobs)?;
+        Ok(blobs)
+    }
+
+    /// Encode the record as canonical DAG-CBOR, the form it is hashed and
+    /// signed in.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the record is outside the atproto data model, e.g. it holds
+    /// a float.
+    pub fn to_dag_cbor(&self) -> Result<Vec<u8>, Error> {
+        to_dag_cbor(&self.0)
+    }
+
+    /// The record's CID, from its canonical DAG-CBOR encoding. Two values
+    /// with the same content have the same CID, whatever order their keys
+    /// were written in.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use muat_core::repo::RecordValue;
+    /// use serde_json::json;
+    ///
+    /// let a: RecordValue = serde_json::from_str(r#"{"$type": "org.example.note", "a": 1, "bb": 2}"#).unwrap();
+    /// let b: RecordValue = serde_json::from_str(r#"{"bb": 2, "a": 1, "$type": "org.example.note"}"#).unwrap();
+    /// assert_eq!(a.cid().unwrap(), b.cid().unwrap());
+    /// ```
+    pub fn cid(&self) -> Result<Cid, Error> {
+        Ok(dag_cbor_cid(&self.to_dag_cbor()?))
+    }
+
+    /// Serialize the record with its keys in `order`, for example to write
+    /// it as JSON in the same order as its DAG-CBOR encoding:
+    ///
+    /// ```
+    /// use muat_core::repo::{KeyOrder, RecordValue};
+    /// use serde_json::json;
+    ///
+    /// let value = RecordValue::with_type("org.example.note", json!({ "text": "hi" })).unwrap();
+    /// let json = serde_json::to_string(&value.with_key_order(KeyOrder::Canonical)).unwrap();
+    /// assert_eq!(json, r#"{"text":"hi","$type":"org.example.note"}"#);
+    /// ```
+    pub fn with_key_order(&self, order: KeyOrder) -> impl Serialize + '_ {
+        Ordered {
+            value: &self.0,
+            order,
+        }
+    }
+
     fn validate(value: &Value) -> Result<(), Error> {
         let obj = value.as_object().ok_or_else(|| {
             Error::InvalidInput(InvalidInputError::RecordValue {
@@ -127,6 +308,31 @@ impl RecordValue {
     }
 }
 
+fn collect_blobs(value: &Value, blobs: &mut Vec<BlobRef>) -> Result<(), Error> {
+    match value {
+        Value::Object(obj) if obj.get("$type").and_then(Value::as_str) == Some("blob") => {
+            let blob = BlobRef::deserialize(value).map_err(|e| {
+                Error::InvalidInput(InvalidInputError::RecordValue {
+                    reason: format!("invalid blob: {}", e),
+                })
+            })?;
+            blobs.push(blob);
+        }
+        Value::Object(obj) => {
+            for child in obj.values() {
+                collect_blobs(child, blobs)?;
+            }
+        }
+        Value::Array(items) => {
+            for child in items {
+                collect_blobs(child, blobs)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
 impl Serialize for RecordValue {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -190,6 +396,23 @@ mod tests {
         assert_eq!(value.record_type(), "org.example.new");
     }
 
+    #[test]
+    fn test_cid_hashes_canonical_dag_cbor() {
+        let value = RecordValue::new(json!({
+            "$type": "app.bsky.feed.post",
+            "text": "Hello, world!",
+            "createdAt": "2024-01-01T00:00:00.000Z"
+        }))
+        .unwrap();
+        assert_eq!(
+            value.cid().unwrap().as_str(),
+            "bafyreiaxwnwm6j7d5eccgx2fo3qa6q3x5fghq5lcep4qk7sbcf55qsmyo4"
+        );
+
+        let float = RecordValue::with_type("org.example.test", json!({ "n": 0.5 })).unwrap();
+        assert!(float.cid().is_err());
+    }
+
     #[test]
     fn test_missing_type_fails() {
         let result = RecordValue::new(json!({
@@ -249,4 +472,67 @@ mod tests {
         let serialized = serde_json::to_value(&value).unwrap();
         assert_eq!(serialized, original);
     }
+
+    #[test]
+    fn test_blob_refs() {
+        let blob = json!({
+            "$type": "blob",
+            "ref": {"$link": "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e"},
+            "mimeType": "image/jpeg",
+            "size": 42
+        });
+        let value = RecordValue::new(json!({
+            "$type": "org.example.gallery",
+            "images": [{"alt": "one", "image": blob}, {"alt": "two", "image": blob}]
+        }))
+        .unwrap();
+        assert_eq!(value.blob_refs().unwrap().len(), 2);
+
+        let invalid = RecordValue::new(json!({
+            "$type": "org.example.gallery",
+            "image": {"$type": "blob", "ref": {"$link": "bafkreiblob"}, "mimeType": "image/jpeg", "size": 1}
+        }))
+        .unwrap();
+        assert!(invalid.blob_refs().is_err());
+    }
+
+    #[test]
+    fn test_deserialize_as_reports_field_path() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Image {
+            alt: String,
+        }
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Gallery {
+            title: String,
+            images: Vec<Image>,
+        }
+
+        let value = RecordValue::new(json!({
+            "$type": "org.example.gallery",
+            "title": "Holiday",
+            "images": [{"alt": "beach"}, {"alt": 3}]
+        }))
+        .unwrap();
+        let err = value.deserialize_as::<Gallery>().unwrap_err().to_string();
+        assert!(err.contains("at `images[1].alt`"), "{}", err);
+        assert!(err.contains("expected a string"), "{}", err);
+
+        let missing = RecordValue::new(json!({"$type": "org.example.gallery"})).unwrap();
+        let err = missing.deserialize_as::<Gallery>().unwrap_err().to_string();
+        assert!(err.contains("missing field `title`"), "{}", err);
+
+        let gallery: Gallery = RecordValue::new(json!({
+            "$type": "org.example.gallery",
+            "title": "Holiday",
+            "images": []
+        }))
+        .unwrap()
+        .deserialize_as()
+        .unwrap();
+        assert_eq!(gallery.title, "Holiday");
+    }
 }
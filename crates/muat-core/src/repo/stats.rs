@@ -0,0 +1,194 @@
+//! Ingestion counters for firehose streams.
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde::Serialize;
+
+use super::events::RepoEvent;
+use crate::Result;
+
+/// Counts of what a firehose stream has delivered.
+///
+/// Serializes to a flat JSON object, so it can be reported as-is by health
+/// endpoints and logs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct EventStats {
+    /// Commit events.
+    pub commits: u64,
+    /// Identity events.
+    pub identities: u64,
+    /// Handle events.
+    pub handles: u64,
+    /// Info events.
+    pub infos: u64,
+    /// Events of a kind this library does not decode.
+    pub unknown: u64,
+    /// Commit operations with the `create` action.
+    pub creates: u64,
+    /// Commit operations with the `update` action.
+    pub updates: u64,
+    /// Commit operations with the `delete` action.
+    pub deletes: u64,
+    /// Errors yielded by the stream, such as undecodable frames.
+    pub errors: u64,
+    /// Raw bytes read from the underlying source, where the stream knows them.
+    pub bytes_received: u64,
+    /// Events discarded because the consumer fell behind
+    /// ([`OverflowPolicy::DropOldest`](super::OverflowPolicy::DropOldest)).
+    pub dropped_events: u64,
+    /// Events received but not yet taken by the consumer. A gauge rather
+    /// than a counter: [`FirehoseStats::reset`] leaves it alone.
+    pub lag: u64,
+}
+
+impl EventStats {
+    /// Total number of events, excluding errors.
+    pub fn events(&self) -> u64 {
+        self.commits + self.identities + self.handles + self.infos + self.unknown
+    }
+
+    /// Count one item yielded by a stream.
+    pub fn record(&mut self, item: &Result<RepoEvent>) {
+        match item {
+            Ok(RepoEvent::Commit(commit)) => {
+                self.commits += 1;
+                for op in &commit.ops {
+                    match op.action.as_str() {
+                        "create" => self.creates += 1,
+                        "update" => self.updates += 1,
+                        "delete" => self.deletes += 1,
+                        _ => {}
+                    }
+                }
+            }
+            Ok(RepoEvent::Identity(_)) => self.identities += 1,
+            Ok(RepoEvent::Handle(_)) => self.handles += 1,
+            Ok(RepoEvent::Info(_)) => self.infos += 1,
+            Ok(RepoEvent::Unknown { .. }) => self.unknown += 1,
+            Err(_) => self.errors += 1,
+        }
+    }
+}
+
+/// Shared handle to a stream's [`EventStats`].
+///
+/// Clones observe the same counters, so a handle taken before the stream is
+/// moved into a filter or another task keeps reporting on it.
+#[derive(Debug, Clone, Default)]
+pub struct FirehoseStats {
+    inner: Arc<Mutex<EventStats>>,
+}
+
+impl FirehoseStats {
+    /// Create zeroed counters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A copy of the current counters.
+    pub fn snapshot(&self) -> EventStats {
+        self.lock().clone()
+    }
+
+    /// Zero all counters.
+    pub fn reset(&self) {
+        let mut stats = self.lock();
+        *stats = EventStats {
+            lag: stats.lag,
+            ..EventStats::default()
+        };
+    }
+
+    /// Count one item yielded by the stream.
+    pub fn record(&self, item: &Result<RepoEvent>) {
+        self.lock().record(item);
+    }
+
+    /// Count raw bytes read from the source.
+    pub fn add_bytes(&self, bytes: usize) {
+        self.lock().bytes_received += bytes as u64;
+    }
+
+    /// Count events discarded because the consumer fell behind.
+    pub fn add_dropped(&self, events: usize) {
+        self.lock().dropped_events += events as u64;
+    }
+
+    /// Record how many events are waiting for the consumer.
+    pub fn set_lag(&self, events: usize) {
+        self.lock().lag = events as u64;
+    }
+
+    /// Events received but not yet taken by the consumer.
+    pub fn lag(&self) -> u64 {
+        self.lock().lag
+    }
+
+    /// Events discarded because the consumer fell behind.
+    pub fn dropped_events(&self) -> u64 {
+        self.lock().dropped_events
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, EventStats> {
+        // Counters stay meaningful even if a holder panicked.
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Error, InvalidInputError};
+    use crate::repo::{CommitEvent, CommitOperation, InfoEvent};
+
+    fn commit(actions: &[&str]) -> Result<RepoEvent> {
+        Ok(RepoEvent::Commit(CommitEvent {
+            repo: "did:plc:abcaaaaaaaaaaaaaaaaaaaaa".to_string(),
+            rev: "rev".to_string(),
+            since: None,
+            seq: 1,
+            time: "2024-01-01T00:00:00Z".parse().unwrap(),
+            ops: actions
+                .iter()
+                .map(|action| CommitOperation {
+                    path: "a.b.c/1".to_string(),
+                    action: action.to_string(),
+                    cid: None,
+                })
+                .collect(),
+            blocks: Vec::new(),
+            blobs: Vec::new(),
+        }))
+    }
+
+    #[test]
+    fn counts_events_ops_and_errors() {
+        let stats = FirehoseStats::new();
+        let handle = stats.clone();
+
+        stats.record(&commit(&["create", "delete"]));
+        stats.record(&commit(&["update"]));
+        stats.record(&Ok(RepoEvent::Info(InfoEvent {
+            name: "OutdatedCursor".to_string(),
+            message: None,
+        })));
+        stats.record(&Err(Error::InvalidInput(InvalidInputError::Other {
+            message: "bad frame".to_string(),
+        })));
+        stats.add_bytes(42);
+
+        let snapshot = handle.snapshot();
+        assert_eq!(snapshot.commits, 2);
+        assert_eq!(snapshot.infos, 1);
+        assert_eq!(snapshot.events(), 3);
+        assert_eq!(
+            (snapshot.creates, snapshot.updates, snapshot.deletes),
+            (1, 1, 1)
+        );
+        assert_eq!(snapshot.errors, 1);
+        assert_eq!(snapshot.bytes_received, 42);
+
+        handle.reset();
+        assert_eq!(stats.snapshot(), EventStats::default());
+    }
+}
@@ -0,0 +1,217 @@
+//! Assigning firehose events to message broker topics.
+
+use crate::Result;
+use crate::error::{Error, InvalidInputError};
+use crate::repo::{CommitOperation, RepoEvent};
+
+/// How an [`EventSink`](crate::traits::EventSink) picks the topic each
+/// event is published to.
+///
+/// Topics are built only from characters that are valid in both NATS
+/// subjects and Kafka topic names. Collection NSIDs are used as they are,
+/// so with NATS each NSID segment is a subject token and subscribers can
+/// match them with wildcards such as `atproto.app.bsky.>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopicMapping {
+    /// Every event goes to one topic.
+    Single(String),
+    /// Commits go to `{prefix}{collection}`, split into one message per
+    /// collection holding only that collection's operations. Other events,
+    /// and commits without operations, go to `other`.
+    ByCollection { prefix: String, other: String },
+    /// Events about a repo go to `{prefix}{did}`, with every character of
+    /// the DID other than ASCII letters, digits, `-` and `_` replaced by
+    /// `_`. Events without a repo go to `other`.
+    ByRepo { prefix: String, other: String },
+}
+
+/// A message for a broker, built from an event by
+/// [`TopicMapping::messages`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SinkMessage {
+    /// The topic or subject to publish to.
+    pub topic: String,
+    /// The DID of the repo the event is about, for brokers that partition
+    /// by key so each repo's events stay in order.
+    pub key: Option<String>,
+    /// The event as JSON, tagged with its kind as [`RepoEvent`] serializes.
+    pub payload: Vec<u8>,
+}
+
+impl TopicMapping {
+    /// Publish every event to `topic`.
+    pub fn single(topic: impl Into<String>) -> Self {
+        Self::Single(topic.into())
+    }
+
+    /// Publish commits to a topic per collection under `prefix`, and other
+    /// events to `other`.
+    pub fn by_collection(prefix: impl Into<String>, other: impl Into<String>) -> Self {
+        Self::ByCollection {
+            prefix: prefix.into(),
+            other: other.into(),
+        }
+    }
+
+    /// Publish events to a topic per repo under `prefix`, and events
+    /// without a repo to `other`.
+    pub fn by_repo(prefix: impl Into<String>, other: impl Into<String>) -> Self {
+        Self::ByRepo {
+            prefix: prefix.into(),
+            other: other.into(),
+        }
+    }
+
+    /// Split `event` into the events to publish, each with its topic.
+    pub fn route(&self, event: &RepoEvent) -> Vec<(String, RepoEvent)> {
+        match (self, event) {
+            (Self::Single(topic), _) => vec![(topic.clone(), event.clone())],
+            (Self::ByCollection { prefix, .. }, RepoEvent::Commit(commit))
+                if !commit.ops.is_empty() =>
+            {
+                let mut groups: Vec<(&str, Vec<CommitOperation>)> = Vec::new();
+                for op in &commit.ops {
+                    let collection = op.path.split('/').next().unwrap_or_default();
+                    match groups.iter_mut().find(|(c, _)| *c == collection) {
+                        Some((_, ops)) => ops.push(op.clone()),
+                        None => groups.push((collection, vec![op.clone()])),
+                    }
+                }
+                groups
+                    .into_iter()
+                    .map(|(collection, ops)| {
+                        let mut commit = commit.clone();
+                        commit.ops = ops;
+                        (
+                            format!("{}{}", prefix, collection),
+                            RepoEvent::Commit(commit),
+                        )
+                    })
+                    .collect()
+            }
+            (Self::ByCollection { other, .. }, _) => vec![(other.clone(), event.clone())],
+            (Self::ByRepo { prefix, other }, _) => {
+                let topic = match event.repo() {
+                    Some(repo) => format!("{}{}", prefix, topic_safe(repo)),
+                    None => other.clone(),
+                };
+                vec![(topic, event.clone())]
+            }
+        }
+    }
+
+    /// Build the messages to publish for `event`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the event cannot be serialized.
+    pub fn messages(&self, event: &RepoEvent) -> Result<Vec<SinkMessage>> {
+        self.route(event)
+            .into_iter()
+            .map(|(topic, event)| {
+                let payload = serde_json::to_vec(&event).map_err(|e| {
+                    Error::InvalidInput(InvalidInputError::Other {
+                        message: format!("Failed to serialize event: {}", e),
+                    })
+                })?;
+                Ok(SinkMessage {
+                    topic,
+                    key: event.repo().map(str::to_string),
+                    payload,
+                })
+            })
+            .collect()
+    }
+}
+
+/// `value` with characters that are not valid in every broker's topic
+/// names replaced by `_`.
+fn topic_safe(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::repo::{CommitEvent, InfoEvent};
+
+    fn commit(paths: &[&str]) -> RepoEvent {
+        RepoEvent::Commit(CommitEvent {
+            repo: "did:web:example.com".to_string(),
+            rev: "3k2a".to_string(),
+            since: None,
+            seq: 7,
+            time: Utc::now(),
+            ops: paths
+                .iter()
+                .map(|path| CommitOperation {
+                    path: path.to_string(),
+                    action: "create".to_string(),
+                    cid: None,
+                })
+                .collect(),
+            blocks: Vec::new(),
+            blobs: Vec::new(),
+        })
+    }
+
+    fn info() -> RepoEvent {
+        RepoEvent::Info(InfoEvent {
+            name: "OutdatedCursor".to_string(),
+            message: None,
+        })
+    }
+
+    fn ops(event: &RepoEvent) -> Vec<&str> {
+        match event {
+            RepoEvent::Commit(commit) => commit.ops.iter().map(|op| op.path.as_str()).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn by_collection_splits_commits() {
+        let mapping = TopicMapping::by_collection("atproto.", "atproto.other");
+        let routed = mapping.route(&commit(&["a.b.post/1", "a.b.like/2", "a.b.post/3"]));
+        let topics: Vec<_> = routed.iter().map(|(topic, _)| topic.as_str()).collect();
+        assert_eq!(topics, ["atproto.a.b.post", "atproto.a.b.like"]);
+        assert_eq!(ops(&routed[0].1), ["a.b.post/1", "a.b.post/3"]);
+        assert_eq!(ops(&routed[1].1), ["a.b.like/2"]);
+
+        let routed = mapping.route(&info());
+        assert_eq!(routed.len(), 1);
+        assert_eq!(routed[0].0, "atproto.other");
+    }
+
+    #[test]
+    fn by_repo_makes_dids_topic_safe() {
+        let mapping = TopicMapping::by_repo("repo.", "repo.other");
+        let routed = mapping.route(&commit(&["a.b.post/1", "a.b.like/2"]));
+        assert_eq!(routed.len(), 1);
+        assert_eq!(routed[0].0, "repo.did_web_example_com");
+        assert_eq!(mapping.route(&info())[0].0, "repo.other");
+    }
+
+    #[test]
+    fn messages_are_tagged_json_keyed_by_repo() {
+        let messages = TopicMapping::single("events")
+            .messages(&commit(&["a.b.post/1"]))
+            .unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].key.as_deref(), Some("did:web:example.com"));
+        let json: serde_json::Value = serde_json::from_slice(&messages[0].payload).unwrap();
+        assert_eq!(json["event"], "commit");
+        assert_eq!(json["seq"], 7);
+    }
+}
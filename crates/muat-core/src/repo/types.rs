@@ -1,6 +1,10 @@
 //! Repository operation types.
 
-use crate::types::AtUri;
+use crate::Result;
+use crate::error::{Error, InvalidInputError};
+use crate::types::{AtUri, Tid};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
 use super::RecordValue;
@@ -21,6 +25,22 @@ pub struct Record {
     pub value: RecordValue,
 }
 
+impl Record {
+    /// Deserialize the record value into a typed struct.
+    ///
+    /// See [`RecordValue::deserialize_as`]; errors also name the record URI.
+    pub fn parse<T: DeserializeOwned>(&self) -> Result<T> {
+        self.value.deserialize_as().map_err(|e| match e {
+            Error::InvalidInput(InvalidInputError::RecordValue { reason }) => {
+                Error::InvalidInput(InvalidInputError::RecordValue {
+                    reason: format!("{}: {}", self.uri, reason),
+                })
+            }
+            other => other,
+        })
+    }
+}
+
 /// Output from listing records in a collection.
 #[derive(Debug, Clone)]
 pub struct ListRecordsOutput {
@@ -30,3 +50,251 @@ pub struct ListRecordsOutput {
     /// Cursor for the next page, if more records exist.
     pub cursor: Option<String>,
 }
+
+/// Order of records returned by [`Session::list_records_with`](crate::Session::list_records_with).
+///
+/// Records are ordered by record key. Every backend applies the same order,
+/// and cursors continue in the order they were issued for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RecordOrder {
+    /// Ascending record key (oldest first for TID keys).
+    #[default]
+    Ascending,
+    /// Descending record key (newest first for TID keys).
+    Descending,
+}
+
+/// Options for listing records in a collection.
+///
+/// # Example
+///
+/// ```
+/// use muat_core::repo::{ListRecordsOptions, RecordOrder};
+///
+/// let options = ListRecordsOptions::new().limit(10).order(RecordOrder::Descending);
+/// assert_eq!(options.get_limit(), Some(10));
+/// assert_eq!(options.get_order(), RecordOrder::Descending);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListRecordsOptions {
+    limit: Option<u32>,
+    cursor: Option<String>,
+    order: RecordOrder,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+}
+
+impl ListRecordsOptions {
+    /// Create options with the defaults: backend page size, first page,
+    /// ascending order.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of records per page.
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Continue from a cursor returned by a previous page.
+    pub fn cursor(mut self, cursor: impl Into<String>) -> Self {
+        self.cursor = Some(cursor.into());
+        self
+    }
+
+    /// Set the record order.
+    pub fn order(mut self, order: RecordOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Shorthand for [`RecordOrder::Descending`] when `reverse` is true.
+    pub fn reverse(self, reverse: bool) -> Self {
+        self.order(if reverse {
+            RecordOrder::Descending
+        } else {
+            RecordOrder::Ascending
+        })
+    }
+
+    /// Returns the page size, if set.
+    pub fn get_limit(&self) -> Option<u32> {
+        self.limit
+    }
+
+    /// Returns the cursor, if set.
+    pub fn get_cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+
+    /// Returns the record order.
+    pub fn get_order(&self) -> RecordOrder {
+        self.order
+    }
+
+    /// Only list records whose TID record key is at or after `time`.
+    ///
+    /// With a time bound set, records whose keys are not TIDs are skipped.
+    /// Bounds are applied as a record key range, so backends can start
+    /// listing at the window instead of scanning the whole collection.
+    pub fn since(mut self, time: DateTime<Utc>) -> Self {
+        self.since = Some(time);
+        self
+    }
+
+    /// Only list records whose TID record key is before `time`.
+    ///
+    /// See [`since`](Self::since).
+    pub fn until(mut self, time: DateTime<Utc>) -> Self {
+        self.until = Some(time);
+        self
+    }
+
+    /// Returns the lower time bound, if set.
+    pub fn get_since(&self) -> Option<DateTime<Utc>> {
+        self.since
+    }
+
+    /// Returns the upper time bound, if set.
+    pub fn get_until(&self) -> Option<DateTime<Utc>> {
+        self.until
+    }
+
+    /// Whether a record key falls inside the `since`/`until` window.
+    ///
+    /// Always true when no bound is set.
+    pub fn in_window(&self, rkey: &str) -> bool {
+        if self.since.is_none() && self.until.is_none() {
+            return true;
+        }
+        Tid::new(rkey).is_ok() && !self.before_window(rkey) && !self.after_window(rkey)
+    }
+
+    /// Whether a record key, and every key after it in the listing order,
+    /// lies outside the window, so listing can stop.
+    pub fn past_window(&self, rkey: &str) -> bool {
+        match self.order {
+            RecordOrder::Ascending => self.after_window(rkey),
+            RecordOrder::Descending => self.before_window(rkey),
+        }
+    }
+
+    /// The cursor to list from: the caller's cursor, moved forward to the
+    /// start of the window when the window begins later.
+    ///
+    /// This relies on cursors being the last record key listed, as they are
+    /// on the reference PDS and the file backend.
+    pub fn window_cursor(&self) -> Option<String> {
+        let (bound, descending) = match self.order {
+            // Ascending lists keys after the cursor, so start just before `since`.
+            RecordOrder::Ascending => (
+                self.since
+                    .and_then(|t| Tid::from_timestamp(t, 0).predecessor()),
+                false,
+            ),
+            // Descending lists keys before the cursor.
+            RecordOrder::Descending => (self.until.map(|t| Tid::from_timestamp(t, 0)), true),
+        };
+        let bound = bound.map(String::from);
+        match (self.cursor.clone(), bound) {
+            (Some(cursor), Some(bound)) => Some(if descending {
+                cursor.min(bound)
+            } else {
+                cursor.max(bound)
+            }),
+            (cursor, bound) => cursor.or(bound),
+        }
+    }
+
+    fn before_window(&self, rkey: &str) -> bool {
+        self.since
+            .is_some_and(|t| rkey < Tid::from_timestamp(t, 0).as_str())
+    }
+
+    fn after_window(&self, rkey: &str) -> bool {
+        self.until
+            .is_some_and(|t| rkey >= Tid::from_timestamp(t, 0).as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tid(time: &str) -> String {
+        Tid::from_timestamp(time.parse().unwrap(), 0).into()
+    }
+
+    #[test]
+    fn window_bounds_tid_keys() {
+        let options = ListRecordsOptions::new()
+            .since("2024-01-01T00:00:00Z".parse().unwrap())
+            .until("2024-02-01T00:00:00Z".parse().unwrap());
+
+        assert!(options.in_window(&tid("2024-01-01T00:00:00Z")));
+        assert!(options.in_window(&tid("2024-01-15T00:00:00Z")));
+        assert!(!options.in_window(&tid("2023-12-31T23:59:59Z")));
+        assert!(!options.in_window(&tid("2024-02-01T00:00:00Z")));
+        assert!(!options.in_window("self"));
+        assert!(ListRecordsOptions::new().in_window("self"));
+
+        assert!(options.past_window(&tid("2024-02-01T00:00:00Z")));
+        assert!(
+            !options
+                .clone()
+                .reverse(true)
+                .past_window(&tid("2024-02-01T00:00:00Z"))
+        );
+        assert!(
+            options
+                .clone()
+                .reverse(true)
+                .past_window(&tid("2023-12-31T00:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn window_cursor_starts_at_the_window() {
+        let since = "2024-01-01T00:00:00Z".parse().unwrap();
+        let options = ListRecordsOptions::new().since(since);
+        let cursor = options.window_cursor().unwrap();
+        assert!(cursor < tid("2024-01-01T00:00:00Z"));
+        assert!(cursor >= tid("2023-12-31T23:59:59.999999Z"));
+
+        // A cursor already inside the window is kept.
+        let later = tid("2024-03-01T00:00:00Z");
+        assert_eq!(options.cursor(later.clone()).window_cursor(), Some(later));
+
+        let until = ListRecordsOptions::new()
+            .reverse(true)
+            .until("2024-02-01T00:00:00Z".parse().unwrap());
+        assert_eq!(until.window_cursor(), Some(tid("2024-02-01T00:00:00Z")));
+        assert_eq!(ListRecordsOptions::new().window_cursor(), None);
+    }
+
+    #[test]
+    fn parse_names_the_record() {
+        #[derive(Debug, Deserialize)]
+        struct Post {
+            text: String,
+        }
+
+        let record = Record {
+            uri: AtUri::new(
+                "at://did:plc:abcaaaaaaaaaaaaaaaaaaaaa/app.bsky.feed.post/3jui7kd54zh2y",
+            )
+            .unwrap(),
+            cid: "bafyrecord".to_string(),
+            value: RecordValue::new(serde_json::json!({
+                "$type": "app.bsky.feed.post",
+                "text": "hello"
+            }))
+            .unwrap(),
+        };
+        assert_eq!(record.parse::<Post>().unwrap().text, "hello");
+
+        let err = record.parse::<Vec<String>>().unwrap_err().to_string();
+        assert!(err.contains(&record.uri.to_string()), "{}", err);
+    }
+}
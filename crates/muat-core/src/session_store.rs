@@ -0,0 +1,69 @@
+//! In-memory [`SessionStore`].
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+
+use crate::Result;
+use crate::traits::{SessionStore, StoredSession};
+
+/// A [`SessionStore`] that keeps sessions in memory, for tests and
+/// short-lived processes. Clones share the same sessions.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySessionStore {
+    sessions: Arc<Mutex<HashMap<String, StoredSession>>>,
+}
+
+impl MemorySessionStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn sessions(&self) -> std::sync::MutexGuard<'_, HashMap<String, StoredSession>> {
+        // A panic while holding the lock cannot leave the map half-updated.
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemorySessionStore {
+    async fn load(&self, key: &str) -> Result<Option<StoredSession>> {
+        Ok(self.sessions().get(key).cloned())
+    }
+
+    async fn save(&self, key: &str, session: &StoredSession) -> Result<()> {
+        self.sessions().insert(key.to_string(), session.clone());
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        self.sessions().remove(key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Did, PdsUrl};
+    use crate::{AccessToken, RefreshToken};
+
+    #[tokio::test]
+    async fn memory_store_clones_share_sessions() {
+        let store = MemorySessionStore::new();
+        let session = StoredSession {
+            pds: PdsUrl::new("https://pds.example.com").unwrap(),
+            did: Did::new("did:plc:test234aaaaaaaaaaaaaaaaa").unwrap(),
+            access_token: AccessToken::new("access"),
+            refresh_token: Some(RefreshToken::new("refresh")),
+        };
+        store.clone().save("a", &session).await.unwrap();
+        assert!(store.load("a").await.unwrap().is_some());
+        assert!(!format!("{:?}", store).contains("access\""));
+
+        store.remove("a").await.unwrap();
+        assert!(store.load("a").await.unwrap().is_none());
+    }
+}
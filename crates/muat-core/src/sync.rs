@@ -0,0 +1,182 @@
+//! Comparing a repository's contents across backends.
+//!
+//! [`diff_repos`] reads every record of a repo from two sessions and
+//! reports which records differ, by collection, rkey and CID. Mirroring,
+//! tests and migration checks use it to see what is out of step.
+//! [`diff_repos_public`] does the same through public reads, without a
+//! session on either side.
+
+use std::collections::BTreeMap;
+
+use futures_util::TryStreamExt;
+
+use crate::Result;
+use crate::error::Error;
+use crate::repo::ListRecordsOptions;
+use crate::traits::{Pds, Session};
+use crate::types::{Did, Nsid, Rkey};
+
+/// A record that differs between two copies of a repo.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordChange {
+    /// The record's collection.
+    pub collection: Nsid,
+    /// The record key.
+    pub rkey: Rkey,
+    /// The record's CID in the first repo, if it is there.
+    pub old_cid: Option<String>,
+    /// The record's CID in the second repo, if it is there.
+    pub new_cid: Option<String>,
+}
+
+/// The changes that turn one copy of a repo into another, returned by
+/// [`diff_repos`].
+///
+/// Each list is ordered by collection, then rkey.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepoDiff {
+    /// Records only in the second repo.
+    pub created: Vec<RecordChange>,
+    /// Records in both repos with different CIDs.
+    pub updated: Vec<RecordChange>,
+    /// Records only in the first repo.
+    pub deleted: Vec<RecordChange>,
+    /// Number of records identical in both repos.
+    pub unchanged: usize,
+}
+
+impl RepoDiff {
+    /// Returns whether the two repos hold the same records.
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.updated.is_empty() && self.deleted.is_empty()
+    }
+
+    /// Total number of records that differ.
+    pub fn len(&self) -> usize {
+        self.created.len() + self.updated.len() + self.deleted.len()
+    }
+}
+
+/// Compare `repo` as stored by `a` with `repo` as stored by `b`.
+///
+/// The diff describes what changes `a`'s copy into `b`'s: `created`
+/// records are only in `b`, `deleted` records only in `a`. Records are
+/// matched by collection and rkey and compared by CID, so both backends
+/// must compute CIDs the same way, as DAG-CBOR backends do.
+///
+/// # Errors
+///
+/// Returns the first error from listing either repo.
+pub async fn diff_repos<A, B>(a: &A, b: &B, repo: &Did) -> Result<RepoDiff>
+where
+    A: Session + ?Sized,
+    B: Session + ?Sized,
+{
+    diff_repos_between(a, repo, b, repo).await
+}
+
+/// Compare `a_repo` as stored by `a` with `b_repo` as stored by `b`, as
+/// [`diff_repos`] does, for copies of a repo under different DIDs, such as
+/// a mirror kept in a local account.
+///
+/// # Errors
+///
+/// Returns the first error from listing either repo.
+pub async fn diff_repos_between<A, B>(a: &A, a_repo: &Did, b: &B, b_repo: &Did) -> Result<RepoDiff>
+where
+    A: Session + ?Sized,
+    B: Session + ?Sized,
+{
+    let old = record_cids(a, a_repo).await?;
+    let new = record_cids(b, b_repo).await?;
+    Ok(diff_cids(old, new))
+}
+
+/// Compare `repo` as hosted by `a` with `repo` as hosted by `b`, as
+/// [`diff_repos`] does, reading both with the public `listRecords`.
+///
+/// Use this when neither side has a session for the repo, such as when
+/// comparing a network PDS with a local mirror of one of its repos.
+///
+/// # Errors
+///
+/// Returns the first error from listing either repo.
+pub async fn diff_repos_public<A, B>(a: &A, b: &B, repo: &Did) -> Result<RepoDiff>
+where
+    A: Pds + ?Sized,
+    B: Pds + ?Sized,
+{
+    let old = public_record_cids(a, repo).await?;
+    let new = public_record_cids(b, repo).await?;
+    Ok(diff_cids(old, new))
+}
+
+fn diff_cids(old: RecordCids, mut new: RecordCids) -> RepoDiff {
+    let mut diff = RepoDiff::default();
+    for (key, (collection, rkey, old_cid)) in old {
+        match new.remove(&key) {
+            Some((_, _, new_cid)) if new_cid == old_cid => diff.unchanged += 1,
+            Some((_, _, new_cid)) => diff.updated.push(RecordChange {
+                collection,
+                rkey,
+                old_cid: Some(old_cid),
+                new_cid: Some(new_cid),
+            }),
+            None => diff.deleted.push(RecordChange {
+                collection,
+                rkey,
+                old_cid: Some(old_cid),
+                new_cid: None,
+            }),
+        }
+    }
+    diff.created = new
+        .into_values()
+        .map(|(collection, rkey, new_cid)| RecordChange {
+            collection,
+            rkey,
+            old_cid: None,
+            new_cid: Some(new_cid),
+        })
+        .collect();
+    diff
+}
+
+type RecordCids = BTreeMap<(String, String), (Nsid, Rkey, String)>;
+
+/// Every record in `repo`, keyed by collection and rkey, with its CID.
+async fn record_cids<S: Session + ?Sized>(session: &S, repo: &Did) -> Result<RecordCids> {
+    session
+        .iter_all_records(repo)
+        .try_fold(RecordCids::new(), |mut cids, record| async move {
+            let (collection, rkey) = record.uri.record_path()?;
+            let key = (collection.to_string(), rkey.to_string());
+            cids.insert(key, (collection.clone(), rkey.clone(), record.cid));
+            Ok::<_, Error>(cids)
+        })
+        .await
+}
+
+/// Like [`record_cids`], reading `repo` from `pds` without a session.
+async fn public_record_cids<P: Pds + ?Sized>(pds: &P, repo: &Did) -> Result<RecordCids> {
+    let mut cids = RecordCids::new();
+    for collection in pds.list_collections_public(repo).await? {
+        let mut options = ListRecordsOptions::new();
+        loop {
+            let page = pds.list_records_public(repo, &collection, &options).await?;
+            let empty = page.records.is_empty();
+            for record in page.records {
+                let (collection, rkey) = record.uri.record_path()?;
+                let key = (collection.to_string(), rkey.to_string());
+                cids.insert(key, (collection.clone(), rkey.clone(), record.cid));
+            }
+            match page.cursor {
+                Some(cursor) if !empty && options.get_cursor() != Some(cursor.as_str()) => {
+                    options = options.cursor(cursor);
+                }
+                _ => break,
+            }
+        }
+    }
+    Ok(cids)
+}
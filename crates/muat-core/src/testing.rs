@@ -0,0 +1,1247 @@
+//! Conformance suite for [`Pds`] and [`Session`] implementations.
+//!
+//! Enabled with the `testing` feature. Every backend should behave the same
+//! way from a caller's point of view; these checks pin down that behaviour so
+//! a new backend can prove it. Each check panics with a descriptive message
+//! on the first violation, so it can be called directly from a test.
+//!
+//! The simplest way to run the whole suite is the [`conformance_tests!`]
+//! macro, which expands to one `#[tokio::test]` per check:
+//!
+//! ```ignore
+//! use muat_core::testing::Fixture;
+//!
+//! async fn fixture() -> Fixture<MyPds> {
+//!     let pds = MyPds::new();
+//!     pds.create_account("alice.test", Some("password"), None, None).await.unwrap();
+//!     Fixture::new(pds, "alice.test", "password")
+//! }
+//!
+//! muat_core::conformance_tests!(fixture());
+//! ```
+//!
+//! [`conformance_tests!`]: crate::conformance_tests
+
+use std::any::Any;
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures_core::Stream;
+use serde_json::json;
+
+use crate::credentials::Credentials;
+use crate::repo::{
+    Backfiller, ByteRange, CommitEvent, CommitOperation, DeleteCollectionOptions,
+    ListRecordsOptions, MigrateOptions, RecordOrder, RecordValue, RepoEvent, dag_cbor_cid,
+    hydrate_ops, migrate_collection_with,
+};
+use crate::session_store::MemorySessionStore;
+use crate::traits::BlobStore;
+use crate::traits::{Pds, Session, SessionStore, StoredSession};
+use crate::types::{AtUri, Did, Handle, Nsid, Rkey, Tid};
+use crate::{AccessToken, CancellationToken, Error, RefreshToken};
+
+/// Collection used when a fixture does not name one.
+pub const DEFAULT_COLLECTION: &str = "org.muat.conformance.record";
+
+/// A backend under test, with an existing account to log in as.
+pub struct Fixture<P: Pds> {
+    /// The PDS under test.
+    pub pds: P,
+    identifier: String,
+    password: String,
+    collection: Nsid,
+    _guard: Option<Box<dyn Any + Send + Sync>>,
+}
+
+impl<P: Pds> Fixture<P> {
+    /// Create a fixture for an account that already exists on `pds`.
+    pub fn new(pds: P, identifier: impl Into<String>, password: impl Into<String>) -> Self {
+        Self {
+            pds,
+            identifier: identifier.into(),
+            password: password.into(),
+            collection: Nsid::new(DEFAULT_COLLECTION).expect("default collection is valid"),
+            _guard: None,
+        }
+    }
+
+    /// Use `collection` for record checks.
+    ///
+    /// The collection should start empty, so pick a unique one when running
+    /// against a shared server.
+    pub fn with_collection(mut self, collection: Nsid) -> Self {
+        self.collection = collection;
+        self
+    }
+
+    /// Keep `guard` alive for as long as the fixture, e.g. a temporary
+    /// directory backing the PDS.
+    pub fn with_guard(mut self, guard: impl Any + Send + Sync) -> Self {
+        self._guard = Some(Box::new(guard));
+        self
+    }
+
+    /// Returns the collection used for record checks.
+    pub fn collection(&self) -> &Nsid {
+        &self.collection
+    }
+
+    /// Returns the fixture's login credentials.
+    pub fn credentials(&self) -> Credentials {
+        Credentials::new(&self.identifier, &self.password)
+    }
+
+    /// Log in as the fixture account.
+    pub async fn login(&self) -> P::Session {
+        self.pds
+            .login(self.credentials())
+            .await
+            .expect("login with fixture credentials failed")
+    }
+}
+
+/// Check create, get, list and delete of a single record.
+pub async fn check_record_crud<S: Session + ?Sized>(session: &S, collection: &Nsid) {
+    let value = record(collection, 0);
+
+    let uri = session
+        .create_record(collection, &value)
+        .await
+        .expect("create_record failed");
+    assert_eq!(
+        uri.repo(),
+        session.did(),
+        "record must be created in the session repo"
+    );
+    assert_eq!(
+        uri.collection(),
+        Some(collection),
+        "record must be created in the collection"
+    );
+
+    let record = session.get_record(&uri).await.expect("get_record failed");
+    assert_eq!(record.uri, uri, "get_record must return the requested URI");
+    assert!(!record.cid.is_empty(), "get_record must return a CID");
+    assert_eq!(
+        record.value.as_value(),
+        value.as_value(),
+        "get_record must return the stored value"
+    );
+
+    let listed = session
+        .list_records(session.did(), collection, None, None)
+        .await
+        .expect("list_records failed");
+    assert!(
+        listed.records.iter().any(|r| r.uri == uri),
+        "list_records must include a created record"
+    );
+
+    session
+        .delete_record(&uri)
+        .await
+        .expect("delete_record failed");
+    assert!(
+        session.get_record(&uri).await.is_err(),
+        "get_record must fail after delete"
+    );
+
+    let listed = session
+        .list_records(session.did(), collection, None, None)
+        .await
+        .expect("list_records failed");
+    assert!(
+        !listed.records.iter().any(|r| r.uri == uri),
+        "list_records must not include a deleted record"
+    );
+}
+
+/// Check that bulk creation reports every value, in input order.
+pub async fn check_create_records_bulk<S: Session + ?Sized>(session: &S, collection: &Nsid) {
+    let values: Vec<RecordValue> = (0..5).map(|i| record(collection, i)).collect();
+
+    let report = session
+        .create_records_bulk(collection, values.clone(), 2, None)
+        .await;
+    assert!(
+        report.is_complete(),
+        "create_records_bulk items failed: {:?}",
+        report.failed
+    );
+    assert_eq!(report.succeeded.len(), values.len(), "one URI per value");
+
+    for (uri, value) in report.succeeded.iter().zip(&values) {
+        let record = session.get_record(uri).await.expect("get_record failed");
+        assert_eq!(
+            record.value.as_value(),
+            value.as_value(),
+            "results must be in input order"
+        );
+    }
+
+    assert!(
+        session
+            .create_records_bulk(collection, Vec::new(), 2, None)
+            .await
+            .is_empty(),
+        "no values must produce an empty report"
+    );
+
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let before = list_all(
+        session,
+        session.did(),
+        collection,
+        ListRecordsOptions::new(),
+    )
+    .await;
+    let report = session
+        .create_records_bulk(collection, values.clone(), 2, Some(&cancel))
+        .await;
+    assert!(
+        report.succeeded.is_empty() && report.failed.is_empty(),
+        "a cancelled bulk create must not write anything"
+    );
+    assert_eq!(
+        report.skipped, values,
+        "a cancelled bulk create must report every value as skipped"
+    );
+    let after = list_all(
+        session,
+        session.did(),
+        collection,
+        ListRecordsOptions::new(),
+    )
+    .await;
+    assert_eq!(
+        before, after,
+        "a cancelled bulk create must not write anything"
+    );
+}
+
+/// Check that bulk deletion reports deleted and failed URIs separately.
+pub async fn check_delete_records_bulk<S: Session + ?Sized>(session: &S, collection: &Nsid) {
+    let mut uris = Vec::new();
+    for i in 0..3 {
+        uris.push(
+            session
+                .create_record(collection, &record(collection, i))
+                .await
+                .expect("create_record failed"),
+        );
+    }
+    // A record in another account's repo cannot be deleted.
+    let other = Did::new("did:plc:bbbbbbbbbbbbbbbbbbbbbbbb").expect("conformance DID is valid");
+    let foreign = AtUri::from_parts(
+        other,
+        collection.clone(),
+        Rkey::new("foreign").expect("conformance rkey is valid"),
+    );
+    let mut inputs = uris.clone();
+    inputs.insert(1, foreign.clone());
+
+    let report = session.delete_records_bulk(inputs, 2, None).await;
+    assert_eq!(
+        report.succeeded, uris,
+        "deleted URIs must be in input order"
+    );
+    assert_eq!(report.failed.len(), 1, "the foreign URI must fail");
+    assert_eq!(
+        report.failed[0].0, foreign,
+        "failures must keep their input"
+    );
+    assert!(report.skipped.is_empty(), "nothing must be skipped");
+
+    for uri in &uris {
+        assert!(
+            session.get_record(uri).await.is_err(),
+            "bulk-deleted records must be gone"
+        );
+    }
+}
+
+/// Check that `put_record` creates and overwrites records and honours the
+/// swap CID.
+pub async fn check_put_record<S: Session + ?Sized>(session: &S, collection: &Nsid) {
+    let rkey = Rkey::new("put").expect("conformance rkey is valid");
+
+    let uri = session
+        .put_record(collection, &rkey, &record(collection, 0), None)
+        .await
+        .expect("put_record failed to create");
+    assert_eq!(
+        uri.rkey(),
+        Some(&rkey),
+        "put_record must use the given rkey"
+    );
+    let first = session.get_record(&uri).await.expect("get_record failed");
+
+    session
+        .put_record(collection, &rkey, &record(collection, 1), Some(&first.cid))
+        .await
+        .expect("put_record with the current CID failed");
+    let second = session.get_record(&uri).await.expect("get_record failed");
+    assert_eq!(
+        second.value.as_value(),
+        record(collection, 1).as_value(),
+        "put_record must overwrite the record"
+    );
+
+    let err = session
+        .put_record(collection, &rkey, &record(collection, 2), Some(&first.cid))
+        .await
+        .expect_err("put_record with a stale CID must fail");
+    assert!(
+        matches!(&err, Error::Protocol(e) if e.is_invalid_swap()),
+        "a stale swap CID must fail with InvalidSwap, got {err}"
+    );
+    let after = session.get_record(&uri).await.expect("get_record failed");
+    assert_eq!(
+        after.value.as_value(),
+        second.value.as_value(),
+        "a failed swap must not write"
+    );
+}
+
+/// Check that `get_record_at` returns the current version by its CID and
+/// that an unknown CID is not found.
+pub async fn check_get_record_at<S: Session + ?Sized>(session: &S, collection: &Nsid) {
+    let rkey = Rkey::new("pinned").expect("conformance rkey is valid");
+    let uri = session
+        .put_record(collection, &rkey, &record(collection, 0), None)
+        .await
+        .expect("put_record failed");
+    session
+        .put_record(collection, &rkey, &record(collection, 1), None)
+        .await
+        .expect("put_record failed to overwrite");
+    let current = session.get_record(&uri).await.expect("get_record failed");
+
+    let pinned = session
+        .get_record_at(&uri, &current.cid)
+        .await
+        .expect("get_record_at with the current CID failed");
+    assert_eq!(pinned.cid, current.cid);
+    assert_eq!(
+        pinned.value.as_value(),
+        record(collection, 1).as_value(),
+        "get_record_at must return the version with that CID"
+    );
+
+    let unknown = dag_cbor_cid(b"no such version").to_string();
+    let err = session
+        .get_record_at(&uri, &unknown)
+        .await
+        .expect_err("get_record_at with an unknown CID must fail");
+    assert!(
+        matches!(&err, Error::Protocol(e) if e.is_not_found()),
+        "an unknown CID must fail with RecordNotFound, got {err}"
+    );
+}
+
+/// Check that a deactivated account refuses writes and reports itself
+/// inactive, and that activating it again restores both.
+pub async fn check_account_activation<S: Session + ?Sized>(session: &S, collection: &Nsid) {
+    let uri = session
+        .create_record(collection, &record(collection, 0))
+        .await
+        .expect("create_record failed");
+
+    let status = session
+        .check_account_status()
+        .await
+        .expect("check_account_status failed");
+    assert!(status.activated, "a new account must be active");
+    assert!(
+        status.indexed_records >= 1,
+        "account status must count the created record"
+    );
+
+    session
+        .deactivate_account(None)
+        .await
+        .expect("deactivate_account failed");
+    let status = session
+        .check_account_status()
+        .await
+        .expect("check_account_status failed while deactivated");
+    assert!(!status.activated, "deactivate_account must deactivate");
+    session
+        .create_record(collection, &record(collection, 1))
+        .await
+        .expect_err("a deactivated account must refuse writes");
+
+    session
+        .activate_account()
+        .await
+        .expect("activate_account failed");
+    let status = session
+        .check_account_status()
+        .await
+        .expect("check_account_status failed");
+    assert!(status.activated, "activate_account must reactivate");
+    session
+        .delete_record(&uri)
+        .await
+        .expect("a reactivated account must accept writes");
+}
+
+/// Check that a [`Backfiller`] replays every record of an empty `collection`
+/// as a `create` commit, across pages, and that `since_rev` skips older
+/// records.
+pub async fn check_backfill<S: Session + ?Sized>(session: &S, collection: &Nsid) {
+    let mut created = Vec::new();
+    for i in 0..5 {
+        let uri = session
+            .create_record(collection, &record(collection, i))
+            .await
+            .expect("create_record failed");
+        let cid = session
+            .get_record(&uri)
+            .await
+            .expect("get_record failed")
+            .cid;
+        created.push((format!("{}/{}", collection, rkey_of(&uri)), cid));
+    }
+
+    let backfill = Backfiller::new(session, std::slice::from_ref(collection)).page_size(2);
+    let dids = [session.did().clone()];
+    let replayed = collect_commits(Box::pin(backfill.run(&dids, None))).await;
+    assert_eq!(
+        replayed, created,
+        "backfill must replay every record as a create, in order"
+    );
+
+    let (_, fourth) = created[3]
+        .0
+        .split_once('/')
+        .expect("paths are collection/rkey");
+    let since = Tid::new(fourth).expect("created records have TID keys");
+    let replayed = collect_commits(Box::pin(backfill.run(&dids, Some(&since)))).await;
+    assert_eq!(
+        replayed,
+        created[3..],
+        "backfill since a revision must skip older records"
+    );
+}
+
+/// The `(path, cid)` of each create in a stream of synthetic commits.
+async fn collect_commits<F: Stream<Item = crate::Result<RepoEvent>> + ?Sized>(
+    mut events: Pin<Box<F>>,
+) -> Vec<(String, String)> {
+    let mut out = Vec::new();
+    while let Some(event) = next(&mut events).await {
+        let RepoEvent::Commit(commit) = event.expect("backfill failed") else {
+            panic!("backfill must only emit commits");
+        };
+        assert_eq!(commit.seq, 0, "synthetic commits must have sequence 0");
+        for op in commit.ops {
+            assert_eq!(op.action, "create", "backfill must emit creates");
+            out.push((op.path, op.cid.expect("creates carry a CID")));
+        }
+    }
+    out
+}
+
+/// Check that `hydrate_ops` pairs a commit's operations with the records as
+/// they are now, with `None` for deletes and records already gone.
+pub async fn check_hydrate_ops<S: Session + ?Sized>(session: &S, collection: &Nsid) {
+    let kept = session
+        .create_record(collection, &record(collection, 1))
+        .await
+        .expect("create_record failed");
+    let gone = session
+        .create_record(collection, &record(collection, 2))
+        .await
+        .expect("create_record failed");
+    session
+        .delete_record(&gone)
+        .await
+        .expect("delete_record failed");
+
+    let op = |action: &str, uri: &AtUri| CommitOperation {
+        path: format!("{}/{}", collection, rkey_of(uri)),
+        action: action.to_string(),
+        cid: None,
+    };
+    let commit = CommitEvent {
+        repo: session.did().to_string(),
+        rev: Tid::now().to_string(),
+        since: None,
+        seq: 1,
+        time: chrono::Utc::now(),
+        ops: vec![
+            op("create", &kept),
+            op("create", &gone),
+            op("delete", &gone),
+        ],
+        blocks: Vec::new(),
+        blobs: Vec::new(),
+    };
+
+    let hydrated = hydrate_ops(session, &commit, 2)
+        .await
+        .expect("hydrate_ops failed");
+    let summary: Vec<(&str, Option<&AtUri>)> = hydrated
+        .iter()
+        .map(|(op, record)| (op.action.as_str(), record.as_ref().map(|r| &r.uri)))
+        .collect();
+    assert_eq!(
+        summary,
+        [("create", Some(&kept)), ("create", None), ("delete", None)],
+        "hydrate_ops must keep operation order and pass over missing records"
+    );
+}
+
+/// Check `migrate_collection_with` in dry-run and write modes over an empty
+/// `collection`.
+pub async fn check_migrate_collection<S: Session + ?Sized>(session: &S, collection: &Nsid) {
+    for i in 0..5 {
+        session
+            .create_record(collection, &record(collection, i))
+            .await
+            .expect("create_record failed");
+    }
+    // Bump even records, leave odd ones alone.
+    let bump = |value: RecordValue| {
+        let n = value.get("n")?.as_u64()?;
+        (n % 2 == 0).then(|| record(collection, n as u32 + 100))
+    };
+
+    let options = MigrateOptions::new().page_size(2).dry_run(true);
+    let report = migrate_collection_with(session, collection, &options, bump)
+        .await
+        .expect("dry-run migration failed");
+    assert_eq!(report.scanned, 5, "migration must scan every record");
+    assert_eq!(report.changed, 3, "dry run must count changed records");
+    assert_eq!(report.unchanged, 2, "dry run must count unchanged records");
+    let listed = session
+        .list_records(session.did(), collection, None, None)
+        .await
+        .expect("list_records failed");
+    assert!(
+        listed
+            .records
+            .iter()
+            .all(|r| r.value.get("n").and_then(|n| n.as_u64()) < Some(100)),
+        "a dry run must not write"
+    );
+
+    let options = MigrateOptions::new().page_size(2);
+    let report = migrate_collection_with(session, collection, &options, bump)
+        .await
+        .expect("migration failed");
+    assert!(report.is_complete(), "migration must complete: {report:?}");
+    assert_eq!(report.changed, 3, "migration must write changed records");
+    let mut values: Vec<u64> = session
+        .list_records(session.did(), collection, None, None)
+        .await
+        .expect("list_records failed")
+        .records
+        .iter()
+        .filter_map(|r| r.value.get("n")?.as_u64())
+        .collect();
+    values.sort();
+    assert_eq!(
+        values,
+        [1, 3, 100, 102, 104],
+        "migration must write the transformed values"
+    );
+}
+
+/// Check that `list_collections` names the collections holding records,
+/// and `iter_all_records` reads every record across them.
+pub async fn check_list_collections<S: Session + ?Sized>(session: &S, collection: &Nsid) {
+    let other = Nsid::new(format!("{}Other", collection)).expect("conformance NSID is valid");
+    let mut created = Vec::new();
+    for (i, collection) in [collection, &other, collection].into_iter().enumerate() {
+        let uri = session
+            .create_record(collection, &record(collection, i as u32))
+            .await
+            .expect("create_record failed");
+        created.push(uri.to_string());
+    }
+    let emptied = Nsid::new(format!("{}Emptied", collection)).expect("conformance NSID is valid");
+    let uri = session
+        .create_record(&emptied, &record(&emptied, 0))
+        .await
+        .expect("create_record failed");
+    session
+        .delete_record(&uri)
+        .await
+        .expect("delete_record failed");
+
+    let collections: Vec<String> = session
+        .list_collections(session.did())
+        .await
+        .expect("list_collections failed")
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        collections,
+        [collection.to_string(), other.to_string()],
+        "list_collections must name each collection with records, sorted"
+    );
+
+    let mut listed = Vec::new();
+    let mut records = session.iter_all_records(session.did());
+    while let Some(record) = next(&mut records).await {
+        listed.push(record.expect("iter_all_records failed").uri.to_string());
+    }
+    created.sort();
+    listed.sort();
+    assert_eq!(listed, created, "iter_all_records must yield every record");
+}
+
+/// Fill an empty `collection` and check that
+/// [`delete_collection`](Session::delete_collection) empties it, paging
+/// through it and leaving other collections alone.
+pub async fn check_delete_collection<S: Session + ?Sized>(session: &S, collection: &Nsid) {
+    let other = Nsid::new(format!("{}Other", collection)).expect("conformance NSID is valid");
+    for i in 0..5 {
+        session
+            .create_record(collection, &record(collection, i))
+            .await
+            .expect("create_record failed");
+    }
+    let kept = session
+        .create_record(&other, &record(&other, 0))
+        .await
+        .expect("create_record failed");
+
+    let pages = Arc::new(AtomicUsize::new(0));
+    let counter = pages.clone();
+    let options = DeleteCollectionOptions::new()
+        .page_size(2)
+        .on_progress(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+    let report = session
+        .delete_collection(session.did(), collection, &options.clone().dry_run(true))
+        .await
+        .expect("dry-run delete_collection failed");
+    assert_eq!(report.scanned, 5, "a dry run must scan every record");
+    assert_eq!(report.deleted, 5, "a dry run must count every record");
+    assert!(report.is_complete(), "a dry run must complete");
+    let listed = session
+        .list_records(session.did(), collection, None, None)
+        .await
+        .expect("list_records failed");
+    assert_eq!(listed.records.len(), 5, "a dry run must not delete");
+
+    pages.store(0, Ordering::Relaxed);
+    let report = session
+        .delete_collection(session.did(), collection, &options)
+        .await
+        .expect("delete_collection failed");
+    assert_eq!(
+        report.scanned, 5,
+        "delete_collection must scan every record"
+    );
+    assert_eq!(
+        report.deleted, 5,
+        "delete_collection must delete every record"
+    );
+    assert!(report.is_complete(), "delete_collection must complete");
+    assert!(
+        pages.load(Ordering::Relaxed) >= 3,
+        "delete_collection must report progress after each page"
+    );
+    let listed = session
+        .list_records(session.did(), collection, None, None)
+        .await
+        .expect("list_records failed");
+    assert!(
+        listed.records.is_empty(),
+        "delete_collection must empty the collection"
+    );
+    session
+        .get_record(&kept)
+        .await
+        .expect("delete_collection must leave other collections alone");
+}
+
+/// Create records in an empty `collection` and check paging and ordering.
+pub async fn check_pagination<S: Session + ?Sized>(session: &S, collection: &Nsid) {
+    let mut rkeys = Vec::new();
+    for i in 0..5 {
+        let uri = session
+            .create_record(collection, &record(collection, i))
+            .await
+            .expect("create_record failed");
+        rkeys.push(rkey_of(&uri));
+    }
+    rkeys.sort();
+    let expected: Vec<&str> = rkeys.iter().map(String::as_str).collect();
+
+    check_list_records_order(session, session.did(), collection, &expected).await;
+}
+
+/// Check the `list_records` ordering contract.
+///
+/// `expected` must be every record key in `collection`, in ascending order.
+/// Verifies the default and descending orders, both as a single page and
+/// when paged one record at a time.
+pub async fn check_list_records_order<S: Session + ?Sized>(
+    session: &S,
+    repo: &Did,
+    collection: &Nsid,
+    expected: &[&str],
+) {
+    let descending: Vec<&str> = expected.iter().rev().copied().collect();
+
+    let all = list_all(session, repo, collection, ListRecordsOptions::new()).await;
+    assert_eq!(all, expected, "default order must be ascending by rkey");
+
+    let shorthand = session
+        .list_records(repo, collection, None, None)
+        .await
+        .expect("list_records failed");
+    let shorthand: Vec<String> = shorthand.records.iter().map(|r| rkey_of(&r.uri)).collect();
+    assert_eq!(
+        shorthand, expected,
+        "list_records must match the default order"
+    );
+
+    let options = ListRecordsOptions::new().order(RecordOrder::Descending);
+    let all = list_all(session, repo, collection, options).await;
+    assert_eq!(all, descending, "descending order must reverse rkeys");
+
+    let options = ListRecordsOptions::new().limit(1);
+    let all = list_all(session, repo, collection, options).await;
+    assert_eq!(all, expected, "ascending pages must continue in order");
+
+    let options = ListRecordsOptions::new().limit(1).reverse(true);
+    let all = list_all(session, repo, collection, options).await;
+    assert_eq!(all, descending, "descending pages must continue in order");
+}
+
+/// Check put, get, list and delete on a blob store.
+///
+/// Uses content unlikely to exist already, so it can run against a store
+/// that holds other blobs.
+pub async fn check_blob_store(store: &dyn BlobStore) {
+    let data = b"muat conformance blob \x00\x01\x02".to_vec();
+
+    let blob = store
+        .put_blob(data.clone(), "application/octet-stream")
+        .await
+        .expect("put_blob failed");
+    assert_eq!(blob.size, data.len() as u64, "blob size must match content");
+    assert_eq!(blob.mime_type, "application/octet-stream");
+
+    let again = store
+        .put_blob(data.clone(), "application/octet-stream")
+        .await
+        .expect("put_blob failed");
+    assert_eq!(
+        again.cid, blob.cid,
+        "identical content must have the same CID"
+    );
+
+    let fetched = store
+        .get_blob(blob.cid.as_str())
+        .await
+        .expect("get_blob failed");
+    assert_eq!(fetched, data, "get_blob must return the stored content");
+
+    let listed = list_all_blobs(store).await;
+    assert!(
+        listed.iter().any(|cid| cid == blob.cid.as_str()),
+        "list_blobs must include a stored blob"
+    );
+
+    store
+        .delete_blob(blob.cid.as_str())
+        .await
+        .expect("delete_blob failed");
+    assert!(
+        store.get_blob(blob.cid.as_str()).await.is_err(),
+        "get_blob must fail after delete"
+    );
+    assert!(
+        !list_all_blobs(store)
+            .await
+            .iter()
+            .any(|cid| cid == blob.cid.as_str()),
+        "list_blobs must not include a deleted blob"
+    );
+}
+
+/// Check ranged reads on a blob store.
+///
+/// Needs only [`BlobStore::put_blob`] and [`BlobStore::get_blob_range`], so
+/// it also runs against stores that cannot delete or list.
+pub async fn check_blob_ranges(store: &dyn BlobStore) {
+    let data = b"muat conformance ranged blob 0123456789".to_vec();
+    let size = data.len() as u64;
+    let blob = store
+        .put_blob(data.clone(), "application/octet-stream")
+        .await
+        .expect("put_blob failed");
+    let cid = blob.cid.as_str();
+
+    let cases = [
+        (ByteRange::new(0, Some(4)), &data[..4]),
+        (ByteRange::new(5, Some(11)), &data[5..16]),
+        (ByteRange::from_offset(29), &data[29..]),
+        (ByteRange::new(30, Some(1000)), &data[30..]),
+        (ByteRange::from_offset(size), &data[..0]),
+    ];
+    for (range, expected) in cases {
+        let fetched = store
+            .get_blob_range(cid, range)
+            .await
+            .unwrap_or_else(|e| panic!("get_blob_range {range:?} failed: {e}"));
+        assert_eq!(fetched, expected, "get_blob_range {range:?}");
+    }
+
+    match store
+        .get_blob_range(cid, ByteRange::from_offset(size + 1))
+        .await
+    {
+        Err(Error::Protocol(e)) => assert_eq!(e.status, 416, "past-the-end ranges are 416"),
+        other => panic!("a range past the end must fail with 416, got {other:?}"),
+    }
+}
+
+/// Check that bad credentials are rejected as authentication failures.
+pub async fn check_auth_failures<P: Pds>(fixture: &Fixture<P>) {
+    let wrong_password = Credentials::new(&fixture.identifier, "not-the-password");
+    let err = fixture
+        .pds
+        .login(wrong_password)
+        .await
+        .err()
+        .expect("login with a wrong password must fail");
+    assert!(
+        is_auth_error(&err),
+        "wrong password must be an auth error, got: {err}"
+    );
+
+    let unknown = Credentials::new("unknown-account.invalid", &fixture.password);
+    assert!(
+        fixture.pds.login(unknown).await.is_err(),
+        "login as an unknown account must fail"
+    );
+}
+
+/// Check that [`Pds::describe`] answers without a session, and that the
+/// fixture's handle is under the user domains it reports.
+pub async fn check_describe<P: Pds>(fixture: &Fixture<P>) {
+    let description = fixture.pds.describe().await.expect("describe failed");
+    assert!(
+        !description.did.is_empty(),
+        "the description must name the server's DID"
+    );
+    if let Ok(handle) = Handle::new(&fixture.identifier) {
+        handle
+            .check_user_domains(&description.available_user_domains)
+            .unwrap_or_else(|e| panic!("existing accounts must fit the user domains: {e}"));
+    }
+}
+
+/// Check that [`Pds::login_or_restore`] stores a new session, resumes it
+/// without logging in, and logs in again once the stored tokens stop
+/// working.
+pub async fn check_login_or_restore<P: Pds>(fixture: &Fixture<P>) {
+    let store = MemorySessionStore::new();
+    let key = StoredSession::key(fixture.pds.url(), &fixture.identifier);
+
+    let first = fixture
+        .pds
+        .login_or_restore(fixture.credentials(), &store)
+        .await
+        .expect("login_or_restore must log in when nothing is stored");
+    let stored = store
+        .load(&key)
+        .await
+        .expect("memory store load cannot fail")
+        .expect("login_or_restore must store the new session");
+    assert_eq!(
+        &stored.did,
+        first.did(),
+        "stored session must be the account's"
+    );
+
+    // A wrong password shows the session was restored, not logged in again.
+    let wrong_password = Credentials::new(&fixture.identifier, "not-the-password");
+    let restored = fixture
+        .pds
+        .login_or_restore(wrong_password.clone(), &store)
+        .await
+        .expect("a stored session must be restored without logging in");
+    assert_eq!(restored.did(), first.did());
+    restored
+        .list_records(restored.did(), &fixture.collection, Some(1), None)
+        .await
+        .expect("a restored session must be usable");
+
+    let broken = StoredSession {
+        access_token: AccessToken::new("not-a-token"),
+        refresh_token: Some(RefreshToken::new("not-a-token")),
+        ..stored
+    };
+    store
+        .save(&key, &broken)
+        .await
+        .expect("memory store save cannot fail");
+    assert!(
+        fixture
+            .pds
+            .login_or_restore(wrong_password, &store)
+            .await
+            .is_err(),
+        "unusable stored tokens must fall back to logging in"
+    );
+    let again = fixture
+        .pds
+        .login_or_restore(fixture.credentials(), &store)
+        .await
+        .expect("login_or_restore must log in when stored tokens are unusable");
+    let resaved = store
+        .load(&key)
+        .await
+        .expect("memory store load cannot fail")
+        .expect("login_or_restore must store the new session");
+    assert_eq!(
+        resaved.access_token.as_str(),
+        again.access_token().as_str(),
+        "login_or_restore must replace unusable stored tokens"
+    );
+}
+
+/// Check that the firehose reports commits in write order.
+///
+/// Subscribes, writes three records and waits for their commits. This waits
+/// indefinitely for events, so run it under a timeout.
+pub async fn check_firehose_order<P: Pds>(fixture: &Fixture<P>) {
+    let session = fixture.login().await;
+    let collection = fixture.collection();
+
+    let mut firehose = Box::pin(fixture.pds.firehose().expect("firehose failed"));
+
+    let mut expected = Vec::new();
+    for i in 0..3 {
+        let uri = session
+            .create_record(collection, &record(collection, i))
+            .await
+            .expect("create_record failed");
+        expected.push(format!("{}/{}", collection, rkey_of(&uri)));
+    }
+
+    let mut seen = Vec::new();
+    let mut last_seq = None;
+    while seen.len() < expected.len() {
+        let event = next(&mut firehose)
+            .await
+            .expect("firehose ended early")
+            .expect("firehose error");
+        let RepoEvent::Commit(commit) = event else {
+            continue;
+        };
+        if commit.repo != session.did().as_str() {
+            continue;
+        }
+        if let Some(last) = last_seq {
+            assert!(commit.seq > last, "firehose sequence numbers must increase");
+        }
+        last_seq = Some(commit.seq);
+        seen.extend(
+            commit
+                .ops
+                .into_iter()
+                .map(|op| op.path)
+                .filter(|path| expected.contains(path)),
+        );
+    }
+    assert_eq!(
+        seen, expected,
+        "firehose must report commits in write order"
+    );
+}
+
+/// Check that a firehose opened with a cursor replays only later events.
+///
+/// Neither subscription needs a session. This waits indefinitely for events,
+/// so run it under a timeout.
+pub async fn check_firehose_cursor<P: Pds>(fixture: &Fixture<P>) {
+    let session = fixture.login().await;
+    let collection = fixture.collection();
+
+    let mut live = Box::pin(fixture.pds.firehose().expect("firehose failed"));
+    let mut paths = Vec::new();
+    for i in 0..2 {
+        let uri = session
+            .create_record(collection, &record(collection, i))
+            .await
+            .expect("create_record failed");
+        paths.push(format!("{}/{}", collection, rkey_of(&uri)));
+    }
+
+    let first = next_commit_with(&mut live, session.did(), &paths[0]).await;
+
+    let mut replay = Box::pin(
+        fixture
+            .pds
+            .firehose_from(Some(first))
+            .expect("firehose_from failed"),
+    );
+    loop {
+        let event = next(&mut replay)
+            .await
+            .expect("firehose ended early")
+            .expect("firehose error");
+        let RepoEvent::Commit(commit) = event else {
+            continue;
+        };
+        assert!(
+            commit.seq > first,
+            "replay must start after the cursor, got seq {} for cursor {}",
+            commit.seq,
+            first
+        );
+        if commit.ops.iter().any(|op| op.path == paths[1]) {
+            return;
+        }
+    }
+}
+
+/// Wait for the commit touching `path` in `repo` and return its sequence number.
+async fn next_commit_with<F: Stream<Item = crate::Result<RepoEvent>> + ?Sized>(
+    stream: &mut Pin<Box<F>>,
+    repo: &Did,
+    path: &str,
+) -> i64 {
+    loop {
+        let event = next(stream)
+            .await
+            .expect("firehose ended early")
+            .expect("firehose error");
+        if let RepoEvent::Commit(commit) = event
+            && commit.repo == repo.as_str()
+            && commit.ops.iter().any(|op| op.path == path)
+        {
+            return commit.seq;
+        }
+    }
+}
+
+/// Expand to one `#[tokio::test]` per conformance check.
+///
+/// `$fixture` is an expression evaluating to a future of
+/// [`Fixture`](crate::testing::Fixture); it is evaluated once per test, so
+/// each test gets a fresh backend. The calling crate needs `tokio` with the
+/// `macros`, `rt` and `time` features as a dev-dependency.
+#[macro_export]
+macro_rules! conformance_tests {
+    ($fixture:expr) => {
+        #[tokio::test]
+        async fn conformance_record_crud() {
+            let fixture = $fixture.await;
+            let session = fixture.login().await;
+            $crate::testing::check_record_crud(&session, fixture.collection()).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_create_records_bulk() {
+            let fixture = $fixture.await;
+            let session = fixture.login().await;
+            $crate::testing::check_create_records_bulk(&session, fixture.collection()).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_delete_records_bulk() {
+            let fixture = $fixture.await;
+            let session = fixture.login().await;
+            $crate::testing::check_delete_records_bulk(&session, fixture.collection()).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_put_record() {
+            let fixture = $fixture.await;
+            let session = fixture.login().await;
+            $crate::testing::check_put_record(&session, fixture.collection()).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_get_record_at() {
+            let fixture = $fixture.await;
+            let session = fixture.login().await;
+            $crate::testing::check_get_record_at(&session, fixture.collection()).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_account_activation() {
+            let fixture = $fixture.await;
+            let session = fixture.login().await;
+            $crate::testing::check_account_activation(&session, fixture.collection()).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_migrate_collection() {
+            let fixture = $fixture.await;
+            let session = fixture.login().await;
+            $crate::testing::check_migrate_collection(&session, fixture.collection()).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_backfill() {
+            let fixture = $fixture.await;
+            let session = fixture.login().await;
+            $crate::testing::check_backfill(&session, fixture.collection()).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_hydrate_ops() {
+            let fixture = $fixture.await;
+            let session = fixture.login().await;
+            $crate::testing::check_hydrate_ops(&session, fixture.collection()).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_delete_collection() {
+            let fixture = $fixture.await;
+            let session = fixture.login().await;
+            $crate::testing::check_delete_collection(&session, fixture.collection()).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_list_collections() {
+            let fixture = $fixture.await;
+            let session = fixture.login().await;
+            $crate::testing::check_list_collections(&session, fixture.collection()).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_pagination() {
+            let fixture = $fixture.await;
+            let session = fixture.login().await;
+            $crate::testing::check_pagination(&session, fixture.collection()).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_blobs() {
+            let fixture = $fixture.await;
+            let session = fixture.login().await;
+            $crate::testing::check_blob_store($crate::traits::Session::blobs(&session)).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_blob_ranges() {
+            let fixture = $fixture.await;
+            let session = fixture.login().await;
+            $crate::testing::check_blob_ranges($crate::traits::Session::blobs(&session)).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_auth_failures() {
+            let fixture = $fixture.await;
+            $crate::testing::check_auth_failures(&fixture).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_describe() {
+            let fixture = $fixture.await;
+            $crate::testing::check_describe(&fixture).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_login_or_restore() {
+            let fixture = $fixture.await;
+            $crate::testing::check_login_or_restore(&fixture).await;
+        }
+
+        #[tokio::test]
+        async fn conformance_firehose_order() {
+            let fixture = $fixture.await;
+            tokio::time::timeout(
+                std::time::Duration::from_secs(30),
+                $crate::testing::check_firehose_order(&fixture),
+            )
+            .await
+            .expect("timed out waiting for firehose events");
+        }
+
+        #[tokio::test]
+        async fn conformance_firehose_cursor() {
+            let fixture = $fixture.await;
+            tokio::time::timeout(
+                std::time::Duration::from_secs(30),
+                $crate::testing::check_firehose_cursor(&fixture),
+            )
+            .await
+            .expect("timed out waiting for firehose events");
+        }
+    };
+}
+
+fn record(collection: &Nsid, n: u32) -> RecordValue {
+    RecordValue::with_type(collection.as_str(), json!({ "n": n }))
+        .expect("conformance record is valid")
+}
+
+fn rkey_of(uri: &AtUri) -> String {
+    uri.rkey()
+        .expect("records must have record URIs")
+        .to_string()
+}
+
+fn is_auth_error(err: &Error) -> bool {
+    match err {
+        Error::Auth(_) => true,
+        Error::Protocol(e) => e.is_auth_error(),
+        _ => false,
+    }
+}
+
+async fn next<F: Stream + ?Sized>(stream: &mut Pin<Box<F>>) -> Option<F::Item> {
+    poll_fn(|cx| stream.as_mut().poll_next(cx)).await
+}
+
+async fn list_all_blobs(store: &dyn BlobStore) -> Vec<String> {
+    let mut cids = Vec::new();
+    let mut cursor: Option<String> = None;
+    for _ in 0..1000 {
+        let page = store
+            .list_blobs(None, cursor.as_deref())
+            .await
+            .expect("list_blobs failed");
+        let empty = page.cids.is_empty();
+        cids.extend(page.cids);
+        match page.cursor {
+            Some(next) if !empty => cursor = Some(next),
+            _ => return cids,
+        }
+    }
+    panic!("list_blobs did not finish paging");
+}
+
+async fn list_all<S: Session + ?Sized>(
+    session: &S,
+    repo: &Did,
+    collection: &Nsid,
+    mut options: ListRecordsOptions,
+) -> Vec<String> {
+    let mut rkeys = Vec::new();
+    // Bound the loop so a cursor that never advances fails instead of hanging.
+    for _ in 0..1000 {
+        let page = session
+            .list_records_with(repo, collection, &options)
+            .await
+            .expect("list_records_with failed");
+        let empty = page.records.is_empty();
+        rkeys.extend(page.records.iter().map(|r| rkey_of(&r.uri)));
+        match page.cursor {
+            Some(cursor) if !empty => options = options.cursor(cursor),
+            _ => return rkeys,
+        }
+    }
+    panic!("list_records_with did not finish paging");
+}
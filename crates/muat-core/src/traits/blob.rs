@@ -0,0 +1,43 @@
+//! Blob storage trait.
+
+use std::fmt;
+
+use async_trait::async_trait;
+
+use crate::Result;
+use crate::repo::{BlobRef, ByteRange, ListBlobsOutput};
+
+/// Content-addressed blob storage.
+///
+/// Blobs are addressed by CID. Stores are independent of record storage, so
+/// a backend can be paired with any implementation (filesystem, S3, memory).
+/// A store may be scoped to a single repository, as the XRPC store is.
+#[async_trait]
+pub trait BlobStore: Send + Sync + fmt::Debug {
+    /// Store a blob and return a reference to embed in records.
+    ///
+    /// Storing identical content twice returns the same CID.
+    async fn put_blob(&self, data: Vec<u8>, mime_type: &str) -> Result<BlobRef>;
+
+    /// Fetch a blob's content by CID.
+    async fn get_blob(&self, cid: &str) -> Result<Vec<u8>>;
+
+    /// Fetch part of a blob's content by CID.
+    ///
+    /// The default fetches the whole blob and slices it; stores that can
+    /// read part of a blob directly should override this.
+    ///
+    /// # Errors
+    ///
+    /// As for [`ByteRange::bounds`], if the range starts past the end.
+    async fn get_blob_range(&self, cid: &str, range: ByteRange) -> Result<Vec<u8>> {
+        range.slice(self.get_blob(cid).await?)
+    }
+
+    /// Delete a blob by CID.
+    async fn delete_blob(&self, cid: &str) -> Result<()>;
+
+    /// List stored blob CIDs in ascending order.
+    async fn list_blobs(&self, limit: Option<u32>, cursor: Option<&str>)
+    -> Result<ListBlobsOutput>;
+}
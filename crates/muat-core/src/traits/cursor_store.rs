@@ -0,0 +1,20 @@
+//! Firehose cursor persistence trait.
+
+use async_trait::async_trait;
+
+use crate::Result;
+
+/// Somewhere to keep firehose cursors between runs, one per host.
+///
+/// A consumer saves the sequence number of the last event it finished with,
+/// and resumes from it when it reconnects.
+/// [`MemoryCursorStore`](crate::MemoryCursorStore) keeps cursors for the
+/// life of the process; `muat_file::FileCursorStore` keeps them in a file.
+#[async_trait]
+pub trait CursorStore: Send + Sync {
+    /// Load the cursor saved for `host`, if any.
+    async fn load(&self, host: &str) -> Result<Option<i64>>;
+
+    /// Save the cursor for `host`, replacing any already there.
+    async fn save(&self, host: &str, cursor: i64) -> Result<()>;
+}
@@ -1,11 +1,454 @@
 //! Firehose stream trait.
 
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
 use futures_core::Stream;
+use tokio_util::sync::{CancellationToken, WaitForCancellationFutureOwned};
 
 use crate::Result;
-use crate::repo::RepoEvent;
+use crate::repo::{GapDetected, InfoEvent, LabelEvent, RepoEvent};
+use crate::types::{Did, Nsid};
 
 /// Firehose stream of repository events.
+///
+/// Implemented for every `Send` stream of `Result<RepoEvent>`, so synthetic
+/// or bridged sources (a message queue, a test fixture) can be passed to code
+/// written against `Firehose` without a wrapper type:
+///
+/// ```
+/// use futures_util::stream;
+/// use muat_core::repo::{InfoEvent, RepoEvent};
+/// use muat_core::{Firehose, FirehoseExt};
+///
+/// fn consume(firehose: impl Firehose) -> impl Firehose {
+///     firehose.commits_only()
+/// }
+///
+/// let events = vec![Ok(RepoEvent::Info(InfoEvent {
+///     name: "bridged".to_string(),
+///     message: None,
+/// }))];
+/// let _filtered = consume(stream::iter(events));
+/// ```
 pub trait Firehose: Stream<Item = Result<RepoEvent>> + Send {}
 
 impl<T> Firehose for T where T: Stream<Item = Result<RepoEvent>> + Send {}
+
+/// Stream of labels from a labeler's `com.atproto.label.subscribeLabels`.
+///
+/// Like [`Firehose`], implemented for every `Send` stream of
+/// `Result<LabelEvent>`, so labels from other sources can be consumed the
+/// same way.
+pub trait LabelStream: Stream<Item = Result<LabelEvent>> + Send {}
+
+impl<T> LabelStream for T where T: Stream<Item = Result<LabelEvent>> + Send {}
+
+/// Client-side filtering combinators for any [`Firehose`].
+///
+/// Errors are always passed through. Filters compose, so
+/// `stream.commits_only().filter_collections(&[nsid])` keeps only commits
+/// touching `nsid`.
+///
+/// These filters run after events are received. When reading Jetstream, also
+/// pass the same collections and DIDs to the connection so the server does
+/// the filtering.
+pub trait FirehoseExt: Firehose + Sized {
+    /// Keep commits with at least one operation in `collections`, dropping
+    /// operations on other collections. Non-commit events pass through.
+    fn filter_collections(self, collections: &[Nsid]) -> Filtered<Self> {
+        Filtered::new(self, EventFilter::Collections(collections.to_vec()))
+    }
+
+    /// Keep events for the given repositories. Events without a repository
+    /// (info, unknown) pass through.
+    fn filter_repos(self, repos: &[Did]) -> Filtered<Self> {
+        Filtered::new(self, EventFilter::Repos(repos.to_vec()))
+    }
+
+    /// Keep only commit events.
+    fn commits_only(self) -> Filtered<Self> {
+        Filtered::new(self, EventFilter::CommitsOnly)
+    }
+
+    /// End the stream once `token` is cancelled.
+    ///
+    /// The consumer's loop then exits normally, so it can flush its cursor
+    /// or other state before shutting down, instead of the task being
+    /// aborted partway through handling an event.
+    fn until_cancelled(self, token: CancellationToken) -> Cancellable<Self> {
+        Cancellable {
+            inner: Box::pin(self),
+            cancelled: Some(Box::pin(token.cancelled_owned())),
+        }
+    }
+
+    /// Check that sequence numbers increase, reporting skipped ones.
+    ///
+    /// When the sequence jumps, a [`GapDetected`] info event is yielded
+    /// before the event that follows the gap (see [`InfoEvent::gap`]), and
+    /// the [`on_gap`](Sequenced::on_gap) callback, if any, is called so the
+    /// missed range can be backfilled. Events whose sequence number is not
+    /// above the last one seen, such as those replayed after reconnecting,
+    /// are dropped. Synthetic events with sequence number 0, such as those
+    /// from a [`Backfiller`](crate::repo::Backfiller), pass through unchecked.
+    ///
+    /// Meant for `subscribeRepos` streams, whose sequence numbers are
+    /// consecutive. Jetstream uses timestamps as sequence numbers, so every
+    /// event there would look like a gap.
+    fn check_sequence(self) -> Sequenced<Self> {
+        Sequenced {
+            inner: Box::pin(self),
+            last_seq: None,
+            pending: None,
+            on_gap: None,
+        }
+    }
+}
+
+impl<T: Firehose + Sized> FirehoseExt for T {}
+
+#[derive(Debug, Clone)]
+enum EventFilter {
+    Collections(Vec<Nsid>),
+    Repos(Vec<Did>),
+    CommitsOnly,
+}
+
+impl EventFilter {
+    fn apply(&self, event: RepoEvent) -> Option<RepoEvent> {
+        match (self, event) {
+            (EventFilter::Collections(collections), RepoEvent::Commit(mut commit)) => {
+                commit.ops.retain(|op| {
+                    let collection = op.path.split('/').next().unwrap_or_default();
+                    collections.iter().any(|c| c.as_str() == collection)
+                });
+                (!commit.ops.is_empty()).then_some(RepoEvent::Commit(commit))
+            }
+            (EventFilter::Collections(_), event) => Some(event),
+            (EventFilter::Repos(repos), event) => match event.repo() {
+                Some(repo) if !repos.iter().any(|d| d.as_str() == repo) => None,
+                _ => Some(event),
+            },
+            (EventFilter::CommitsOnly, event @ RepoEvent::Commit(_)) => Some(event),
+            (EventFilter::CommitsOnly, _) => None,
+        }
+    }
+}
+
+/// A firehose with a client-side filter applied.
+///
+/// Created by the [`FirehoseExt`] combinators.
+pub struct Filtered<S> {
+    inner: Pin<Box<S>>,
+    filter: EventFilter,
+}
+
+impl<S> Filtered<S> {
+    fn new(inner: S, filter: EventFilter) -> Self {
+        Self {
+            inner: Box::pin(inner),
+            filter,
+        }
+    }
+}
+
+impl<S: Firehose> Stream for Filtered<S> {
+    type Item = Result<RepoEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(event))) => {
+                    if let Some(event) = self.filter.apply(event) {
+                        return Poll::Ready(Some(Ok(event)));
+                    }
+                }
+                other => return other,
+            }
+        }
+    }
+}
+
+/// A firehose that ends when a [`CancellationToken`] is cancelled.
+///
+/// Created by [`FirehoseExt::until_cancelled`].
+pub struct Cancellable<S> {
+    inner: Pin<Box<S>>,
+    cancelled: Option<Pin<Box<WaitForCancellationFutureOwned>>>,
+}
+
+impl<S: Firehose> Stream for Cancellable<S> {
+    type Item = Result<RepoEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let Some(cancelled) = self.cancelled.as_mut() else {
+            return Poll::Ready(None);
+        };
+        if cancelled.as_mut().poll(cx).is_ready() {
+            self.cancelled = None;
+            return Poll::Ready(None);
+        }
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+/// Callback invoked with each gap in a [`Sequenced`] firehose.
+type GapFn = Arc<dyn Fn(GapDetected) + Send + Sync>;
+
+/// A firehose whose sequence numbers are checked for gaps.
+///
+/// Created by [`FirehoseExt::check_sequence`].
+pub struct Sequenced<S> {
+    inner: Pin<Box<S>>,
+    last_seq: Option<i64>,
+    /// The event after a gap, held back while the gap is reported.
+    pending: Option<RepoEvent>,
+    on_gap: Option<GapFn>,
+}
+
+impl<S> Sequenced<S> {
+    /// Treat `seq` as already received, as when resuming from a saved
+    /// cursor, so a gap before the first event is reported too.
+    pub fn after(mut self, seq: i64) -> Self {
+        self.last_seq = Some(seq);
+        self
+    }
+
+    /// Call `on_gap` with every gap, for example to start a backfill.
+    ///
+    /// It runs inside the stream's poll, so it should hand work off (send
+    /// on a channel, spawn a task) rather than block.
+    pub fn on_gap(mut self, on_gap: impl Fn(GapDetected) + Send + Sync + 'static) -> Self {
+        self.on_gap = Some(Arc::new(on_gap));
+        self
+    }
+
+    /// The highest sequence number received so far.
+    pub fn last_seq(&self) -> Option<i64> {
+        self.last_seq
+    }
+}
+
+impl<S> fmt::Debug for Sequenced<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sequenced")
+            .field("last_seq", &self.last_seq)
+            .field("on_gap", &self.on_gap.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S: Firehose> Stream for Sequenced<S> {
+    type Item = Result<RepoEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(event) = self.pending.take() {
+            return Poll::Ready(Some(Ok(event)));
+        }
+        loop {
+            let event = match self.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(event))) => event,
+                other => return other,
+            };
+            let Some(seq) = event.seq().filter(|&seq| seq != 0) else {
+                return Poll::Ready(Some(Ok(event)));
+            };
+            let last = self.last_seq;
+            if last.is_some_and(|last| seq <= last) {
+                continue;
+            }
+            self.last_seq = Some(seq);
+            match last {
+                Some(from) if seq > from.saturating_add(1) => {
+                    let gap = GapDetected { from, to: seq };
+                    if let Some(on_gap) = &self.on_gap {
+                        on_gap(gap);
+                    }
+                    self.pending = Some(event);
+                    return Poll::Ready(Some(Ok(RepoEvent::Info(InfoEvent::from(gap)))));
+                }
+                _ => return Poll::Ready(Some(Ok(event))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repo::{CommitEvent, CommitOperation, IdentityEvent};
+
+    struct Events(Vec<Result<RepoEvent>>);
+
+    impl Stream for Events {
+        type Item = Result<RepoEvent>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            if self.0.is_empty() {
+                Poll::Ready(None)
+            } else {
+                Poll::Ready(Some(self.0.remove(0)))
+            }
+        }
+    }
+
+    fn commit(repo: &str, paths: &[&str]) -> Result<RepoEvent> {
+        Ok(RepoEvent::Commit(CommitEvent {
+            repo: repo.to_string(),
+            rev: "rev".to_string(),
+            since: None,
+            seq: 1,
+            time: "2024-01-01T00:00:00Z".parse().unwrap(),
+            ops: paths
+                .iter()
+                .map(|p| CommitOperation {
+                    path: p.to_string(),
+                    action: "create".to_string(),
+                    cid: None,
+                })
+                .collect(),
+            blocks: Vec::new(),
+            blobs: Vec::new(),
+        }))
+    }
+
+    fn identity(did: &str) -> Result<RepoEvent> {
+        identity_at(did, 1)
+    }
+
+    fn identity_at(did: &str, seq: i64) -> Result<RepoEvent> {
+        Ok(RepoEvent::Identity(IdentityEvent {
+            did: did.to_string(),
+            seq,
+            time: "2024-01-01T00:00:00Z".parse().unwrap(),
+        }))
+    }
+
+    fn collect<S: Stream<Item = Result<RepoEvent>>>(stream: S) -> Vec<RepoEvent> {
+        let waker = std::task::Waker::noop();
+        let mut cx = Context::from_waker(waker);
+        let mut stream = Box::pin(stream);
+        let mut out = Vec::new();
+        while let Poll::Ready(Some(item)) = stream.as_mut().poll_next(&mut cx) {
+            out.push(item.unwrap());
+        }
+        out
+    }
+
+    #[test]
+    fn filter_collections_trims_ops() {
+        let posts = Nsid::new("app.bsky.feed.post").unwrap();
+        let events = Events(vec![
+            commit(
+                "did:plc:aaaaaaaaaaaaaaaaaaaaaaaa",
+                &["app.bsky.feed.post/1", "app.bsky.feed.like/2"],
+            ),
+            commit(
+                "did:plc:aaaaaaaaaaaaaaaaaaaaaaaa",
+                &["app.bsky.feed.like/3"],
+            ),
+            identity("did:plc:aaaaaaaaaaaaaaaaaaaaaaaa"),
+        ]);
+
+        let out = collect(events.filter_collections(&[posts]));
+        assert_eq!(out.len(), 2);
+        let RepoEvent::Commit(commit) = &out[0] else {
+            panic!("expected commit");
+        };
+        assert_eq!(commit.ops.len(), 1);
+        assert!(matches!(out[1], RepoEvent::Identity(_)));
+    }
+
+    #[test]
+    fn filters_compose() {
+        let did = Did::new("did:plc:aaaaaaaaaaaaaaaaaaaaaaaa").unwrap();
+        let events = Events(vec![
+            commit(
+                "did:plc:aaaaaaaaaaaaaaaaaaaaaaaa",
+                &["app.bsky.feed.post/1"],
+            ),
+            commit(
+                "did:plc:baaaaaaaaaaaaaaaaaaaaaaa",
+                &["app.bsky.feed.post/2"],
+            ),
+            identity("did:plc:aaaaaaaaaaaaaaaaaaaaaaaa"),
+        ]);
+
+        let out = collect(events.filter_repos(&[did]).commits_only());
+        assert_eq!(out.len(), 1);
+        assert!(
+            matches!(&out[0], RepoEvent::Commit(c) if c.repo == "did:plc:aaaaaaaaaaaaaaaaaaaaaaaa")
+        );
+    }
+
+    #[test]
+    fn until_cancelled_ends_the_stream() {
+        let token = CancellationToken::new();
+        let events = Events(vec![
+            identity("did:plc:aaaaaaaaaaaaaaaaaaaaaaaa"),
+            identity("did:plc:baaaaaaaaaaaaaaaaaaaaaaa"),
+        ]);
+        let mut stream = Box::pin(events.until_cancelled(token.clone()));
+        let mut cx = Context::from_waker(std::task::Waker::noop());
+
+        assert!(matches!(
+            stream.as_mut().poll_next(&mut cx),
+            Poll::Ready(Some(Ok(_)))
+        ));
+        token.cancel();
+        assert!(matches!(
+            stream.as_mut().poll_next(&mut cx),
+            Poll::Ready(None)
+        ));
+        assert!(matches!(
+            stream.as_mut().poll_next(&mut cx),
+            Poll::Ready(None)
+        ));
+    }
+
+    #[test]
+    fn check_sequence_reports_gaps_and_drops_replays() {
+        let did = "did:plc:aaaaaaaaaaaaaaaaaaaaaaaa";
+        let events = Events(vec![
+            identity_at(did, 11),
+            identity_at(did, 12),
+            identity_at(did, 12),
+            identity_at(did, 0),
+            identity_at(did, 15),
+            identity_at(did, 16),
+        ]);
+        let gaps = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = gaps.clone();
+        let sequenced = events
+            .check_sequence()
+            .after(9)
+            .on_gap(move |gap| seen.lock().unwrap().push(gap));
+
+        let out = collect(sequenced);
+        let summary: Vec<String> = out
+            .iter()
+            .map(|event| match event {
+                RepoEvent::Info(info) => {
+                    let gap = info.gap().unwrap();
+                    format!("gap {}-{}", gap.from, gap.to)
+                }
+                event => event.seq().unwrap().to_string(),
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            ["gap 9-11", "11", "12", "0", "gap 12-15", "15", "16"]
+        );
+        assert_eq!(
+            *gaps.lock().unwrap(),
+            [
+                GapDetected { from: 9, to: 11 },
+                GapDetected { from: 12, to: 15 }
+            ]
+        );
+    }
+}
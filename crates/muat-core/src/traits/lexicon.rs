@@ -0,0 +1,34 @@
+//! Typed lexicon definitions.
+
+/// A Rust type for a lexicon definition, naming the schema it follows.
+///
+/// Implemented by the types `muat-codegen` generates, and by hand for
+/// types written without it. Records convert to and from
+/// [`RecordValue`](crate::RecordValue) with
+/// [`RecordValue::from_lexicon`](crate::RecordValue::from_lexicon) and
+/// [`RecordValue::to_lexicon`](crate::RecordValue::to_lexicon).
+///
+/// # Example
+///
+/// ```
+/// use muat_core::Lexicon;
+///
+/// struct Note {
+///     text: String,
+/// }
+///
+/// impl Lexicon for Note {
+///     const NSID: &'static str = "org.example.note";
+/// }
+///
+/// assert_eq!(Note::TYPE, "org.example.note");
+/// ```
+pub trait Lexicon {
+    /// The NSID of the lexicon the definition is in, such as
+    /// `app.bsky.feed.post`.
+    const NSID: &'static str;
+
+    /// The `$type` of values of this definition: the NSID for a lexicon's
+    /// `main` definition, and `nsid#name` for any other.
+    const TYPE: &'static str = Self::NSID;
+}
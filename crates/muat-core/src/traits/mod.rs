@@ -1,9 +1,19 @@
 //! Core traits for PDS and session behavior.
 
+mod blob;
+mod cursor_store;
 mod firehose;
+mod lexicon;
 mod pds;
 mod session;
+mod session_store;
+mod sink;
 
-pub use firehose::Firehose;
-pub use pds::{CreateAccountOutput, Pds};
-pub use session::Session;
+pub use blob::BlobStore;
+pub use cursor_store::CursorStore;
+pub use firehose::{Cancellable, Filtered, Firehose, FirehoseExt, LabelStream, Sequenced};
+pub use lexicon::Lexicon;
+pub use pds::{CreateAccountOutput, Pds, ServerDescription, ServerLinks};
+pub use session::{AccountStatus, Session, create_records_pipelined, delete_records_pipelined};
+pub use session_store::{SessionStore, StoredSession};
+pub use sink::EventSink;
@@ -2,10 +2,12 @@
 
 use async_trait::async_trait;
 
-use crate::types::{Did, PdsUrl};
+use crate::error::{InvalidInputError, ProtocolError};
+use crate::repo::{Car, ListRecordsOptions, ListRecordsOutput, Record};
+use crate::types::{AtUri, Cid, Did, Handle, Nsid, PdsUrl, Tid};
 use crate::{AccessToken, Credentials, Result};
 
-use super::{Firehose, Session};
+use super::{Firehose, Session, SessionStore, StoredSession};
 
 /// Output from account creation.
 #[derive(Debug, Clone)]
@@ -16,6 +18,48 @@ pub struct CreateAccountOutput {
     pub handle: String,
 }
 
+/// What a PDS reports about itself, from `com.atproto.server.describeServer`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerDescription {
+    /// The server's DID.
+    pub did: String,
+    /// Handle domains accounts can be created under, such as
+    /// `.bsky.social`; empty if any domain is accepted.
+    pub available_user_domains: Vec<String>,
+    /// Whether an invite code is needed to create an account.
+    pub invite_code_required: bool,
+    /// Whether a verified phone number is needed to create an account.
+    pub phone_verification_required: bool,
+    /// Links to the server's policies.
+    pub links: ServerLinks,
+    /// Email address for contacting the server's operators.
+    pub contact_email: Option<String>,
+}
+
+impl ServerDescription {
+    /// Check a new account's handle and invite code against what the server
+    /// requires, so a request it would reject fails here with a clear error.
+    pub fn check_new_account(&self, handle: &Handle, invite_code: Option<&str>) -> Result<()> {
+        handle.check_user_domains(&self.available_user_domains)?;
+        if self.invite_code_required && invite_code.is_none_or(str::is_empty) {
+            return Err(InvalidInputError::Other {
+                message: "this PDS requires an invite code to create an account".to_string(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Policy links in a [`ServerDescription`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServerLinks {
+    /// URL of the privacy policy.
+    pub privacy_policy: Option<String>,
+    /// URL of the terms of service.
+    pub terms_of_service: Option<String>,
+}
+
 /// A PDS implementation.
 #[async_trait]
 pub trait Pds: Send + Sync {
@@ -30,6 +74,45 @@ pub trait Pds: Send + Sync {
     /// Authenticate with the PDS and create a new session.
     async fn login(&self, credentials: Credentials) -> Result<Self::Session>;
 
+    /// Resume a stored session of this PDS.
+    ///
+    /// Checks the tokens are still accepted, refreshing them where the
+    /// backend supports it, so the returned session's tokens may differ
+    /// from `stored`.
+    async fn restore(&self, stored: &StoredSession) -> Result<Self::Session>;
+
+    /// Resume the session stored for `credentials`, or log in if there is
+    /// none or it can no longer be used, and save the session's tokens.
+    ///
+    /// Sessions are stored under [`StoredSession::key`]. Tokens that change
+    /// later, e.g. through an explicit refresh, are not saved automatically;
+    /// save [`StoredSession::from_session`] under the same key.
+    async fn login_or_restore(
+        &self,
+        credentials: Credentials,
+        store: &dyn SessionStore,
+    ) -> Result<Self::Session> {
+        let key = StoredSession::key(self.url(), credentials.identifier());
+
+        let restored = match store.load(&key).await? {
+            Some(stored) if &stored.pds == self.url() => self.restore(&stored).await.ok(),
+            _ => None,
+        };
+        let session = match restored {
+            Some(session) => session,
+            None => self.login(credentials).await?,
+        };
+
+        store
+            .save(&key, &StoredSession::from_session(&session))
+            .await?;
+        Ok(session)
+    }
+
+    /// Describe the server: what creating an account needs, which handle
+    /// domains it hosts, and its policy links. Needs no session.
+    async fn describe(&self) -> Result<ServerDescription>;
+
     /// Create a new account.
     async fn create_account(
         &self,
@@ -47,6 +130,73 @@ pub trait Pds: Send + Sync {
         password: Option<&str>,
     ) -> Result<()>;
 
+    /// Ask the PDS to email a password reset token to the account with this
+    /// address, for [`reset_password`](Self::reset_password). Needs no
+    /// session.
+    async fn request_password_reset(&self, email: &str) -> Result<()>;
+
+    /// Set a new password with a token sent by
+    /// [`request_password_reset`](Self::request_password_reset).
+    ///
+    /// Fails with an `InvalidToken` or `ExpiredToken` protocol error if the
+    /// token is wrong or too old. Existing sessions may end.
+    async fn reset_password(&self, token: &str, password: &str) -> Result<()>;
+
+    /// Fetch a record without a session.
+    ///
+    /// Repository records are public, so no credentials are sent.
+    async fn get_record_public(&self, uri: &AtUri) -> Result<Record>;
+
+    /// List records in any repo the PDS hosts, without a session.
+    ///
+    /// Options and ordering are as for
+    /// [`Session::list_records_with`](super::Session::list_records_with).
+    async fn list_records_public(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        options: &ListRecordsOptions,
+    ) -> Result<ListRecordsOutput>;
+
+    /// List the collections of any repo the PDS hosts, without a session.
+    async fn list_collections_public(&self, repo: &Did) -> Result<Vec<Nsid>>;
+
+    /// Fetch blocks of a repo by CID, from `com.atproto.sync.getBlocks`,
+    /// without a session.
+    ///
+    /// The returned CAR has no roots. Backends that do not store repos as
+    /// blocks fail with a `MethodNotImplemented` protocol error.
+    async fn get_blocks(&self, did: &Did, cids: &[Cid]) -> Result<Car> {
+        let _ = (did, cids);
+        Err(sync_not_implemented("getBlocks"))
+    }
+
+    /// Fetch a repo as a CAR, from `com.atproto.sync.getRepo`, without a
+    /// session.
+    ///
+    /// With `since`, only blocks added after that revision are included, so
+    /// a copy known to be at `since` can be brought up to date; without it,
+    /// the whole repo. The CAR's root is the latest commit. Backends that do
+    /// not store repos as blocks fail with a `MethodNotImplemented`
+    /// protocol error.
+    async fn get_repo_since(&self, did: &Did, since: Option<&Tid>) -> Result<Car> {
+        let _ = (did, since);
+        Err(sync_not_implemented("getRepo"))
+    }
+
+    /// Fetch the blocks proving a record's contents at the repo's latest
+    /// commit, from `com.atproto.sync.getRecord`, without a session.
+    ///
+    /// The CAR holds the signed commit, the MST nodes on the path to the
+    /// record, and the record if it exists. Check it with
+    /// [`verify_inclusion`](crate::repo::verify_inclusion). Backends that do
+    /// not store repos as blocks fail with a `MethodNotImplemented`
+    /// protocol error.
+    async fn get_record_proof(&self, uri: &AtUri) -> Result<Car> {
+        let _ = uri;
+        Err(sync_not_implemented("getRecord"))
+    }
+
     /// Subscribe to the firehose stream.
     fn firehose(&self) -> Result<Self::Firehose> {
         self.firehose_from(None)
@@ -55,3 +205,15 @@ pub trait Pds: Send + Sync {
     /// Subscribe to the firehose stream from an optional cursor.
     fn firehose_from(&self, cursor: Option<i64>) -> Result<Self::Firehose>;
 }
+
+fn sync_not_implemented(method: &str) -> crate::Error {
+    ProtocolError::new(
+        501,
+        Some("MethodNotImplemented".to_string()),
+        Some(format!(
+            "this PDS does not serve com.atproto.sync.{}",
+            method
+        )),
+    )
+    .into()
+}
@@ -1,9 +1,23 @@
 //! Authenticated session trait.
 
+use std::collections::VecDeque;
+
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_core::Stream;
+use futures_util::stream::BoxStream;
+use futures_util::{StreamExt, stream};
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+use crate::repo::{
+    BlobRef, BulkReport, ByteRange, DeleteCollectionOptions, DeletionReport, ListRecordsOptions,
+    ListRecordsOutput, Record, RecordValue, delete_collection_paged,
+};
 
-use crate::repo::{ListRecordsOutput, Record, RecordValue};
-use crate::types::{AtUri, Did, Nsid, PdsUrl};
+use super::BlobStore;
+use crate::error::{Error, ProtocolError};
+use crate::types::{AtUri, Did, Nsid, PdsUrl, Rkey};
 use crate::{AccessToken, RefreshToken, Result};
 
 /// An authenticated session for repository operations.
@@ -21,18 +35,71 @@ pub trait Session: Send + Sync {
     /// Returns the refresh token for this session, if any.
     fn refresh_token(&self) -> Option<RefreshToken>;
 
-    /// List records in a collection.
+    /// List records in a collection in ascending record key order.
+    ///
+    /// Shorthand for [`list_records_with`](Self::list_records_with) with the
+    /// given limit and cursor.
     async fn list_records(
         &self,
         repo: &Did,
         collection: &Nsid,
         limit: Option<u32>,
         cursor: Option<&str>,
+    ) -> Result<ListRecordsOutput> {
+        let mut options = ListRecordsOptions::new();
+        if let Some(limit) = limit {
+            options = options.limit(limit);
+        }
+        if let Some(cursor) = cursor {
+            options = options.cursor(cursor);
+        }
+        self.list_records_with(repo, collection, &options).await
+    }
+
+    /// List records in a collection.
+    ///
+    /// Records are ordered by record key as set by
+    /// [`ListRecordsOptions::order`], ascending by default. Implementations
+    /// must honour the order identically, including across cursor pages.
+    async fn list_records_with(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        options: &ListRecordsOptions,
     ) -> Result<ListRecordsOutput>;
 
+    /// List the collections of a repo that hold records, sorted.
+    async fn list_collections(&self, repo: &Did) -> Result<Vec<Nsid>>;
+
+    /// Stream every record in a repo, one collection after another, in
+    /// record key order within each.
+    ///
+    /// Pages are fetched as the stream is read. It ends after the first
+    /// error, which is yielded.
+    fn iter_all_records<'a>(&'a self, repo: &'a Did) -> BoxStream<'a, Result<Record>> {
+        Box::pin(all_records(self, repo))
+    }
+
     /// Get a single record by its AT URI.
     async fn get_record(&self, uri: &AtUri) -> Result<Record>;
 
+    /// Get the version of a record with the given CID.
+    ///
+    /// Backends that keep record history return earlier versions too; the
+    /// default, like a network PDS, returns the record only while it still
+    /// has that CID. Fails with a `RecordNotFound` protocol error otherwise.
+    async fn get_record_at(&self, uri: &AtUri, cid: &str) -> Result<Record> {
+        let record = self.get_record(uri).await?;
+        if record.cid != cid {
+            return Err(Error::Protocol(ProtocolError::new(
+                404,
+                Some("RecordNotFound".to_string()),
+                Some(format!("Record {} has no version with CID {}", uri, cid)),
+            )));
+        }
+        Ok(record)
+    }
+
     /// Create a new record in a collection with a validated [`RecordValue`].
     async fn create_record(&self, collection: &Nsid, value: &RecordValue) -> Result<AtUri>;
 
@@ -46,6 +113,286 @@ pub trait Session: Send + Sync {
         self.create_record(collection, &record_value).await
     }
 
+    /// Create many records in a collection.
+    ///
+    /// A failed item does not stop the others; the report lists the created
+    /// URIs and each failed value with its error, in input order. At most
+    /// `concurrency` requests are in flight (values below 1 are treated as
+    /// 1). The default issues pipelined [`create_record`](Self::create_record)
+    /// calls; backends may batch writes instead.
+    ///
+    /// Once `cancel` is cancelled no further writes start; writes already in
+    /// flight finish. The values that were not written are returned in
+    /// [`BulkReport::skipped`] and can be resumed.
+    async fn create_records_bulk(
+        &self,
+        collection: &Nsid,
+        values: Vec<RecordValue>,
+        concurrency: usize,
+        cancel: Option<&CancellationToken>,
+    ) -> BulkReport<RecordValue, AtUri> {
+        create_records_pipelined(self, collection, values, concurrency, cancel).await
+    }
+
+    /// Create or overwrite the record at a caller-chosen record key.
+    ///
+    /// With `swap_cid`, the write only succeeds if the record currently has
+    /// that CID; otherwise it fails with an `InvalidSwap` protocol error and
+    /// nothing is written. Use this to update a record read earlier without
+    /// clobbering a concurrent change.
+    async fn put_record(
+        &self,
+        collection: &Nsid,
+        rkey: &Rkey,
+        value: &RecordValue,
+        swap_cid: Option<&str>,
+    ) -> Result<AtUri>;
+
     /// Delete a record by its AT URI.
     async fn delete_record(&self, uri: &AtUri) -> Result<()>;
+
+    /// Delete many records by AT URI.
+    ///
+    /// Behaves like [`create_records_bulk`](Self::create_records_bulk): the
+    /// report lists the deleted URIs, each failed URI with its error, and the
+    /// URIs skipped after `cancel` was cancelled.
+    async fn delete_records_bulk(
+        &self,
+        uris: Vec<AtUri>,
+        concurrency: usize,
+        cancel: Option<&CancellationToken>,
+    ) -> BulkReport<AtUri, AtUri> {
+        delete_records_pipelined(self, uris, concurrency, cancel).await
+    }
+
+    /// Delete every record in a collection, a page at a time.
+    ///
+    /// Each page is deleted with
+    /// [`delete_records_bulk`](Self::delete_records_bulk), so backends that
+    /// batch writes batch these too. Failed deletes are collected in the
+    /// report rather than stopping the run; errors listing the collection
+    /// are returned. See [`DeleteCollectionOptions`] for dry runs, progress
+    /// and cancellation.
+    async fn delete_collection(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        options: &DeleteCollectionOptions,
+    ) -> Result<DeletionReport> {
+        delete_collection_paged(self, repo, collection, options).await
+    }
+
+    /// Ask the PDS to email a token confirming the account's email address,
+    /// for [`confirm_email`](Self::confirm_email).
+    async fn request_email_confirmation(&self) -> Result<()>;
+
+    /// Confirm the account's email address with a token sent by
+    /// [`request_email_confirmation`](Self::request_email_confirmation).
+    ///
+    /// Fails with an `InvalidToken` or `ExpiredToken` protocol error if the
+    /// token is wrong or too old, and `InvalidEmail` if `email` is not the
+    /// account's address.
+    async fn confirm_email(&self, email: &str, token: &str) -> Result<()>;
+
+    /// Report whether the account is active and how much of its
+    /// repository the PDS holds, from `com.atproto.server.checkAccountStatus`.
+    ///
+    /// Use it after importing a repository and its blobs into a new PDS to
+    /// check the migration is complete before
+    /// [`activate_account`](Self::activate_account).
+    async fn check_account_status(&self) -> Result<AccountStatus>;
+
+    /// Activate the account, such as once it has been migrated to this PDS.
+    /// Activating an active account does nothing.
+    async fn activate_account(&self) -> Result<()>;
+
+    /// Deactivate the account. Its repository is no longer served and
+    /// writes fail until it is activated again; the session itself keeps
+    /// working. With `delete_after`, the PDS may delete the account once that
+    /// time has passed.
+    async fn deactivate_account(&self, delete_after: Option<DateTime<Utc>>) -> Result<()>;
+
+    /// Returns the blob store backing this session.
+    fn blobs(&self) -> &dyn BlobStore;
+
+    /// Upload a blob for use in records.
+    async fn upload_blob(&self, data: Vec<u8>, mime_type: &str) -> Result<BlobRef> {
+        self.blobs().put_blob(data, mime_type).await
+    }
+
+    /// Fetch a blob's content by CID.
+    async fn get_blob(&self, cid: &str) -> Result<Vec<u8>> {
+        self.blobs().get_blob(cid).await
+    }
+
+    /// Fetch part of a blob's content by CID, such as the rest of an
+    /// interrupted download.
+    async fn get_blob_range(&self, cid: &str, range: ByteRange) -> Result<Vec<u8>> {
+        self.blobs().get_blob_range(cid, range).await
+    }
+}
+
+/// An account's state, from
+/// [`check_account_status`](Session::check_account_status).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountStatus {
+    /// Whether the account is active.
+    pub activated: bool,
+    /// Whether the account's DID document points at this PDS, with its
+    /// signing key.
+    pub valid_did: bool,
+    /// CID of the repository's latest commit, if the PDS reports one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo_commit: Option<String>,
+    /// Revision of the repository's latest commit, if it has any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo_rev: Option<String>,
+    /// Blocks in the repository.
+    #[serde(default)]
+    pub repo_blocks: u64,
+    /// Records in the repository.
+    #[serde(default)]
+    pub indexed_records: u64,
+    /// Private state values, such as preferences.
+    #[serde(default)]
+    pub private_state_values: u64,
+    /// Blobs the repository's records reference.
+    #[serde(default)]
+    pub expected_blobs: u64,
+    /// Blobs the PDS holds for the repository. A migration is missing blobs
+    /// while this is below `expected_blobs`.
+    #[serde(default)]
+    pub imported_blobs: u64,
+}
+
+/// Create records with pipelined [`Session::create_record`] calls.
+///
+/// This is the default [`Session::create_records_bulk`] strategy, exposed so
+/// batching backends can fall back to it.
+pub async fn create_records_pipelined<S: Session + ?Sized>(
+    session: &S,
+    collection: &Nsid,
+    values: Vec<RecordValue>,
+    concurrency: usize,
+    cancel: Option<&CancellationToken>,
+) -> BulkReport<RecordValue, AtUri> {
+    // Collect the futures up front; a lazy `map` closure trips the
+    // higher-ranked `Send` check in async-trait default methods.
+    let requests: Vec<_> = values
+        .iter()
+        .map(|value| async move {
+            // Futures start in input order, so once one sees the token
+            // cancelled every later one does too.
+            if cancel.is_some_and(CancellationToken::is_cancelled) {
+                return None;
+            }
+            Some(session.create_record(collection, value).await)
+        })
+        .collect();
+    let results: Vec<_> = stream::iter(requests)
+        .buffered(concurrency.max(1))
+        .collect()
+        .await;
+    let results: Vec<_> = results.into_iter().map_while(|result| result).collect();
+    BulkReport::from_results(values, results)
+}
+
+/// Delete records with pipelined [`Session::delete_record`] calls.
+///
+/// This is the default [`Session::delete_records_bulk`] strategy, exposed so
+/// batching backends can fall back to it.
+pub async fn delete_records_pipelined<S: Session + ?Sized>(
+    session: &S,
+    uris: Vec<AtUri>,
+    concurrency: usize,
+    cancel: Option<&CancellationToken>,
+) -> BulkReport<AtUri, AtUri> {
+    // Collect the futures up front, as in `create_records_pipelined`.
+    let requests: Vec<_> = uris
+        .iter()
+        .map(|uri| async move {
+            if cancel.is_some_and(CancellationToken::is_cancelled) {
+                return None;
+            }
+            Some(session.delete_record(uri).await.map(|()| uri.clone()))
+        })
+        .collect();
+    let results: Vec<_> = stream::iter(requests)
+        .buffered(concurrency.max(1))
+        .collect()
+        .await;
+    let results: Vec<_> = results.into_iter().map_while(|result| result).collect();
+    BulkReport::from_results(uris, results)
+}
+
+/// Where [`Session::iter_all_records`] has got to.
+struct AllRecords<'a, S: ?Sized> {
+    session: &'a S,
+    repo: &'a Did,
+    /// Collections still to read, or `None` before they are listed.
+    collections: Option<VecDeque<Nsid>>,
+    /// Cursor into the first collection.
+    cursor: Option<String>,
+    /// Records read but not yet yielded.
+    ready: VecDeque<Record>,
+}
+
+fn all_records<'a, S: Session + ?Sized>(
+    session: &'a S,
+    repo: &'a Did,
+) -> impl Stream<Item = Result<Record>> + Send + 'a {
+    let progress = AllRecords {
+        session,
+        repo,
+        collections: None,
+        cursor: None,
+        ready: VecDeque::new(),
+    };
+    stream::unfold(Some(progress), |progress| async move {
+        let mut progress = progress?;
+        match progress.next().await? {
+            Ok(record) => Some((Ok(record), Some(progress))),
+            Err(e) => Some((Err(e), None)),
+        }
+    })
+}
+
+impl<S: Session + ?Sized> AllRecords<'_, S> {
+    async fn next(&mut self) -> Option<Result<Record>> {
+        loop {
+            if let Some(record) = self.ready.pop_front() {
+                return Some(Ok(record));
+            }
+            let collections = match &mut self.collections {
+                Some(collections) => collections,
+                None => match self.session.list_collections(self.repo).await {
+                    Ok(collections) => self.collections.insert(collections.into()),
+                    Err(e) => return Some(Err(e)),
+                },
+            };
+            let collection = collections.front()?.clone();
+            let mut options = ListRecordsOptions::new();
+            if let Some(cursor) = &self.cursor {
+                options = options.cursor(cursor.clone());
+            }
+            let page = match self
+                .session
+                .list_records_with(self.repo, &collection, &options)
+                .await
+            {
+                Ok(page) => page,
+                Err(e) => return Some(Err(e)),
+            };
+            let done =
+                page.records.is_empty() || page.cursor.is_none() || page.cursor == self.cursor;
+            self.ready.extend(page.records);
+            if done {
+                collections.pop_front();
+                self.cursor = None;
+            } else {
+                self.cursor = page.cursor;
+            }
+        }
+    }
 }
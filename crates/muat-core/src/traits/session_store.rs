@@ -0,0 +1,92 @@
+//! Session persistence trait.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Did, PdsUrl};
+use crate::{AccessToken, RefreshToken, Result};
+
+use super::Session;
+
+/// The tokens of a session, as kept by a [`SessionStore`].
+///
+/// Debug output redacts the tokens; the serialized form does not, so treat
+/// it as a secret.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "StoredSessionRepr", from = "StoredSessionRepr")]
+pub struct StoredSession {
+    /// The PDS the session belongs to.
+    pub pds: PdsUrl,
+    /// The account DID.
+    pub did: Did,
+    /// The access token.
+    pub access_token: AccessToken,
+    /// The refresh token, if the backend issues one.
+    pub refresh_token: Option<RefreshToken>,
+}
+
+impl StoredSession {
+    /// Capture the current tokens of a session.
+    pub fn from_session<S: Session + ?Sized>(session: &S) -> Self {
+        Self {
+            pds: session.pds().clone(),
+            did: session.did().clone(),
+            access_token: session.access_token(),
+            refresh_token: session.refresh_token(),
+        }
+    }
+
+    /// The key [`Pds::login_or_restore`](super::Pds::login_or_restore) stores
+    /// the session for a PDS and login identifier under.
+    pub fn key(pds: &PdsUrl, identifier: &str) -> String {
+        format!("{} {}", pds, identifier)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredSessionRepr {
+    pds: PdsUrl,
+    did: Did,
+    access_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    refresh_token: Option<String>,
+}
+
+impl From<StoredSession> for StoredSessionRepr {
+    fn from(session: StoredSession) -> Self {
+        Self {
+            pds: session.pds,
+            did: session.did,
+            access_token: session.access_token.0,
+            refresh_token: session.refresh_token.map(|token| token.0),
+        }
+    }
+}
+
+impl From<StoredSessionRepr> for StoredSession {
+    fn from(repr: StoredSessionRepr) -> Self {
+        Self {
+            pds: repr.pds,
+            did: repr.did,
+            access_token: AccessToken::new(repr.access_token),
+            refresh_token: repr.refresh_token.map(RefreshToken::new),
+        }
+    }
+}
+
+/// Somewhere to keep sessions between runs, keyed by string.
+///
+/// [`MemorySessionStore`](crate::MemorySessionStore) keeps sessions for the
+/// life of the process; `muat_file::FileSessionStore` keeps them in a file.
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    /// Load the session stored under `key`, if any.
+    async fn load(&self, key: &str) -> Result<Option<StoredSession>>;
+
+    /// Store a session under `key`, replacing any already there.
+    async fn save(&self, key: &str, session: &StoredSession) -> Result<()>;
+
+    /// Remove the session stored under `key`. Removing a missing key is not
+    /// an error.
+    async fn remove(&self, key: &str) -> Result<()>;
+}
@@ -0,0 +1,62 @@
+//! Publishing firehose events to message brokers.
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+
+use super::Firehose;
+use crate::Result;
+use crate::repo::{RepoEvent, SinkMessage, TopicMapping};
+
+/// Somewhere to publish firehose events for downstream consumers, such as a
+/// NATS server (`muat-sink-nats`) or a Kafka cluster (`muat-sink-kafka`).
+///
+/// Implementations send [`SinkMessage`]s; the provided methods turn events
+/// into messages with the sink's [`TopicMapping`].
+///
+/// # Example
+///
+/// ```ignore
+/// let topics = TopicMapping::by_collection("atproto.", "atproto.other");
+/// let sink = NatsSink::connect("nats://localhost:4222", topics).await?;
+/// let published = sink.publish_all(pds.firehose_from(cursor)?).await?;
+/// ```
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    /// Returns how events are assigned to topics.
+    fn topics(&self) -> &TopicMapping;
+
+    /// Publish one message to its topic.
+    async fn send(&self, message: SinkMessage) -> Result<()>;
+
+    /// Wait until every message sent so far has been delivered.
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Publish `event` to the topics it maps to, in order.
+    async fn publish(&self, event: &RepoEvent) -> Result<()> {
+        for message in self.topics().messages(event)? {
+            self.send(message).await?;
+        }
+        Ok(())
+    }
+
+    /// Publish every event from `firehose` until it ends, then flush.
+    ///
+    /// Returns the number of events published. Stops at the first error
+    /// from the stream or the sink; events published before it stay
+    /// published.
+    async fn publish_all<F: Firehose>(&self, firehose: F) -> Result<u64>
+    where
+        Self: Sized,
+    {
+        let mut firehose = Box::pin(firehose);
+        let mut count = 0;
+        while let Some(event) = firehose.next().await {
+            self.publish(&event?).await?;
+            count += 1;
+        }
+        self.flush().await?;
+        Ok(count)
+    }
+}
@@ -9,8 +9,12 @@ use crate::error::{Error, InvalidInputError};
 
 /// A validated AT Protocol URI.
 ///
-/// AT URIs identify specific records in the AT Protocol network.
-/// Format: `at://<repo>/<collection>/<rkey>`
+/// AT URIs identify repositories, collections and records in the AT
+/// Protocol network.
+/// Format: `at://<repo>[/<collection>[/<rkey>]][?<query>][#<fragment>]`
+///
+/// Most APIs take record URIs, which name a collection and record key; use
+/// [`record_path`](Self::record_path) to require one.
 ///
 /// # Example
 ///
@@ -18,14 +22,19 @@ use crate::error::{Error, InvalidInputError};
 /// use muat_core::AtUri;
 ///
 /// let uri = AtUri::new("at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.feed.post/3jui7kd54zh2y").unwrap();
-/// assert_eq!(uri.collection().as_str(), "app.bsky.feed.post");
-/// assert_eq!(uri.rkey().as_str(), "3jui7kd54zh2y");
+/// assert_eq!(uri.collection().unwrap().as_str(), "app.bsky.feed.post");
+/// assert_eq!(uri.rkey().unwrap().as_str(), "3jui7kd54zh2y");
+///
+/// let posts = uri.parent_collection_uri().unwrap();
+/// assert_eq!(posts.to_string(), "at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.feed.post");
 /// ```
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct AtUri {
     repo: Did,
-    collection: Nsid,
-    rkey: Rkey,
+    collection: Option<Nsid>,
+    rkey: Option<Rkey>,
+    query: Option<String>,
+    fragment: Option<String>,
 }
 
 impl AtUri {
@@ -39,12 +48,38 @@ impl AtUri {
         Self::parse(s)
     }
 
-    /// Create an AT URI from its components.
+    /// Create a record URI from its components.
     pub fn from_parts(repo: Did, collection: Nsid, rkey: Rkey) -> Self {
         Self {
             repo,
-            collection,
-            rkey,
+            collection: Some(collection),
+            rkey: Some(rkey),
+            query: None,
+            fragment: None,
+        }
+    }
+
+    /// Start building a URI for a repository.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use muat_core::{AtUri, Did, Nsid};
+    ///
+    /// let uri = AtUri::builder(Did::new("did:plc:z72i7hdynmk6r22z27h6tvur").unwrap())
+    ///     .collection(Nsid::new("app.bsky.feed.post").unwrap())
+    ///     .fragment("/text")
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(uri.to_string(), "at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.feed.post#/text");
+    /// ```
+    pub fn builder(repo: Did) -> AtUriBuilder {
+        AtUriBuilder {
+            repo,
+            collection: None,
+            rkey: None,
+            query: None,
+            fragment: None,
         }
     }
 
@@ -53,62 +88,302 @@ impl AtUri {
         &self.repo
     }
 
-    /// Returns the collection (NSID).
-    pub fn collection(&self) -> &Nsid {
-        &self.collection
+    /// Returns the collection (NSID), if the URI names one.
+    pub fn collection(&self) -> Option<&Nsid> {
+        self.collection.as_ref()
+    }
+
+    /// Returns the record key, if the URI names a record.
+    pub fn rkey(&self) -> Option<&Rkey> {
+        self.rkey.as_ref()
+    }
+
+    /// Returns the query, without the leading `?`.
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    /// Returns the fragment, without the leading `#`.
+    pub fn fragment(&self) -> Option<&str> {
+        self.fragment.as_deref()
+    }
+
+    /// Returns whether the URI names a record.
+    pub fn is_record(&self) -> bool {
+        self.rkey.is_some()
+    }
+
+    /// The collection and record key of a record URI.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URI names a repository or collection rather
+    /// than a record.
+    pub fn record_path(&self) -> Result<(&Nsid, &Rkey), Error> {
+        match (&self.collection, &self.rkey) {
+            (Some(collection), Some(rkey)) => Ok((collection, rkey)),
+            _ => Err(InvalidInputError::AtUri {
+                value: self.to_string(),
+                reason: "must name a record: 'at://<repo>/<collection>/<rkey>'".to_string(),
+            }
+            .into()),
+        }
+    }
+
+    /// The URI of a record in this URI's collection.
+    ///
+    /// Any query or fragment is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URI does not name a collection.
+    pub fn with_rkey(&self, rkey: Rkey) -> Result<Self, Error> {
+        let collection = self
+            .collection
+            .clone()
+            .ok_or_else(|| InvalidInputError::AtUri {
+                value: self.to_string(),
+                reason: "must name a collection to add a record key".to_string(),
+            })?;
+        Ok(Self::from_parts(self.repo.clone(), collection, rkey))
     }
 
-    /// Returns the record key.
-    pub fn rkey(&self) -> &Rkey {
-        &self.rkey
+    /// For a record URI, the URI of its collection.
+    pub fn parent_collection_uri(&self) -> Option<Self> {
+        self.rkey.as_ref()?;
+        Some(Self {
+            repo: self.repo.clone(),
+            collection: self.collection.clone(),
+            rkey: None,
+            query: None,
+            fragment: None,
+        })
     }
 
     fn parse(s: &str) -> Result<Self, Error> {
-        // Format: at://<repo>/<collection>/<rkey>
+        // Format: at://<repo>[/<collection>[/<rkey>]][?<query>][#<fragment>]
         let rest = s
-            .strip_prefix("at://")
+            .strip_prefix(PREFIX)
             .ok_or_else(|| InvalidInputError::AtUri {
                 value: s.to_string(),
-                reason: "must start with 'at://'".to_string(),
+                reason: format!("must start with '{}'{}", PREFIX, prefix_hint(s)),
             })?;
 
-        // Split into parts
-        let parts: Vec<&str> = rest.splitn(3, '/').collect();
+        let (rest, fragment) = match rest.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(fragment)),
+            None => (rest, None),
+        };
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (rest, None),
+        };
 
-        if parts.len() != 3 {
-            return Err(InvalidInputError::AtUri {
-                value: s.to_string(),
-                reason: "must have format 'at://<repo>/<collection>/<rkey>'".to_string(),
-            }
-            .into());
-        }
+        // Split into parts
+        let parts: Vec<&str> = path.splitn(3, '/').collect();
 
-        let repo = Did::new(parts[0]).map_err(|_| InvalidInputError::AtUri {
-            value: s.to_string(),
-            reason: format!("invalid DID: {}", parts[0]),
+        let repo_at = PREFIX.len();
+        let repo = Did::new(parts[0]).map_err(|e| {
+            let hint = if !parts[0].contains(':') && parts[0].contains('.') {
+                format!(
+                    "; '{}' looks like a handle, resolve it to a DID first",
+                    parts[0]
+                )
+            } else {
+                String::new()
+            };
+            segment_error(s, "repo", parts[0], repo_at, e, &hint)
         })?;
 
-        let collection = Nsid::new(parts[1]).map_err(|_| InvalidInputError::AtUri {
-            value: s.to_string(),
-            reason: format!("invalid NSID: {}", parts[1]),
-        })?;
+        let collection_at = repo_at + parts[0].len() + 1;
+        let collection = parts
+            .get(1)
+            .map(|segment| {
+                Nsid::new(*segment)
+                    .map_err(|e| segment_error(s, "collection", segment, collection_at, e, ""))
+            })
+            .transpose()?;
 
-        let rkey = Rkey::new(parts[2]).map_err(|_| InvalidInputError::AtUri {
-            value: s.to_string(),
-            reason: format!("invalid rkey: {}", parts[2]),
-        })?;
+        let rkey_at = collection_at + parts.get(1).map_or(0, |p| p.len()) + 1;
+        let rkey = parts
+            .get(2)
+            .map(|segment| {
+                Rkey::new(*segment).map_err(|e| segment_error(s, "rkey", segment, rkey_at, e, ""))
+            })
+            .transpose()?;
+
+        let query_at = PREFIX.len() + path.len() + 1;
+        if let Some(query) = query {
+            validate_component(s, "query", query, query_at)?;
+        }
+        if let Some(fragment) = fragment {
+            let fragment_at = query_at + query.map_or(0, |q| q.len() + 1);
+            validate_component(s, "fragment", fragment, fragment_at)?;
+        }
 
         Ok(Self {
             repo,
             collection,
             rkey,
+            query: query.map(str::to_string),
+            fragment: fragment.map(str::to_string),
         })
     }
 }
 
+/// Builds an [`AtUri`] from its components.
+#[derive(Clone, Debug)]
+pub struct AtUriBuilder {
+    repo: Did,
+    collection: Option<Nsid>,
+    rkey: Option<Rkey>,
+    query: Option<String>,
+    fragment: Option<String>,
+}
+
+impl AtUriBuilder {
+    /// Set the collection.
+    pub fn collection(mut self, collection: Nsid) -> Self {
+        self.collection = Some(collection);
+        self
+    }
+
+    /// Set the record key. Requires a collection.
+    pub fn rkey(mut self, rkey: Rkey) -> Self {
+        self.rkey = Some(rkey);
+        self
+    }
+
+    /// Set the query, without the leading `?`.
+    pub fn query(mut self, query: impl Into<String>) -> Self {
+        self.query = Some(query.into());
+        self
+    }
+
+    /// Set the fragment, without the leading `#`.
+    pub fn fragment(mut self, fragment: impl Into<String>) -> Self {
+        self.fragment = Some(fragment.into());
+        self
+    }
+
+    /// Build the URI.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a record key is set without a collection, or the
+    /// query or fragment contains characters not allowed in a URI.
+    pub fn build(self) -> Result<AtUri, Error> {
+        let uri = AtUri {
+            repo: self.repo,
+            collection: self.collection,
+            rkey: self.rkey,
+            query: self.query,
+            fragment: self.fragment,
+        };
+        if uri.rkey.is_some() && uri.collection.is_none() {
+            return Err(InvalidInputError::AtUri {
+                value: uri.to_string(),
+                reason: "a record key needs a collection".to_string(),
+            }
+            .into());
+        }
+        // Round-trip through the parser to check the query and fragment.
+        AtUri::new(uri.to_string())
+    }
+}
+
+const PREFIX: &str = "at://";
+
+/// Suggest a fix for a string that is missing the `at://` prefix.
+fn prefix_hint(s: &str) -> String {
+    if s.starts_with("http://") || s.starts_with("https://") {
+        return "; this is a web URL, not an AT URI".to_string();
+    }
+
+    let candidate = if let Some(rest) = s.strip_prefix("at:") {
+        rest.trim_start_matches('/')
+    } else {
+        s
+    };
+
+    if candidate.contains('/') {
+        format!("; did you mean '{}{}'?", PREFIX, candidate)
+    } else {
+        String::new()
+    }
+}
+
+/// Build an AT URI error for an invalid path segment starting at byte `offset`.
+///
+/// Positions inside `detail` are relative to the segment itself.
+fn segment_error(
+    value: &str,
+    name: &str,
+    segment: &str,
+    offset: usize,
+    cause: Error,
+    hint: &str,
+) -> InvalidInputError {
+    let detail = match &cause {
+        Error::InvalidInput(e) => e.reason().to_string(),
+        other => other.to_string(),
+    };
+
+    InvalidInputError::AtUri {
+        value: value.to_string(),
+        reason: format!(
+            "invalid {} '{}' at byte {}: {}{}",
+            name, segment, offset, detail, hint
+        ),
+    }
+}
+
+/// Check a query or fragment against the characters RFC 3986 allows there.
+fn validate_component(
+    value: &str,
+    name: &str,
+    component: &str,
+    offset: usize,
+) -> Result<(), Error> {
+    let bytes = component.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let len = match bytes[i] {
+            b'%' if bytes
+                .get(i + 1..i + 3)
+                .is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit)) =>
+            {
+                3
+            }
+            c if c.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/?".contains(&c) => 1,
+            _ => {
+                return Err(InvalidInputError::AtUri {
+                    value: value.to_string(),
+                    reason: format!("invalid {} character at byte {}", name, offset + i),
+                }
+                .into());
+            }
+        };
+        i += len;
+    }
+    Ok(())
+}
+
 impl fmt::Display for AtUri {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "at://{}/{}/{}", self.repo, self.collection, self.rkey)
+        write!(f, "at://{}", self.repo)?;
+        if let Some(collection) = &self.collection {
+            write!(f, "/{}", collection)?;
+        }
+        if let Some(rkey) = &self.rkey {
+            write!(f, "/{}", rkey)?;
+        }
+        if let Some(query) = &self.query {
+            write!(f, "?{}", query)?;
+        }
+        if let Some(fragment) = &self.fragment {
+            write!(f, "#{}", fragment)?;
+        }
+        Ok(())
     }
 }
 
@@ -150,8 +425,9 @@ mod tests {
                 .unwrap();
 
         assert_eq!(uri.repo().as_str(), "did:plc:z72i7hdynmk6r22z27h6tvur");
-        assert_eq!(uri.collection().as_str(), "app.bsky.feed.post");
-        assert_eq!(uri.rkey().as_str(), "3jui7kd54zh2y");
+        assert_eq!(uri.collection().unwrap().as_str(), "app.bsky.feed.post");
+        assert_eq!(uri.rkey().unwrap().as_str(), "3jui7kd54zh2y");
+        assert!(uri.is_record());
     }
 
     #[test]
@@ -180,7 +456,87 @@ mod tests {
     }
 
     #[test]
-    fn invalid_missing_rkey() {
-        assert!(AtUri::new("at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.feed.post").is_err());
+    fn collection_and_repo_uris() {
+        let uri = AtUri::new("at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.feed.post").unwrap();
+        assert_eq!(uri.collection().unwrap().as_str(), "app.bsky.feed.post");
+        assert!(uri.rkey().is_none());
+        assert!(uri.record_path().is_err());
+        assert!(uri.parent_collection_uri().is_none());
+
+        let record = uri.with_rkey(Rkey::new("3jui7kd54zh2y").unwrap()).unwrap();
+        assert_eq!(
+            record.to_string(),
+            "at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.feed.post/3jui7kd54zh2y"
+        );
+        assert_eq!(record.parent_collection_uri().unwrap(), uri);
+
+        let repo = AtUri::new("at://did:plc:z72i7hdynmk6r22z27h6tvur").unwrap();
+        assert!(repo.collection().is_none());
+        assert!(repo.with_rkey(Rkey::new("self").unwrap()).is_err());
+        assert!(AtUri::new("at://did:plc:z72i7hdynmk6r22z27h6tvur/").is_err());
+    }
+
+    #[test]
+    fn query_and_fragment_round_trip() {
+        let original =
+            "at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.feed.post/3jui7kd54zh2y?a=1#/text";
+        let uri = AtUri::new(original).unwrap();
+        assert_eq!(uri.query(), Some("a=1"));
+        assert_eq!(uri.fragment(), Some("/text"));
+        assert_eq!(uri.rkey().unwrap().as_str(), "3jui7kd54zh2y");
+        assert_eq!(uri.to_string(), original);
+
+        assert!(AtUri::new("at://did:plc:z72i7hdynmk6r22z27h6tvur#bad fragment").is_err());
+        assert!(AtUri::new("at://did:plc:z72i7hdynmk6r22z27h6tvur#%zz").is_err());
+    }
+
+    #[test]
+    fn builder() {
+        let repo = Did::new("did:plc:z72i7hdynmk6r22z27h6tvur").unwrap();
+        let uri = AtUri::builder(repo.clone())
+            .collection(Nsid::new("app.bsky.feed.post").unwrap())
+            .rkey(Rkey::new("3jui7kd54zh2y").unwrap())
+            .fragment("/embed")
+            .build()
+            .unwrap();
+        assert_eq!(
+            uri.to_string(),
+            "at://did:plc:z72i7hdynmk6r22z27h6tvur/app.bsky.feed.post/3jui7kd54zh2y#/embed"
+        );
+
+        assert!(
+            AtUri::builder(repo.clone())
+                .rkey(Rkey::new("self").unwrap())
+                .build()
+                .is_err()
+        );
+        assert!(AtUri::builder(repo).query("a b").build().is_err());
+    }
+
+    #[test]
+    fn missing_prefix_suggests_fix() {
+        let err = AtUri::new("did:plc:abc/app.bsky.feed.post/rkey").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("did you mean 'at://did:plc:abc/app.bsky.feed.post/rkey'?")
+        );
+    }
+
+    #[test]
+    fn handle_repo_suggests_did() {
+        let err = AtUri::new("at://alice.bsky.social/app.bsky.feed.post/rkey").unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("invalid repo 'alice.bsky.social' at byte 5"));
+        assert!(msg.contains("looks like a handle"));
+    }
+
+    #[test]
+    fn segment_errors_report_offset() {
+        let err = AtUri::new("at://did:plc:abcaaaaaaaaaaaaaaaaaaaaa/app.bsky.feed.post/bad/key")
+            .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("invalid rkey 'bad/key' at byte 57")
+        );
     }
 }
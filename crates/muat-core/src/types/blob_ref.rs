@@ -0,0 +1,162 @@
+//! Blob reference type.
+
+use serde::{Deserialize, Serialize};
+
+use super::Cid;
+use crate::error::{Error, InvalidInputError};
+
+/// A reference to an uploaded blob (the lexicon `blob` type).
+///
+/// Serializes to the form records embed:
+/// `{"$type": "blob", "ref": {"$link": "<cid>"}, "mimeType": "...", "size": n}`.
+/// The CID and MIME type are validated on construction and deserialization.
+///
+/// # Example
+///
+/// ```
+/// use muat_core::{BlobRef, Cid};
+///
+/// let cid = Cid::new("bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e").unwrap();
+/// let blob = BlobRef::new(cid, "image/png", 1024).unwrap();
+/// let json = serde_json::to_value(&blob).unwrap();
+/// assert_eq!(json["$type"], "blob");
+/// assert_eq!(json["ref"]["$link"], blob.cid.as_str());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    tag = "$type",
+    rename = "blob",
+    rename_all = "camelCase",
+    try_from = "RawBlobRef"
+)]
+pub struct BlobRef {
+    /// CID of the blob content.
+    #[serde(rename = "ref", with = "cid_link")]
+    pub cid: Cid,
+    /// MIME type of the blob.
+    pub mime_type: String,
+    /// Size in bytes.
+    pub size: u64,
+}
+
+impl BlobRef {
+    /// Create a blob reference.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `mime_type` is not of the form `type/subtype`.
+    pub fn new(cid: Cid, mime_type: impl Into<String>, size: u64) -> Result<Self, Error> {
+        let mime_type = mime_type.into();
+        validate_mime_type(&mime_type)?;
+        Ok(Self {
+            cid,
+            mime_type,
+            size,
+        })
+    }
+}
+
+fn validate_mime_type(mime_type: &str) -> Result<(), Error> {
+    let valid = mime_type.split_once('/').is_some_and(|(kind, subtype)| {
+        !kind.is_empty()
+            && !subtype.is_empty()
+            && !mime_type.contains(char::is_whitespace)
+            && mime_type.is_ascii()
+    });
+    if !valid {
+        return Err(Error::InvalidInput(InvalidInputError::Other {
+            message: format!(
+                "invalid blob MIME type '{}': expected type/subtype",
+                mime_type
+            ),
+        }));
+    }
+    Ok(())
+}
+
+/// Unvalidated wire form, checked by `TryFrom`.
+#[derive(Deserialize)]
+#[serde(tag = "$type", rename = "blob", rename_all = "camelCase")]
+struct RawBlobRef {
+    #[serde(rename = "ref", with = "cid_link")]
+    cid: Cid,
+    mime_type: String,
+    size: u64,
+}
+
+impl TryFrom<RawBlobRef> for BlobRef {
+    type Error = Error;
+
+    fn try_from(raw: RawBlobRef) -> Result<Self, Self::Error> {
+        Self::new(raw.cid, raw.mime_type, raw.size)
+    }
+}
+
+/// Serde helper for `{"$link": "<cid>"}`.
+mod cid_link {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Cid;
+
+    #[derive(Serialize, Deserialize)]
+    struct Link<T> {
+        #[serde(rename = "$link")]
+        link: T,
+    }
+
+    pub fn serialize<S: Serializer>(cid: &Cid, serializer: S) -> Result<S::Ok, S::Error> {
+        Link { link: cid }.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Cid, D::Error> {
+        Link::deserialize(deserializer).map(|l| l.link)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CID: &str = "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e";
+
+    #[test]
+    fn blob_ref_round_trips_lexicon_form() {
+        let value = json!({
+            "$type": "blob",
+            "ref": {"$link": CID},
+            "mimeType": "image/jpeg",
+            "size": 42
+        });
+
+        let blob: BlobRef = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(
+            blob,
+            BlobRef::new(Cid::new(CID).unwrap(), "image/jpeg", 42).unwrap()
+        );
+        assert_eq!(serde_json::to_value(&blob).unwrap(), value);
+    }
+
+    #[test]
+    fn blob_ref_rejects_invalid_fields() {
+        let cid = Cid::new(CID).unwrap();
+        assert!(BlobRef::new(cid.clone(), "jpeg", 1).is_err());
+        assert!(BlobRef::new(cid, "image/", 1).is_err());
+
+        let bad_cid = json!({
+            "$type": "blob",
+            "ref": {"$link": "bafkreiblob"},
+            "mimeType": "image/jpeg",
+            "size": 42
+        });
+        assert!(serde_json::from_value::<BlobRef>(bad_cid).is_err());
+
+        let bad_mime = json!({
+            "$type": "blob",
+            "ref": {"$link": CID},
+            "mimeType": "",
+            "size": 42
+        });
+        assert!(serde_json::from_value::<BlobRef>(bad_mime).is_err());
+    }
+}
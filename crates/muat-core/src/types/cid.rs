@@ -0,0 +1,395 @@
+//! Content identifier (CID) type.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Error, InvalidInputError};
+
+/// RFC 4648 base32 alphabet, lowercase.
+const BASE32: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// A validated content identifier (CID).
+///
+/// Accepts CIDv1 in base32 multibase (the `b...` form AT Protocol uses) and
+/// legacy CIDv0 (`Qm...`). The string is checked to decode to a well-formed
+/// CID: version, codec and a multihash whose digest length matches.
+///
+/// # Example
+///
+/// ```
+/// use muat_core::Cid;
+///
+/// let cid = Cid::new("bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e").unwrap();
+/// assert_eq!(cid.version(), 1);
+/// assert_eq!(cid.codec(), Cid::RAW);
+/// assert!(Cid::new("bafkreiblob").is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Cid(String);
+
+impl Cid {
+    /// Multicodec for raw bytes, used for blobs.
+    pub const RAW: u64 = 0x55;
+    /// Multicodec for DAG-CBOR, used for records and commits.
+    pub const DAG_CBOR: u64 = 0x71;
+    /// Multicodec for DAG-PB, the implicit codec of CIDv0.
+    pub const DAG_PB: u64 = 0x70;
+    /// Multihash code for SHA-256.
+    pub const SHA2_256: u64 = 0x12;
+
+    /// Create a CID from a string, validating the format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the string is not a base32 CIDv1 or a CIDv0.
+    pub fn new(s: impl Into<String>) -> Result<Self, Error> {
+        let s = s.into();
+        if let Err(reason) = Self::parse(&s) {
+            return Err(InvalidInputError::Cid {
+                value: s,
+                reason: reason.to_string(),
+            }
+            .into());
+        }
+        Ok(Self(s))
+    }
+
+    /// Build a base32 CIDv1 from a codec, a multihash code and a digest.
+    ///
+    /// For example, a blob CID is `Cid::v1(Cid::RAW, Cid::SHA2_256, &sha256)`.
+    pub fn v1(codec: u64, hash_code: u64, digest: &[u8]) -> Self {
+        let mut bytes = Vec::with_capacity(digest.len() + 8);
+        write_varint(&mut bytes, 1);
+        write_varint(&mut bytes, codec);
+        write_varint(&mut bytes, hash_code);
+        write_varint(&mut bytes, digest.len() as u64);
+        bytes.extend_from_slice(digest);
+        Self(format!("b{}", base32_lower(&bytes)))
+    }
+
+    /// Create a CID from its binary form, the inverse of [`Cid::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not a well-formed CID.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() == 34 && bytes.starts_with(&[0x12, 0x20]) {
+            return Self::new(bs58::encode(bytes).into_string());
+        }
+        Self::new(format!("b{}", base32_lower(bytes)))
+    }
+
+    /// Read a binary CID from the start of `bytes`, advancing past it, as
+    /// CAR sections store them.
+    pub(crate) fn read_prefix(bytes: &mut &[u8]) -> Result<Self, Error> {
+        let start = *bytes;
+        let mut rest = start;
+        // A CIDv0 is a bare multihash; a CIDv1 has a version and codec first.
+        let fields = if start.starts_with(&[0x12, 0x20]) {
+            1
+        } else {
+            3
+        };
+        for _ in 0..fields {
+            read_varint(&mut rest).ok_or_else(|| truncated(start))?;
+        }
+        let length = read_varint(&mut rest).ok_or_else(|| truncated(start))?;
+        if (rest.len() as u64) < length {
+            return Err(truncated(start));
+        }
+        let end = start.len() - rest.len() + length as usize;
+        let cid = Self::from_bytes(&start[..end])?;
+        *bytes = &start[end..];
+        Ok(cid)
+    }
+
+    /// Returns the CID version (0 or 1).
+    pub fn version(&self) -> u64 {
+        self.fields().version
+    }
+
+    /// Returns the multicodec of the content, such as [`Cid::RAW`].
+    pub fn codec(&self) -> u64 {
+        self.fields().codec
+    }
+
+    /// Returns the multihash code, such as [`Cid::SHA2_256`].
+    pub fn hash_code(&self) -> u64 {
+        self.fields().hash_code
+    }
+
+    /// Returns the binary form of the CID, as DAG-CBOR links embed it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        // Validated at construction, so both decodes succeed.
+        match self.0.strip_prefix('b') {
+            Some(encoded) => decode_base32(encoded).unwrap_or_default(),
+            None => bs58::decode(&self.0).into_vec().unwrap_or_default(),
+        }
+    }
+
+    /// Returns the CID string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    fn fields(&self) -> Fields {
+        // Validated at construction.
+        Self::parse(&self.0).unwrap_or(Fields {
+            version: 0,
+            codec: Self::DAG_PB,
+            hash_code: Self::SHA2_256,
+        })
+    }
+
+    fn parse(s: &str) -> Result<Fields, &'static str> {
+        if s.is_empty() {
+            return Err("cannot be empty");
+        }
+
+        if s.len() == 46 && s.starts_with("Qm") {
+            let bytes = bs58::decode(s)
+                .into_vec()
+                .map_err(|_| "CIDv0 must be base58btc")?;
+            if bytes.len() != 34 || bytes[0] != 0x12 || bytes[1] != 0x20 {
+                return Err("CIDv0 must be a 32-byte SHA-256 multihash");
+            }
+            return Ok(Fields {
+                version: 0,
+                codec: Self::DAG_PB,
+                hash_code: Self::SHA2_256,
+            });
+        }
+
+        let Some(encoded) = s.strip_prefix('b') else {
+            return Err(
+                "must be a base32 CIDv1 (starting with 'b') or a CIDv0 (starting with 'Qm')",
+            );
+        };
+        let bytes = decode_base32(encoded).ok_or("must be lowercase base32 (a-z, 2-7)")?;
+
+        let mut rest = bytes.as_slice();
+        let version = read_varint(&mut rest).ok_or("truncated version")?;
+        if version != 1 {
+            return Err("unsupported CID version");
+        }
+        let codec = read_varint(&mut rest).ok_or("truncated codec")?;
+        let hash_code = read_varint(&mut rest).ok_or("truncated multihash code")?;
+        let length = read_varint(&mut rest).ok_or("truncated multihash length")?;
+        if rest.len() as u64 != length {
+            return Err("multihash digest length does not match its content");
+        }
+
+        Ok(Fields {
+            version,
+            codec,
+            hash_code,
+        })
+    }
+}
+
+/// Decoded header of a CID.
+struct Fields {
+    version: u64,
+    codec: u64,
+    hash_code: u64,
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn truncated(bytes: &[u8]) -> Error {
+    InvalidInputError::Cid {
+        value: format!("{} bytes", bytes.len()),
+        reason: "truncated binary CID".to_string(),
+    }
+    .into()
+}
+
+/// Read an unsigned varint from the start of `bytes`, advancing past it.
+pub(crate) fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    // Multiformats varints are at most 9 bytes.
+    for (i, &byte) in bytes.iter().enumerate().take(9) {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            *bytes = &bytes[i + 1..];
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// RFC 4648 base32, lowercase, no padding: the encoding of CIDv1 strings
+/// after their `b` multibase prefix, and of `did:plc` identifiers.
+pub fn base32_lower(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(5) * 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = (buffer << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    out
+}
+
+fn decode_base32(s: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len() * 5 / 8);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in s.bytes() {
+        let value = BASE32.iter().position(|&b| b == c)? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    // Leftover bits are padding and must be zero.
+    if bits >= 5 || buffer & ((1 << bits) - 1) != 0 {
+        return None;
+    }
+    Some(out)
+}
+
+impl fmt::Display for Cid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Cid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<String> for Cid {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::new(s)
+    }
+}
+
+impl From<Cid> for String {
+    fn from(cid: Cid) -> Self {
+        cid.0
+    }
+}
+
+impl AsRef<str> for Cid {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOB: &str = "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e";
+    const RECORD: &str = "bafyreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm";
+
+    #[test]
+    fn parses_v1_cids() {
+        let blob = Cid::new(BLOB).unwrap();
+        assert_eq!(blob.version(), 1);
+        assert_eq!(blob.codec(), Cid::RAW);
+        assert_eq!(blob.hash_code(), Cid::SHA2_256);
+
+        let record = Cid::new(RECORD).unwrap();
+        assert_eq!(record.codec(), Cid::DAG_CBOR);
+    }
+
+    #[test]
+    fn parses_v0_cids() {
+        let cid = Cid::new("QmdfTbBqBPQ7VNxZEYEj14VmRuZBkqFbiwReogJgS1zR1n").unwrap();
+        assert_eq!(cid.version(), 0);
+        assert_eq!(cid.codec(), Cid::DAG_PB);
+        // A CIDv0 is its bare multihash.
+        assert_eq!(&cid.to_bytes()[..2], &[0x12, 0x20]);
+    }
+
+    #[test]
+    fn v1_round_trips() {
+        let digest = [7u8; 32];
+        let cid = Cid::v1(Cid::RAW, Cid::SHA2_256, &digest);
+        assert!(cid.as_str().starts_with("bafkrei"));
+        assert_eq!(Cid::new(cid.as_str()).unwrap(), cid);
+
+        let mut bytes = vec![0x01, 0x55, 0x12, 0x20];
+        bytes.extend(digest);
+        assert_eq!(cid.to_bytes(), bytes);
+        assert_eq!(Cid::from_bytes(&bytes).unwrap(), cid);
+    }
+
+    #[test]
+    fn binary_round_trips() {
+        for s in [
+            BLOB,
+            RECORD,
+            "QmdfTbBqBPQ7VNxZEYEj14VmRuZBkqFbiwReogJgS1zR1n",
+        ] {
+            let cid = Cid::new(s).unwrap();
+            assert_eq!(Cid::from_bytes(&cid.to_bytes()).unwrap(), cid);
+        }
+        assert!(Cid::from_bytes(&[0x01, 0x55]).is_err());
+    }
+
+    #[test]
+    fn reads_binary_prefix() {
+        let cid = Cid::new(RECORD).unwrap();
+        let mut bytes = cid.to_bytes();
+        bytes.extend([0xa0, 0x01]);
+        let mut rest = bytes.as_slice();
+        assert_eq!(Cid::read_prefix(&mut rest).unwrap(), cid);
+        assert_eq!(rest, [0xa0, 0x01]);
+
+        let v0 = Cid::new("QmdfTbBqBPQ7VNxZEYEj14VmRuZBkqFbiwReogJgS1zR1n").unwrap();
+        let bytes = v0.to_bytes();
+        let mut rest = bytes.as_slice();
+        assert_eq!(Cid::read_prefix(&mut rest).unwrap(), v0);
+        assert!(rest.is_empty());
+
+        let bytes = cid.to_bytes();
+        assert!(Cid::read_prefix(&mut &bytes[..20]).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_cids() {
+        for invalid in [
+            "",
+            "bafkreiblob",
+            "zb2rhe5P4gXftAwvA4eXQ5HJwsER2owDyS9sKaQRRVQPn93bA",
+            "BAFKREIFZJUT3TE2NHYEKKLSS27NH3K72YSCO7Y32KOAO5EEI66WOF36N5E",
+            "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5",
+            "Qm",
+        ] {
+            assert!(Cid::new(invalid).is_err(), "{invalid:?} should be invalid");
+        }
+    }
+
+    #[test]
+    fn serde_validates() {
+        let cid: Cid = serde_json::from_str(&format!("\"{BLOB}\"")).unwrap();
+        assert_eq!(cid.as_str(), BLOB);
+        assert!(serde_json::from_str::<Cid>("\"bafkreiblob\"").is_err());
+    }
+}
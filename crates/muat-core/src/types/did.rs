@@ -6,9 +6,17 @@ use std::str::FromStr;
 
 use crate::error::{Error, InvalidInputError};
 
+/// Longest DID accepted, per the AT Protocol spec.
+const MAX_LEN: usize = 2048;
+
+/// Length of a `did:plc` method-specific identifier.
+const PLC_ID_LEN: usize = 24;
+
 /// A validated Decentralized Identifier (DID).
 ///
 /// DIDs in the AT Protocol typically use the `did:plc:` or `did:web:` methods.
+/// Every DID is checked against the generic DID syntax; `did:plc` and
+/// `did:web` are also checked against their method's rules.
 ///
 /// # Example
 ///
@@ -17,6 +25,10 @@ use crate::error::{Error, InvalidInputError};
 ///
 /// let did = Did::new("did:plc:z72i7hdynmk6r22z27h6tvur").unwrap();
 /// assert_eq!(did.method(), "plc");
+///
+/// let web = Did::web("localhost:3000").unwrap();
+/// assert_eq!(web.as_str(), "did:web:localhost%3A3000");
+/// assert_eq!(web.web_host().as_deref(), Some("localhost:3000"));
 /// ```
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(try_from = "String", into = "String")]
@@ -34,6 +46,15 @@ impl Did {
         Ok(Self(s))
     }
 
+    /// Create a `did:web` DID for a host, with an optional `:port`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `host` is not a valid hostname.
+    pub fn web(host: &str) -> Result<Self, Error> {
+        Self::new(format!("did:web:{}", host.replacen(':', "%3A", 1)))
+    }
+
     /// Returns the DID method (e.g., "plc" for "did:plc:...").
     pub fn method(&self) -> &str {
         // Safe because we validated at construction
@@ -43,8 +64,8 @@ impl Did {
             .unwrap_or("")
     }
 
-    /// Returns the method-specific identifier.
-    pub fn identifier(&self) -> &str {
+    /// Returns the method-specific identifier (e.g., the part after "did:plc:").
+    pub fn method_specific_id(&self) -> &str {
         // Safe because we validated at construction
         self.0
             .strip_prefix("did:")
@@ -53,54 +74,105 @@ impl Did {
             .unwrap_or("")
     }
 
+    /// Renamed to [`method_specific_id`](Self::method_specific_id).
+    #[deprecated(note = "renamed to `method_specific_id`")]
+    pub fn identifier(&self) -> &str {
+        self.method_specific_id()
+    }
+
+    /// For a `did:web` DID, the host it names, including any port.
+    pub fn web_host(&self) -> Option<String> {
+        (self.method() == "web").then(|| self.method_specific_id().replacen("%3A", ":", 1))
+    }
+
     /// Returns the full DID string.
     pub fn as_str(&self) -> &str {
         &self.0
     }
 
     fn validate(s: &str) -> Result<(), Error> {
-        // Basic DID validation per AT Protocol spec
-        // Format: did:<method>:<method-specific-id>
-        if !s.starts_with("did:") {
-            return Err(InvalidInputError::Did {
+        let invalid = |reason: &str| {
+            Err(InvalidInputError::Did {
                 value: s.to_string(),
-                reason: "must start with 'did:'".to_string(),
+                reason: reason.to_string(),
             }
-            .into());
-        }
+            .into())
+        };
 
-        let rest = &s[4..];
-        let parts: Vec<&str> = rest.splitn(2, ':').collect();
+        // Format: did:<method>:<method-specific-id>
+        let Some(rest) = s.strip_prefix("did:") else {
+            return invalid("must start with 'did:'");
+        };
+        let Some((method, id)) = rest.split_once(':') else {
+            return invalid("must have format 'did:<method>:<identifier>'");
+        };
 
-        if parts.len() < 2 {
-            return Err(InvalidInputError::Did {
-                value: s.to_string(),
-                reason: "must have format 'did:<method>:<identifier>'".to_string(),
-            }
-            .into());
+        if s.len() > MAX_LEN {
+            return invalid("must be at most 2048 characters");
         }
 
-        let method = parts[0];
-        let identifier = parts[1];
-
         // Method must be non-empty lowercase alphanumeric
         if method.is_empty() || !method.chars().all(|c| c.is_ascii_lowercase()) {
-            return Err(InvalidInputError::Did {
-                value: s.to_string(),
-                reason: "method must be non-empty lowercase letters".to_string(),
-            }
-            .into());
+            return invalid("method must be non-empty lowercase letters");
         }
 
         // Identifier must be non-empty
-        if identifier.is_empty() {
-            return Err(InvalidInputError::Did {
-                value: s.to_string(),
-                reason: "identifier must be non-empty".to_string(),
-            }
-            .into());
+        if id.is_empty() {
+            return invalid("identifier must be non-empty");
+        }
+        if !id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | ':' | '%' | '-'))
+        {
+            return invalid("identifier may only contain letters, digits and ._:%-");
         }
+        if id.ends_with(':') || id.ends_with('%') {
+            return invalid("identifier must not end with ':' or '%'");
+        }
+
+        match method {
+            "plc" => Self::validate_plc(id).or_else(&invalid),
+            "web" => Self::validate_web(id).or_else(&invalid),
+            _ => Ok(()),
+        }
+    }
+
+    fn validate_plc(id: &str) -> Result<(), &'static str> {
+        if id.len() != PLC_ID_LEN {
+            return Err("did:plc identifier must be 24 characters");
+        }
+        if !id.bytes().all(|c| matches!(c, b'a'..=b'z' | b'2'..=b'7')) {
+            return Err("did:plc identifier must be lowercase base32 (a-z, 2-7)");
+        }
+        Ok(())
+    }
 
+    fn validate_web(id: &str) -> Result<(), &'static str> {
+        // Paths (further ':' segments) are not supported by the AT Protocol.
+        let (host, port) = match id.split_once("%3A") {
+            Some((host, port)) => (host, Some(port)),
+            None => (id, None),
+        };
+        if host.contains([':', '%']) {
+            return Err("did:web must name a host, with the port encoded as %3A");
+        }
+        let valid_label = |label: &str| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|c| c.is_ascii_alphanumeric() || c == b'-')
+        };
+        if host.len() > 253 || !host.split('.').all(valid_label) {
+            return Err("did:web host is not a valid hostname");
+        }
+        if let Some(port) = port
+            && port.parse::<u16>().is_err()
+        {
+            return Err("did:web port must be a number");
+        }
         Ok(())
     }
 }
@@ -147,14 +219,47 @@ mod tests {
     fn valid_plc_did() {
         let did = Did::new("did:plc:z72i7hdynmk6r22z27h6tvur").unwrap();
         assert_eq!(did.method(), "plc");
-        assert_eq!(did.identifier(), "z72i7hdynmk6r22z27h6tvur");
+        assert_eq!(did.method_specific_id(), "z72i7hdynmk6r22z27h6tvur");
     }
 
     #[test]
     fn valid_web_did() {
         let did = Did::new("did:web:example.com").unwrap();
         assert_eq!(did.method(), "web");
-        assert_eq!(did.identifier(), "example.com");
+        assert_eq!(did.method_specific_id(), "example.com");
+        assert_eq!(did.web_host().as_deref(), Some("example.com"));
+    }
+
+    #[test]
+    fn web_constructor_encodes_port() {
+        let did = Did::web("localhost:2583").unwrap();
+        assert_eq!(did.as_str(), "did:web:localhost%3A2583");
+        assert_eq!(did.web_host().as_deref(), Some("localhost:2583"));
+        assert!(Did::web("not a host").is_err());
+        assert!(Did::web("example.com:port").is_err());
+    }
+
+    #[test]
+    fn invalid_plc_identifier() {
+        // Wrong length, and characters outside the base32 alphabet.
+        assert!(Did::new("did:plc:abc").is_err());
+        assert!(Did::new("did:plc:z72i7hdynmk6r22z27h6tvu1").is_err());
+        assert!(Did::new("did:plc:Z72I7HDYNMK6R22Z27H6TVUR").is_err());
+        assert!(Did::new("did:plc:0123456789abcdefghij0123").is_err());
+    }
+
+    #[test]
+    fn invalid_web_identifier() {
+        assert!(Did::new("did:web:example.com:user:alice").is_err());
+        assert!(Did::new("did:web:-example.com").is_err());
+        assert!(Did::new("did:web:example..com").is_err());
+    }
+
+    #[test]
+    fn other_methods_use_generic_syntax() {
+        assert!(Did::new("did:key:zQ3shokFTS3brHcDQrn82RUDfCZESWL1ZdCEJwekUDPQiYBme").is_ok());
+        assert!(Did::new("did:example:a b").is_err());
+        assert!(Did::new("did:example:abc:").is_err());
     }
 
     #[test]
@@ -0,0 +1,219 @@
+//! Handle type.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{Error, InvalidInputError};
+
+/// Longest handle accepted, per the AT Protocol spec.
+const MAX_LEN: usize = 253;
+
+/// Longest label (dot-separated segment) of a handle.
+const MAX_LABEL_LEN: usize = 63;
+
+/// A validated AT Protocol handle, such as `alice.example.com`.
+///
+/// Handles are domain names: at least two labels of ASCII letters, digits
+/// and hyphens, with a top-level label that starts with a letter. Any
+/// domain is accepted, since self-hosted PDSes issue handles under their
+/// own; which domains a given PDS allows comes from its `describeServer`
+/// response and is checked with [`check_user_domains`](Self::check_user_domains).
+///
+/// Handles are case-insensitive and stored lowercased.
+///
+/// # Example
+///
+/// ```
+/// use muat_core::Handle;
+///
+/// let handle = Handle::new("Alice.PDS.example.com").unwrap();
+/// assert_eq!(handle.as_str(), "alice.pds.example.com");
+/// assert!(handle.is_under(".pds.example.com"));
+/// assert!(handle.check_user_domains(&[".bsky.social"]).is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Handle(String);
+
+impl Handle {
+    /// Create a new handle from a string, validating the format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the string is not a valid handle.
+    pub fn new(s: impl Into<String>) -> Result<Self, Error> {
+        let s = s.into();
+        Self::validate(&s)?;
+        Ok(Self(s.to_ascii_lowercase()))
+    }
+
+    /// Returns the full handle string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns true if the handle is a subdomain of `domain`.
+    ///
+    /// `domain` may be given with or without the leading dot that
+    /// `describeServer` uses (`.bsky.social` or `bsky.social`). The domain
+    /// itself is not under itself.
+    pub fn is_under(&self, domain: &str) -> bool {
+        let domain = domain.strip_prefix('.').unwrap_or(domain);
+        self.0
+            .strip_suffix(&domain.to_ascii_lowercase())
+            .and_then(|prefix| prefix.strip_suffix('.'))
+            .is_some_and(|prefix| !prefix.is_empty())
+    }
+
+    /// Check the handle against a PDS's available user domains.
+    ///
+    /// An empty list places no restriction, as for PDSes that accept any
+    /// domain.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the handle is under none of `domains`.
+    pub fn check_user_domains<S: AsRef<str>>(&self, domains: &[S]) -> Result<(), Error> {
+        if domains.is_empty() || domains.iter().any(|d| self.is_under(d.as_ref())) {
+            return Ok(());
+        }
+        let domains: Vec<_> = domains.iter().map(AsRef::as_ref).collect();
+        Err(InvalidInputError::Handle {
+            value: self.0.clone(),
+            reason: format!(
+                "must be under one of this PDS's domains: {}",
+                domains.join(", ")
+            ),
+        }
+        .into())
+    }
+
+    fn validate(s: &str) -> Result<(), Error> {
+        let invalid = |reason: &str| {
+            Err(InvalidInputError::Handle {
+                value: s.to_string(),
+                reason: reason.to_string(),
+            }
+            .into())
+        };
+
+        if s.is_empty() {
+            return invalid("cannot be empty");
+        }
+        if s.len() > MAX_LEN {
+            return invalid("must be at most 253 characters");
+        }
+
+        let labels: Vec<&str> = s.split('.').collect();
+        if labels.len() < 2 {
+            return invalid("must have at least two segments (e.g., alice.example.com)");
+        }
+        for label in &labels {
+            if label.is_empty() || label.len() > MAX_LABEL_LEN {
+                return invalid("segments must be 1 to 63 characters");
+            }
+            if !label
+                .bytes()
+                .all(|c| c.is_ascii_alphanumeric() || c == b'-')
+            {
+                return invalid("may only contain letters, digits, hyphens and dots");
+            }
+            if label.starts_with('-') || label.ends_with('-') {
+                return invalid("segments must not start or end with a hyphen");
+            }
+        }
+        if labels
+            .last()
+            .is_some_and(|tld| !tld.starts_with(|c: char| c.is_ascii_alphabetic()))
+        {
+            return invalid("last segment must start with a letter");
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Handle {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<String> for Handle {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::new(s)
+    }
+}
+
+impl From<Handle> for String {
+    fn from(handle: Handle) -> Self {
+        handle.0
+    }
+}
+
+impl AsRef<str> for Handle {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_handles() {
+        assert!(Handle::new("alice.bsky.social").is_ok());
+        assert!(Handle::new("alice.local").is_ok());
+        assert!(Handle::new("x-1.pds.example.co.uk").is_ok());
+        assert_eq!(
+            Handle::new("Bob.Example.COM").unwrap().as_str(),
+            "bob.example.com"
+        );
+    }
+
+    #[test]
+    fn invalid_handles() {
+        assert!(Handle::new("").is_err());
+        assert!(Handle::new("alice").is_err());
+        assert!(Handle::new("alice..example.com").is_err());
+        assert!(Handle::new("-alice.example.com").is_err());
+        assert!(Handle::new("alice.example.com.").is_err());
+        assert!(Handle::new("alice_b.example.com").is_err());
+        assert!(Handle::new("alice.example.123").is_err());
+        assert!(Handle::new(format!("{}.com", "a".repeat(64))).is_err());
+    }
+
+    #[test]
+    fn custom_user_domains() {
+        let handle = Handle::new("alice.pds.example.org").unwrap();
+        assert!(handle.is_under(".pds.example.org"));
+        assert!(handle.is_under("Example.org"));
+        assert!(!handle.is_under(".ample.org"));
+        assert!(
+            !Handle::new("pds.example.org")
+                .unwrap()
+                .is_under(".pds.example.org")
+        );
+
+        assert!(handle.check_user_domains::<&str>(&[]).is_ok());
+        assert!(
+            handle
+                .check_user_domains(&[".bsky.social", ".pds.example.org"])
+                .is_ok()
+        );
+        let err = handle.check_user_domains(&[".bsky.social"]).unwrap_err();
+        assert!(err.to_string().contains(".bsky.social"), "{}", err);
+    }
+}
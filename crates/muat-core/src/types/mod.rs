@@ -4,13 +4,22 @@
 //! ensuring invalid states are unrepresentable.
 
 mod at_uri;
+mod blob_ref;
+mod cid;
 mod did;
+mod handle;
 mod nsid;
 mod pds_url;
 mod rkey;
+mod tid;
 
-pub use at_uri::AtUri;
+pub use at_uri::{AtUri, AtUriBuilder};
+pub use blob_ref::BlobRef;
+pub(crate) use cid::read_varint;
+pub use cid::{Cid, base32_lower};
 pub use did::Did;
+pub use handle::Handle;
 pub use nsid::Nsid;
 pub use pds_url::PdsUrl;
 pub use rkey::Rkey;
+pub use tid::{Tid, TidGenerator};
@@ -85,41 +85,55 @@ impl Nsid {
         if segments.len() < 3 {
             return Err(InvalidInputError::Nsid {
                 value: s.to_string(),
-                reason: "must have at least 3 segments (e.g., 'app.bsky.feed')".to_string(),
+                reason: format!(
+                    "must have at least 3 segments (e.g., 'app.bsky.feed'), found {}",
+                    segments.len()
+                ),
             }
             .into());
         }
 
-        // Validate each segment
+        // Validate each segment, tracking its byte offset for error messages
+        let mut offset = 0;
         for (i, segment) in segments.iter().enumerate() {
             if segment.is_empty() {
                 return Err(InvalidInputError::Nsid {
                     value: s.to_string(),
-                    reason: format!("segment {} is empty", i + 1),
+                    reason: format!("segment {} at byte {} is empty", i + 1, offset),
                 }
                 .into());
             }
 
             // Segments must start with a letter
-            let first_char = segment.chars().next().unwrap();
-            if !first_char.is_ascii_alphabetic() {
+            if !segment.starts_with(|c: char| c.is_ascii_alphabetic()) {
                 return Err(InvalidInputError::Nsid {
                     value: s.to_string(),
-                    reason: format!("segment '{}' must start with a letter", segment),
+                    reason: format!(
+                        "segment '{}' at byte {} must start with a letter",
+                        segment, offset
+                    ),
                 }
                 .into());
             }
 
             // Segments can only contain letters, numbers, and hyphens
-            for c in segment.chars() {
+            for (at, c) in segment.char_indices() {
                 if !c.is_ascii_alphanumeric() && c != '-' {
                     return Err(InvalidInputError::Nsid {
                         value: s.to_string(),
-                        reason: format!("segment '{}' contains invalid character '{}'", segment, c),
+                        reason: format!(
+                            "invalid character '{}' at byte {} in segment '{}'{}",
+                            c,
+                            offset + at,
+                            segment,
+                            char_hint(c)
+                        ),
                     }
                     .into());
                 }
             }
+
+            offset += segment.len() + 1;
         }
 
         // Total length check (max 317 per spec)
@@ -135,6 +149,16 @@ impl Nsid {
     }
 }
 
+/// Explain common mistakes behind an invalid NSID character.
+fn char_hint(c: char) -> &'static str {
+    match c {
+        '/' => "; NSIDs are dot-separated (did you pass a path or AT URI?)",
+        '#' => "; '#' fragments are not part of an NSID",
+        ':' => "; did you pass a DID or URI instead of an NSID?",
+        _ => "",
+    }
+}
+
 impl fmt::Display for Nsid {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.0)
@@ -201,4 +225,12 @@ mod tests {
     fn invalid_starts_with_number() {
         assert!(Nsid::new("1app.bsky.feed").is_err());
     }
+
+    #[test]
+    fn invalid_character_reports_offset() {
+        let err = Nsid::new("app.bsky.feed/post").unwrap_err();
+        let msg = err.to_string();
+        assert!(msg.contains("'/' at byte 13"));
+        assert!(msg.contains("dot-separated"));
+    }
 }
@@ -4,10 +4,13 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::path::PathBuf;
 use std::str::FromStr;
-use url::Url;
+use url::{Position, Url};
 
 use crate::error::{Error, InvalidInputError};
 
+/// Scheme of a local PDS stored in SQLite.
+const FILE_SQLITE: &str = "file+sqlite";
+
 /// A validated PDS (Personal Data Server) URL.
 ///
 /// This type supports both network PDS URLs (HTTPS/HTTP) and local filesystem
@@ -18,10 +21,22 @@ use crate::error::{Error, InvalidInputError};
 /// Network URLs must use HTTPS (or HTTP for localhost) and are used to
 /// connect to remote PDS instances.
 ///
+/// A network URL may include a non-standard port and a path prefix for PDSes
+/// hosted below the root (e.g. `https://example.com:8443/pds`). XRPC URLs are
+/// built beneath that prefix.
+///
 /// # File URLs
 ///
 /// File URLs (`file:///path/to/pds`) enable local-only development and testing
 /// without running a network PDS. Records are stored on the filesystem.
+/// `file+sqlite:///path/to/pds` is the same PDS with its records, accounts
+/// and firehose kept in a SQLite database under that directory.
+///
+/// # Unix socket URLs
+///
+/// `unix:///run/muat.sock` addresses a PDS speaking HTTP over a Unix domain
+/// socket on the same machine. The socket path is not part of the request
+/// URL; XRPC requests are addressed to `http://localhost`.
 ///
 /// # Example
 ///
@@ -55,26 +70,48 @@ impl PdsUrl {
 
         Self::validate(&url, s)?;
 
-        // Normalize: remove trailing slash
-        let normalized = if url.path() == "/" {
-            let mut u = url.clone();
-            u.set_path("");
-            u
-        } else {
-            url
-        };
+        // Normalize: remove trailing slashes, keeping any path prefix
+        let mut normalized = url;
+        let keeps_path = matches!(normalized.scheme(), "file" | FILE_SQLITE | "unix");
+        if !keeps_path && normalized.path().ends_with('/') {
+            let path = normalized.path().trim_end_matches('/').to_string();
+            normalized.set_path(&path);
+        }
 
         Ok(Self(normalized))
     }
 
     /// Returns the XRPC endpoint URL for a given method.
+    ///
+    /// Any path prefix on the PDS URL is preserved, so
+    /// `https://example.com/pds` yields `https://example.com/pds/xrpc/<method>`.
     pub fn xrpc_url(&self, method: &str) -> String {
+        // The socket path is carried by the transport, not the request URL
+        if self.is_unix_socket() {
+            return format!("http://localhost/xrpc/{}", method);
+        }
+
         // The URL crate always adds a trailing slash to root paths,
         // so we need to handle that when constructing the XRPC URL
-        let base = self.0.as_str().trim_end_matches('/');
+        let base = self.0[..Position::AfterPath].trim_end_matches('/');
         format!("{}/xrpc/{}", base, method)
     }
 
+    /// Returns the path prefix the PDS is served under, or `""` at the root.
+    ///
+    /// For `https://example.com/pds`, returns `/pds`.
+    pub fn path_prefix(&self) -> &str {
+        if self.is_local() || self.is_unix_socket() {
+            return "";
+        }
+        self.0.path().trim_end_matches('/')
+    }
+
+    /// Returns the port, falling back to the scheme's default.
+    pub fn port(&self) -> Option<u16> {
+        self.0.port_or_known_default()
+    }
+
     /// Returns the base URL as a string.
     pub fn as_str(&self) -> &str {
         self.0.as_str()
@@ -95,9 +132,29 @@ impl PdsUrl {
         self.0.scheme()
     }
 
-    /// Returns true if this is a local filesystem PDS (file:// URL).
+    /// Returns true if this is a local filesystem PDS (`file://` or
+    /// `file+sqlite://` URL).
     pub fn is_local(&self) -> bool {
-        self.0.scheme() == "file"
+        matches!(self.0.scheme(), "file" | FILE_SQLITE)
+    }
+
+    /// Returns true if this is a local PDS stored in SQLite (`file+sqlite://` URL).
+    pub fn is_sqlite(&self) -> bool {
+        self.0.scheme() == FILE_SQLITE
+    }
+
+    /// Returns true if this is a Unix domain socket PDS (unix:// URL).
+    pub fn is_unix_socket(&self) -> bool {
+        self.0.scheme() == "unix"
+    }
+
+    /// Returns the socket path for unix:// URLs.
+    pub fn socket_path(&self) -> Option<PathBuf> {
+        if self.is_unix_socket() {
+            Some(PathBuf::from(self.0.path()))
+        } else {
+            None
+        }
     }
 
     /// Returns true if this is a network PDS (http:// or https:// URL).
@@ -106,13 +163,30 @@ impl PdsUrl {
         scheme == "http" || scheme == "https"
     }
 
-    /// Returns the filesystem path for file:// URLs.
+    /// Returns the filesystem path for file:// and file+sqlite:// URLs.
     ///
-    /// Returns `None` for non-file URLs.
+    /// Returns `None` for non-file URLs, and on targets without a filesystem
+    /// (such as `wasm32-unknown-unknown`).
     pub fn to_file_path(&self) -> Option<PathBuf> {
-        if self.is_local() {
+        if !self.is_local() {
+            return None;
+        }
+
+        #[cfg(any(unix, windows, target_os = "redox", target_os = "wasi"))]
+        {
+            if self.is_sqlite() {
+                // Only `file` URLs convert to paths; the rest is the same.
+                let rest = &self.0.as_str()[FILE_SQLITE.len()..];
+                return Url::parse(&format!("file{}", rest))
+                    .ok()?
+                    .to_file_path()
+                    .ok();
+            }
             self.0.to_file_path().ok()
-        } else {
+        }
+
+        #[cfg(not(any(unix, windows, target_os = "redox", target_os = "wasi")))]
+        {
             None
         }
     }
@@ -129,8 +203,8 @@ impl PdsUrl {
 
         let scheme = url.scheme();
 
-        // Handle file:// URLs
-        if scheme == "file" {
+        // Handle file:// and file+sqlite:// URLs
+        if scheme == "file" || scheme == FILE_SQLITE {
             // file:// URLs don't need a host, just a path
             if url.path().is_empty() {
                 return Err(InvalidInputError::PdsUrl {
@@ -142,6 +216,26 @@ impl PdsUrl {
             return Ok(());
         }
 
+        // Handle unix:// URLs: an absolute socket path, nothing else
+        if scheme == "unix" {
+            if url.host_str().is_some_and(|h| !h.is_empty()) {
+                return Err(InvalidInputError::PdsUrl {
+                    value: original.to_string(),
+                    reason: "unix:// URL must not have a host (use unix:///path/to.sock)"
+                        .to_string(),
+                }
+                .into());
+            }
+            if url.path().len() <= 1 || url.path().ends_with('/') {
+                return Err(InvalidInputError::PdsUrl {
+                    value: original.to_string(),
+                    reason: "unix:// URL must have a socket path".to_string(),
+                }
+                .into());
+            }
+            return Ok(());
+        }
+
         // Must be HTTPS (or HTTP for localhost)
         let is_localhost = url
             .host_str()
@@ -164,6 +258,24 @@ impl PdsUrl {
             .into());
         }
 
+        // XRPC URLs are built by appending to the path, so anything after it
+        // would end up in the wrong place
+        if url.query().is_some() || url.fragment().is_some() {
+            return Err(InvalidInputError::PdsUrl {
+                value: original.to_string(),
+                reason: "must not have a query string or fragment".to_string(),
+            }
+            .into());
+        }
+
+        if !url.username().is_empty() || url.password().is_some() {
+            return Err(InvalidInputError::PdsUrl {
+                value: original.to_string(),
+                reason: "must not contain credentials".to_string(),
+            }
+            .into());
+        }
+
         Ok(())
     }
 }
@@ -242,6 +354,61 @@ mod tests {
         );
     }
 
+    #[test]
+    fn xrpc_url_keeps_path_prefix() {
+        let pds = PdsUrl::new("https://example.com/pds/").unwrap();
+        assert_eq!(pds.path_prefix(), "/pds");
+        assert_eq!(
+            pds.xrpc_url("com.atproto.server.describeServer"),
+            "https://example.com/pds/xrpc/com.atproto.server.describeServer"
+        );
+    }
+
+    #[test]
+    fn custom_port_with_prefix() {
+        let pds = PdsUrl::new("https://example.com:8443/a/b").unwrap();
+        assert_eq!(pds.port(), Some(8443));
+        assert_eq!(
+            pds.xrpc_url("com.atproto.repo.getRecord"),
+            "https://example.com:8443/a/b/xrpc/com.atproto.repo.getRecord"
+        );
+    }
+
+    #[test]
+    fn default_port_and_empty_prefix() {
+        let pds = PdsUrl::new("https://bsky.social").unwrap();
+        assert_eq!(pds.port(), Some(443));
+        assert_eq!(pds.path_prefix(), "");
+    }
+
+    #[test]
+    fn invalid_query_or_fragment() {
+        assert!(PdsUrl::new("https://example.com/pds?x=1").is_err());
+        assert!(PdsUrl::new("https://example.com/pds#top").is_err());
+    }
+
+    #[test]
+    fn valid_unix_socket_url() {
+        let pds = PdsUrl::new("unix:///run/muat.sock").unwrap();
+        assert!(pds.is_unix_socket());
+        assert!(!pds.is_network());
+        assert!(!pds.is_local());
+        assert_eq!(
+            pds.socket_path(),
+            Some(std::path::PathBuf::from("/run/muat.sock"))
+        );
+        assert_eq!(
+            pds.xrpc_url("com.atproto.server.describeServer"),
+            "http://localhost/xrpc/com.atproto.server.describeServer"
+        );
+    }
+
+    #[test]
+    fn invalid_unix_socket_url() {
+        assert!(PdsUrl::new("unix://host/run/muat.sock").is_err());
+        assert!(PdsUrl::new("unix:///").is_err());
+    }
+
     #[test]
     fn invalid_http_non_localhost() {
         assert!(PdsUrl::new("http://bsky.social").is_err());
@@ -277,6 +444,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn sqlite_file_url() {
+        let pds = PdsUrl::new("file+sqlite:///tmp/test-pds").unwrap();
+        assert!(pds.is_local());
+        assert!(pds.is_sqlite());
+        assert!(!PdsUrl::new("file:///tmp/test-pds").unwrap().is_sqlite());
+
+        #[cfg(unix)]
+        assert_eq!(
+            pds.to_file_path().unwrap(),
+            std::path::PathBuf::from("/tmp/test-pds")
+        );
+    }
+
     #[test]
     fn network_url_not_local() {
         let pds = PdsUrl::new("https://bsky.social").unwrap();
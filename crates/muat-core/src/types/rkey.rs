@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::str::FromStr;
 
+use super::Tid;
 use crate::error::{Error, InvalidInputError};
 
 /// A validated AT Protocol Record Key (rkey).
@@ -35,6 +36,14 @@ impl Rkey {
         Ok(Self(s))
     }
 
+    /// A new TID record key for the current time.
+    ///
+    /// See [`Tid::now`]; backends that create many records use a
+    /// [`TidGenerator`](super::TidGenerator) instead, so keys never repeat.
+    pub fn generate_tid() -> Self {
+        Self::from(Tid::now())
+    }
+
     /// Returns the rkey string.
     pub fn as_str(&self) -> &str {
         &self.0
@@ -70,11 +79,16 @@ impl Rkey {
             .into());
         }
 
-        for c in s.chars() {
+        for (at, c) in s.char_indices() {
             if !c.is_ascii_alphanumeric() && c != '.' && c != '-' && c != '_' && c != '~' {
+                let hint = if c == '/' {
+                    "; pass only the final segment of an AT URI"
+                } else {
+                    ""
+                };
                 return Err(InvalidInputError::Rkey {
                     value: s.to_string(),
-                    reason: format!("contains invalid character '{}'", c),
+                    reason: format!("invalid character '{}' at byte {}{}", c, at, hint),
                 }
                 .into());
             }
@@ -106,6 +120,13 @@ impl TryFrom<String> for Rkey {
     }
 }
 
+impl From<Tid> for Rkey {
+    fn from(tid: Tid) -> Self {
+        // Every TID is a valid record key.
+        Self(tid.into())
+    }
+}
+
 impl From<Rkey> for String {
     fn from(rkey: Rkey) -> Self {
         rkey.0
@@ -128,6 +149,12 @@ mod tests {
         assert_eq!(rkey.as_str(), "3jui7kd54zh2y");
     }
 
+    #[test]
+    fn generated_rkeys_are_tids() {
+        let rkey = Rkey::generate_tid();
+        assert!(Tid::new(rkey.as_str()).is_ok());
+    }
+
     #[test]
     fn valid_self_rkey() {
         let rkey = Rkey::new("self").unwrap();
@@ -153,4 +180,10 @@ mod tests {
     fn invalid_character() {
         assert!(Rkey::new("test/key").is_err());
     }
+
+    #[test]
+    fn invalid_character_reports_offset() {
+        let err = Rkey::new("test/key").unwrap_err();
+        assert!(err.to_string().contains("'/' at byte 4"));
+    }
 }
@@ -0,0 +1,261 @@
+//! Timestamp identifier (TID) type.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::error::{Error, InvalidInputError};
+
+/// Sortable base32 alphabet: TIDs compare as strings in timestamp order.
+const ALPHABET: &[u8; 32] = b"234567abcdefghijklmnopqrstuvwxyz";
+
+/// Length of every TID.
+const LEN: usize = 13;
+
+/// Bits below the timestamp holding the clock identifier.
+const CLOCK_BITS: u32 = 10;
+
+/// A validated timestamp identifier.
+///
+/// TIDs are the usual record keys: 13 characters of sortable base32 encoding
+/// a microsecond timestamp and a 10-bit clock identifier, so they sort as
+/// strings in creation order.
+///
+/// # Example
+///
+/// ```
+/// use muat_core::Tid;
+///
+/// let tid = Tid::new("3jui7kd54zh2y").unwrap();
+/// assert_eq!(tid.timestamp().to_rfc3339(), "2023-04-29T03:42:21.953005+00:00");
+/// assert_eq!(Tid::from_timestamp(tid.timestamp(), tid.clock_id()), tid);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Tid(String);
+
+impl Tid {
+    /// Create a TID from a string, validating the format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the string is not a valid TID.
+    pub fn new(s: impl Into<String>) -> Result<Self, Error> {
+        let s = s.into();
+        Self::validate(&s)?;
+        Ok(Self(s))
+    }
+
+    /// The TID for a timestamp and clock identifier.
+    ///
+    /// Times before the Unix epoch map to the epoch, and only the low 10
+    /// bits of `clock_id` are used.
+    pub fn from_timestamp(time: DateTime<Utc>, clock_id: u16) -> Self {
+        let micros = u64::try_from(time.timestamp_micros()).unwrap_or(0);
+        let clock = u64::from(clock_id) & ((1 << CLOCK_BITS) - 1);
+        Self::from_integer(((micros << CLOCK_BITS) | clock) & (u64::MAX >> 1))
+    }
+
+    /// A TID for the current time with a random clock identifier.
+    ///
+    /// Two calls in the same microsecond can collide; use a [`TidGenerator`]
+    /// when keys must be unique.
+    pub fn now() -> Self {
+        Self::from_timestamp(Utc::now(), random_clock_id())
+    }
+
+    /// Returns the TID string.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The time this TID encodes.
+    pub fn timestamp(&self) -> DateTime<Utc> {
+        let micros = (self.to_integer() >> CLOCK_BITS) as i64;
+        DateTime::from_timestamp_micros(micros).unwrap_or_default()
+    }
+
+    /// The clock identifier this TID encodes.
+    pub fn clock_id(&self) -> u16 {
+        (self.to_integer() & ((1 << CLOCK_BITS) - 1)) as u16
+    }
+
+    /// The TID immediately before this one, if any.
+    pub(crate) fn predecessor(&self) -> Option<Self> {
+        self.to_integer().checked_sub(1).map(Self::from_integer)
+    }
+
+    fn from_integer(mut value: u64) -> Self {
+        let mut chars = [ALPHABET[0]; LEN];
+        for c in chars.iter_mut().rev() {
+            *c = ALPHABET[(value & 31) as usize];
+            value >>= 5;
+        }
+        Self(chars.iter().map(|&c| c as char).collect())
+    }
+
+    fn to_integer(&self) -> u64 {
+        self.0.bytes().fold(0, |value, c| {
+            let digit = ALPHABET.iter().position(|&a| a == c).unwrap_or(0);
+            (value << 5) | digit as u64
+        })
+    }
+
+    fn validate(s: &str) -> Result<(), Error> {
+        let invalid = |reason: String| {
+            Err(InvalidInputError::Tid {
+                value: s.to_string(),
+                reason,
+            }
+            .into())
+        };
+
+        if s.len() != LEN {
+            return invalid(format!("must be {} characters", LEN));
+        }
+        if let Some((at, c)) = s
+            .char_indices()
+            .find(|(_, c)| !c.is_ascii() || !ALPHABET.contains(&(*c as u8)))
+        {
+            return invalid(format!("invalid character '{}' at byte {}", c, at));
+        }
+        // The top bit of the 64-bit value is always zero.
+        if !b"234567abcdefghij".contains(&s.as_bytes()[0]) {
+            return invalid("first character must be one of 234567abcdefghij".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// Generates strictly increasing TIDs.
+///
+/// Each generator picks a random clock identifier and never repeats a
+/// timestamp, so its TIDs are unique even when requested faster than the
+/// clock ticks. Clones share the same clock.
+#[derive(Debug, Clone)]
+pub struct TidGenerator {
+    clock_id: u16,
+    last_micros: Arc<Mutex<i64>>,
+}
+
+impl TidGenerator {
+    /// Create a generator with a random clock identifier.
+    pub fn new() -> Self {
+        Self::with_clock_id(random_clock_id())
+    }
+
+    /// Create a generator with a fixed clock identifier (low 10 bits used).
+    pub fn with_clock_id(clock_id: u16) -> Self {
+        Self {
+            clock_id,
+            last_micros: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    /// The next TID, later than any this generator returned before.
+    pub fn next_tid(&self) -> Tid {
+        let micros = {
+            // The last timestamp stays meaningful even if a holder panicked.
+            let mut last = self
+                .last_micros
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            *last = Utc::now().timestamp_micros().max(*last + 1);
+            *last
+        };
+        let time = DateTime::from_timestamp_micros(micros).unwrap_or_default();
+        Tid::from_timestamp(time, self.clock_id)
+    }
+}
+
+impl Default for TidGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A random 10-bit clock identifier, from the standard library's randomly
+/// seeded hasher keys.
+fn random_clock_id() -> u16 {
+    (RandomState::new().hash_one(0u8) & ((1 << CLOCK_BITS) - 1)) as u16
+}
+
+impl fmt::Display for Tid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl FromStr for Tid {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<String> for Tid {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::new(s)
+    }
+}
+
+impl From<Tid> for String {
+    fn from(tid: Tid) -> Self {
+        tid.0
+    }
+}
+
+impl AsRef<str> for Tid {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_timestamp_and_clock() {
+        let time: DateTime<Utc> = "2024-01-01T00:00:00.123456Z".parse().unwrap();
+        let tid = Tid::from_timestamp(time, 7);
+        assert_eq!(tid.as_str().len(), 13);
+        assert_eq!(tid.timestamp(), time);
+        assert_eq!(tid.clock_id(), 7);
+        assert_eq!(Tid::new(tid.as_str()).unwrap(), tid);
+    }
+
+    #[test]
+    fn sorts_in_time_order() {
+        let earlier = Tid::from_timestamp("2024-01-01T00:00:00Z".parse().unwrap(), 1023);
+        let later = Tid::from_timestamp("2024-01-01T00:00:00.000001Z".parse().unwrap(), 0);
+        assert!(earlier.as_str() < later.as_str());
+    }
+
+    #[test]
+    fn generator_is_strictly_increasing() {
+        let generator = TidGenerator::with_clock_id(5);
+        let tids: Vec<Tid> = (0..100).map(|_| generator.next_tid()).collect();
+        assert!(tids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(tids.iter().all(|tid| tid.clock_id() == 5));
+
+        let now = Tid::now();
+        assert!(Tid::new(now.as_str()).is_ok());
+    }
+
+    #[test]
+    fn rejects_non_tids() {
+        assert!(Tid::new("self").is_err());
+        assert!(Tid::new("3jui7kd54zh2").is_err());
+        assert!(Tid::new("3jui7kd54zh21").is_err());
+        assert!(Tid::new("zjui7kd54zh2y").is_err());
+    }
+}
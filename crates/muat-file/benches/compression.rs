@@ -0,0 +1,107 @@
+//! Space/time comparison of plain and zstd-compressed record storage.
+//!
+//! Run with `cargo bench -p muat-file --features zstd`.
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use muat_core::traits::{Pds, Session};
+use muat_core::{Credentials, Nsid, PdsUrl, RecordValue};
+use muat_file::{Compression, FilePds};
+use serde_json::json;
+
+const RECORDS: usize = 500;
+const COLLECTION: &str = "org.example.bench";
+
+struct Report {
+    label: String,
+    write: Duration,
+    read: Duration,
+    bytes: u64,
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+async fn run(label: &str, compression: Compression) -> Report {
+    let temp = tempfile::tempdir().expect("temp dir");
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).expect("pds url");
+    let pds = FilePds::new(temp.path(), pds_url).with_compression(compression);
+
+    pds.create_account("bench.local", Some("password"), None, None)
+        .await
+        .expect("create account");
+    let session = pds
+        .login(Credentials::new("bench.local", "password"))
+        .await
+        .expect("login");
+
+    let collection = Nsid::new(COLLECTION).expect("nsid");
+    let mut uris = Vec::with_capacity(RECORDS);
+
+    let start = Instant::now();
+    for i in 0..RECORDS {
+        let value = RecordValue::with_type(
+            COLLECTION,
+            json!({
+                "text": format!("Benchmark record number {i} with some repeated body text."),
+                "tags": ["bench", "compression", "muat"],
+                "createdAt": "2024-01-01T00:00:00.000Z",
+            }),
+        )
+        .expect("record");
+        uris.push(
+            session
+                .create_record(&collection, &value)
+                .await
+                .expect("create"),
+        );
+    }
+    let write = start.elapsed();
+
+    let start = Instant::now();
+    for uri in &uris {
+        session.get_record(uri).await.expect("get");
+    }
+    let read = start.elapsed();
+
+    Report {
+        label: label.to_string(),
+        write,
+        read,
+        bytes: dir_size(&temp.path().join("pds").join("repos")),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let reports = vec![
+        run("plain", Compression::None).await,
+        run("zstd-1", Compression::Zstd(1)).await,
+        run("zstd-3", Compression::Zstd(3)).await,
+        run("zstd-9", Compression::Zstd(9)).await,
+    ];
+
+    println!("{RECORDS} records");
+    println!(
+        "{:<8} {:>12} {:>12} {:>12}",
+        "format", "write", "read", "bytes"
+    );
+    for r in reports {
+        println!(
+            "{:<8} {:>12.2?} {:>12.2?} {:>12}",
+            r.label, r.write, r.read, r.bytes
+        );
+    }
+}
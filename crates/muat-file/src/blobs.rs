@@ -0,0 +1,151 @@
+//! Filesystem blob store.
+
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use tracing::{debug, instrument};
+
+use muat_core::error::{Error, InvalidInputError, ProtocolError};
+use muat_core::repo::{BlobRef, ByteRange, ListBlobsOutput};
+use muat_core::traits::BlobStore;
+use muat_core::{Cid, Result};
+
+use crate::store::map_io;
+
+/// Default page size for [`BlobStore::list_blobs`].
+const DEFAULT_LIST_LIMIT: usize = 500;
+
+/// Content-addressed blob store in a directory.
+///
+/// Each blob is a file named by its CID (CIDv1, raw codec, sha-256), so
+/// identical content is stored once.
+#[derive(Debug, Clone)]
+pub struct FileBlobStore {
+    root: PathBuf,
+}
+
+impl FileBlobStore {
+    /// Create a blob store rooted at `root`. The directory is created on the
+    /// first write.
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    fn blob_path(&self, cid: &str) -> Result<PathBuf> {
+        // CIDs are base32 lowercase; anything else could escape the root.
+        let valid = cid.len() > 1
+            && cid.starts_with('b')
+            && cid
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || ('2'..='7').contains(&c));
+        if !valid {
+            return Err(Error::InvalidInput(InvalidInputError::Other {
+                message: format!("invalid blob CID '{}'", cid),
+            }));
+        }
+        Ok(self.root.join(cid))
+    }
+
+    fn existing_blob_path(&self, cid: &str) -> Result<PathBuf> {
+        let path = self.blob_path(cid)?;
+        if !path.exists() {
+            return Err(Error::Protocol(ProtocolError::new(
+                404,
+                Some("BlobNotFound".to_string()),
+                Some(format!("Blob {} not found", cid)),
+            )));
+        }
+        Ok(path)
+    }
+}
+
+#[async_trait]
+impl BlobStore for FileBlobStore {
+    #[instrument(skip(self, data), fields(size = data.len()))]
+    async fn put_blob(&self, data: Vec<u8>, mime_type: &str) -> Result<BlobRef> {
+        let cid = Cid::v1(Cid::RAW, Cid::SHA2_256, &Sha256::digest(&data));
+        let path = self.blob_path(cid.as_str())?;
+
+        if !path.exists() {
+            fs::create_dir_all(&self.root).map_err(map_io)?;
+            let temp_path = path.with_extension("tmp");
+            fs::write(&temp_path, &data).map_err(map_io)?;
+            fs::rename(&temp_path, &path).map_err(map_io)?;
+            debug!(%cid, "Stored blob");
+        }
+
+        BlobRef::new(cid, mime_type, data.len() as u64)
+    }
+
+    #[instrument(skip(self))]
+    async fn get_blob(&self, cid: &str) -> Result<Vec<u8>> {
+        let path = self.existing_blob_path(cid)?;
+        fs::read(&path).map_err(map_io)
+    }
+
+    /// Reads only the requested bytes.
+    #[instrument(skip(self))]
+    async fn get_blob_range(&self, cid: &str, range: ByteRange) -> Result<Vec<u8>> {
+        let path = self.existing_blob_path(cid)?;
+        let mut file = File::open(&path).map_err(map_io)?;
+        let size = file.metadata().map_err(map_io)?.len();
+        let (start, end) = range.bounds(size)?;
+
+        file.seek(SeekFrom::Start(start)).map_err(map_io)?;
+        let mut data = Vec::with_capacity((end - start) as usize);
+        file.take(end - start)
+            .read_to_end(&mut data)
+            .map_err(map_io)?;
+        Ok(data)
+    }
+
+    #[instrument(skip(self))]
+    async fn delete_blob(&self, cid: &str) -> Result<()> {
+        let path = self.blob_path(cid)?;
+        if path.exists() {
+            fs::remove_file(&path).map_err(map_io)?;
+            debug!(%cid, "Deleted blob");
+        }
+        Ok(())
+    }
+
+    #[instrument(skip(self))]
+    async fn list_blobs(
+        &self,
+        limit: Option<u32>,
+        cursor: Option<&str>,
+    ) -> Result<ListBlobsOutput> {
+        let limit = limit.map_or(DEFAULT_LIST_LIMIT, |l| l as usize);
+
+        let mut cids: Vec<String> = if self.root.exists() {
+            fs::read_dir(&self.root)
+                .map_err(map_io)?
+                .filter_map(|e| e.ok())
+                .filter_map(|e| e.file_name().into_string().ok())
+                .filter(|name| self.blob_path(name).is_ok())
+                .collect()
+        } else {
+            Vec::new()
+        };
+        cids.sort();
+
+        let cids: Vec<String> = cids
+            .into_iter()
+            .filter(|cid| cursor.is_none_or(|c| cid.as_str() > c))
+            .take(limit)
+            .collect();
+
+        let cursor = if cids.len() == limit {
+            cids.last().cloned()
+        } else {
+            None
+        };
+
+        Ok(ListBlobsOutput { cids, cursor })
+    }
+}
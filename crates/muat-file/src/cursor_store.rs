@@ -0,0 +1,101 @@
+//! File-backed [`CursorStore`].
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+
+use muat_core::Result;
+use muat_core::error::{Error, InvalidInputError};
+use muat_core::traits::CursorStore;
+
+use crate::store::map_io;
+
+/// A [`CursorStore`] that keeps cursors in a JSON file mapping each host
+/// to its cursor.
+///
+/// Each save rewrites the whole file atomically. Processes sharing a file
+/// do not lock it, so give each consumer its own.
+#[derive(Debug, Clone)]
+pub struct FileCursorStore {
+    path: PathBuf,
+}
+
+impl FileCursorStore {
+    /// Keep cursors in the file at `path`, created on first save.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Returns the path of the cursors file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, i64>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(BTreeMap::new());
+            }
+            Err(e) => return Err(map_io(e)),
+        };
+
+        serde_json::from_str(&content).map_err(|e| {
+            Error::InvalidInput(InvalidInputError::Other {
+                message: format!("invalid cursor file {}: {}", self.path.display(), e),
+            })
+        })
+    }
+
+    fn write(&self, cursors: &BTreeMap<String, i64>) -> Result<()> {
+        let json = serde_json::to_vec_pretty(cursors).map_err(|e| {
+            Error::InvalidInput(InvalidInputError::Other {
+                message: e.to_string(),
+            })
+        })?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(map_io)?;
+        }
+
+        // Replace the file in one step, so it is never half-written.
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, &json).map_err(map_io)?;
+        fs::rename(&tmp_path, &self.path).map_err(map_io)
+    }
+}
+
+#[async_trait]
+impl CursorStore for FileCursorStore {
+    async fn load(&self, host: &str) -> Result<Option<i64>> {
+        Ok(self.read()?.get(host).copied())
+    }
+
+    async fn save(&self, host: &str, cursor: i64) -> Result<()> {
+        let mut cursors = self.read()?;
+        cursors.insert(host.to_string(), cursor);
+        self.write(&cursors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn file_store_keeps_a_cursor_per_host() {
+        let temp = tempfile::tempdir().unwrap();
+        let store = FileCursorStore::new(temp.path().join("app").join("cursors.json"));
+        assert!(store.load("bsky.network").await.unwrap().is_none());
+
+        store.save("bsky.network", 10).await.unwrap();
+        store.save("relay.example.com", 7).await.unwrap();
+        store.save("bsky.network", 12).await.unwrap();
+
+        let reopened = FileCursorStore::new(store.path());
+        assert_eq!(reopened.load("bsky.network").await.unwrap(), Some(12));
+        assert_eq!(reopened.load("relay.example.com").await.unwrap(), Some(7));
+    }
+}
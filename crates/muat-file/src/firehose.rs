@@ -4,28 +4,50 @@ use std::fs::File;
 use std::io::{BufRead, BufReader, Seek, SeekFrom};
 use std::path::PathBuf;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 
 use futures_util::Stream;
 use notify::{RecursiveMode, Watcher};
-use tokio::sync::mpsc;
+use tokio::sync::Notify;
 
 use muat_core::Result;
 use muat_core::error::{Error, InvalidInputError};
-use muat_core::repo::{CommitEvent, CommitOperation, RepoEvent};
+use muat_core::repo::{
+    CommitEvent, CommitOperation, EventStats, FirehoseBuffer, FirehoseStats, HandleEvent,
+    IdentityEvent, RepoEvent,
+};
+use muat_core::types::Tid;
 
-use crate::store::{FileStore, FirehoseLogEvent, FirehoseLogOp};
+#[cfg(feature = "sqlite")]
+use crate::sqlite::{DATABASE_FILE, FIREHOSE_BATCH, SqliteStore};
+use crate::storage::Storage;
+use crate::store::{FirehoseLogEvent, FirehoseLogOp};
+
+/// Reads the events appended to a store's firehose log since the last read.
+type LogReader = Box<dyn FnMut(&FirehoseStats) -> Vec<RepoEvent> + Send>;
 
 /// Firehose stream for file-backed PDS.
+///
+/// Counts what it delivers; see [`stats`](Self::stats).
 pub struct FileFirehose {
     inner: Pin<Box<dyn Stream<Item = Result<RepoEvent>> + Send>>,
+    stats: FirehoseStats,
 }
 
 impl FileFirehose {
-    pub(crate) fn from_store(store: FileStore) -> Result<Self> {
+    /// Tail the store's firehose log.
+    ///
+    /// With a cursor, events with a sequence number greater than the cursor
+    /// are replayed from the start of the log before new events; without
+    /// one, only new events are delivered.
+    pub(crate) fn from_store(
+        store: Storage,
+        cursor: Option<i64>,
+        buffer: FirehoseBuffer,
+    ) -> Result<Self> {
         let pds_dir = store.root().join("pds");
-        let firehose_path = store.firehose_path();
 
         std::fs::create_dir_all(&pds_dir).map_err(|e| {
             Error::InvalidInput(InvalidInputError::Other {
@@ -33,20 +55,51 @@ impl FileFirehose {
             })
         })?;
 
-        let (tx, mut rx) = mpsc::channel::<Result<RepoEvent>>(100);
+        let stats = FirehoseStats::new();
+        let (tx, rx) = buffer.channel(stats.clone());
 
-        let initial_pos = if firehose_path.exists() {
-            std::fs::metadata(&firehose_path)
-                .map(|m| m.len())
-                .unwrap_or(0)
-        } else {
-            0
+        // The log's file name, which is also the prefix of any files the
+        // store writes beside it.
+        let (log_name, mut read_log): (&'static str, LogReader) = match store {
+            Storage::File(store) => {
+                let firehose_path = store.firehose_path();
+                let mut position = match cursor {
+                    Some(_) => 0,
+                    None => std::fs::metadata(&firehose_path)
+                        .map(|m| m.len())
+                        .unwrap_or(0),
+                };
+                (
+                    "firehose.jsonl",
+                    Box::new(move |stats| {
+                        read_new_firehose_events(&firehose_path, &mut position, cursor, stats)
+                    }),
+                )
+            }
+            #[cfg(feature = "sqlite")]
+            Storage::Sqlite(store) => {
+                let mut after = match cursor {
+                    Some(cursor) => cursor,
+                    None => store.last_seq()?,
+                };
+                (
+                    DATABASE_FILE,
+                    Box::new(move |stats| read_new_sqlite_events(&store, &mut after, stats)),
+                )
+            }
+            #[cfg(not(feature = "sqlite"))]
+            Storage::SqliteUnavailable(_) => {
+                return Err(Error::InvalidInput(InvalidInputError::Other {
+                    message: "file+sqlite:// PDS URLs need the `sqlite` feature of muat-file"
+                        .to_string(),
+                }));
+            }
         };
 
-        let position = std::sync::Arc::new(std::sync::Mutex::new(initial_pos));
-        let position_clone = position.clone();
-        let firehose_path_clone = firehose_path.clone();
-        let tx_clone = tx.clone();
+        // The watcher only wakes the reader task; all reads happen there so
+        // events are delivered once and in log order.
+        let wake = Arc::new(Notify::new());
+        let wake_watcher = wake.clone();
 
         let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
             if let Ok(event) = res {
@@ -57,16 +110,15 @@ impl FileFirehose {
                     return;
                 }
 
-                let is_firehose = event
-                    .paths
-                    .iter()
-                    .any(|p| p.file_name().is_some_and(|n| n == "firehose.jsonl"));
+                let is_firehose = event.paths.iter().any(|p| {
+                    p.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.starts_with(log_name))
+                });
 
-                if !is_firehose {
-                    return;
+                if is_firehose {
+                    wake_watcher.notify_one();
                 }
-
-                read_new_firehose_events(&firehose_path_clone, &position_clone, &tx_clone);
             }
         })
         .map_err(|e| {
@@ -83,95 +135,210 @@ impl FileFirehose {
                 })
             })?;
 
-        let firehose_path_poll = firehose_path.clone();
+        let reader_stats = stats.clone();
+
         tokio::spawn(async move {
             let _watcher = watcher;
-            let mut interval = tokio::time::interval(Duration::from_millis(500));
 
             loop {
-                interval.tick().await;
-                read_new_firehose_events(&firehose_path_poll, &position, &tx);
-            }
-        });
+                let events = read_log(&reader_stats);
+                for event in events {
+                    if !tx.send(Ok(event)).await {
+                        return;
+                    }
+                }
 
-        let stream = async_stream::stream! {
-            while let Some(event) = rx.recv().await {
-                yield event;
+                // Poll as a fallback in case a watcher notification is missed.
+                let _ = tokio::time::timeout(Duration::from_millis(500), wake.notified()).await;
             }
-        };
+        });
 
         Ok(Self {
-            inner: Box::pin(stream),
+            inner: Box::pin(rx.into_stream()),
+            stats,
         })
     }
+
+    /// Counters for the events, operations and log bytes read so far.
+    pub fn stats(&self) -> EventStats {
+        self.stats.snapshot()
+    }
+
+    /// Zero the counters returned by [`stats`](Self::stats).
+    pub fn reset_stats(&self) {
+        self.stats.reset();
+    }
+
+    /// A handle to this stream's counters that stays valid after the stream
+    /// is moved, e.g. into a filter or another task.
+    pub fn stats_handle(&self) -> FirehoseStats {
+        self.stats.clone()
+    }
+
+    /// Events read from the log but not yet consumed.
+    pub fn lag(&self) -> u64 {
+        self.stats.lag()
+    }
+
+    /// Events discarded because the consumer fell behind.
+    pub fn dropped_events(&self) -> u64 {
+        self.stats.dropped_events()
+    }
 }
 
 impl Stream for FileFirehose {
     type Item = Result<RepoEvent>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        self.inner.as_mut().poll_next(cx)
+        let poll = self.inner.as_mut().poll_next(cx);
+        if let Poll::Ready(Some(item)) = &poll {
+            self.stats.record(item);
+        }
+        poll
     }
 }
 
+/// Read events appended since `position`, skipping any at or before `cursor`.
 fn read_new_firehose_events(
     firehose_path: &PathBuf,
-    position: &std::sync::Arc<std::sync::Mutex<u64>>,
-    tx: &mpsc::Sender<Result<RepoEvent>>,
-) {
-    if let Ok(mut file) = File::open(firehose_path) {
-        let mut pos = position.lock().unwrap();
-        if file.seek(SeekFrom::Start(*pos)).is_ok() {
-            let reader = BufReader::new(&file);
-            for line in reader.lines().map_while(|line| line.ok()) {
-                if line.trim().is_empty() {
-                    continue;
-                }
-                if let Ok(event) = serde_json::from_str::<FirehoseLogEvent>(&line) {
-                    let repo_event = firehose_to_repo_event(&event);
-                    let _ = tx.blocking_send(Ok(repo_event));
+    position: &mut u64,
+    cursor: Option<i64>,
+    stats: &FirehoseStats,
+) -> Vec<RepoEvent> {
+    let mut events = Vec::new();
+
+    if let Ok(mut file) = File::open(firehose_path)
+        && file.seek(SeekFrom::Start(*position)).is_ok()
+    {
+        let mut reader = BufReader::new(&mut file);
+        let mut line = String::new();
+        // Only consume complete lines; a partial write is picked up next time.
+        while let Ok(n) = reader.read_line(&mut line) {
+            if n == 0 || !line.ends_with('\n') {
+                break;
+            }
+            *position += n as u64;
+            stats.add_bytes(n);
+
+            if let Ok(event) = serde_json::from_str::<FirehoseLogEvent>(line.trim()) {
+                let repo_event = firehose_to_repo_event(&event);
+                let replayed = match (event_seq(&repo_event), cursor) {
+                    (Some(seq), Some(cursor)) => seq <= cursor,
+                    _ => false,
+                };
+                if !replayed {
+                    events.push(repo_event);
                 }
             }
-            if let Ok(new_pos) = file.stream_position() {
-                *pos = new_pos;
+            line.clear();
+        }
+    }
+
+    events
+}
+
+/// Read the events logged after sequence number `after`, advancing it past
+/// them. Queries in batches until caught up.
+#[cfg(feature = "sqlite")]
+fn read_new_sqlite_events(
+    store: &SqliteStore,
+    after: &mut i64,
+    stats: &FirehoseStats,
+) -> Vec<RepoEvent> {
+    let mut events = Vec::new();
+
+    loop {
+        let rows = match store.firehose_since(*after) {
+            Ok(rows) => rows,
+            Err(e) => {
+                tracing::warn!(error = %e, "Failed to read firehose events");
+                break;
             }
+        };
+        let full = rows.len() == FIREHOSE_BATCH;
+
+        for (seq, event, size) in rows {
+            *after = seq;
+            stats.add_bytes(size);
+            if let Some(event) = event {
+                events.push(firehose_to_repo_event(&event));
+            }
+        }
+
+        if !full {
+            break;
         }
     }
+
+    events
+}
+
+fn event_seq(event: &RepoEvent) -> Option<i64> {
+    match event {
+        RepoEvent::Commit(commit) => Some(commit.seq),
+        RepoEvent::Identity(identity) => Some(identity.seq),
+        RepoEvent::Handle(handle) => Some(handle.seq),
+        RepoEvent::Info(_) | RepoEvent::Unknown { .. } => None,
+    }
 }
 
 fn firehose_to_repo_event(event: &FirehoseLogEvent) -> RepoEvent {
-    let uri = &event.uri;
-
-    let (repo, path) = if let Some(rest) = uri.strip_prefix("at://") {
-        if let Some(slash_pos) = rest.find('/') {
-            let repo = rest[..slash_pos].to_string();
-            let path = rest[slash_pos + 1..].to_string();
-            (repo, path)
-        } else {
-            ("unknown".to_string(), "unknown".to_string())
-        }
-    } else {
-        ("unknown".to_string(), "unknown".to_string())
+    // Record events carry `at://<did>/<collection>/<rkey>`, account events
+    // just `at://<did>`.
+    let (repo, path) = match event.uri.strip_prefix("at://") {
+        Some(rest) => match rest.split_once('/') {
+            Some((repo, path)) => (repo.to_string(), path.to_string()),
+            None => (rest.to_string(), "unknown".to_string()),
+        },
+        None => ("unknown".to_string(), "unknown".to_string()),
     };
 
+    let time = chrono::DateTime::parse_from_rfc3339(&event.time)
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+        .unwrap_or_default();
+    let seq = event.seq();
+
     let action = match event.op {
         FirehoseLogOp::Create => "create",
+        FirehoseLogOp::Update => "update",
         FirehoseLogOp::Delete => "delete",
+        // Deleted accounts have no dedicated event type; an identity event
+        // tells consumers to re-resolve the DID.
+        FirehoseLogOp::AccountCreate | FirehoseLogOp::AccountDelete => {
+            return RepoEvent::Identity(IdentityEvent {
+                did: repo,
+                seq,
+                time,
+            });
+        }
+        FirehoseLogOp::HandleChange => {
+            return RepoEvent::Handle(HandleEvent {
+                did: repo,
+                handle: event.handle.clone().unwrap_or_default(),
+                seq,
+                time,
+            });
+        }
     };
 
-    let seq = chrono::DateTime::parse_from_rfc3339(&event.time)
-        .map(|dt| dt.timestamp_micros())
-        .unwrap_or(0);
+    // Logs written before revisions were kept get one from the event time.
+    let rev = event
+        .rev
+        .clone()
+        .unwrap_or_else(|| Tid::from_timestamp(time, 0).into());
 
     RepoEvent::Commit(CommitEvent {
         repo,
-        rev: format!("rev-{}", seq),
+        rev,
+        since: event.since.clone(),
         seq,
-        time: event.time.clone(),
+        time,
         ops: vec![CommitOperation {
             path,
             action: action.to_string(),
             cid: None,
         }],
+        blocks: Vec::new(),
+        blobs: Vec::new(),
     })
 }
@@ -1,10 +1,30 @@
 //! muat-file - Filesystem-backed PDS implementation.
 
+mod blobs;
+mod cursor_store;
 mod firehose;
+mod mail;
+mod mirror;
+mod password;
 mod pds;
+mod recording;
 mod session;
+mod session_store;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod storage;
 mod store;
+mod validation;
 
+pub use blobs::FileBlobStore;
+pub use cursor_store::FileCursorStore;
 pub use firehose::FileFirehose;
+pub use mail::SentMail;
+pub use mirror::{Mirror, MirrorReport};
+pub use password::{PasswordAlgorithm, PasswordHashing};
 pub use pds::FilePds;
+pub use recording::{FirehoseRecorder, FirehoseReplayer};
 pub use session::FileSession;
+pub use session_store::FileSessionStore;
+pub use store::{CompactionStats, Compression, RecordVersion};
+pub use validation::RecordValidator;
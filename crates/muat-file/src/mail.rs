@@ -0,0 +1,160 @@
+//! Email a file-backed PDS would send, kept in an outbox directory.
+//!
+//! There is no mail server: each message is written to
+//! `pds/outbox/<address>.json`, replacing the previous one to that address,
+//! so tests and local tools can read the tokens back.
+
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use muat_core::Result;
+use muat_core::error::{Error, InvalidInputError, ProtocolError};
+use muat_core::types::base32_lower;
+
+use crate::store::map_io;
+
+/// How long an emailed token stays valid.
+const TOKEN_LIFETIME: TimeDelta = TimeDelta::minutes(15);
+
+/// What an emailed token is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum TokenPurpose {
+    ConfirmEmail,
+    ResetPassword,
+}
+
+impl TokenPurpose {
+    fn subject(self) -> &'static str {
+        match self {
+            Self::ConfirmEmail => "Confirm your email address",
+            Self::ResetPassword => "Reset your password",
+        }
+    }
+}
+
+/// The outstanding token sent to an account's address, stored with the
+/// account. A new token replaces the previous one.
+#[derive(Clone, Serialize, Deserialize)]
+pub(crate) struct EmailToken {
+    pub purpose: TokenPurpose,
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl std::fmt::Debug for EmailToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmailToken")
+            .field("purpose", &self.purpose)
+            .field("expires_at", &self.expires_at)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EmailToken {
+    /// A new random token, in the `XXXXX-XXXXX` form a network PDS sends.
+    pub(crate) fn new(purpose: TokenPurpose) -> Self {
+        let id = base32_lower(Uuid::new_v4().as_bytes()).to_uppercase();
+        Self {
+            purpose,
+            token: format!("{}-{}", &id[..5], &id[5..10]),
+            expires_at: Utc::now() + TOKEN_LIFETIME,
+        }
+    }
+
+    /// Returns true if `token` is this token, ignoring case and
+    /// surrounding whitespace as it may have been copied from an email.
+    pub(crate) fn matches(&self, purpose: TokenPurpose, token: &str) -> bool {
+        self.purpose == purpose && self.token.eq_ignore_ascii_case(token.trim())
+    }
+
+    /// Fail with `ExpiredToken` once the token's lifetime has passed.
+    pub(crate) fn check_expiry(&self) -> Result<()> {
+        if Utc::now() > self.expires_at {
+            return Err(Error::Protocol(ProtocolError::new(
+                400,
+                Some("ExpiredToken".to_string()),
+                Some("Token has expired".to_string()),
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The `InvalidToken` error for a token that matches nothing outstanding.
+pub(crate) fn invalid_token() -> Error {
+    Error::Protocol(ProtocolError::new(
+        400,
+        Some("InvalidToken".to_string()),
+        Some("Token is invalid".to_string()),
+    ))
+}
+
+/// Check that `email` looks like an address: one `@` between a local part
+/// and a domain, with no whitespace or path separators.
+pub(crate) fn check_email(email: &str) -> Result<()> {
+    let valid = email.split_once('@').is_some_and(|(local, domain)| {
+        !local.is_empty() && domain.contains('.') && !domain.contains('@')
+    }) && !email.contains(|c: char| c.is_whitespace() || c == '/' || c == '\\');
+    if !valid {
+        return Err(Error::InvalidInput(InvalidInputError::Other {
+            message: format!("invalid email address {:?}", email),
+        }));
+    }
+    Ok(())
+}
+
+/// An email a [`FilePds`](crate::FilePds) sent, read back with
+/// [`FilePds::sent_mail`](crate::FilePds::sent_mail).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SentMail {
+    /// The address it was sent to.
+    pub to: String,
+    /// The subject line, which says what the token is for.
+    pub subject: String,
+    /// The token to pass to `confirm_email` or `reset_password`.
+    pub token: String,
+    /// When it was sent.
+    pub sent_at: DateTime<Utc>,
+}
+
+/// Write a message carrying `token` to `to`'s outbox file.
+pub(crate) fn send(outbox: &Path, to: &str, token: &EmailToken) -> Result<()> {
+    let mail = SentMail {
+        to: to.to_string(),
+        subject: token.purpose.subject().to_string(),
+        token: token.token.clone(),
+        sent_at: Utc::now(),
+    };
+    let content = serde_json::to_string_pretty(&mail).map_err(|e| {
+        Error::InvalidInput(InvalidInputError::Other {
+            message: e.to_string(),
+        })
+    })?;
+    fs::create_dir_all(outbox).map_err(map_io)?;
+    fs::write(outbox_path(outbox, to), content).map_err(map_io)
+}
+
+/// Read the last message sent to `to`, if any.
+pub(crate) fn last_sent(outbox: &Path, to: &str) -> Result<Option<SentMail>> {
+    check_email(to)?;
+    let path = outbox_path(outbox, to);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).map_err(map_io)?;
+    let mail = serde_json::from_str(&content).map_err(|e| {
+        Error::InvalidInput(InvalidInputError::Other {
+            message: format!("invalid outbox message: {}", e),
+        })
+    })?;
+    Ok(Some(mail))
+}
+
+fn outbox_path(outbox: &Path, to: &str) -> std::path::PathBuf {
+    outbox.join(format!("{}.json", to.to_lowercase()))
+}
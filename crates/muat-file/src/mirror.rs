@@ -0,0 +1,242 @@
+//! Mirroring network repos into a file-backed PDS.
+
+use std::fmt;
+use std::sync::Arc;
+
+use futures_util::StreamExt;
+use tracing::{debug, info, instrument, warn};
+
+use muat_core::Result;
+use muat_core::error::Error;
+use muat_core::repo::{CommitEvent, RepoEvent};
+use muat_core::sync::{RecordChange, diff_repos_public};
+use muat_core::traits::{CursorStore, FirehoseExt, Pds};
+use muat_core::types::{AtUri, Did};
+use muat_core::{CancellationToken, MemoryCursorStore};
+
+use crate::pds::FilePds;
+
+/// Keeps copies of repos hosted elsewhere in a [`FilePds`], up to date.
+///
+/// Records are stored under their own DIDs, without local accounts, so the
+/// file PDS serves them at the same AT URIs as the source does. Writes go
+/// straight to the store, skipping the PDS's record validators, and are
+/// logged to its firehose like any other write.
+///
+/// [`backfill`](Self::backfill) brings each repo in line with the source by
+/// comparing the two with [`diff_repos_public`]; [`run`](Self::run) does
+/// that and then follows the source's firehose. Commits are applied by
+/// reading each record the source now holds, so replaying an event is
+/// harmless and the firehose cursor only needs saving after each commit.
+/// Cursors are kept under the source's URL in the
+/// [`with_cursor_store`](Self::with_cursor_store) store, in memory by
+/// default.
+///
+/// # Example
+///
+/// ```ignore
+/// let mirror = Mirror::new(XrpcPds::new(source_url), local_pds, &[did])
+///     .with_cursor_store(Arc::new(FileCursorStore::new("cursors.json")));
+/// let report = mirror.run(CancellationToken::new()).await?;
+/// ```
+#[derive(Clone)]
+pub struct Mirror<P> {
+    source: P,
+    local: FilePds,
+    repos: Vec<Did>,
+    cursors: Arc<dyn CursorStore>,
+}
+
+impl<P> fmt::Debug for Mirror<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mirror")
+            .field("local", &self.local.url())
+            .field("repos", &self.repos)
+            .finish_non_exhaustive()
+    }
+}
+
+/// What a [`Mirror`] copied.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MirrorReport {
+    /// Records written that were not in the local copy.
+    pub created: usize,
+    /// Records rewritten because the source's copy changed.
+    pub updated: usize,
+    /// Records removed because the source no longer has them.
+    pub deleted: usize,
+    /// Records already the same in both copies when backfilled.
+    pub unchanged: usize,
+    /// Firehose commits applied after the backfill.
+    pub commits: u64,
+    /// The sequence number of the last event handled, saved as the cursor.
+    pub cursor: Option<i64>,
+}
+
+impl<P: Pds> Mirror<P> {
+    /// Mirror `repos` from `source` into `local`.
+    pub fn new(source: P, local: FilePds, repos: &[Did]) -> Self {
+        Self {
+            source,
+            local,
+            repos: repos.to_vec(),
+            cursors: Arc::new(MemoryCursorStore::new()),
+        }
+    }
+
+    /// Keep the firehose cursor in `store`, so a restarted mirror resumes
+    /// where it left off rather than missing what happened meanwhile.
+    pub fn with_cursor_store(mut self, store: Arc<dyn CursorStore>) -> Self {
+        self.cursors = store;
+        self
+    }
+
+    /// Returns the repos being mirrored.
+    pub fn repos(&self) -> &[Did] {
+        &self.repos
+    }
+
+    /// Returns the PDS holding the copies.
+    pub fn local(&self) -> &FilePds {
+        &self.local
+    }
+
+    /// The key the source's cursor is saved under.
+    fn host(&self) -> String {
+        self.source.url().to_string()
+    }
+
+    /// Bring every mirrored repo in line with the source.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error reading either copy or writing the local
+    /// one; repos already copied stay copied.
+    pub async fn backfill(&self) -> Result<MirrorReport> {
+        let mut report = MirrorReport::default();
+        for repo in &self.repos {
+            self.backfill_repo(repo, &mut report).await?;
+        }
+        Ok(report)
+    }
+
+    #[instrument(skip(self, report))]
+    async fn backfill_repo(&self, repo: &Did, report: &mut MirrorReport) -> Result<()> {
+        let diff = diff_repos_public(&self.local, &self.source, repo).await?;
+        debug!(
+            created = diff.created.len(),
+            updated = diff.updated.len(),
+            deleted = diff.deleted.len(),
+            "Backfilling repo"
+        );
+        for change in diff.created.iter().chain(&diff.updated) {
+            self.copy(&change_uri(repo, change)).await?;
+        }
+        for change in &diff.deleted {
+            self.local
+                .store()
+                .delete_record(&change_uri(repo, change))
+                .await?;
+        }
+        report.created += diff.created.len();
+        report.updated += diff.updated.len();
+        report.deleted += diff.deleted.len();
+        report.unchanged += diff.unchanged;
+        Ok(())
+    }
+
+    /// Backfill, then apply the source's firehose until it ends or `cancel`
+    /// is cancelled.
+    ///
+    /// The firehose is subscribed to before the backfill, from the saved
+    /// cursor if there is one, so nothing written during the backfill is
+    /// missed. Stream errors such as undecodable frames are logged and
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from the backfill, from reading a record
+    /// the firehose reports, or from writing the local copy. The cursor is
+    /// saved up to the last commit applied, so running again resumes there.
+    pub async fn run(&self, cancel: CancellationToken) -> Result<MirrorReport> {
+        let host = self.host();
+        let cursor = self.cursors.load(&host).await?;
+        info!(%host, ?cursor, repos = self.repos.len(), "Starting mirror");
+        let firehose = self
+            .source
+            .firehose_from(cursor)?
+            .filter_repos(&self.repos)
+            .until_cancelled(cancel);
+
+        let mut report = self.backfill().await?;
+        report.cursor = cursor;
+
+        let mut firehose = std::pin::pin!(firehose);
+        while let Some(event) = firehose.next().await {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!(error = %e, "Skipping firehose error");
+                    continue;
+                }
+            };
+            if let RepoEvent::Commit(commit) = &event {
+                self.apply(commit).await?;
+                report.commits += 1;
+            }
+            if let Some(seq) = event.seq().filter(|seq| *seq > 0) {
+                self.cursors.save(&host, seq).await?;
+                report.cursor = Some(seq);
+            }
+        }
+        info!(commits = report.commits, cursor = ?report.cursor, "Mirror stopped");
+        Ok(report)
+    }
+
+    /// Apply a firehose commit from the source to the local copy.
+    ///
+    /// Created and updated records are read from the source as they are
+    /// now; deleted ones, and those gone again by the time they are read,
+    /// are removed. Commits for repos not being mirrored are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error reading the source or writing the copy.
+    #[instrument(skip(self, commit), fields(repo = %commit.repo, seq = commit.seq))]
+    pub async fn apply(&self, commit: &CommitEvent) -> Result<()> {
+        let repo = Did::new(&commit.repo)?;
+        if !self.repos.contains(&repo) {
+            return Ok(());
+        }
+        for op in &commit.ops {
+            let uri = AtUri::new(format!("at://{}/{}", repo, op.path))?;
+            match op.action.as_str() {
+                "delete" => self.local.store().delete_record(&uri).await?,
+                _ => self.copy(&uri).await?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy the record at `uri` from the source, or remove the local copy
+    /// if the source no longer has it.
+    async fn copy(&self, uri: &AtUri) -> Result<()> {
+        let record = match self.source.get_record_public(uri).await {
+            Ok(record) => record,
+            Err(Error::Protocol(e)) if e.is_not_found() => {
+                return self.local.store().delete_record(uri).await;
+            }
+            Err(e) => return Err(e),
+        };
+        let (collection, rkey) = uri.record_path()?;
+        self.local
+            .store()
+            .put_record(uri.repo(), collection, rkey, &record.value, None)
+            .await?;
+        Ok(())
+    }
+}
+
+fn change_uri(repo: &Did, change: &RecordChange) -> AtUri {
+    AtUri::from_parts(repo.clone(), change.collection.clone(), change.rkey.clone())
+}
@@ -0,0 +1,183 @@
+//! Password hashing for local accounts.
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use muat_core::Result;
+use muat_core::error::{Error, InvalidInputError};
+
+/// Algorithm a stored password hash was made with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PasswordAlgorithm {
+    /// bcrypt. Accounts written before the algorithm was recorded use it.
+    #[default]
+    Bcrypt,
+    /// Argon2id, as a PHC string.
+    Argon2id,
+}
+
+/// How passwords of new accounts are hashed.
+///
+/// Existing hashes are verified with whichever algorithm made them. When a
+/// login succeeds against a hash that does not match the policy, the
+/// password is re-hashed with it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordHashing {
+    /// bcrypt with the given cost (4-31).
+    Bcrypt {
+        /// Base-2 logarithm of the number of rounds.
+        cost: u32,
+    },
+    /// Argon2id with the given parameters.
+    Argon2id {
+        /// Memory size in KiB.
+        memory_kib: u32,
+        /// Number of passes.
+        iterations: u32,
+        /// Degree of parallelism.
+        parallelism: u32,
+    },
+}
+
+impl Default for PasswordHashing {
+    fn default() -> Self {
+        Self::Bcrypt {
+            cost: bcrypt::DEFAULT_COST,
+        }
+    }
+}
+
+impl PasswordHashing {
+    /// Argon2id with the OWASP recommended minimum parameters
+    /// (19 MiB, 2 passes, 1 lane).
+    pub fn argon2id() -> Self {
+        Self::Argon2id {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+
+    /// The algorithm this policy hashes with.
+    pub fn algorithm(&self) -> PasswordAlgorithm {
+        match self {
+            Self::Bcrypt { .. } => PasswordAlgorithm::Bcrypt,
+            Self::Argon2id { .. } => PasswordAlgorithm::Argon2id,
+        }
+    }
+
+    /// Hash a password.
+    pub(crate) fn hash(&self, password: &str) -> Result<String> {
+        match *self {
+            Self::Bcrypt { cost } => bcrypt::hash(password, cost).map_err(invalid),
+            Self::Argon2id { .. } => {
+                let salt = SaltString::encode_b64(Uuid::new_v4().as_bytes()).map_err(invalid)?;
+                let hash = self
+                    .argon2()?
+                    .hash_password(password.as_bytes(), &salt)
+                    .map_err(invalid)?;
+                Ok(hash.to_string())
+            }
+        }
+    }
+
+    /// Whether a stored hash was made with a different algorithm or
+    /// parameters than this policy.
+    pub(crate) fn needs_rehash(&self, algorithm: PasswordAlgorithm, hash: &str) -> bool {
+        if algorithm != self.algorithm() {
+            return true;
+        }
+        match *self {
+            // bcrypt hashes look like `$2b$<cost>$<salt and hash>`.
+            Self::Bcrypt { cost } => hash.split('$').nth(2) != Some(&format!("{:02}", cost)),
+            Self::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                let Ok(params) = PasswordHash::new(hash).and_then(|h| Params::try_from(&h)) else {
+                    return true;
+                };
+                (params.m_cost(), params.t_cost(), params.p_cost())
+                    != (memory_kib, iterations, parallelism)
+            }
+        }
+    }
+
+    fn argon2(&self) -> Result<Argon2<'static>> {
+        let Self::Argon2id {
+            memory_kib,
+            iterations,
+            parallelism,
+        } = *self
+        else {
+            return Err(invalid("not an Argon2id policy"));
+        };
+        let params = Params::new(memory_kib, iterations, parallelism, None).map_err(invalid)?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+/// Check a password against a stored hash.
+pub(crate) fn verify(password: &str, algorithm: PasswordAlgorithm, hash: &str) -> Result<bool> {
+    match algorithm {
+        PasswordAlgorithm::Bcrypt => bcrypt::verify(password, hash).map_err(invalid),
+        PasswordAlgorithm::Argon2id => {
+            let hash = PasswordHash::new(hash).map_err(invalid)?;
+            // Parameters come from the hash itself.
+            Ok(Argon2::default()
+                .verify_password(password.as_bytes(), &hash)
+                .is_ok())
+        }
+    }
+}
+
+fn invalid(e: impl ToString) -> Error {
+    Error::InvalidInput(InvalidInputError::Other {
+        message: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Small parameters keep the tests fast.
+    const ARGON2: PasswordHashing = PasswordHashing::Argon2id {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+    const BCRYPT: PasswordHashing = PasswordHashing::Bcrypt { cost: 4 };
+
+    #[test]
+    fn hashes_verify_with_their_algorithm() {
+        for policy in [ARGON2, BCRYPT] {
+            let hash = policy.hash("secret").unwrap();
+            assert!(verify("secret", policy.algorithm(), &hash).unwrap());
+            assert!(!verify("wrong", policy.algorithm(), &hash).unwrap());
+            assert!(!policy.needs_rehash(policy.algorithm(), &hash));
+        }
+    }
+
+    #[test]
+    fn policy_changes_need_rehash() {
+        let bcrypt = BCRYPT.hash("secret").unwrap();
+        assert!(ARGON2.needs_rehash(PasswordAlgorithm::Bcrypt, &bcrypt));
+        assert!(
+            PasswordHashing::Bcrypt { cost: 5 }.needs_rehash(PasswordAlgorithm::Bcrypt, &bcrypt)
+        );
+
+        let argon2 = ARGON2.hash("secret").unwrap();
+        let stronger = PasswordHashing::Argon2id {
+            memory_kib: 128,
+            iterations: 1,
+            parallelism: 1,
+        };
+        assert!(stronger.needs_rehash(PasswordAlgorithm::Argon2id, &argon2));
+        assert!(BCRYPT.needs_rehash(PasswordAlgorithm::Argon2id, &argon2));
+    }
+}
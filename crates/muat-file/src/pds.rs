@@ -1,44 +1,364 @@
 //! File-backed PDS implementation.
 
+use std::sync::Arc;
+
 use async_trait::async_trait;
-use bcrypt::{DEFAULT_COST, hash, verify};
 use serde_json::json;
-
-use muat_core::error::{AuthError, Error, InvalidInputError};
-use muat_core::traits::{CreateAccountOutput, Pds};
-use muat_core::types::{Did, PdsUrl};
-use muat_core::{AccessToken, Credentials, Result};
-
+use tracing::{debug, warn};
+
+use muat_core::error::{AuthError, Error, InvalidInputError, ProtocolError};
+use muat_core::repo::{
+    FirehoseBuffer, KeyOrder, ListRecordsOptions, ListRecordsOutput, Record, RecordValue,
+};
+use muat_core::traits::{
+    BlobStore, CreateAccountOutput, Pds, ServerDescription, Session as _, StoredSession,
+};
+use muat_core::types::{AtUri, Did, Handle, Nsid, PdsUrl};
+use muat_core::{AccessToken, Credentials, Lexicon, Result};
+use serde::de::DeserializeOwned;
+
+use crate::blobs::FileBlobStore;
 use crate::firehose::FileFirehose;
+use crate::mail::{self, EmailToken, SentMail, TokenPurpose, check_email, invalid_token};
+use crate::password::{PasswordHashing, verify};
 use crate::session::FileSession;
-use crate::store::{FileStore, LocalAccount};
+use crate::storage::Storage;
+use crate::store::{CompactionStats, Compression, FileStore, LocalAccount, RecordVersion};
+use crate::validation::{LexiconValidator, RecordValidator, Validators};
+
+/// The DID a file-backed PDS describes itself with; it has no host name
+/// of its own.
+const SERVER_DID: &str = "did:web:localhost";
 
 /// Filesystem-backed PDS implementation.
 #[derive(Debug, Clone)]
 pub struct FilePds {
-    store: FileStore,
+    store: Storage,
+    blobs: Arc<dyn BlobStore>,
     url: PdsUrl,
+    hashing: PasswordHashing,
+    firehose_buffer: FirehoseBuffer,
+    user_domains: Vec<String>,
+    validators: Validators,
 }
 
 impl FilePds {
     /// Create a new file-backed PDS at the given root directory.
+    ///
+    /// Records are kept in a file each, or in a SQLite database if `url` is
+    /// a `file+sqlite://` URL (see [`with_sqlite`](Self::with_sqlite)).
+    /// Blobs are stored under the same root unless replaced with
+    /// [`with_blob_store`](Self::with_blob_store).
+    ///
+    /// Accounts that earlier versions gave hex `did:plc` DIDs are first moved
+    /// to base32 DIDs, with each hex digit replaced by the letter of the same
+    /// value (`0` is `a`, `f` is `p`).
     pub fn new(root: impl AsRef<std::path::Path>, url: PdsUrl) -> Self {
+        let store = if url.is_sqlite() {
+            Storage::sqlite(root)
+        } else {
+            let store = FileStore::new(root);
+            if let Err(e) = store.migrate_legacy_dids() {
+                warn!(error = %e, "Failed to migrate legacy did:plc accounts");
+            }
+            Storage::File(store)
+        };
+        let blobs = Arc::new(FileBlobStore::new(store.root().join("pds").join("blobs")));
         Self {
-            store: FileStore::new(root),
+            store,
+            blobs,
             url,
+            hashing: PasswordHashing::default(),
+            firehose_buffer: FirehoseBuffer::default(),
+            user_domains: Vec::new(),
+            validators: Validators::default(),
         }
     }
 
+    /// Use a different blob store, shared by all sessions of this PDS.
+    pub fn with_blob_store(mut self, blobs: Arc<dyn BlobStore>) -> Self {
+        self.blobs = blobs;
+        self
+    }
+
+    /// Set how account passwords are hashed (bcrypt by default).
+    ///
+    /// Applies to new accounts, and to existing ones the next time they log
+    /// in: a password hashed another way is re-hashed with this policy. That
+    /// replaces the stored hash, so earlier sessions of the account end.
+    pub fn with_password_hashing(mut self, hashing: PasswordHashing) -> Self {
+        self.hashing = hashing;
+        self
+    }
+
+    /// Set how many firehose events are buffered for a slow consumer, and
+    /// what happens when the buffer fills (100 events, blocking, by default).
+    pub fn with_firehose_buffer(mut self, buffer: FirehoseBuffer) -> Self {
+        self.firehose_buffer = buffer;
+        self
+    }
+
+    /// Restrict new accounts to handles under these domains, such as
+    /// `.pds.example.com`, as a network PDS advertises in `describeServer`.
+    ///
+    /// With no domains (the default), any valid handle is accepted.
+    pub fn with_user_domains<I, S>(mut self, domains: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.user_domains = domains.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Returns the domains new account handles must be under; empty if any
+    /// handle is accepted.
+    pub fn user_domains(&self) -> &[String] {
+        &self.user_domains
+    }
+
+    /// Check every record written with `validator`, rejecting those it
+    /// fails with an `InvalidRecord` protocol error, as a network PDS
+    /// rejects records that do not match their lexicon. Validators run in
+    /// the order added.
+    ///
+    /// Records already stored are not checked.
+    ///
+    /// ```
+    /// # use muat_core::PdsUrl;
+    /// # use muat_file::FilePds;
+    /// let pds = FilePds::new("./pds", PdsUrl::new("file://./pds").unwrap()).with_validator(
+    ///     |collection: &muat_core::Nsid, record: &muat_core::RecordValue| {
+    ///         match record.get("text") {
+    ///             Some(text) if !text.is_string() => Err("text must be a string".to_string()),
+    ///             _ => Ok(()),
+    ///         }
+    ///     },
+    /// );
+    /// ```
+    pub fn with_validator(mut self, validator: impl RecordValidator + 'static) -> Self {
+        self.validators.push(Arc::new(validator));
+        self
+    }
+
+    /// Check records written to `T::NSID` by deserializing them as `T`,
+    /// such as a type generated by `muat-codegen`. Records that do not
+    /// deserialize, or whose `$type` is not `T::TYPE`, are rejected as by
+    /// [`with_validator`](Self::with_validator).
+    pub fn with_lexicon<T>(self) -> Self
+    where
+        T: Lexicon + DeserializeOwned + 'static,
+    {
+        self.with_validator(LexiconValidator::<T>::new())
+    }
+
+    /// Keep accounts, records and the firehose log in a SQLite database,
+    /// `pds/pds.sqlite` under the root, instead of a file per record.
+    ///
+    /// Each write commits with its firehose event, and bulk creates are
+    /// atomic. Firehose sequence numbers count up from 1 rather than being
+    /// timestamps. Records already stored as files are not moved.
+    #[cfg(feature = "sqlite")]
+    pub fn with_sqlite(mut self) -> Self {
+        self.store = Storage::sqlite(self.store.root()).with_key_order(self.store.key_order());
+        self
+    }
+
+    /// Set the compression used for newly written records.
+    ///
+    /// Existing records are read in whichever format they were written; use
+    /// [`compact_records`](Self::compact_records) to convert them. Has no
+    /// effect on a SQLite store.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.store = self.store.with_compression(compression);
+        self
+    }
+
+    /// Set the order keys are written in for newly written records:
+    /// bytewise, as `serde_json` sorts them (the default), or
+    /// [`KeyOrder::Canonical`] to match their DAG-CBOR encoding.
+    ///
+    /// Record CIDs are the same either way, as they hash the canonical
+    /// DAG-CBOR encoding rather than the stored JSON. Existing records keep
+    /// the order they were written in.
+    pub fn with_key_order(mut self, key_order: KeyOrder) -> Self {
+        self.store = self.store.with_key_order(key_order);
+        self
+    }
+
+    /// Rewrite all stored records in the configured compression format.
+    ///
+    /// A SQLite store is rebuilt to reclaim unused space instead.
+    pub fn compact_records(&self) -> Result<CompactionStats> {
+        self.store.compact()
+    }
+
     /// Returns the PDS URL for this instance.
     pub fn url(&self) -> &PdsUrl {
         &self.url
     }
 
-    /// Access the underlying file store.
-    pub(crate) fn store(&self) -> &FileStore {
+    /// Returns the root directory of this PDS.
+    pub fn root(&self) -> &std::path::Path {
+        self.store.root()
+    }
+
+    /// Returns the number of accounts stored in this PDS.
+    pub fn account_count(&self) -> Result<usize> {
+        Ok(self.store.list_accounts()?.len())
+    }
+
+    /// Returns whether the PDS directory can be written to.
+    ///
+    /// Checks by creating and removing a probe file, so it reflects
+    /// read-only mounts as well as permissions. A root that does not exist
+    /// yet is checked at its nearest existing parent.
+    pub fn is_writable(&self) -> bool {
+        let pds_dir = self.store.root().join("pds");
+        let Some(dir) = pds_dir
+            .ancestors()
+            .find(|p| p.as_os_str().is_empty() || p.is_dir())
+        else {
+            return false;
+        };
+        // A relative root with no existing component lives in the current directory.
+        let dir = if dir.as_os_str().is_empty() {
+            std::path::Path::new(".")
+        } else {
+            dir
+        };
+        let probe = dir.join(format!(".write-probe-{}", uuid::Uuid::new_v4()));
+        match std::fs::File::create(&probe) {
+            Ok(_) => {
+                let _ = std::fs::remove_file(&probe);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the handle of a local account, if it exists.
+    pub fn handle_of(&self, did: &Did) -> Result<Option<String>> {
+        Ok(self.store.get_account(did)?.map(|account| account.handle))
+    }
+
+    /// Returns whether a local account exists and has not been deactivated.
+    pub fn is_active(&self, did: &Did) -> Result<bool> {
+        Ok(self
+            .store
+            .get_account(did)?
+            .is_some_and(|account| account.deactivated_at.is_none()))
+    }
+
+    /// Look up the DID of a local account by handle.
+    pub fn resolve_handle(&self, handle: &str) -> Result<Option<Did>> {
+        self.store
+            .find_account_by_handle(handle)?
+            .map(|account| Did::new(account.did))
+            .transpose()
+    }
+
+    /// Read a record as it was after the firehose event with sequence
+    /// number `seq`, replaying the firehose log.
+    ///
+    /// Fails with `RecordNotFound` if the record did not exist then, and
+    /// with an invalid-input error if its last write at that point predates
+    /// the log keeping record content.
+    pub async fn get_record_as_of(&self, uri: &AtUri, seq: i64) -> Result<Record> {
+        self.store.get_record_as_of(uri, seq).await
+    }
+
+    /// Every write to a record, oldest first, replaying the firehose log.
+    ///
+    /// Deletes appear as versions without a record. Writes logged before
+    /// the log kept record content are left out.
+    pub async fn record_history(&self, uri: &AtUri) -> Result<Vec<RecordVersion>> {
+        self.store.record_history(uri).await
+    }
+
+    /// Read the version of a record with CID `cid`: the current record, or
+    /// an earlier version from the firehose log.
+    ///
+    /// Fails with `RecordNotFound` if the record never had that CID.
+    pub async fn get_record_at(&self, uri: &AtUri, cid: &str) -> Result<Record> {
+        match self.store.get_record(uri).await {
+            Ok(record) if record.cid == cid => return Ok(record),
+            Ok(_) => {}
+            Err(Error::Protocol(e)) if e.is_not_found() => {}
+            Err(e) => return Err(e),
+        }
+        self.store
+            .record_history(uri)
+            .await?
+            .into_iter()
+            .rev()
+            .find_map(|version| version.record.filter(|record| record.cid == cid))
+            .ok_or_else(|| {
+                Error::Protocol(ProtocolError::new(
+                    404,
+                    Some("RecordNotFound".to_string()),
+                    Some(format!("Record {} has no version with CID {}", uri, cid)),
+                ))
+            })
+    }
+
+    /// List a collection as it was after the firehose event with sequence
+    /// number `seq`. Paging, order and time windows work as for
+    /// [`list_records_public`](Pds::list_records_public).
+    pub async fn list_records_as_of(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        seq: i64,
+        options: &ListRecordsOptions,
+    ) -> Result<ListRecordsOutput> {
+        self.store
+            .list_records_as_of(repo, collection, seq, options)
+            .await
+    }
+
+    /// Returns the last email sent to `to`: the confirmation or password
+    /// reset token a network PDS would have mailed. This PDS does not send
+    /// mail; messages are kept in `pds/outbox/` under the root instead.
+    pub fn sent_mail(&self, to: &str) -> Result<Option<SentMail>> {
+        mail::last_sent(&self.outbox_dir(), to)
+    }
+
+    fn outbox_dir(&self) -> std::path::PathBuf {
+        self.store.root().join("pds").join("outbox")
+    }
+
+    fn find_account_by_email(&self, email: &str) -> Result<Option<LocalAccount>> {
+        Ok(self.store.list_accounts()?.into_iter().find(|account| {
+            account
+                .email
+                .as_deref()
+                .is_some_and(|address| address.eq_ignore_ascii_case(email))
+        }))
+    }
+
+    /// Store a new token for the account and mail it to `email`, replacing
+    /// any token sent before.
+    pub(crate) fn send_token(&self, did: &Did, email: &str, purpose: TokenPurpose) -> Result<()> {
+        let token = EmailToken::new(purpose);
+        let stored = token.clone();
+        self.store
+            .update_account(did, |account| account.email_token = Some(stored))?;
+        mail::send(&self.outbox_dir(), email, &token)?;
+        debug!(did = %did, ?purpose, "Sent email token");
+        Ok(())
+    }
+
+    /// Access the underlying record store.
+    pub(crate) fn store(&self) -> &Storage {
         &self.store
     }
 
+    /// Access the blob store.
+    pub(crate) fn blob_store(&self) -> &dyn BlobStore {
+        self.blobs.as_ref()
+    }
+
     fn make_token(did: &Did, password_hash: &str) -> AccessToken {
         let token = json!({
             "did": did.as_str(),
@@ -88,6 +408,29 @@ impl FilePds {
     }
 
     pub(crate) fn ensure_repo_access(&self, token: &AccessToken, repo: &Did) -> Result<()> {
+        self.repo_owner(token, repo).map(|_| ())
+    }
+
+    /// Like [`ensure_repo_access`](Self::ensure_repo_access), and also fail
+    /// while the account is deactivated.
+    pub(crate) fn ensure_writable(&self, token: &AccessToken, repo: &Did) -> Result<()> {
+        let account = self.repo_owner(token, repo)?;
+        if account.deactivated_at.is_some() {
+            return Err(AuthError::AccountUnavailable {
+                reason: "account is deactivated".to_string(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Run the registered validators on a record about to be written.
+    pub(crate) fn validate_record(&self, collection: &Nsid, value: &RecordValue) -> Result<()> {
+        self.validators.check(collection, value)
+    }
+
+    /// The account `token` belongs to, if it owns `repo`.
+    fn repo_owner(&self, token: &AccessToken, repo: &Did) -> Result<LocalAccount> {
         let account = self.validate_token(token)?;
         let did = Did::new(&account.did)?;
 
@@ -95,6 +438,23 @@ impl FilePds {
             return Err(AuthError::InvalidCredentials("Access denied".to_string()).into());
         }
 
+        Ok(account)
+    }
+
+    /// Fail with `RepoDeactivated` if `repo` belongs to a deactivated
+    /// account, which is not served publicly.
+    fn ensure_repo_active(&self, repo: &Did) -> Result<()> {
+        let deactivated = self
+            .store
+            .get_account(repo)?
+            .is_some_and(|account| account.deactivated_at.is_some());
+        if deactivated {
+            return Err(Error::Protocol(ProtocolError::new(
+                400,
+                Some("RepoDeactivated".to_string()),
+                Some(format!("Repo has been deactivated: {}", repo)),
+            )));
+        }
         Ok(())
     }
 
@@ -114,11 +474,7 @@ impl FilePds {
         }
 
         if let Some(password) = password {
-            let ok = verify(password, &account.password_hash).map_err(|e| {
-                Error::InvalidInput(InvalidInputError::Other {
-                    message: e.to_string(),
-                })
-            })?;
+            let ok = verify(password, account.password_algorithm, &account.password_hash)?;
 
             if !ok {
                 return Err(AuthError::InvalidCredentials("Invalid password".to_string()).into());
@@ -149,27 +505,69 @@ impl Pds for FilePds {
         }
         .ok_or_else(|| AuthError::InvalidCredentials("Account not found".to_string()))?;
 
-        let ok = verify(credentials.password(), &account.password_hash).map_err(|e| {
-            Error::InvalidInput(InvalidInputError::Other {
-                message: e.to_string(),
-            })
-        })?;
+        let ok = verify(
+            credentials.password(),
+            account.password_algorithm,
+            &account.password_hash,
+        )?;
 
         if !ok {
             return Err(AuthError::InvalidCredentials("Invalid password".to_string()).into());
         }
 
         let did = Did::new(&account.did)?;
-        let token = Self::make_token(&did, &account.password_hash);
+        let mut password_hash = account.password_hash;
+        if self
+            .hashing
+            .needs_rehash(account.password_algorithm, &password_hash)
+        {
+            // The login still succeeds if the new hash cannot be stored.
+            let rehashed = self.hashing.hash(credentials.password()).and_then(|hash| {
+                self.store
+                    .update_password_hash(&did, &hash, self.hashing.algorithm())?;
+                Ok(hash)
+            });
+            match rehashed {
+                Ok(hash) => {
+                    debug!(did = %did, algorithm = ?self.hashing.algorithm(), "Re-hashed password");
+                    password_hash = hash;
+                }
+                Err(e) => warn!(did = %did, error = %e, "Failed to re-hash password"),
+            }
+        }
+        let token = Self::make_token(&did, &password_hash);
 
         Ok(FileSession::new(self.clone(), did, token))
     }
 
+    async fn restore(&self, stored: &StoredSession) -> Result<Self::Session> {
+        let session = FileSession::from_persisted(self.clone(), stored.access_token.clone())?;
+        if session.did() != &stored.did {
+            return Err(AuthError::InvalidCredentials(
+                "Stored token belongs to another account".to_string(),
+            )
+            .into());
+        }
+        session.validate()?;
+        Ok(session)
+    }
+
+    async fn describe(&self) -> Result<ServerDescription> {
+        Ok(ServerDescription {
+            did: SERVER_DID.to_string(),
+            available_user_domains: self.user_domains.clone(),
+            ..ServerDescription::default()
+        })
+    }
+
+    /// Keeps `email`, which must not belong to another local account, for
+    /// [`request_password_reset`](Pds::request_password_reset) and email
+    /// confirmation.
     async fn create_account(
         &self,
         handle: &str,
         password: Option<&str>,
-        _email: Option<&str>,
+        email: Option<&str>,
         _invite_code: Option<&str>,
     ) -> Result<CreateAccountOutput> {
         let password = password.ok_or_else(|| {
@@ -178,17 +576,32 @@ impl Pds for FilePds {
             })
         })?;
 
-        let password_hash = hash(password, DEFAULT_COST).map_err(|e| {
-            Error::InvalidInput(InvalidInputError::Other {
-                message: e.to_string(),
-            })
-        })?;
+        let handle = Handle::new(handle)?;
+        handle.check_user_domains(&self.user_domains)?;
+
+        if let Some(email) = email {
+            check_email(email)?;
+            if self.find_account_by_email(email)?.is_some() {
+                return Err(Error::Protocol(ProtocolError::new(
+                    400,
+                    Some("InvalidRequest".to_string()),
+                    Some("Email already taken".to_string()),
+                )));
+            }
+        }
+
+        let password_hash = self.hashing.hash(password)?;
 
-        let did = self.store.create_account(handle, &password_hash)?;
+        let did = self.store.create_account(
+            handle.as_str(),
+            email,
+            &password_hash,
+            self.hashing.algorithm(),
+        )?;
 
         Ok(CreateAccountOutput {
             did,
-            handle: handle.to_string(),
+            handle: handle.into(),
         })
     }
 
@@ -201,7 +614,69 @@ impl Pds for FilePds {
         self.remove_account(did, token, true, password).await
     }
 
-    fn firehose_from(&self, _cursor: Option<i64>) -> Result<Self::Firehose> {
-        FileFirehose::from_store(self.store.clone())
+    /// Writes the token to the outbox (see [`sent_mail`](Self::sent_mail)).
+    /// Succeeds without sending anything if no account has this address, so
+    /// the call does not reveal which addresses have accounts.
+    async fn request_password_reset(&self, email: &str) -> Result<()> {
+        check_email(email)?;
+        let Some(account) = self.find_account_by_email(email)? else {
+            debug!("No account for password reset address");
+            return Ok(());
+        };
+        let did = Did::new(&account.did)?;
+        self.send_token(&did, email, TokenPurpose::ResetPassword)
+    }
+
+    /// Changing the password ends the account's existing sessions.
+    async fn reset_password(&self, token: &str, password: &str) -> Result<()> {
+        let account = self
+            .store
+            .list_accounts()?
+            .into_iter()
+            .find(|account| {
+                account
+                    .email_token
+                    .as_ref()
+                    .is_some_and(|sent| sent.matches(TokenPurpose::ResetPassword, token))
+            })
+            .ok_or_else(invalid_token)?;
+        if let Some(sent) = &account.email_token {
+            sent.check_expiry()?;
+        }
+
+        let did = Did::new(&account.did)?;
+        let password_hash = self.hashing.hash(password)?;
+        let algorithm = self.hashing.algorithm();
+        self.store.update_account(&did, |account| {
+            account.password_hash = password_hash;
+            account.password_algorithm = algorithm;
+            account.email_token = None;
+        })?;
+        debug!(did = %did, "Reset password");
+        Ok(())
+    }
+
+    async fn get_record_public(&self, uri: &AtUri) -> Result<Record> {
+        self.ensure_repo_active(uri.repo())?;
+        self.store.get_record(uri).await
+    }
+
+    async fn list_collections_public(&self, repo: &Did) -> Result<Vec<Nsid>> {
+        self.ensure_repo_active(repo)?;
+        self.store.list_collections(repo).await
+    }
+
+    async fn list_records_public(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        options: &ListRecordsOptions,
+    ) -> Result<ListRecordsOutput> {
+        self.ensure_repo_active(repo)?;
+        self.store.list_records(repo, collection, options).await
+    }
+
+    fn firehose_from(&self, cursor: Option<i64>) -> Result<Self::Firehose> {
+        FileFirehose::from_store(self.store.clone(), cursor, self.firehose_buffer)
     }
 }
@@ -0,0 +1,135 @@
+//! Recording and replaying firehose streams.
+//!
+//! Events are stored one per line as JSON (jsonl), tagged with an `event`
+//! field. Files are append-only, so a recording can be resumed by opening
+//! the same path again.
+
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::{Stream, StreamExt};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use muat_core::Result;
+use muat_core::error::{Error, InvalidInputError};
+use muat_core::repo::RepoEvent;
+use muat_core::traits::Firehose;
+
+use crate::store::map_io;
+
+/// Writes firehose events to an append-only jsonl file.
+///
+/// # Example
+///
+/// ```ignore
+/// use futures_util::StreamExt;
+///
+/// let mut recorder = FirehoseRecorder::open("events.jsonl").await?;
+/// let count = recorder.record_all(pds.firehose()?.take(100)).await?;
+/// ```
+#[derive(Debug)]
+pub struct FirehoseRecorder {
+    file: File,
+}
+
+impl FirehoseRecorder {
+    /// Open `path` for appending, creating it if it does not exist.
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path.as_ref())
+            .await
+            .map_err(map_io)?;
+        Ok(Self { file })
+    }
+
+    /// Append a single event.
+    ///
+    /// Each event is flushed as it is written, so an interrupted recording
+    /// is still readable up to the last complete event.
+    pub async fn record(&mut self, event: &RepoEvent) -> Result<()> {
+        let mut line = serde_json::to_string(event).map_err(|e| {
+            Error::InvalidInput(InvalidInputError::Other {
+                message: format!("Failed to serialize event: {}", e),
+            })
+        })?;
+        line.push('\n');
+
+        self.file.write_all(line.as_bytes()).await.map_err(map_io)?;
+        self.file.flush().await.map_err(map_io)
+    }
+
+    /// Record every event from `stream` until it ends.
+    ///
+    /// Returns the number of events written. Stops at the first error from
+    /// the stream; events received before it are kept.
+    pub async fn record_all<S: Firehose>(&mut self, stream: S) -> Result<u64> {
+        let mut stream = Box::pin(stream);
+        let mut count = 0;
+
+        while let Some(event) = stream.next().await {
+            self.record(&event?).await?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
+
+/// Re-emits a recording made by [`FirehoseRecorder`] as a firehose.
+///
+/// The stream ends after the last recorded event. A malformed line yields an
+/// error and ends the stream.
+pub struct FirehoseReplayer {
+    inner: Pin<Box<dyn Stream<Item = Result<RepoEvent>> + Send>>,
+}
+
+impl FirehoseReplayer {
+    /// Open a recording for replay.
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path.as_ref()).await.map_err(map_io)?;
+        let mut lines = BufReader::new(file).lines();
+
+        let stream = async_stream::stream! {
+            loop {
+                let line = match lines.next_line().await {
+                    Ok(Some(line)) => line,
+                    Ok(None) => break,
+                    Err(e) => {
+                        yield Err(map_io(e));
+                        break;
+                    }
+                };
+
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                match serde_json::from_str::<RepoEvent>(&line) {
+                    Ok(event) => yield Ok(event),
+                    Err(e) => {
+                        yield Err(Error::InvalidInput(InvalidInputError::Other {
+                            message: format!("Malformed recorded event: {}", e),
+                        }));
+                        break;
+                    }
+                }
+            }
+        };
+
+        Ok(Self {
+            inner: Box::pin(stream),
+        })
+    }
+}
+
+impl Stream for FirehoseReplayer {
+    type Item = Result<RepoEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
@@ -1,15 +1,28 @@
 //! File-backed session implementation.
 
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use tracing::{debug, instrument};
 
-use muat_core::repo::{ListRecordsOutput, Record, RecordValue};
-use muat_core::traits::Session as SessionTrait;
-use muat_core::types::{AtUri, Did, Nsid, PdsUrl};
+#[cfg(feature = "sqlite")]
+use muat_core::CancellationToken;
+use muat_core::error::{Error, InvalidInputError, ProtocolError};
+#[cfg(feature = "sqlite")]
+use muat_core::repo::BulkReport;
+use muat_core::repo::{ListRecordsOptions, ListRecordsOutput, Record, RecordValue};
+#[cfg(feature = "sqlite")]
+use muat_core::traits::create_records_pipelined;
+use muat_core::traits::{AccountStatus, BlobStore, Session as SessionTrait};
+use muat_core::types::{AtUri, Did, Handle, Nsid, PdsUrl, Rkey};
 use muat_core::{AccessToken, RefreshToken, Result};
 
+use crate::mail::{TokenPurpose, invalid_token};
 use crate::pds::FilePds;
 
+/// Most records written in one transaction by `create_records_bulk`.
+#[cfg(feature = "sqlite")]
+const BULK_TRANSACTION_MAX: usize = 200;
+
 /// Session for a file-backed PDS.
 #[derive(Debug, Clone)]
 pub struct FileSession {
@@ -31,6 +44,57 @@ impl FileSession {
         let (did, _) = FilePds::parse_token(&access_token)?;
         Ok(Self::new(pds, did, access_token))
     }
+
+    /// Returns the PDS this session belongs to.
+    pub fn file_pds(&self) -> &FilePds {
+        &self.pds
+    }
+
+    /// Check that the account still exists and the token matches it.
+    pub fn validate(&self) -> Result<()> {
+        self.pds.validate_token(&self.access_token).map(|_| ())
+    }
+
+    /// Create a record under a caller-chosen record key.
+    ///
+    /// Fails if a record already exists at that key.
+    #[instrument(skip(self, value), fields(did = %self.did, %collection, %rkey))]
+    pub async fn create_record_with_rkey(
+        &self,
+        collection: &Nsid,
+        rkey: &Rkey,
+        value: &RecordValue,
+    ) -> Result<AtUri> {
+        debug!("Creating record with rkey");
+        self.pds.ensure_writable(&self.access_token, &self.did)?;
+        self.pds.validate_record(collection, value)?;
+
+        let uri = AtUri::from_parts(self.did.clone(), collection.clone(), rkey.clone());
+        if self.pds.store().get_record(&uri).await.is_ok() {
+            return Err(Error::Protocol(ProtocolError::new(
+                400,
+                Some("InvalidRequest".to_string()),
+                Some(format!("Record {} already exists", uri)),
+            )));
+        }
+
+        self.pds
+            .store()
+            .create_record(&self.did, collection, value, Some(rkey.as_str()))
+            .await
+    }
+
+    /// Change the account's handle.
+    ///
+    /// Emits a handle event on the firehose. Fails if another local account
+    /// already uses the handle. The PDS's user domains are not enforced, so
+    /// an account can move to a handle under its own domain.
+    #[instrument(skip(self), fields(did = %self.did))]
+    pub fn update_handle(&self, handle: &str) -> Result<()> {
+        self.pds.validate_token(&self.access_token)?;
+        let handle = Handle::new(handle)?;
+        self.pds.store().update_handle(&self.did, handle.as_str())
+    }
 }
 
 #[async_trait]
@@ -52,21 +116,27 @@ impl SessionTrait for FileSession {
     }
 
     #[instrument(skip(self), fields(did = %self.did, %collection))]
-    async fn list_records(
+    async fn list_records_with(
         &self,
         repo: &Did,
         collection: &Nsid,
-        limit: Option<u32>,
-        cursor: Option<&str>,
+        options: &ListRecordsOptions,
     ) -> Result<ListRecordsOutput> {
         debug!("Listing records");
         self.pds.ensure_repo_access(&self.access_token, repo)?;
         self.pds
             .store()
-            .list_records(repo, collection, limit, cursor)
+            .list_records(repo, collection, options)
             .await
     }
 
+    #[instrument(skip(self), fields(did = %self.did, %repo))]
+    async fn list_collections(&self, repo: &Did) -> Result<Vec<Nsid>> {
+        debug!("Listing collections");
+        self.pds.ensure_repo_access(&self.access_token, repo)?;
+        self.pds.store().list_collections(repo).await
+    }
+
     #[instrument(skip(self), fields(did = %self.did, %uri))]
     async fn get_record(&self, uri: &AtUri) -> Result<Record> {
         debug!("Getting record");
@@ -75,21 +145,208 @@ impl SessionTrait for FileSession {
         self.pds.store().get_record(uri).await
     }
 
+    /// Earlier versions are read from the firehose log.
+    #[instrument(skip(self), fields(did = %self.did, %uri))]
+    async fn get_record_at(&self, uri: &AtUri, cid: &str) -> Result<Record> {
+        debug!("Getting record version");
+        self.pds
+            .ensure_repo_access(&self.access_token, uri.repo())?;
+        self.pds.get_record_at(uri, cid).await
+    }
+
     #[instrument(skip(self, value), fields(did = %self.did, %collection))]
     async fn create_record(&self, collection: &Nsid, value: &RecordValue) -> Result<AtUri> {
         debug!("Creating record");
-        self.pds.ensure_repo_access(&self.access_token, &self.did)?;
+        self.pds.ensure_writable(&self.access_token, &self.did)?;
+        self.pds.validate_record(collection, value)?;
         self.pds
             .store()
             .create_record(&self.did, collection, value, None)
             .await
     }
 
+    /// Overwrites appear on the firehose as `update` events and new keys as
+    /// `create` events.
+    #[instrument(skip(self, value), fields(did = %self.did, %collection, %rkey))]
+    async fn put_record(
+        &self,
+        collection: &Nsid,
+        rkey: &Rkey,
+        value: &RecordValue,
+        swap_cid: Option<&str>,
+    ) -> Result<AtUri> {
+        debug!("Putting record");
+        self.pds.ensure_writable(&self.access_token, &self.did)?;
+        self.pds.validate_record(collection, value)?;
+
+        self.pds
+            .store()
+            .put_record(&self.did, collection, rkey, value, swap_cid)
+            .await
+    }
+
     #[instrument(skip(self), fields(did = %self.did, %uri))]
     async fn delete_record(&self, uri: &AtUri) -> Result<()> {
         debug!("Deleting record");
-        self.pds
-            .ensure_repo_access(&self.access_token, uri.repo())?;
+        self.pds.ensure_writable(&self.access_token, uri.repo())?;
         self.pds.store().delete_record(uri).await
     }
+
+    /// On a SQLite store, records are written in transactions of up to 200;
+    /// a failed transaction writes none of its records, and each of them
+    /// reports the error. A record failing validation fails its
+    /// transaction, as it fails an `applyWrites` call on a network PDS.
+    #[cfg(feature = "sqlite")]
+    #[instrument(skip(self, values), fields(did = %self.did, %collection, count = values.len()))]
+    async fn create_records_bulk(
+        &self,
+        collection: &Nsid,
+        values: Vec<RecordValue>,
+        concurrency: usize,
+        cancel: Option<&CancellationToken>,
+    ) -> BulkReport<RecordValue, AtUri> {
+        if !self.pds.store().is_transactional() {
+            return create_records_pipelined(self, collection, values, concurrency, cancel).await;
+        }
+        debug!("Creating records in bulk");
+
+        let mut results = Vec::with_capacity(values.len());
+        for chunk in values.chunks(BULK_TRANSACTION_MAX) {
+            if cancel.is_some_and(CancellationToken::is_cancelled) {
+                break;
+            }
+            let checked = self
+                .pds
+                .ensure_writable(&self.access_token, &self.did)
+                .and_then(|()| {
+                    chunk
+                        .iter()
+                        .try_for_each(|value| self.pds.validate_record(collection, value))
+                });
+            let written = match checked {
+                Ok(()) => {
+                    self.pds
+                        .store()
+                        .create_records(&self.did, collection, chunk)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            match written {
+                Ok(uris) => results.extend(uris.into_iter().map(Ok)),
+                Err(e) => results.extend(std::iter::repeat_n(Err(e), chunk.len())),
+            }
+        }
+        BulkReport::from_results(values, results)
+    }
+
+    /// Writes the token to the outbox; see
+    /// [`FilePds::sent_mail`](crate::FilePds::sent_mail).
+    #[instrument(skip(self), fields(did = %self.did))]
+    async fn request_email_confirmation(&self) -> Result<()> {
+        let account = self.pds.validate_token(&self.access_token)?;
+        let email = account.email.ok_or_else(|| {
+            Error::InvalidInput(InvalidInputError::Other {
+                message: "account has no email address".to_string(),
+            })
+        })?;
+        self.pds
+            .send_token(&self.did, &email, TokenPurpose::ConfirmEmail)
+    }
+
+    #[instrument(skip(self, email, token), fields(did = %self.did))]
+    async fn confirm_email(&self, email: &str, token: &str) -> Result<()> {
+        let account = self.pds.validate_token(&self.access_token)?;
+        let matches_account = account
+            .email
+            .as_deref()
+            .is_some_and(|address| address.eq_ignore_ascii_case(email.trim()));
+        if !matches_account {
+            return Err(Error::Protocol(ProtocolError::new(
+                400,
+                Some("InvalidEmail".to_string()),
+                Some("Email does not match the account's address".to_string()),
+            )));
+        }
+
+        let sent = account
+            .email_token
+            .filter(|sent| sent.matches(TokenPurpose::ConfirmEmail, token))
+            .ok_or_else(invalid_token)?;
+        sent.check_expiry()?;
+
+        self.pds.store().update_account(&self.did, |account| {
+            account.email_confirmed = true;
+            account.email_token = None;
+        })?;
+        debug!("Confirmed email");
+        Ok(())
+    }
+
+    /// The file-backed PDS keeps no commit blocks or private state, so
+    /// `repo_commit` is unset and blocks are counted as records. Its blob
+    /// store is shared by every account, so the blob counts cover all of
+    /// them, and all are imported.
+    #[instrument(skip(self), fields(did = %self.did))]
+    async fn check_account_status(&self) -> Result<AccountStatus> {
+        let account = self.pds.validate_token(&self.access_token)?;
+        let records = self.pds.store().count_records(&self.did)?;
+
+        let mut blobs = 0;
+        let mut cursor = None;
+        loop {
+            let page = self
+                .pds
+                .blob_store()
+                .list_blobs(None, cursor.as_deref())
+                .await?;
+            blobs += page.cids.len() as u64;
+            cursor = page.cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        Ok(AccountStatus {
+            activated: account.deactivated_at.is_none(),
+            valid_did: true,
+            repo_commit: None,
+            repo_rev: self.pds.store().repo_rev(&self.did)?,
+            repo_blocks: records,
+            indexed_records: records,
+            private_state_values: 0,
+            expected_blobs: blobs,
+            imported_blobs: blobs,
+        })
+    }
+
+    #[instrument(skip(self), fields(did = %self.did))]
+    async fn activate_account(&self) -> Result<()> {
+        self.pds.validate_token(&self.access_token)?;
+        self.pds.store().update_account(&self.did, |account| {
+            account.deactivated_at = None;
+            account.delete_after = None;
+        })?;
+        debug!("Activated account");
+        Ok(())
+    }
+
+    /// The account is never deleted automatically; `delete_after` is only
+    /// recorded.
+    #[instrument(skip(self), fields(did = %self.did))]
+    async fn deactivate_account(&self, delete_after: Option<DateTime<Utc>>) -> Result<()> {
+        self.pds.validate_token(&self.access_token)?;
+        self.pds.store().update_account(&self.did, |account| {
+            account
+                .deactivated_at
+                .get_or_insert_with(|| Utc::now().to_rfc3339());
+            account.delete_after = delete_after.map(|time| time.to_rfc3339());
+        })?;
+        debug!("Deactivated account");
+        Ok(())
+    }
+
+    fn blobs(&self) -> &dyn BlobStore {
+        self.pds.blob_store()
+    }
 }
@@ -0,0 +1,187 @@
+//! File-backed [`SessionStore`].
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+
+use muat_core::Result;
+use muat_core::error::{Error, InvalidInputError};
+use muat_core::traits::{SessionStore, StoredSession};
+
+use crate::store::map_io;
+
+/// A [`SessionStore`] that keeps sessions in a JSON file, readable only
+/// by the current user on Unix.
+///
+/// Each save rewrites the whole file atomically. Processes sharing a file
+/// do not lock it, so concurrent saves can lose one another's changes.
+#[derive(Debug, Clone)]
+pub struct FileSessionStore {
+    path: PathBuf,
+}
+
+impl FileSessionStore {
+    /// Keep sessions in the file at `path`, created on first save.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Keep sessions in `sessions.json` under the XDG data directory for
+    /// `app`: `$XDG_DATA_HOME/<app>`, or `~/.local/share/<app>`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither `XDG_DATA_HOME` nor `HOME` is set.
+    pub fn for_app(app: &str) -> Result<Self> {
+        let data_home = std::env::var_os("XDG_DATA_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME")
+                    .map(|home| PathBuf::from(home).join(".local").join("share"))
+            })
+            .ok_or_else(|| {
+                Error::InvalidInput(InvalidInputError::Other {
+                    message: "cannot find a data directory: set XDG_DATA_HOME or HOME".to_string(),
+                })
+            })?;
+
+        Ok(Self::new(data_home.join(app).join("sessions.json")))
+    }
+
+    /// Returns the path of the sessions file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, StoredSession>> {
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(BTreeMap::new());
+            }
+            Err(e) => return Err(map_io(e)),
+        };
+
+        // The error position is enough to find the problem; serde's
+        // message could quote a token.
+        serde_json::from_str(&content).map_err(|e| {
+            Error::InvalidInput(InvalidInputError::Other {
+                message: format!(
+                    "invalid session file {} at line {}, column {}",
+                    self.path.display(),
+                    e.line(),
+                    e.column()
+                ),
+            })
+        })
+    }
+
+    fn write(&self, sessions: &BTreeMap<String, StoredSession>) -> Result<()> {
+        let json = serde_json::to_vec_pretty(sessions).map_err(|e| {
+            Error::InvalidInput(InvalidInputError::Other {
+                message: e.to_string(),
+            })
+        })?;
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(map_io)?;
+        }
+
+        // Replace the file in one step, so it is never half-written.
+        let tmp_path = self.path.with_extension("json.tmp");
+        let _ = fs::remove_file(&tmp_path);
+        write_private(&tmp_path, &json).map_err(map_io)?;
+        fs::rename(&tmp_path, &self.path).map_err(map_io)
+    }
+}
+
+#[async_trait]
+impl SessionStore for FileSessionStore {
+    async fn load(&self, key: &str) -> Result<Option<StoredSession>> {
+        Ok(self.read()?.remove(key))
+    }
+
+    async fn save(&self, key: &str, session: &StoredSession) -> Result<()> {
+        let mut sessions = self.read()?;
+        sessions.insert(key.to_string(), session.clone());
+        self.write(&sessions)
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        let mut sessions = self.read()?;
+        if sessions.remove(key).is_some() {
+            self.write(&sessions)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write a new file readable only by the current user (Unix only).
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use muat_core::types::{Did, PdsUrl};
+    use muat_core::{AccessToken, RefreshToken};
+
+    fn stored(access: &str) -> StoredSession {
+        StoredSession {
+            pds: PdsUrl::new("https://pds.example.com").unwrap(),
+            did: Did::new("did:plc:test234aaaaaaaaaaaaaaaaa").unwrap(),
+            access_token: AccessToken::new(access),
+            refresh_token: Some(RefreshToken::new("refresh")),
+        }
+    }
+
+    #[tokio::test]
+    async fn file_store_round_trips_sessions() {
+        let temp = tempfile::tempdir().unwrap();
+        let store = FileSessionStore::new(temp.path().join("app").join("sessions.json"));
+        assert!(store.load("a").await.unwrap().is_none());
+
+        store.save("a", &stored("access-a")).await.unwrap();
+        store.save("b", &stored("access-b")).await.unwrap();
+        store.remove("b").await.unwrap();
+        store.remove("missing").await.unwrap();
+
+        let reopened = FileSessionStore::new(store.path());
+        let session = reopened.load("a").await.unwrap().unwrap();
+        assert_eq!(session.access_token.as_str(), "access-a");
+        assert_eq!(session.refresh_token.unwrap().as_str(), "refresh");
+        assert!(reopened.load("b").await.unwrap().is_none());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(store.path())
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[tokio::test]
+    async fn file_store_errors_do_not_quote_tokens() {
+        let temp = tempfile::tempdir().unwrap();
+        let path = temp.path().join("sessions.json");
+        std::fs::write(&path, r#"{"a": {"access_token": "secret-token"}}"#).unwrap();
+
+        let err = FileSessionStore::new(&path).load("a").await.unwrap_err();
+        assert!(!err.to_string().contains("secret-token"), "{err}");
+    }
+}
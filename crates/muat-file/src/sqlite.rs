@@ -0,0 +1,932 @@
+//! SQLite storage for the file-backed PDS.
+//!
+//! Holds the same accounts, records and firehose log as
+//! [`FileStore`](crate::store::FileStore), in one database file. A write and
+//! its firehose event commit in one transaction, so several records can be
+//! written atomically, and firehose events are numbered by an increasing
+//! sequence rather than by time.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use chrono::Utc;
+use rusqlite::{Connection, OptionalExtension, Transaction, TransactionBehavior, params};
+use tracing::{debug, instrument};
+use uuid::Uuid;
+
+use muat_core::Result;
+use muat_core::error::{Error, InvalidInputError, ProtocolError, TransportError};
+use muat_core::repo::{
+    KeyOrder, ListRecordsOptions, ListRecordsOutput, Record, RecordOrder, RecordValue,
+};
+use muat_core::types::{AtUri, Did, Nsid, Rkey, Tid, TidGenerator, base32_lower};
+
+use crate::password::PasswordAlgorithm;
+use crate::store::{
+    CompactionStats, FirehoseLogEvent, FirehoseLogOp, LocalAccount, RecordVersion, history_page,
+    history_record, map_io, next_rev_after, page_output, record_cid, record_json, record_versions,
+    replay_collection,
+};
+
+/// Database file name, in the PDS data directory. SQLite keeps its
+/// write-ahead log beside it, in files with the same prefix.
+pub(crate) const DATABASE_FILE: &str = "pds.sqlite";
+
+/// Most firehose events read in one query.
+pub(crate) const FIREHOSE_BATCH: usize = 1000;
+
+/// How long a write waits for another connection's write to finish.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS accounts (
+        did TEXT PRIMARY KEY,
+        handle TEXT NOT NULL,
+        account TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS accounts_handle ON accounts (handle);
+
+    CREATE TABLE IF NOT EXISTS records (
+        did TEXT NOT NULL,
+        collection TEXT NOT NULL,
+        rkey TEXT NOT NULL,
+        content TEXT NOT NULL,
+        PRIMARY KEY (did, collection, rkey)
+    ) WITHOUT ROWID;
+
+    CREATE TABLE IF NOT EXISTS repos (
+        did TEXT PRIMARY KEY,
+        rev TEXT NOT NULL
+    );
+
+    CREATE TABLE IF NOT EXISTS firehose (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        uri TEXT NOT NULL,
+        event TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS firehose_uri ON firehose (uri, seq);
+";
+
+fn map_sqlite(err: rusqlite::Error) -> Error {
+    Error::Transport(TransportError::Http {
+        message: format!("SQLite error: {}", err),
+    })
+}
+
+fn map_json(err: serde_json::Error) -> Error {
+    Error::InvalidInput(InvalidInputError::Other {
+        message: err.to_string(),
+    })
+}
+
+fn account_not_found(did: &Did) -> Error {
+    Error::Protocol(ProtocolError::new(
+        404,
+        Some("AccountNotFound".to_string()),
+        Some(format!("Account {} not found", did)),
+    ))
+}
+
+/// Read an account row.
+fn read_account(conn: &Connection, did: &Did) -> Result<Option<LocalAccount>> {
+    let content: Option<String> = conn
+        .query_row(
+            "SELECT account FROM accounts WHERE did = ?1",
+            [did.as_str()],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(map_sqlite)?;
+
+    content
+        .map(|content| serde_json::from_str(&content).map_err(map_json))
+        .transpose()
+}
+
+/// Replace an account row.
+fn write_account(conn: &Connection, account: &LocalAccount) -> Result<()> {
+    let content = serde_json::to_string(account).map_err(map_json)?;
+    conn.execute(
+        "INSERT INTO accounts (did, handle, account) VALUES (?1, ?2, ?3)
+         ON CONFLICT (did) DO UPDATE SET handle = excluded.handle, account = excluded.account",
+        params![account.did, account.handle, content],
+    )
+    .map_err(map_sqlite)?;
+    Ok(())
+}
+
+/// Read a record's stored JSON.
+fn read_record(
+    conn: &Connection,
+    repo: &Did,
+    collection: &Nsid,
+    rkey: &str,
+) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT content FROM records WHERE did = ?1 AND collection = ?2 AND rkey = ?3",
+        params![repo.as_str(), collection.as_str(), rkey],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(map_sqlite)
+}
+
+/// Insert or replace a record's stored JSON.
+fn write_record(
+    conn: &Connection,
+    repo: &Did,
+    collection: &Nsid,
+    rkey: &str,
+    content: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO records (did, collection, rkey, content) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (did, collection, rkey) DO UPDATE SET content = excluded.content",
+        params![repo.as_str(), collection.as_str(), rkey, content],
+    )
+    .map_err(map_sqlite)?;
+    Ok(())
+}
+
+/// Build a record from its stored JSON.
+fn record_from_content(uri: AtUri, content: &str) -> Result<Record> {
+    let value: RecordValue = serde_json::from_str(content).map_err(map_json)?;
+    Ok(Record {
+        uri,
+        cid: record_cid(content),
+        value,
+    })
+}
+
+/// Parse a firehose row, numbering the event by its row.
+fn firehose_event(seq: i64, content: &str) -> Option<FirehoseLogEvent> {
+    let mut event: FirehoseLogEvent = serde_json::from_str(content).ok()?;
+    event.seq = Some(seq);
+    Some(event)
+}
+
+/// Size of the database, in bytes.
+fn database_size(conn: &Connection) -> Result<u64> {
+    let pages: i64 = conn
+        .pragma_query_value(None, "page_count", |row| row.get(0))
+        .map_err(map_sqlite)?;
+    let page_size: i64 = conn
+        .pragma_query_value(None, "page_size", |row| row.get(0))
+        .map_err(map_sqlite)?;
+    Ok((pages * page_size).max(0) as u64)
+}
+
+/// SQLite-backed storage for a local PDS.
+///
+/// The database is `pds/pds.sqlite` under the root, opened on first use.
+/// Clones share one connection; other processes may open the same database.
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    root: PathBuf,
+    key_order: KeyOrder,
+    tids: TidGenerator,
+    connection: Arc<Mutex<Option<Connection>>>,
+}
+
+impl SqliteStore {
+    /// Create a SQLite store at the given root directory.
+    pub fn new(root: impl AsRef<Path>) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            key_order: KeyOrder::default(),
+            tids: TidGenerator::new(),
+            connection: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Set the order keys are written in for newly written records.
+    pub fn with_key_order(mut self, key_order: KeyOrder) -> Self {
+        self.key_order = key_order;
+        self
+    }
+
+    /// Returns the order keys are written in.
+    pub fn key_order(&self) -> KeyOrder {
+        self.key_order
+    }
+
+    /// Get the root directory path.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Get the database path.
+    pub(crate) fn database_path(&self) -> PathBuf {
+        self.root.join("pds").join(DATABASE_FILE)
+    }
+
+    fn open(&self) -> Result<Connection> {
+        let path = self.database_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(map_io)?;
+        }
+
+        let conn = Connection::open(&path).map_err(map_sqlite)?;
+        conn.busy_timeout(BUSY_TIMEOUT).map_err(map_sqlite)?;
+        // The write-ahead log lets readers, such as firehose tails in other
+        // processes, run alongside a writer.
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))
+            .map_err(map_sqlite)?;
+        conn.execute_batch(SCHEMA).map_err(map_sqlite)?;
+
+        debug!(path = %path.display(), "Opened SQLite store");
+        Ok(conn)
+    }
+
+    /// Run `f` on the shared connection, opening it if needed.
+    fn with_connection<T>(&self, f: impl FnOnce(&mut Connection) -> Result<T>) -> Result<T> {
+        // A panic mid-transaction rolls it back, so the connection is still usable.
+        let mut guard = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let conn = match guard.take() {
+            Some(conn) => conn,
+            None => self.open()?,
+        };
+        f(guard.insert(conn))
+    }
+
+    /// Run `f` in a write transaction, committed if it succeeds.
+    fn write<T>(&self, f: impl FnOnce(&Transaction) -> Result<T>) -> Result<T> {
+        self.with_connection(|conn| {
+            // Take the write lock up front, so revisions read inside are current.
+            let tx = conn
+                .transaction_with_behavior(TransactionBehavior::Immediate)
+                .map_err(map_sqlite)?;
+            let output = f(&tx)?;
+            tx.commit().map_err(map_sqlite)?;
+            Ok(output)
+        })
+    }
+
+    /// Advance a repo's commit revision, returning the new revision and the
+    /// previous one.
+    fn next_rev(&self, tx: &Transaction, repo: &Did) -> Result<(Tid, Option<Tid>)> {
+        let since: Option<String> = tx
+            .query_row(
+                "SELECT rev FROM repos WHERE did = ?1",
+                [repo.as_str()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(map_sqlite)?;
+        let since = since.and_then(|rev| Tid::new(rev).ok());
+
+        let rev = next_rev_after(&self.tids, since.as_ref());
+        tx.execute(
+            "INSERT INTO repos (did, rev) VALUES (?1, ?2)
+             ON CONFLICT (did) DO UPDATE SET rev = excluded.rev",
+            params![repo.as_str(), rev.as_str()],
+        )
+        .map_err(map_sqlite)?;
+
+        Ok((rev, since))
+    }
+
+    /// Append an event to the firehose log.
+    ///
+    /// Record operations also advance the repo's revision.
+    fn append_firehose(
+        &self,
+        tx: &Transaction,
+        uri: &str,
+        op: FirehoseLogOp,
+        handle: Option<&str>,
+        record: Option<&RecordValue>,
+    ) -> Result<()> {
+        let (rev, since) = match op {
+            FirehoseLogOp::Create | FirehoseLogOp::Update | FirehoseLogOp::Delete => {
+                let uri = AtUri::new(uri)?;
+                let (rev, since) = self.next_rev(tx, uri.repo())?;
+                (Some(rev.into()), since.map(String::from))
+            }
+            _ => (None, None),
+        };
+
+        let event = FirehoseLogEvent {
+            uri: uri.to_string(),
+            time: Utc::now().to_rfc3339(),
+            op,
+            handle: handle.map(str::to_string),
+            record: record.map(|value| value.as_value().clone()),
+            rev,
+            since,
+            seq: None,
+        };
+        let content = serde_json::to_string(&event).map_err(map_json)?;
+
+        tx.execute(
+            "INSERT INTO firehose (uri, event) VALUES (?1, ?2)",
+            params![uri, content],
+        )
+        .map_err(map_sqlite)?;
+        Ok(())
+    }
+
+    /// Write a new record and log its creation.
+    fn insert_record(
+        &self,
+        tx: &Transaction,
+        repo: &Did,
+        collection: &Nsid,
+        value: &RecordValue,
+        rkey: Option<&str>,
+    ) -> Result<AtUri> {
+        let rkey = match rkey {
+            Some(rkey) => Rkey::new(rkey)?,
+            None => Rkey::new(String::from(self.tids.next_tid()))?,
+        };
+        let content = record_json(value, self.key_order)?;
+
+        write_record(tx, repo, collection, rkey.as_str(), &content)?;
+
+        let uri = AtUri::from_parts(repo.clone(), collection.clone(), rkey);
+        self.append_firehose(
+            tx,
+            &uri.to_string(),
+            FirehoseLogOp::Create,
+            None,
+            Some(value),
+        )?;
+        Ok(uri)
+    }
+
+    // ========================================================================
+    // Account Management
+    // ========================================================================
+
+    #[instrument(skip(self, email, password_hash))]
+    pub fn create_account(
+        &self,
+        handle: &str,
+        email: Option<&str>,
+        password_hash: &str,
+        password_algorithm: PasswordAlgorithm,
+    ) -> Result<Did> {
+        // did:plc identifiers are 24 characters of lowercase base32.
+        let id = base32_lower(Uuid::new_v4().as_bytes());
+        let did = Did::new(format!("did:plc:{}", &id[..24]))?;
+
+        let account = LocalAccount {
+            did: did.to_string(),
+            handle: handle.to_string(),
+            created_at: Utc::now().to_rfc3339(),
+            password_hash: password_hash.to_string(),
+            password_algorithm,
+            email: email.map(str::to_string),
+            email_confirmed: false,
+            email_token: None,
+            deactivated_at: None,
+            delete_after: None,
+        };
+
+        self.write(|tx| {
+            write_account(tx, &account)?;
+            self.append_firehose(
+                tx,
+                &format!("at://{}", did),
+                FirehoseLogOp::AccountCreate,
+                Some(handle),
+                None,
+            )
+        })?;
+
+        debug!(did = %did, handle = %handle, "Created local account");
+
+        Ok(did)
+    }
+
+    pub fn get_account(&self, did: &Did) -> Result<Option<LocalAccount>> {
+        self.with_connection(|conn| read_account(conn, did))
+    }
+
+    #[instrument(skip(self))]
+    pub fn remove_account(&self, did: &Did, delete_records: bool) -> Result<()> {
+        self.write(|tx| {
+            let removed = tx
+                .execute("DELETE FROM accounts WHERE did = ?1", [did.as_str()])
+                .map_err(map_sqlite)?;
+            if removed == 0 {
+                return Err(account_not_found(did));
+            }
+
+            self.append_firehose(
+                tx,
+                &format!("at://{}", did),
+                FirehoseLogOp::AccountDelete,
+                None,
+                None,
+            )?;
+
+            if delete_records {
+                tx.execute("DELETE FROM records WHERE did = ?1", [did.as_str()])
+                    .map_err(map_sqlite)?;
+                tx.execute("DELETE FROM repos WHERE did = ?1", [did.as_str()])
+                    .map_err(map_sqlite)?;
+            }
+            Ok(())
+        })?;
+
+        debug!(did = %did, "Removed local account");
+
+        Ok(())
+    }
+
+    #[instrument(skip(self, password_hash))]
+    pub fn update_password_hash(
+        &self,
+        did: &Did,
+        password_hash: &str,
+        password_algorithm: PasswordAlgorithm,
+    ) -> Result<()> {
+        self.write(|tx| {
+            let mut account = read_account(tx, did)?.ok_or_else(|| account_not_found(did))?;
+            account.password_hash = password_hash.to_string();
+            account.password_algorithm = password_algorithm;
+            write_account(tx, &account)
+        })
+    }
+
+    /// Change an account's email and password fields in place.
+    #[instrument(skip(self, update))]
+    pub fn update_account(&self, did: &Did, update: impl FnOnce(&mut LocalAccount)) -> Result<()> {
+        self.write(|tx| {
+            let mut account = read_account(tx, did)?.ok_or_else(|| account_not_found(did))?;
+            update(&mut account);
+            write_account(tx, &account)
+        })
+    }
+
+    #[instrument(skip(self))]
+    pub fn update_handle(&self, did: &Did, handle: &str) -> Result<()> {
+        let changed = self.write(|tx| {
+            let mut account = read_account(tx, did)?.ok_or_else(|| account_not_found(did))?;
+            if account.handle == handle {
+                return Ok(false);
+            }
+
+            let taken = tx
+                .query_row("SELECT 1 FROM accounts WHERE handle = ?1", [handle], |_| {
+                    Ok(())
+                })
+                .optional()
+                .map_err(map_sqlite)?
+                .is_some();
+            if taken {
+                return Err(Error::Protocol(ProtocolError::new(
+                    400,
+                    Some("HandleNotAvailable".to_string()),
+                    Some(format!("Handle {} is already taken", handle)),
+                )));
+            }
+
+            account.handle = handle.to_string();
+            write_account(tx, &account)?;
+            self.append_firehose(
+                tx,
+                &format!("at://{}", did),
+                FirehoseLogOp::HandleChange,
+                Some(handle),
+                None,
+            )?;
+            Ok(true)
+        })?;
+
+        if changed {
+            debug!(did = %did, handle = %handle, "Updated local account handle");
+        }
+
+        Ok(())
+    }
+
+    /// The revision of a repo's latest commit, if it has any.
+    pub fn repo_rev(&self, did: &Did) -> Result<Option<String>> {
+        self.with_connection(|conn| {
+            conn.query_row(
+                "SELECT rev FROM repos WHERE did = ?1",
+                [did.as_str()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(map_sqlite)
+        })
+    }
+
+    /// Count the records in a repo across its collections.
+    pub fn count_records(&self, did: &Did) -> Result<u64> {
+        self.with_connection(|conn| {
+            conn.query_row(
+                "SELECT COUNT(*) FROM records WHERE did = ?1",
+                [did.as_str()],
+                |row| row.get(0),
+            )
+            .map_err(map_sqlite)
+        })
+    }
+
+    pub fn list_accounts(&self) -> Result<Vec<LocalAccount>> {
+        self.with_connection(|conn| {
+            let mut statement = conn
+                .prepare("SELECT account FROM accounts ORDER BY did")
+                .map_err(map_sqlite)?;
+            let rows = statement
+                .query_map([], |row| row.get::<_, String>(0))
+                .map_err(map_sqlite)?;
+
+            let mut accounts = Vec::new();
+            for content in rows {
+                let content = content.map_err(map_sqlite)?;
+                if let Ok(account) = serde_json::from_str::<LocalAccount>(&content) {
+                    accounts.push(account);
+                }
+            }
+            Ok(accounts)
+        })
+    }
+
+    pub fn find_account_by_handle(&self, handle: &str) -> Result<Option<LocalAccount>> {
+        self.with_connection(|conn| {
+            let content: Option<String> = conn
+                .query_row(
+                    "SELECT account FROM accounts WHERE handle = ?1 LIMIT 1",
+                    [handle],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(map_sqlite)?;
+
+            content
+                .map(|content| serde_json::from_str(&content).map_err(map_json))
+                .transpose()
+        })
+    }
+
+    // ========================================================================
+    // Record Operations
+    // ========================================================================
+
+    #[instrument(skip(self, value))]
+    pub async fn create_record(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        value: &RecordValue,
+        rkey: Option<&str>,
+    ) -> Result<AtUri> {
+        let uri = self.write(|tx| self.insert_record(tx, repo, collection, value, rkey))?;
+
+        debug!(uri = %uri, "Created record");
+
+        Ok(uri)
+    }
+
+    /// Create several records with generated keys in one transaction: either
+    /// all of them are written, or none.
+    #[instrument(skip(self, values), fields(count = values.len()))]
+    pub async fn create_records(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        values: &[RecordValue],
+    ) -> Result<Vec<AtUri>> {
+        let uris = self.write(|tx| {
+            values
+                .iter()
+                .map(|value| self.insert_record(tx, repo, collection, value, None))
+                .collect::<Result<Vec<_>>>()
+        })?;
+
+        debug!(count = uris.len(), "Created records");
+
+        Ok(uris)
+    }
+
+    /// Write a record at a fixed key, overwriting any existing record.
+    ///
+    /// Logs an update if the key was already in use, otherwise a create.
+    #[instrument(skip(self, value))]
+    pub async fn put_record(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        rkey: &Rkey,
+        value: &RecordValue,
+        swap_cid: Option<&str>,
+    ) -> Result<AtUri> {
+        let uri = AtUri::from_parts(repo.clone(), collection.clone(), rkey.clone());
+        let content = record_json(value, self.key_order)?;
+
+        let op = self.write(|tx| {
+            let existing = read_record(tx, repo, collection, rkey.as_str())?;
+
+            if let Some(swap_cid) = swap_cid {
+                let current = existing.as_deref().map(record_cid);
+                if current.as_deref() != Some(swap_cid) {
+                    return Err(Error::Protocol(ProtocolError::new(
+                        400,
+                        Some("InvalidSwap".to_string()),
+                        Some(format!(
+                            "Record was at {}",
+                            current.as_deref().unwrap_or("null")
+                        )),
+                    )));
+                }
+            }
+
+            write_record(tx, repo, collection, rkey.as_str(), &content)?;
+
+            let op = if existing.is_some() {
+                FirehoseLogOp::Update
+            } else {
+                FirehoseLogOp::Create
+            };
+            self.append_firehose(tx, &uri.to_string(), op, None, Some(value))?;
+            Ok(op)
+        })?;
+
+        debug!(uri = %uri, ?op, "Put record");
+
+        Ok(uri)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get_record(&self, uri: &AtUri) -> Result<Record> {
+        let (collection, rkey) = uri.record_path()?;
+        let content =
+            self.with_connection(|conn| read_record(conn, uri.repo(), collection, rkey.as_str()))?;
+
+        let Some(content) = content else {
+            return Err(Error::Protocol(ProtocolError::new(
+                404,
+                Some("RecordNotFound".to_string()),
+                Some(format!("Record {} not found", uri)),
+            )));
+        };
+
+        record_from_content(uri.clone(), &content)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn list_collections(&self, repo: &Did) -> Result<Vec<Nsid>> {
+        let names = self.with_connection(|conn| {
+            let mut statement = conn
+                .prepare(
+                    "SELECT DISTINCT collection FROM records WHERE did = ?1 ORDER BY collection",
+                )
+                .map_err(map_sqlite)?;
+            let rows = statement
+                .query_map(params![repo.as_str()], |row| row.get::<_, String>(0))
+                .map_err(map_sqlite)?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()
+                .map_err(map_sqlite)
+        })?;
+        Ok(names
+            .iter()
+            .filter_map(|name| Nsid::new(name).ok())
+            .collect())
+    }
+
+    /// List a page of records. Paging runs in the database, so only the
+    /// records on the page are read.
+    #[instrument(skip(self))]
+    pub async fn list_records(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        options: &ListRecordsOptions,
+    ) -> Result<ListRecordsOutput> {
+        let limit = options.get_limit().unwrap_or(50) as usize;
+        // The cursor is the last rkey of the previous page; resume after it
+        // in the requested order.
+        let sql = match options.get_order() {
+            RecordOrder::Descending => {
+                "SELECT rkey, content FROM records
+                 WHERE did = ?1 AND collection = ?2 AND (?3 IS NULL OR rkey < ?3)
+                 ORDER BY rkey DESC"
+            }
+            _ => {
+                "SELECT rkey, content FROM records
+                 WHERE did = ?1 AND collection = ?2 AND (?3 IS NULL OR rkey > ?3)
+                 ORDER BY rkey ASC"
+            }
+        };
+
+        let records = self.with_connection(|conn| {
+            let mut statement = conn.prepare(sql).map_err(map_sqlite)?;
+            let mut rows = statement
+                .query(params![
+                    repo.as_str(),
+                    collection.as_str(),
+                    options.get_cursor()
+                ])
+                .map_err(map_sqlite)?;
+
+            let mut records = Vec::new();
+            while records.len() < limit
+                && let Some(row) = rows.next().map_err(map_sqlite)?
+            {
+                let rkey: String = row.get(0).map_err(map_sqlite)?;
+                if !options.in_window(&rkey) {
+                    continue;
+                }
+                let Ok(rkey) = Rkey::new(&rkey) else {
+                    continue;
+                };
+                let content: String = row.get(1).map_err(map_sqlite)?;
+
+                let uri = AtUri::from_parts(repo.clone(), collection.clone(), rkey);
+                if let Ok(record) = record_from_content(uri, &content) {
+                    records.push(record);
+                }
+            }
+            Ok(records)
+        })?;
+
+        Ok(page_output(records, options))
+    }
+
+    #[instrument(skip(self))]
+    pub async fn delete_record(&self, uri: &AtUri) -> Result<()> {
+        let (collection, rkey) = uri.record_path()?;
+
+        let removed = self.write(|tx| {
+            let removed = tx
+                .execute(
+                    "DELETE FROM records WHERE did = ?1 AND collection = ?2 AND rkey = ?3",
+                    params![uri.repo().as_str(), collection.as_str(), rkey.as_str()],
+                )
+                .map_err(map_sqlite)?;
+            if removed > 0 {
+                self.append_firehose(tx, &uri.to_string(), FirehoseLogOp::Delete, None, None)?;
+            }
+            Ok(removed > 0)
+        })?;
+
+        if removed {
+            debug!(uri = %uri, "Deleted record");
+        }
+
+        Ok(())
+    }
+
+    // ========================================================================
+    // History
+    // ========================================================================
+
+    /// Replay the firehose log to find a collection's records as they were
+    /// after every event up to sequence number `seq`.
+    fn collection_as_of(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        seq: i64,
+    ) -> Result<std::collections::BTreeMap<String, Option<serde_json::Value>>> {
+        // Every record URI in the collection sorts between these two.
+        let first = format!("at://{}/{}/", repo, collection);
+        let last = format!("at://{}/{}0", repo, collection);
+
+        let events = self.with_connection(|conn| {
+            let mut statement = conn
+                .prepare(
+                    "SELECT seq, event FROM firehose
+                     WHERE uri >= ?1 AND uri < ?2 AND seq <= ?3
+                     ORDER BY seq",
+                )
+                .map_err(map_sqlite)?;
+            let rows = statement
+                .query_map(params![first, last, seq], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+                })
+                .map_err(map_sqlite)?;
+
+            let mut events = Vec::new();
+            for row in rows {
+                let (seq, content) = row.map_err(map_sqlite)?;
+                events.extend(firehose_event(seq, &content));
+            }
+            Ok(events)
+        })?;
+
+        Ok(replay_collection(events, repo, collection, seq))
+    }
+
+    #[instrument(skip(self))]
+    pub async fn get_record_as_of(&self, uri: &AtUri, seq: i64) -> Result<Record> {
+        let (collection, _) = uri.record_path()?;
+        let history = self.collection_as_of(uri.repo(), collection, seq)?;
+        history_record(uri, seq, history)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn list_records_as_of(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        seq: i64,
+        options: &ListRecordsOptions,
+    ) -> Result<ListRecordsOutput> {
+        let history = self.collection_as_of(repo, collection, seq)?;
+        history_page(repo, collection, history, options)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn record_history(&self, uri: &AtUri) -> Result<Vec<RecordVersion>> {
+        let events = self.with_connection(|conn| {
+            let mut statement = conn
+                .prepare("SELECT seq, event FROM firehose WHERE uri = ?1 ORDER BY seq")
+                .map_err(map_sqlite)?;
+            let rows = statement
+                .query_map(params![uri.to_string()], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+                })
+                .map_err(map_sqlite)?;
+
+            let mut events = Vec::new();
+            for row in rows {
+                let (seq, content) = row.map_err(map_sqlite)?;
+                events.extend(firehose_event(seq, &content));
+            }
+            Ok(events)
+        })?;
+
+        record_versions(uri, events)
+    }
+
+    // ========================================================================
+    // Firehose
+    // ========================================================================
+
+    /// The sequence number of the latest firehose event, or 0 if none.
+    pub(crate) fn last_seq(&self) -> Result<i64> {
+        self.with_connection(|conn| {
+            conn.query_row("SELECT COALESCE(MAX(seq), 0) FROM firehose", [], |row| {
+                row.get(0)
+            })
+            .map_err(map_sqlite)
+        })
+    }
+
+    /// Up to [`FIREHOSE_BATCH`] firehose events after sequence number
+    /// `after`, in order: each event's sequence number, the event if it can
+    /// be read, and its stored size.
+    pub(crate) fn firehose_since(
+        &self,
+        after: i64,
+    ) -> Result<Vec<(i64, Option<FirehoseLogEvent>, usize)>> {
+        self.with_connection(|conn| {
+            let mut statement = conn
+                .prepare("SELECT seq, event FROM firehose WHERE seq > ?1 ORDER BY seq LIMIT ?2")
+                .map_err(map_sqlite)?;
+            let rows = statement
+                .query_map(params![after, FIREHOSE_BATCH as i64], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+                })
+                .map_err(map_sqlite)?;
+
+            let mut events = Vec::new();
+            for row in rows {
+                let (seq, content) = row.map_err(map_sqlite)?;
+                events.push((seq, firehose_event(seq, &content), content.len()));
+            }
+            Ok(events)
+        })
+    }
+
+    // ========================================================================
+    // Maintenance
+    // ========================================================================
+
+    /// Rebuild the database file to reclaim space left by deleted records
+    /// and old revisions of updated ones.
+    ///
+    /// Records are stored uncompressed, so none are rewritten.
+    #[instrument(skip(self))]
+    pub fn compact(&self) -> Result<CompactionStats> {
+        let stats = self.with_connection(|conn| {
+            let records: i64 = conn
+                .query_row("SELECT COUNT(*) FROM records", [], |row| row.get(0))
+                .map_err(map_sqlite)?;
+            let bytes_before = database_size(conn)?;
+            conn.execute_batch("VACUUM").map_err(map_sqlite)?;
+
+            Ok(CompactionStats {
+                records: records.max(0) as usize,
+                rewritten: 0,
+                bytes_before,
+                bytes_after: database_size(conn)?,
+            })
+        })?;
+
+        debug!(?stats, "Compacted database");
+
+        Ok(stats)
+    }
+}
@@ -0,0 +1,243 @@
+//! Storage engine selection for the file-backed PDS.
+
+use std::path::Path;
+#[cfg(not(feature = "sqlite"))]
+use std::path::PathBuf;
+
+use muat_core::Result;
+use muat_core::error::{Error, InvalidInputError};
+use muat_core::repo::{KeyOrder, ListRecordsOptions, ListRecordsOutput, Record, RecordValue};
+use muat_core::types::{AtUri, Did, Nsid, Rkey};
+
+use crate::password::PasswordAlgorithm;
+#[cfg(feature = "sqlite")]
+use crate::sqlite::SqliteStore;
+use crate::store::{CompactionStats, Compression, FileStore, LocalAccount, RecordVersion};
+
+/// The store behind a [`FilePds`](crate::FilePds): a file per record, or a
+/// SQLite database.
+#[derive(Debug, Clone)]
+pub(crate) enum Storage {
+    File(FileStore),
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteStore),
+    /// A SQLite store asked for without the `sqlite` feature; every
+    /// operation fails.
+    #[cfg(not(feature = "sqlite"))]
+    SqliteUnavailable(PathBuf),
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn sqlite_unavailable() -> Error {
+    Error::InvalidInput(InvalidInputError::Other {
+        message: "file+sqlite:// PDS URLs need the `sqlite` feature of muat-file".to_string(),
+    })
+}
+
+/// Call `$call` on whichever store `$storage` holds, bound to `$store`.
+macro_rules! dispatch {
+    ($storage:expr, $store:ident => $call:expr) => {
+        match $storage {
+            Storage::File($store) => $call,
+            #[cfg(feature = "sqlite")]
+            Storage::Sqlite($store) => $call,
+            #[cfg(not(feature = "sqlite"))]
+            Storage::SqliteUnavailable(_) => Err(sqlite_unavailable()),
+        }
+    };
+}
+
+impl Storage {
+    /// A SQLite store at the given root directory.
+    pub(crate) fn sqlite(root: impl AsRef<Path>) -> Self {
+        #[cfg(feature = "sqlite")]
+        {
+            Self::Sqlite(SqliteStore::new(root))
+        }
+
+        #[cfg(not(feature = "sqlite"))]
+        {
+            Self::SqliteUnavailable(root.as_ref().to_path_buf())
+        }
+    }
+
+    /// Returns true if several records can be written in one transaction.
+    #[cfg(feature = "sqlite")]
+    pub(crate) fn is_transactional(&self) -> bool {
+        matches!(self, Self::Sqlite(_))
+    }
+
+    /// Set the compression used for newly written record files. A database
+    /// stores records uncompressed.
+    pub(crate) fn with_compression(self, compression: Compression) -> Self {
+        match self {
+            Self::File(store) => Self::File(store.with_compression(compression)),
+            other => other,
+        }
+    }
+
+    /// Set the order keys are written in for newly written records.
+    pub(crate) fn with_key_order(self, key_order: KeyOrder) -> Self {
+        match self {
+            Self::File(store) => Self::File(store.with_key_order(key_order)),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => Self::Sqlite(store.with_key_order(key_order)),
+            #[cfg(not(feature = "sqlite"))]
+            other => other,
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    pub(crate) fn key_order(&self) -> KeyOrder {
+        match self {
+            Self::File(store) => store.key_order(),
+            Self::Sqlite(store) => store.key_order(),
+        }
+    }
+
+    pub(crate) fn root(&self) -> &Path {
+        match self {
+            Self::File(store) => store.root(),
+            #[cfg(feature = "sqlite")]
+            Self::Sqlite(store) => store.root(),
+            #[cfg(not(feature = "sqlite"))]
+            Self::SqliteUnavailable(root) => root,
+        }
+    }
+
+    pub(crate) fn compact(&self) -> Result<CompactionStats> {
+        dispatch!(self, store => store.compact())
+    }
+
+    pub(crate) fn create_account(
+        &self,
+        handle: &str,
+        email: Option<&str>,
+        password_hash: &str,
+        password_algorithm: PasswordAlgorithm,
+    ) -> Result<Did> {
+        dispatch!(self, store => store.create_account(handle, email, password_hash, password_algorithm))
+    }
+
+    pub(crate) fn get_account(&self, did: &Did) -> Result<Option<LocalAccount>> {
+        dispatch!(self, store => store.get_account(did))
+    }
+
+    pub(crate) fn remove_account(&self, did: &Did, delete_records: bool) -> Result<()> {
+        dispatch!(self, store => store.remove_account(did, delete_records))
+    }
+
+    pub(crate) fn update_password_hash(
+        &self,
+        did: &Did,
+        password_hash: &str,
+        password_algorithm: PasswordAlgorithm,
+    ) -> Result<()> {
+        dispatch!(self, store => store.update_password_hash(did, password_hash, password_algorithm))
+    }
+
+    pub(crate) fn update_account(
+        &self,
+        did: &Did,
+        update: impl FnOnce(&mut LocalAccount),
+    ) -> Result<()> {
+        dispatch!(self, store => store.update_account(did, update))
+    }
+
+    pub(crate) fn update_handle(&self, did: &Did, handle: &str) -> Result<()> {
+        dispatch!(self, store => store.update_handle(did, handle))
+    }
+
+    pub(crate) fn repo_rev(&self, did: &Did) -> Result<Option<String>> {
+        dispatch!(self, store => store.repo_rev(did))
+    }
+
+    pub(crate) fn count_records(&self, did: &Did) -> Result<u64> {
+        dispatch!(self, store => store.count_records(did))
+    }
+
+    pub(crate) fn list_accounts(&self) -> Result<Vec<LocalAccount>> {
+        dispatch!(self, store => store.list_accounts())
+    }
+
+    pub(crate) fn find_account_by_handle(&self, handle: &str) -> Result<Option<LocalAccount>> {
+        dispatch!(self, store => store.find_account_by_handle(handle))
+    }
+
+    pub(crate) async fn create_record(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        value: &RecordValue,
+        rkey: Option<&str>,
+    ) -> Result<AtUri> {
+        dispatch!(self, store => store.create_record(repo, collection, value, rkey).await)
+    }
+
+    /// Create records in one transaction. Only transactional stores support
+    /// this; see [`is_transactional`](Self::is_transactional).
+    #[cfg(feature = "sqlite")]
+    pub(crate) async fn create_records(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        values: &[RecordValue],
+    ) -> Result<Vec<AtUri>> {
+        match self {
+            Self::Sqlite(store) => store.create_records(repo, collection, values).await,
+            Self::File(_) => Err(Error::InvalidInput(InvalidInputError::Other {
+                message: "The file store cannot write records in a transaction".to_string(),
+            })),
+        }
+    }
+
+    pub(crate) async fn put_record(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        rkey: &Rkey,
+        value: &RecordValue,
+        swap_cid: Option<&str>,
+    ) -> Result<AtUri> {
+        dispatch!(self, store => store.put_record(repo, collection, rkey, value, swap_cid).await)
+    }
+
+    pub(crate) async fn get_record(&self, uri: &AtUri) -> Result<Record> {
+        dispatch!(self, store => store.get_record(uri).await)
+    }
+
+    pub(crate) async fn list_collections(&self, repo: &Did) -> Result<Vec<Nsid>> {
+        dispatch!(self, store => store.list_collections(repo).await)
+    }
+
+    pub(crate) async fn list_records(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        options: &ListRecordsOptions,
+    ) -> Result<ListRecordsOutput> {
+        dispatch!(self, store => store.list_records(repo, collection, options).await)
+    }
+
+    pub(crate) async fn get_record_as_of(&self, uri: &AtUri, seq: i64) -> Result<Record> {
+        dispatch!(self, store => store.get_record_as_of(uri, seq).await)
+    }
+
+    pub(crate) async fn list_records_as_of(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        seq: i64,
+        options: &ListRecordsOptions,
+    ) -> Result<ListRecordsOutput> {
+        dispatch!(self, store => store.list_records_as_of(repo, collection, seq, options).await)
+    }
+
+    pub(crate) async fn record_history(&self, uri: &AtUri) -> Result<Vec<RecordVersion>> {
+        dispatch!(self, store => store.record_history(uri).await)
+    }
+
+    pub(crate) async fn delete_record(&self, uri: &AtUri) -> Result<()> {
+        dispatch!(self, store => store.delete_record(uri).await)
+    }
+}
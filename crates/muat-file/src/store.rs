@@ -1,21 +1,28 @@
 //! Filesystem storage for the file-backed PDS.
 
-use std::fs::{self, OpenOptions};
-use std::io::Write;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
 use chrono::Utc;
 use fs2::FileExt;
 use serde::{Deserialize, Serialize};
-use tracing::{debug, instrument};
+use tracing::{debug, instrument, warn};
 use uuid::Uuid;
 
 use muat_core::Result;
 use muat_core::error::{Error, InvalidInputError, ProtocolError, TransportError};
-use muat_core::repo::{ListRecordsOutput, Record, RecordValue};
-use muat_core::types::{AtUri, Did, Nsid, Rkey};
+use muat_core::repo::{
+    KeyOrder, ListRecordsOptions, ListRecordsOutput, Record, RecordOrder, RecordValue,
+    dag_cbor_cid, to_dag_cbor,
+};
+use muat_core::types::{AtUri, Did, Nsid, Rkey, Tid, TidGenerator, base32_lower};
 
-fn map_io(err: std::io::Error) -> Error {
+use crate::mail::EmailToken;
+use crate::password::PasswordAlgorithm;
+
+pub(crate) fn map_io(err: std::io::Error) -> Error {
     Error::Transport(TransportError::Http {
         message: format!("IO error: {}", err),
     })
@@ -30,35 +37,494 @@ pub struct LocalAccount {
     pub handle: String,
     /// When the account was created.
     pub created_at: String,
-    /// Password hash (bcrypt).
+    /// Password hash.
     pub password_hash: String,
+    /// Algorithm of `password_hash`.
+    #[serde(default)]
+    pub password_algorithm: PasswordAlgorithm,
+    /// Email address, if the account was created with one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Whether `email` has been confirmed.
+    #[serde(default)]
+    pub email_confirmed: bool,
+    /// The last confirmation or reset token emailed, until it is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) email_token: Option<EmailToken>,
+    /// When the account was deactivated, if it is inactive.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deactivated_at: Option<String>,
+    /// When the owner of a deactivated account allowed it to be deleted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delete_after: Option<String>,
 }
 
 /// An event in the firehose log.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub(crate) struct FirehoseLogEvent {
-    /// The AT URI of the affected record.
+    /// The AT URI of the affected record, or `at://<did>` for account events.
     pub uri: String,
     /// ISO 8601 timestamp.
     pub time: String,
     /// The operation type.
     pub op: FirehoseLogOp,
+    /// The account handle, for account creation and handle changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
+    /// The record written, for creates and updates. Absent from logs
+    /// written before record history was kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record: Option<serde_json::Value>,
+    /// The repo revision after this commit, for record operations. Absent
+    /// from logs written before revisions were kept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// The repo revision before this commit, if it had one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    /// The sequence number, for stores that assign one. Never written to
+    /// the log file, whose events are numbered by time.
+    #[serde(skip)]
+    pub seq: Option<i64>,
+}
+
+impl FirehoseLogEvent {
+    /// The event's sequence number: the one the store assigned, or else its
+    /// timestamp in microseconds.
+    pub(crate) fn seq(&self) -> i64 {
+        self.seq.unwrap_or_else(|| {
+            chrono::DateTime::parse_from_rfc3339(&self.time)
+                .map(|dt| dt.timestamp_micros())
+                .unwrap_or_default()
+        })
+    }
 }
 
 /// The type of firehose operation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
+#[serde(rename_all = "snake_case")]
 pub(crate) enum FirehoseLogOp {
     /// A record was created.
     Create,
     /// A record was deleted.
     Delete,
+    /// An existing record was overwritten.
+    Update,
+    /// An account was created.
+    AccountCreate,
+    /// An account's handle was changed.
+    HandleChange,
+    /// An account was removed.
+    AccountDelete,
+}
+
+/// A record write in progress, journaled before it touches any file so a
+/// process that dies part way through leaves it for the next writer to
+/// finish.
+#[derive(Debug, Serialize, Deserialize)]
+struct PendingWrite {
+    /// The AT URI of the record.
+    uri: String,
+    /// `create`, `update` or `delete`.
+    op: FirehoseLogOp,
+    /// The record file content, for creates and updates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    /// Length of the firehose log before the write, so recovery can tell
+    /// whether the write's event was appended.
+    firehose_len: u64,
+}
+
+/// Compression applied to record files when they are written.
+///
+/// Reads are transparent: a store can hold a mix of plain and compressed
+/// records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// Plain `<rkey>.json` files.
+    #[default]
+    None,
+    /// zstd-compressed `<rkey>.json.zst` files at the given level (1-22).
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+/// Outcome of [`FileStore::compact`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CompactionStats {
+    /// Number of record files examined.
+    pub records: usize,
+    /// Number of record files rewritten in the target format.
+    pub rewritten: usize,
+    /// Total size of record files before compaction, in bytes.
+    pub bytes_before: u64,
+    /// Total size of record files after compaction, in bytes.
+    pub bytes_after: u64,
+}
+
+/// One write to a record, from [`FilePds::record_history`](crate::FilePds::record_history).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordVersion {
+    /// Sequence number of the firehose event that wrote it.
+    pub seq: i64,
+    /// When it was written.
+    pub time: chrono::DateTime<Utc>,
+    /// The repo revision after the write, if the log kept it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// The record as written, or `None` for a delete.
+    pub record: Option<Record>,
+}
+
+const PLAIN_EXT: &str = ".json";
+const ZSTD_EXT: &str = ".json.zst";
+
+/// Per-collection index of rkeys, kept beside the record files. It cannot
+/// clash with a record, whose file names always end in `.json` or
+/// `.json.zst`.
+///
+/// The index is a journal: each line is `+<rkey>` for a record written or
+/// `-<rkey>` for one deleted, appended as records change. Replaying it gives
+/// the collection's rkeys without reading the directory. A missing index is
+/// rebuilt from the record files, so deleting it is always safe.
+const INDEX_FILE: &str = ".index";
+
+/// Dead journal lines tolerated before the index is rewritten, for small
+/// collections; larger ones tolerate as many as they have live rkeys.
+const INDEX_SLACK: usize = 1024;
+
+/// Returns the rkey for a record file name, if it is one.
+fn rkey_from_file_name(name: &str) -> Option<&str> {
+    name.strip_suffix(ZSTD_EXT)
+        .or_else(|| name.strip_suffix(PLAIN_EXT))
+}
+
+fn decode_record_file(path: &Path, bytes: Vec<u8>) -> Result<String> {
+    let is_compressed = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.ends_with(ZSTD_EXT));
+
+    let bytes = if is_compressed {
+        #[cfg(feature = "zstd")]
+        {
+            zstd::decode_all(bytes.as_slice()).map_err(map_io)?
+        }
+
+        #[cfg(not(feature = "zstd"))]
+        {
+            return Err(Error::InvalidInput(InvalidInputError::Other {
+                message: format!(
+                    "{} is zstd-compressed; enable the `zstd` feature to read it",
+                    path.display()
+                ),
+            }));
+        }
+    } else {
+        bytes
+    };
+
+    String::from_utf8(bytes).map_err(|e| {
+        Error::InvalidInput(InvalidInputError::Other {
+            message: format!("{} is not valid UTF-8: {}", path.display(), e),
+        })
+    })
+}
+
+/// Select the rkeys on the page `options` asks for, in its order.
+pub(crate) fn page_rkeys(mut rkeys: Vec<String>, options: &ListRecordsOptions) -> Vec<String> {
+    let limit = options.get_limit().unwrap_or(50) as usize;
+
+    rkeys.retain(|rkey| options.in_window(rkey));
+    rkeys.sort();
+    rkeys.dedup();
+
+    let descending = options.get_order() == RecordOrder::Descending;
+    if descending {
+        rkeys.reverse();
+    }
+
+    // The cursor is the last rkey of the previous page; resume after it in
+    // the requested order.
+    let start_idx = match options.get_cursor() {
+        Some(cursor) => rkeys
+            .iter()
+            .position(|rkey| {
+                if descending {
+                    rkey.as_str() < cursor
+                } else {
+                    rkey.as_str() > cursor
+                }
+            })
+            .unwrap_or(rkeys.len()),
+        None => 0,
+    };
+
+    rkeys.into_iter().skip(start_idx).take(limit).collect()
+}
+
+/// Wrap a page of records, with a cursor if the page is full.
+pub(crate) fn page_output(records: Vec<Record>, options: &ListRecordsOptions) -> ListRecordsOutput {
+    let limit = options.get_limit().unwrap_or(50) as usize;
+    let cursor = if records.len() == limit {
+        records
+            .last()
+            .and_then(|r| r.uri.rkey())
+            .map(|rkey| rkey.to_string())
+    } else {
+        None
+    };
+
+    ListRecordsOutput { records, cursor }
+}
+
+/// The CID of a record from its stored JSON: the hash of its canonical
+/// DAG-CBOR encoding, so it does not depend on how the JSON is laid out.
+///
+/// Content outside the atproto data model, such as a float written by hand,
+/// gets a local hash instead.
+pub(crate) fn record_cid(content: &str) -> String {
+    let encoded = serde_json::from_str(content)
+        .ok()
+        .and_then(|value| to_dag_cbor(&value).ok());
+    if let Some(bytes) = encoded {
+        return dag_cbor_cid(&bytes).into();
+    }
+
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    format!("bafylocal{:016x}", hasher.finish())
+}
+
+/// Serialize a record value the way stores keep it, with keys in `order`.
+pub(crate) fn record_json(value: &RecordValue, order: KeyOrder) -> Result<String> {
+    serde_json::to_string_pretty(&value.with_key_order(order)).map_err(|e| {
+        Error::InvalidInput(InvalidInputError::Other {
+            message: e.to_string(),
+        })
+    })
+}
+
+/// The revision following `since`. Revisions must increase even if the
+/// clock steps back or another process wrote the last one.
+pub(crate) fn next_rev_after(tids: &TidGenerator, since: Option<&Tid>) -> Tid {
+    let rev = tids.next_tid();
+    match since {
+        Some(since) if rev <= *since => Tid::from_timestamp(
+            since.timestamp() + chrono::TimeDelta::microseconds(1),
+            rev.clock_id(),
+        ),
+        _ => rev,
+    }
+}
+
+/// Replay firehose events to find a collection's records as they were
+/// after every event up to sequence number `seq`.
+///
+/// Maps rkeys to record content, or to `None` for records whose last write
+/// was logged without its content.
+pub(crate) fn replay_collection(
+    events: impl IntoIterator<Item = FirehoseLogEvent>,
+    repo: &Did,
+    collection: &Nsid,
+    seq: i64,
+) -> BTreeMap<String, Option<serde_json::Value>> {
+    let mut records = BTreeMap::new();
+    let prefix = format!("at://{}/{}/", repo, collection);
+    for event in events {
+        if event.seq() > seq {
+            continue;
+        }
+        let Some(rkey) = event.uri.strip_prefix(&prefix) else {
+            continue;
+        };
+
+        match event.op {
+            FirehoseLogOp::Create | FirehoseLogOp::Update => {
+                records.insert(rkey.to_string(), event.record);
+            }
+            FirehoseLogOp::Delete => {
+                records.remove(rkey);
+            }
+            _ => {}
+        }
+    }
+    records
+}
+
+/// Build a record from content replayed from the log, with the CID it had
+/// when it was stored.
+fn record_from_history(uri: AtUri, value: Option<serde_json::Value>) -> Result<Record> {
+    let Some(value) = value else {
+        return Err(Error::InvalidInput(InvalidInputError::Other {
+            message: format!(
+                "{} was written before the firehose log kept record content",
+                uri
+            ),
+        }));
+    };
+
+    let content = serde_json::to_string_pretty(&value).map_err(|e| {
+        Error::InvalidInput(InvalidInputError::Other {
+            message: e.to_string(),
+        })
+    })?;
+    let value: RecordValue = serde_json::from_value(value).map_err(|e| {
+        Error::InvalidInput(InvalidInputError::Other {
+            message: e.to_string(),
+        })
+    })?;
+
+    Ok(Record {
+        uri,
+        cid: record_cid(&content),
+        value,
+    })
+}
+
+/// Find a record in a collection's replayed history.
+pub(crate) fn history_record(
+    uri: &AtUri,
+    seq: i64,
+    mut history: BTreeMap<String, Option<serde_json::Value>>,
+) -> Result<Record> {
+    let (_, rkey) = uri.record_path()?;
+    let Some(value) = history.remove(rkey.as_str()) else {
+        return Err(Error::Protocol(ProtocolError::new(
+            404,
+            Some("RecordNotFound".to_string()),
+            Some(format!("Record {} not found at seq {}", uri, seq)),
+        )));
+    };
+
+    record_from_history(uri.clone(), value)
+}
+
+/// Page through a collection's replayed history.
+pub(crate) fn history_page(
+    repo: &Did,
+    collection: &Nsid,
+    mut history: BTreeMap<String, Option<serde_json::Value>>,
+    options: &ListRecordsOptions,
+) -> Result<ListRecordsOutput> {
+    let rkeys = history.keys().cloned().collect();
+
+    let mut records = Vec::new();
+    for rkey in page_rkeys(rkeys, options) {
+        let Ok(rkey_validated) = Rkey::new(&rkey) else {
+            continue;
+        };
+        let uri = AtUri::from_parts(repo.clone(), collection.clone(), rkey_validated);
+        let value = history.remove(&rkey).flatten();
+        records.push(record_from_history(uri, value)?);
+    }
+
+    Ok(page_output(records, options))
+}
+
+/// Every write to `uri` in `events`, oldest first. Writes logged without
+/// their content are left out.
+pub(crate) fn record_versions(
+    uri: &AtUri,
+    events: impl IntoIterator<Item = FirehoseLogEvent>,
+) -> Result<Vec<RecordVersion>> {
+    let uri_str = uri.to_string();
+    let mut versions = Vec::new();
+    for event in events {
+        if event.uri != uri_str {
+            continue;
+        }
+        let seq = event.seq();
+        let record = match event.op {
+            FirehoseLogOp::Create | FirehoseLogOp::Update => match event.record {
+                Some(value) => Some(record_from_history(uri.clone(), Some(value))?),
+                None => continue,
+            },
+            FirehoseLogOp::Delete => None,
+            _ => continue,
+        };
+        versions.push(RecordVersion {
+            seq,
+            time: chrono::DateTime::parse_from_rfc3339(&event.time)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_default(),
+            rev: event.rev,
+            record,
+        });
+    }
+    Ok(versions)
+}
+
+/// Replace a collection index with one line per rkey.
+fn write_index(path: &Path, rkeys: &BTreeSet<String>) -> Result<()> {
+    let mut journal = String::new();
+    for rkey in rkeys {
+        journal.push('+');
+        journal.push_str(rkey);
+        journal.push('\n');
+    }
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, journal).map_err(map_io)?;
+    fs::rename(&temp_path, path).map_err(map_io)
+}
+
+/// Returns true for a `did:plc` ID of 24 hex digits, as earlier versions
+/// took from a UUID, that is not also valid base32.
+fn is_legacy_plc_id(id: &str) -> bool {
+    id.len() == 24
+        && id.bytes().all(|c| matches!(c, b'0'..=b'9' | b'a'..=b'f'))
+        && Did::new(format!("did:plc:{}", id)).is_err()
+}
+
+/// The base32 ID a legacy hex `did:plc` ID migrates to: each hex digit
+/// becomes the letter of the same value, `0` to `a` through `f` to `p`.
+fn migrated_plc_id(hex: &str) -> String {
+    hex.bytes()
+        .map(|c| {
+            let value = (c as char).to_digit(16).unwrap_or(0) as u8;
+            char::from(b'a' + value)
+        })
+        .collect()
+}
+
+/// Rewrite each old DID in a file as its new one.
+fn replace_dids(path: &Path, moves: &[(String, Did)]) -> Result<()> {
+    let mut content = fs::read_to_string(path).map_err(map_io)?;
+    for (old, new) in moves {
+        content = content.replace(old.as_str(), new.as_str());
+    }
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, content).map_err(map_io)?;
+    fs::rename(&temp_path, path).map_err(map_io)
+}
+
+/// Open (creating if needed) and exclusively lock a lock file, blocking
+/// until any other holder, in this process or another, lets go.
+fn lock_exclusive(path: &Path) -> Result<File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(map_io)?;
+    }
+
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(path)
+        .map_err(map_io)?;
+    lock_file.lock_exclusive().map_err(map_io)?;
+    Ok(lock_file)
 }
 
 /// Filesystem-backed storage for a local PDS.
 #[derive(Debug, Clone)]
 pub struct FileStore {
     root: PathBuf,
+    compression: Compression,
+    key_order: KeyOrder,
+    tids: TidGenerator,
 }
 
 impl FileStore {
@@ -66,9 +532,30 @@ impl FileStore {
     pub fn new(root: impl AsRef<Path>) -> Self {
         Self {
             root: root.as_ref().to_path_buf(),
+            compression: Compression::None,
+            key_order: KeyOrder::default(),
+            tids: TidGenerator::new(),
         }
     }
 
+    /// Set the compression used for newly written records.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Set the order keys are written in for newly written records.
+    pub fn with_key_order(mut self, key_order: KeyOrder) -> Self {
+        self.key_order = key_order;
+        self
+    }
+
+    /// Returns the order keys are written in.
+    #[cfg(feature = "sqlite")]
+    pub fn key_order(&self) -> KeyOrder {
+        self.key_order
+    }
+
     /// Get the root directory path.
     pub fn root(&self) -> &Path {
         &self.root
@@ -102,6 +589,26 @@ impl FileStore {
             .join("account.json")
     }
 
+    /// Get the file holding a repo's latest commit revision.
+    fn repo_rev_path(&self, did: &Did) -> PathBuf {
+        self.repos_dir().join(Self::did_dir_name(did)).join("rev")
+    }
+
+    /// Get the journal of a repo's unfinished record write.
+    fn repo_journal_path(&self, did: &Did) -> PathBuf {
+        self.repos_dir()
+            .join(Self::did_dir_name(did))
+            .join("journal.json")
+    }
+
+    /// Get the lock file serializing record writes to a repo. It lives
+    /// outside the repo directory, which is removed with the account.
+    fn repo_lock_path(&self, did_dir_name: &str) -> PathBuf {
+        self.pds_dir()
+            .join("locks")
+            .join(format!("{}.lock", did_dir_name))
+    }
+
     /// Get the collections directory for a specific repo (DID).
     fn repo_collections_dir(&self, did: &Did) -> PathBuf {
         self.repos_dir()
@@ -109,11 +616,71 @@ impl FileStore {
             .join("collections")
     }
 
-    /// Get the path for a specific record.
-    fn record_path(&self, collection: &Nsid, did: &Did, rkey: &str) -> PathBuf {
-        self.repo_collections_dir(did)
-            .join(collection.as_str())
-            .join(format!("{}.json", rkey))
+    /// Get the directory holding a collection's records.
+    fn collection_dir(&self, did: &Did, collection: &Nsid) -> PathBuf {
+        self.repo_collections_dir(did).join(collection.as_str())
+    }
+
+    /// Get the plain and compressed paths for a specific record.
+    fn record_paths(&self, collection: &Nsid, did: &Did, rkey: &str) -> [PathBuf; 2] {
+        let dir = self.collection_dir(did, collection);
+        [
+            dir.join(format!("{}{}", rkey, PLAIN_EXT)),
+            dir.join(format!("{}{}", rkey, ZSTD_EXT)),
+        ]
+    }
+
+    /// Index into [`record_paths`](Self::record_paths) that new records are
+    /// written to.
+    fn target_index(&self) -> usize {
+        match self.compression {
+            Compression::None => 0,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(_) => 1,
+        }
+    }
+
+    /// Encode record content for the configured compression.
+    fn encode_record(&self, content: &str) -> Result<Vec<u8>> {
+        match self.compression {
+            Compression::None => Ok(content.as_bytes().to_vec()),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd(level) => zstd::encode_all(content.as_bytes(), level).map_err(map_io),
+        }
+    }
+
+    /// Write record content in the configured format, removing any copy in
+    /// the other format.
+    fn write_record_file(&self, paths: &[PathBuf; 2], content: &str) -> Result<()> {
+        let target = self.target_index();
+        let bytes = self.encode_record(content)?;
+        let path = &paths[target];
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(map_io)?;
+        }
+
+        let temp_path = path.with_extension("tmp");
+        fs::write(&temp_path, &bytes).map_err(map_io)?;
+        fs::rename(&temp_path, path).map_err(map_io)?;
+
+        let stale = &paths[1 - target];
+        if stale.exists() {
+            fs::remove_file(stale).map_err(map_io)?;
+        }
+
+        Ok(())
+    }
+
+    /// Read and decode a record file, whichever format it is stored in.
+    fn read_record_file(&self, paths: &[PathBuf; 2]) -> Result<Option<String>> {
+        for path in paths {
+            if path.exists() {
+                let bytes = fs::read(path).map_err(map_io)?;
+                return decode_record_file(path, bytes).map(Some);
+            }
+        }
+        Ok(None)
     }
 
     /// Get the firehose log path.
@@ -126,46 +693,266 @@ impl FileStore {
         self.pds_dir().join("firehose.lock")
     }
 
-    /// Generate a new record key (TID-style).
+    /// Generate a new TID record key.
     fn generate_rkey(&self) -> String {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_micros();
-        format!("{:x}", now)
+        self.tids.next_tid().into()
     }
 
-    /// Generate a simple CID for a record.
-    fn generate_cid(&self, content: &str) -> String {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-        let mut hasher = DefaultHasher::new();
-        content.hash(&mut hasher);
-        format!("bafylocal{:016x}", hasher.finish())
+    /// Advance a repo's commit revision, returning the new revision and the
+    /// previous one. Called with the firehose lock held.
+    fn next_rev(&self, repo: &Did) -> Result<(Tid, Option<Tid>)> {
+        let path = self.repo_rev_path(repo);
+        let since = match fs::read_to_string(&path) {
+            Ok(content) => Tid::new(content.trim()).ok(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(map_io(e)),
+        };
+
+        let rev = next_rev_after(&self.tids, since.as_ref());
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(map_io)?;
+        }
+        fs::write(&path, rev.as_str()).map_err(map_io)?;
+
+        Ok((rev, since))
     }
 
-    /// Append an event to the firehose log.
-    fn append_firehose(&self, uri: &AtUri, op: FirehoseLogOp) -> Result<()> {
-        let firehose_path = self.firehose_path();
-        let lock_path = self.firehose_lock_path();
+    /// Take the store-wide write lock, held until the returned file is
+    /// unlocked or dropped. Not reentrant: never take it twice at once.
+    fn lock(&self) -> Result<File> {
+        lock_exclusive(&self.firehose_lock_path())
+    }
+
+    /// Take a repo's write lock, held until the returned file is dropped,
+    /// and finish any write a previous holder left unfinished.
+    ///
+    /// Record writes hold it from reading the existing record until their
+    /// firehose event is logged, so writes from other processes cannot
+    /// interleave. Take it before, never while holding, the store-wide lock.
+    fn lock_repo(&self, did: &Did) -> Result<File> {
+        let lock_file = lock_exclusive(&self.repo_lock_path(&Self::did_dir_name(did)))?;
+        self.recover(did)?;
+        Ok(lock_file)
+    }
+
+    /// Finish the write journaled for a repo, if any. Called with the
+    /// repo's lock held.
+    fn recover(&self, did: &Did) -> Result<()> {
+        let path = self.repo_journal_path(did);
+        let journal = match fs::read_to_string(&path) {
+            Ok(journal) => journal,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(map_io(e)),
+        };
+        let pending: PendingWrite = serde_json::from_str(&journal).map_err(|e| {
+            Error::InvalidInput(InvalidInputError::Other {
+                message: format!("Corrupt journal {}: {}", path.display(), e),
+            })
+        })?;
+
+        warn!(uri = %pending.uri, op = ?pending.op, "Finishing interrupted record write");
+        self.apply_write(&pending, true)?;
+        fs::remove_file(&path).map_err(map_io)
+    }
 
-        if let Some(parent) = firehose_path.parent() {
+    /// Journal a record write, apply it, then clear the journal. Called
+    /// with the repo's lock held.
+    fn commit(&self, repo: &Did, pending: &PendingWrite) -> Result<()> {
+        let path = self.repo_journal_path(repo);
+        if let Some(parent) = path.parent() {
             fs::create_dir_all(parent).map_err(map_io)?;
         }
+        let journal = serde_json::to_string(pending).map_err(|e| {
+            Error::InvalidInput(InvalidInputError::Other {
+                message: e.to_string(),
+            })
+        })?;
+        let temp_path = path.with_extension("tmp");
+        let mut file = File::create(&temp_path).map_err(map_io)?;
+        file.write_all(journal.as_bytes()).map_err(map_io)?;
+        file.sync_data().map_err(map_io)?;
+        fs::rename(&temp_path, &path).map_err(map_io)?;
 
-        let lock_file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .truncate(false)
-            .open(&lock_path)
+        self.apply_write(pending, false)?;
+        fs::remove_file(&path).map_err(map_io)
+    }
+
+    /// Write or delete the record file, update the index and log the
+    /// firehose event. Each step can be repeated, so a write interrupted
+    /// at any point can be applied again; when `recovering`, the event is
+    /// only logged if the interrupted write did not get that far.
+    fn apply_write(&self, pending: &PendingWrite, recovering: bool) -> Result<()> {
+        let uri = AtUri::new(&pending.uri)?;
+        let (collection, rkey) = uri.record_path()?;
+        let paths = self.record_paths(collection, uri.repo(), rkey.as_str());
+
+        let record = match (&pending.op, &pending.content) {
+            (FirehoseLogOp::Create | FirehoseLogOp::Update, Some(content)) => {
+                let value: RecordValue = serde_json::from_str(content).map_err(|e| {
+                    Error::InvalidInput(InvalidInputError::Other {
+                        message: e.to_string(),
+                    })
+                })?;
+                self.write_record_file(&paths, content)?;
+                if pending.op == FirehoseLogOp::Create {
+                    self.update_index(uri.repo(), collection, '+', rkey.as_str())?;
+                }
+                Some(value)
+            }
+            (FirehoseLogOp::Delete, None) => {
+                for path in &paths {
+                    match fs::remove_file(path) {
+                        Ok(()) => {}
+                        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                        Err(e) => return Err(map_io(e)),
+                    }
+                }
+                self.update_index(uri.repo(), collection, '-', rkey.as_str())?;
+                None
+            }
+            _ => {
+                return Err(Error::InvalidInput(InvalidInputError::Other {
+                    message: format!("Invalid journaled write to {}", pending.uri),
+                }));
+            }
+        };
+
+        if !recovering || !self.firehose_logged(pending)? {
+            self.append_firehose(&pending.uri, pending.op, None, record.as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Whether the firehose log has the event of a journaled write.
+    fn firehose_logged(&self, pending: &PendingWrite) -> Result<bool> {
+        let mut file = match File::open(self.firehose_path()) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(map_io(e)),
+        };
+        file.seek(SeekFrom::Start(pending.firehose_len))
             .map_err(map_io)?;
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(map_io)?;
+            if let Ok(event) = serde_json::from_str::<FirehoseLogEvent>(&line)
+                && event.uri == pending.uri
+                && event.op == pending.op
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// The firehose log's current length.
+    fn firehose_len(&self) -> Result<u64> {
+        match fs::metadata(self.firehose_path()) {
+            Ok(metadata) => Ok(metadata.len()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(map_io(e)),
+        }
+    }
 
-        lock_file.lock_exclusive().map_err(map_io)?;
+    /// Record in the collection's index that `rkey` was written (`'+'`) or
+    /// deleted (`'-'`). An index that does not exist yet is left to be
+    /// built from the record files when the collection is next listed.
+    fn update_index(&self, did: &Did, collection: &Nsid, op: char, rkey: &str) -> Result<()> {
+        let path = self.collection_dir(did, collection).join(INDEX_FILE);
+        let lock_file = self.lock()?;
+
+        if path.exists() {
+            let mut file = OpenOptions::new()
+                .append(true)
+                .open(&path)
+                .map_err(map_io)?;
+            writeln!(file, "{}{}", op, rkey).map_err(map_io)?;
+        }
+
+        lock_file.unlock().map_err(map_io)
+    }
+
+    /// The rkeys in a collection, sorted, from its index.
+    fn collection_rkeys(&self, did: &Did, collection: &Nsid) -> Result<Vec<String>> {
+        let dir = self.collection_dir(did, collection);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let path = dir.join(INDEX_FILE);
+        let lock_file = self.lock()?;
+
+        let rkeys = match fs::read_to_string(&path) {
+            Ok(journal) => {
+                let mut rkeys = BTreeSet::new();
+                let mut lines = 0;
+                for line in journal.lines() {
+                    lines += 1;
+                    if let Some(rkey) = line.strip_prefix('+') {
+                        rkeys.insert(rkey.to_string());
+                    } else if let Some(rkey) = line.strip_prefix('-') {
+                        rkeys.remove(rkey);
+                    }
+                }
+                if lines - rkeys.len() > rkeys.len().max(INDEX_SLACK) {
+                    debug!(collection = %collection, lines, live = rkeys.len(), "Compacting index");
+                    write_index(&path, &rkeys)?;
+                }
+                rkeys
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let rkeys: BTreeSet<String> = fs::read_dir(&dir)
+                    .map_err(map_io)?
+                    .filter_map(|e| e.ok())
+                    .filter_map(|e| {
+                        e.file_name()
+                            .to_str()
+                            .and_then(rkey_from_file_name)
+                            .map(str::to_string)
+                    })
+                    .collect();
+                debug!(collection = %collection, count = rkeys.len(), "Built index");
+                write_index(&path, &rkeys)?;
+                rkeys
+            }
+            Err(e) => return Err(map_io(e)),
+        };
+
+        lock_file.unlock().map_err(map_io)?;
+        Ok(rkeys.into_iter().collect())
+    }
+
+    /// Append an event to the firehose log.
+    ///
+    /// Record operations also advance the repo's revision.
+    fn append_firehose(
+        &self,
+        uri: &str,
+        op: FirehoseLogOp,
+        handle: Option<&str>,
+        record: Option<&RecordValue>,
+    ) -> Result<()> {
+        let firehose_path = self.firehose_path();
+
+        let lock_file = self.lock()?;
+
+        let (rev, since) = match op {
+            FirehoseLogOp::Create | FirehoseLogOp::Update | FirehoseLogOp::Delete => {
+                let uri = AtUri::new(uri)?;
+                let (rev, since) = self.next_rev(uri.repo())?;
+                (Some(rev.into()), since.map(String::from))
+            }
+            _ => (None, None),
+        };
 
         let event = FirehoseLogEvent {
             uri: uri.to_string(),
             time: Utc::now().to_rfc3339(),
             op,
+            handle: handle.map(str::to_string),
+            record: record.map(|value| value.as_value().clone()),
+            rev,
+            since,
+            seq: None,
         };
 
         let mut file = OpenOptions::new()
@@ -192,10 +979,17 @@ impl FileStore {
     // Account Management
     // ========================================================================
 
-    #[instrument(skip(self, password_hash))]
-    pub fn create_account(&self, handle: &str, password_hash: &str) -> Result<Did> {
-        let uuid_str = Uuid::new_v4().to_string().replace("-", "");
-        let did_str = format!("did:plc:{}", &uuid_str[..24]);
+    #[instrument(skip(self, email, password_hash))]
+    pub fn create_account(
+        &self,
+        handle: &str,
+        email: Option<&str>,
+        password_hash: &str,
+        password_algorithm: PasswordAlgorithm,
+    ) -> Result<Did> {
+        // did:plc identifiers are 24 characters of lowercase base32.
+        let id = base32_lower(Uuid::new_v4().as_bytes());
+        let did_str = format!("did:plc:{}", &id[..24]);
         let did = Did::new(&did_str)?;
 
         let account = LocalAccount {
@@ -203,6 +997,12 @@ impl FileStore {
             handle: handle.to_string(),
             created_at: Utc::now().to_rfc3339(),
             password_hash: password_hash.to_string(),
+            password_algorithm,
+            email: email.map(str::to_string),
+            email_confirmed: false,
+            email_token: None,
+            deactivated_at: None,
+            delete_after: None,
         };
 
         let account_path = self.account_path(&did);
@@ -211,18 +1011,28 @@ impl FileStore {
             fs::create_dir_all(parent).map_err(map_io)?;
         }
 
-        let content = serde_json::to_string_pretty(&account).map_err(|e| {
-            Error::InvalidInput(InvalidInputError::Other {
-                message: e.to_string(),
-            })
-        })?;
-        fs::write(&account_path, content).map_err(map_io)?;
+        self.write_account(&did, &account)?;
+        self.append_firehose(
+            &format!("at://{}", did),
+            FirehoseLogOp::AccountCreate,
+            Some(handle),
+            None,
+        )?;
 
         debug!(did = %did, handle = %handle, "Created local account");
 
         Ok(did)
     }
 
+    fn write_account(&self, did: &Did, account: &LocalAccount) -> Result<()> {
+        let content = serde_json::to_string_pretty(account).map_err(|e| {
+            Error::InvalidInput(InvalidInputError::Other {
+                message: e.to_string(),
+            })
+        })?;
+        fs::write(self.account_path(did), content).map_err(map_io)
+    }
+
     pub fn get_account(&self, did: &Did) -> Result<Option<LocalAccount>> {
         let account_path = self.account_path(did);
 
@@ -253,8 +1063,15 @@ impl FileStore {
         }
 
         fs::remove_dir_all(&account_dir).map_err(map_io)?;
+        self.append_firehose(
+            &format!("at://{}", did),
+            FirehoseLogOp::AccountDelete,
+            None,
+            None,
+        )?;
 
         if delete_records {
+            let _lock = self.lock_repo(did)?;
             let repo_dir = self.repos_dir().join(Self::did_dir_name(did));
             if repo_dir.exists() {
                 fs::remove_dir_all(&repo_dir).map_err(map_io)?;
@@ -266,6 +1083,107 @@ impl FileStore {
         Ok(())
     }
 
+    #[instrument(skip(self, password_hash))]
+    pub fn update_password_hash(
+        &self,
+        did: &Did,
+        password_hash: &str,
+        password_algorithm: PasswordAlgorithm,
+    ) -> Result<()> {
+        let mut account = self.get_account(did)?.ok_or_else(|| {
+            Error::Protocol(ProtocolError::new(
+                404,
+                Some("AccountNotFound".to_string()),
+                Some(format!("Account {} not found", did)),
+            ))
+        })?;
+
+        account.password_hash = password_hash.to_string();
+        account.password_algorithm = password_algorithm;
+        self.write_account(did, &account)
+    }
+
+    /// Change an account's email and password fields in place.
+    #[instrument(skip(self, update))]
+    pub fn update_account(&self, did: &Did, update: impl FnOnce(&mut LocalAccount)) -> Result<()> {
+        let mut account = self.get_account(did)?.ok_or_else(|| {
+            Error::Protocol(ProtocolError::new(
+                404,
+                Some("AccountNotFound".to_string()),
+                Some(format!("Account {} not found", did)),
+            ))
+        })?;
+
+        update(&mut account);
+        self.write_account(did, &account)
+    }
+
+    #[instrument(skip(self))]
+    pub fn update_handle(&self, did: &Did, handle: &str) -> Result<()> {
+        let mut account = self.get_account(did)?.ok_or_else(|| {
+            Error::Protocol(ProtocolError::new(
+                404,
+                Some("AccountNotFound".to_string()),
+                Some(format!("Account {} not found", did)),
+            ))
+        })?;
+
+        if account.handle == handle {
+            return Ok(());
+        }
+        if self.find_account_by_handle(handle)?.is_some() {
+            return Err(Error::Protocol(ProtocolError::new(
+                400,
+                Some("HandleNotAvailable".to_string()),
+                Some(format!("Handle {} is already taken", handle)),
+            )));
+        }
+
+        account.handle = handle.to_string();
+        self.write_account(did, &account)?;
+        self.append_firehose(
+            &format!("at://{}", did),
+            FirehoseLogOp::HandleChange,
+            Some(handle),
+            None,
+        )?;
+
+        debug!(did = %did, handle = %handle, "Updated local account handle");
+
+        Ok(())
+    }
+
+    /// The revision of a repo's latest commit, if it has any.
+    pub fn repo_rev(&self, did: &Did) -> Result<Option<String>> {
+        match fs::read_to_string(self.repo_rev_path(did)) {
+            Ok(content) => Ok(Some(content.trim().to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(map_io(e)),
+        }
+    }
+
+    /// Count the records in a repo across its collections.
+    pub fn count_records(&self, did: &Did) -> Result<u64> {
+        let collections_dir = self.repo_collections_dir(did);
+        if !collections_dir.is_dir() {
+            return Ok(0);
+        }
+
+        let mut count = 0;
+        for entry in fs::read_dir(&collections_dir).map_err(map_io)? {
+            let entry = entry.map_err(map_io)?;
+            let Some(collection) = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| Nsid::new(name).ok())
+            else {
+                continue;
+            };
+            count += self.collection_rkeys(did, &collection)?.len() as u64;
+        }
+        Ok(count)
+    }
+
     pub fn list_accounts(&self) -> Result<Vec<LocalAccount>> {
         let accounts_dir = self.accounts_dir();
 
@@ -295,29 +1213,108 @@ impl FileStore {
         Ok(accounts.into_iter().find(|a| a.handle == handle))
     }
 
+    /// Move accounts given hex `did:plc` DIDs by earlier versions to the
+    /// base32 DIDs [`Did`] accepts, returning how many were moved.
+    ///
+    /// The new DID is derived from the old one, so a root migrates the same
+    /// way every time. The DID is rewritten in the firehose log, any
+    /// unfinished write journal and the account itself, and the account and
+    /// repo directories are renamed last, so an interrupted migration picks
+    /// up where it stopped.
+    #[instrument(skip(self))]
+    pub fn migrate_legacy_dids(&self) -> Result<usize> {
+        let accounts_dir = self.accounts_dir();
+
+        if !accounts_dir.exists() {
+            return Ok(0);
+        }
+
+        let mut moves = Vec::new();
+        for entry in fs::read_dir(&accounts_dir).map_err(map_io)? {
+            let entry = entry.map_err(map_io)?;
+            let name = entry.file_name();
+            let Some(id) = name.to_str().and_then(|n| n.strip_prefix("did_plc_")) else {
+                continue;
+            };
+            if is_legacy_plc_id(id) {
+                moves.push((
+                    format!("did:plc:{}", id),
+                    Did::new(format!("did:plc:{}", migrated_plc_id(id)))?,
+                ));
+            }
+        }
+
+        if moves.is_empty() {
+            return Ok(0);
+        }
+
+        let _lock = self.lock()?;
+        let firehose_path = self.firehose_path();
+        if firehose_path.exists() {
+            replace_dids(&firehose_path, &moves)?;
+        }
+
+        for (old, new) in &moves {
+            let old_dir = old.replace(':', "_");
+
+            let repo_dir = self.repos_dir().join(&old_dir);
+            if repo_dir.exists() {
+                let journal = repo_dir.join("journal.json");
+                if journal.exists() {
+                    replace_dids(&journal, &moves)?;
+                }
+                fs::rename(&repo_dir, self.repos_dir().join(Self::did_dir_name(new)))
+                    .map_err(map_io)?;
+            }
+
+            let account_dir = accounts_dir.join(&old_dir);
+            let account_file = account_dir.join("account.json");
+            if account_file.exists() {
+                let content = fs::read_to_string(&account_file).map_err(map_io)?;
+                let mut account: LocalAccount = serde_json::from_str(&content).map_err(|e| {
+                    Error::InvalidInput(InvalidInputError::Other {
+                        message: e.to_string(),
+                    })
+                })?;
+                account.did = new.to_string();
+                let content = serde_json::to_string_pretty(&account).map_err(|e| {
+                    Error::InvalidInput(InvalidInputError::Other {
+                        message: e.to_string(),
+                    })
+                })?;
+                fs::write(&account_file, content).map_err(map_io)?;
+            }
+            fs::rename(&account_dir, accounts_dir.join(Self::did_dir_name(new))).map_err(map_io)?;
+
+            debug!(from = %old, to = %new, "Migrated legacy did:plc account");
+        }
+
+        Ok(moves.len())
+    }
+
     // ========================================================================
     // Record Operations
     // ========================================================================
 
     async fn get_record_internal(&self, uri: &AtUri) -> Result<Record> {
-        let path = self.record_path(uri.collection(), uri.repo(), uri.rkey().as_str());
+        let (collection, rkey) = uri.record_path()?;
+        let paths = self.record_paths(collection, uri.repo(), rkey.as_str());
 
-        if !path.exists() {
+        let Some(content) = self.read_record_file(&paths)? else {
             return Err(Error::Protocol(ProtocolError::new(
                 404,
                 Some("RecordNotFound".to_string()),
                 Some(format!("Record {} not found", uri)),
             )));
-        }
+        };
 
-        let content = fs::read_to_string(&path).map_err(map_io)?;
         let value: RecordValue = serde_json::from_str(&content).map_err(|e| {
             Error::InvalidInput(InvalidInputError::Other {
                 message: e.to_string(),
             })
         })?;
 
-        let cid = self.generate_cid(&content);
+        let cid = record_cid(&content);
 
         Ok(Record {
             uri: uri.clone(),
@@ -339,27 +1336,70 @@ impl FileStore {
             .unwrap_or_else(|| self.generate_rkey());
 
         let rkey_validated = Rkey::new(&rkey)?;
-        let path = self.record_path(collection, repo, &rkey);
+        let content = record_json(value, self.key_order)?;
+        let uri = AtUri::from_parts(repo.clone(), collection.clone(), rkey_validated);
 
-        if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent).map_err(map_io)?;
-        }
+        let _lock = self.lock_repo(repo)?;
+        let pending = PendingWrite {
+            uri: uri.to_string(),
+            op: FirehoseLogOp::Create,
+            content: Some(content),
+            firehose_len: self.firehose_len()?,
+        };
+        self.commit(repo, &pending)?;
 
-        let content = serde_json::to_string_pretty(value.as_value()).map_err(|e| {
-            Error::InvalidInput(InvalidInputError::Other {
-                message: e.to_string(),
-            })
-        })?;
+        debug!(uri = %uri, "Created record");
 
-        let temp_path = path.with_extension("tmp");
-        fs::write(&temp_path, &content).map_err(map_io)?;
-        fs::rename(&temp_path, &path).map_err(map_io)?;
+        Ok(uri)
+    }
 
-        let uri = AtUri::from_parts(repo.clone(), collection.clone(), rkey_validated);
+    /// Write a record at a fixed key, overwriting any existing record.
+    ///
+    /// Logs an update if the key was already in use, otherwise a create.
+    #[instrument(skip(self, value))]
+    pub async fn put_record(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        rkey: &Rkey,
+        value: &RecordValue,
+        swap_cid: Option<&str>,
+    ) -> Result<AtUri> {
+        let paths = self.record_paths(collection, repo, rkey.as_str());
+        let content = record_json(value, self.key_order)?;
+
+        let _lock = self.lock_repo(repo)?;
+        let existing = self.read_record_file(&paths)?;
+
+        if let Some(swap_cid) = swap_cid {
+            let current = existing.as_deref().map(record_cid);
+            if current.as_deref() != Some(swap_cid) {
+                return Err(Error::Protocol(ProtocolError::new(
+                    400,
+                    Some("InvalidSwap".to_string()),
+                    Some(format!(
+                        "Record was at {}",
+                        current.as_deref().unwrap_or("null")
+                    )),
+                )));
+            }
+        }
+        let uri = AtUri::from_parts(repo.clone(), collection.clone(), rkey.clone());
+        let op = if existing.is_some() {
+            FirehoseLogOp::Update
+        } else {
+            FirehoseLogOp::Create
+        };
 
-        self.append_firehose(&uri, FirehoseLogOp::Create)?;
+        let pending = PendingWrite {
+            uri: uri.to_string(),
+            op,
+            content: Some(content),
+            firehose_len: self.firehose_len()?,
+        };
+        self.commit(repo, &pending)?;
 
-        debug!(uri = %uri, "Created record");
+        debug!(uri = %uri, ?op, "Put record");
 
         Ok(uri)
     }
@@ -369,83 +1409,230 @@ impl FileStore {
         self.get_record_internal(uri).await
     }
 
+    #[instrument(skip(self))]
+    pub async fn list_collections(&self, repo: &Did) -> Result<Vec<Nsid>> {
+        let dir = self.repo_collections_dir(repo);
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(map_io(e)),
+        };
+
+        let mut names: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .collect();
+        names.sort();
+
+        // Collections whose records were all deleted keep their directory.
+        let mut collections = Vec::new();
+        for name in names {
+            let Ok(collection) = Nsid::new(&name) else {
+                continue;
+            };
+            if !self.collection_rkeys(repo, &collection)?.is_empty() {
+                collections.push(collection);
+            }
+        }
+        Ok(collections)
+    }
+
     #[instrument(skip(self))]
     pub async fn list_records(
         &self,
         repo: &Did,
         collection: &Nsid,
-        limit: Option<u32>,
-        cursor: Option<&str>,
+        options: &ListRecordsOptions,
     ) -> Result<ListRecordsOutput> {
-        let dir = self.repo_collections_dir(repo).join(collection.as_str());
+        let rkeys = self.collection_rkeys(repo, collection)?;
 
         let mut records = Vec::new();
-        let limit = limit.unwrap_or(50) as usize;
-
-        if dir.exists() {
-            let mut entries: Vec<_> = fs::read_dir(&dir)
-                .map_err(map_io)?
-                .filter_map(|e| e.ok())
-                .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
-                .collect();
-
-            entries.sort_by_key(|e| e.file_name());
-
-            let start_idx = if let Some(cursor) = cursor {
-                entries
-                    .iter()
-                    .position(|e| {
-                        e.path()
-                            .file_stem()
-                            .and_then(|s| s.to_str())
-                            .is_some_and(|s| s > cursor)
-                    })
-                    .unwrap_or(0)
-            } else {
-                0
+        for rkey in page_rkeys(rkeys, options) {
+            let rkey_validated = match Rkey::new(&rkey) {
+                Ok(r) => r,
+                Err(_) => continue,
             };
 
-            for entry in entries.iter().skip(start_idx).take(limit) {
-                let rkey = entry
-                    .path()
-                    .file_stem()
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("")
-                    .to_string();
-
-                let rkey_validated = match Rkey::new(&rkey) {
-                    Ok(r) => r,
-                    Err(_) => continue,
-                };
-
-                let uri = AtUri::from_parts(repo.clone(), collection.clone(), rkey_validated);
-                if let Ok(record) = self.get_record_internal(&uri).await {
-                    records.push(record);
-                }
+            let uri = AtUri::from_parts(repo.clone(), collection.clone(), rkey_validated);
+            if let Ok(record) = self.get_record_internal(&uri).await {
+                records.push(record);
             }
         }
 
-        let cursor = if records.len() == limit {
-            records.last().map(|r| r.uri.rkey().as_str().to_string())
-        } else {
-            None
+        Ok(page_output(records, options))
+    }
+
+    // ========================================================================
+    // History
+    // ========================================================================
+
+    /// Replay the firehose log to find a collection's records as they were
+    /// after every event up to sequence number `seq`.
+    fn collection_as_of(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        seq: i64,
+    ) -> Result<BTreeMap<String, Option<serde_json::Value>>> {
+        let file = match File::open(self.firehose_path()) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(map_io(e)),
         };
 
-        Ok(ListRecordsOutput { records, cursor })
+        let mut read_error = None;
+        let events = BufReader::new(file)
+            .lines()
+            .map_while(|line| line.map_err(|e| read_error = Some(e)).ok())
+            .filter_map(|line| serde_json::from_str::<FirehoseLogEvent>(&line).ok());
+        let history = replay_collection(events, repo, collection, seq);
+
+        match read_error {
+            Some(e) => Err(map_io(e)),
+            None => Ok(history),
+        }
     }
 
     #[instrument(skip(self))]
-    pub async fn delete_record(&self, uri: &AtUri) -> Result<()> {
-        let path = self.record_path(uri.collection(), uri.repo(), uri.rkey().as_str());
+    pub async fn get_record_as_of(&self, uri: &AtUri, seq: i64) -> Result<Record> {
+        let (collection, _) = uri.record_path()?;
+        let history = self.collection_as_of(uri.repo(), collection, seq)?;
+        history_record(uri, seq, history)
+    }
 
-        if path.exists() {
-            fs::remove_file(&path).map_err(map_io)?;
+    #[instrument(skip(self))]
+    pub async fn list_records_as_of(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        seq: i64,
+        options: &ListRecordsOptions,
+    ) -> Result<ListRecordsOutput> {
+        let history = self.collection_as_of(repo, collection, seq)?;
+        history_page(repo, collection, history, options)
+    }
+
+    #[instrument(skip(self))]
+    pub async fn record_history(&self, uri: &AtUri) -> Result<Vec<RecordVersion>> {
+        let file = match File::open(self.firehose_path()) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(map_io(e)),
+        };
 
-            self.append_firehose(uri, FirehoseLogOp::Delete)?;
+        let mut read_error = None;
+        let events = BufReader::new(file)
+            .lines()
+            .map_while(|line| line.map_err(|e| read_error = Some(e)).ok())
+            .filter_map(|line| serde_json::from_str::<FirehoseLogEvent>(&line).ok());
+        let versions = record_versions(uri, events)?;
+
+        match read_error {
+            Some(e) => Err(map_io(e)),
+            None => Ok(versions),
+        }
+    }
+
+    #[instrument(skip(self))]
+    pub async fn delete_record(&self, uri: &AtUri) -> Result<()> {
+        let (collection, rkey) = uri.record_path()?;
+        let paths = self.record_paths(collection, uri.repo(), rkey.as_str());
+
+        let _lock = self.lock_repo(uri.repo())?;
+        if paths.iter().any(|path| path.exists()) {
+            let pending = PendingWrite {
+                uri: uri.to_string(),
+                op: FirehoseLogOp::Delete,
+                content: None,
+                firehose_len: self.firehose_len()?,
+            };
+            self.commit(uri.repo(), &pending)?;
 
             debug!(uri = %uri, "Deleted record");
         }
 
         Ok(())
     }
+
+    // ========================================================================
+    // Maintenance
+    // ========================================================================
+
+    /// Rewrite every record file in the configured compression format.
+    ///
+    /// Record contents, rkeys and CIDs are unchanged, so no firehose events
+    /// are emitted. Safe to re-run; records already in the target format are
+    /// left alone.
+    #[instrument(skip(self), fields(compression = ?self.compression))]
+    pub fn compact(&self) -> Result<CompactionStats> {
+        let mut stats = CompactionStats::default();
+        let repos_dir = self.repos_dir();
+
+        if !repos_dir.exists() {
+            return Ok(stats);
+        }
+
+        let target = self.target_index();
+
+        for repo in fs::read_dir(&repos_dir).map_err(map_io)? {
+            let repo = repo.map_err(map_io)?;
+            let collections_dir = repo.path().join("collections");
+            if !collections_dir.is_dir() {
+                continue;
+            }
+            // Hold the repo's lock so a concurrent write is not overwritten
+            // with the record's previous content.
+            let Some(name) = repo.file_name().to_str().map(str::to_string) else {
+                continue;
+            };
+            let _lock = lock_exclusive(&self.repo_lock_path(&name))?;
+
+            for collection in fs::read_dir(&collections_dir).map_err(map_io)? {
+                let collection_dir = collection.map_err(map_io)?.path();
+                if !collection_dir.is_dir() {
+                    continue;
+                }
+
+                for entry in fs::read_dir(&collection_dir).map_err(map_io)? {
+                    let path = entry.map_err(map_io)?.path();
+                    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                        continue;
+                    };
+                    let Some(rkey) = rkey_from_file_name(name) else {
+                        continue;
+                    };
+                    // A duplicate in the other format may already have been
+                    // folded into its sibling.
+                    if !path.exists() {
+                        continue;
+                    }
+
+                    stats.records += 1;
+                    let size = fs::metadata(&path).map_err(map_io)?.len();
+                    stats.bytes_before += size;
+
+                    let current = usize::from(name.ends_with(ZSTD_EXT));
+                    if current == target {
+                        stats.bytes_after += size;
+                        continue;
+                    }
+
+                    let bytes = fs::read(&path).map_err(map_io)?;
+                    let content = decode_record_file(&path, bytes)?;
+                    let paths = [
+                        collection_dir.join(format!("{}{}", rkey, PLAIN_EXT)),
+                        collection_dir.join(format!("{}{}", rkey, ZSTD_EXT)),
+                    ];
+                    self.write_record_file(&paths, &content)?;
+
+                    stats.bytes_after += fs::metadata(&paths[target]).map_err(map_io)?.len();
+                    stats.rewritten += 1;
+                }
+            }
+        }
+
+        debug!(?stats, "Compacted record files");
+
+        Ok(stats)
+    }
 }
@@ -0,0 +1,82 @@
+//! Record validation hooks for the file-backed PDS.
+
+use std::fmt;
+use std::sync::Arc;
+
+use muat_core::error::{Error, ProtocolError};
+use muat_core::repo::RecordValue;
+use muat_core::types::Nsid;
+use muat_core::{Lexicon, Result};
+
+/// Checks records before a [`FilePds`](crate::FilePds) stores them, as a
+/// network PDS checks records against their lexicons.
+///
+/// Any `Fn(&Nsid, &RecordValue) -> Result<(), String>` closure is a
+/// validator.
+pub trait RecordValidator: Send + Sync {
+    /// Check `record`, about to be written to `collection`. An error
+    /// rejects the write, with the message saying what is wrong.
+    fn validate(&self, collection: &Nsid, record: &RecordValue) -> std::result::Result<(), String>;
+}
+
+impl<F> RecordValidator for F
+where
+    F: Fn(&Nsid, &RecordValue) -> std::result::Result<(), String> + Send + Sync,
+{
+    fn validate(&self, collection: &Nsid, record: &RecordValue) -> std::result::Result<(), String> {
+        self(collection, record)
+    }
+}
+
+/// Validates records in `T::NSID` by deserializing them as `T`, such as a
+/// type generated by `muat-codegen`.
+pub(crate) struct LexiconValidator<T>(std::marker::PhantomData<fn() -> T>);
+
+impl<T> LexiconValidator<T> {
+    pub(crate) fn new() -> Self {
+        Self(std::marker::PhantomData)
+    }
+}
+
+impl<T: Lexicon + serde::de::DeserializeOwned> RecordValidator for LexiconValidator<T> {
+    fn validate(&self, collection: &Nsid, record: &RecordValue) -> std::result::Result<(), String> {
+        if collection.as_str() != T::NSID {
+            return Ok(());
+        }
+        record.to_lexicon::<T>().map(drop).map_err(|e| match e {
+            Error::InvalidInput(e) => e.reason().to_string(),
+            e => e.to_string(),
+        })
+    }
+}
+
+/// The validators registered on a PDS, run in the order added.
+#[derive(Clone, Default)]
+pub(crate) struct Validators(Vec<Arc<dyn RecordValidator>>);
+
+impl Validators {
+    pub(crate) fn push(&mut self, validator: Arc<dyn RecordValidator>) {
+        self.0.push(validator);
+    }
+
+    /// Check a record with every validator, failing as a PDS does with
+    /// `InvalidRecord`.
+    pub(crate) fn check(&self, collection: &Nsid, record: &RecordValue) -> Result<()> {
+        for validator in &self.0 {
+            if let Err(message) = validator.validate(collection, record) {
+                return Err(Error::Protocol(ProtocolError::new(
+                    400,
+                    Some("InvalidRecord".to_string()),
+                    Some(format!("Invalid {} record: {}", collection, message)),
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Validators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Validators({})", self.0.len())
+    }
+}
@@ -0,0 +1,1179 @@
+//! Shared conformance suite run against the file-backed PDS, plus checks
+//! specific to its storage format.
+
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use muat_core::repo::{FirehoseBuffer, KeyOrder, OverflowPolicy, RepoEvent};
+use muat_core::testing::Fixture;
+use muat_core::traits::{BlobStore, Pds, Session};
+use muat_core::{Credentials, Error, ListRecordsOptions, Nsid, PdsUrl, RecordValue, Rkey, Tid};
+use muat_file::{FileBlobStore, FilePds, PasswordHashing};
+
+async fn fixture() -> Fixture<FilePds> {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    let pds = FilePds::new(temp.path(), pds_url);
+
+    pds.create_account("alice.local", Some("password"), None, None)
+        .await
+        .unwrap();
+
+    Fixture::new(pds, "alice.local", "password").with_guard(temp)
+}
+
+muat_core::conformance_tests!(fixture());
+
+#[tokio::test]
+async fn test_blob_cid_is_raw_sha256() {
+    let temp = tempfile::tempdir().unwrap();
+    let store = FileBlobStore::new(temp.path());
+
+    let blob = store
+        .put_blob(b"hello world".to_vec(), "text/plain")
+        .await
+        .unwrap();
+    assert_eq!(
+        blob.cid.as_str(),
+        "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e"
+    );
+}
+
+#[tokio::test]
+async fn test_firehose_attributes_each_event_to_its_repo() {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    let pds = FilePds::new(temp.path(), pds_url);
+
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let value = RecordValue::with_type("org.muat.test.record", serde_json::json!({})).unwrap();
+    let mut dids = Vec::new();
+    for handle in ["alice.local", "bob.local"] {
+        pds.create_account(handle, Some("password"), None, None)
+            .await
+            .unwrap();
+        let session = pds
+            .login(Credentials::new(handle, "password"))
+            .await
+            .unwrap();
+        session.create_record(&collection, &value).await.unwrap();
+        dids.push(session.did().to_string());
+    }
+
+    let firehose = pds.firehose_from(Some(0)).unwrap();
+    let repos: Vec<String> = tokio::time::timeout(
+        Duration::from_secs(10),
+        firehose
+            .filter_map(|event| async move {
+                match event.unwrap() {
+                    RepoEvent::Commit(commit) => Some(commit.repo),
+                    _ => None,
+                }
+            })
+            .take(2)
+            .collect(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(repos, dids);
+}
+
+#[tokio::test]
+async fn test_firehose_drops_oldest_events_for_slow_consumer() {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    let pds = FilePds::new(temp.path(), pds_url)
+        .with_firehose_buffer(FirehoseBuffer::new(2).overflow(OverflowPolicy::DropOldest));
+
+    pds.create_account("alice.local", Some("password"), None, None)
+        .await
+        .unwrap();
+    let session = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let value = RecordValue::with_type("org.muat.test.record", serde_json::json!({})).unwrap();
+    let mut last = None;
+    for _ in 0..5 {
+        last = Some(session.create_record(&collection, &value).await.unwrap());
+    }
+    let last = last.unwrap();
+
+    // Let the reader replay the whole log into the buffer before consuming.
+    let firehose = pds.firehose_from(Some(0)).unwrap();
+    let stats = firehose.stats_handle();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while stats.dropped_events() < 4 {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(firehose.lag(), 2);
+
+    let events: Vec<RepoEvent> = tokio::time::timeout(
+        Duration::from_secs(10),
+        firehose.map(|event| event.unwrap()).take(2).collect(),
+    )
+    .await
+    .unwrap();
+    let RepoEvent::Commit(commit) = &events[1] else {
+        panic!("expected the newest commit, got {:?}", events[1]);
+    };
+    assert_eq!(
+        commit.ops[0].path,
+        format!("{}/{}", collection, last.rkey().unwrap())
+    );
+}
+
+#[tokio::test]
+async fn test_create_record_with_rkey_and_handle_lookup() {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    let pds = FilePds::new(temp.path(), pds_url);
+
+    let created = pds
+        .create_account("alice.local", Some("password"), None, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        pds.resolve_handle("alice.local").unwrap(),
+        Some(created.did.clone())
+    );
+    assert_eq!(
+        pds.handle_of(&created.did).unwrap().as_deref(),
+        Some("alice.local")
+    );
+    assert_eq!(pds.resolve_handle("bob.local").unwrap(), None);
+
+    let session = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let rkey = Rkey::new("self").unwrap();
+    let value = RecordValue::with_type("org.muat.test.record", serde_json::json!({})).unwrap();
+
+    let uri = session
+        .create_record_with_rkey(&collection, &rkey, &value)
+        .await
+        .unwrap();
+    assert_eq!(uri.rkey().unwrap(), &rkey);
+    assert!(
+        session
+            .create_record_with_rkey(&collection, &rkey, &value)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_firehose_reports_account_events() {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    let pds = FilePds::new(temp.path(), pds_url);
+
+    let alice = pds
+        .create_account("alice.local", Some("password"), None, None)
+        .await
+        .unwrap();
+    pds.create_account("bob.local", Some("password"), None, None)
+        .await
+        .unwrap();
+    let session = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+
+    assert!(session.update_handle("bob.local").is_err());
+    session.update_handle("alice2.local").unwrap();
+    assert_eq!(
+        pds.resolve_handle("alice2.local").unwrap(),
+        Some(alice.did.clone())
+    );
+    pds.delete_account(&alice.did, &session.access_token(), None)
+        .await
+        .unwrap();
+
+    let firehose = pds.firehose_from(Some(0)).unwrap();
+    let events: Vec<RepoEvent> = tokio::time::timeout(
+        Duration::from_secs(10),
+        firehose.map(|event| event.unwrap()).take(4).collect(),
+    )
+    .await
+    .unwrap();
+
+    let summary: Vec<String> = events
+        .iter()
+        .map(|event| match event {
+            RepoEvent::Identity(identity) => format!("identity {}", identity.did),
+            RepoEvent::Handle(handle) => format!("handle {} {}", handle.did, handle.handle),
+            other => format!("{:?}", other),
+        })
+        .collect();
+    assert_eq!(summary[0], format!("identity {}", alice.did));
+    assert!(summary[1].starts_with("identity did:plc:"));
+    assert_eq!(summary[2], format!("handle {} alice2.local", alice.did));
+    assert_eq!(summary[3], format!("identity {}", alice.did));
+}
+
+#[tokio::test]
+async fn test_put_record_emits_update() {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    let pds = FilePds::new(temp.path(), pds_url);
+    pds.create_account("alice.local", Some("password"), None, None)
+        .await
+        .unwrap();
+    let session = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let rkey = Rkey::new("self").unwrap();
+    let mut uris = Vec::new();
+    for text in ["first", "second", "third"] {
+        let value =
+            RecordValue::with_type("org.muat.test.record", serde_json::json!({ "text": text }))
+                .unwrap();
+        uris.push(
+            session
+                .put_record(&collection, &rkey, &value, None)
+                .await
+                .unwrap(),
+        );
+    }
+
+    let uri = &uris[2];
+    assert!(uris.iter().all(|u| u == uri));
+    let record = session.get_record(uri).await.unwrap();
+    assert_eq!(record.value.get("text").unwrap(), "third");
+    let page = session
+        .list_records(session.did(), &collection, None, None)
+        .await
+        .unwrap();
+    assert_eq!(page.records.len(), 1);
+
+    let firehose = pds.firehose_from(Some(0)).unwrap();
+    let stats = firehose.stats_handle();
+    let actions: Vec<String> = tokio::time::timeout(
+        Duration::from_secs(10),
+        firehose
+            .filter_map(|event| async move {
+                match event.unwrap() {
+                    RepoEvent::Commit(commit) => Some(commit.ops[0].action.clone()),
+                    _ => None,
+                }
+            })
+            .take(3)
+            .collect(),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(actions, ["create", "update", "update"]);
+
+    let counted = stats.snapshot();
+    assert_eq!(counted.commits, 3);
+    assert_eq!((counted.creates, counted.updates), (1, 2));
+    assert!(counted.bytes_received > 0);
+}
+
+#[tokio::test]
+async fn test_list_records_time_window() {
+    let fixture = fixture().await;
+    let session = fixture.login().await;
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let value = RecordValue::with_type("org.muat.test.record", serde_json::json!({})).unwrap();
+
+    let tid = |time: &str| Tid::from_timestamp(time.parse().unwrap(), 0);
+    let (jan, feb, mar) = (
+        tid("2024-01-01T00:00:00Z"),
+        tid("2024-02-01T00:00:00Z"),
+        tid("2024-03-01T00:00:00Z"),
+    );
+    for rkey in [jan.as_str(), feb.as_str(), mar.as_str(), "self"] {
+        session
+            .create_record_with_rkey(&collection, &Rkey::new(rkey).unwrap(), &value)
+            .await
+            .unwrap();
+    }
+
+    let list = |options: ListRecordsOptions| {
+        let session = &session;
+        let collection = &collection;
+        async move {
+            session
+                .list_records_with(session.did(), collection, &options)
+                .await
+                .unwrap()
+                .records
+                .into_iter()
+                .map(|record| record.uri.rkey().unwrap().to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    let since = ListRecordsOptions::new().since(feb.timestamp());
+    assert_eq!(list(since.clone()).await, [feb.as_str(), mar.as_str()]);
+    assert_eq!(
+        list(since.reverse(true)).await,
+        [mar.as_str(), feb.as_str()]
+    );
+    assert_eq!(
+        list(ListRecordsOptions::new().until(feb.timestamp())).await,
+        [jan.as_str()]
+    );
+    assert_eq!(list(ListRecordsOptions::new()).await.len(), 4);
+}
+
+#[tokio::test]
+async fn test_list_records_reads_collection_index() {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    let pds = FilePds::new(temp.path(), pds_url);
+    pds.create_account("alice.local", Some("password"), None, None)
+        .await
+        .unwrap();
+    let session = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let value = RecordValue::with_type("org.muat.test.record", serde_json::json!({})).unwrap();
+
+    let dir = temp
+        .path()
+        .join("pds")
+        .join("repos")
+        .join(session.did().as_str().replace(':', "_"))
+        .join("collections")
+        .join(collection.as_str());
+    let index = dir.join(".index");
+    let rkeys = || {
+        let session = &session;
+        let collection = &collection;
+        async move {
+            session
+                .list_records_with(session.did(), collection, &ListRecordsOptions::new())
+                .await
+                .unwrap()
+                .records
+                .into_iter()
+                .map(|record| record.uri.rkey().unwrap().to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    for rkey in ["b", "a", "c"] {
+        session
+            .create_record_with_rkey(&collection, &Rkey::new(rkey).unwrap(), &value)
+            .await
+            .unwrap();
+    }
+    // The first listing builds the index from the record files.
+    assert!(!index.exists());
+    assert_eq!(rkeys().await, ["a", "b", "c"]);
+    assert_eq!(std::fs::read_to_string(&index).unwrap(), "+a\n+b\n+c\n");
+
+    // Later writes are journalled, and listings no longer read the directory.
+    let uri = session
+        .create_record_with_rkey(&collection, &Rkey::new("d").unwrap(), &value)
+        .await
+        .unwrap();
+    session.delete_record(&uri).await.unwrap();
+    session
+        .put_record(&collection, &Rkey::new("a").unwrap(), &value, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&index).unwrap(),
+        "+a\n+b\n+c\n+d\n-d\n"
+    );
+    std::fs::write(dir.join("stray.json"), value.as_value().to_string()).unwrap();
+    assert_eq!(rkeys().await, ["a", "b", "c"]);
+
+    // Deleting the index rebuilds it.
+    std::fs::remove_file(&index).unwrap();
+    assert_eq!(rkeys().await, ["a", "b", "c", "stray"]);
+}
+
+#[tokio::test]
+async fn test_login_rehashes_password_when_policy_changes() {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    let bcrypt = FilePds::new(temp.path(), pds_url.clone())
+        .with_password_hashing(PasswordHashing::Bcrypt { cost: 4 });
+    let created = bcrypt
+        .create_account("alice.local", Some("password"), None, None)
+        .await
+        .unwrap();
+    let old_session = bcrypt
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+
+    let account_file = temp
+        .path()
+        .join("pds")
+        .join("accounts")
+        .join(created.did.as_str().replace(':', "_"))
+        .join("account.json");
+    let account = || -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(&account_file).unwrap()).unwrap()
+    };
+    assert_eq!(account()["password_algorithm"], "bcrypt");
+
+    let argon2 =
+        FilePds::new(temp.path(), pds_url).with_password_hashing(PasswordHashing::Argon2id {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        });
+    let session = argon2
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    assert_eq!(account()["password_algorithm"], "argon2id");
+    assert!(
+        account()["password_hash"]
+            .as_str()
+            .unwrap()
+            .starts_with("$argon2id$")
+    );
+    session.validate().unwrap();
+    // The stored hash changed, so sessions issued before the re-hash end.
+    assert!(old_session.validate().is_err());
+
+    assert!(
+        argon2
+            .login(Credentials::new("alice.local", "wrong"))
+            .await
+            .is_err()
+    );
+    argon2
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_generated_rkeys_are_increasing_tids() {
+    let fixture = fixture().await;
+    let session = fixture.login().await;
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let value = RecordValue::with_type("org.muat.test.record", serde_json::json!({})).unwrap();
+
+    let mut tids = Vec::new();
+    for _ in 0..10 {
+        let uri = session.create_record(&collection, &value).await.unwrap();
+        tids.push(Tid::new(uri.rkey().unwrap().as_str()).unwrap());
+    }
+    assert!(tids.windows(2).all(|pair| pair[0] < pair[1]));
+    let age = chrono::Utc::now() - tids[0].timestamp();
+    assert!(age < chrono::Duration::minutes(1));
+}
+
+#[tokio::test]
+async fn test_reads_as_of_firehose_sequence() {
+    let fixture = fixture().await;
+    let pds = &fixture.pds;
+    let session = fixture.login().await;
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let text = |text: &str| {
+        RecordValue::with_type("org.muat.test.record", serde_json::json!({ "text": text })).unwrap()
+    };
+    let (a, b) = (Rkey::new("a").unwrap(), Rkey::new("b").unwrap());
+
+    let uri_a = session
+        .put_record(&collection, &a, &text("first"), None)
+        .await
+        .unwrap();
+    let first_cid = session.get_record(&uri_a).await.unwrap().cid;
+    session
+        .put_record(&collection, &a, &text("second"), None)
+        .await
+        .unwrap();
+    let uri_b = session
+        .put_record(&collection, &b, &text("other"), None)
+        .await
+        .unwrap();
+    session.delete_record(&uri_b).await.unwrap();
+
+    let seqs: Vec<i64> = tokio::time::timeout(
+        Duration::from_secs(10),
+        pds.firehose_from(Some(0))
+            .unwrap()
+            .filter_map(|event| async move {
+                match event.unwrap() {
+                    RepoEvent::Commit(commit) => Some(commit.seq),
+                    _ => None,
+                }
+            })
+            .take(4)
+            .collect(),
+    )
+    .await
+    .unwrap();
+
+    let rkeys_at = |seq: i64| {
+        let collection = &collection;
+        let session = &session;
+        async move {
+            pds.list_records_as_of(session.did(), collection, seq, &ListRecordsOptions::new())
+                .await
+                .unwrap()
+                .records
+                .into_iter()
+                .map(|record| record.uri.rkey().unwrap().to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    let not_found =
+        |err: muat_core::Error| matches!(err, muat_core::Error::Protocol(ref e) if e.status == 404);
+    assert!(not_found(
+        pds.get_record_as_of(&uri_a, seqs[0] - 1).await.unwrap_err()
+    ));
+    assert!(rkeys_at(seqs[0] - 1).await.is_empty());
+
+    let record = pds.get_record_as_of(&uri_a, seqs[0]).await.unwrap();
+    assert_eq!(record.value.get("text").unwrap(), "first");
+    assert_eq!(record.cid, first_cid);
+
+    let record = pds.get_record_as_of(&uri_a, seqs[2]).await.unwrap();
+    assert_eq!(record.value.get("text").unwrap(), "second");
+    assert_eq!(rkeys_at(seqs[2]).await, ["a", "b"]);
+
+    assert!(not_found(
+        pds.get_record_as_of(&uri_b, seqs[3]).await.unwrap_err()
+    ));
+    assert_eq!(rkeys_at(seqs[3]).await, ["a"]);
+    let page = pds
+        .list_records_as_of(
+            session.did(),
+            &collection,
+            seqs[2],
+            &ListRecordsOptions::new().limit(1).reverse(true),
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.records[0].uri, uri_b);
+    assert_eq!(page.cursor.as_deref(), Some("b"));
+
+    // Logs written before record content was kept cannot be replayed.
+    let log = pds.root().join("pds").join("firehose.jsonl");
+    let legacy = serde_json::json!({
+        "uri": format!("at://{}/org.muat.test.record/c", session.did()),
+        "time": chrono::Utc::now().to_rfc3339(),
+        "op": "create"
+    });
+    let mut content = std::fs::read_to_string(&log).unwrap();
+    content.push_str(&format!("{}\n", legacy));
+    std::fs::write(&log, content).unwrap();
+    let uri_c =
+        muat_core::AtUri::new(format!("at://{}/org.muat.test.record/c", session.did())).unwrap();
+    let err = pds.get_record_as_of(&uri_c, i64::MAX).await.unwrap_err();
+    assert!(matches!(err, muat_core::Error::InvalidInput(_)));
+}
+
+#[tokio::test]
+async fn test_reads_earlier_versions_by_cid() {
+    let fixture = fixture().await;
+    let pds = &fixture.pds;
+    let session = fixture.login().await;
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let text = |text: &str| {
+        RecordValue::with_type("org.muat.test.record", serde_json::json!({ "text": text })).unwrap()
+    };
+    let rkey = Rkey::new("a").unwrap();
+
+    let uri = session
+        .put_record(&collection, &rkey, &text("first"), None)
+        .await
+        .unwrap();
+    let first = session.get_record(&uri).await.unwrap();
+    session
+        .put_record(&collection, &rkey, &text("second"), None)
+        .await
+        .unwrap();
+    session.delete_record(&uri).await.unwrap();
+
+    let history = pds.record_history(&uri).await.unwrap();
+    assert_eq!(history.len(), 3);
+    assert!(history.windows(2).all(|w| w[0].seq < w[1].seq));
+    assert!(history.iter().all(|version| version.rev.is_some()));
+    let texts: Vec<_> = history
+        .iter()
+        .map(|version| {
+            version
+                .record
+                .as_ref()
+                .map(|record| record.value.get("text").unwrap().as_str().unwrap())
+        })
+        .collect();
+    assert_eq!(texts, [Some("first"), Some("second"), None]);
+    assert_eq!(history[0].record.as_ref().unwrap().cid, first.cid);
+
+    // Deleted records can still be read at an earlier CID.
+    let pinned = session.get_record_at(&uri, &first.cid).await.unwrap();
+    assert_eq!(pinned.value.get("text").unwrap(), "first");
+    let second_cid = &history[1].record.as_ref().unwrap().cid;
+    let err = session
+        .get_record_at(&uri, "bafynotacid")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, muat_core::Error::Protocol(ref e) if e.is_not_found()));
+    assert_eq!(
+        pds.get_record_at(&uri, second_cid)
+            .await
+            .unwrap()
+            .value
+            .get("text")
+            .unwrap(),
+        "second"
+    );
+}
+
+#[tokio::test]
+async fn test_commits_carry_increasing_revs() {
+    let fixture = fixture().await;
+    let session = fixture.login().await;
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let value = RecordValue::with_type("org.muat.test.record", serde_json::json!({})).unwrap();
+
+    let uri = session.create_record(&collection, &value).await.unwrap();
+    session
+        .put_record(&collection, &Rkey::new("self").unwrap(), &value, None)
+        .await
+        .unwrap();
+    session.delete_record(&uri).await.unwrap();
+
+    let commits: Vec<_> = tokio::time::timeout(
+        Duration::from_secs(10),
+        fixture
+            .pds
+            .firehose_from(Some(0))
+            .unwrap()
+            .filter_map(|event| async move {
+                match event.unwrap() {
+                    RepoEvent::Commit(commit) => Some(commit),
+                    _ => None,
+                }
+            })
+            .take(3)
+            .collect(),
+    )
+    .await
+    .unwrap();
+
+    assert!(commits[0].since.is_none());
+    for pair in commits.windows(2) {
+        assert!(Tid::new(&pair[1].rev).is_ok());
+        assert!(pair[1].rev > pair[0].rev);
+        assert_eq!(pair[1].since.as_deref(), Some(pair[0].rev.as_str()));
+    }
+}
+
+#[tokio::test]
+async fn test_key_order_sets_on_disk_layout_but_not_cid() {
+    let value = RecordValue::with_type(
+        "app.bsky.feed.post",
+        serde_json::json!({"text": "Hello, world!", "createdAt": "2024-01-01T00:00:00.000Z"}),
+    )
+    .unwrap();
+    let collection = Nsid::new("app.bsky.feed.post").unwrap();
+    let rkey = Rkey::new("self").unwrap();
+
+    let mut stored = Vec::new();
+    for key_order in [KeyOrder::Sorted, KeyOrder::Canonical] {
+        let temp = tempfile::tempdir().unwrap();
+        let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+        let pds = FilePds::new(temp.path(), pds_url).with_key_order(key_order);
+        pds.create_account("alice.local", Some("password"), None, None)
+            .await
+            .unwrap();
+        let session = pds
+            .login(Credentials::new("alice.local", "password"))
+            .await
+            .unwrap();
+        let uri = session
+            .create_record_with_rkey(&collection, &rkey, &value)
+            .await
+            .unwrap();
+
+        let record = session.get_record(&uri).await.unwrap();
+        assert_eq!(
+            record.cid,
+            "bafyreiaxwnwm6j7d5eccgx2fo3qa6q3x5fghq5lcep4qk7sbcf55qsmyo4"
+        );
+        assert_eq!(record.value, value);
+
+        let path = temp
+            .path()
+            .join("pds")
+            .join("repos")
+            .join(session.did().as_str().replace(':', "_"))
+            .join("collections")
+            .join(collection.as_str())
+            .join("self.json");
+        let json = std::fs::read_to_string(path).unwrap();
+        let keys: Vec<usize> = ["\"$type\"", "\"createdAt\"", "\"text\""]
+            .iter()
+            .map(|key| json.find(key).unwrap())
+            .collect();
+        stored.push(keys);
+    }
+
+    // Sorted: $type, createdAt, text. Canonical: text, $type, createdAt.
+    assert!(stored[0][0] < stored[0][1] && stored[0][1] < stored[0][2]);
+    assert!(stored[1][2] < stored[1][0] && stored[1][0] < stored[1][1]);
+}
+
+#[tokio::test]
+async fn test_email_tokens_are_written_to_the_outbox() {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    let pds = FilePds::new(temp.path(), pds_url);
+    pds.create_account(
+        "alice.local",
+        Some("password"),
+        Some("alice@example.com"),
+        None,
+    )
+    .await
+    .unwrap();
+    assert!(
+        pds.create_account(
+            "bob.local",
+            Some("password"),
+            Some("ALICE@example.com"),
+            None
+        )
+        .await
+        .is_err()
+    );
+    let session = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+
+    session.request_email_confirmation().await.unwrap();
+    let mail = pds.sent_mail("alice@example.com").unwrap().unwrap();
+    assert_eq!(mail.to, "alice@example.com");
+    let protocol_error = |err: Error| match err {
+        Error::Protocol(e) => e.error.unwrap_or_default(),
+        other => panic!("expected a protocol error, got {:?}", other),
+    };
+    let err = session
+        .confirm_email("bob@example.com", &mail.token)
+        .await
+        .unwrap_err();
+    assert_eq!(protocol_error(err), "InvalidEmail");
+    // A confirmation token cannot reset the password.
+    let err = pds
+        .reset_password(&mail.token, "new password")
+        .await
+        .unwrap_err();
+    assert_eq!(protocol_error(err), "InvalidToken");
+    session
+        .confirm_email("alice@example.com", &mail.token.to_lowercase())
+        .await
+        .unwrap();
+    let err = session
+        .confirm_email("alice@example.com", &mail.token)
+        .await
+        .unwrap_err();
+    assert_eq!(protocol_error(err), "InvalidToken");
+
+    // Unknown addresses get no mail, and no error.
+    pds.request_password_reset("nobody@example.com")
+        .await
+        .unwrap();
+    assert!(pds.sent_mail("nobody@example.com").unwrap().is_none());
+
+    pds.request_password_reset("alice@example.com")
+        .await
+        .unwrap();
+    let mail = pds.sent_mail("alice@example.com").unwrap().unwrap();
+    pds.reset_password(&mail.token, "new password")
+        .await
+        .unwrap();
+    assert!(session.validate().is_err());
+    assert!(
+        pds.login(Credentials::new("alice.local", "password"))
+            .await
+            .is_err()
+    );
+    pds.login(Credentials::new("alice.local", "new password"))
+        .await
+        .unwrap();
+    let err = pds
+        .reset_password(&mail.token, "another password")
+        .await
+        .unwrap_err();
+    assert_eq!(protocol_error(err), "InvalidToken");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_writers_sharing_a_directory_do_not_race() {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    FilePds::new(temp.path(), pds_url.clone())
+        .create_account("alice.local", Some("password"), None, None)
+        .await
+        .unwrap();
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let value = RecordValue::with_type("org.muat.test.record", serde_json::json!({})).unwrap();
+    let shared = Rkey::new("shared").unwrap();
+
+    // Separate instances stand in for separate processes: none share
+    // in-memory state, only the directory and its locks.
+    let mut writers = Vec::new();
+    for _ in 0..4 {
+        let pds = FilePds::new(temp.path(), pds_url.clone());
+        let (collection, value, shared) = (collection.clone(), value.clone(), shared.clone());
+        writers.push(tokio::spawn(async move {
+            let session = pds
+                .login(Credentials::new("alice.local", "password"))
+                .await
+                .unwrap();
+            for _ in 0..10 {
+                session.create_record(&collection, &value).await.unwrap();
+                session
+                    .put_record(&collection, &shared, &value, None)
+                    .await
+                    .unwrap();
+            }
+        }));
+    }
+    for writer in writers {
+        writer.await.unwrap();
+    }
+
+    let pds = FilePds::new(temp.path(), pds_url);
+    let session = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    let listed = session
+        .list_records_with(
+            session.did(),
+            &collection,
+            &ListRecordsOptions::new().limit(100),
+        )
+        .await
+        .unwrap();
+    assert_eq!(listed.records.len(), 41);
+
+    // Exactly one writer created the shared record; the rest updated it.
+    let log = std::fs::read_to_string(temp.path().join("pds").join("firehose.jsonl")).unwrap();
+    let events: Vec<serde_json::Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .filter(|event: &serde_json::Value| event["rev"].is_string())
+        .collect();
+    let shared_uri = format!("at://{}/org.muat.test.record/shared", session.did());
+    let shared_ops: Vec<&str> = events
+        .iter()
+        .filter(|event| event["uri"] == shared_uri.as_str())
+        .map(|event| event["op"].as_str().unwrap())
+        .collect();
+    assert_eq!(shared_ops.len(), 40);
+    assert_eq!(shared_ops.iter().filter(|op| **op == "create").count(), 1);
+    assert_eq!(shared_ops[0], "create");
+
+    let revs: Vec<&str> = events
+        .iter()
+        .map(|event| event["rev"].as_str().unwrap())
+        .collect();
+    assert!(revs.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[tokio::test]
+async fn test_interrupted_write_is_finished_by_next_writer() {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    let pds = FilePds::new(temp.path(), pds_url);
+    pds.create_account("alice.local", Some("password"), None, None)
+        .await
+        .unwrap();
+    let session = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let value = RecordValue::with_type("org.muat.test.record", serde_json::json!({})).unwrap();
+
+    let log = temp.path().join("pds").join("firehose.jsonl");
+    let journal = temp
+        .path()
+        .join("pds")
+        .join("repos")
+        .join(session.did().as_str().replace(':', "_"))
+        .join("journal.json");
+    let log_len = || std::fs::metadata(&log).map(|m| m.len()).unwrap_or(0);
+    let logged = |uri: &str| {
+        std::fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .filter(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["uri"] == uri)
+            .count()
+    };
+
+    // A create that died before writing anything is finished.
+    let lost = format!("at://{}/org.muat.test.record/lost", session.did());
+    let pending = serde_json::json!({
+        "uri": lost,
+        "op": "create",
+        "content": value.as_value().to_string(),
+        "firehose_len": log_len(),
+    });
+    std::fs::create_dir_all(journal.parent().unwrap()).unwrap();
+    std::fs::write(&journal, pending.to_string()).unwrap();
+    session.create_record(&collection, &value).await.unwrap();
+    assert!(!journal.exists());
+    session
+        .get_record(&muat_core::AtUri::new(&lost).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(logged(&lost), 1);
+
+    // A delete that died after logging its event is not logged twice.
+    let before = log_len();
+    session
+        .delete_record(&muat_core::AtUri::new(&lost).unwrap())
+        .await
+        .unwrap();
+    let pending = serde_json::json!({
+        "uri": lost,
+        "op": "delete",
+        "firehose_len": before,
+    });
+    std::fs::write(&journal, pending.to_string()).unwrap();
+    session.create_record(&collection, &value).await.unwrap();
+    assert!(!journal.exists());
+    assert_eq!(logged(&lost), 2);
+    let listed = session
+        .list_records(session.did(), &collection, None, None)
+        .await
+        .unwrap();
+    assert_eq!(listed.records.len(), 2);
+}
+
+#[tokio::test]
+async fn test_validators_reject_invalid_records() {
+    #[derive(serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Note {
+        text: String,
+    }
+
+    impl muat_core::Lexicon for Note {
+        const NSID: &'static str = "org.muat.test.note";
+    }
+
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    let pds = FilePds::new(temp.path(), pds_url)
+        .with_validator(|collection: &Nsid, record: &RecordValue| {
+            match record.get("text").and_then(|text| text.as_str()) {
+                Some(text) if text.len() > 10 => {
+                    Err(format!("text is too long for {}", collection))
+                }
+                _ => Ok(()),
+            }
+        })
+        .with_lexicon::<Note>();
+    pds.create_account("alice.local", Some("password"), None, None)
+        .await
+        .unwrap();
+    let session = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    let record = Nsid::new("org.muat.test.record").unwrap();
+    let note = Nsid::new("org.muat.test.note").unwrap();
+    let value =
+        |kind: &str, fields: serde_json::Value| RecordValue::with_type(kind, fields).unwrap();
+    let invalid_record = |e: Error| match e {
+        Error::Protocol(e) => {
+            assert_eq!(e.error.as_deref(), Some("InvalidRecord"));
+            e.message.unwrap_or_default()
+        }
+        other => panic!("expected InvalidRecord, got {:?}", other),
+    };
+
+    let long = value(
+        "org.muat.test.record",
+        serde_json::json!({ "text": "far too long" }),
+    );
+    let message = invalid_record(session.create_record(&record, &long).await.unwrap_err());
+    assert!(
+        message.contains("text is too long for org.muat.test.record"),
+        "{message}"
+    );
+    let rkey = Rkey::new("self").unwrap();
+    invalid_record(
+        session
+            .put_record(&record, &rkey, &long, None)
+            .await
+            .unwrap_err(),
+    );
+    invalid_record(
+        session
+            .create_record_with_rkey(&record, &rkey, &long)
+            .await
+            .unwrap_err(),
+    );
+
+    let untyped = value("org.muat.test.note", serde_json::json!({ "text": 3 }));
+    let message = invalid_record(session.create_record(&note, &untyped).await.unwrap_err());
+    assert!(message.contains("at `text`"), "{message}");
+    let mistyped = value("org.muat.test.record", serde_json::json!({ "text": "hi" }));
+    invalid_record(session.create_record(&note, &mistyped).await.unwrap_err());
+
+    // Nothing was written; valid records still are.
+    for collection in [&record, &note] {
+        let page = session
+            .list_records(session.did(), collection, None, None)
+            .await
+            .unwrap();
+        assert!(page.records.is_empty());
+    }
+    session
+        .create_record(
+            &note,
+            &value("org.muat.test.note", serde_json::json!({ "text": "hi" })),
+        )
+        .await
+        .unwrap();
+    session
+        .put_record(
+            &record,
+            &rkey,
+            &value(
+                "org.muat.test.record",
+                serde_json::json!({ "text": "short" }),
+            ),
+            None,
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_diff_repos_between_two_pds_copies() {
+    async fn account(temp: &tempfile::TempDir) -> (FilePds, muat_file::FileSession) {
+        let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+        let pds = FilePds::new(temp.path(), pds_url);
+        pds.create_account("alice.local", Some("password"), None, None)
+            .await
+            .unwrap();
+        let session = pds
+            .login(Credentials::new("alice.local", "password"))
+            .await
+            .unwrap();
+        (pds, session)
+    }
+
+    let (temp_a, temp_b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    let (_pds_a, a) = account(&temp_a).await;
+    let (_pds_b, b) = account(&temp_b).await;
+    let posts = Nsid::new("org.muat.test.post").unwrap();
+    let likes = Nsid::new("org.muat.test.like").unwrap();
+    let value = |text: &str| {
+        RecordValue::with_type("org.muat.test.post", serde_json::json!({ "text": text })).unwrap()
+    };
+    let rkey = |rkey: &str| Rkey::new(rkey).unwrap();
+
+    for session in [&a, &b] {
+        session
+            .put_record(&posts, &rkey("same"), &value("same"), None)
+            .await
+            .unwrap();
+        session
+            .put_record(
+                &posts,
+                &rkey("edited"),
+                &value(session.did().as_str()),
+                None,
+            )
+            .await
+            .unwrap();
+    }
+    a.put_record(&likes, &rkey("removed"), &value("removed"), None)
+        .await
+        .unwrap();
+    b.put_record(&posts, &rkey("added"), &value("added"), None)
+        .await
+        .unwrap();
+
+    let diff = muat_core::sync::diff_repos_between(&a, a.did(), &b, b.did())
+        .await
+        .unwrap();
+    assert_eq!(diff.unchanged, 1);
+    assert_eq!(diff.len(), 3);
+    let keys = |changes: &[muat_core::sync::RecordChange]| {
+        changes
+            .iter()
+            .map(|change| format!("{}/{}", change.collection, change.rkey))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(keys(&diff.created), ["org.muat.test.post/added"]);
+    assert_eq!(keys(&diff.updated), ["org.muat.test.post/edited"]);
+    assert_eq!(keys(&diff.deleted), ["org.muat.test.like/removed"]);
+    assert!(diff.created[0].old_cid.is_none());
+    assert_ne!(diff.updated[0].old_cid, diff.updated[0].new_cid);
+    assert!(diff.deleted[0].new_cid.is_none());
+
+    let same = muat_core::sync::diff_repos(&a, &a, a.did()).await.unwrap();
+    assert!(same.is_empty());
+    assert_eq!(same.unchanged, 3);
+}
+
+#[tokio::test]
+async fn test_accounts_with_legacy_hex_dids_are_migrated() {
+    // Earlier versions took the DID from the first 24 hex digits of a UUID.
+    let temp = tempfile::tempdir().unwrap();
+    let old = "did:plc:9f1c0b8e4a2d47e1b3c5d6e7";
+    let new = "did:plc:jpbmalioekcnehobldmfngoh";
+    let account_dir = temp.path().join("pds/accounts").join(old.replace(':', "_"));
+    std::fs::create_dir_all(&account_dir).unwrap();
+    let account = serde_json::json!({
+        "did": old,
+        "handle": "alice.local",
+        "created_at": "2024-01-01T00:00:00Z",
+        "password_hash": bcrypt::hash("password", 4).unwrap(),
+    });
+    std::fs::write(account_dir.join("account.json"), account.to_string()).unwrap();
+    let event = serde_json::json!({
+        "uri": format!("at://{}", old),
+        "time": "2024-01-01T00:00:00Z",
+        "op": "account_create",
+        "handle": "alice.local",
+    });
+    let firehose = temp.path().join("pds/firehose.jsonl");
+    std::fs::write(&firehose, format!("{}\n", event)).unwrap();
+
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    let pds = FilePds::new(temp.path(), pds_url);
+    assert!(!account_dir.exists());
+    assert!(!std::fs::read_to_string(&firehose).unwrap().contains(old));
+
+    let session = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    assert_eq!(session.did().as_str(), new);
+
+    let posts = Nsid::new("org.muat.test.post").unwrap();
+    let value =
+        RecordValue::with_type("org.muat.test.post", serde_json::json!({ "text": "hi" })).unwrap();
+    let uri = session.create_record(&posts, &value).await.unwrap();
+    assert_eq!(uri.repo().as_str(), new);
+    session.get_record(&uri).await.unwrap();
+}
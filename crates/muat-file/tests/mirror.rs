@@ -0,0 +1,119 @@
+//! Mirroring a repo from one file-backed PDS into another.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use muat_core::sync::diff_repos_public;
+use muat_core::traits::{CursorStore, Pds, Session};
+use muat_core::{
+    AtUri, CancellationToken, Credentials, MemoryCursorStore, Nsid, PdsUrl, RecordValue, Rkey,
+};
+use muat_file::{FilePds, FileSession, Mirror};
+
+fn pds(temp: &tempfile::TempDir) -> FilePds {
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    FilePds::new(temp.path(), pds_url)
+}
+
+async fn alice(pds: &FilePds) -> FileSession {
+    pds.create_account("alice.local", Some("password"), None, None)
+        .await
+        .unwrap();
+    pds.login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap()
+}
+
+fn post(text: &str) -> RecordValue {
+    RecordValue::with_type("org.muat.test.post", serde_json::json!({ "text": text })).unwrap()
+}
+
+#[tokio::test]
+async fn test_backfill_copies_and_resyncs_a_repo() {
+    let (temp_source, temp_local) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    let (source, local) = (pds(&temp_source), pds(&temp_local));
+    let session = alice(&source).await;
+    let posts = Nsid::new("org.muat.test.post").unwrap();
+    let rkey = |rkey: &str| Rkey::new(rkey).unwrap();
+    for key in ["one", "two", "three"] {
+        session
+            .put_record(&posts, &rkey(key), &post(key), None)
+            .await
+            .unwrap();
+    }
+
+    let mirror = Mirror::new(source.clone(), local.clone(), &[session.did().clone()]);
+    let report = mirror.backfill().await.unwrap();
+    assert_eq!(report.created, 3);
+    let diff = diff_repos_public(&source, &local, session.did())
+        .await
+        .unwrap();
+    assert!(diff.is_empty());
+
+    session
+        .put_record(&posts, &rkey("two"), &post("edited"), None)
+        .await
+        .unwrap();
+    let uri = |key: &str| AtUri::from_parts(session.did().clone(), posts.clone(), rkey(key));
+    session.delete_record(&uri("three")).await.unwrap();
+
+    let report = mirror.backfill().await.unwrap();
+    assert_eq!(
+        (
+            report.created,
+            report.updated,
+            report.deleted,
+            report.unchanged
+        ),
+        (0, 1, 1, 1)
+    );
+    let copy = local.get_record_public(&uri("two")).await.unwrap();
+    assert_eq!(copy.value, post("edited"));
+    assert!(local.get_record_public(&uri("three")).await.is_err());
+}
+
+#[tokio::test]
+async fn test_run_follows_the_firehose_and_saves_the_cursor() {
+    let (temp_source, temp_local) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+    let (source, local) = (pds(&temp_source), pds(&temp_local));
+    let session = alice(&source).await;
+    let posts = Nsid::new("org.muat.test.post").unwrap();
+    let before = session
+        .create_record(&posts, &post("before"))
+        .await
+        .unwrap();
+
+    let cursors = Arc::new(MemoryCursorStore::new());
+    let mirror = Mirror::new(source.clone(), local.clone(), &[session.did().clone()])
+        .with_cursor_store(cursors.clone());
+    let cancel = CancellationToken::new();
+    let running = tokio::spawn({
+        let (mirror, cancel) = (mirror.clone(), cancel.clone());
+        async move { mirror.run(cancel).await }
+    });
+
+    // The backfill has finished, and the firehose is subscribed, once the
+    // existing record is copied.
+    wait_for(&local, &before).await;
+    let after = session.create_record(&posts, &post("after")).await.unwrap();
+    wait_for(&local, &after).await;
+
+    cancel.cancel();
+    let report = running.await.unwrap().unwrap();
+    assert_eq!(report.created, 1);
+    assert!(report.commits >= 1);
+    let saved = cursors.load(source.url().as_ref()).await.unwrap();
+    assert_eq!(saved, report.cursor);
+    assert!(saved.is_some());
+}
+
+async fn wait_for(local: &FilePds, uri: &AtUri) {
+    let copied = async {
+        while local.get_record_public(uri).await.is_err() {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(5), copied)
+        .await
+        .expect("record was not mirrored");
+}
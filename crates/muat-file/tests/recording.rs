@@ -0,0 +1,131 @@
+//! Recording a firehose to disk and replaying it.
+
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use muat_core::repo::{CommitEvent, InfoEvent, RepoEvent};
+use muat_core::traits::{Pds, Session};
+use muat_core::{Credentials, FirehoseExt, Nsid, PdsUrl, RecordValue};
+use muat_file::{FilePds, FirehoseRecorder, FirehoseReplayer};
+use serde_json::json;
+
+fn commit(seq: i64) -> RepoEvent {
+    RepoEvent::Commit(CommitEvent {
+        repo: "did:plc:aliceaaaaaaaaaaaaaaaaaaa".to_string(),
+        rev: format!("rev-{}", seq),
+        since: None,
+        seq,
+        time: "2024-01-01T00:00:00Z".parse().unwrap(),
+        ops: vec![],
+        blocks: Vec::new(),
+        blobs: Vec::new(),
+    })
+}
+
+async fn replay_all(path: &std::path::Path) -> Vec<RepoEvent> {
+    FirehoseReplayer::open(path)
+        .await
+        .unwrap()
+        .map(|event| event.unwrap())
+        .collect()
+        .await
+}
+
+#[tokio::test]
+async fn test_record_and_replay_round_trip() {
+    let temp = tempfile::tempdir().unwrap();
+    let path = temp.path().join("events.jsonl");
+
+    let events = vec![
+        Ok(RepoEvent::Info(InfoEvent {
+            name: "OutdatedCursor".to_string(),
+            message: None,
+        })),
+        Ok(commit(1)),
+        Ok(RepoEvent::Unknown {
+            kind: "#sync".to_string(),
+        }),
+        Ok(commit(2)),
+    ];
+
+    let mut recorder = FirehoseRecorder::open(&path).await.unwrap();
+    let count = recorder
+        .record_all(futures_util::stream::iter(events))
+        .await
+        .unwrap();
+    assert_eq!(count, 4);
+
+    let replayed = replay_all(&path).await;
+    assert_eq!(replayed.len(), 4);
+    assert!(matches!(&replayed[0], RepoEvent::Info(info) if info.name == "OutdatedCursor"));
+    assert!(matches!(&replayed[1], RepoEvent::Commit(c) if c.seq == 1));
+    assert!(matches!(&replayed[2], RepoEvent::Unknown { kind } if kind == "#sync"));
+    assert!(matches!(&replayed[3], RepoEvent::Commit(c) if c.seq == 2));
+
+    // Reopening appends rather than truncating.
+    let mut recorder = FirehoseRecorder::open(&path).await.unwrap();
+    recorder.record(&commit(3)).await.unwrap();
+    assert_eq!(replay_all(&path).await.len(), 5);
+}
+
+#[tokio::test]
+async fn test_replay_reports_malformed_line() {
+    let temp = tempfile::tempdir().unwrap();
+    let path = temp.path().join("events.jsonl");
+
+    let mut recorder = FirehoseRecorder::open(&path).await.unwrap();
+    recorder.record(&commit(1)).await.unwrap();
+    drop(recorder);
+    let mut contents = std::fs::read_to_string(&path).unwrap();
+    contents.push_str("not json\n");
+    std::fs::write(&path, contents).unwrap();
+
+    let mut replay = FirehoseReplayer::open(&path).await.unwrap();
+    assert!(replay.next().await.unwrap().is_ok());
+    assert!(replay.next().await.unwrap().is_err());
+    assert!(replay.next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_record_file_pds_firehose() {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    let pds = FilePds::new(temp.path(), pds_url);
+    pds.create_account("alice.local", Some("password"), None, None)
+        .await
+        .unwrap();
+    let session = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let value = RecordValue::with_type("org.muat.test.record", json!({})).unwrap();
+    let mut uris = Vec::new();
+    for _ in 0..3 {
+        uris.push(session.create_record(&collection, &value).await.unwrap());
+    }
+
+    let path = temp.path().join("events.jsonl");
+    let mut recorder = FirehoseRecorder::open(&path).await.unwrap();
+    let live = pds.firehose_from(Some(0)).unwrap().commits_only().take(3);
+    let count = tokio::time::timeout(Duration::from_secs(10), recorder.record_all(live))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(count, 3);
+
+    let paths: Vec<String> = replay_all(&path)
+        .await
+        .into_iter()
+        .filter_map(|event| match event {
+            RepoEvent::Commit(commit) => Some(commit.ops[0].path.clone()),
+            _ => None,
+        })
+        .collect();
+    let expected: Vec<String> = uris
+        .iter()
+        .map(|uri| format!("{}/{}", uri.collection().unwrap(), uri.rkey().unwrap()))
+        .collect();
+    assert_eq!(paths, expected);
+}
@@ -0,0 +1,215 @@
+//! Shared conformance suite run against the SQLite storage engine, plus
+//! checks specific to it.
+
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use muat_core::repo::RepoEvent;
+use muat_core::testing::Fixture;
+use muat_core::traits::{Pds, Session};
+use muat_core::{Credentials, ListRecordsOptions, Nsid, PdsUrl, RecordValue, Rkey};
+use muat_file::FilePds;
+
+async fn fixture() -> Fixture<FilePds> {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file+sqlite://{}", temp.path().display())).unwrap();
+    let pds = FilePds::new(temp.path(), pds_url);
+
+    pds.create_account("alice.local", Some("password"), None, None)
+        .await
+        .unwrap();
+
+    Fixture::new(pds, "alice.local", "password").with_guard(temp)
+}
+
+muat_core::conformance_tests!(fixture());
+
+fn value(text: &str) -> RecordValue {
+    RecordValue::with_type("org.muat.test.record", serde_json::json!({ "text": text })).unwrap()
+}
+
+#[tokio::test]
+async fn test_sqlite_url_and_builder_select_database() {
+    let fixture = fixture().await;
+    let pds = &fixture.pds;
+    let session = fixture.login().await;
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    session
+        .create_record(&collection, &value("a"))
+        .await
+        .unwrap();
+
+    assert!(pds.root().join("pds").join("pds.sqlite").exists());
+    assert!(!pds.root().join("pds").join("repos").exists());
+    assert!(!pds.root().join("pds").join("firehose.jsonl").exists());
+
+    // The builder reaches the same database from a plain file:// URL.
+    let url = PdsUrl::new(format!("file://{}", pds.root().display())).unwrap();
+    let reopened = FilePds::new(pds.root(), url).with_sqlite();
+    assert_eq!(reopened.account_count().unwrap(), 1);
+    let session = reopened
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    let page = session
+        .list_records(session.did(), &collection, None, None)
+        .await
+        .unwrap();
+    assert_eq!(page.records.len(), 1);
+}
+
+#[tokio::test]
+async fn test_bulk_create_writes_transactions_with_numbered_events() {
+    let fixture = fixture().await;
+    let pds = &fixture.pds;
+    let session = fixture.login().await;
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+
+    let values: Vec<_> = (0..250).map(|i| value(&i.to_string())).collect();
+    let report = session
+        .create_records_bulk(&collection, values, 4, None)
+        .await;
+    assert!(report.is_complete());
+    assert_eq!(report.succeeded.len(), 250);
+
+    // The account event is 1; each record is one commit after it.
+    let seqs: Vec<i64> = tokio::time::timeout(
+        Duration::from_secs(10),
+        pds.firehose_from(Some(0))
+            .unwrap()
+            .map(|event| match event.unwrap() {
+                RepoEvent::Commit(commit) => commit.seq,
+                RepoEvent::Identity(identity) => identity.seq,
+                other => panic!("unexpected event {:?}", other),
+            })
+            .take(251)
+            .collect(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(seqs, (1..=251).collect::<Vec<_>>());
+
+    let page = session
+        .list_records_with(
+            session.did(),
+            &collection,
+            &ListRecordsOptions::new().limit(100),
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.records.len(), 100);
+    assert_eq!(
+        page.records[0].uri.rkey(),
+        report.succeeded[0].rkey(),
+        "records list in rkey order"
+    );
+}
+
+#[tokio::test]
+async fn test_paging_and_history_read_the_database() {
+    let fixture = fixture().await;
+    let pds = &fixture.pds;
+    let session = fixture.login().await;
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+
+    for rkey in ["a", "b", "c"] {
+        session
+            .put_record(&collection, &Rkey::new(rkey).unwrap(), &value(rkey), None)
+            .await
+            .unwrap();
+    }
+    let uri_b = session
+        .put_record(&collection, &Rkey::new("b").unwrap(), &value("b2"), None)
+        .await
+        .unwrap();
+
+    let options = ListRecordsOptions::new().limit(2).reverse(true);
+    let first = session
+        .list_records_with(session.did(), &collection, &options)
+        .await
+        .unwrap();
+    let rkeys: Vec<_> = first
+        .records
+        .iter()
+        .map(|r| r.uri.rkey().unwrap().to_string())
+        .collect();
+    assert_eq!(rkeys, ["c", "b"]);
+    assert_eq!(first.cursor.as_deref(), Some("b"));
+
+    let rest = session
+        .list_records_with(
+            session.did(),
+            &collection,
+            &options.clone().cursor(first.cursor.unwrap()),
+        )
+        .await
+        .unwrap();
+    assert_eq!(rest.records.len(), 1);
+    assert_eq!(rest.records[0].uri.rkey().unwrap().as_str(), "a");
+    assert!(rest.cursor.is_none());
+
+    // Account creation is event 1, then the three creates and the update.
+    let before_update = pds.get_record_as_of(&uri_b, 4).await.unwrap();
+    assert_eq!(before_update.value.get("text").unwrap(), "b");
+    let after_update = pds.get_record_as_of(&uri_b, 5).await.unwrap();
+    assert_eq!(
+        after_update.cid,
+        session.get_record(&uri_b).await.unwrap().cid
+    );
+    let then = pds
+        .list_records_as_of(session.did(), &collection, 2, &ListRecordsOptions::new())
+        .await
+        .unwrap();
+    assert_eq!(then.records.len(), 1);
+
+    let history = pds.record_history(&uri_b).await.unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|version| version.seq)
+            .collect::<Vec<_>>(),
+        [3, 5]
+    );
+    let pinned = session
+        .get_record_at(&uri_b, &before_update.cid)
+        .await
+        .unwrap();
+    assert_eq!(pinned.value.get("text").unwrap(), "b");
+}
+
+#[tokio::test]
+async fn test_invalid_record_fails_its_bulk_transaction() {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file+sqlite://{}", temp.path().display())).unwrap();
+    let pds =
+        FilePds::new(temp.path(), pds_url).with_validator(|_: &Nsid, record: &RecordValue| {
+            match record.get("text") {
+                Some(text) if text == "bad" => Err("text is bad".to_string()),
+                _ => Ok(()),
+            }
+        });
+    pds.create_account("alice.local", Some("password"), None, None)
+        .await
+        .unwrap();
+    let session = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+
+    let report = session
+        .create_records_bulk(
+            &collection,
+            vec![value("a"), value("bad"), value("c")],
+            4,
+            None,
+        )
+        .await;
+    assert_eq!(report.failed.len(), 3);
+    assert!(report.succeeded.is_empty());
+    let page = session
+        .list_records(session.did(), &collection, None, None)
+        .await
+        .unwrap();
+    assert!(page.records.is_empty());
+}
@@ -0,0 +1,141 @@
+//! PLC directory client.
+
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use tracing::{debug, instrument};
+
+use muat_core::error::{Error, ProtocolError, TransportError};
+use muat_core::{Did, Result};
+use muat_xrpc::{HttpMethod, HttpRequest, HttpResponse, HttpTransport};
+
+use crate::operation::SignedPlcOperation;
+
+/// The public PLC directory.
+pub const DEFAULT_PLC_DIRECTORY: &str = "https://plc.directory";
+
+/// Client for a PLC directory such as `plc.directory`.
+///
+/// # Example
+///
+/// ```no_run
+/// use muat_core::Did;
+/// use muat_plc::{Keypair, KeyAlgorithm, PlcClient};
+///
+/// # async fn example(rotation_key: Keypair) -> Result<(), muat_core::Error> {
+/// let client = PlcClient::new();
+/// let did = Did::new("did:plc:ewvi7nxzyoun6zhxrhs64oiz")?;
+///
+/// let last = client.get_last_operation(&did).await?;
+/// let mut update = last.next()?;
+/// update.set_handle("alice.example.com");
+/// client.submit(&did, &update.sign(&rotation_key)?).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct PlcClient {
+    base_url: String,
+    transport: Arc<dyn HttpTransport>,
+}
+
+#[derive(Deserialize)]
+struct DirectoryError {
+    message: Option<String>,
+}
+
+impl PlcClient {
+    /// Create a client for `plc.directory` using the default transport.
+    #[cfg(feature = "reqwest")]
+    pub fn new() -> Self {
+        Self::with_transport(
+            DEFAULT_PLC_DIRECTORY,
+            Arc::new(muat_xrpc::ReqwestTransport::new()),
+        )
+    }
+
+    /// Create a client for another directory, sending requests through
+    /// `transport`.
+    pub fn with_transport(base_url: impl Into<String>, transport: Arc<dyn HttpTransport>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            transport,
+        }
+    }
+
+    /// Returns the directory URL.
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Fetch every operation for a DID, oldest first.
+    #[instrument(skip(self), fields(%did))]
+    pub async fn get_log(&self, did: &Did) -> Result<Vec<SignedPlcOperation>> {
+        self.get(&format!("{}/{}/log", self.base_url, did)).await
+    }
+
+    /// Fetch the latest operation for a DID.
+    #[instrument(skip(self), fields(%did))]
+    pub async fn get_last_operation(&self, did: &Did) -> Result<SignedPlcOperation> {
+        self.get(&format!("{}/{}/log/last", self.base_url, did))
+            .await
+    }
+
+    /// Submit a signed operation for a DID.
+    ///
+    /// For a genesis operation, `did` must be [`SignedPlcOperation::did`].
+    #[instrument(skip(self, operation), fields(%did))]
+    pub async fn submit(&self, did: &Did, operation: &SignedPlcOperation) -> Result<()> {
+        debug!("Submitting PLC operation");
+
+        let body = serde_json::to_vec(operation).map_err(|e| {
+            Error::Transport(TransportError::Http {
+                message: format!("failed to encode PLC operation: {}", e),
+            })
+        })?;
+        let mut request = HttpRequest::new(HttpMethod::Post, format!("{}/{}", self.base_url, did));
+        request
+            .headers
+            .push(("content-type".to_string(), "application/json".to_string()));
+        request.body = Some(body);
+
+        let response = self.transport.send(request).await?;
+        check_status(&response)
+    }
+
+    async fn get<R: DeserializeOwned>(&self, url: &str) -> Result<R> {
+        let response = self
+            .transport
+            .send(HttpRequest::new(HttpMethod::Get, url))
+            .await?;
+        check_status(&response)?;
+
+        serde_json::from_slice(&response.body).map_err(|e| {
+            Error::Transport(TransportError::Http {
+                message: format!("error decoding PLC directory response: {}", e),
+            })
+        })
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl Default for PlcClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn check_status(response: &HttpResponse) -> Result<()> {
+    if response.is_success() {
+        return Ok(());
+    }
+    let message = serde_json::from_slice::<DirectoryError>(&response.body)
+        .ok()
+        .and_then(|e| e.message);
+    Err(Error::Protocol(ProtocolError::new(
+        response.status,
+        None,
+        message,
+    )))
+}
@@ -0,0 +1,211 @@
+//! Signing keys and `did:key` encoding.
+//!
+//! AT Protocol accepts secp256k1 (K-256) and NIST P-256 keys. Signatures are
+//! ECDSA over SHA-256 in compact `r || s` form with a low S value.
+
+use std::fmt;
+
+use k256::ecdsa::signature::{Signer, Verifier};
+use rand_core::OsRng;
+
+use muat_core::Result;
+use muat_core::crypto::keys::{KeyAlgorithm, PublicKey};
+use muat_core::error::{Error, InvalidInputError};
+use muat_core::repo::Commit;
+
+/// A private signing key.
+///
+/// The secret is never shown in `Debug` output.
+#[derive(Clone)]
+pub enum Keypair {
+    /// A secp256k1 key.
+    Secp256k1(k256::ecdsa::SigningKey),
+    /// A P-256 key.
+    P256(p256::ecdsa::SigningKey),
+}
+
+impl Keypair {
+    /// Generate a new random key.
+    pub fn generate(algorithm: KeyAlgorithm) -> Self {
+        match algorithm {
+            KeyAlgorithm::Secp256k1 => Self::Secp256k1(k256::ecdsa::SigningKey::random(&mut OsRng)),
+            KeyAlgorithm::P256 => Self::P256(p256::ecdsa::SigningKey::random(&mut OsRng)),
+        }
+    }
+
+    /// Load a key from its 32-byte secret scalar.
+    pub fn from_secret_bytes(algorithm: KeyAlgorithm, bytes: &[u8]) -> Result<Self> {
+        let invalid = |_| {
+            Error::InvalidInput(InvalidInputError::Other {
+                message: "invalid private key".to_string(),
+            })
+        };
+        Ok(match algorithm {
+            KeyAlgorithm::Secp256k1 => {
+                Self::Secp256k1(k256::ecdsa::SigningKey::from_slice(bytes).map_err(invalid)?)
+            }
+            KeyAlgorithm::P256 => {
+                Self::P256(p256::ecdsa::SigningKey::from_slice(bytes).map_err(invalid)?)
+            }
+        })
+    }
+
+    /// Returns the 32-byte secret scalar, for storing the key.
+    ///
+    /// # Security
+    ///
+    /// Anyone holding these bytes can sign as this key.
+    pub fn secret_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Secp256k1(key) => key.to_bytes().to_vec(),
+            Self::P256(key) => key.to_bytes().to_vec(),
+        }
+    }
+
+    /// Returns the algorithm of this key.
+    pub fn algorithm(&self) -> KeyAlgorithm {
+        match self {
+            Self::Secp256k1(_) => KeyAlgorithm::Secp256k1,
+            Self::P256(_) => KeyAlgorithm::P256,
+        }
+    }
+
+    /// Returns the public key as a `did:key`.
+    pub fn did_key(&self) -> String {
+        let point = match self {
+            Self::Secp256k1(key) => key.verifying_key().to_encoded_point(true).to_bytes(),
+            Self::P256(key) => key.verifying_key().to_encoded_point(true).to_bytes(),
+        };
+
+        let mut bytes = self.algorithm().multicodec_prefix().to_vec();
+        bytes.extend_from_slice(&point);
+        format!("did:key:z{}", bs58::encode(bytes).into_string())
+    }
+
+    /// Sign `data`, returning a 64-byte low-S signature.
+    pub fn sign(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Secp256k1(key) => {
+                let sig: k256::ecdsa::Signature = key.sign(data);
+                sig.normalize_s().unwrap_or(sig).to_bytes().to_vec()
+            }
+            Self::P256(key) => {
+                let sig: p256::ecdsa::Signature = key.sign(data);
+                sig.normalize_s().unwrap_or(sig).to_bytes().to_vec()
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keypair")
+            .field("algorithm", &self.algorithm())
+            .field("did_key", &self.did_key())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Verify a signature made by the key named by `did_key`.
+///
+/// High-S signatures are rejected, as atproto requires.
+pub fn verify_signature(did_key: &str, data: &[u8], signature: &[u8]) -> Result<()> {
+    let bad_signature = || {
+        Error::InvalidInput(InvalidInputError::Other {
+            message: "signature does not match".to_string(),
+        })
+    };
+    let bad_point = || {
+        Error::InvalidInput(InvalidInputError::Key {
+            value: did_key.to_string(),
+            reason: "not a point on the curve".to_string(),
+        })
+    };
+
+    let public = PublicKey::from_did_key(did_key)?;
+    match public.algorithm() {
+        KeyAlgorithm::Secp256k1 => {
+            let key = k256::ecdsa::VerifyingKey::from_sec1_bytes(public.as_bytes())
+                .map_err(|_| bad_point())?;
+            let sig = k256::ecdsa::Signature::from_slice(signature).map_err(|_| bad_signature())?;
+            if sig.normalize_s().is_some() {
+                return Err(bad_signature());
+            }
+            key.verify(data, &sig).map_err(|_| bad_signature())
+        }
+        KeyAlgorithm::P256 => {
+            let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(public.as_bytes())
+                .map_err(|_| bad_point())?;
+            let sig = p256::ecdsa::Signature::from_slice(signature).map_err(|_| bad_signature())?;
+            if sig.normalize_s().is_some() {
+                return Err(bad_signature());
+            }
+            key.verify(data, &sig).map_err(|_| bad_signature())
+        }
+    }
+}
+
+/// Verify a repo commit was signed by `did_key`, the repo's signing key
+/// (the `atproto` verification method of its DID document).
+///
+/// Together with [`muat_core::repo::verify_inclusion`], this shows a record
+/// fetched from any server is what the repo's owner published.
+pub fn verify_commit(commit: &Commit, did_key: &str) -> Result<()> {
+    verify_signature(did_key, commit.unsigned_bytes(), &commit.sig)
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD_NO_PAD;
+
+    use super::*;
+
+    #[test]
+    fn did_key_prefixes_match_curve() {
+        // Multibase prefixes are fixed by the multicodec prefix and key size.
+        let k256 = Keypair::generate(KeyAlgorithm::Secp256k1).did_key();
+        let p256 = Keypair::generate(KeyAlgorithm::P256).did_key();
+        assert!(k256.starts_with("did:key:zQ3s"), "{}", k256);
+        assert!(p256.starts_with("did:key:zDn"), "{}", p256);
+    }
+
+    #[test]
+    fn sign_and_verify_round_trip() {
+        for algorithm in [KeyAlgorithm::Secp256k1, KeyAlgorithm::P256] {
+            let key = Keypair::generate(algorithm);
+            let sig = key.sign(b"hello");
+            assert_eq!(sig.len(), 64);
+            verify_signature(&key.did_key(), b"hello", &sig).unwrap();
+            assert!(verify_signature(&key.did_key(), b"goodbye", &sig).is_err());
+        }
+    }
+
+    #[test]
+    fn verifies_commit_signatures() {
+        let key = Keypair::generate(KeyAlgorithm::Secp256k1);
+        let mut unsigned = serde_json::json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "version": 3,
+            "data": { "$link": "bafyreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm" },
+            "rev": "3k2a",
+            "prev": null,
+        });
+        let sig = key.sign(&muat_core::repo::to_dag_cbor(&unsigned).unwrap());
+        unsigned["sig"] = serde_json::json!({ "$bytes": STANDARD_NO_PAD.encode(sig) });
+        let commit = Commit::decode(&muat_core::repo::to_dag_cbor(&unsigned).unwrap()).unwrap();
+
+        verify_commit(&commit, &key.did_key()).unwrap();
+        let other = Keypair::generate(KeyAlgorithm::Secp256k1);
+        assert!(verify_commit(&commit, &other.did_key()).is_err());
+    }
+
+    #[test]
+    fn secret_bytes_round_trip() {
+        let key = Keypair::generate(KeyAlgorithm::Secp256k1);
+        let restored =
+            Keypair::from_secret_bytes(KeyAlgorithm::Secp256k1, &key.secret_bytes()).unwrap();
+        assert_eq!(key.did_key(), restored.did_key());
+        assert!(!format!("{:?}", key).contains("secret"));
+    }
+}
@@ -0,0 +1,22 @@
+//! muat-plc - `did:plc` operations and key management.
+//!
+//! This crate builds, signs and verifies PLC operations, derives `did:plc`
+//! identifiers from genesis operations, and submits operations to a PLC
+//! directory. It is what handle changes and PDS migrations need to update an
+//! account's DID document.
+//!
+//! Keys are secp256k1 or P-256 and are named by their `did:key`; parsing
+//! `did:key`s is done by [`muat_core::crypto::keys`]. Storing rotation keys
+//! safely is left to the caller.
+
+mod client;
+mod keys;
+mod operation;
+
+pub use client::{DEFAULT_PLC_DIRECTORY, PlcClient};
+pub use keys::{Keypair, verify_commit, verify_signature};
+pub use muat_core::crypto::keys::KeyAlgorithm;
+pub use operation::{
+    ATPROTO_PDS_SERVICE, ATPROTO_PDS_TYPE, ATPROTO_SIGNING_METHOD, PlcOperation, PlcService,
+    SignedPlcOperation,
+};
@@ -0,0 +1,263 @@
+//! PLC operations.
+//!
+//! An operation is the full state of a `did:plc` document: rotation keys,
+//! verification methods, handles (`alsoKnownAs`) and services. Each
+//! operation after the first names the CID of the one before it in `prev`
+//! and is signed by one of the previous operation's rotation keys.
+
+use std::collections::BTreeMap;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use muat_core::error::{Error, InvalidInputError};
+use muat_core::repo::{dag_cbor_cid, to_dag_cbor};
+use muat_core::types::base32_lower;
+use muat_core::{Did, Result};
+
+use crate::keys::{Keypair, verify_signature};
+
+/// Service ID of the account's PDS.
+pub const ATPROTO_PDS_SERVICE: &str = "atproto_pds";
+
+/// Service type of the account's PDS.
+pub const ATPROTO_PDS_TYPE: &str = "AtprotoPersonalDataServer";
+
+/// Verification method ID of the repo signing key.
+pub const ATPROTO_SIGNING_METHOD: &str = "atproto";
+
+/// A service endpoint in a PLC document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlcService {
+    /// The service type, e.g. `AtprotoPersonalDataServer`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The service URL.
+    pub endpoint: String,
+}
+
+/// An unsigned PLC operation (`plc_operation`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename = "plc_operation", rename_all = "camelCase")]
+pub struct PlcOperation {
+    /// `did:key`s allowed to sign the next operation, highest priority first.
+    pub rotation_keys: Vec<String>,
+    /// Verification methods by ID; `atproto` is the repo signing key.
+    pub verification_methods: BTreeMap<String, String>,
+    /// Other identifiers, e.g. `at://alice.example.com` for the handle.
+    pub also_known_as: Vec<String>,
+    /// Services by ID; `atproto_pds` is the account's PDS.
+    pub services: BTreeMap<String, PlcService>,
+    /// CID of the previous operation; `None` for the genesis operation.
+    pub prev: Option<String>,
+}
+
+impl PlcOperation {
+    /// Build a genesis operation for an atproto account.
+    pub fn genesis(
+        rotation_keys: Vec<String>,
+        signing_key: impl Into<String>,
+        handle: &str,
+        pds_endpoint: impl Into<String>,
+    ) -> Self {
+        let mut operation = Self {
+            rotation_keys,
+            verification_methods: BTreeMap::from([(
+                ATPROTO_SIGNING_METHOD.to_string(),
+                signing_key.into(),
+            )]),
+            also_known_as: Vec::new(),
+            services: BTreeMap::new(),
+            prev: None,
+        };
+        operation.set_handle(handle);
+        operation.set_pds(pds_endpoint);
+        operation
+    }
+
+    /// Returns the handle from `alsoKnownAs`, if any.
+    pub fn handle(&self) -> Option<&str> {
+        self.also_known_as
+            .iter()
+            .find_map(|aka| aka.strip_prefix("at://"))
+    }
+
+    /// Replace the handle, keeping any other `alsoKnownAs` entries.
+    pub fn set_handle(&mut self, handle: &str) {
+        self.also_known_as.retain(|aka| !aka.starts_with("at://"));
+        self.also_known_as.insert(0, format!("at://{}", handle));
+    }
+
+    /// Returns the repo signing key's `did:key`, if any.
+    pub fn signing_key(&self) -> Option<&str> {
+        self.verification_methods
+            .get(ATPROTO_SIGNING_METHOD)
+            .map(String::as_str)
+    }
+
+    /// Returns the PDS endpoint, if any.
+    pub fn pds(&self) -> Option<&str> {
+        self.services
+            .get(ATPROTO_PDS_SERVICE)
+            .map(|service| service.endpoint.as_str())
+    }
+
+    /// Replace the PDS endpoint.
+    pub fn set_pds(&mut self, endpoint: impl Into<String>) {
+        self.services.insert(
+            ATPROTO_PDS_SERVICE.to_string(),
+            PlcService {
+                kind: ATPROTO_PDS_TYPE.to_string(),
+                endpoint: endpoint.into(),
+            },
+        );
+    }
+
+    /// Sign this operation with a rotation key.
+    ///
+    /// The key must be one of the previous operation's rotation keys (or,
+    /// for genesis, this operation's) for the directory to accept it.
+    pub fn sign(self, key: &Keypair) -> Result<SignedPlcOperation> {
+        let bytes = to_dag_cbor(&to_value(&self)?)?;
+        let sig = URL_SAFE_NO_PAD.encode(key.sign(&bytes));
+        Ok(SignedPlcOperation {
+            operation: self,
+            sig,
+        })
+    }
+}
+
+/// A signed PLC operation, as stored in the directory's log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedPlcOperation {
+    /// The operation.
+    #[serde(flatten)]
+    pub operation: PlcOperation,
+    /// Base64url signature over the DAG-CBOR encoded operation.
+    pub sig: String,
+}
+
+impl SignedPlcOperation {
+    /// Returns the CID of this operation, used as `prev` by the next one.
+    pub fn cid(&self) -> Result<String> {
+        Ok(dag_cbor_cid(&to_dag_cbor(&to_value(self)?)?).into())
+    }
+
+    /// Derive the DID created by this genesis operation.
+    pub fn did(&self) -> Result<Did> {
+        if self.operation.prev.is_some() {
+            return Err(Error::InvalidInput(InvalidInputError::Other {
+                message: "only a genesis operation (prev = null) defines a DID".to_string(),
+            }));
+        }
+        let digest = Sha256::digest(to_dag_cbor(&to_value(self)?)?);
+        Did::new(format!("did:plc:{}", &base32_lower(&digest)[..24]))
+    }
+
+    /// Check the signature was made by one of `rotation_keys`.
+    ///
+    /// Pass the previous operation's rotation keys, or this operation's own
+    /// for a genesis operation.
+    pub fn verify(&self, rotation_keys: &[String]) -> Result<()> {
+        let bytes = to_dag_cbor(&to_value(&self.operation)?)?;
+        let sig = URL_SAFE_NO_PAD.decode(&self.sig).map_err(|_| {
+            Error::InvalidInput(InvalidInputError::Other {
+                message: "signature is not base64url".to_string(),
+            })
+        })?;
+
+        if rotation_keys
+            .iter()
+            .any(|key| verify_signature(key, &bytes, &sig).is_ok())
+        {
+            Ok(())
+        } else {
+            Err(Error::InvalidInput(InvalidInputError::Other {
+                message: "operation is not signed by any rotation key".to_string(),
+            }))
+        }
+    }
+
+    /// Start the next operation: a copy of this one with `prev` set to its
+    /// CID. Modify the result and [`sign`](PlcOperation::sign) it.
+    pub fn next(&self) -> Result<PlcOperation> {
+        let mut operation = self.operation.clone();
+        operation.prev = Some(self.cid()?);
+        Ok(operation)
+    }
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<serde_json::Value> {
+    serde_json::to_value(value).map_err(|e| {
+        Error::InvalidInput(InvalidInputError::Other {
+            message: format!("failed to encode PLC operation: {}", e),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use muat_core::crypto::keys::KeyAlgorithm;
+    use serde_json::json;
+
+    fn genesis(rotation: &Keypair) -> SignedPlcOperation {
+        let signing = Keypair::generate(KeyAlgorithm::Secp256k1);
+        PlcOperation::genesis(
+            vec![rotation.did_key()],
+            signing.did_key(),
+            "alice.test",
+            "https://pds.test",
+        )
+        .sign(rotation)
+        .unwrap()
+    }
+
+    #[test]
+    fn json_shape_matches_directory_format() {
+        let op = genesis(&Keypair::generate(KeyAlgorithm::P256));
+        let value = serde_json::to_value(&op).unwrap();
+
+        assert_eq!(value["type"], "plc_operation");
+        assert_eq!(value["prev"], json!(null));
+        assert_eq!(value["alsoKnownAs"], json!(["at://alice.test"]));
+        assert_eq!(
+            value["services"]["atproto_pds"],
+            json!({"type": "AtprotoPersonalDataServer", "endpoint": "https://pds.test"})
+        );
+
+        let parsed: SignedPlcOperation = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, op);
+    }
+
+    #[test]
+    fn genesis_derives_did_and_verifies() {
+        let rotation = Keypair::generate(KeyAlgorithm::Secp256k1);
+        let op = genesis(&rotation);
+
+        let did = op.did().unwrap();
+        assert_eq!(did.as_str().len(), "did:plc:".len() + 24);
+        op.verify(&op.operation.rotation_keys).unwrap();
+
+        let other = Keypair::generate(KeyAlgorithm::Secp256k1);
+        assert!(op.verify(&[other.did_key()]).is_err());
+    }
+
+    #[test]
+    fn next_links_to_previous_cid() {
+        let rotation = Keypair::generate(KeyAlgorithm::Secp256k1);
+        let op = genesis(&rotation);
+
+        let mut update = op.next().unwrap();
+        update.set_handle("alice.example.com");
+        let update = update.sign(&rotation).unwrap();
+
+        assert_eq!(update.operation.prev, Some(op.cid().unwrap()));
+        assert!(op.cid().unwrap().starts_with("bafyrei"));
+        assert_eq!(update.operation.handle(), Some("alice.example.com"));
+        assert!(update.did().is_err());
+        update.verify(&op.operation.rotation_keys).unwrap();
+    }
+}
@@ -0,0 +1,85 @@
+//! PLC directory client tests against a mock directory.
+
+use std::sync::Arc;
+
+use muat_plc::{KeyAlgorithm, Keypair, PlcClient, PlcOperation};
+use muat_xrpc::ReqwestTransport;
+use serde_json::json;
+use wiremock::matchers::{body_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn client(server: &MockServer) -> PlcClient {
+    PlcClient::with_transport(server.uri(), Arc::new(ReqwestTransport::new()))
+}
+
+#[tokio::test]
+async fn test_genesis_then_handle_update() {
+    let server = MockServer::start().await;
+    let client = client(&server);
+
+    let rotation = Keypair::generate(KeyAlgorithm::Secp256k1);
+    let signing = Keypair::generate(KeyAlgorithm::Secp256k1);
+    let genesis = PlcOperation::genesis(
+        vec![rotation.did_key()],
+        signing.did_key(),
+        "alice.test",
+        "https://pds.test",
+    )
+    .sign(&rotation)
+    .unwrap();
+    let did = genesis.did().unwrap();
+
+    Mock::given(method("POST"))
+        .and(path(format!("/{}", did)))
+        .and(body_json(serde_json::to_value(&genesis).unwrap()))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+    client.submit(&did, &genesis).await.unwrap();
+
+    Mock::given(method("GET"))
+        .and(path(format!("/{}/log/last", did)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&genesis))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/{}/log", did)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([genesis])))
+        .mount(&server)
+        .await;
+
+    let last = client.get_last_operation(&did).await.unwrap();
+    assert_eq!(last, genesis);
+    assert_eq!(client.get_log(&did).await.unwrap().len(), 1);
+
+    let mut update = last.next().unwrap();
+    update.set_handle("alice.example.com");
+    let update = update.sign(&rotation).unwrap();
+    update.verify(&last.operation.rotation_keys).unwrap();
+    assert_eq!(update.operation.prev, Some(genesis.cid().unwrap()));
+}
+
+#[tokio::test]
+async fn test_directory_errors_are_protocol_errors() {
+    let server = MockServer::start().await;
+    let client = client(&server);
+
+    Mock::given(method("GET"))
+        .and(path("/did:plc:aaaaaaaaaaaaaaaaaaaaaaaa/log/last"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "message": "DID not registered: did:plc:aaaaaaaaaaaaaaaaaaaaaaaa"
+        })))
+        .mount(&server)
+        .await;
+
+    let did = muat_core::Did::new("did:plc:aaaaaaaaaaaaaaaaaaaaaaaa").unwrap();
+    let err = client.get_last_operation(&did).await.unwrap_err();
+    match err {
+        muat_core::Error::Protocol(e) => {
+            assert_eq!(e.status, 404);
+            assert!(e.message.unwrap().contains("not registered"));
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
@@ -0,0 +1,124 @@
+//! XRPC error responses.
+
+use axum::Json;
+use axum::extract::rejection::{JsonRejection, QueryRejection};
+use axum::http::{StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+use tracing::error;
+
+use muat_core::Error;
+use muat_core::error::AuthError;
+
+/// An XRPC error, sent as `{"error": ..., "message": ...}`.
+#[derive(Debug)]
+pub(crate) struct XrpcError {
+    status: StatusCode,
+    error: String,
+    message: String,
+}
+
+impl XrpcError {
+    pub(crate) fn new(status: StatusCode, error: &str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            error: error.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn invalid_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "InvalidRequest", message)
+    }
+
+    pub(crate) fn authentication_required(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, "AuthenticationRequired", message)
+    }
+}
+
+impl From<Error> for XrpcError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Auth(AuthError::InvalidCredentials(message)) => {
+                Self::authentication_required(message)
+            }
+            Error::Auth(AuthError::AccountUnavailable { reason }) => {
+                Self::new(StatusCode::UNAUTHORIZED, "AccountDeactivated", reason)
+            }
+            Error::Auth(AuthError::AccountSuspended(message)) => {
+                Self::new(StatusCode::UNAUTHORIZED, "AccountSuspended", message)
+            }
+            Error::Auth(AuthError::AccountTakendown(message)) => {
+                Self::new(StatusCode::UNAUTHORIZED, "AccountTakedown", message)
+            }
+            Error::Auth(e @ AuthError::RateLimited { .. }) => Self::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "RateLimitExceeded",
+                e.to_string(),
+            ),
+            Error::Auth(e) => Self::new(StatusCode::UNAUTHORIZED, "ExpiredToken", e.to_string()),
+            Error::InvalidInput(e) => Self::invalid_request(e.to_string()),
+            Error::Protocol(e) => {
+                let status =
+                    StatusCode::from_u16(e.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+                let code = e.error.unwrap_or_else(|| {
+                    if status.is_client_error() {
+                        "InvalidRequest".to_string()
+                    } else {
+                        "InternalServerError".to_string()
+                    }
+                });
+                Self {
+                    status,
+                    error: code,
+                    message: e.message.unwrap_or_default(),
+                }
+            }
+            Error::Transport(e) => Self::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "InternalServerError",
+                e.to_string(),
+            ),
+            Error::Firehose(e) => Self::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "InternalServerError",
+                e.to_string(),
+            ),
+        }
+    }
+}
+
+impl From<JsonRejection> for XrpcError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::invalid_request(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for XrpcError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::invalid_request(rejection.body_text())
+    }
+}
+
+impl IntoResponse for XrpcError {
+    fn into_response(self) -> Response {
+        if self.status.is_server_error() {
+            error!(error = %self.error, message = %self.message, "XRPC request failed");
+        }
+        (
+            self.status,
+            Json(json!({ "error": self.error, "message": self.message })),
+        )
+            .into_response()
+    }
+}
+
+/// Fallback for XRPC methods this server does not implement.
+pub(crate) async fn method_not_implemented(uri: Uri) -> XrpcError {
+    let method = uri.path().strip_prefix("/xrpc/").unwrap_or(uri.path());
+    XrpcError::new(
+        StatusCode::NOT_IMPLEMENTED,
+        "MethodNotImplemented",
+        format!("Method not implemented: {}", method),
+    )
+}
@@ -0,0 +1,42 @@
+//! Request extractors: bearer-token sessions, and JSON bodies and query
+//! strings that reject with XRPC errors.
+
+use axum::extract::{FromRequest, FromRequestParts};
+use axum::http::header::AUTHORIZATION;
+use axum::http::request::Parts;
+
+use muat_core::AccessToken;
+use muat_file::{FilePds, FileSession};
+
+use crate::error::XrpcError;
+
+/// The session named by the request's `Authorization: Bearer` token.
+pub(crate) struct Authed(pub(crate) FileSession);
+
+impl FromRequestParts<FilePds> for Authed {
+    type Rejection = XrpcError;
+
+    async fn from_request_parts(parts: &mut Parts, pds: &FilePds) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| XrpcError::authentication_required("Authentication Required"))?;
+
+        let session = FileSession::from_persisted(pds.clone(), AccessToken::new(token))
+            .map_err(|_| XrpcError::authentication_required("Invalid token"))?;
+        session.validate()?;
+        Ok(Self(session))
+    }
+}
+
+/// A JSON request body.
+#[derive(FromRequest)]
+#[from_request(via(axum::Json), rejection(XrpcError))]
+pub(crate) struct XrpcJson<T>(pub(crate) T);
+
+/// XRPC query parameters.
+#[derive(FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(XrpcError))]
+pub(crate) struct XrpcQuery<T>(pub(crate) T);
@@ -0,0 +1,191 @@
+//! muat-serve - Serve a file-backed PDS over XRPC.
+//!
+//! [`FileServer`] exposes a [`FilePds`] on real HTTP and WebSocket endpoints,
+//! so any AT Protocol client can be tested against a local PDS directory:
+//!
+//! - `com.atproto.server`: `describeServer`, `createAccount`,
+//!   `createSession`, `getSession`, `refreshSession`,
+//!   `requestEmailConfirmation`, `confirmEmail`, `requestPasswordReset`,
+//!   `resetPassword`
+//! - `com.atproto.repo`: `createRecord`, `getRecord`, `listRecords`,
+//!   `deleteRecord`, `uploadBlob`
+//! - `com.atproto.sync.getBlob`, honouring HTTP `Range` headers
+//! - `com.atproto.sync.subscribeRepos` over WebSocket
+//!
+//! Access tokens are the file backend's own tokens. Reads require a token for
+//! the repo being read, as they do in-process.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use muat_core::PdsUrl;
+//! use muat_file::FilePds;
+//! use muat_serve::FileServer;
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let pds = FilePds::new("./pds", PdsUrl::new("file://./pds")?);
+//! let listener = tokio::net::TcpListener::bind("127.0.0.1:2583").await?;
+//! FileServer::new(pds).serve(listener).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod blob;
+mod error;
+mod extract;
+mod repo;
+mod server;
+mod sync;
+
+use std::future::Future;
+use std::time::Instant;
+
+use axum::Router;
+use axum::extract::Request;
+use axum::middleware::{self, Next};
+use axum::response::Response;
+use axum::routing::{get, post};
+use tokio::net::TcpListener;
+use tracing::info;
+
+use muat_core::Result;
+use muat_core::error::{Error, TransportError};
+use muat_file::FilePds;
+
+/// An XRPC server backed by a [`FilePds`].
+#[derive(Debug, Clone)]
+pub struct FileServer {
+    pds: FilePds,
+}
+
+impl FileServer {
+    /// Create a server for a file-backed PDS.
+    pub fn new(pds: FilePds) -> Self {
+        Self { pds }
+    }
+
+    /// Returns the PDS being served.
+    pub fn pds(&self) -> &FilePds {
+        &self.pds
+    }
+
+    /// Build the router, for mounting in another axum application.
+    ///
+    /// Each request is logged at `info` level with its method, path, status
+    /// and duration. Query strings and headers are not logged.
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route(
+                "/xrpc/com.atproto.server.describeServer",
+                get(server::describe_server),
+            )
+            .route(
+                "/xrpc/com.atproto.server.createAccount",
+                post(server::create_account),
+            )
+            .route(
+                "/xrpc/com.atproto.server.createSession",
+                post(server::create_session),
+            )
+            .route(
+                "/xrpc/com.atproto.server.getSession",
+                get(server::get_session),
+            )
+            .route(
+                "/xrpc/com.atproto.server.refreshSession",
+                post(server::refresh_session),
+            )
+            .route(
+                "/xrpc/com.atproto.server.requestEmailConfirmation",
+                post(server::request_email_confirmation),
+            )
+            .route(
+                "/xrpc/com.atproto.server.confirmEmail",
+                post(server::confirm_email),
+            )
+            .route(
+                "/xrpc/com.atproto.server.requestPasswordReset",
+                post(server::request_password_reset),
+            )
+            .route(
+                "/xrpc/com.atproto.server.resetPassword",
+                post(server::reset_password),
+            )
+            .route(
+                "/xrpc/com.atproto.server.checkAccountStatus",
+                get(server::check_account_status),
+            )
+            .route(
+                "/xrpc/com.atproto.server.activateAccount",
+                post(server::activate_account),
+            )
+            .route(
+                "/xrpc/com.atproto.server.deactivateAccount",
+                post(server::deactivate_account),
+            )
+            .route(
+                "/xrpc/com.atproto.repo.createRecord",
+                post(repo::create_record),
+            )
+            .route("/xrpc/com.atproto.repo.getRecord", get(repo::get_record))
+            .route(
+                "/xrpc/com.atproto.repo.listRecords",
+                get(repo::list_records),
+            )
+            .route("/xrpc/com.atproto.repo.putRecord", post(repo::put_record))
+            .route(
+                "/xrpc/com.atproto.repo.deleteRecord",
+                post(repo::delete_record),
+            )
+            .route("/xrpc/com.atproto.repo.uploadBlob", post(blob::upload_blob))
+            .route("/xrpc/com.atproto.sync.getBlob", get(blob::get_blob))
+            .route(
+                "/xrpc/com.atproto.sync.subscribeRepos",
+                get(sync::subscribe_repos),
+            )
+            .fallback(error::method_not_implemented)
+            .layer(middleware::from_fn(log_request))
+            .with_state(self.pds.clone())
+    }
+
+    /// Serve requests on `listener` until the process exits.
+    pub async fn serve(self, listener: TcpListener) -> Result<()> {
+        self.serve_with_shutdown(listener, std::future::pending())
+            .await
+    }
+
+    /// Serve requests on `listener` until `shutdown` completes.
+    pub async fn serve_with_shutdown<F>(self, listener: TcpListener, shutdown: F) -> Result<()>
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        if let Ok(addr) = listener.local_addr() {
+            info!(%addr, root = %self.pds.root().display(), "Serving local PDS");
+        }
+
+        axum::serve(listener, self.router())
+            .with_graceful_shutdown(shutdown)
+            .await
+            .map_err(|e| {
+                Error::Transport(TransportError::Connection {
+                    message: format!("server error: {}", e),
+                })
+            })
+    }
+}
+
+async fn log_request(request: Request, next: Next) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+    info!(
+        %method,
+        %path,
+        status = response.status().as_u16(),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "XRPC request"
+    );
+    response
+}
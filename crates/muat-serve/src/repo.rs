@@ -0,0 +1,227 @@
+//! `com.atproto.repo.*` handlers.
+
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use muat_core::repo::{ListRecordsOptions, Record, RecordOrder};
+use muat_core::traits::Session;
+use muat_core::{AtUri, Did, Nsid, RecordValue, Rkey};
+use muat_file::FilePds;
+
+use crate::error::XrpcError;
+use crate::extract::{Authed, XrpcJson, XrpcQuery};
+
+#[derive(Deserialize)]
+pub(crate) struct CreateRecordInput {
+    repo: String,
+    collection: String,
+    record: Value,
+    rkey: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct CreateRecordOutput {
+    uri: String,
+    cid: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GetRecordParams {
+    repo: String,
+    collection: String,
+    rkey: String,
+    cid: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ListRecordsParams {
+    repo: String,
+    collection: String,
+    limit: Option<u32>,
+    cursor: Option<String>,
+    reverse: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct ListRecordsOutput {
+    records: Vec<RecordOutput>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct RecordOutput {
+    uri: String,
+    cid: String,
+    value: Value,
+}
+
+impl From<Record> for RecordOutput {
+    fn from(record: Record) -> Self {
+        Self {
+            uri: record.uri.to_string(),
+            cid: record.cid,
+            value: record.value.into_value(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PutRecordInput {
+    repo: String,
+    collection: String,
+    rkey: String,
+    record: Value,
+    swap_record: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct DeleteRecordInput {
+    repo: String,
+    collection: String,
+    rkey: String,
+}
+
+pub(crate) async fn create_record(
+    State(pds): State<FilePds>,
+    Authed(session): Authed,
+    XrpcJson(input): XrpcJson<CreateRecordInput>,
+) -> Result<Json<CreateRecordOutput>, XrpcError> {
+    let repo = resolve_repo(&pds, &input.repo)?;
+    if &repo != session.did() {
+        return Err(XrpcError::authentication_required(
+            "Cannot write to another account's repo",
+        ));
+    }
+
+    let collection = Nsid::new(input.collection)?;
+    let value = RecordValue::new(input.record)?;
+    let uri = match input.rkey {
+        Some(rkey) => {
+            session
+                .create_record_with_rkey(&collection, &Rkey::new(rkey)?, &value)
+                .await?
+        }
+        None => session.create_record(&collection, &value).await?,
+    };
+    let record = session.get_record(&uri).await?;
+
+    Ok(Json(CreateRecordOutput {
+        uri: uri.to_string(),
+        cid: record.cid,
+    }))
+}
+
+pub(crate) async fn put_record(
+    State(pds): State<FilePds>,
+    Authed(session): Authed,
+    XrpcJson(input): XrpcJson<PutRecordInput>,
+) -> Result<Json<CreateRecordOutput>, XrpcError> {
+    let repo = resolve_repo(&pds, &input.repo)?;
+    if &repo != session.did() {
+        return Err(XrpcError::authentication_required(
+            "Cannot write to another account's repo",
+        ));
+    }
+
+    let collection = Nsid::new(input.collection)?;
+    let rkey = Rkey::new(input.rkey)?;
+    let value = RecordValue::new(input.record)?;
+    let uri = session
+        .put_record(&collection, &rkey, &value, input.swap_record.as_deref())
+        .await?;
+    let record = session.get_record(&uri).await?;
+
+    Ok(Json(CreateRecordOutput {
+        uri: uri.to_string(),
+        cid: record.cid,
+    }))
+}
+
+pub(crate) async fn get_record(
+    State(pds): State<FilePds>,
+    Authed(session): Authed,
+    XrpcQuery(params): XrpcQuery<GetRecordParams>,
+) -> Result<Json<RecordOutput>, XrpcError> {
+    let uri = record_uri(&pds, &params.repo, params.collection, params.rkey)?;
+    let record = session.get_record(&uri).await?;
+
+    if let Some(cid) = params.cid
+        && cid != record.cid
+    {
+        return Err(XrpcError::new(
+            StatusCode::NOT_FOUND,
+            "RecordNotFound",
+            format!("Could not locate record: {} at {}", uri, cid),
+        ));
+    }
+
+    Ok(Json(record.into()))
+}
+
+pub(crate) async fn list_records(
+    State(pds): State<FilePds>,
+    Authed(session): Authed,
+    XrpcQuery(params): XrpcQuery<ListRecordsParams>,
+) -> Result<Json<ListRecordsOutput>, XrpcError> {
+    let repo = resolve_repo(&pds, &params.repo)?;
+    let collection = Nsid::new(params.collection)?;
+
+    // XRPC lists newest first unless `reverse` is set, the opposite of the
+    // Session default.
+    let order = match params.reverse {
+        Some(true) => RecordOrder::Ascending,
+        _ => RecordOrder::Descending,
+    };
+    let mut options = ListRecordsOptions::new().order(order);
+    if let Some(limit) = params.limit {
+        options = options.limit(limit);
+    }
+    if let Some(cursor) = params.cursor {
+        options = options.cursor(cursor);
+    }
+
+    let output = session
+        .list_records_with(&repo, &collection, &options)
+        .await?;
+    Ok(Json(ListRecordsOutput {
+        records: output.records.into_iter().map(RecordOutput::from).collect(),
+        cursor: output.cursor,
+    }))
+}
+
+pub(crate) async fn delete_record(
+    State(pds): State<FilePds>,
+    Authed(session): Authed,
+    XrpcJson(input): XrpcJson<DeleteRecordInput>,
+) -> Result<Json<Value>, XrpcError> {
+    let uri = record_uri(&pds, &input.repo, input.collection, input.rkey)?;
+    session.delete_record(&uri).await?;
+    Ok(Json(json!({})))
+}
+
+/// Resolve a `repo` parameter, which may be a DID or a local handle.
+fn resolve_repo(pds: &FilePds, repo: &str) -> Result<Did, XrpcError> {
+    if repo.starts_with("did:") {
+        return Ok(Did::new(repo)?);
+    }
+    pds.resolve_handle(repo)?
+        .ok_or_else(|| XrpcError::invalid_request(format!("Could not find repo: {}", repo)))
+}
+
+fn record_uri(
+    pds: &FilePds,
+    repo: &str,
+    collection: String,
+    rkey: String,
+) -> Result<AtUri, XrpcError> {
+    Ok(AtUri::from_parts(
+        resolve_repo(pds, repo)?,
+        Nsid::new(collection)?,
+        Rkey::new(rkey)?,
+    ))
+}
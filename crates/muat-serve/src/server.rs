@@ -0,0 +1,214 @@
+//! `com.atproto.server.*` handlers.
+
+use axum::Json;
+use axum::extract::State;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use muat_core::traits::{AccountStatus, Pds, Session};
+use muat_core::{Credentials, Did};
+use muat_file::{FilePds, FileSession};
+
+use crate::error::XrpcError;
+use crate::extract::{Authed, XrpcJson};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DescribeServerOutput {
+    did: String,
+    available_user_domains: Vec<String>,
+    invite_code_required: bool,
+    phone_verification_required: bool,
+}
+
+pub(crate) async fn describe_server(
+    State(pds): State<FilePds>,
+) -> Result<Json<DescribeServerOutput>, XrpcError> {
+    let description = pds.describe().await?;
+    Ok(Json(DescribeServerOutput {
+        did: description.did,
+        available_user_domains: description.available_user_domains,
+        invite_code_required: description.invite_code_required,
+        phone_verification_required: description.phone_verification_required,
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CreateAccountInput {
+    handle: String,
+    password: Option<String>,
+    email: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct CreateSessionInput {
+    identifier: String,
+    password: String,
+}
+
+/// Session output shared by `createAccount`, `createSession` and
+/// `refreshSession`.
+///
+/// File sessions have a single long-lived token, so it is returned as both
+/// the access and the refresh token.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SessionOutput {
+    did: String,
+    handle: String,
+    access_jwt: String,
+    refresh_jwt: String,
+    active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct GetSessionOutput {
+    did: String,
+    handle: String,
+    active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<&'static str>,
+}
+
+pub(crate) async fn create_account(
+    State(pds): State<FilePds>,
+    XrpcJson(input): XrpcJson<CreateAccountInput>,
+) -> Result<Json<SessionOutput>, XrpcError> {
+    debug!(handle = %input.handle, "createAccount");
+    let password = input
+        .password
+        .ok_or_else(|| XrpcError::invalid_request("Password is required"))?;
+
+    let created = pds
+        .create_account(&input.handle, Some(&password), input.email.as_deref(), None)
+        .await?;
+    let session = pds
+        .login(Credentials::new(created.did.as_str(), password))
+        .await?;
+    Ok(Json(session_output(&pds, &session)?))
+}
+
+pub(crate) async fn create_session(
+    State(pds): State<FilePds>,
+    XrpcJson(input): XrpcJson<CreateSessionInput>,
+) -> Result<Json<SessionOutput>, XrpcError> {
+    debug!(identifier = %input.identifier, "createSession");
+    let session = pds
+        .login(Credentials::new(input.identifier, input.password))
+        .await?;
+    Ok(Json(session_output(&pds, &session)?))
+}
+
+pub(crate) async fn get_session(
+    State(pds): State<FilePds>,
+    Authed(session): Authed,
+) -> Result<Json<GetSessionOutput>, XrpcError> {
+    let active = pds.is_active(session.did())?;
+    Ok(Json(GetSessionOutput {
+        did: session.did().to_string(),
+        handle: handle_of(&pds, session.did())?,
+        active,
+        status: inactive_status(active),
+    }))
+}
+
+pub(crate) async fn refresh_session(
+    State(pds): State<FilePds>,
+    Authed(session): Authed,
+) -> Result<Json<SessionOutput>, XrpcError> {
+    Ok(Json(session_output(&pds, &session)?))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ConfirmEmailInput {
+    email: String,
+    token: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct RequestPasswordResetInput {
+    email: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ResetPasswordInput {
+    token: String,
+    password: String,
+}
+
+pub(crate) async fn request_email_confirmation(Authed(session): Authed) -> Result<(), XrpcError> {
+    Ok(session.request_email_confirmation().await?)
+}
+
+pub(crate) async fn confirm_email(
+    Authed(session): Authed,
+    XrpcJson(input): XrpcJson<ConfirmEmailInput>,
+) -> Result<(), XrpcError> {
+    Ok(session.confirm_email(&input.email, &input.token).await?)
+}
+
+pub(crate) async fn request_password_reset(
+    State(pds): State<FilePds>,
+    XrpcJson(input): XrpcJson<RequestPasswordResetInput>,
+) -> Result<(), XrpcError> {
+    Ok(pds.request_password_reset(&input.email).await?)
+}
+
+pub(crate) async fn reset_password(
+    State(pds): State<FilePds>,
+    XrpcJson(input): XrpcJson<ResetPasswordInput>,
+) -> Result<(), XrpcError> {
+    Ok(pds.reset_password(&input.token, &input.password).await?)
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DeactivateAccountInput {
+    delete_after: Option<DateTime<Utc>>,
+}
+
+pub(crate) async fn check_account_status(
+    Authed(session): Authed,
+) -> Result<Json<AccountStatus>, XrpcError> {
+    Ok(Json(session.check_account_status().await?))
+}
+
+pub(crate) async fn activate_account(Authed(session): Authed) -> Result<(), XrpcError> {
+    Ok(session.activate_account().await?)
+}
+
+pub(crate) async fn deactivate_account(
+    Authed(session): Authed,
+    XrpcJson(input): XrpcJson<DeactivateAccountInput>,
+) -> Result<(), XrpcError> {
+    Ok(session.deactivate_account(input.delete_after).await?)
+}
+
+fn session_output(pds: &FilePds, session: &FileSession) -> Result<SessionOutput, XrpcError> {
+    let token = session.access_token().as_str().to_string();
+    let active = pds.is_active(session.did())?;
+    Ok(SessionOutput {
+        did: session.did().to_string(),
+        handle: handle_of(pds, session.did())?,
+        access_jwt: token.clone(),
+        refresh_jwt: token,
+        active,
+        status: inactive_status(active),
+    })
+}
+
+/// The `status` of an inactive session's account; a file-backed account
+/// can only be inactive by deactivating it.
+fn inactive_status(active: bool) -> Option<&'static str> {
+    (!active).then_some("deactivated")
+}
+
+fn handle_of(pds: &FilePds, did: &Did) -> Result<String, XrpcError> {
+    pds.handle_of(did)?
+        .ok_or_else(|| XrpcError::authentication_required("Account not found"))
+}
@@ -0,0 +1,214 @@
+//! `com.atproto.sync.subscribeRepos` over WebSocket.
+//!
+//! Each event is one binary message: a DAG-CBOR header (`op`, `t`) followed
+//! by a DAG-CBOR body, as in the AT Protocol event stream framing. The file
+//! backend has no repository blocks or commit CIDs, so `blocks` is always
+//! empty, `commit` is omitted, and op CIDs are sent as strings.
+
+use axum::extract::State;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::response::Response;
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use muat_core::repo::RepoEvent;
+use muat_core::traits::Pds;
+use muat_file::{FileFirehose, FilePds};
+
+use crate::error::XrpcError;
+use crate::extract::XrpcQuery;
+
+#[derive(Deserialize)]
+pub(crate) struct SubscribeParams {
+    cursor: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct Header<'a> {
+    op: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    t: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CommitBody<'a> {
+    seq: i64,
+    rebase: bool,
+    too_big: bool,
+    repo: &'a str,
+    rev: &'a str,
+    since: Option<&'a str>,
+    #[serde(with = "serde_bytes")]
+    blocks: &'a [u8],
+    ops: Vec<OpBody<'a>>,
+    blobs: Vec<String>,
+    time: String,
+}
+
+#[derive(Serialize)]
+struct OpBody<'a> {
+    action: &'a str,
+    path: &'a str,
+    cid: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct IdentityBody<'a> {
+    seq: i64,
+    did: &'a str,
+    time: String,
+}
+
+#[derive(Serialize)]
+struct HandleBody<'a> {
+    seq: i64,
+    did: &'a str,
+    handle: &'a str,
+    time: String,
+}
+
+#[derive(Serialize)]
+struct InfoBody<'a> {
+    name: &'a str,
+    message: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str,
+    message: &'a str,
+}
+
+pub(crate) async fn subscribe_repos(
+    State(pds): State<FilePds>,
+    XrpcQuery(params): XrpcQuery<SubscribeParams>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, XrpcError> {
+    // Open the firehose before upgrading so a bad cursor or unreadable log
+    // is reported as an HTTP error.
+    let firehose = pds.firehose_from(params.cursor)?;
+    debug!(cursor = ?params.cursor, "subscribeRepos");
+    Ok(ws.on_upgrade(move |socket| stream_events(socket, firehose)))
+}
+
+async fn stream_events(mut socket: WebSocket, mut firehose: FileFirehose) {
+    loop {
+        tokio::select! {
+            event = firehose.next() => {
+                let frame = match event {
+                    Some(Ok(event)) => match encode_event(&event) {
+                        Some(frame) => frame,
+                        None => continue,
+                    },
+                    Some(Err(e)) => {
+                        warn!(error = %e, "Firehose failed");
+                        if let Some(frame) = encode_error("InternalError", &e.to_string()) {
+                            let _ = socket.send(Message::Binary(frame.into())).await;
+                        }
+                        break;
+                    }
+                    None => break,
+                };
+                if socket.send(Message::Binary(frame.into())).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => {
+                match message {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                }
+            }
+        }
+    }
+    debug!("subscribeRepos closed");
+}
+
+/// Encode an event as a frame, or `None` for events with no wire form.
+fn encode_event(event: &RepoEvent) -> Option<Vec<u8>> {
+    match event {
+        RepoEvent::Commit(commit) => encode_frame(
+            "#commit",
+            &CommitBody {
+                seq: commit.seq,
+                rebase: false,
+                too_big: false,
+                repo: &commit.repo,
+                rev: &commit.rev,
+                since: None,
+                blocks: &[],
+                ops: commit
+                    .ops
+                    .iter()
+                    .map(|op| OpBody {
+                        action: &op.action,
+                        path: &op.path,
+                        cid: op.cid.as_deref(),
+                    })
+                    .collect(),
+                blobs: Vec::new(),
+                time: wire_time(commit.time),
+            },
+        ),
+        RepoEvent::Identity(identity) => encode_frame(
+            "#identity",
+            &IdentityBody {
+                seq: identity.seq,
+                did: &identity.did,
+                time: wire_time(identity.time),
+            },
+        ),
+        RepoEvent::Handle(handle) => encode_frame(
+            "#handle",
+            &HandleBody {
+                seq: handle.seq,
+                did: &handle.did,
+                handle: &handle.handle,
+                time: wire_time(handle.time),
+            },
+        ),
+        RepoEvent::Info(info) => encode_frame(
+            "#info",
+            &InfoBody {
+                name: &info.name,
+                message: info.message.as_deref(),
+            },
+        ),
+        RepoEvent::Unknown { .. } => None,
+    }
+}
+
+fn wire_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+fn encode_frame<B: Serialize>(kind: &str, body: &B) -> Option<Vec<u8>> {
+    encode(
+        &Header {
+            op: 1,
+            t: Some(kind),
+        },
+        body,
+    )
+}
+
+fn encode_error(error: &str, message: &str) -> Option<Vec<u8>> {
+    encode(&Header { op: -1, t: None }, &ErrorBody { error, message })
+}
+
+fn encode<B: Serialize>(header: &Header<'_>, body: &B) -> Option<Vec<u8>> {
+    let result = serde_ipld_dagcbor::to_vec(header).and_then(|mut frame| {
+        frame.extend(serde_ipld_dagcbor::to_vec(body)?);
+        Ok(frame)
+    });
+    match result {
+        Ok(frame) => Some(frame),
+        Err(e) => {
+            warn!(error = %e, "Failed to encode firehose frame");
+            None
+        }
+    }
+}
@@ -0,0 +1,415 @@
+//! Runs the XRPC client from muat-xrpc against a served file PDS.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use serde::Deserialize;
+use tempfile::TempDir;
+use tokio_tungstenite::tungstenite::Message;
+
+use muat_core::error::Error;
+use muat_core::testing::{
+    Fixture, check_blob_ranges, check_list_records_order, check_login_or_restore,
+    check_migrate_collection, check_put_record,
+};
+use muat_core::traits::{Pds, Session};
+use muat_core::{Credentials, ListRecordsOptions, Nsid, PdsUrl, RecordValue, Rkey, Tid};
+use muat_file::FilePds;
+use muat_serve::FileServer;
+use muat_xrpc::XrpcPds;
+
+async fn start() -> (FilePds, SocketAddr, TempDir) {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    let pds = FilePds::new(temp.path(), pds_url);
+    pds.create_account("alice.local", Some("password"), None, None)
+        .await
+        .unwrap();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(FileServer::new(pds.clone()).serve(listener));
+
+    (pds, addr, temp)
+}
+
+fn client(addr: SocketAddr) -> XrpcPds {
+    XrpcPds::new(PdsUrl::new(format!("http://{}", addr)).unwrap())
+}
+
+#[tokio::test]
+async fn test_xrpc_client_round_trip() {
+    let (_pds, addr, _temp) = start().await;
+    let pds = client(addr);
+
+    let description = pds.describe().await.unwrap();
+    assert_eq!(description.did, "did:web:localhost");
+
+    let session = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    session.validate().await.unwrap();
+
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let value = RecordValue::with_type(
+        "org.muat.test.record",
+        serde_json::json!({ "text": "served" }),
+    )
+    .unwrap();
+    let uri = session.create_record(&collection, &value).await.unwrap();
+
+    let record = session.get_record(&uri).await.unwrap();
+    assert_eq!(record.value.get("text").unwrap(), "served");
+
+    let page = session
+        .list_records(session.did(), &collection, None, None)
+        .await
+        .unwrap();
+    assert_eq!(page.records.len(), 1);
+    assert_eq!(page.records[0].uri, uri);
+
+    session.delete_record(&uri).await.unwrap();
+    let page = session
+        .list_records(session.did(), &collection, None, None)
+        .await
+        .unwrap();
+    assert!(page.records.is_empty());
+}
+
+#[tokio::test]
+async fn test_user_domains_are_advertised_and_enforced() {
+    let temp = tempfile::tempdir().unwrap();
+    let pds_url = PdsUrl::new(format!("file://{}", temp.path().display())).unwrap();
+    let pds = FilePds::new(temp.path(), pds_url).with_user_domains([".pds.example.org"]);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(FileServer::new(pds.clone()).serve(listener));
+    let client = client(addr);
+
+    let description = client.describe().await.unwrap();
+    assert_eq!(description.available_user_domains, vec![".pds.example.org"]);
+
+    let created = client
+        .create_account("alice.pds.example.org", Some("password"), None, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        pds.handle_of(&created.did).unwrap().unwrap(),
+        "alice.pds.example.org"
+    );
+
+    // Checked by the client first, and by the served PDS as well.
+    let err = client
+        .create_account("bob.local", Some("password"), None, None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::InvalidInput(_)), "{:?}", err);
+    assert!(
+        pds.create_account("bob.local", Some("password"), None, None)
+            .await
+            .is_err()
+    );
+    assert_eq!(pds.account_count().unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_email_confirmation_and_password_reset_over_xrpc() {
+    let (pds, addr, _temp) = start().await;
+    pds.create_account("bob.local", Some("password"), Some("bob@example.com"), None)
+        .await
+        .unwrap();
+    let client = client(addr);
+
+    let session = client
+        .login(Credentials::new("bob.local", "password"))
+        .await
+        .unwrap();
+    session.request_email_confirmation().await.unwrap();
+    let token = pds.sent_mail("bob@example.com").unwrap().unwrap().token;
+    let err = session
+        .confirm_email("bob@example.com", "AAAAA-AAAAA")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, Error::Protocol(e) if e.error.as_deref() == Some("InvalidToken")),
+        "{:?}",
+        err
+    );
+    session
+        .confirm_email("bob@example.com", &token)
+        .await
+        .unwrap();
+
+    client
+        .request_password_reset("bob@example.com")
+        .await
+        .unwrap();
+    let token = pds.sent_mail("bob@example.com").unwrap().unwrap().token;
+    client.reset_password(&token, "new password").await.unwrap();
+
+    assert!(session.validate().await.is_err());
+    client
+        .login(Credentials::new("bob.local", "new password"))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_errors_use_xrpc_shape() {
+    let (_pds, addr, _temp) = start().await;
+    let pds = client(addr);
+
+    match pds.login(Credentials::new("alice.local", "wrong")).await {
+        Err(Error::Protocol(e)) => {
+            assert_eq!(e.status, 401);
+            assert_eq!(e.error.as_deref(), Some("AuthenticationRequired"));
+        }
+        other => panic!("expected a protocol error, got {:?}", other.map(|_| ())),
+    }
+
+    let response = reqwest::get(format!(
+        "http://{}/xrpc/com.atproto.repo.listRecords?repo=alice.local&collection=a.b.c",
+        addr
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 401);
+
+    let response = reqwest::get(format!("http://{}/xrpc/com.example.missing", addr))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 501);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["error"], "MethodNotImplemented");
+}
+
+#[tokio::test]
+async fn test_put_record_and_migration_over_xrpc() {
+    let (_pds, addr, _temp) = start().await;
+    let session = client(addr)
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+
+    check_put_record(&session, &Nsid::new("org.muat.test.put").unwrap()).await;
+    check_migrate_collection(&session, &Nsid::new("org.muat.test.migrate").unwrap()).await;
+}
+
+#[tokio::test]
+async fn test_login_or_restore_over_xrpc() {
+    let (_pds, addr, temp) = start().await;
+    let fixture = Fixture::new(client(addr), "alice.local", "password").with_guard(temp);
+    check_login_or_restore(&fixture).await;
+}
+
+#[tokio::test]
+async fn test_get_blob_honours_ranges() {
+    let (_pds, addr, _temp) = start().await;
+    let session = client(addr)
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    check_blob_ranges(&session).await;
+
+    let blob = session
+        .upload_blob(b"0123456789".to_vec(), "text/plain")
+        .await
+        .unwrap();
+    let token = session.access_token();
+    let http = reqwest::Client::new();
+    let get = |range: &'static str| {
+        http.get(format!(
+            "http://{}/xrpc/com.atproto.sync.getBlob?did={}&cid={}",
+            addr,
+            session.did(),
+            blob.cid
+        ))
+        .bearer_auth(token.as_str())
+        .header("range", range)
+        .send()
+    };
+
+    let response = get("bytes=2-4").await.unwrap();
+    assert_eq!(response.status(), 206);
+    assert_eq!(response.headers()["content-range"], "bytes 2-4/*");
+    assert_eq!(response.bytes().await.unwrap().as_ref(), b"234");
+
+    let response = get("bytes=-3").await.unwrap();
+    assert_eq!(response.status(), 206);
+    assert_eq!(response.headers()["content-range"], "bytes 7-9/10");
+    assert_eq!(response.bytes().await.unwrap().as_ref(), b"789");
+
+    // Multiple ranges are ignored, and the whole blob is sent.
+    let response = get("bytes=0-1,4-5").await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["accept-ranges"], "bytes");
+    assert_eq!(response.bytes().await.unwrap().as_ref(), b"0123456789");
+
+    for range in ["bytes=10-", "bytes=11-20"] {
+        let response = get(range).await.unwrap();
+        assert_eq!(response.status(), 416, "{range}");
+        assert_eq!(response.headers()["content-range"], "bytes */10", "{range}");
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct Header {
+    op: i64,
+    t: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CommitBody {
+    seq: i64,
+    repo: String,
+    ops: Vec<Op>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdentityBody {
+    did: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Op {
+    action: String,
+    path: String,
+}
+
+#[tokio::test]
+async fn test_subscribe_repos_streams_event_frames() {
+    let (pds, addr, _temp) = start().await;
+
+    let session = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let value = RecordValue::with_type("org.muat.test.record", serde_json::json!({})).unwrap();
+    let uri = session.create_record(&collection, &value).await.unwrap();
+
+    let url = format!(
+        "ws://{}/xrpc/com.atproto.sync.subscribeRepos?cursor=0",
+        addr
+    );
+    let (mut socket, _) = tokio_tungstenite::connect_async(url).await.unwrap();
+
+    let mut frames = Vec::new();
+    for _ in 0..2 {
+        let frame = tokio::time::timeout(Duration::from_secs(10), socket.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let Message::Binary(data) = frame else {
+            panic!("expected a binary frame, got {:?}", frame);
+        };
+        frames.push(data);
+    }
+
+    // Account creation comes first, as an identity event.
+    let mut reader = &frames[0][..];
+    let header: Header = serde_ipld_dagcbor::de::from_reader_once(&mut reader).unwrap();
+    let body: IdentityBody = serde_ipld_dagcbor::de::from_reader_once(&mut reader).unwrap();
+    assert_eq!((header.op, header.t.as_str()), (1, "#identity"));
+    assert_eq!(body.did, session.did().as_str());
+
+    let mut reader = &frames[1][..];
+    let header: Header = serde_ipld_dagcbor::de::from_reader_once(&mut reader).unwrap();
+    let body: CommitBody = serde_ipld_dagcbor::de::from_reader_once(&mut reader).unwrap();
+
+    assert_eq!((header.op, header.t.as_str()), (1, "#commit"));
+    assert!(body.seq > 0);
+    assert_eq!(body.repo, session.did().as_str());
+    assert_eq!(body.ops[0].action, "create");
+    assert_eq!(
+        body.ops[0].path,
+        format!("{}/{}", collection, uri.rkey().unwrap())
+    );
+}
+
+#[tokio::test]
+async fn test_xrpc_firehose_counts_received_frames() {
+    let (pds, addr, _temp) = start().await;
+    let session = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let value = RecordValue::with_type("org.muat.test.record", serde_json::json!({})).unwrap();
+    session.create_record(&collection, &value).await.unwrap();
+
+    let mut firehose = client(addr).firehose_from(Some(0)).unwrap();
+    for _ in 0..2 {
+        tokio::time::timeout(Duration::from_secs(10), firehose.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+
+    let stats = firehose.stats();
+    assert_eq!(stats.events(), 2);
+    assert!(stats.bytes_received > 0);
+
+    firehose.reset_stats();
+    assert_eq!(firehose.stats().events(), 0);
+}
+
+#[tokio::test]
+async fn test_list_records_time_window_over_xrpc() {
+    let (pds, addr, _temp) = start().await;
+    let local = pds
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    let collection = Nsid::new("org.muat.test.record").unwrap();
+    let value = RecordValue::with_type("org.muat.test.record", serde_json::json!({})).unwrap();
+
+    let tids: Vec<Tid> = (1..=5)
+        .map(|month| {
+            let time = format!("2024-{:02}-01T00:00:00Z", month);
+            Tid::from_timestamp(time.parse().unwrap(), 0)
+        })
+        .collect();
+    for tid in &tids {
+        local
+            .create_record_with_rkey(&collection, &Rkey::new(tid.as_str()).unwrap(), &value)
+            .await
+            .unwrap();
+    }
+
+    let session = client(addr)
+        .login(Credentials::new("alice.local", "password"))
+        .await
+        .unwrap();
+    let expected: Vec<&str> = tids.iter().map(Tid::as_str).collect();
+    check_list_records_order(&session, session.did(), &collection, &expected).await;
+
+    let options = ListRecordsOptions::new()
+        .limit(1)
+        .since(tids[1].timestamp())
+        .until(tids[3].timestamp());
+
+    // Page through the window one record at a time.
+    let mut rkeys = Vec::new();
+    let mut options = options;
+    loop {
+        let page = session
+            .list_records_with(session.did(), &collection, &options)
+            .await
+            .unwrap();
+        rkeys.extend(
+            page.records
+                .iter()
+                .map(|r| r.uri.rkey().unwrap().to_string()),
+        );
+        match page.cursor {
+            Some(cursor) => options = options.cursor(cursor),
+            None => break,
+        }
+    }
+    assert_eq!(rkeys, [tids[1].as_str(), tids[2].as_str()]);
+}
@@ -0,0 +1,145 @@
+//! muat-sink-kafka - Publish firehose events to Kafka.
+//!
+//! [`KafkaSink`] is an [`EventSink`] that produces each event as a JSON
+//! message to a Kafka topic chosen by its [`TopicMapping`], keyed by the
+//! repo's DID so each repo's events land on one partition, in order.
+//! Consumer groups can then share the work of processing a firehose.
+//!
+//! librdkafka is built from source by default; enable the
+//! `dynamic-linking` feature to link the system library instead.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use rdkafka::ClientConfig;
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use tracing::{debug, instrument};
+
+use muat_core::Result;
+use muat_core::error::{Error, TransportError};
+use muat_core::repo::{SinkMessage, TopicMapping};
+use muat_core::traits::EventSink;
+
+pub use rdkafka;
+
+/// Produces firehose events to Kafka topics.
+///
+/// Each [`send`](EventSink::send) waits for the broker to acknowledge the
+/// message, so an event returned as published has been stored.
+///
+/// # Example
+///
+/// ```no_run
+/// use muat_core::{EventSink, TopicMapping};
+/// use muat_sink_kafka::KafkaSink;
+///
+/// # async fn example(firehose: impl muat_core::Firehose) -> muat_core::Result<()> {
+/// let topics = TopicMapping::by_collection("atproto.", "atproto.other");
+/// let sink = KafkaSink::new("localhost:9092", topics)?;
+/// let published = sink.publish_all(firehose).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct KafkaSink {
+    producer: FutureProducer,
+    topics: TopicMapping,
+    queue_timeout: Duration,
+}
+
+impl std::fmt::Debug for KafkaSink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KafkaSink")
+            .field("topics", &self.topics)
+            .field("queue_timeout", &self.queue_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KafkaSink {
+    /// How long a message may wait for room in the producer's queue.
+    pub const DEFAULT_QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Produce to the cluster at `brokers`, a comma-separated list of
+    /// `host:port` addresses, with topics chosen by `topics`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with a transport error if the producer cannot be created.
+    /// Brokers are not contacted until the first message is sent.
+    #[instrument(skip(topics))]
+    pub fn new(brokers: &str, topics: TopicMapping) -> Result<Self> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .create()
+            .map_err(|e| {
+                Error::Transport(TransportError::Connection {
+                    message: format!("Failed to create Kafka producer for {}: {}", brokers, e),
+                })
+            })?;
+        debug!("Created Kafka producer");
+        Ok(Self::from_producer(producer, topics))
+    }
+
+    /// Produce with an already configured producer, such as one set up
+    /// with SASL or TLS.
+    pub fn from_producer(producer: FutureProducer, topics: TopicMapping) -> Self {
+        Self {
+            producer,
+            topics,
+            queue_timeout: Self::DEFAULT_QUEUE_TIMEOUT,
+        }
+    }
+
+    /// Set how long a message may wait for room in the producer's queue
+    /// before sending fails.
+    pub fn queue_timeout(mut self, timeout: Duration) -> Self {
+        self.queue_timeout = timeout;
+        self
+    }
+
+    /// Returns the Kafka producer.
+    pub fn producer(&self) -> &FutureProducer {
+        &self.producer
+    }
+}
+
+#[async_trait]
+impl EventSink for KafkaSink {
+    fn topics(&self) -> &TopicMapping {
+        &self.topics
+    }
+
+    async fn send(&self, message: SinkMessage) -> Result<()> {
+        let mut record = FutureRecord::to(&message.topic).payload(&message.payload);
+        if let Some(key) = &message.key {
+            record = record.key(key);
+        }
+        self.producer
+            .send(record, self.queue_timeout)
+            .await
+            .map(|_| ())
+            .map_err(|(e, _)| {
+                Error::Transport(TransportError::Connection {
+                    message: format!("Failed to produce to {}: {}", message.topic, e),
+                })
+            })
+    }
+
+    async fn flush(&self) -> Result<()> {
+        let producer = self.producer.clone();
+        let timeout = self.queue_timeout;
+        tokio::task::spawn_blocking(move || producer.flush(timeout))
+            .await
+            .map_err(|e| {
+                Error::Transport(TransportError::Connection {
+                    message: format!("Kafka flush task failed: {}", e),
+                })
+            })?
+            .map_err(|e| {
+                Error::Transport(TransportError::Connection {
+                    message: format!("Failed to flush Kafka messages: {}", e),
+                })
+            })
+    }
+}
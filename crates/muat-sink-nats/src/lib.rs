@@ -0,0 +1,97 @@
+//! muat-sink-nats - Publish firehose events to NATS.
+//!
+//! [`NatsSink`] is an [`EventSink`] that publishes each event as a JSON
+//! message to a NATS subject chosen by its [`TopicMapping`]. Subscribers,
+//! or a JetStream stream capturing the subjects, can then share the work of
+//! processing a firehose without connecting to it themselves.
+
+use async_trait::async_trait;
+use tracing::{debug, instrument};
+
+use muat_core::Result;
+use muat_core::error::{Error, TransportError};
+use muat_core::repo::{SinkMessage, TopicMapping};
+use muat_core::traits::EventSink;
+
+pub use async_nats::{self, Client};
+
+/// Publishes firehose events to NATS subjects.
+///
+/// Messages are published with core NATS, which does not wait for
+/// delivery; [`flush`](EventSink::flush) waits until they have been written
+/// to the server.
+///
+/// # Example
+///
+/// ```no_run
+/// use muat_core::{EventSink, TopicMapping};
+/// use muat_sink_nats::NatsSink;
+///
+/// # async fn example(firehose: impl muat_core::Firehose) -> muat_core::Result<()> {
+/// let topics = TopicMapping::by_collection("atproto.", "atproto.other");
+/// let sink = NatsSink::connect("nats://localhost:4222", topics).await?;
+/// let published = sink.publish_all(firehose).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct NatsSink {
+    client: Client,
+    topics: TopicMapping,
+}
+
+impl NatsSink {
+    /// Connect to the NATS server at `url` and publish to subjects chosen
+    /// by `topics`.
+    ///
+    /// # Errors
+    ///
+    /// Fails with a transport error if the server cannot be reached.
+    #[instrument(skip(topics))]
+    pub async fn connect(url: &str, topics: TopicMapping) -> Result<Self> {
+        let client = async_nats::connect(url).await.map_err(|e| {
+            Error::Transport(TransportError::Connection {
+                message: format!("Failed to connect to NATS at {}: {}", url, e),
+            })
+        })?;
+        debug!("Connected to NATS");
+        Ok(Self::new(client, topics))
+    }
+
+    /// Publish with an already connected client, such as one set up with
+    /// credentials or TLS.
+    pub fn new(client: Client, topics: TopicMapping) -> Self {
+        Self { client, topics }
+    }
+
+    /// Returns the NATS client.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+}
+
+#[async_trait]
+impl EventSink for NatsSink {
+    fn topics(&self) -> &TopicMapping {
+        &self.topics
+    }
+
+    async fn send(&self, message: SinkMessage) -> Result<()> {
+        self.client
+            .publish(message.topic.clone(), message.payload.into())
+            .await
+            .map_err(|e| {
+                Error::Transport(TransportError::Connection {
+                    message: format!("Failed to publish to {}: {}", message.topic, e),
+                })
+            })
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.client.flush().await.map_err(|e| {
+            Error::Transport(TransportError::Connection {
+                message: format!("Failed to flush NATS messages: {}", e),
+            })
+        })
+    }
+}
@@ -0,0 +1,182 @@
+//! Assertions about records and firehose events.
+//!
+//! The record macros call a [`Session`] and must be used in an async test.
+//! Each returns what it found, so a test can keep checking it.
+
+use serde_json::Value;
+
+use muat_core::Result;
+use muat_core::repo::{ListRecordsOptions, Record};
+use muat_core::traits::Session;
+use muat_core::types::Nsid;
+
+/// Returns true if `actual` has everything in `expected`: objects may have
+/// extra keys, while arrays and scalars must match exactly (arrays element by
+/// element, with the same rule for their elements).
+pub fn json_contains(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => expected.iter().all(|(key, value)| {
+            actual
+                .get(key)
+                .is_some_and(|actual| json_contains(actual, value))
+        }),
+        (Value::Array(actual), Value::Array(expected)) => {
+            actual.len() == expected.len()
+                && actual
+                    .iter()
+                    .zip(expected)
+                    .all(|(actual, expected)| json_contains(actual, expected))
+        }
+        _ => actual == expected,
+    }
+}
+
+/// Find the first record in the session's own `collection` whose value
+/// contains `expected` (see [`json_contains`]), reading every page.
+pub async fn find_record<S: Session + ?Sized>(
+    session: &S,
+    collection: &Nsid,
+    expected: &Value,
+) -> Result<Option<Record>> {
+    let mut options = ListRecordsOptions::new();
+    loop {
+        let page = session
+            .list_records_with(session.did(), collection, &options)
+            .await?;
+        if let Some(record) = page
+            .records
+            .into_iter()
+            .find(|record| json_contains(record.value.as_value(), expected))
+        {
+            return Ok(Some(record));
+        }
+        match page.cursor {
+            Some(cursor) if options.get_cursor() != Some(cursor.as_str()) => {
+                options = options.cursor(cursor);
+            }
+            _ => return Ok(None),
+        }
+    }
+}
+
+/// Assert the session's repo has a record in a collection whose value
+/// contains the expected JSON, and return it.
+///
+/// ```ignore
+/// let record = assert_record_created!(session, &collection, json!({ "text": "hi" }));
+/// ```
+#[macro_export]
+macro_rules! assert_record_created {
+    ($session:expr, $collection:expr, $expected:expr $(,)?) => {{
+        let expected: $crate::__private::serde_json::Value = $expected;
+        match $crate::find_record(&$session, $collection, &expected).await {
+            Ok(Some(record)) => record,
+            Ok(None) => panic!(
+                "no record in {} of {} contains {}",
+                $collection,
+                $crate::__private::muat_core::traits::Session::did(&$session),
+                expected
+            ),
+            Err(e) => panic!("listing {} failed: {}", $collection, e),
+        }
+    }};
+}
+
+/// Assert a record exists, optionally with a value containing the expected
+/// JSON, and return it.
+///
+/// ```ignore
+/// assert_record_exists!(session, &uri);
+/// assert_record_exists!(session, &uri, json!({ "text": "hi" }));
+/// ```
+#[macro_export]
+macro_rules! assert_record_exists {
+    ($session:expr, $uri:expr $(,)?) => {{
+        match $crate::__private::muat_core::traits::Session::get_record(&$session, $uri).await {
+            Ok(record) => record,
+            Err(e) => panic!("expected record {} to exist: {}", $uri, e),
+        }
+    }};
+    ($session:expr, $uri:expr, $expected:expr $(,)?) => {{
+        let record = $crate::assert_record_exists!($session, $uri);
+        let expected: $crate::__private::serde_json::Value = $expected;
+        assert!(
+            $crate::json_contains(record.value.as_value(), &expected),
+            "record {} is {}, which does not contain {}",
+            $uri,
+            record.value.as_value(),
+            expected
+        );
+        record
+    }};
+}
+
+/// Assert a record does not exist: reading it fails with `RecordNotFound`.
+///
+/// ```ignore
+/// session.delete_record(&uri).await?;
+/// assert_record_deleted!(session, &uri);
+/// ```
+#[macro_export]
+macro_rules! assert_record_deleted {
+    ($session:expr, $uri:expr $(,)?) => {{
+        match $crate::__private::muat_core::traits::Session::get_record(&$session, $uri).await {
+            Ok(record) => panic!(
+                "expected record {} to be deleted, but it is {}",
+                $uri,
+                record.value.as_value()
+            ),
+            Err($crate::__private::muat_core::Error::Protocol(e))
+                if e.status == 404 || e.error.as_deref() == Some("RecordNotFound") => {}
+            Err(e) => panic!(
+                "expected record {} to be not found, but reading it failed: {}",
+                $uri, e
+            ),
+        }
+    }};
+}
+
+/// Assert a firehose event is a commit with an operation of the given
+/// action (`create`, `update` or `delete`) on a record, and return the
+/// commit.
+///
+/// The record is an [`AtUri`](muat_core::AtUri); the commit must be for its
+/// repo and carry an operation on its `collection/rkey` path.
+///
+/// ```ignore
+/// let commit = assert_commit!(event, "create", &uri);
+/// ```
+#[macro_export]
+macro_rules! assert_commit {
+    ($event:expr, $action:expr, $uri:expr $(,)?) => {{
+        let uri: &$crate::__private::muat_core::AtUri = $uri;
+        let action: &str = $action;
+        match $event {
+            $crate::__private::muat_core::repo::RepoEvent::Commit(commit) => {
+                let path = uri
+                    .record_path()
+                    .map(|(collection, rkey)| format!("{}/{}", collection, rkey))
+                    .unwrap_or_else(|e| panic!("{} is not a record URI: {}", uri, e));
+                assert_eq!(
+                    commit.repo,
+                    uri.repo().as_str(),
+                    "commit {} is for another repo",
+                    commit.seq
+                );
+                assert!(
+                    commit
+                        .ops
+                        .iter()
+                        .any(|op| op.action == action && op.path == path),
+                    "commit {} has no {} of {}; its operations are {:?}",
+                    commit.seq,
+                    action,
+                    path,
+                    commit.ops
+                );
+                commit
+            }
+            other => panic!("expected a commit to {} {}, got {:?}", action, uri, other),
+        }
+    }};
+}
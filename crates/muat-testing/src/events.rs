@@ -0,0 +1,174 @@
+//! Builders for firehose events.
+
+use chrono::{DateTime, Utc};
+use futures_core::Stream;
+use sha2::{Digest, Sha256};
+
+use muat_core::Result;
+use muat_core::repo::{
+    CommitEvent, CommitOperation, HandleEvent, IdentityEvent, RecordValue, RepoEvent,
+};
+use muat_core::types::{Cid, Did, Nsid, Rkey, TidGenerator};
+
+/// Multicodec code for DAG-JSON, which [`record_cid`] falls back to.
+const DAG_JSON: u64 = 0x0129;
+
+/// A CID for a record value: its DAG-CBOR CID, as a PDS would assign.
+///
+/// Values outside the atproto data model (floats) have no DAG-CBOR
+/// encoding; for those this falls back to sha-256 of the JSON, as CIDv1
+/// DAG-JSON, so tests can still predict the CIDs in events they build.
+pub fn record_cid(value: &RecordValue) -> Cid {
+    value.cid().unwrap_or_else(|_| {
+        let digest = Sha256::digest(value.as_value().to_string().as_bytes());
+        Cid::v1(DAG_JSON, Cid::SHA2_256, &digest)
+    })
+}
+
+/// Builds firehose events for one repo.
+///
+/// Sequence numbers count up from 1 (or [`starting_at`](Self::starting_at)),
+/// and each commit gets a new TID revision with the previous one as
+/// `since`, as a PDS would send them.
+///
+/// # Example
+///
+/// ```
+/// use muat_core::repo::{RecordValue, RepoEvent};
+/// use muat_core::{Did, Nsid, Rkey};
+/// use muat_testing::EventBuilder;
+///
+/// let mut events = EventBuilder::new(Did::new("did:plc:z72i7hdynmk6r22z27h6tvur").unwrap());
+/// let collection = Nsid::new("org.example.note").unwrap();
+/// let rkey = Rkey::new("3k2aaaaaaaaa2").unwrap();
+/// let value = RecordValue::with_type("org.example.note", serde_json::json!({})).unwrap();
+///
+/// let created = events.create(&collection, &rkey, &value);
+/// let deleted = events.delete(&collection, &rkey);
+/// let (RepoEvent::Commit(created), RepoEvent::Commit(deleted)) = (created, deleted) else {
+///     unreachable!()
+/// };
+/// assert_eq!((created.seq, deleted.seq), (1, 2));
+/// assert_eq!(deleted.since.as_deref(), Some(created.rev.as_str()));
+/// ```
+#[derive(Debug)]
+pub struct EventBuilder {
+    did: Did,
+    next_seq: i64,
+    revs: TidGenerator,
+    last_rev: Option<String>,
+    time: Option<DateTime<Utc>>,
+}
+
+impl EventBuilder {
+    /// Build events for `did`, starting at sequence number 1.
+    pub fn new(did: Did) -> Self {
+        Self {
+            did,
+            next_seq: 1,
+            revs: TidGenerator::new(),
+            last_rev: None,
+            time: None,
+        }
+    }
+
+    /// Number the next event `seq`.
+    pub fn starting_at(mut self, seq: i64) -> Self {
+        self.next_seq = seq;
+        self
+    }
+
+    /// Timestamp every event with `time` rather than the current time.
+    pub fn at_time(mut self, time: DateTime<Utc>) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    /// The repo the events are for.
+    pub fn did(&self) -> &Did {
+        &self.did
+    }
+
+    /// A commit creating a record.
+    pub fn create(&mut self, collection: &Nsid, rkey: &Rkey, value: &RecordValue) -> RepoEvent {
+        self.commit(vec![operation("create", collection, rkey, Some(value))])
+    }
+
+    /// A commit updating a record.
+    pub fn update(&mut self, collection: &Nsid, rkey: &Rkey, value: &RecordValue) -> RepoEvent {
+        self.commit(vec![operation("update", collection, rkey, Some(value))])
+    }
+
+    /// A commit deleting a record.
+    pub fn delete(&mut self, collection: &Nsid, rkey: &Rkey) -> RepoEvent {
+        self.commit(vec![operation("delete", collection, rkey, None)])
+    }
+
+    /// A commit with any operations, such as several writes at once.
+    pub fn commit(&mut self, ops: Vec<CommitOperation>) -> RepoEvent {
+        let rev = self.revs.next_tid().to_string();
+        let since = self.last_rev.replace(rev.clone());
+        RepoEvent::Commit(CommitEvent {
+            repo: self.did.to_string(),
+            rev,
+            since,
+            seq: self.next_seq(),
+            time: self.time(),
+            ops,
+            blocks: Vec::new(),
+            blobs: Vec::new(),
+        })
+    }
+
+    /// An identity event for the repo.
+    pub fn identity(&mut self) -> RepoEvent {
+        RepoEvent::Identity(IdentityEvent {
+            did: self.did.to_string(),
+            seq: self.next_seq(),
+            time: self.time(),
+        })
+    }
+
+    /// A handle change for the repo.
+    pub fn handle(&mut self, handle: &str) -> RepoEvent {
+        RepoEvent::Handle(HandleEvent {
+            did: self.did.to_string(),
+            handle: handle.to_string(),
+            seq: self.next_seq(),
+            time: self.time(),
+        })
+    }
+
+    fn next_seq(&mut self) -> i64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        seq
+    }
+
+    fn time(&self) -> DateTime<Utc> {
+        self.time.unwrap_or_else(Utc::now)
+    }
+}
+
+/// A commit operation, for [`EventBuilder::commit`]. `value` is `None` for
+/// deletes.
+pub fn operation(
+    action: &str,
+    collection: &Nsid,
+    rkey: &Rkey,
+    value: Option<&RecordValue>,
+) -> CommitOperation {
+    CommitOperation {
+        path: format!("{}/{}", collection, rkey),
+        action: action.to_string(),
+        cid: value.map(|value| record_cid(value).to_string()),
+    }
+}
+
+/// A firehose-shaped stream of events, for code that consumes
+/// [`Pds::firehose_from`](muat_core::traits::Pds::firehose_from).
+pub fn event_stream(
+    events: impl IntoIterator<Item = RepoEvent>,
+) -> impl Stream<Item = Result<RepoEvent>> + Send + Unpin {
+    futures_util::stream::iter(events.into_iter().map(Ok).collect::<Vec<_>>())
+}
@@ -0,0 +1,61 @@
+//! muat-testing - Fixtures, event builders and assertions for apps built on
+//! muat.
+//!
+//! - [`TestPds`] is a throwaway file-backed PDS in a temporary directory,
+//!   seeded with accounts and records by [`TestPdsBuilder`]. With the
+//!   `serve` feature (on by default), [`TestPds::serve`] puts it behind real
+//!   XRPC endpoints for code that talks to an [`XrpcPds`](muat_xrpc::XrpcPds).
+//! - [`EventBuilder`] makes firehose events for a repo, with increasing
+//!   sequence numbers and revisions, and [`event_stream`] feeds them to code
+//!   that consumes a firehose.
+//! - [`assert_record_created!`], [`assert_record_exists!`],
+//!   [`assert_record_deleted!`] and [`assert_commit!`] check the results.
+//!
+//! The backend conformance suite from `muat-core` is re-exported as
+//! [`conformance`].
+//!
+//! # Example
+//!
+//! ```
+//! use muat_core::Session;
+//! use muat_testing::{TestPds, assert_record_created};
+//! use serde_json::json;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() -> muat_core::Result<()> {
+//! let pds = TestPds::builder()
+//!     .account("alice.test")
+//!     .record("alice.test", "org.example.note", json!({ "text": "seeded" }))
+//!     .build()
+//!     .await?;
+//! let alice = pds.login("alice.test").await?;
+//!
+//! let collection = muat_core::Nsid::new("org.example.note")?;
+//! let value = muat_core::RecordValue::with_type("org.example.note", json!({ "text": "hi" }))?;
+//! alice.create_record(&collection, &value).await?;
+//!
+//! assert_record_created!(alice, &collection, json!({ "text": "hi" }));
+//! # Ok(())
+//! # }
+//! ```
+
+mod assertions;
+mod events;
+mod pds;
+
+pub use assertions::{find_record, json_contains};
+pub use events::{EventBuilder, event_stream, operation, record_cid};
+#[cfg(feature = "serve")]
+pub use pds::ServedPds;
+pub use pds::{PASSWORD, TestAccount, TestPds, TestPdsBuilder};
+
+/// The backend conformance suite, re-exported from `muat_core::testing`.
+pub use muat_core::testing as conformance;
+
+/// Paths used by the assertion macros, so callers need not depend on these
+/// crates themselves.
+#[doc(hidden)]
+pub mod __private {
+    pub use muat_core;
+    pub use serde_json;
+}
@@ -0,0 +1,266 @@
+//! A throwaway file-backed PDS seeded with accounts and records.
+
+use serde_json::Value;
+use tempfile::TempDir;
+
+use muat_core::error::{Error, InvalidInputError, TransportError};
+use muat_core::repo::RecordValue;
+use muat_core::testing::Fixture;
+use muat_core::traits::Pds;
+use muat_core::types::{AtUri, Did, Nsid, PdsUrl, Rkey};
+use muat_core::{Credentials, Result};
+use muat_file::{FilePds, FileSession, PasswordHashing};
+
+/// Password of every account a [`TestPds`] creates.
+pub const PASSWORD: &str = "password";
+
+/// The cheapest bcrypt cost, so creating and logging in to accounts is fast.
+const TEST_BCRYPT_COST: u32 = 4;
+
+/// An account created by a [`TestPds`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestAccount {
+    /// The account's DID.
+    pub did: Did,
+    /// The account's handle.
+    pub handle: String,
+}
+
+/// A record to create when the PDS is built.
+#[derive(Debug)]
+struct SeedRecord {
+    handle: String,
+    collection: String,
+    rkey: Option<String>,
+    fields: Value,
+}
+
+/// Builds a [`TestPds`]. Accounts are created first, then records, in the
+/// order they were added.
+#[derive(Debug, Default)]
+pub struct TestPdsBuilder {
+    handles: Vec<String>,
+    records: Vec<SeedRecord>,
+    sqlite: bool,
+}
+
+impl TestPdsBuilder {
+    /// Add an account with the given handle and [`PASSWORD`].
+    pub fn account(mut self, handle: impl Into<String>) -> Self {
+        self.handles.push(handle.into());
+        self
+    }
+
+    /// Add a record to an account's repo, with a generated record key.
+    ///
+    /// `fields` is a JSON object; its `$type` is set to `collection`.
+    pub fn record(
+        mut self,
+        handle: impl Into<String>,
+        collection: impl Into<String>,
+        fields: Value,
+    ) -> Self {
+        self.records.push(SeedRecord {
+            handle: handle.into(),
+            collection: collection.into(),
+            rkey: None,
+            fields,
+        });
+        self
+    }
+
+    /// Add a record under a chosen record key.
+    pub fn record_with_rkey(
+        mut self,
+        handle: impl Into<String>,
+        collection: impl Into<String>,
+        rkey: impl Into<String>,
+        fields: Value,
+    ) -> Self {
+        self.records.push(SeedRecord {
+            handle: handle.into(),
+            collection: collection.into(),
+            rkey: Some(rkey.into()),
+            fields,
+        });
+        self
+    }
+
+    /// Store the PDS in a SQLite database rather than a file per record.
+    ///
+    /// Needs the `sqlite` feature of `muat-file`; without it every
+    /// operation fails.
+    pub fn sqlite(mut self) -> Self {
+        self.sqlite = true;
+        self
+    }
+
+    /// Create the PDS in a new temporary directory.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the directory cannot be created, a handle or record is
+    /// invalid, or a record names an account that was not added.
+    pub async fn build(self) -> Result<TestPds> {
+        let dir = TempDir::new().map_err(|e| {
+            Error::Transport(TransportError::Http {
+                message: format!("IO error: {}", e),
+            })
+        })?;
+        let scheme = if self.sqlite { "file+sqlite" } else { "file" };
+        let url = PdsUrl::new(format!("{}://{}", scheme, dir.path().display()))?;
+        let pds = FilePds::new(dir.path(), url).with_password_hashing(PasswordHashing::Bcrypt {
+            cost: TEST_BCRYPT_COST,
+        });
+
+        let mut test_pds = TestPds {
+            pds,
+            accounts: Vec::new(),
+            records: Vec::new(),
+            _dir: dir,
+        };
+        for handle in &self.handles {
+            test_pds.create_account(handle).await?;
+        }
+        for seed in self.records {
+            let uri = test_pds.seed(seed).await?;
+            test_pds.records.push(uri);
+        }
+        Ok(test_pds)
+    }
+}
+
+/// A file-backed PDS in a temporary directory, removed when dropped.
+///
+/// Every account has the password [`PASSWORD`].
+#[derive(Debug)]
+pub struct TestPds {
+    pds: FilePds,
+    accounts: Vec<TestAccount>,
+    records: Vec<AtUri>,
+    _dir: TempDir,
+}
+
+impl TestPds {
+    /// Start building a test PDS.
+    pub fn builder() -> TestPdsBuilder {
+        TestPdsBuilder::default()
+    }
+
+    /// An empty test PDS, with no accounts.
+    pub async fn new() -> Result<Self> {
+        Self::builder().build().await
+    }
+
+    /// The PDS, for calls not wrapped here.
+    pub fn pds(&self) -> &FilePds {
+        &self.pds
+    }
+
+    /// Accounts in the order they were created.
+    pub fn accounts(&self) -> &[TestAccount] {
+        &self.accounts
+    }
+
+    /// Look up an account by handle.
+    pub fn account(&self, handle: &str) -> Option<&TestAccount> {
+        self.accounts.iter().find(|a| a.handle == handle)
+    }
+
+    /// URIs of the seeded records, in the order they were added.
+    pub fn records(&self) -> &[AtUri] {
+        &self.records
+    }
+
+    /// Create another account with [`PASSWORD`].
+    pub async fn create_account(&mut self, handle: &str) -> Result<TestAccount> {
+        let created = self
+            .pds
+            .create_account(handle, Some(PASSWORD), None, None)
+            .await?;
+        let account = TestAccount {
+            did: created.did,
+            handle: created.handle,
+        };
+        self.accounts.push(account.clone());
+        Ok(account)
+    }
+
+    /// Log in to an account by handle.
+    pub async fn login(&self, handle: &str) -> Result<FileSession> {
+        self.pds.login(Credentials::new(handle, PASSWORD)).await
+    }
+
+    /// A conformance [`Fixture`] for an account, keeping the PDS directory
+    /// alive as long as the fixture.
+    pub fn into_fixture(self, handle: &str) -> Fixture<FilePds> {
+        Fixture::new(self.pds.clone(), handle, PASSWORD).with_guard(self._dir)
+    }
+
+    /// Serve the PDS over XRPC on a free local port until the returned
+    /// handle is dropped.
+    #[cfg(feature = "serve")]
+    pub async fn serve(&self) -> Result<ServedPds> {
+        let connection = |e: std::io::Error| {
+            Error::Transport(TransportError::Connection {
+                message: e.to_string(),
+            })
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(connection)?;
+        let addr = listener.local_addr().map_err(connection)?;
+        let url = PdsUrl::new(format!("http://{}", addr))?;
+        let task = tokio::spawn(muat_serve::FileServer::new(self.pds.clone()).serve(listener));
+        Ok(ServedPds { url, task })
+    }
+
+    async fn seed(&self, seed: SeedRecord) -> Result<AtUri> {
+        let session = self.login(&seed.handle).await.map_err(|e| {
+            Error::InvalidInput(InvalidInputError::Other {
+                message: format!(
+                    "Seed record for '{}' needs that account: {}",
+                    seed.handle, e
+                ),
+            })
+        })?;
+        let collection = Nsid::new(&seed.collection)?;
+        let value = RecordValue::with_type(&seed.collection, seed.fields)?;
+        match seed.rkey {
+            Some(rkey) => {
+                session
+                    .create_record_with_rkey(&collection, &Rkey::new(rkey)?, &value)
+                    .await
+            }
+            None => muat_core::traits::Session::create_record(&session, &collection, &value).await,
+        }
+    }
+}
+
+/// A [`TestPds`] served over XRPC. The server stops when this is dropped.
+#[cfg(feature = "serve")]
+#[derive(Debug)]
+pub struct ServedPds {
+    url: PdsUrl,
+    task: tokio::task::JoinHandle<Result<()>>,
+}
+
+#[cfg(feature = "serve")]
+impl ServedPds {
+    /// The server's `http://127.0.0.1:<port>` URL.
+    pub fn url(&self) -> &PdsUrl {
+        &self.url
+    }
+
+    /// An XRPC client for the server.
+    pub fn client(&self) -> muat_xrpc::XrpcPds {
+        muat_xrpc::XrpcPds::new(self.url.clone())
+    }
+}
+
+#[cfg(feature = "serve")]
+impl Drop for ServedPds {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,215 @@
+//! Exercises the fixtures, builders and assertions as a downstream app would.
+
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use serde_json::json;
+
+use muat_core::repo::{RecordValue, RepoEvent};
+use muat_core::traits::{Pds, Session};
+use muat_core::{Credentials, Did, Nsid, Rkey};
+use muat_testing::{
+    EventBuilder, PASSWORD, TestPds, assert_commit, assert_record_created, assert_record_deleted,
+    assert_record_exists, conformance, event_stream, json_contains, operation, record_cid,
+};
+
+const NOTE: &str = "org.example.note";
+
+fn note(text: &str) -> RecordValue {
+    RecordValue::with_type(NOTE, json!({ "text": text, "tags": ["a", "b"] })).unwrap()
+}
+
+#[tokio::test]
+async fn test_builder_seeds_accounts_and_records() {
+    let pds = TestPds::builder()
+        .account("alice.test")
+        .account("bob.test")
+        .record("alice.test", NOTE, json!({ "text": "first" }))
+        .record_with_rkey("bob.test", NOTE, "pinned", json!({ "text": "pinned" }))
+        .build()
+        .await
+        .unwrap();
+
+    assert_eq!(pds.accounts().len(), 2);
+    let bob = pds.account("bob.test").unwrap();
+    assert_eq!(pds.records().len(), 2);
+    assert_eq!(pds.records()[1].repo(), &bob.did);
+    assert_eq!(pds.records()[1].rkey().unwrap().as_str(), "pinned");
+
+    let alice = pds.login("alice.test").await.unwrap();
+    let record = assert_record_exists!(alice, &pds.records()[0], json!({ "text": "first" }));
+    assert_eq!(record.value.record_type(), NOTE);
+
+    // Accounts use the shared test password.
+    pds.pds()
+        .login(Credentials::new("bob.test", PASSWORD))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_seed_record_needs_its_account() {
+    let err = TestPds::builder()
+        .account("alice.test")
+        .record("carol.test", NOTE, json!({}))
+        .build()
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("carol.test"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_record_assertions() {
+    let mut pds = TestPds::new().await.unwrap();
+    pds.create_account("alice.test").await.unwrap();
+    let alice = pds.login("alice.test").await.unwrap();
+    let collection = Nsid::new(NOTE).unwrap();
+
+    let uri = alice
+        .create_record(&collection, &note("hello"))
+        .await
+        .unwrap();
+    let found = assert_record_created!(alice, &collection, json!({ "tags": ["a", "b"] }));
+    assert_eq!(found.uri, uri);
+    assert_record_exists!(alice, &uri);
+
+    alice.delete_record(&uri).await.unwrap();
+    assert_record_deleted!(alice, &uri);
+}
+
+#[tokio::test]
+#[should_panic(expected = "no record in org.example.note")]
+async fn test_record_created_reports_missing_record() {
+    let pds = TestPds::builder()
+        .account("alice.test")
+        .record("alice.test", NOTE, json!({ "text": "other" }))
+        .build()
+        .await
+        .unwrap();
+    let alice = pds.login("alice.test").await.unwrap();
+    let collection = Nsid::new(NOTE).unwrap();
+    assert_record_created!(alice, &collection, json!({ "text": "missing" }));
+}
+
+#[test]
+fn test_json_contains() {
+    let actual = json!({ "text": "hi", "reply": { "root": "x", "parent": "y" }, "tags": [1, 2] });
+    assert!(json_contains(&actual, &json!({ "reply": { "root": "x" } })));
+    assert!(json_contains(&actual, &json!({ "tags": [1, 2] })));
+    assert!(!json_contains(&actual, &json!({ "tags": [1] })));
+    assert!(!json_contains(&actual, &json!({ "text": "bye" })));
+    assert!(!json_contains(&actual, &json!({ "missing": null })));
+}
+
+#[tokio::test]
+async fn test_event_builder_feeds_consumers() {
+    let did = Did::new("did:plc:z72i7hdynmk6r22z27h6tvur").unwrap();
+    let collection = Nsid::new(NOTE).unwrap();
+    let rkey = Rkey::new("3k2aaaaaaaaa2").unwrap();
+    let uri = muat_core::AtUri::builder(did.clone())
+        .collection(collection.clone())
+        .rkey(rkey.clone())
+        .build()
+        .unwrap();
+    let mut events = EventBuilder::new(did).starting_at(10);
+
+    let built = vec![
+        events.identity(),
+        events.create(&collection, &rkey, &note("a")),
+        events.update(&collection, &rkey, &note("b")),
+        events.commit(vec![
+            operation("delete", &collection, &rkey, None),
+            operation("create", &collection, &rkey, Some(&note("c"))),
+        ]),
+        events.handle("alice.example.org"),
+    ];
+    let received: Vec<RepoEvent> = event_stream(built)
+        .map(|event| event.unwrap())
+        .collect()
+        .await;
+
+    let seqs: Vec<i64> = received
+        .iter()
+        .map(|event| match event {
+            RepoEvent::Commit(e) => e.seq,
+            RepoEvent::Identity(e) => e.seq,
+            RepoEvent::Handle(e) => e.seq,
+            other => panic!("unexpected {:?}", other),
+        })
+        .collect();
+    assert_eq!(seqs, [10, 11, 12, 13, 14]);
+
+    let created = assert_commit!(&received[1], "create", &uri);
+    assert_eq!(
+        created.ops[0].cid.as_deref(),
+        Some(record_cid(&note("a")).as_str())
+    );
+    let updated = assert_commit!(&received[2], "update", &uri);
+    assert_eq!(updated.since.as_deref(), Some(created.rev.as_str()));
+    assert!(updated.rev > created.rev);
+    assert_commit!(&received[3], "delete", &uri);
+    assert_ne!(record_cid(&note("a")), record_cid(&note("b")));
+}
+
+#[tokio::test]
+#[should_panic(expected = "has no delete")]
+async fn test_commit_assertion_checks_action() {
+    let pds = TestPds::builder()
+        .account("alice.test")
+        .record("alice.test", NOTE, json!({}))
+        .build()
+        .await
+        .unwrap();
+    let commit = tokio::time::timeout(
+        Duration::from_secs(10),
+        pds.pds()
+            .firehose_from(Some(0))
+            .unwrap()
+            .filter_map(|event| async move {
+                match event.unwrap() {
+                    event @ RepoEvent::Commit(_) => Some(event),
+                    _ => None,
+                }
+            })
+            .boxed()
+            .next(),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert_commit!(&commit, "create", &pds.records()[0]);
+    assert_commit!(&commit, "delete", &pds.records()[0]);
+}
+
+#[cfg(feature = "serve")]
+#[tokio::test]
+async fn test_served_pds_answers_xrpc_clients() {
+    let pds = TestPds::builder()
+        .account("alice.test")
+        .record("alice.test", NOTE, json!({ "text": "served" }))
+        .build()
+        .await
+        .unwrap();
+    let served = pds.serve().await.unwrap();
+    assert!(served.url().to_string().starts_with("http://127.0.0.1:"));
+
+    let session = served
+        .client()
+        .login(Credentials::new("alice.test", PASSWORD))
+        .await
+        .unwrap();
+    assert_record_exists!(session, &pds.records()[0], json!({ "text": "served" }));
+}
+
+#[tokio::test]
+async fn test_into_fixture_runs_conformance_checks() {
+    let pds = TestPds::builder()
+        .account("alice.test")
+        .build()
+        .await
+        .unwrap();
+    let fixture = pds.into_fixture("alice.test");
+    let session = fixture.login().await;
+    conformance::check_record_crud(&session, fixture.collection()).await;
+}
@@ -1,51 +1,599 @@
 //! Firehose stream for XRPC-backed PDS.
+//!
+//! Two WebSocket backends are available:
+//!
+//! - `native-ws` (default): `tokio-tungstenite`, for native targets.
+//! - `wasm`: the browser `WebSocket` API, for `wasm32-unknown-unknown`.
 
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
-use futures_util::{Stream, StreamExt};
-use tokio_tungstenite::{connect_async, tungstenite::Message};
-use tracing::{debug, error, info, trace, warn};
+use chrono::{DateTime, Utc};
+use futures_util::Stream;
 
 use muat_core::Result;
-use muat_core::error::{Error, TransportError};
-use muat_core::repo::RepoEvent;
+#[cfg(not(any(feature = "native-ws", all(feature = "wasm", target_arch = "wasm32"))))]
+use muat_core::error::TransportError;
+use muat_core::error::{Error, FirehoseError};
+use muat_core::repo::{
+    CommitEvent, CommitOperation, EventStats, FirehoseBuffer, FirehoseStats, HandleEvent,
+    IdentityEvent, InfoEvent, RepoEvent, from_dag_cbor, read_dag_cbor, read_dag_cbor_raw,
+};
 use muat_core::types::PdsUrl;
+use serde_json::Value;
+
+#[cfg(feature = "native-ws")]
+use crate::xrpc::endpoints::SUBSCRIBE_REPOS;
 
 /// Firehose stream for XRPC-backed PDS.
+///
+/// Counts what it delivers; see [`stats`](Self::stats).
 pub struct XrpcFirehose {
     inner: Pin<Box<dyn Stream<Item = Result<RepoEvent>> + Send>>,
+    stats: FirehoseStats,
 }
 
 impl XrpcFirehose {
-    pub(crate) fn new<S>(stream: S) -> Self
+    /// Wrap an event stream; its transport counts bytes into `stats`.
+    #[cfg_attr(
+        not(any(feature = "native-ws", all(feature = "wasm", target_arch = "wasm32"))),
+        allow(dead_code)
+    )]
+    pub(crate) fn new<S>(stream: S, stats: FirehoseStats) -> Self
     where
         S: Stream<Item = Result<RepoEvent>> + Send + 'static,
     {
         Self {
             inner: Box::pin(stream),
+            stats,
         }
     }
 
+    /// Counters for the events, operations, errors and bytes received so far.
+    pub fn stats(&self) -> EventStats {
+        self.stats.snapshot()
+    }
+
+    /// Zero the counters returned by [`stats`](Self::stats).
+    pub fn reset_stats(&self) {
+        self.stats.reset();
+    }
+
+    /// A handle to this stream's counters that stays valid after the stream
+    /// is moved, e.g. into a filter or another task.
+    pub fn stats_handle(&self) -> FirehoseStats {
+        self.stats.clone()
+    }
+
+    /// Events received but not yet consumed.
+    pub fn lag(&self) -> u64 {
+        self.stats.lag()
+    }
+
+    /// Events discarded because the consumer fell behind.
+    pub fn dropped_events(&self) -> u64 {
+        self.stats.dropped_events()
+    }
+
+    /// Open a firehose subscription using the WebSocket backend compiled in.
+    ///
+    /// With `native-ws` the connection is made on a background task, so this
+    /// must be called from within a Tokio runtime.
+    #[cfg(feature = "native-ws")]
+    pub(crate) fn connect(
+        pds: &PdsUrl,
+        cursor: Option<i64>,
+        buffer: FirehoseBuffer,
+        limits: FirehoseLimits,
+        timeout: Option<Duration>,
+    ) -> Result<Self> {
+        use futures_util::StreamExt;
+
+        let pds = pds.clone();
+        let stats = FirehoseStats::new();
+        let (tx, rx) = buffer.channel(stats.clone());
+        let transport_stats = stats.clone();
+
+        tokio::spawn(async move {
+            let connected = match reject_unix_socket(&pds) {
+                Ok(()) => {
+                    native::connect(
+                        build_ws_url(&pds, SUBSCRIBE_REPOS, cursor),
+                        decode_firehose,
+                        "firehose",
+                        transport_stats,
+                        limits,
+                        timeout,
+                    )
+                    .await
+                }
+                Err(e) => Err(e),
+            };
+            match connected {
+                Ok(stream) => {
+                    let mut stream = std::pin::pin!(stream);
+                    while let Some(event) = stream.next().await {
+                        if !tx.send(event).await {
+                            break;
+                        }
+                    }
+                }
+                Err(e) => {
+                    tx.send(Err(e)).await;
+                }
+            }
+        });
+
+        Ok(Self::new(rx.into_stream(), stats))
+    }
+
+    /// Open a firehose subscription using the browser WebSocket API.
+    ///
+    /// Browser callbacks cannot wait, so this backend buffers without limit.
+    /// The browser API has no handshake timeout, so none is applied.
+    #[cfg(all(not(feature = "native-ws"), feature = "wasm", target_arch = "wasm32"))]
+    pub(crate) fn connect(
+        pds: &PdsUrl,
+        cursor: Option<i64>,
+        _buffer: FirehoseBuffer,
+        _limits: FirehoseLimits,
+        _timeout: Option<Duration>,
+    ) -> Result<Self> {
+        Self::from_browser_websocket(pds, cursor)
+    }
+
+    /// Without a WebSocket backend, subscriptions are unavailable.
+    #[cfg(not(any(feature = "native-ws", all(feature = "wasm", target_arch = "wasm32"))))]
+    pub(crate) fn connect(
+        _pds: &PdsUrl,
+        _cursor: Option<i64>,
+        _buffer: FirehoseBuffer,
+        _limits: FirehoseLimits,
+        _timeout: Option<Duration>,
+    ) -> Result<Self> {
+        Err(Error::Transport(TransportError::Connection {
+            message: "no WebSocket backend compiled in (enable `native-ws` or `wasm`)".to_string(),
+        }))
+    }
+
+    /// Connect to `subscribeRepos` with `tokio-tungstenite`.
+    #[cfg(feature = "native-ws")]
     pub async fn from_websocket(pds: &PdsUrl, cursor: Option<i64>) -> Result<Self> {
-        let ws_url = build_ws_url(pds, cursor);
-        info!(url = %ws_url, "Connecting to firehose");
+        Self::from_websocket_with_limits(pds, cursor, FirehoseLimits::default()).await
+    }
+
+    /// Connect to `subscribeRepos` with `tokio-tungstenite`, refusing
+    /// messages over `limits`.
+    #[cfg(feature = "native-ws")]
+    pub async fn from_websocket_with_limits(
+        pds: &PdsUrl,
+        cursor: Option<i64>,
+        limits: FirehoseLimits,
+    ) -> Result<Self> {
+        reject_unix_socket(pds)?;
+        let stats = FirehoseStats::new();
+        native::connect(
+            build_ws_url(pds, SUBSCRIBE_REPOS, cursor),
+            decode_firehose,
+            "firehose",
+            stats.clone(),
+            limits,
+            None,
+        )
+        .await
+        .map(|stream| Self::new(stream, stats))
+    }
+
+    /// Connect to `subscribeRepos` with the browser `WebSocket` API.
+    #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+    pub fn from_browser_websocket(pds: &PdsUrl, cursor: Option<i64>) -> Result<Self> {
+        reject_unix_socket(pds)?;
+        let stats = FirehoseStats::new();
+        browser::connect(pds, cursor, stats.clone()).map(|stream| Self::new(stream, stats))
+    }
+}
+
+impl Stream for XrpcFirehose {
+    type Item = Result<RepoEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = self.inner.as_mut().poll_next(cx);
+        if let Poll::Ready(Some(item)) = &poll {
+            self.stats.record(item);
+        }
+        poll
+    }
+}
+
+/// Size limits for incoming WebSocket messages, so a server sending huge
+/// frames cannot make the client buffer them.
+///
+/// A message over the limit ends the stream with
+/// [`FirehoseError::MessageTooLarge`]; the frame header is checked before
+/// its payload is read. The browser WebSocket API offers no limits, so
+/// these apply to the native backend only.
+///
+/// ```
+/// use muat_xrpc::FirehoseLimits;
+///
+/// let limits = FirehoseLimits::new().message_size(4 << 20);
+/// assert_eq!(limits.max_message_size(), 4 << 20);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FirehoseLimits {
+    message_size: usize,
+    frame_size: usize,
+}
+
+impl Default for FirehoseLimits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FirehoseLimits {
+    /// Largest message accepted by default: 16 MiB, well above any
+    /// `subscribeRepos` event.
+    pub const DEFAULT_MESSAGE_SIZE: usize = 16 << 20;
+
+    /// Limits of [`DEFAULT_MESSAGE_SIZE`](Self::DEFAULT_MESSAGE_SIZE) for
+    /// both messages and frames.
+    pub fn new() -> Self {
+        Self {
+            message_size: Self::DEFAULT_MESSAGE_SIZE,
+            frame_size: Self::DEFAULT_MESSAGE_SIZE,
+        }
+    }
+
+    /// Set the largest message, in bytes, after joining its frames. Also
+    /// lowers the frame limit to match if it was higher.
+    pub fn message_size(mut self, bytes: usize) -> Self {
+        self.message_size = bytes;
+        self.frame_size = self.frame_size.min(bytes);
+        self
+    }
+
+    /// Set the largest single frame, in bytes.
+    pub fn frame_size(mut self, bytes: usize) -> Self {
+        self.frame_size = bytes;
+        self
+    }
+
+    /// Largest message accepted, in bytes.
+    pub fn max_message_size(&self) -> usize {
+        self.message_size
+    }
+
+    /// Largest frame accepted, in bytes.
+    pub fn max_frame_size(&self) -> usize {
+        self.frame_size
+    }
+}
+
+/// WebSocket backends only speak TCP, and a `unix://` URL must never fall
+/// back to `localhost`.
+#[cfg(any(feature = "native-ws", all(feature = "wasm", target_arch = "wasm32")))]
+pub(crate) fn reject_unix_socket(pds: &PdsUrl) -> Result<()> {
+    if pds.is_unix_socket() {
+        return Err(muat_core::error::Error::Transport(
+            muat_core::error::TransportError::Connection {
+                message: "the firehose is not available over a Unix socket".to_string(),
+            },
+        ));
+    }
+    Ok(())
+}
 
-        let (ws_stream, _) = connect_async(&ws_url).await.map_err(|e| {
-            Error::Transport(TransportError::Connection {
-                message: e.to_string(),
+#[cfg_attr(
+    not(any(feature = "native-ws", all(feature = "wasm", target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+pub(crate) fn build_ws_url(pds: &PdsUrl, method: &str, cursor: Option<i64>) -> String {
+    let http_url = pds.xrpc_url(method);
+    let mut url = if let Some(rest) = http_url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = http_url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else {
+        http_url
+    };
+
+    if let Some(cursor) = cursor {
+        url.push_str(&format!("?cursor={}", cursor));
+    }
+
+    url
+}
+
+/// A WebSocket data frame.
+#[cfg(feature = "native-ws")]
+pub(crate) enum Frame<'a> {
+    Binary(&'a [u8]),
+    Text(&'a str),
+}
+
+/// Turns a data frame into an event, or `None` to skip it.
+#[cfg(feature = "native-ws")]
+pub(crate) type Decoder<T> = fn(Frame<'_>) -> Option<Result<T>>;
+
+/// An event decoded from a WebSocket stream.
+#[cfg(feature = "native-ws")]
+pub(crate) trait StreamEvent: Send + 'static {
+    /// The event kind, as counted in metrics.
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    fn kind(&self) -> &'static str;
+}
+
+#[cfg(feature = "native-ws")]
+impl StreamEvent for RepoEvent {
+    fn kind(&self) -> &'static str {
+        match self {
+            RepoEvent::Commit(_) => "commit",
+            RepoEvent::Identity(_) => "identity",
+            RepoEvent::Handle(_) => "handle",
+            RepoEvent::Info(_) => "info",
+            RepoEvent::Unknown { .. } => "unknown",
+        }
+    }
+}
+
+#[cfg(feature = "native-ws")]
+fn decode_firehose(frame: Frame<'_>) -> Option<Result<RepoEvent>> {
+    match frame {
+        Frame::Binary(data) => Some(parse_ws_event(data)),
+        Frame::Text(_) => None,
+    }
+}
+
+/// Decode a `subscribeRepos` frame.
+///
+/// `#commit`, `#identity`, `#handle` and `#info` bodies are decoded into
+/// their events; other kinds, and bodies that do not decode, are passed on
+/// as [`RepoEvent::Unknown`] with a preview of their bytes. An
+/// `OutdatedCursor` info message becomes [`FirehoseError::CursorTooOld`].
+#[cfg_attr(
+    not(any(feature = "native-ws", all(feature = "wasm", target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+fn parse_ws_event(data: &[u8]) -> Result<RepoEvent> {
+    let (kind, mut body) = read_frame(data)?;
+    let (fields, byte_strings) = match read_dag_cbor_raw(&mut body) {
+        Ok((fields, byte_strings)) if body.is_empty() => (Some(fields), byte_strings),
+        _ => (None, Vec::new()),
+    };
+    if kind.as_deref() == Some("#info")
+        && let Some(fields) = &fields
+        && let Some(name) = text(fields, "name")
+    {
+        let message = text(fields, "message").map(str::to_string);
+        if name == "OutdatedCursor" {
+            return Err(Error::Firehose(FirehoseError::CursorTooOld { message }));
+        }
+        return Ok(RepoEvent::Info(InfoEvent {
+            name: name.to_string(),
+            message,
+        }));
+    }
+
+    let event = fields.and_then(|fields| match kind.as_deref()? {
+        "#commit" => commit_event(&fields, &byte_strings).map(RepoEvent::Commit),
+        "#identity" => Some(RepoEvent::Identity(IdentityEvent {
+            did: text(&fields, "did")?.to_string(),
+            seq: fields.get("seq")?.as_i64()?,
+            time: event_time(&fields)?,
+        })),
+        "#handle" => Some(RepoEvent::Handle(HandleEvent {
+            did: text(&fields, "did")?.to_string(),
+            handle: text(&fields, "handle")?.to_string(),
+            seq: fields.get("seq")?.as_i64()?,
+            time: event_time(&fields)?,
+        })),
+        _ => None,
+    });
+    if let Some(event) = event {
+        return Ok(event);
+    }
+
+    let preview = data
+        .iter()
+        .take(32)
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+
+    Ok(RepoEvent::Unknown {
+        kind: format!("binary:{}", preview),
+    })
+}
+
+#[cfg_attr(
+    not(any(feature = "native-ws", all(feature = "wasm", target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+/// Read a `#commit` body decoded by [`read_dag_cbor_raw`], whose byte
+/// strings are in `byte_strings`.
+fn commit_event(fields: &Value, byte_strings: &[&[u8]]) -> Option<CommitEvent> {
+    let link = |value: &Value| value.get("$link")?.as_str().map(str::to_string);
+    let ops = fields
+        .get("ops")?
+        .as_array()?
+        .iter()
+        .map(|op| {
+            Some(CommitOperation {
+                path: text(op, "path")?.to_string(),
+                action: text(op, "action")?.to_string(),
+                cid: op.get("cid").and_then(link),
             })
-        })?;
+        })
+        .collect::<Option<_>>()?;
+    let blobs = match fields.get("blobs").and_then(Value::as_array) {
+        Some(blobs) => blobs.iter().filter_map(link).collect(),
+        None => Vec::new(),
+    };
+    Some(CommitEvent {
+        repo: text(fields, "repo")?.to_string(),
+        rev: text(fields, "rev")?.to_string(),
+        since: text(fields, "since").map(str::to_string),
+        seq: fields.get("seq")?.as_i64()?,
+        time: event_time(fields)?,
+        ops,
+        blocks: fields
+            .get("blocks")
+            .and_then(|blocks| blocks.get("$bytes")?.as_u64())
+            .and_then(|index| byte_strings.get(usize::try_from(index).ok()?))
+            .map(|blocks| blocks.to_vec())
+            .unwrap_or_default(),
+        blobs,
+    })
+}
+
+#[cfg_attr(
+    not(any(feature = "native-ws", all(feature = "wasm", target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+fn event_time(fields: &Value) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text(fields, "time")?)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// The string at `key` in a decoded map.
+#[cfg_attr(
+    not(any(feature = "native-ws", all(feature = "wasm", target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+pub(crate) fn text<'a>(fields: &'a Value, key: &str) -> Option<&'a str> {
+    fields.get(key)?.as_str()
+}
+
+/// Split an event stream frame into its message type (the header's `t`)
+/// and its undecoded body.
+///
+/// Both are DAG-CBOR, back to back. Error frames (`op: -1`) become
+/// errors, as [`parse_error_frame`] maps them.
+#[cfg_attr(
+    not(any(feature = "native-ws", all(feature = "wasm", target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+pub(crate) fn read_frame(data: &[u8]) -> Result<(Option<String>, &[u8])> {
+    let mut body = data;
+    let header = read_dag_cbor(&mut body)
+        .ok()
+        .filter(Value::is_object)
+        .ok_or_else(|| decode_error("binary", "frame header is not a DAG-CBOR map"))?;
+    match header.get("op").and_then(Value::as_i64) {
+        Some(1) => Ok((text(&header, "t").map(str::to_string), body)),
+        Some(-1) => Err(parse_error_frame(body)),
+        _ => Err(decode_error("binary", "frame header has no valid op")),
+    }
+}
+
+/// Map the body of an error frame (`op: -1`) to a [`FirehoseError`].
+#[cfg_attr(
+    not(any(feature = "native-ws", all(feature = "wasm", target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+pub(crate) fn parse_error_frame(body: &[u8]) -> Error {
+    let Some(fields) = from_dag_cbor(body).ok().filter(Value::is_object) else {
+        return decode_error("error", "error frame body is not a DAG-CBOR map");
+    };
+    let Some(name) = text(&fields, "error") else {
+        return decode_error("error", "error frame has no error name");
+    };
+    Error::Firehose(FirehoseError::ServerError {
+        name: name.to_string(),
+        message: text(&fields, "message").map(str::to_string),
+    })
+}
+
+#[cfg_attr(
+    not(any(feature = "native-ws", all(feature = "wasm", target_arch = "wasm32"))),
+    allow(dead_code)
+)]
+pub(crate) fn decode_error(frame_kind: &str, message: impl Into<String>) -> Error {
+    Error::Firehose(FirehoseError::DecodeError {
+        frame_kind: frame_kind.to_string(),
+        message: message.into(),
+    })
+}
+
+#[cfg(feature = "native-ws")]
+pub(crate) mod native {
+    use std::time::{Duration, Instant};
+
+    use futures_util::{Stream, StreamExt};
+    use tokio_tungstenite::connect_async_with_config;
+    use tokio_tungstenite::tungstenite::error::CapacityError;
+    use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+    use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+    use tracing::{debug, error, info, trace, warn};
+
+    use muat_core::Result;
+    use muat_core::error::{Error, FirehoseError};
+    use muat_core::repo::FirehoseStats;
+
+    use super::{Decoder, FirehoseLimits, Frame, StreamEvent};
+
+    /// Connect to `ws_url`. `source` names the stream in metrics, `limits`
+    /// caps incoming messages, and `timeout` bounds the handshake.
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    pub(crate) async fn connect<T: StreamEvent>(
+        ws_url: String,
+        decode: Decoder<T>,
+        source: &'static str,
+        stats: FirehoseStats,
+        limits: FirehoseLimits,
+        timeout: Option<Duration>,
+    ) -> Result<impl Stream<Item = Result<T>> + Send + 'static> {
+        info!(url = %ws_url, "Connecting to firehose");
+
+        let config = WebSocketConfig::default()
+            .max_message_size(Some(limits.max_message_size()))
+            .max_frame_size(Some(limits.max_frame_size()));
+        let handshake = async {
+            connect_async_with_config(&ws_url, Some(config), false)
+                .await
+                .map_err(|e| {
+                    let status = match &e {
+                        WsError::Http(response) => Some(response.status().as_u16()),
+                        _ => None,
+                    };
+                    Error::Firehose(FirehoseError::ConnectFailed {
+                        message: e.to_string(),
+                        status,
+                    })
+                })
+        };
+        let connected = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, handshake)
+                .await
+                .unwrap_or_else(|_| {
+                    Err(Error::Firehose(FirehoseError::ConnectFailed {
+                        message: format!("handshake timed out after {}ms", timeout.as_millis()),
+                        status: None,
+                    }))
+                }),
+            None => handshake.await,
+        };
+        #[cfg(feature = "metrics")]
+        crate::metrics::connection(source, connected.is_ok());
+        let (ws_stream, _) = connected?;
 
         debug!("WebSocket connected, listening for events");
+        let connected_at = Instant::now();
 
-        let stream = async_stream::stream! {
+        Ok(async_stream::stream! {
             let (mut write, mut read) = ws_stream.split();
 
             while let Some(msg) = read.next().await {
                 match msg {
                     Ok(Message::Binary(data)) => {
-                        yield parse_ws_event(&data);
+                        stats.add_bytes(data.len());
+                        if let Some(event) = decode(Frame::Binary(&data)) {
+                            #[cfg(feature = "metrics")]
+                            crate::metrics::event(source, &event);
+                            yield event;
+                        }
                     }
                     Ok(Message::Ping(data)) => {
                         trace!("Received ping");
@@ -58,7 +606,15 @@ impl XrpcFirehose {
                         break;
                     }
                     Ok(Message::Text(text)) => {
-                        trace!(text = %text, "Received text message");
+                        stats.add_bytes(text.len());
+                        match decode(Frame::Text(&text)) {
+                            Some(event) => {
+                                #[cfg(feature = "metrics")]
+                                crate::metrics::event(source, &event);
+                                yield event;
+                            }
+                            None => trace!(text = %text, "Received text message"),
+                        }
                     }
                     Ok(Message::Pong(_)) => {
                         trace!("Received pong");
@@ -66,52 +622,154 @@ impl XrpcFirehose {
                     Ok(Message::Frame(_)) => {
                         // Raw frame, ignore
                     }
+                    Err(WsError::Capacity(CapacityError::MessageTooLong { size, max_size })) => {
+                        error!(size, limit = max_size, "WebSocket message over the size limit");
+                        let event = Err(Error::Firehose(FirehoseError::MessageTooLarge {
+                            size,
+                            limit: max_size,
+                        }));
+                        #[cfg(feature = "metrics")]
+                        crate::metrics::event(source, &event);
+                        yield event;
+                        break;
+                    }
                     Err(e) => {
                         error!(error = %e, "WebSocket error");
-                        yield Err(Error::Transport(TransportError::Connection {
+                        let event = Err(Error::Firehose(FirehoseError::Disconnected {
+                            after: connected_at.elapsed(),
                             message: e.to_string(),
                         }));
+                        #[cfg(feature = "metrics")]
+                        crate::metrics::event(source, &event);
+                        yield event;
                         break;
                     }
                 }
             }
-        };
-
-        Ok(Self::new(stream))
+        })
     }
 }
 
-impl Stream for XrpcFirehose {
-    type Item = Result<RepoEvent>;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+mod browser {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::Duration;
 
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        self.inner.as_mut().poll_next(cx)
-    }
-}
+    use futures_util::Stream;
+    use send_wrapper::SendWrapper;
+    use tokio::sync::mpsc;
+    use tracing::{info, warn};
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen::closure::Closure;
+    use web_sys::{BinaryType, CloseEvent, Event, MessageEvent, WebSocket};
 
-fn build_ws_url(pds: &PdsUrl, cursor: Option<i64>) -> String {
-    let base = pds.as_str();
-    let ws_base = base
-        .replace("https://", "wss://")
-        .replace("http://", "ws://");
+    use muat_core::Result;
+    use muat_core::error::{Error, FirehoseError};
+    use muat_core::repo::{FirehoseStats, RepoEvent};
+    use muat_core::types::PdsUrl;
 
-    let mut url = format!("{}/xrpc/com.atproto.sync.subscribeRepos", ws_base);
+    use super::{build_ws_url, parse_ws_event};
+    use crate::xrpc::endpoints::SUBSCRIBE_REPOS;
 
-    if let Some(cursor) = cursor {
-        url.push_str(&format!("?cursor={}", cursor));
+    /// Keeps the socket and its callbacks alive for as long as the stream.
+    struct Connection {
+        socket: WebSocket,
+        _on_open: Closure<dyn FnMut(Event)>,
+        _on_message: Closure<dyn FnMut(MessageEvent)>,
+        _on_error: Closure<dyn FnMut(Event)>,
+        _on_close: Closure<dyn FnMut(CloseEvent)>,
     }
 
-    url
-}
+    impl Drop for Connection {
+        fn drop(&mut self) {
+            self.socket.set_onopen(None);
+            self.socket.set_onmessage(None);
+            self.socket.set_onerror(None);
+            self.socket.set_onclose(None);
+            let _ = self.socket.close();
+        }
+    }
 
-fn parse_ws_event(data: &[u8]) -> Result<RepoEvent> {
-    let preview = data
-        .iter()
-        .take(32)
-        .map(|b| format!("{:02x}", b))
-        .collect::<String>();
+    pub(super) fn connect(
+        pds: &PdsUrl,
+        cursor: Option<i64>,
+        stats: FirehoseStats,
+    ) -> Result<impl Stream<Item = Result<RepoEvent>> + Send + 'static> {
+        let ws_url = build_ws_url(pds, SUBSCRIBE_REPOS, cursor);
+        info!(url = %ws_url, "Connecting to firehose (browser)");
 
-    Ok(RepoEvent::Unknown {
-        kind: format!("binary:{}", preview),
-    })
+        let socket = WebSocket::new(&ws_url).map_err(|e| {
+            Error::Firehose(FirehoseError::ConnectFailed {
+                message: format!("{:?}", e),
+                status: None,
+            })
+        })?;
+        socket.set_binary_type(BinaryType::Arraybuffer);
+
+        // None signals the end of the stream.
+        let (tx, mut rx) = mpsc::unbounded_channel::<Option<Result<RepoEvent>>>();
+
+        let message_tx = tx.clone();
+        let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+            if let Ok(buffer) = event.data().dyn_into::<js_sys::ArrayBuffer>() {
+                let data = js_sys::Uint8Array::new(&buffer).to_vec();
+                stats.add_bytes(data.len());
+                let _ = message_tx.send(Some(parse_ws_event(&data)));
+            }
+        });
+
+        // When the socket opened, in milliseconds since the epoch.
+        let opened_at = Rc::new(Cell::new(None));
+        let open_time = opened_at.clone();
+        let on_open = Closure::<dyn FnMut(Event)>::new(move |_event: Event| {
+            open_time.set(Some(js_sys::Date::now()));
+        });
+
+        let error_tx = tx.clone();
+        let on_error = Closure::<dyn FnMut(Event)>::new(move |_event: Event| {
+            warn!("WebSocket error");
+            // The browser does not say what went wrong.
+            let message = "browser WebSocket error".to_string();
+            let error = match opened_at.get() {
+                Some(opened) => FirehoseError::Disconnected {
+                    after: Duration::from_millis((js_sys::Date::now() - opened).max(0.0) as u64),
+                    message,
+                },
+                None => FirehoseError::ConnectFailed {
+                    message,
+                    status: None,
+                },
+            };
+            let _ = error_tx.send(Some(Err(Error::Firehose(error))));
+            let _ = error_tx.send(None);
+        });
+
+        let on_close = Closure::<dyn FnMut(CloseEvent)>::new(move |event: CloseEvent| {
+            info!(code = event.code(), "WebSocket closed by server");
+            let _ = tx.send(None);
+        });
+
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        socket.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+        // Browser handles are single-threaded; wasm32 has no other threads to
+        // move them to, so wrapping them to satisfy `Send` is sound here.
+        let connection = SendWrapper::new(Connection {
+            socket,
+            _on_open: on_open,
+            _on_message: on_message,
+            _on_error: on_error,
+            _on_close: on_close,
+        });
+
+        Ok(async_stream::stream! {
+            let _connection = connection;
+            while let Some(Some(event)) = rx.recv().await {
+                yield event;
+            }
+        })
+    }
 }
@@ -0,0 +1,201 @@
+//! Jetstream consumer.
+//!
+//! Jetstream is a JSON re-encoding of the repository firehose, served over a
+//! plain WebSocket. Events are mapped onto the same [`RepoEvent`] enum as the
+//! CBOR firehose so consumers do not need to care which one they are reading.
+
+use serde::Deserialize;
+use tracing::debug;
+
+use muat_core::Result;
+use muat_core::error::{Error, InvalidInputError};
+use muat_core::repo::{CommitEvent, CommitOperation, FirehoseStats, IdentityEvent, RepoEvent};
+use muat_core::types::{Did, Nsid};
+
+use crate::firehose::{FirehoseLimits, Frame, XrpcFirehose, decode_error, native};
+
+impl XrpcFirehose {
+    /// Connect to a Jetstream instance.
+    ///
+    /// `url` is the instance base URL (e.g.
+    /// `wss://jetstream2.us-east.bsky.network`) or its `/subscribe` endpoint.
+    /// Empty filter lists subscribe to everything; filtering is applied by the
+    /// server.
+    pub async fn from_jetstream(
+        url: &str,
+        wanted_collections: &[Nsid],
+        wanted_dids: &[Did],
+    ) -> Result<Self> {
+        let ws_url = build_jetstream_url(url, wanted_collections, wanted_dids)?;
+        let stats = FirehoseStats::new();
+        native::connect(
+            ws_url,
+            decode_jetstream,
+            "jetstream",
+            stats.clone(),
+            FirehoseLimits::default(),
+            None,
+        )
+        .await
+        .map(|stream| Self::new(stream, stats))
+    }
+}
+
+fn build_jetstream_url(
+    url: &str,
+    wanted_collections: &[Nsid],
+    wanted_dids: &[Did],
+) -> Result<String> {
+    let base = if let Some(rest) = url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else if url.starts_with("wss://") || url.starts_with("ws://") {
+        url.to_string()
+    } else {
+        return Err(InvalidInputError::Other {
+            message: format!("Jetstream URL must use ws, wss, http or https: {}", url),
+        }
+        .into());
+    };
+
+    let base = base.trim_end_matches('/');
+    let mut ws_url = if base.ends_with("/subscribe") {
+        base.to_string()
+    } else {
+        format!("{}/subscribe", base)
+    };
+
+    let params: Vec<(&str, &str)> = wanted_collections
+        .iter()
+        .map(|c| ("wantedCollections", c.as_str()))
+        .chain(wanted_dids.iter().map(|d| ("wantedDids", d.as_str())))
+        .collect();
+
+    if !params.is_empty() {
+        let query = serde_urlencoded::to_string(&params).map_err(|e| {
+            Error::InvalidInput(InvalidInputError::Other {
+                message: format!("failed to encode Jetstream filters: {}", e),
+            })
+        })?;
+        ws_url.push('?');
+        ws_url.push_str(&query);
+    }
+
+    Ok(ws_url)
+}
+
+fn decode_jetstream(frame: Frame<'_>) -> Option<Result<RepoEvent>> {
+    match frame {
+        Frame::Text(text) => Some(parse_jetstream_event(text)),
+        // Compressed (zstd) frames are only sent when requested.
+        Frame::Binary(_) => None,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JetstreamMessage {
+    did: String,
+    time_us: i64,
+    kind: String,
+    commit: Option<JetstreamCommit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JetstreamCommit {
+    rev: String,
+    operation: String,
+    collection: String,
+    rkey: String,
+    cid: Option<String>,
+}
+
+fn parse_jetstream_event(text: &str) -> Result<RepoEvent> {
+    let message: JetstreamMessage = serde_json::from_str(text)
+        .map_err(|e| decode_error("text", format!("invalid Jetstream event: {}", e)))?;
+
+    // Jetstream cursors are microsecond timestamps, so they double as `seq`.
+    let time = chrono::DateTime::from_timestamp_micros(message.time_us).unwrap_or_default();
+
+    let event = match (message.kind.as_str(), message.commit) {
+        ("commit", Some(commit)) => RepoEvent::Commit(CommitEvent {
+            repo: message.did,
+            rev: commit.rev,
+            since: None,
+            seq: message.time_us,
+            time,
+            ops: vec![CommitOperation {
+                path: format!("{}/{}", commit.collection, commit.rkey),
+                action: commit.operation,
+                cid: commit.cid,
+            }],
+            blocks: Vec::new(),
+            blobs: Vec::new(),
+        }),
+        ("identity", _) => RepoEvent::Identity(IdentityEvent {
+            did: message.did,
+            seq: message.time_us,
+            time,
+        }),
+        (kind, _) => {
+            debug!(kind, "Unhandled Jetstream event kind");
+            RepoEvent::Unknown {
+                kind: format!("jetstream:{}", kind),
+            }
+        }
+    };
+
+    Ok(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use muat_core::error::FirehoseError;
+
+    #[test]
+    fn url_includes_filters() {
+        let url = build_jetstream_url(
+            "https://jetstream.example.com/",
+            &[Nsid::new("app.bsky.feed.post").unwrap()],
+            &[Did::new("did:plc:abcaaaaaaaaaaaaaaaaaaaaa").unwrap()],
+        )
+        .unwrap();
+        assert_eq!(
+            url,
+            "wss://jetstream.example.com/subscribe?wantedCollections=app.bsky.feed.post&wantedDids=did%3Aplc%3Aabcaaaaaaaaaaaaaaaaaaaaa"
+        );
+    }
+
+    #[test]
+    fn parses_commit_event() {
+        let text = r#"{"did":"did:plc:abcaaaaaaaaaaaaaaaaaaaaa","time_us":1725911162329308,"kind":"commit","commit":{"rev":"3l3qo2vutsw2b","operation":"create","collection":"app.bsky.feed.post","rkey":"3l3qo2vuowo2b","record":{"text":"hi"},"cid":"bafyrei"}}"#;
+
+        let RepoEvent::Commit(commit) = parse_jetstream_event(text).unwrap() else {
+            panic!("expected commit");
+        };
+        assert_eq!(commit.repo, "did:plc:abcaaaaaaaaaaaaaaaaaaaaa");
+        assert_eq!(commit.seq, 1725911162329308);
+        assert_eq!(commit.time.timestamp_micros(), 1725911162329308);
+        assert_eq!(commit.ops[0].path, "app.bsky.feed.post/3l3qo2vuowo2b");
+        assert_eq!(commit.ops[0].action, "create");
+    }
+
+    #[test]
+    fn malformed_events_are_decode_errors() {
+        let err =
+            parse_jetstream_event(r#"{"did":"did:plc:abcaaaaaaaaaaaaaaaaaaaaa"}"#).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Firehose(FirehoseError::DecodeError { ref frame_kind, .. }) if frame_kind == "text"
+        ));
+        assert!(!matches!(err, Error::Firehose(ref e) if e.is_retryable()));
+    }
+
+    #[test]
+    fn unknown_kinds_are_preserved() {
+        let text = r#"{"did":"did:plc:abcaaaaaaaaaaaaaaaaaaaaa","time_us":1,"kind":"account","account":{"active":true}}"#;
+        let event = parse_jetstream_event(text).unwrap();
+        assert!(matches!(event, RepoEvent::Unknown { kind } if kind == "jetstream:account"));
+    }
+}
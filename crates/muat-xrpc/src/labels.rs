@@ -0,0 +1,238 @@
+//! Labels from a labeler service.
+//!
+//! `com.atproto.label.queryLabels` pages through stored labels, and
+//! `com.atproto.label.subscribeLabels` streams new ones over a WebSocket in
+//! the same framing as `subscribeRepos`. Labelers are usually separate
+//! services, so point an [`XrpcPds`] at the labeler's URL to use these.
+
+#[cfg(feature = "native-ws")]
+use std::collections::VecDeque;
+#[cfg(feature = "native-ws")]
+use std::pin::Pin;
+#[cfg(feature = "native-ws")]
+use std::task::{Context, Poll};
+
+#[cfg(feature = "native-ws")]
+use futures_util::Stream;
+use tracing::instrument;
+
+use muat_core::Result;
+use muat_core::error::{Error, InvalidInputError};
+#[cfg(feature = "native-ws")]
+use muat_core::repo::LabelEvent;
+use muat_core::repo::QueryLabelsOutput;
+use muat_core::types::Did;
+
+use crate::pds::XrpcPds;
+use crate::xrpc::endpoints::{QUERY_LABELS, QueryLabelsResponse};
+
+impl XrpcPds {
+    /// Query the labels applied to subjects matching `uri_patterns`, oldest
+    /// first. Needs no session.
+    ///
+    /// A pattern is an AT URI or DID, optionally ending in `*` to match by
+    /// prefix. With `sources`, only labels created by those labelers are
+    /// returned. Labels from a query carry sequence number 0.
+    #[instrument(skip(self), fields(pds = %self.url()))]
+    pub async fn query_labels(
+        &self,
+        uri_patterns: &[&str],
+        sources: &[Did],
+        limit: Option<u32>,
+        cursor: Option<&str>,
+    ) -> Result<QueryLabelsOutput> {
+        if uri_patterns.is_empty() {
+            return Err(Error::InvalidInput(InvalidInputError::Other {
+                message: "queryLabels needs at least one URI pattern".to_string(),
+            }));
+        }
+        let mut params: Vec<(&str, String)> = uri_patterns
+            .iter()
+            .map(|pattern| ("uriPatterns", pattern.to_string()))
+            .chain(sources.iter().map(|did| ("sources", did.to_string())))
+            .collect();
+        if let Some(limit) = limit {
+            params.push(("limit", limit.to_string()));
+        }
+        if let Some(cursor) = cursor {
+            params.push(("cursor", cursor.to_string()));
+        }
+
+        let response: QueryLabelsResponse = self.client().query(QUERY_LABELS, &params).await?;
+        Ok(QueryLabelsOutput {
+            labels: response.labels,
+            cursor: response.cursor,
+        })
+    }
+
+    /// Subscribe to the labeler's `subscribeLabels` stream, starting after
+    /// `cursor` if given, or with new labels only.
+    ///
+    /// Each label in a message becomes its own [`LabelEvent`] carrying the
+    /// message's sequence number; resume with the last one seen.
+    #[cfg(feature = "native-ws")]
+    pub async fn subscribe_labels(&self, cursor: Option<i64>) -> Result<XrpcLabelStream> {
+        use crate::firehose::{build_ws_url, native, reject_unix_socket};
+        use crate::xrpc::endpoints::SUBSCRIBE_LABELS;
+        use muat_core::repo::FirehoseStats;
+
+        reject_unix_socket(self.url())?;
+        let stream = native::connect(
+            build_ws_url(self.url(), SUBSCRIBE_LABELS, cursor),
+            decode::decode_labels,
+            "labels",
+            FirehoseStats::new(),
+            self.firehose_limits(),
+            self.client().timeout_for(SUBSCRIBE_LABELS),
+        )
+        .await?;
+        Ok(XrpcLabelStream {
+            inner: Box::pin(stream),
+            pending: VecDeque::new(),
+            last_seq: cursor,
+        })
+    }
+}
+
+/// Stream of labels from [`XrpcPds::subscribe_labels`].
+#[cfg(feature = "native-ws")]
+pub struct XrpcLabelStream {
+    inner: Pin<Box<dyn Stream<Item = Result<Vec<LabelEvent>>> + Send>>,
+    /// Labels from the last message not yet yielded.
+    pending: VecDeque<LabelEvent>,
+    last_seq: Option<i64>,
+}
+
+#[cfg(feature = "native-ws")]
+impl XrpcLabelStream {
+    /// The sequence number of the last message received, to resume from.
+    pub fn last_seq(&self) -> Option<i64> {
+        self.last_seq
+    }
+}
+
+#[cfg(feature = "native-ws")]
+impl std::fmt::Debug for XrpcLabelStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("XrpcLabelStream")
+            .field("pending", &self.pending.len())
+            .field("last_seq", &self.last_seq)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "native-ws")]
+impl Stream for XrpcLabelStream {
+    type Item = Result<LabelEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(label) = self.pending.pop_front() {
+                return Poll::Ready(Some(Ok(label)));
+            }
+            match self.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(labels))) => {
+                    if let Some(seq) = labels.first().map(|label| label.seq) {
+                        self.last_seq = Some(seq);
+                    }
+                    self.pending.extend(labels);
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "native-ws")]
+mod decode {
+    use chrono::{DateTime, Utc};
+    use serde_json::Value;
+    use tracing::{debug, info};
+
+    use muat_core::Result;
+    use muat_core::repo::{LabelEvent, from_dag_cbor};
+
+    use crate::firehose::{Frame, StreamEvent, decode_error, read_frame, text};
+
+    impl StreamEvent for Vec<LabelEvent> {
+        fn kind(&self) -> &'static str {
+            "labels"
+        }
+    }
+
+    /// Decode a `subscribeLabels` frame into its labels, skipping info and
+    /// unknown messages.
+    pub(super) fn decode_labels(frame: Frame<'_>) -> Option<Result<Vec<LabelEvent>>> {
+        let Frame::Binary(data) = frame else {
+            return None;
+        };
+        let (kind, body) = match read_frame(data) {
+            Ok(frame) => frame,
+            Err(e) => return Some(Err(e)),
+        };
+        let Some(fields) = from_dag_cbor(body).ok().filter(Value::is_object) else {
+            return Some(Err(decode_error(
+                "labels",
+                "message body is not a DAG-CBOR map",
+            )));
+        };
+        match kind.as_deref() {
+            Some("#labels") => Some(labels(&fields)),
+            Some("#info") => {
+                info!(
+                    name = text(&fields, "name"),
+                    message = text(&fields, "message"),
+                    "Label stream info"
+                );
+                None
+            }
+            kind => {
+                debug!(?kind, "Skipping unknown label stream message");
+                None
+            }
+        }
+    }
+
+    fn labels(fields: &Value) -> Result<Vec<LabelEvent>> {
+        let seq = fields
+            .get("seq")
+            .and_then(Value::as_i64)
+            .ok_or_else(|| decode_error("labels", "labels message has no seq"))?;
+        let Some(items) = fields.get("labels").and_then(Value::as_array) else {
+            return Err(decode_error("labels", "labels message has no labels"));
+        };
+        items
+            .iter()
+            .map(|item| match item {
+                Value::Object(_) => self::label(seq, item),
+                _ => Err(decode_error("labels", "label is not a map")),
+            })
+            .collect()
+    }
+
+    fn label(seq: i64, fields: &Value) -> Result<LabelEvent> {
+        let required = |key: &str| {
+            text(fields, key)
+                .map(str::to_string)
+                .ok_or_else(|| decode_error("labels", format!("label has no {}", key)))
+        };
+        Ok(LabelEvent {
+            seq,
+            src: required("src")?,
+            uri: required("uri")?,
+            cid: text(fields, "cid").map(str::to_string),
+            val: required("val")?,
+            neg: fields.get("neg") == Some(&Value::Bool(true)),
+            cts: time(&required("cts")?)?,
+            exp: text(fields, "exp").map(time).transpose()?,
+        })
+    }
+
+    fn time(value: &str) -> Result<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(value)
+            .map(|time| time.with_timezone(&Utc))
+            .map_err(|e| decode_error("labels", format!("invalid label time {:?}: {}", value, e)))
+    }
+}
@@ -1,10 +1,30 @@
 //! muat-xrpc - XRPC-backed PDS implementation.
 
 mod firehose;
+#[cfg(feature = "native-ws")]
+mod jetstream;
+mod labels;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod middleware;
 mod pds;
+mod relay;
 mod session;
+mod transport;
 mod xrpc;
 
-pub use firehose::XrpcFirehose;
+pub use firehose::{FirehoseLimits, XrpcFirehose};
+#[cfg(feature = "native-ws")]
+pub use labels::XrpcLabelStream;
+pub use middleware::{Middleware, Next};
 pub use pds::XrpcPds;
+pub use relay::{RelayClient, RelayFirehose};
 pub use session::XrpcSession;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub use transport::FetchTransport;
+#[cfg(feature = "reqwest")]
+pub use transport::ReqwestTransport;
+#[cfg(all(unix, feature = "unix-socket"))]
+pub use transport::UnixSocketTransport;
+pub use transport::{HttpMethod, HttpRequest, HttpResponse, HttpTransport};
+pub use xrpc::client::BinaryResponse;
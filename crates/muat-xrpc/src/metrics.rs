@@ -0,0 +1,59 @@
+//! Metrics recorded through the [`metrics`] facade.
+//!
+//! Nothing is exported unless the embedding application installs a recorder,
+//! such as `metrics-exporter-prometheus`.
+
+use std::time::Duration;
+
+use metrics::{counter, histogram};
+
+use muat_core::Result;
+
+#[cfg(feature = "native-ws")]
+use crate::firehose::StreamEvent;
+use crate::transport::HttpResponse;
+use crate::xrpc::endpoints::XrpcErrorResponse;
+
+#[cfg(target_arch = "wasm32")]
+compile_error!("the `metrics` feature times requests with std::time::Instant, which wasm32 lacks");
+
+/// Count an XRPC request and record its latency and any error code.
+pub(crate) fn request(method: &str, response: &Result<HttpResponse>, elapsed: Duration) {
+    let method = method.to_string();
+    let (status, error) = match response {
+        Ok(response) if response.is_success() => (response.status.to_string(), None),
+        Ok(response) => {
+            let code = serde_json::from_slice::<XrpcErrorResponse>(&response.body)
+                .ok()
+                .and_then(|body| body.error)
+                .unwrap_or_else(|| response.status.to_string());
+            (response.status.to_string(), Some(code))
+        }
+        Err(_) => ("transport".to_string(), Some("transport".to_string())),
+    };
+
+    counter!("muat_xrpc_requests_total", "method" => method.clone(), "status" => status)
+        .increment(1);
+    histogram!("muat_xrpc_request_duration_seconds", "method" => method.clone())
+        .record(elapsed.as_secs_f64());
+    if let Some(error) = error {
+        counter!("muat_xrpc_errors_total", "method" => method, "error" => error).increment(1);
+    }
+}
+
+/// Count a WebSocket connection attempt.
+pub(crate) fn connection(source: &'static str, connected: bool) {
+    let result = if connected { "ok" } else { "error" };
+    counter!("muat_firehose_connections_total", "source" => source, "result" => result)
+        .increment(1);
+}
+
+/// Count an event received from a WebSocket stream.
+#[cfg(feature = "native-ws")]
+pub(crate) fn event<E: StreamEvent>(source: &'static str, event: &Result<E>) {
+    let kind = match event {
+        Ok(event) => event.kind(),
+        Err(_) => "error",
+    };
+    counter!("muat_firehose_events_total", "source" => source, "kind" => kind).increment(1);
+}
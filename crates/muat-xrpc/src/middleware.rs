@@ -0,0 +1,119 @@
+//! Request/response middleware for the XRPC client.
+//!
+//! Middleware sits between the XRPC client and its [`HttpTransport`]. Each
+//! layer sees every outgoing [`HttpRequest`] and can change it, answer it
+//! itself, or pass it on with [`Next::run`] and inspect the response. Layers
+//! run in the order they were added, so the first one added is outermost.
+//!
+//! [`XrpcPds::with_header_provider`](crate::XrpcPds::with_header_provider)
+//! and [`with_interceptor`](crate::XrpcPds::with_interceptor) cover the
+//! common cases with a closure; implement [`Middleware`] for anything that
+//! needs the response, such as caching or timing.
+
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use muat_core::Result;
+
+use crate::transport::{HttpRequest, HttpResponse, HttpTransport};
+
+/// A layer in the XRPC client's request chain.
+///
+/// Requests carry the session's bearer token in the `authorization` header;
+/// implementations must not log it.
+#[async_trait]
+pub trait Middleware: Send + Sync + fmt::Debug {
+    /// Handle a request, usually by calling `next.run(request)`.
+    async fn handle(&self, request: HttpRequest, next: Next<'_>) -> Result<HttpResponse>;
+}
+
+/// Lets callers keep a handle on middleware they share with the client.
+#[async_trait]
+impl<M: Middleware + ?Sized> Middleware for Arc<M> {
+    async fn handle(&self, request: HttpRequest, next: Next<'_>) -> Result<HttpResponse> {
+        (**self).handle(request, next).await
+    }
+}
+
+/// The rest of the chain after the current middleware.
+pub struct Next<'a> {
+    middleware: &'a [Arc<dyn Middleware>],
+    transport: &'a dyn HttpTransport,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(
+        middleware: &'a [Arc<dyn Middleware>],
+        transport: &'a dyn HttpTransport,
+    ) -> Self {
+        Self {
+            middleware,
+            transport,
+        }
+    }
+
+    /// Pass the request to the next middleware, or to the transport at the
+    /// end of the chain.
+    pub async fn run(self, request: HttpRequest) -> Result<HttpResponse> {
+        match self.middleware.split_first() {
+            Some((first, rest)) => first.handle(request, Next::new(rest, self.transport)).await,
+            None => self.transport.send(request).await,
+        }
+    }
+}
+
+impl fmt::Debug for Next<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Next")
+            .field("remaining", &self.middleware.len())
+            .finish()
+    }
+}
+
+/// Adds the headers returned by a closure to every request, replacing any
+/// header of the same name.
+pub(crate) struct HeaderProvider<F>(pub(crate) F);
+
+#[async_trait]
+impl<F> Middleware for HeaderProvider<F>
+where
+    F: Fn() -> Vec<(String, String)> + Send + Sync,
+{
+    async fn handle(&self, mut request: HttpRequest, next: Next<'_>) -> Result<HttpResponse> {
+        for (name, value) in (self.0)() {
+            request
+                .headers
+                .retain(|(existing, _)| !existing.eq_ignore_ascii_case(&name));
+            request.headers.push((name, value));
+        }
+        next.run(request).await
+    }
+}
+
+impl<F> fmt::Debug for HeaderProvider<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("HeaderProvider")
+    }
+}
+
+/// Calls a closure on every request before it is sent.
+pub(crate) struct Interceptor<F>(pub(crate) F);
+
+#[async_trait]
+impl<F> Middleware for Interceptor<F>
+where
+    F: Fn(&mut HttpRequest) + Send + Sync,
+{
+    async fn handle(&self, mut request: HttpRequest, next: Next<'_>) -> Result<HttpResponse> {
+        (self.0)(&mut request);
+        next.run(request).await
+    }
+}
+
+impl<F> fmt::Debug for Interceptor<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Interceptor")
+    }
+}
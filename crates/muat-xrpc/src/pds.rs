@@ -1,16 +1,24 @@
 //! XRPC-backed PDS implementation.
 
+use std::sync::Arc;
+use std::time::Duration;
+
 use async_trait::async_trait;
 use tracing::{debug, instrument};
 
-use muat_core::error::AuthError;
-use muat_core::repo::{ListRecordsOutput, Record, RecordValue};
-use muat_core::traits::{CreateAccountOutput, Pds};
-use muat_core::types::{AtUri, Did, Nsid, PdsUrl};
-use muat_core::{AccessToken, Credentials, RefreshToken, Result};
-
-use crate::firehose::XrpcFirehose;
+use muat_core::error::{AuthError, ProtocolError};
+use muat_core::repo::{
+    BlobRef, ByteRange, Car, FirehoseBuffer, ListBlobsOutput, ListRecordsOptions,
+    ListRecordsOutput, Record, RecordOrder, RecordValue,
+};
+use muat_core::traits::{CreateAccountOutput, Pds, ServerDescription, ServerLinks, StoredSession};
+use muat_core::types::{AtUri, Cid, Did, Handle, Nsid, PdsUrl, Rkey, Tid};
+use muat_core::{AccessToken, Credentials, Error, RefreshToken, Result};
+
+use crate::firehose::{FirehoseLimits, XrpcFirehose};
+use crate::middleware::{HeaderProvider, Interceptor, Middleware};
 use crate::session::XrpcSession;
+use crate::transport::{HttpRequest, HttpTransport};
 use crate::xrpc::client::XrpcClient;
 use crate::xrpc::endpoints::*;
 
@@ -59,13 +67,126 @@ struct DeleteAccountRequest<'a> {
 pub struct XrpcPds {
     pds: PdsUrl,
     client: XrpcClient,
+    firehose_buffer: FirehoseBuffer,
+    firehose_limits: FirehoseLimits,
 }
 
 impl XrpcPds {
-    /// Create a new XRPC PDS for the given PDS URL.
+    /// Create a new XRPC PDS for the given PDS URL using the default transport.
+    ///
+    /// The default is [`ReqwestTransport`](crate::ReqwestTransport) with the
+    /// `reqwest` feature, or `FetchTransport` with `wasm` on wasm32.
+    #[cfg(any(feature = "reqwest", all(feature = "wasm", target_arch = "wasm32")))]
     pub fn new(pds: PdsUrl) -> Self {
         let client = XrpcClient::new(pds.clone());
-        Self { pds, client }
+        Self {
+            pds,
+            client,
+            firehose_buffer: FirehoseBuffer::default(),
+            firehose_limits: FirehoseLimits::default(),
+        }
+    }
+
+    /// Create a new XRPC PDS that sends requests through a custom transport.
+    pub fn with_transport(pds: PdsUrl, transport: Arc<dyn HttpTransport>) -> Self {
+        let client = XrpcClient::with_transport(pds.clone(), transport);
+        Self {
+            pds,
+            client,
+            firehose_buffer: FirehoseBuffer::default(),
+            firehose_limits: FirehoseLimits::default(),
+        }
+    }
+
+    /// Set how many firehose events are buffered for a slow consumer, and
+    /// what happens when the buffer fills (100 events, blocking, by default).
+    pub fn with_firehose_buffer(mut self, buffer: FirehoseBuffer) -> Self {
+        self.firehose_buffer = buffer;
+        self
+    }
+
+    /// Set the largest firehose and label stream messages accepted (16 MiB
+    /// by default). A larger message ends the stream with
+    /// [`FirehoseError::MessageTooLarge`](muat_core::error::FirehoseError::MessageTooLarge).
+    pub fn with_firehose_limits(mut self, limits: FirehoseLimits) -> Self {
+        self.firehose_limits = limits;
+        self
+    }
+
+    /// The firehose message size limits.
+    pub fn firehose_limits(&self) -> FirehoseLimits {
+        self.firehose_limits
+    }
+
+    /// Fail requests that take longer than `timeout` with
+    /// [`TransportError::Timeout`](muat_core::error::TransportError::Timeout),
+    /// unless a more specific timeout applies. Also bounds the firehose
+    /// WebSocket handshake. By default requests wait indefinitely.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = self.client.with_timeout(timeout);
+        self
+    }
+
+    /// Set the timeout for one XRPC method, overriding
+    /// [`with_timeout`](Self::with_timeout). Use
+    /// `com.atproto.sync.subscribeRepos` for the firehose handshake.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use muat_core::PdsUrl;
+    /// # use muat_xrpc::XrpcPds;
+    /// # fn example(url: PdsUrl) {
+    /// let pds = XrpcPds::new(url)
+    ///     .with_timeout(Duration::from_secs(10))
+    ///     .with_endpoint_timeout("com.atproto.repo.uploadBlob", Duration::from_secs(300));
+    /// # }
+    /// ```
+    pub fn with_endpoint_timeout(mut self, method: &str, timeout: Duration) -> Self {
+        self.client = self.client.with_endpoint_timeout(method, timeout);
+        self
+    }
+
+    pub(crate) fn with_timeout_override(mut self, timeout: Duration) -> Self {
+        self.client = self.client.with_timeout_override(timeout);
+        self
+    }
+
+    /// Add a [`Middleware`] layer to every request this PDS and its sessions
+    /// send. Layers run in the order added, the first outermost.
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.client = self.client.with_middleware(Arc::new(middleware));
+        self
+    }
+
+    /// Add the headers `provider` returns to every request, replacing any
+    /// header of the same name. It is called once per request, so values
+    /// can change over time.
+    ///
+    /// ```no_run
+    /// # use muat_core::PdsUrl;
+    /// # use muat_xrpc::XrpcPds;
+    /// # fn example(url: PdsUrl) {
+    /// let pds = XrpcPds::new(url).with_header_provider(|| {
+    ///     vec![(
+    ///         "atproto-proxy".to_string(),
+    ///         "did:web:api.bsky.chat#bsky_chat".to_string(),
+    ///     )]
+    /// });
+    /// # }
+    /// ```
+    pub fn with_header_provider<F>(self, provider: F) -> Self
+    where
+        F: Fn() -> Vec<(String, String)> + Send + Sync + 'static,
+    {
+        self.with_middleware(HeaderProvider(provider))
+    }
+
+    /// Call `interceptor` on every request before it is sent.
+    pub fn with_interceptor<F>(self, interceptor: F) -> Self
+    where
+        F: Fn(&mut HttpRequest) + Send + Sync + 'static,
+    {
+        self.with_middleware(Interceptor(interceptor))
     }
 
     /// Returns the PDS URL for this instance.
@@ -73,12 +194,52 @@ impl XrpcPds {
         &self.pds
     }
 
+    pub(crate) fn client(&self) -> &XrpcClient {
+        &self.client
+    }
+
+    /// Check that a handle can be registered on this PDS before creating an
+    /// account: it must be valid, and under one of the server's available
+    /// user domains if it advertises any. Needs no session.
+    ///
+    /// The domains come from `describeServer`, so self-hosted PDSes with
+    /// their own domains are checked the same way as `bsky.social`. Whether
+    /// the handle is already taken is left to the PDS.
+    #[instrument(skip(self), fields(pds = %self.pds))]
+    pub async fn check_new_handle(&self, handle: &str) -> Result<Handle> {
+        let handle = Handle::new(handle)?;
+        let description = self.describe().await?;
+        handle.check_user_domains(&description.available_user_domains)?;
+        Ok(handle)
+    }
+
+    /// Resolve a handle to a DID. Needs no session.
+    #[instrument(skip(self), fields(pds = %self.pds))]
+    pub async fn resolve_handle(&self, handle: &str) -> Result<Did> {
+        let response: ResolveHandleResponse = self
+            .client
+            .query(RESOLVE_HANDLE, &ResolveHandleQuery { handle })
+            .await?;
+        Did::new(response.did)
+    }
+
+    /// Get a record only if it still has `cid`. Needs no session.
+    ///
+    /// A PDS keeps no earlier versions, so a record that has changed since
+    /// is `RecordNotFound`.
+    pub async fn get_record_at(&self, uri: &AtUri, cid: &str) -> Result<Record> {
+        self.get_record(uri, Some(cid), None).await
+    }
+
     pub async fn refresh_session(&self, refresh_token: &str) -> Result<RefreshSessionResponse> {
         self.client
             .procedure_authed_no_body(REFRESH_SESSION, refresh_token)
             .await
+            .map_err(account_status_error)
     }
 
+    /// Create a record, returning its URI and the commit rev if the PDS
+    /// reported one.
     #[instrument(skip(self, value, token))]
     pub(crate) async fn create_record(
         &self,
@@ -87,7 +248,7 @@ impl XrpcPds {
         value: &RecordValue,
         rkey: Option<&str>,
         token: &str,
-    ) -> Result<AtUri> {
+    ) -> Result<(AtUri, Option<String>)> {
         debug!(repo = %repo, collection = %collection, "Creating record via XRPC");
 
         let request = CreateRecordRequest {
@@ -103,22 +264,204 @@ impl XrpcPds {
             .procedure_authed(CREATE_RECORD, &request, token)
             .await?;
 
-        AtUri::new(&response.uri)
+        Ok((
+            AtUri::new(&response.uri)?,
+            response.commit.map(|commit| commit.rev),
+        ))
+    }
+
+    /// Create records in one atomic applyWrites call, returning their URIs
+    /// and the commit rev if the PDS reported one.
+    #[instrument(skip(self, values, token), fields(count = values.len()))]
+    pub(crate) async fn apply_creates(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        values: &[RecordValue],
+        token: &str,
+    ) -> Result<(Vec<AtUri>, Option<String>)> {
+        debug!(repo = %repo, collection = %collection, "Creating records via applyWrites");
+
+        let request = ApplyWritesRequest {
+            repo: repo.as_str(),
+            writes: values
+                .iter()
+                .map(|value| ApplyWritesCreate {
+                    kind: "com.atproto.repo.applyWrites#create",
+                    collection: collection.as_str(),
+                    value: value.as_value(),
+                })
+                .collect(),
+        };
+
+        let response: ApplyWritesResponse = self
+            .client
+            .procedure_authed(APPLY_WRITES, &request, token)
+            .await?;
+
+        let results = response.results.unwrap_or_default();
+        if results.len() != values.len() {
+            return Err(ProtocolError::new(
+                200,
+                Some("InvalidResponse".to_string()),
+                Some(format!(
+                    "applyWrites returned {} results for {} writes",
+                    results.len(),
+                    values.len()
+                )),
+            )
+            .into());
+        }
+
+        let uris = results
+            .into_iter()
+            .map(|result| match result.uri {
+                Some(uri) => AtUri::new(&uri),
+                None => Err(ProtocolError::new(
+                    200,
+                    Some("InvalidResponse".to_string()),
+                    Some("applyWrites create result has no URI".to_string()),
+                )
+                .into()),
+            })
+            .collect::<Result<_>>()?;
+        Ok((uris, response.commit.map(|commit| commit.rev)))
+    }
+
+    /// Delete records from `repo` in one atomic applyWrites call, returning
+    /// the commit rev if the PDS reported one.
+    #[instrument(skip(self, uris, token), fields(count = uris.len()))]
+    pub(crate) async fn apply_deletes(
+        &self,
+        repo: &Did,
+        uris: &[AtUri],
+        token: &str,
+    ) -> Result<Option<String>> {
+        debug!(repo = %repo, "Deleting records via applyWrites");
+
+        let writes = uris
+            .iter()
+            .map(|uri| {
+                let (collection, rkey) = uri.record_path()?;
+                Ok(ApplyWritesDelete {
+                    kind: "com.atproto.repo.applyWrites#delete",
+                    collection: collection.as_str(),
+                    rkey: rkey.as_str(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        let request = ApplyWritesRequest {
+            repo: repo.as_str(),
+            writes,
+        };
+
+        let response: ApplyWritesResponse = self
+            .client
+            .procedure_authed(APPLY_WRITES, &request, token)
+            .await?;
+        Ok(response.commit.map(|commit| commit.rev))
+    }
+
+    #[instrument(skip(self, data, token), fields(size = data.len()))]
+    pub(crate) async fn upload_blob(
+        &self,
+        data: Vec<u8>,
+        mime_type: &str,
+        token: &str,
+    ) -> Result<BlobRef> {
+        debug!(mime_type, "Uploading blob via XRPC");
+
+        let response: UploadBlobResponse = self
+            .client
+            .procedure_binary_authed(UPLOAD_BLOB, data, mime_type, token)
+            .await?
+            .json()?;
+
+        Ok(response.blob)
     }
 
     #[instrument(skip(self, token))]
-    pub(crate) async fn get_record(&self, uri: &AtUri, token: &str) -> Result<Record> {
-        debug!(uri = %uri, "Getting record via XRPC");
+    pub(crate) async fn get_blob(&self, did: &Did, cid: &str, token: &str) -> Result<Vec<u8>> {
+        debug!(did = %did, cid, "Getting blob via XRPC");
+
+        let query = GetBlobQuery {
+            did: did.as_str(),
+            cid,
+        };
 
+        let response = self
+            .client
+            .query_binary_authed(GET_BLOB, &query, token)
+            .await?;
+        Ok(response.body)
+    }
+
+    #[instrument(skip(self, token))]
+    pub(crate) async fn get_blob_range(
+        &self,
+        did: &Did,
+        cid: &str,
+        range: ByteRange,
+        token: &str,
+    ) -> Result<Vec<u8>> {
+        debug!(did = %did, cid, ?range, "Getting blob range via XRPC");
+
+        let query = GetBlobQuery {
+            did: did.as_str(),
+            cid,
+        };
+
+        self.client
+            .query_bytes_range_authed(GET_BLOB, &query, token, range)
+            .await
+    }
+
+    #[instrument(skip(self, token))]
+    pub(crate) async fn list_blobs(
+        &self,
+        did: &Did,
+        limit: Option<u32>,
+        cursor: Option<&str>,
+        token: &str,
+    ) -> Result<ListBlobsOutput> {
+        debug!(did = %did, "Listing blobs via XRPC");
+
+        let query = ListBlobsQuery {
+            did: did.as_str(),
+            limit,
+            cursor,
+        };
+
+        let response: ListBlobsResponse =
+            self.client.query_authed(LIST_BLOBS, &query, token).await?;
+
+        Ok(ListBlobsOutput {
+            cids: response.cids,
+            cursor: response.cursor,
+        })
+    }
+
+    #[instrument(skip(self, token))]
+    pub(crate) async fn get_record(
+        &self,
+        uri: &AtUri,
+        cid: Option<&str>,
+        token: Option<&str>,
+    ) -> Result<Record> {
+        debug!(uri = %uri, ?cid, "Getting record via XRPC");
+
+        let (collection, rkey) = uri.record_path()?;
         let query = GetRecordQuery {
             repo: uri.repo().as_str(),
-            collection: uri.collection().as_str(),
-            rkey: uri.rkey().as_str(),
-            cid: None,
+            collection: collection.as_str(),
+            rkey: rkey.as_str(),
+            cid,
         };
 
-        let response: GetRecordResponse =
-            self.client.query_authed(GET_RECORD, &query, token).await?;
+        let response: GetRecordResponse = match token {
+            Some(token) => self.client.query_authed(GET_RECORD, &query, token).await?,
+            None => self.client.query(GET_RECORD, &query).await?,
+        };
 
         Ok(Record {
             uri: AtUri::new(&response.uri)?,
@@ -127,63 +470,169 @@ impl XrpcPds {
         })
     }
 
+    #[instrument(skip(self, token))]
+    pub(crate) async fn list_collections(
+        &self,
+        repo: &Did,
+        token: Option<&str>,
+    ) -> Result<Vec<Nsid>> {
+        debug!(repo = %repo, "Describing repo via XRPC");
+
+        let query = DescribeRepoQuery {
+            repo: repo.as_str(),
+        };
+        let response: DescribeRepoResponse = match token {
+            Some(token) => {
+                self.client
+                    .query_authed(DESCRIBE_REPO, &query, token)
+                    .await?
+            }
+            None => self.client.query(DESCRIBE_REPO, &query).await?,
+        };
+
+        let mut collections = response.collections;
+        collections.sort();
+        collections.iter().map(Nsid::new).collect()
+    }
+
     #[instrument(skip(self, token))]
     pub(crate) async fn list_records(
         &self,
         repo: &Did,
         collection: &Nsid,
-        limit: Option<u32>,
-        cursor: Option<&str>,
-        token: &str,
+        options: &ListRecordsOptions,
+        token: Option<&str>,
     ) -> Result<ListRecordsOutput> {
         debug!(repo = %repo, collection = %collection, "Listing records via XRPC");
 
+        // listRecords returns descending rkeys unless `reverse` is set, which
+        // is the opposite of the Session contract.
+        let reverse = match options.get_order() {
+            RecordOrder::Ascending => Some(true),
+            RecordOrder::Descending => None,
+        };
+
+        // A time window starts listing at its first TID and stops at its last.
+        let cursor = options.window_cursor();
         let query = ListRecordsQuery {
             repo: repo.as_str(),
             collection: collection.as_str(),
-            limit,
-            cursor,
-            reverse: None,
+            limit: options.get_limit(),
+            cursor: cursor.as_deref(),
+            reverse,
         };
 
-        let response: ListRecordsResponse = self
+        let response: ListRecordsResponse = match token {
+            Some(token) => {
+                self.client
+                    .query_authed(LIST_RECORDS, &query, token)
+                    .await?
+            }
+            None => self.client.query(LIST_RECORDS, &query).await?,
+        };
+
+        let mut records = Vec::new();
+        let mut cursor = response.cursor;
+        for r in response.records {
+            let uri = AtUri::new(&r.uri)?;
+            let rkey = uri.record_path()?.1.as_str();
+            if options.past_window(rkey) {
+                cursor = None;
+                break;
+            }
+            if !options.in_window(rkey) {
+                continue;
+            }
+            records.push(Record {
+                uri,
+                cid: r.cid,
+                value: RecordValue::new(r.value)?,
+            });
+        }
+
+        Ok(ListRecordsOutput { records, cursor })
+    }
+
+    /// Create or replace a record, returning its URI and the commit rev if
+    /// the PDS reported one.
+    #[instrument(skip(self, value, token))]
+    pub(crate) async fn put_record(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        rkey: &Rkey,
+        value: &RecordValue,
+        swap_cid: Option<&str>,
+        token: &str,
+    ) -> Result<(AtUri, Option<String>)> {
+        debug!(repo = %repo, collection = %collection, rkey = %rkey, "Putting record via XRPC");
+
+        let request = PutRecordRequest {
+            repo: repo.as_str(),
+            collection: collection.as_str(),
+            rkey: rkey.as_str(),
+            record: value.as_value(),
+            swap_record: swap_cid,
+        };
+
+        let response: PutRecordResponse = self
             .client
-            .query_authed(LIST_RECORDS, &query, token)
+            .procedure_authed(PUT_RECORD, &request, token)
             .await?;
 
-        let records = response
-            .records
-            .into_iter()
-            .map(|r| {
-                Ok(Record {
-                    uri: AtUri::new(&r.uri)?,
-                    cid: r.cid,
-                    value: RecordValue::new(r.value)?,
-                })
-            })
-            .collect::<Result<Vec<_>>>()?;
-
-        Ok(ListRecordsOutput {
-            records,
-            cursor: response.cursor,
-        })
+        Ok((
+            AtUri::new(&response.uri)?,
+            response.commit.map(|commit| commit.rev),
+        ))
     }
 
+    /// Delete a record, returning the commit rev if the PDS reported one.
     #[instrument(skip(self, token))]
-    pub(crate) async fn delete_record(&self, uri: &AtUri, token: &str) -> Result<()> {
+    pub(crate) async fn delete_record(&self, uri: &AtUri, token: &str) -> Result<Option<String>> {
         debug!(uri = %uri, "Deleting record via XRPC");
 
+        let (collection, rkey) = uri.record_path()?;
         let request = DeleteRecordRequest {
             repo: uri.repo().as_str(),
-            collection: uri.collection().as_str(),
-            rkey: uri.rkey().as_str(),
+            collection: collection.as_str(),
+            rkey: rkey.as_str(),
             swap_record: None,
             swap_commit: None,
         };
 
-        self.client
-            .procedure_authed_no_response(DELETE_RECORD, &request, token)
-            .await
+        let response: Option<DeleteRecordResponse> = self
+            .client
+            .procedure_authed_optional(DELETE_RECORD, &request, token)
+            .await?;
+        Ok(response
+            .and_then(|response| response.commit)
+            .map(|commit| commit.rev))
+    }
+}
+
+/// Surface the session errors a PDS returns for accounts its operator has
+/// acted on, or that are locked out after repeated failures, as typed
+/// [`AuthError`]s.
+fn account_status_error(error: Error) -> Error {
+    let Error::Protocol(e) = &error else {
+        return error;
+    };
+    if e.is_rate_limited() {
+        return AuthError::RateLimited {
+            retry_after: e.retry_after,
+        }
+        .into();
+    }
+    let message = e.message.clone().unwrap_or_default();
+    match e.error.as_deref() {
+        Some("AccountSuspended") => AuthError::AccountSuspended(message).into(),
+        // Temporary takedowns are reported with the takedown code.
+        Some("AccountTakedown") if message.to_ascii_lowercase().contains("suspend") => {
+            AuthError::AccountSuspended(message).into()
+        }
+        Some("AccountTakedown") => AuthError::AccountTakendown(message).into(),
+        Some("AccountDeactivated") => AuthError::AccountUnavailable { reason: message }.into(),
+        _ => error,
     }
 }
 
@@ -202,8 +651,11 @@ impl Pds for XrpcPds {
             password: credentials.password(),
         };
 
-        let response: CreateSessionResponse =
-            self.client.procedure(CREATE_SESSION, &request).await?;
+        let response: CreateSessionResponse = self
+            .client
+            .procedure(CREATE_SESSION, &request)
+            .await
+            .map_err(account_status_error)?;
 
         let did = Did::new(&response.did)?;
 
@@ -215,6 +667,47 @@ impl Pds for XrpcPds {
         ))
     }
 
+    async fn restore(&self, stored: &StoredSession) -> Result<Self::Session> {
+        let session = XrpcSession::from_persisted_with_pds(
+            self.clone(),
+            stored.did.clone(),
+            stored.access_token.clone(),
+            stored.refresh_token.clone(),
+        )?;
+
+        // Refreshing both checks the session and renews an expired access
+        // token; without a refresh token, the access token must still work.
+        if stored.refresh_token.is_some() {
+            session.refresh().await?;
+        } else {
+            session.validate().await?;
+        }
+        Ok(session)
+    }
+
+    /// Also a reachability check, as it needs no session.
+    #[instrument(skip(self), fields(pds = %self.pds))]
+    async fn describe(&self) -> Result<ServerDescription> {
+        let response: DescribeServerResponse = self.client.query(DESCRIBE_SERVER, &()).await?;
+        let links = response
+            .links
+            .map_or_else(ServerLinks::default, |links| ServerLinks {
+                privacy_policy: links.privacy_policy,
+                terms_of_service: links.terms_of_service,
+            });
+        Ok(ServerDescription {
+            did: response.did,
+            available_user_domains: response.available_user_domains,
+            invite_code_required: response.invite_code_required.unwrap_or(false),
+            phone_verification_required: response.phone_verification_required.unwrap_or(false),
+            links,
+            contact_email: response.contact.and_then(|contact| contact.email),
+        })
+    }
+
+    /// Checks the handle and invite code against [`describe`](Pds::describe)
+    /// first, so a signup the server would reject fails without a
+    /// `createAccount` call.
     async fn create_account(
         &self,
         handle: &str,
@@ -222,8 +715,12 @@ impl Pds for XrpcPds {
         email: Option<&str>,
         invite_code: Option<&str>,
     ) -> Result<CreateAccountOutput> {
+        let handle = Handle::new(handle)?;
+        self.describe()
+            .await?
+            .check_new_account(&handle, invite_code)?;
         let request = CreateAccountRequest {
-            handle,
+            handle: handle.as_str(),
             password,
             email,
             invite_code,
@@ -259,32 +756,81 @@ impl Pds for XrpcPds {
             .await
     }
 
-    fn firehose_from(&self, cursor: Option<i64>) -> Result<Self::Firehose> {
-        let pds = self.pds.clone();
-        let (tx, mut rx) = tokio::sync::mpsc::channel::<Result<muat_core::repo::RepoEvent>>(100);
-
-        tokio::spawn(async move {
-            match XrpcFirehose::from_websocket(&pds, cursor).await {
-                Ok(mut stream) => {
-                    use futures_util::StreamExt;
-                    while let Some(event) = stream.next().await {
-                        if tx.send(event).await.is_err() {
-                            break;
-                        }
-                    }
-                }
-                Err(e) => {
-                    let _ = tx.send(Err(e)).await;
-                }
-            }
-        });
+    #[instrument(skip(self, email), fields(pds = %self.pds))]
+    async fn request_password_reset(&self, email: &str) -> Result<()> {
+        let request = RequestPasswordResetRequest { email };
+        self.client
+            .procedure_no_response(REQUEST_PASSWORD_RESET, &request)
+            .await
+    }
 
-        let stream = async_stream::stream! {
-            while let Some(event) = rx.recv().await {
-                yield event;
-            }
+    #[instrument(skip(self, token, password), fields(pds = %self.pds))]
+    async fn reset_password(&self, token: &str, password: &str) -> Result<()> {
+        let request = ResetPasswordRequest { token, password };
+        self.client
+            .procedure_no_response(RESET_PASSWORD, &request)
+            .await
+    }
+
+    async fn get_record_public(&self, uri: &AtUri) -> Result<Record> {
+        self.get_record(uri, None, None).await
+    }
+
+    async fn list_records_public(
+        &self,
+        repo: &Did,
+        collection: &Nsid,
+        options: &ListRecordsOptions,
+    ) -> Result<ListRecordsOutput> {
+        self.list_records(repo, collection, options, None).await
+    }
+
+    async fn list_collections_public(&self, repo: &Did) -> Result<Vec<Nsid>> {
+        self.list_collections(repo, None).await
+    }
+
+    #[instrument(skip(self, cids), fields(pds = %self.pds, cids = cids.len()))]
+    async fn get_blocks(&self, did: &Did, cids: &[Cid]) -> Result<Car> {
+        // Repeated `cids` parameters, which a struct cannot serialize.
+        let mut query = vec![("did", did.as_str())];
+        query.extend(cids.iter().map(|cid| ("cids", cid.as_str())));
+
+        let response = self.client.query_binary(GET_BLOCKS, &query).await?;
+        Car::from_bytes(response.body)
+    }
+
+    #[instrument(skip(self), fields(pds = %self.pds))]
+    async fn get_repo_since(&self, did: &Did, since: Option<&Tid>) -> Result<Car> {
+        let query = GetRepoQuery {
+            did: did.as_str(),
+            since: since.map(Tid::as_str),
+        };
+
+        let response = self.client.query_binary(GET_REPO, &query).await?;
+        Car::from_bytes(response.body)
+    }
+
+    #[instrument(skip(self), fields(pds = %self.pds))]
+    async fn get_record_proof(&self, uri: &AtUri) -> Result<Car> {
+        let (collection, rkey) = uri.record_path()?;
+        let query = GetSyncRecordQuery {
+            did: uri.repo().as_str(),
+            collection: collection.as_str(),
+            rkey: rkey.as_str(),
         };
 
-        Ok(XrpcFirehose::new(stream))
+        let response = self.client.query_binary(GET_SYNC_RECORD, &query).await?;
+        Car::from_bytes(response.body)
+    }
+
+    fn firehose_from(&self, cursor: Option<i64>) -> Result<Self::Firehose> {
+        let timeout = self.client.timeout_for(SUBSCRIBE_REPOS);
+        XrpcFirehose::connect(
+            &self.pds,
+            cursor,
+            self.firehose_buffer,
+            self.firehose_limits,
+            timeout,
+        )
     }
 }
@@ -0,0 +1,218 @@
+//! Relay client.
+//!
+//! A relay (formerly BGS) aggregates the firehoses of many PDSes into one
+//! `subscribeRepos` stream, so indexers usually read a relay rather than
+//! each PDS. [`RelayClient`] subscribes to one, resuming from a cursor kept
+//! in a [`CursorStore`], and asks it to crawl new PDS hosts.
+
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures_util::Stream;
+use tracing::{debug, instrument};
+
+use muat_core::MemoryCursorStore;
+use muat_core::Result;
+use muat_core::repo::{EventStats, RepoEvent};
+use muat_core::traits::{CursorStore, Pds};
+use muat_core::types::PdsUrl;
+
+use crate::firehose::XrpcFirehose;
+use crate::pds::XrpcPds;
+use crate::xrpc::endpoints::{HostnameRequest, NOTIFY_OF_UPDATE, REQUEST_CRAWL};
+
+/// A client for a relay such as `https://bsky.network`.
+///
+/// Cursors are saved under the relay's host (with its port, if one is
+/// given), so one store can hold cursors for several relays. Without
+/// [`with_cursor_store`](Self::with_cursor_store) they are kept in memory.
+///
+/// ```no_run
+/// # use std::sync::Arc;
+/// # use futures_util::StreamExt;
+/// # use muat_core::PdsUrl;
+/// # use muat_xrpc::RelayClient;
+/// # async fn example(store: Arc<dyn muat_core::CursorStore>) -> muat_core::Result<()> {
+/// let relay = RelayClient::new(PdsUrl::new("https://bsky.network")?).with_cursor_store(store);
+/// let mut firehose = relay.subscribe().await?;
+/// while let Some(event) = firehose.next().await {
+///     let _event = event?;
+///     // ...index the event, then occasionally:
+///     firehose.checkpoint().await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RelayClient {
+    pds: XrpcPds,
+    host: String,
+    cursors: Arc<dyn CursorStore>,
+}
+
+impl RelayClient {
+    /// Create a client for the relay at `relay` using the default transport.
+    #[cfg(any(feature = "reqwest", all(feature = "wasm", target_arch = "wasm32")))]
+    pub fn new(relay: PdsUrl) -> Self {
+        Self::from_pds(XrpcPds::new(relay))
+    }
+
+    /// Create a client that talks to the relay through an [`XrpcPds`], to
+    /// choose its transport, timeouts, firehose buffer and size limits.
+    pub fn from_pds(pds: XrpcPds) -> Self {
+        let host = host_key(pds.url());
+        Self {
+            pds,
+            host,
+            cursors: Arc::new(MemoryCursorStore::new()),
+        }
+    }
+
+    /// Keep cursors in `store`, so a restarted consumer resumes where it
+    /// left off.
+    pub fn with_cursor_store(mut self, store: Arc<dyn CursorStore>) -> Self {
+        self.cursors = store;
+        self
+    }
+
+    /// Returns the relay URL.
+    pub fn url(&self) -> &PdsUrl {
+        self.pds.url()
+    }
+
+    /// Returns the key this relay's cursor is saved under.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// The cursor saved for this relay, if any.
+    pub async fn cursor(&self) -> Result<Option<i64>> {
+        self.cursors.load(&self.host).await
+    }
+
+    /// Save `seq` as this relay's cursor.
+    pub async fn save_cursor(&self, seq: i64) -> Result<()> {
+        self.cursors.save(&self.host, seq).await
+    }
+
+    /// Subscribe to the relay's `subscribeRepos`, resuming after the saved
+    /// cursor, or with new events only if there is none.
+    ///
+    /// The stream does not reconnect; when it ends, subscribe again to
+    /// resume from the last checkpoint.
+    #[instrument(skip(self), fields(host = %self.host))]
+    pub async fn subscribe(&self) -> Result<RelayFirehose> {
+        let cursor = self.cursor().await?;
+        debug!(?cursor, "Subscribing to relay");
+        self.subscribe_from(cursor)
+    }
+
+    /// Subscribe to the relay's `subscribeRepos` after `cursor`, ignoring
+    /// the saved one. Checkpoints still save to this relay's key.
+    pub fn subscribe_from(&self, cursor: Option<i64>) -> Result<RelayFirehose> {
+        Ok(RelayFirehose {
+            inner: self.pds.firehose_from(cursor)?,
+            last_seq: cursor,
+            host: self.host.clone(),
+            cursors: self.cursors.clone(),
+        })
+    }
+
+    /// Ask the relay to crawl the PDS at `hostname` (such as
+    /// `pds.example.com`) and add it to the firehose.
+    #[instrument(skip(self), fields(host = %self.host))]
+    pub async fn request_crawl(&self, hostname: &str) -> Result<()> {
+        self.pds
+            .client()
+            .procedure_no_response(REQUEST_CRAWL, &HostnameRequest { hostname })
+            .await
+    }
+
+    /// Tell the relay that the PDS at `hostname` has new commits. Relays
+    /// that read the PDS firehose may ignore this.
+    #[instrument(skip(self), fields(host = %self.host))]
+    pub async fn notify_of_update(&self, hostname: &str) -> Result<()> {
+        self.pds
+            .client()
+            .procedure_no_response(NOTIFY_OF_UPDATE, &HostnameRequest { hostname })
+            .await
+    }
+}
+
+impl fmt::Debug for RelayClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RelayClient")
+            .field("url", self.pds.url())
+            .field("host", &self.host)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A relay's `subscribeRepos` stream, from [`RelayClient::subscribe`].
+///
+/// Tracks the sequence number of the last event yielded;
+/// [`checkpoint`](Self::checkpoint) saves it as the relay's cursor.
+pub struct RelayFirehose {
+    inner: XrpcFirehose,
+    last_seq: Option<i64>,
+    host: String,
+    cursors: Arc<dyn CursorStore>,
+}
+
+impl RelayFirehose {
+    /// The sequence number of the last event yielded, or the cursor the
+    /// stream started from.
+    pub fn last_seq(&self) -> Option<i64> {
+        self.last_seq
+    }
+
+    /// Save the sequence number of the last event yielded as the relay's
+    /// cursor. Call it once those events are handled; saving after every
+    /// event is safe but slow.
+    pub async fn checkpoint(&self) -> Result<()> {
+        match self.last_seq {
+            Some(seq) => self.cursors.save(&self.host, seq).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Counters for the events, operations, errors and bytes received so far.
+    pub fn stats(&self) -> EventStats {
+        self.inner.stats()
+    }
+}
+
+impl fmt::Debug for RelayFirehose {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RelayFirehose")
+            .field("host", &self.host)
+            .field("last_seq", &self.last_seq)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Stream for RelayFirehose {
+    type Item = Result<RepoEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut self.inner).poll_next(cx);
+        if let Poll::Ready(Some(Ok(event))) = &poll
+            && let Some(seq) = event.seq()
+        {
+            self.last_seq = Some(seq);
+        }
+        poll
+    }
+}
+
+/// The key a relay's cursor is saved under: its host, with the port if
+/// the URL gives one.
+fn host_key(url: &PdsUrl) -> String {
+    let host = url.host().unwrap_or_default();
+    match url.as_url().port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    }
+}
@@ -1,29 +1,84 @@
 //! XRPC-backed session implementation.
 
 use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
 use async_trait::async_trait;
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures_util::{StreamExt, stream};
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::sync::{Semaphore, SemaphorePermit};
 use tracing::{debug, info, instrument};
 
-use muat_core::error::AuthError;
-use muat_core::repo::{ListRecordsOutput, Record, RecordValue};
-use muat_core::traits::Session as SessionTrait;
-use muat_core::types::{AtUri, Did, Nsid, PdsUrl};
-use muat_core::{AccessToken, RefreshToken, Result};
+use muat_core::Error;
+use muat_core::error::{AuthError, InvalidInputError, ProtocolError};
+use muat_core::repo::{
+    BlobRef, BulkReport, ByteRange, ListBlobsOutput, ListRecordsOptions, ListRecordsOutput, Record,
+    RecordValue,
+};
+use muat_core::traits::{
+    AccountStatus, BlobStore, Session as SessionTrait, create_records_pipelined,
+    delete_records_pipelined,
+};
+use muat_core::types::{AtUri, Did, Nsid, PdsUrl, Rkey};
+use muat_core::{AccessToken, CancellationToken, RefreshToken, Result};
 
+use crate::middleware::HeaderProvider;
 use crate::pds::XrpcPds;
+use crate::transport::HttpMethod;
+use crate::xrpc::client::{BinaryResponse, XrpcClient, check_token};
+use crate::xrpc::endpoints::{
+    ACTIVATE_ACCOUNT, CHECK_ACCOUNT_STATUS, CONFIRM_EMAIL, ConfirmEmailRequest, DEACTIVATE_ACCOUNT,
+    DeactivateAccountRequest, GET_SESSION, GetSessionResponse, REQUEST_EMAIL_CONFIRMATION,
+};
 
 /// Session for an XRPC-backed PDS.
+///
+/// Clones share tokens and any concurrency limit configured with
+/// [`with_max_in_flight`](Self::with_max_in_flight).
 #[derive(Clone)]
 pub struct XrpcSession {
     inner: Arc<SessionInner>,
+    limiter: Option<Arc<Semaphore>>,
+    pds_impl: Arc<XrpcPds>,
+    /// Client for [`xrpc_query`](Self::xrpc_query) and
+    /// [`xrpc_procedure`](Self::xrpc_procedure), which adds the service proxy
+    /// and labeler headers if they are set.
+    client: XrpcClient,
+    proxy: Option<Arc<str>>,
+    /// The `atproto-accept-labelers` header value.
+    labelers: Option<Arc<str>>,
+    /// The latest commit rev of this session's writes, when tracked with
+    /// [`with_read_after_write`](Self::with_read_after_write).
+    repo_rev: Option<Arc<RepoRev>>,
+}
+
+/// Header asking a service to answer as of at least this repo rev.
+const ACCEPT_REPO_REV: &str = "atproto-accept-repo-rev";
+
+/// The newest commit rev seen in write responses.
+#[derive(Debug, Default)]
+struct RepoRev(RwLock<Option<String>>);
+
+impl RepoRev {
+    fn get(&self) -> Option<String> {
+        self.0.read().ok()?.clone()
+    }
+
+    /// Keep `rev` if it is newer; revs are TIDs, which sort by time.
+    fn advance(&self, rev: String) {
+        if let Ok(mut latest) = self.0.write()
+            && latest.as_ref().is_none_or(|latest| rev > *latest)
+        {
+            *latest = Some(rev);
+        }
+    }
 }
 
 #[derive(Debug)]
 struct SessionInner {
     did: Did,
     pds: PdsUrl,
-    pds_impl: XrpcPds,
     tokens: RwLock<SessionTokens>,
 }
 
@@ -41,26 +96,293 @@ impl XrpcSession {
         refresh_token: Option<RefreshToken>,
     ) -> Self {
         Self {
+            client: pds_impl.client().clone(),
+            proxy: None,
+            labelers: None,
+            repo_rev: None,
             inner: Arc::new(SessionInner {
                 did,
                 pds: pds_impl.url().clone(),
-                pds_impl,
                 tokens: RwLock::new(SessionTokens {
                     access_token,
                     refresh_token,
                 }),
             }),
+            limiter: None,
+            pds_impl: Arc::new(pds_impl),
+        }
+    }
+
+    /// Cap the number of concurrent requests made through this session.
+    ///
+    /// Requests beyond the limit wait for an in-flight request to finish.
+    /// Clones made after this call share the same limit. By default a
+    /// session is unlimited. A `max` of zero is treated as one.
+    pub fn with_max_in_flight(mut self, max: usize) -> Self {
+        self.limiter = Some(Arc::new(Semaphore::new(max.max(1))));
+        self
+    }
+
+    /// Returns a handle to this session whose
+    /// [`xrpc_query`](Self::xrpc_query) and
+    /// [`xrpc_procedure`](Self::xrpc_procedure) calls ask the PDS to forward
+    /// them to another service, with the `atproto-proxy` header.
+    ///
+    /// `service` is a DID and a service id from its DID document, such as
+    /// `did:web:api.bsky.app#bsky_appview`. Record and blob operations still
+    /// go to the PDS itself. The handle shares this session's tokens and
+    /// concurrency limit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `service` is not `<did>#<service id>`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use muat_core::Nsid;
+    /// # async fn example(session: muat_xrpc::XrpcSession) -> Result<(), muat_core::Error> {
+    /// let appview = session.with_service_proxy("did:web:api.bsky.app#bsky_appview")?;
+    /// let method = Nsid::new("app.bsky.actor.getProfile")?;
+    /// let profile: serde_json::Value = appview.xrpc_query(&method, &[("actor", "alice.test")]).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_service_proxy(&self, service: &str) -> Result<Self> {
+        let valid = service.split_once('#').is_some_and(|(did, id)| {
+            Did::new(did).is_ok() && !id.is_empty() && !id.contains(char::is_whitespace)
+        });
+        if !valid {
+            return Err(Error::InvalidInput(InvalidInputError::Other {
+                message: format!(
+                    "service proxy must be `<did>#<service id>`, got {:?}",
+                    service
+                ),
+            }));
+        }
+
+        let mut handle = self.clone();
+        handle.proxy = Some(Arc::from(service));
+        handle.client = handle.xrpc_client();
+        Ok(handle)
+    }
+
+    /// Returns a handle to this session whose
+    /// [`xrpc_query`](Self::xrpc_query) and
+    /// [`xrpc_procedure`](Self::xrpc_procedure) calls ask for labels from
+    /// these labelers, with the `atproto-accept-labelers` header.
+    ///
+    /// Each entry is a labeler DID, optionally followed by `;redact` to ask
+    /// the app view to remove content that labeler marks `!takedown` rather
+    /// than only labelling it. Labels come back in the `labels` fields of
+    /// app view responses. An empty list sends no header, leaving the choice
+    /// to the app view. The handle shares this session's tokens, concurrency
+    /// limit and service proxy.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an entry is not `<did>` or `<did>;redact`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # async fn example(session: muat_xrpc::XrpcSession) -> Result<(), muat_core::Error> {
+    /// let session = session.with_accept_labelers(&[
+    ///     "did:plc:ar7c4by46qjdydhdevvrndac;redact",
+    ///     "did:plc:newitj5jo3uel7o4mnf3vj2o",
+    /// ])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_accept_labelers(&self, labelers: &[&str]) -> Result<Self> {
+        let mut entries = Vec::with_capacity(labelers.len());
+        for labeler in labelers {
+            let (did, params) = labeler.split_once(';').unwrap_or((labeler, ""));
+            let redact = match params.trim() {
+                "" => false,
+                "redact" => true,
+                _ => return Err(invalid_labeler(labeler)),
+            };
+            let did = Did::new(did.trim()).map_err(|_| invalid_labeler(labeler))?;
+            entries.push(if redact {
+                format!("{};redact", did)
+            } else {
+                did.to_string()
+            });
+        }
+
+        let mut handle = self.clone();
+        handle.labelers = (!entries.is_empty()).then(|| Arc::from(entries.join(", ")));
+        handle.client = handle.xrpc_client();
+        Ok(handle)
+    }
+
+    /// Returns a handle to this session whose requests all fail with
+    /// [`TransportError::Timeout`](muat_core::error::TransportError::Timeout)
+    /// after `timeout`, overriding the PDS's default and per-endpoint
+    /// timeouts. The handle shares this session's tokens, concurrency limit
+    /// and service proxy.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use muat_core::AtUri;
+    /// # use muat_core::traits::Session;
+    /// # async fn example(session: muat_xrpc::XrpcSession, uri: AtUri) -> Result<(), muat_core::Error> {
+    /// let record = session
+    ///     .with_timeout(Duration::from_secs(2))
+    ///     .get_record(&uri)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_timeout(&self, timeout: Duration) -> Self {
+        let mut handle = self.clone();
+        handle.pds_impl = Arc::new(XrpcPds::clone(&self.pds_impl).with_timeout_override(timeout));
+        handle.client = handle.client.with_timeout_override(timeout);
+        handle
+    }
+
+    /// Returns a handle to this session that remembers the commit rev of
+    /// each write it makes and sends it on later reads, with the
+    /// `atproto-accept-repo-rev` header, so a service that has not caught up
+    /// with those writes can tell.
+    ///
+    /// Every `GET` through the handle carries the header once a write has
+    /// reported a rev, including record reads and
+    /// [`xrpc_query`](Self::xrpc_query) calls proxied to an app view. Nothing
+    /// is retried: to wait until a write is visible, poll and compare with
+    /// [`repo_rev`](Self::repo_rev). The handle shares this session's
+    /// tokens, concurrency limit, service proxy and labelers; clones made
+    /// from it share the rev.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use muat_core::{Nsid, RecordValue};
+    /// # use muat_core::traits::Session;
+    /// # async fn example(session: muat_xrpc::XrpcSession, value: RecordValue) -> Result<(), muat_core::Error> {
+    /// let session = session.with_read_after_write();
+    /// let collection = Nsid::new("app.bsky.feed.post")?;
+    /// let uri = session.create_record(&collection, &value).await?;
+    /// println!("written at rev {:?}", session.repo_rev());
+    /// let record = session.get_record(&uri).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_read_after_write(&self) -> Self {
+        if self.repo_rev.is_some() {
+            return self.clone();
+        }
+        let repo_rev = Arc::new(RepoRev::default());
+        let header_rev = repo_rev.clone();
+        let mut handle = self.clone();
+        handle.pds_impl = Arc::new(XrpcPds::clone(&self.pds_impl).with_interceptor(
+            move |request| {
+                if request.method == HttpMethod::Get
+                    && let Some(rev) = header_rev.get()
+                {
+                    request.headers.push((ACCEPT_REPO_REV.to_string(), rev));
+                }
+            },
+        ));
+        handle.repo_rev = Some(repo_rev);
+        handle.client = handle.xrpc_client();
+        handle
+    }
+
+    /// Returns the commit rev of the latest write made through a
+    /// [`with_read_after_write`](Self::with_read_after_write) handle, if the
+    /// PDS has reported one.
+    pub fn repo_rev(&self) -> Option<String> {
+        self.repo_rev.as_ref()?.get()
+    }
+
+    /// Remember a write's commit rev, if revs are tracked.
+    fn wrote(&self, rev: Option<String>) {
+        if let (Some(repo_rev), Some(rev)) = (&self.repo_rev, rev) {
+            repo_rev.advance(rev);
+        }
+    }
+
+    /// Returns the service that XRPC calls are proxied to, if any.
+    pub fn service_proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    /// Returns the `atproto-accept-labelers` header XRPC calls carry, if any.
+    pub fn accept_labelers(&self) -> Option<&str> {
+        self.labelers.as_deref()
+    }
+
+    /// The client for raw XRPC calls: the PDS client plus this handle's
+    /// service proxy and labeler headers.
+    fn xrpc_client(&self) -> XrpcClient {
+        let client = self.pds_impl.client().clone();
+        if self.proxy.is_none() && self.labelers.is_none() {
+            return client;
+        }
+        let proxy = self.proxy.clone();
+        let labelers = self.labelers.clone();
+        client.with_middleware(Arc::new(HeaderProvider(move || {
+            let mut headers = Vec::new();
+            if let Some(proxy) = &proxy {
+                headers.push(("atproto-proxy".to_string(), proxy.to_string()));
+            }
+            if let Some(labelers) = &labelers {
+                headers.push(("atproto-accept-labelers".to_string(), labelers.to_string()));
+            }
+            headers
+        })))
+    }
+
+    /// Returns the number of request slots currently available, if limited.
+    pub fn available_permits(&self) -> Option<usize> {
+        self.limiter.as_ref().map(|s| s.available_permits())
+    }
+
+    /// Wait for a request slot when a concurrency limit is configured.
+    async fn acquire_permit(&self) -> Option<SemaphorePermit<'_>> {
+        match &self.limiter {
+            // The semaphore is never closed, so acquire cannot fail.
+            Some(semaphore) => semaphore.acquire().await.ok(),
+            None => None,
         }
     }
 
     /// Restore a session from persisted tokens.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AuthError::MalformedToken`] if a token is empty or holds
+    /// characters that cannot be sent in an HTTP header, as a corrupted
+    /// session file might.
+    #[cfg(any(feature = "reqwest", all(feature = "wasm", target_arch = "wasm32")))]
     pub fn from_persisted(
         pds: PdsUrl,
         did: Did,
         access_token: AccessToken,
         refresh_token: Option<RefreshToken>,
-    ) -> Self {
-        Self::new(XrpcPds::new(pds), did, access_token, refresh_token)
+    ) -> Result<Self> {
+        Self::from_persisted_with_pds(XrpcPds::new(pds), did, access_token, refresh_token)
+    }
+
+    /// Restore a session from persisted tokens against an existing PDS.
+    ///
+    /// Use this to resume a session over a custom transport. Tokens are
+    /// checked as for [`from_persisted`](Self::from_persisted).
+    pub fn from_persisted_with_pds(
+        pds: XrpcPds,
+        did: Did,
+        access_token: AccessToken,
+        refresh_token: Option<RefreshToken>,
+    ) -> Result<Self> {
+        check_token("access", access_token.as_str())?;
+        if let Some(refresh_token) = &refresh_token {
+            check_token("refresh", refresh_token.as_str())?;
+        }
+        Ok(Self::new(pds, did, access_token, refresh_token))
     }
 
     /// Refresh the session tokens.
@@ -78,7 +400,8 @@ impl XrpcSession {
 
         let refresh_token = refresh_token.ok_or(AuthError::RefreshTokenInvalid)?;
 
-        let response = self.inner.pds_impl.refresh_session(&refresh_token).await?;
+        let _permit = self.acquire_permit().await;
+        let response = self.pds_impl.refresh_session(&refresh_token).await?;
 
         {
             let mut tokens = self.inner.tokens.write().unwrap();
@@ -90,6 +413,153 @@ impl XrpcSession {
         Ok(())
     }
 
+    /// Check that the PDS accepts this session's access token.
+    ///
+    /// Calls `com.atproto.server.getSession` and fails if the token is
+    /// rejected or belongs to a different account.
+    #[instrument(skip(self), fields(did = %self.inner.did))]
+    pub async fn validate(&self) -> Result<()> {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        let response: GetSessionResponse = self
+            .pds_impl
+            .client()
+            .query_authed(GET_SESSION, &(), &token)
+            .await?;
+
+        if response.did != self.inner.did.as_str() {
+            return Err(Error::Auth(AuthError::InvalidCredentials(
+                "access token belongs to a different account".to_string(),
+            )));
+        }
+        Ok(())
+    }
+
+    /// Make an authenticated XRPC query with this session.
+    ///
+    /// Use this for endpoints without a typed wrapper, such as lexicons
+    /// served by an app view through the PDS. The current access token is
+    /// attached and the session's in-flight limit applies.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use muat_core::Nsid;
+    /// # async fn example(session: muat_xrpc::XrpcSession) -> Result<(), muat_core::Error> {
+    /// let method = Nsid::new("app.bsky.actor.getProfile")?;
+    /// let profile: serde_json::Value = session.xrpc_query(&method, &[("actor", "alice.test")]).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(skip(self, params), fields(did = %self.inner.did, %method))]
+    pub async fn xrpc_query<Q, R>(&self, method: &Nsid, params: &Q) -> Result<R>
+    where
+        Q: Serialize + std::fmt::Debug + Sync,
+        R: DeserializeOwned,
+    {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        self.client
+            .query_authed(method.as_str(), params, &token)
+            .await
+    }
+
+    /// Make an authenticated XRPC procedure with this session.
+    #[instrument(skip(self, body), fields(did = %self.inner.did, %method))]
+    pub async fn xrpc_procedure<B, R>(&self, method: &Nsid, body: &B) -> Result<R>
+    where
+        B: Serialize + std::fmt::Debug + Sync,
+        R: DeserializeOwned,
+    {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        self.client
+            .procedure_authed(method.as_str(), body, &token)
+            .await
+    }
+
+    /// Make an authenticated XRPC procedure that returns no content.
+    #[instrument(skip(self, body), fields(did = %self.inner.did, %method))]
+    pub async fn xrpc_procedure_no_response<B>(&self, method: &Nsid, body: &B) -> Result<()>
+    where
+        B: Serialize + std::fmt::Debug + Sync,
+    {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        self.client
+            .procedure_authed_no_response(method.as_str(), body, &token)
+            .await
+    }
+
+    /// Make an authenticated XRPC query whose response is not JSON, such as
+    /// `com.atproto.sync.getRepo`, which returns a CAR file.
+    ///
+    /// The body comes back unchanged along with its content type.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use muat_core::{Nsid, Session};
+    /// # async fn example(session: muat_xrpc::XrpcSession) -> Result<(), muat_core::Error> {
+    /// let method = Nsid::new("com.atproto.sync.getRepo")?;
+    /// let car = session.query_binary(&method, &[("did", session.did().as_str())]).await?;
+    /// assert_eq!(car.mime_type().as_deref(), Some("application/vnd.ipld.car"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(skip(self, params), fields(did = %self.inner.did, %method))]
+    pub async fn query_binary<Q>(&self, method: &Nsid, params: &Q) -> Result<BinaryResponse>
+    where
+        Q: Serialize + std::fmt::Debug + Sync,
+    {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        self.client
+            .query_binary_authed(method.as_str(), params, &token)
+            .await
+    }
+
+    /// Make an authenticated XRPC procedure with a raw body of
+    /// `content_type`, such as `com.atproto.repo.importRepo`.
+    ///
+    /// The response comes back unchanged along with its content type; use
+    /// [`BinaryResponse::json`] for procedures that answer in JSON.
+    #[instrument(skip(self, body), fields(did = %self.inner.did, %method, len = body.len()))]
+    pub async fn procedure_binary(
+        &self,
+        method: &Nsid,
+        body: Vec<u8>,
+        content_type: &str,
+    ) -> Result<BinaryResponse> {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        self.client
+            .procedure_binary_authed(method.as_str(), body, content_type, &token)
+            .await
+    }
+
+    async fn apply_creates(&self, collection: &Nsid, values: &[RecordValue]) -> Result<Vec<AtUri>> {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        let (uris, rev) = self
+            .pds_impl
+            .apply_creates(&self.inner.did, collection, values, &token)
+            .await?;
+        self.wrote(rev);
+        Ok(uris)
+    }
+
+    async fn apply_deletes(&self, uris: &[AtUri]) -> Result<()> {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        let rev = self
+            .pds_impl
+            .apply_deletes(&self.inner.did, uris, &token)
+            .await?;
+        self.wrote(rev);
+        Ok(())
+    }
+
     fn access_token_string(&self) -> Result<String> {
         let tokens = self.inner.tokens.read().unwrap();
         Ok(tokens.access_token.as_str().to_string())
@@ -121,43 +591,259 @@ impl SessionTrait for XrpcSession {
     }
 
     #[instrument(skip(self), fields(did = %self.inner.did, %collection))]
-    async fn list_records(
+    async fn list_records_with(
         &self,
         repo: &Did,
         collection: &Nsid,
-        limit: Option<u32>,
-        cursor: Option<&str>,
+        options: &ListRecordsOptions,
     ) -> Result<ListRecordsOutput> {
         debug!("Listing records");
+        let _permit = self.acquire_permit().await;
         let token = self.access_token_string()?;
-        self.inner
-            .pds_impl
-            .list_records(repo, collection, limit, cursor, &token)
+        self.pds_impl
+            .list_records(repo, collection, options, Some(&token))
             .await
     }
 
+    #[instrument(skip(self), fields(did = %self.inner.did, %repo))]
+    async fn list_collections(&self, repo: &Did) -> Result<Vec<Nsid>> {
+        debug!("Listing collections");
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        self.pds_impl.list_collections(repo, Some(&token)).await
+    }
+
     #[instrument(skip(self), fields(did = %self.inner.did, %uri))]
     async fn get_record(&self, uri: &AtUri) -> Result<Record> {
         debug!("Getting record");
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        self.pds_impl.get_record(uri, None, Some(&token)).await
+    }
+
+    /// The PDS only serves the record while it still has `cid`.
+    #[instrument(skip(self), fields(did = %self.inner.did, %uri))]
+    async fn get_record_at(&self, uri: &AtUri, cid: &str) -> Result<Record> {
+        debug!("Getting record version");
+        let _permit = self.acquire_permit().await;
         let token = self.access_token_string()?;
-        self.inner.pds_impl.get_record(uri, &token).await
+        self.pds_impl.get_record(uri, Some(cid), Some(&token)).await
     }
 
     #[instrument(skip(self, value), fields(did = %self.inner.did, %collection))]
     async fn create_record(&self, collection: &Nsid, value: &RecordValue) -> Result<AtUri> {
         debug!("Creating record");
+        let _permit = self.acquire_permit().await;
         let token = self.access_token_string()?;
-        self.inner
+        let (uri, rev) = self
             .pds_impl
             .create_record(&self.inner.did, collection, value, None, &token)
-            .await
+            .await?;
+        self.wrote(rev);
+        Ok(uri)
+    }
+
+    /// Creates records with `com.atproto.repo.applyWrites`, up to 200 per
+    /// call and `concurrency` calls in flight.
+    ///
+    /// Each call is atomic, so when one fails every record in it reports
+    /// that error and none of them were written. Falls back to pipelined
+    /// createRecord calls if the PDS does not implement applyWrites.
+    /// Cancellation takes effect between calls.
+    #[instrument(skip(self, values, cancel), fields(did = %self.inner.did, %collection, count = values.len()))]
+    async fn create_records_bulk(
+        &self,
+        collection: &Nsid,
+        values: Vec<RecordValue>,
+        concurrency: usize,
+        cancel: Option<&CancellationToken>,
+    ) -> BulkReport<RecordValue, AtUri> {
+        debug!("Creating records in bulk");
+        let cancelled = || cancel.is_some_and(CancellationToken::is_cancelled);
+        if cancelled() {
+            return BulkReport::from_results(values, []);
+        }
+        let mut chunks = values.chunks(APPLY_WRITES_MAX);
+        let Some(first) = chunks.next() else {
+            return BulkReport::new();
+        };
+
+        // The first call doubles as a capability probe; nothing is written
+        // when the method is missing.
+        let first_result = self.apply_creates(collection, first).await;
+        if let Err(e) = &first_result
+            && is_unimplemented(e)
+        {
+            debug!("applyWrites not implemented, falling back to createRecord");
+            return create_records_pipelined(self, collection, values, concurrency, cancel).await;
+        }
+
+        // Calls start in order, so once one sees the token cancelled every
+        // later one does too.
+        let requests: Vec<_> = chunks
+            .map(|chunk| async move {
+                if cancelled() {
+                    return None;
+                }
+                Some((chunk.len(), self.apply_creates(collection, chunk).await))
+            })
+            .collect();
+        let rest: Vec<_> = stream::iter(requests)
+            .buffered(concurrency.max(1))
+            .collect()
+            .await;
+
+        let results: Vec<_> = std::iter::once((first.len(), first_result))
+            .chain(rest.into_iter().map_while(|call| call))
+            .flat_map(|(len, result)| match result {
+                Ok(uris) => uris.into_iter().map(Ok).collect::<Vec<_>>(),
+                Err(e) => vec![Err(e); len],
+            })
+            .collect();
+        BulkReport::from_results(values, results)
+    }
+
+    #[instrument(skip(self, value), fields(did = %self.inner.did, %collection, %rkey))]
+    async fn put_record(
+        &self,
+        collection: &Nsid,
+        rkey: &Rkey,
+        value: &RecordValue,
+        swap_cid: Option<&str>,
+    ) -> Result<AtUri> {
+        debug!("Putting record");
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        let (uri, rev) = self
+            .pds_impl
+            .put_record(&self.inner.did, collection, rkey, value, swap_cid, &token)
+            .await?;
+        self.wrote(rev);
+        Ok(uri)
     }
 
     #[instrument(skip(self), fields(did = %self.inner.did, %uri))]
     async fn delete_record(&self, uri: &AtUri) -> Result<()> {
         debug!("Deleting record");
+        let _permit = self.acquire_permit().await;
         let token = self.access_token_string()?;
-        self.inner.pds_impl.delete_record(uri, &token).await
+        let rev = self.pds_impl.delete_record(uri, &token).await?;
+        self.wrote(rev);
+        Ok(())
+    }
+
+    /// Deletes records of the session's repo with
+    /// `com.atproto.repo.applyWrites`, in batches as for
+    /// [`create_records_bulk`](Self::create_records_bulk), with the same
+    /// fallback. Other repos' records are deleted one at a time.
+    #[instrument(skip(self, uris, cancel), fields(did = %self.inner.did, count = uris.len()))]
+    async fn delete_records_bulk(
+        &self,
+        uris: Vec<AtUri>,
+        concurrency: usize,
+        cancel: Option<&CancellationToken>,
+    ) -> BulkReport<AtUri, AtUri> {
+        debug!("Deleting records in bulk");
+        let cancelled = || cancel.is_some_and(CancellationToken::is_cancelled);
+        if cancelled() {
+            return BulkReport::from_results(uris, []);
+        }
+        let batchable = uris
+            .iter()
+            .all(|uri| uri.repo() == &self.inner.did && uri.record_path().is_ok());
+        let mut chunks = uris.chunks(APPLY_WRITES_MAX);
+        let (true, Some(first)) = (batchable, chunks.next()) else {
+            return delete_records_pipelined(self, uris, concurrency, cancel).await;
+        };
+
+        let first_result = self.apply_deletes(first).await;
+        if let Err(e) = &first_result
+            && is_unimplemented(e)
+        {
+            debug!("applyWrites not implemented, falling back to deleteRecord");
+            return delete_records_pipelined(self, uris, concurrency, cancel).await;
+        }
+
+        let requests: Vec<_> = chunks
+            .map(|chunk| async move {
+                if cancelled() {
+                    return None;
+                }
+                Some((chunk, self.apply_deletes(chunk).await))
+            })
+            .collect();
+        let rest: Vec<_> = stream::iter(requests)
+            .buffered(concurrency.max(1))
+            .collect()
+            .await;
+
+        let results: Vec<_> = std::iter::once((first, first_result))
+            .chain(rest.into_iter().map_while(|call| call))
+            .flat_map(|(chunk, result)| match result {
+                Ok(()) => chunk.iter().cloned().map(Ok).collect::<Vec<_>>(),
+                Err(e) => vec![Err(e); chunk.len()],
+            })
+            .collect();
+        BulkReport::from_results(uris, results)
+    }
+
+    #[instrument(skip(self), fields(did = %self.inner.did))]
+    async fn request_email_confirmation(&self) -> Result<()> {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        self.pds_impl
+            .client()
+            .procedure_authed_empty(REQUEST_EMAIL_CONFIRMATION, &token)
+            .await
+    }
+
+    #[instrument(skip(self, email, token), fields(did = %self.inner.did))]
+    async fn confirm_email(&self, email: &str, token: &str) -> Result<()> {
+        let _permit = self.acquire_permit().await;
+        let access_token = self.access_token_string()?;
+        let request = ConfirmEmailRequest { email, token };
+        self.pds_impl
+            .client()
+            .procedure_authed_no_response(CONFIRM_EMAIL, &request, &access_token)
+            .await
+    }
+
+    #[instrument(skip(self), fields(did = %self.inner.did))]
+    async fn check_account_status(&self) -> Result<AccountStatus> {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        self.pds_impl
+            .client()
+            .query_authed(CHECK_ACCOUNT_STATUS, &(), &token)
+            .await
+    }
+
+    #[instrument(skip(self), fields(did = %self.inner.did))]
+    async fn activate_account(&self) -> Result<()> {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        self.pds_impl
+            .client()
+            .procedure_authed_empty(ACTIVATE_ACCOUNT, &token)
+            .await
+    }
+
+    #[instrument(skip(self), fields(did = %self.inner.did))]
+    async fn deactivate_account(&self, delete_after: Option<DateTime<Utc>>) -> Result<()> {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        let request = DeactivateAccountRequest {
+            delete_after: delete_after
+                .map(|time| time.to_rfc3339_opts(SecondsFormat::Millis, true)),
+        };
+        self.pds_impl
+            .client()
+            .procedure_authed_no_response(DEACTIVATE_ACCOUNT, &request, &token)
+            .await
+    }
+
+    fn blobs(&self) -> &dyn BlobStore {
+        self
     }
 }
 
@@ -178,12 +864,89 @@ impl XrpcSession {
     }
 }
 
+/// Blob storage on the session's PDS, scoped to the session repository.
+#[async_trait]
+impl BlobStore for XrpcSession {
+    #[instrument(skip(self, data), fields(did = %self.inner.did, size = data.len()))]
+    async fn put_blob(&self, data: Vec<u8>, mime_type: &str) -> Result<BlobRef> {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        self.pds_impl.upload_blob(data, mime_type, &token).await
+    }
+
+    #[instrument(skip(self), fields(did = %self.inner.did))]
+    async fn get_blob(&self, cid: &str) -> Result<Vec<u8>> {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        self.pds_impl.get_blob(&self.inner.did, cid, &token).await
+    }
+
+    /// Sends an HTTP `Range` header, so only the requested bytes travel.
+    #[instrument(skip(self), fields(did = %self.inner.did))]
+    async fn get_blob_range(&self, cid: &str, range: ByteRange) -> Result<Vec<u8>> {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        self.pds_impl
+            .get_blob_range(&self.inner.did, cid, range, &token)
+            .await
+    }
+
+    /// Not supported: a PDS deletes blobs itself once no record references
+    /// them.
+    async fn delete_blob(&self, _cid: &str) -> Result<()> {
+        Err(ProtocolError::new(
+            501,
+            Some("MethodNotImplemented".to_string()),
+            Some("blobs are removed by the PDS when no record references them".to_string()),
+        )
+        .into())
+    }
+
+    #[instrument(skip(self), fields(did = %self.inner.did))]
+    async fn list_blobs(
+        &self,
+        limit: Option<u32>,
+        cursor: Option<&str>,
+    ) -> Result<ListBlobsOutput> {
+        let _permit = self.acquire_permit().await;
+        let token = self.access_token_string()?;
+        self.pds_impl
+            .list_blobs(&self.inner.did, limit, cursor, &token)
+            .await
+    }
+}
+
 impl std::fmt::Debug for XrpcSession {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("XrpcSession")
             .field("did", &self.inner.did)
             .field("pds", &self.inner.pds)
             .field("tokens", &"[REDACTED]")
+            .field("service_proxy", &self.proxy)
+            .field("accept_labelers", &self.labelers)
+            .field("repo_rev", &self.repo_rev())
+            .field("available_permits", &self.available_permits())
             .finish()
     }
 }
+
+fn invalid_labeler(labeler: &str) -> Error {
+    Error::InvalidInput(InvalidInputError::Other {
+        message: format!(
+            "accept labeler must be `<did>` or `<did>;redact`, got {:?}",
+            labeler
+        ),
+    })
+}
+
+/// Maximum writes per applyWrites call accepted by the reference PDS.
+const APPLY_WRITES_MAX: usize = 200;
+
+fn is_unimplemented(err: &Error) -> bool {
+    match err {
+        Error::Protocol(e) => {
+            e.status == 404 || e.status == 501 || e.error.as_deref() == Some("MethodNotImplemented")
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,625 @@
+//! Pluggable HTTP transport for XRPC requests.
+//!
+//! The XRPC client does not talk to the network directly. Every
+//! request is handed to an [`HttpTransport`] as a plain [`HttpRequest`] and the
+//! raw [`HttpResponse`] is interpreted by the XRPC layer. This lets callers
+//! inject an alternative HTTP stack, a middleware chain, or a test double.
+//!
+//! Built-in implementations:
+//!
+//! - `ReqwestTransport`, behind the `reqwest` feature (enabled by default).
+//! - `FetchTransport`, behind the `wasm` feature on `wasm32` targets, which
+//!   uses the browser (or worker) `fetch` API.
+//! - `UnixSocketTransport`, behind the `unix-socket` feature on unix targets,
+//!   which speaks HTTP/1.1 over a Unix domain socket.
+
+use std::fmt;
+#[cfg(any(feature = "reqwest", all(feature = "wasm", target_arch = "wasm32")))]
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use muat_core::Result;
+
+/// HTTP method used by an XRPC request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpMethod {
+    /// XRPC query.
+    Get,
+    /// XRPC procedure.
+    Post,
+}
+
+impl HttpMethod {
+    /// Returns the method name as used on the wire.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HttpMethod::Get => "GET",
+            HttpMethod::Post => "POST",
+        }
+    }
+}
+
+impl fmt::Display for HttpMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An outgoing HTTP request.
+///
+/// The URL is fully formed, including any query string.
+#[derive(Clone)]
+pub struct HttpRequest {
+    /// The HTTP method.
+    pub method: HttpMethod,
+    /// The absolute request URL.
+    pub url: String,
+    /// Request headers as name/value pairs.
+    pub headers: Vec<(String, String)>,
+    /// Optional request body.
+    pub body: Option<Vec<u8>>,
+    /// How long the whole exchange may take before failing with
+    /// [`TransportError::Timeout`](muat_core::error::TransportError::Timeout).
+    /// `None` waits indefinitely.
+    pub timeout: Option<Duration>,
+}
+
+impl HttpRequest {
+    /// Create a new request with no headers and no body.
+    pub fn new(method: HttpMethod, url: impl Into<String>) -> Self {
+        Self {
+            method,
+            url: url.into(),
+            headers: Vec::new(),
+            body: None,
+            timeout: None,
+        }
+    }
+
+    /// Returns the first value of the named header (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+// Headers may carry bearer tokens, so only header names are shown.
+impl fmt::Debug for HttpRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.headers.iter().map(|(n, _)| n.as_str()).collect();
+        f.debug_struct("HttpRequest")
+            .field("method", &self.method)
+            .field("url", &self.url)
+            .field("headers", &names)
+            .field("body_len", &self.body.as_ref().map(Vec::len))
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+/// An HTTP response returned by a transport.
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    /// HTTP status code.
+    pub status: u16,
+    /// Response headers as name/value pairs.
+    pub headers: Vec<(String, String)>,
+    /// Raw response body.
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Create a new response with no headers.
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// Returns true if the status code is in the 2xx range.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Returns the first value of the named header (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+/// A transport capable of sending HTTP requests.
+///
+/// Implementations must map their own failures onto
+/// [`TransportError`](muat_core::error::TransportError). Non-2xx responses are
+/// not errors at this layer; they are returned as-is and interpreted by the
+/// XRPC client. Implementations should honour [`HttpRequest::timeout`].
+#[async_trait]
+pub trait HttpTransport: Send + Sync + fmt::Debug {
+    /// Send a request and return the response.
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse>;
+}
+
+#[cfg(feature = "reqwest")]
+pub use self::reqwest_transport::ReqwestTransport;
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub use self::fetch_transport::FetchTransport;
+
+#[cfg(all(unix, feature = "unix-socket"))]
+pub use self::unix_transport::UnixSocketTransport;
+
+/// The transport used by `XrpcPds::new`.
+///
+/// `unix://` URLs get a [`UnixSocketTransport`] when available. Otherwise
+/// they get a transport that always fails, so requests never fall through to
+/// TCP on `localhost`.
+#[cfg(any(feature = "reqwest", all(feature = "wasm", target_arch = "wasm32")))]
+pub(crate) fn default_transport(pds: &muat_core::types::PdsUrl) -> Arc<dyn HttpTransport> {
+    if let Some(path) = pds.socket_path() {
+        #[cfg(all(unix, feature = "unix-socket"))]
+        return Arc::new(UnixSocketTransport::new(path));
+
+        #[cfg(not(all(unix, feature = "unix-socket")))]
+        return Arc::new(Unsupported(path));
+    }
+
+    #[cfg(feature = "reqwest")]
+    return Arc::new(ReqwestTransport::new());
+
+    #[cfg(not(feature = "reqwest"))]
+    Arc::new(FetchTransport::new())
+}
+
+/// Stands in for a Unix socket transport that was not compiled in.
+#[cfg(all(
+    not(all(unix, feature = "unix-socket")),
+    any(feature = "reqwest", all(feature = "wasm", target_arch = "wasm32"))
+))]
+#[derive(Debug)]
+struct Unsupported(std::path::PathBuf);
+
+#[cfg(all(
+    not(all(unix, feature = "unix-socket")),
+    any(feature = "reqwest", all(feature = "wasm", target_arch = "wasm32"))
+))]
+#[async_trait]
+impl HttpTransport for Unsupported {
+    async fn send(&self, _request: HttpRequest) -> Result<HttpResponse> {
+        Err(muat_core::error::Error::Transport(
+            muat_core::error::TransportError::Connection {
+                message: format!(
+                    "cannot connect to {}: Unix socket support requires the `unix-socket` feature on a unix target",
+                    self.0.display()
+                ),
+            },
+        ))
+    }
+}
+
+#[cfg(feature = "reqwest")]
+mod reqwest_transport {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+    use reqwest::header::{HeaderName, HeaderValue};
+
+    use muat_core::Result;
+    use muat_core::error::{Error, InvalidInputError, TransportError};
+
+    use super::{HttpMethod, HttpRequest, HttpResponse, HttpTransport};
+
+    /// Default [`HttpTransport`] backed by `reqwest`.
+    #[derive(Debug, Clone)]
+    pub struct ReqwestTransport {
+        client: reqwest::Client,
+    }
+
+    impl ReqwestTransport {
+        /// Create a transport with the default muat user agent.
+        pub fn new() -> Self {
+            let client = reqwest::Client::builder()
+                .user_agent(concat!("muat/", env!("CARGO_PKG_VERSION")))
+                .build()
+                .expect("failed to build HTTP client");
+
+            Self { client }
+        }
+
+        /// Create a transport from an existing `reqwest` client.
+        pub fn from_client(client: reqwest::Client) -> Self {
+            Self { client }
+        }
+    }
+
+    impl Default for ReqwestTransport {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    #[async_trait]
+    impl HttpTransport for ReqwestTransport {
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            let method = match request.method {
+                HttpMethod::Get => reqwest::Method::GET,
+                HttpMethod::Post => reqwest::Method::POST,
+            };
+
+            let mut builder = self.client.request(method, &request.url);
+
+            for (name, value) in &request.headers {
+                let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| {
+                    Error::InvalidInput(InvalidInputError::Other {
+                        message: format!("invalid header name '{}': {}", name, e),
+                    })
+                })?;
+                // Header values may be secrets; never include them in errors.
+                let value = HeaderValue::from_str(value).map_err(|_| {
+                    Error::InvalidInput(InvalidInputError::Other {
+                        message: format!("invalid characters in header '{}'", name),
+                    })
+                })?;
+                builder = builder.header(name, value);
+            }
+
+            if let Some(body) = request.body {
+                builder = builder.body(body);
+            }
+            if let Some(timeout) = request.timeout {
+                builder = builder.timeout(timeout);
+            }
+
+            let map_error = |e| map_reqwest_error(e, request.timeout);
+            let response = builder.send().await.map_err(map_error)?;
+
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .filter_map(|(n, v)| {
+                    v.to_str()
+                        .ok()
+                        .map(|v| (n.as_str().to_string(), v.to_string()))
+                })
+                .collect();
+            let body = response.bytes().await.map_err(map_error)?.to_vec();
+
+            Ok(HttpResponse {
+                status,
+                headers,
+                body,
+            })
+        }
+    }
+
+    fn map_reqwest_error(err: reqwest::Error, timeout: Option<Duration>) -> Error {
+        if err.is_timeout() {
+            Error::Transport(TransportError::Timeout {
+                duration_ms: timeout.map_or(0, |t| t.as_millis() as u64),
+            })
+        } else if err.is_connect() {
+            Error::Transport(TransportError::Connection {
+                message: err.to_string(),
+            })
+        } else {
+            Error::Transport(TransportError::Http {
+                message: err.to_string(),
+            })
+        }
+    }
+}
+
+#[cfg(all(unix, feature = "unix-socket"))]
+mod unix_transport {
+    use std::path::PathBuf;
+
+    use async_trait::async_trait;
+    use http_body_util::{BodyExt, Full};
+    use hyper::body::Bytes;
+    use hyper_util::rt::TokioIo;
+    use tokio::net::UnixStream;
+    use tracing::debug;
+
+    use muat_core::Result;
+    use muat_core::error::{Error, InvalidInputError, TransportError};
+
+    use super::{HttpMethod, HttpRequest, HttpResponse, HttpTransport};
+
+    /// [`HttpTransport`] that speaks HTTP/1.1 over a Unix domain socket.
+    ///
+    /// Only the path and query of each request URL are used; the host is
+    /// always sent as `localhost`. A new connection is opened per request.
+    #[derive(Debug, Clone)]
+    pub struct UnixSocketTransport {
+        path: PathBuf,
+    }
+
+    impl UnixSocketTransport {
+        /// Create a transport for the socket at `path`.
+        pub fn new(path: impl Into<PathBuf>) -> Self {
+            Self { path: path.into() }
+        }
+
+        /// Returns the socket path.
+        pub fn path(&self) -> &std::path::Path {
+            &self.path
+        }
+    }
+
+    #[async_trait]
+    impl HttpTransport for UnixSocketTransport {
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            let Some(timeout) = request.timeout else {
+                return self.exchange(request).await;
+            };
+            tokio::time::timeout(timeout, self.exchange(request))
+                .await
+                .map_err(|_| {
+                    Error::Transport(TransportError::Timeout {
+                        duration_ms: timeout.as_millis() as u64,
+                    })
+                })?
+        }
+    }
+
+    impl UnixSocketTransport {
+        async fn exchange(&self, request: HttpRequest) -> Result<HttpResponse> {
+            let target = path_and_query(&request.url);
+            debug!(socket = %self.path.display(), %target, "Unix socket request");
+
+            let stream = UnixStream::connect(&self.path).await.map_err(|e| {
+                Error::Transport(TransportError::Connection {
+                    message: format!("{}: {}", self.path.display(), e),
+                })
+            })?;
+
+            let (mut sender, connection) =
+                hyper::client::conn::http1::handshake(TokioIo::new(stream))
+                    .await
+                    .map_err(http_error)?;
+            tokio::spawn(async move {
+                if let Err(e) = connection.await {
+                    debug!(error = %e, "Unix socket connection closed with error");
+                }
+            });
+
+            let method = match request.method {
+                HttpMethod::Get => hyper::Method::GET,
+                HttpMethod::Post => hyper::Method::POST,
+            };
+
+            let mut builder = hyper::Request::builder()
+                .method(method)
+                .uri(target)
+                .header(hyper::header::HOST, "localhost");
+            for (name, value) in &request.headers {
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+
+            let body = Full::new(Bytes::from(request.body.unwrap_or_default()));
+            // Header values may be secrets; never include them in errors.
+            let http_request = builder.body(body).map_err(|_| {
+                Error::InvalidInput(InvalidInputError::Other {
+                    message: "invalid request line or headers".to_string(),
+                })
+            })?;
+
+            let response = sender
+                .send_request(http_request)
+                .await
+                .map_err(http_error)?;
+
+            let status = response.status().as_u16();
+            let headers = response
+                .headers()
+                .iter()
+                .filter_map(|(n, v)| {
+                    v.to_str()
+                        .ok()
+                        .map(|v| (n.as_str().to_string(), v.to_string()))
+                })
+                .collect();
+            let body = response
+                .into_body()
+                .collect()
+                .await
+                .map_err(http_error)?
+                .to_bytes()
+                .to_vec();
+
+            Ok(HttpResponse {
+                status,
+                headers,
+                body,
+            })
+        }
+    }
+
+    /// Strip the scheme and authority, keeping `/path?query`.
+    fn path_and_query(url: &str) -> &str {
+        let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+        rest.find('/').map_or("/", |i| &rest[i..])
+    }
+
+    fn http_error(err: hyper::Error) -> Error {
+        Error::Transport(TransportError::Http {
+            message: err.to_string(),
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn request_target_drops_authority() {
+            assert_eq!(
+                path_and_query("http://localhost/xrpc/a.b.c?x=1"),
+                "/xrpc/a.b.c?x=1"
+            );
+            assert_eq!(path_and_query("http://localhost"), "/");
+        }
+    }
+}
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+mod fetch_transport {
+    use async_trait::async_trait;
+    use send_wrapper::SendWrapper;
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::JsFuture;
+
+    use muat_core::Result;
+    use muat_core::error::{Error, TransportError};
+
+    use super::{HttpRequest, HttpResponse, HttpTransport};
+
+    /// [`HttpTransport`] backed by the browser `fetch` API.
+    ///
+    /// Works in both window and worker contexts.
+    #[derive(Debug, Clone, Default)]
+    pub struct FetchTransport;
+
+    impl FetchTransport {
+        /// Create a new fetch transport.
+        pub fn new() -> Self {
+            Self
+        }
+    }
+
+    #[async_trait]
+    impl HttpTransport for FetchTransport {
+        async fn send(&self, request: HttpRequest) -> Result<HttpResponse> {
+            // JS futures are !Send; wasm32 is single-threaded, so this is sound.
+            SendWrapper::new(fetch(request)).await
+        }
+    }
+
+    async fn fetch(request: HttpRequest) -> Result<HttpResponse> {
+        let init = web_sys::RequestInit::new();
+        init.set_method(request.method.as_str());
+
+        let headers = web_sys::Headers::new().map_err(js_error)?;
+        for (name, value) in &request.headers {
+            headers.set(name, value).map_err(|_| {
+                Error::Transport(TransportError::Http {
+                    message: format!("invalid header '{}'", name),
+                })
+            })?;
+        }
+        init.set_headers(&headers);
+
+        if let Some(body) = &request.body {
+            let bytes = js_sys::Uint8Array::from(body.as_slice());
+            init.set_body(&bytes);
+        }
+
+        if let Some(timeout) = request.timeout {
+            let millis = u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX);
+            init.set_signal(Some(&web_sys::AbortSignal::timeout_with_u32(millis)));
+        }
+
+        let js_request =
+            web_sys::Request::new_with_str_and_init(&request.url, &init).map_err(js_error)?;
+
+        let promise = if let Some(window) = web_sys::window() {
+            window.fetch_with_request(&js_request)
+        } else {
+            js_sys::global()
+                .unchecked_into::<web_sys::WorkerGlobalScope>()
+                .fetch_with_request(&js_request)
+        };
+
+        let response: web_sys::Response = JsFuture::from(promise)
+            .await
+            .map_err(|e| match request.timeout {
+                Some(timeout) if is_timeout(&e) => Error::Transport(TransportError::Timeout {
+                    duration_ms: timeout.as_millis() as u64,
+                }),
+                _ => Error::Transport(TransportError::Connection {
+                    message: describe(&e),
+                }),
+            })?
+            .dyn_into()
+            .map_err(js_error)?;
+
+        let status = response.status();
+
+        let mut response_headers = Vec::new();
+        if let Ok(Some(entries)) = js_sys::try_iter(&response.headers()) {
+            for entry in entries.flatten() {
+                let pair = js_sys::Array::from(&entry);
+                if let (Some(name), Some(value)) =
+                    (pair.get(0).as_string(), pair.get(1).as_string())
+                {
+                    response_headers.push((name, value));
+                }
+            }
+        }
+
+        let buffer = JsFuture::from(response.array_buffer().map_err(js_error)?)
+            .await
+            .map_err(js_error)?;
+        let body = js_sys::Uint8Array::new(&buffer).to_vec();
+
+        Ok(HttpResponse {
+            status,
+            headers: response_headers,
+            body,
+        })
+    }
+
+    fn js_error(value: JsValue) -> Error {
+        Error::Transport(TransportError::Http {
+            message: describe(&value),
+        })
+    }
+
+    /// Whether `value` is the `TimeoutError` an `AbortSignal.timeout` raises.
+    fn is_timeout(value: &JsValue) -> bool {
+        js_sys::Reflect::get(value, &JsValue::from_str("name"))
+            .ok()
+            .and_then(|name| name.as_string())
+            .is_some_and(|name| name == "TimeoutError")
+    }
+
+    fn describe(value: &JsValue) -> String {
+        value.as_string().unwrap_or_else(|| format!("{:?}", value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_lookup_is_case_insensitive() {
+        let mut response = HttpResponse::new(200, Vec::new());
+        response
+            .headers
+            .push(("Content-Type".to_string(), "application/json".to_string()));
+        assert_eq!(response.header("content-type"), Some("application/json"));
+        assert!(response.is_success());
+    }
+
+    #[test]
+    fn request_debug_hides_header_values() {
+        let mut request = HttpRequest::new(HttpMethod::Get, "https://example.com");
+        request
+            .headers
+            .push(("authorization".to_string(), "Bearer secret".to_string()));
+        let debug = format!("{:?}", request);
+        assert!(debug.contains("authorization"));
+        assert!(!debug.contains("secret"));
+    }
+}
@@ -1,30 +1,96 @@
 //! XRPC HTTP client implementation.
 
-use reqwest::header::{AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderValue};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
 use serde::{Serialize, de::DeserializeOwned};
 use tracing::{debug, instrument, trace};
 
-use muat_core::error::{Error, ProtocolError, TransportError};
+use muat_core::error::{AuthError, Error, InvalidInputError, ProtocolError, TransportError};
+use muat_core::repo::ByteRange;
 use muat_core::types::PdsUrl;
 
 use super::endpoints::XrpcErrorResponse;
+use crate::middleware::{Middleware, Next};
+use crate::transport::{HttpMethod, HttpRequest, HttpResponse, HttpTransport};
 
 /// HTTP client for XRPC requests.
 #[derive(Debug, Clone)]
 pub struct XrpcClient {
-    client: reqwest::Client,
+    transport: Arc<dyn HttpTransport>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    timeouts: Arc<Timeouts>,
     pds: PdsUrl,
 }
 
+/// Request timeouts, layered from most to least specific.
+#[derive(Debug, Clone, Default)]
+struct Timeouts {
+    /// Overrides everything below, for a session handle.
+    all: Option<Duration>,
+    /// Per-method overrides, keyed by NSID.
+    methods: HashMap<String, Duration>,
+    /// Used when nothing more specific is set.
+    default: Option<Duration>,
+}
+
+impl Timeouts {
+    fn get(&self, method: &str) -> Option<Duration> {
+        self.all
+            .or_else(|| self.methods.get(method).copied())
+            .or(self.default)
+    }
+}
+
 impl XrpcClient {
-    /// Create a new XRPC client for the given PDS.
+    /// Create a new XRPC client for the given PDS using the default transport.
+    #[cfg(any(feature = "reqwest", all(feature = "wasm", target_arch = "wasm32")))]
     pub fn new(pds: PdsUrl) -> Self {
-        let client = reqwest::Client::builder()
-            .user_agent(concat!("muat/", env!("CARGO_PKG_VERSION")))
-            .build()
-            .expect("failed to build HTTP client");
+        let transport = crate::transport::default_transport(&pds);
+        Self::with_transport(pds, transport)
+    }
+
+    /// Create a new XRPC client for the given PDS using a custom transport.
+    pub fn with_transport(pds: PdsUrl, transport: Arc<dyn HttpTransport>) -> Self {
+        Self {
+            transport,
+            middleware: Vec::new(),
+            timeouts: Arc::default(),
+            pds,
+        }
+    }
 
-        Self { client, pds }
+    /// Add a middleware layer inside those already added.
+    pub fn with_middleware(mut self, middleware: Arc<dyn Middleware>) -> Self {
+        self.middleware.push(middleware);
+        self
+    }
+
+    /// Set the timeout for requests with no more specific one.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        Arc::make_mut(&mut self.timeouts).default = Some(timeout);
+        self
+    }
+
+    /// Set the timeout for one XRPC method, overriding the default.
+    pub fn with_endpoint_timeout(mut self, method: &str, timeout: Duration) -> Self {
+        Arc::make_mut(&mut self.timeouts)
+            .methods
+            .insert(method.to_string(), timeout);
+        self
+    }
+
+    /// Set a timeout that applies to every method, overriding both the
+    /// default and per-method timeouts.
+    pub fn with_timeout_override(mut self, timeout: Duration) -> Self {
+        Arc::make_mut(&mut self.timeouts).all = Some(timeout);
+        self
+    }
+
+    /// Returns the timeout that applies to `method`, if any.
+    pub fn timeout_for(&self, method: &str) -> Option<Duration> {
+        self.timeouts.get(method)
     }
 
     /// Returns the PDS URL this client is configured for.
@@ -41,19 +107,13 @@ impl XrpcClient {
         Q: Serialize + std::fmt::Debug,
         R: DeserializeOwned,
     {
-        let url = self.pds.xrpc_url(method);
         debug!(method, "XRPC query");
         trace!(?params, "query parameters");
 
-        let response = self
-            .client
-            .get(&url)
-            .query(params)
-            .send()
-            .await
-            .map_err(map_reqwest_error)?;
+        let request = HttpRequest::new(HttpMethod::Get, self.query_url(method, params)?);
+        let response = self.send(method, request).await?;
 
-        self.handle_response(response).await
+        self.handle_response(response)
     }
 
     /// Make an authenticated XRPC query (GET request).
@@ -68,20 +128,14 @@ impl XrpcClient {
         Q: Serialize + std::fmt::Debug,
         R: DeserializeOwned,
     {
-        let url = self.pds.xrpc_url(method);
         debug!(method, "XRPC authenticated query");
         trace!(?params, "query parameters");
 
-        let response = self
-            .client
-            .get(&url)
-            .query(params)
-            .headers(self.auth_headers(token))
-            .send()
-            .await
-            .map_err(map_reqwest_error)?;
+        let mut request = HttpRequest::new(HttpMethod::Get, self.query_url(method, params)?);
+        request.headers = self.auth_headers(token)?;
+        let response = self.send(method, request).await?;
 
-        self.handle_response(response).await
+        self.handle_response(response)
     }
 
     /// Make an unauthenticated XRPC procedure (POST request).
@@ -94,15 +148,28 @@ impl XrpcClient {
         let url = self.pds.xrpc_url(method);
         debug!(method, %url, "XRPC procedure");
 
-        let response = self
-            .client
-            .post(&url)
-            .json(body)
-            .send()
-            .await
-            .map_err(map_reqwest_error)?;
+        let mut request = HttpRequest::new(HttpMethod::Post, url);
+        request.headers.push(json_content_type());
+        request.body = Some(encode_body(body)?);
+        let response = self.send(method, request).await?;
+
+        self.handle_response(response)
+    }
+
+    /// Make an unauthenticated XRPC procedure that returns no content.
+    #[instrument(skip(self, body), fields(pds = %self.pds))]
+    pub async fn procedure_no_response<B>(&self, method: &str, body: &B) -> Result<(), Error>
+    where
+        B: Serialize,
+    {
+        debug!(method, "XRPC procedure (no response)");
+
+        let mut request = HttpRequest::new(HttpMethod::Post, self.pds.xrpc_url(method));
+        request.headers.push(json_content_type());
+        request.body = Some(encode_body(body)?);
+        let response = self.send(method, request).await?;
 
-        self.handle_response(response).await
+        self.handle_empty_response(response)
     }
 
     /// Make an authenticated XRPC procedure (POST request).
@@ -117,19 +184,14 @@ impl XrpcClient {
         B: Serialize + std::fmt::Debug,
         R: DeserializeOwned,
     {
-        let url = self.pds.xrpc_url(method);
         debug!(method, "XRPC authenticated procedure");
 
-        let response = self
-            .client
-            .post(&url)
-            .json(body)
-            .headers(self.auth_headers(token))
-            .send()
-            .await
-            .map_err(map_reqwest_error)?;
+        let mut request = HttpRequest::new(HttpMethod::Post, self.pds.xrpc_url(method));
+        request.headers = self.auth_headers(token)?;
+        request.body = Some(encode_body(body)?);
+        let response = self.send(method, request).await?;
 
-        self.handle_response(response).await
+        self.handle_response(response)
     }
 
     /// Make an authenticated XRPC procedure that returns no content.
@@ -143,25 +205,44 @@ impl XrpcClient {
     where
         B: Serialize + std::fmt::Debug,
     {
-        let url = self.pds.xrpc_url(method);
         debug!(method, "XRPC authenticated procedure (no response)");
 
-        let response = self
-            .client
-            .post(&url)
-            .json(body)
-            .headers(self.auth_headers(token))
-            .send()
-            .await
-            .map_err(map_reqwest_error)?;
-
-        let status = response.status();
-        if status.is_success() {
+        let mut request = HttpRequest::new(HttpMethod::Post, self.pds.xrpc_url(method));
+        request.headers = self.auth_headers(token)?;
+        request.body = Some(encode_body(body)?);
+        let response = self.send(method, request).await?;
+
+        if response.is_success() {
             Ok(())
         } else {
-            let error = self.parse_error_response(response).await;
-            Err(Error::Protocol(error))
+            Err(Error::Protocol(self.parse_error_response(&response)))
+        }
+    }
+
+    /// Make an authenticated XRPC procedure whose response body may be
+    /// empty, such as deleteRecord on older PDSes. An empty body is `None`.
+    #[instrument(skip(self, token), fields(pds = %self.pds))]
+    pub async fn procedure_authed_optional<B, R>(
+        &self,
+        method: &str,
+        body: &B,
+        token: &str,
+    ) -> Result<Option<R>, Error>
+    where
+        B: Serialize + std::fmt::Debug,
+        R: DeserializeOwned,
+    {
+        debug!(method, "XRPC authenticated procedure (optional response)");
+
+        let mut request = HttpRequest::new(HttpMethod::Post, self.pds.xrpc_url(method));
+        request.headers = self.auth_headers(token)?;
+        request.body = Some(encode_body(body)?);
+        let response = self.send(method, request).await?;
+
+        if response.is_success() && response.body.trim_ascii().is_empty() {
+            return Ok(None);
         }
+        self.handle_response(response).map(Some)
     }
 
     /// Make an authenticated XRPC procedure with no request body.
@@ -171,87 +252,378 @@ impl XrpcClient {
     where
         R: DeserializeOwned,
     {
-        let url = self.pds.xrpc_url(method);
         debug!(method, "XRPC authenticated procedure (no body)");
 
-        let response = self
-            .client
-            .post(&url)
-            .header(AUTHORIZATION, format!("Bearer {}", token))
-            .send()
-            .await
-            .map_err(map_reqwest_error)?;
+        let mut request = HttpRequest::new(HttpMethod::Post, self.pds.xrpc_url(method));
+        request.headers.push(bearer(token)?);
+        let response = self.send(method, request).await?;
 
-        self.handle_response(response).await
+        self.handle_response(response)
     }
 
-    /// Create authorization headers for authenticated requests.
-    fn auth_headers(&self, token: &str) -> HeaderMap {
-        let mut headers = HeaderMap::new();
-        let auth_value = format!("Bearer {}", token);
-        headers.insert(
-            AUTHORIZATION,
-            HeaderValue::from_str(&auth_value).expect("invalid token characters"),
+    /// Make an authenticated XRPC procedure with no request body that
+    /// returns no content, such as requestEmailConfirmation.
+    #[instrument(skip(self, token), fields(pds = %self.pds))]
+    pub async fn procedure_authed_empty(&self, method: &str, token: &str) -> Result<(), Error> {
+        debug!(method, "XRPC authenticated procedure (empty)");
+
+        let mut request = HttpRequest::new(HttpMethod::Post, self.pds.xrpc_url(method));
+        request.headers.push(bearer(token)?);
+        let response = self.send(method, request).await?;
+
+        self.handle_empty_response(response)
+    }
+
+    /// Make an XRPC query whose response may be of any content type, such
+    /// as a CAR file.
+    #[instrument(skip(self), fields(pds = %self.pds))]
+    pub async fn query_binary<Q>(&self, method: &str, params: &Q) -> Result<BinaryResponse, Error>
+    where
+        Q: Serialize + std::fmt::Debug,
+    {
+        debug!(method, "XRPC query (binary)");
+        trace!(?params, "query parameters");
+
+        let request = HttpRequest::new(HttpMethod::Get, self.query_url(method, params)?);
+        let response = self.send(method, request).await?;
+
+        self.handle_binary_response(response)
+    }
+
+    /// Make an authenticated XRPC query whose response may be of any content
+    /// type, such as a blob or a CAR file.
+    #[instrument(skip(self, token), fields(pds = %self.pds))]
+    pub async fn query_binary_authed<Q>(
+        &self,
+        method: &str,
+        params: &Q,
+        token: &str,
+    ) -> Result<BinaryResponse, Error>
+    where
+        Q: Serialize + std::fmt::Debug,
+    {
+        debug!(method, "XRPC authenticated query (binary)");
+        trace!(?params, "query parameters");
+
+        let mut request = HttpRequest::new(HttpMethod::Get, self.query_url(method, params)?);
+        request.headers.push(bearer(token)?);
+        let response = self.send(method, request).await?;
+
+        self.handle_binary_response(response)
+    }
+
+    /// Make an authenticated XRPC query for part of a binary response,
+    /// using an HTTP `Range` header.
+    ///
+    /// Servers that ignore the header send everything, which is sliced here.
+    #[instrument(skip(self, token), fields(pds = %self.pds))]
+    pub async fn query_bytes_range_authed<Q>(
+        &self,
+        method: &str,
+        params: &Q,
+        token: &str,
+        range: ByteRange,
+    ) -> Result<Vec<u8>, Error>
+    where
+        Q: Serialize + std::fmt::Debug,
+    {
+        debug!(method, "XRPC authenticated ranged query (bytes)");
+        trace!(?params, "query parameters");
+
+        let mut request = HttpRequest::new(HttpMethod::Get, self.query_url(method, params)?);
+        request.headers.push(bearer(token)?);
+        request
+            .headers
+            .push(("range".to_string(), range_header(range)));
+        let response = self.send(method, request).await?;
+
+        match response.status {
+            206 => {
+                let mut body = response.body;
+                if let Some(length) = range.length {
+                    body.truncate(length.try_into().unwrap_or(usize::MAX));
+                }
+                Ok(body)
+            }
+            // A resumed download that already finished.
+            416 if response.header("content-range").and_then(unsatisfied_size)
+                == Some(range.offset) =>
+            {
+                Ok(Vec::new())
+            }
+            _ if response.is_success() => range.slice(response.body),
+            _ => Err(Error::Protocol(self.parse_error_response(&response))),
+        }
+    }
+
+    /// Make an authenticated XRPC procedure with a raw request body of
+    /// `content_type`. The response may be of any content type.
+    #[instrument(skip(self, body, token), fields(pds = %self.pds, len = body.len()))]
+    pub async fn procedure_binary_authed(
+        &self,
+        method: &str,
+        body: Vec<u8>,
+        content_type: &str,
+        token: &str,
+    ) -> Result<BinaryResponse, Error> {
+        debug!(
+            method,
+            content_type, "XRPC authenticated procedure (binary)"
         );
-        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
-        headers
+
+        let mut request = HttpRequest::new(HttpMethod::Post, self.pds.xrpc_url(method));
+        request.headers = vec![
+            bearer(token)?,
+            ("content-type".to_string(), content_type.to_string()),
+        ];
+        request.body = Some(body);
+        let response = self.send(method, request).await?;
+
+        self.handle_binary_response(response)
+    }
+
+    /// Send a request through the middleware chain, recording metrics when
+    /// the `metrics` feature is on.
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    async fn send(&self, method: &str, mut request: HttpRequest) -> Result<HttpResponse, Error> {
+        // Set before middleware runs, so a layer can still adjust it.
+        request.timeout = self.timeouts.get(method);
+        #[cfg(feature = "metrics")]
+        let started = std::time::Instant::now();
+        let response = Next::new(&self.middleware, self.transport.as_ref())
+            .run(request)
+            .await;
+        #[cfg(feature = "metrics")]
+        crate::metrics::request(method, &response, started.elapsed());
+        response
+    }
+
+    /// Build the XRPC URL for a query, including encoded parameters.
+    fn query_url<Q: Serialize>(&self, method: &str, params: &Q) -> Result<String, Error> {
+        let url = self.pds.xrpc_url(method);
+        let query = serde_urlencoded::to_string(params).map_err(|e| {
+            Error::InvalidInput(InvalidInputError::Other {
+                message: format!("failed to encode query parameters: {}", e),
+            })
+        })?;
+
+        if query.is_empty() {
+            Ok(url)
+        } else {
+            Ok(format!("{}?{}", url, query))
+        }
+    }
+
+    /// Create authorization headers for authenticated requests.
+    fn auth_headers(&self, token: &str) -> Result<Vec<(String, String)>, Error> {
+        Ok(vec![bearer(token)?, json_content_type()])
     }
 
     /// Handle an XRPC response, parsing the body or error.
-    async fn handle_response<R: DeserializeOwned>(
-        &self,
-        response: reqwest::Response,
-    ) -> Result<R, Error> {
-        let status = response.status();
-        trace!(status = %status, "XRPC response");
-
-        if status.is_success() {
-            let body = response.json::<R>().await.map_err(map_reqwest_error)?;
-            Ok(body)
+    fn handle_response<R: DeserializeOwned>(&self, response: HttpResponse) -> Result<R, Error> {
+        trace!(status = response.status, "XRPC response");
+
+        if response.is_success() {
+            serde_json::from_slice::<R>(&response.body).map_err(|e| {
+                Error::Transport(TransportError::Http {
+                    message: format!("error decoding response body: {}", e),
+                })
+            })
         } else {
-            let error = self.parse_error_response(response).await;
-            Err(Error::Protocol(error))
+            Err(Error::Protocol(self.parse_error_response(&response)))
         }
     }
 
-    /// Parse an XRPC error response.
-    async fn parse_error_response(&self, response: reqwest::Response) -> ProtocolError {
-        let status = response.status().as_u16();
+    /// Accept any successful response, ignoring its body.
+    fn handle_empty_response(&self, response: HttpResponse) -> Result<(), Error> {
+        trace!(status = response.status, "XRPC response");
 
-        // Try to parse as XRPC error format
-        match response
-            .json::<XrpcErrorResponse>()
-            .await
-            .map_err(map_reqwest_error)
-        {
-            Ok(error_body) => ProtocolError::new(status, error_body.error, error_body.message),
-            Err(_) => ProtocolError::new(status, None, None),
+        if response.is_success() {
+            Ok(())
+        } else {
+            Err(Error::Protocol(self.parse_error_response(&response)))
         }
     }
+
+    /// Keep a successful response body as is, with its content type.
+    fn handle_binary_response(&self, response: HttpResponse) -> Result<BinaryResponse, Error> {
+        trace!(status = response.status, "XRPC response");
+
+        if response.is_success() {
+            Ok(BinaryResponse {
+                content_type: response.header("content-type").map(str::to_string),
+                body: response.body,
+            })
+        } else {
+            Err(Error::Protocol(self.parse_error_response(&response)))
+        }
+    }
+
+    /// Parse an XRPC error response.
+    fn parse_error_response(&self, response: &HttpResponse) -> ProtocolError {
+        // Try to parse as XRPC error format
+        let error = match serde_json::from_slice::<XrpcErrorResponse>(&response.body) {
+            Ok(error_body) => {
+                ProtocolError::new(response.status, error_body.error, error_body.message)
+            }
+            Err(_) => ProtocolError::new(response.status, None, None),
+        };
+        error.with_retry_after(response.header("retry-after").and_then(parse_retry_after))
+    }
 }
 
-fn map_reqwest_error(err: reqwest::Error) -> Error {
-    if err.is_timeout() {
-        Error::Transport(TransportError::Timeout { duration_ms: 0 })
-    } else if err.is_connect() {
-        Error::Transport(TransportError::Connection {
-            message: err.to_string(),
+/// The body of an XRPC response that is not necessarily JSON, such as a
+/// blob (`com.atproto.sync.getBlob`) or a CAR file (`com.atproto.sync.getRepo`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryResponse {
+    /// The response body.
+    pub body: Vec<u8>,
+    /// The `Content-Type` header, if the server sent one.
+    pub content_type: Option<String>,
+}
+
+impl BinaryResponse {
+    /// The media type without parameters, lowercased: `application/json`
+    /// for `application/json; charset=utf-8`.
+    pub fn mime_type(&self) -> Option<String> {
+        let content_type = self.content_type.as_deref()?;
+        let essence = content_type.split(';').next().unwrap_or_default().trim();
+        (!essence.is_empty()).then(|| essence.to_ascii_lowercase())
+    }
+
+    /// Whether the body is JSON, by its content type.
+    pub fn is_json(&self) -> bool {
+        self.mime_type().as_deref() == Some("application/json")
+    }
+
+    /// Decode the body as JSON, whatever content type it was sent with.
+    pub fn json<R: DeserializeOwned>(&self) -> Result<R, Error> {
+        serde_json::from_slice(&self.body).map_err(|e| {
+            Error::Transport(TransportError::Http {
+                message: format!("error decoding response body: {}", e),
+            })
         })
+    }
+}
+
+/// Parse a `Retry-After` value: delay-seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    // A date in the past means retrying now is fine.
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// The `Range` header value for `range`. A zero length cannot be written
+/// as a byte range, so it asks for the rest and the caller truncates.
+fn range_header(range: ByteRange) -> String {
+    match range.length {
+        Some(length) if length > 0 => format!(
+            "bytes={}-{}",
+            range.offset,
+            range.offset.saturating_add(length - 1)
+        ),
+        _ => format!("bytes={}-", range.offset),
+    }
+}
+
+/// The blob size from a 416 response's `Content-Range: bytes */<size>`.
+fn unsatisfied_size(value: &str) -> Option<u64> {
+    value.trim().strip_prefix("bytes */")?.parse().ok()
+}
+
+/// The `authorization` header for a bearer token.
+fn bearer(token: &str) -> Result<(String, String), Error> {
+    check_token("bearer", token)?;
+    Ok(("authorization".to_string(), format!("Bearer {}", token)))
+}
+
+/// Check that a token can be sent in an HTTP header: non-empty, visible
+/// ASCII only. `kind` names the token in the error; the token itself is
+/// never included.
+pub(crate) fn check_token(kind: &str, token: &str) -> Result<(), Error> {
+    let reason = if token.is_empty() {
+        format!("{} token is empty", kind)
+    } else if !token.bytes().all(|b| b.is_ascii_graphic()) {
+        format!(
+            "{} token contains characters not allowed in an HTTP header",
+            kind
+        )
     } else {
-        Error::Transport(TransportError::Http {
-            message: err.to_string(),
+        return Ok(());
+    };
+    Err(AuthError::MalformedToken(reason).into())
+}
+
+fn json_content_type() -> (String, String) {
+    ("content-type".to_string(), "application/json".to_string())
+}
+
+fn encode_body<B: Serialize>(body: &B) -> Result<Vec<u8>, Error> {
+    serde_json::to_vec(body).map_err(|e| {
+        Error::InvalidInput(InvalidInputError::Other {
+            message: format!("failed to encode request body: {}", e),
         })
-    }
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[cfg(feature = "reqwest")]
     #[test]
     fn client_creation() {
         let pds = PdsUrl::new("https://bsky.social").unwrap();
         let client = XrpcClient::new(pds.clone());
         assert_eq!(client.pds().as_str(), pds.as_str());
     }
+
+    #[test]
+    fn binary_response_mime_type_drops_parameters() {
+        let response = |content_type: Option<&str>| BinaryResponse {
+            body: Vec::new(),
+            content_type: content_type.map(str::to_string),
+        };
+        assert!(response(Some("Application/JSON; charset=utf-8")).is_json());
+        assert_eq!(
+            response(Some("application/vnd.ipld.car"))
+                .mime_type()
+                .as_deref(),
+            Some("application/vnd.ipld.car")
+        );
+        assert_eq!(response(Some(" ; q=1")).mime_type(), None);
+        assert!(!response(None).is_json());
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_and_dates() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+        let later = (chrono::Utc::now() + chrono::Duration::seconds(90)).to_rfc2822();
+        let wait = parse_retry_after(&later).unwrap();
+        assert!(wait > Duration::from_secs(80) && wait <= Duration::from_secs(90));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn query_url_encodes_params() {
+        let pds = PdsUrl::new("https://bsky.social").unwrap();
+        let client = XrpcClient::new(pds);
+        let url = client
+            .query_url("com.atproto.repo.listRecords", &[("repo", "did:plc:abc")])
+            .unwrap();
+        assert_eq!(
+            url,
+            "https://bsky.social/xrpc/com.atproto.repo.listRecords?repo=did%3Aplc%3Aabc"
+        );
+    }
 }
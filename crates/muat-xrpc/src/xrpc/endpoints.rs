@@ -4,6 +4,8 @@
 
 use serde::{Deserialize, Serialize};
 
+use muat_core::repo::LabelEvent;
+
 // ============================================================================
 // Endpoint Names
 // ============================================================================
@@ -17,6 +19,36 @@ pub const REFRESH_SESSION: &str = "com.atproto.server.refreshSession";
 /// com.atproto.server.getSession
 pub const GET_SESSION: &str = "com.atproto.server.getSession";
 
+/// com.atproto.server.describeServer
+pub const DESCRIBE_SERVER: &str = "com.atproto.server.describeServer";
+
+/// com.atproto.server.requestEmailConfirmation
+pub const REQUEST_EMAIL_CONFIRMATION: &str = "com.atproto.server.requestEmailConfirmation";
+
+/// com.atproto.server.confirmEmail
+pub const CONFIRM_EMAIL: &str = "com.atproto.server.confirmEmail";
+
+/// com.atproto.server.requestPasswordReset
+pub const REQUEST_PASSWORD_RESET: &str = "com.atproto.server.requestPasswordReset";
+
+/// com.atproto.server.resetPassword
+pub const RESET_PASSWORD: &str = "com.atproto.server.resetPassword";
+
+/// com.atproto.server.checkAccountStatus
+pub const CHECK_ACCOUNT_STATUS: &str = "com.atproto.server.checkAccountStatus";
+
+/// com.atproto.server.activateAccount
+pub const ACTIVATE_ACCOUNT: &str = "com.atproto.server.activateAccount";
+
+/// com.atproto.server.deactivateAccount
+pub const DEACTIVATE_ACCOUNT: &str = "com.atproto.server.deactivateAccount";
+
+/// com.atproto.identity.resolveHandle
+pub const RESOLVE_HANDLE: &str = "com.atproto.identity.resolveHandle";
+
+/// com.atproto.repo.describeRepo
+pub const DESCRIBE_REPO: &str = "com.atproto.repo.describeRepo";
+
 /// com.atproto.repo.listRecords
 pub const LIST_RECORDS: &str = "com.atproto.repo.listRecords";
 
@@ -26,16 +58,58 @@ pub const GET_RECORD: &str = "com.atproto.repo.getRecord";
 /// com.atproto.repo.createRecord
 pub const CREATE_RECORD: &str = "com.atproto.repo.createRecord";
 
+/// com.atproto.repo.putRecord
+pub const PUT_RECORD: &str = "com.atproto.repo.putRecord";
+
 /// com.atproto.repo.deleteRecord
 pub const DELETE_RECORD: &str = "com.atproto.repo.deleteRecord";
 
+/// com.atproto.repo.applyWrites
+pub const APPLY_WRITES: &str = "com.atproto.repo.applyWrites";
+
+/// com.atproto.repo.uploadBlob
+pub const UPLOAD_BLOB: &str = "com.atproto.repo.uploadBlob";
+
+/// com.atproto.sync.getBlob
+pub const GET_BLOB: &str = "com.atproto.sync.getBlob";
+
+/// com.atproto.sync.listBlobs
+pub const LIST_BLOBS: &str = "com.atproto.sync.listBlobs";
+
+/// com.atproto.sync.getBlocks
+pub const GET_BLOCKS: &str = "com.atproto.sync.getBlocks";
+
+/// com.atproto.sync.getRepo
+pub const GET_REPO: &str = "com.atproto.sync.getRepo";
+
+/// com.atproto.sync.getRecord
+pub const GET_SYNC_RECORD: &str = "com.atproto.sync.getRecord";
+
 /// com.atproto.sync.subscribeRepos
 pub const SUBSCRIBE_REPOS: &str = "com.atproto.sync.subscribeRepos";
 
+/// com.atproto.sync.requestCrawl
+pub const REQUEST_CRAWL: &str = "com.atproto.sync.requestCrawl";
+
+/// com.atproto.sync.notifyOfUpdate
+pub const NOTIFY_OF_UPDATE: &str = "com.atproto.sync.notifyOfUpdate";
+
+/// com.atproto.label.queryLabels
+pub const QUERY_LABELS: &str = "com.atproto.label.queryLabels";
+
+/// com.atproto.label.subscribeLabels
+pub const SUBSCRIBE_LABELS: &str = "com.atproto.label.subscribeLabels";
+
 // ============================================================================
 // Request/Response Types
 // ============================================================================
 
+/// Request body for requestCrawl and notifyOfUpdate.
+#[derive(Debug, Serialize)]
+pub struct HostnameRequest<'a> {
+    pub hostname: &'a str,
+}
+
 /// Request body for createSession.
 #[derive(Debug, Serialize)]
 pub struct CreateSessionRequest<'a> {
@@ -80,6 +154,102 @@ pub struct GetSessionResponse {
     pub email_confirmed: Option<bool>,
 }
 
+/// Response from describeServer.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DescribeServerResponse {
+    pub did: String,
+    #[serde(default)]
+    pub available_user_domains: Vec<String>,
+    #[serde(default)]
+    pub invite_code_required: Option<bool>,
+    #[serde(default)]
+    pub phone_verification_required: Option<bool>,
+    #[serde(default)]
+    pub links: Option<DescribeServerLinks>,
+    #[serde(default)]
+    pub contact: Option<DescribeServerContact>,
+}
+
+/// Policy links in a describeServer response.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DescribeServerLinks {
+    #[serde(default)]
+    pub privacy_policy: Option<String>,
+    #[serde(default)]
+    pub terms_of_service: Option<String>,
+}
+
+/// Contact details in a describeServer response.
+#[derive(Debug, Deserialize)]
+pub struct DescribeServerContact {
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+/// Request body for confirmEmail.
+#[derive(Serialize)]
+pub struct ConfirmEmailRequest<'a> {
+    pub email: &'a str,
+    pub token: &'a str,
+}
+
+/// Leaves out the token, as request bodies are recorded in spans.
+impl std::fmt::Debug for ConfirmEmailRequest<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfirmEmailRequest")
+            .field("email", &self.email)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Request body for requestPasswordReset.
+#[derive(Debug, Serialize)]
+pub struct RequestPasswordResetRequest<'a> {
+    pub email: &'a str,
+}
+
+/// Request body for resetPassword.
+#[derive(Serialize)]
+pub struct ResetPasswordRequest<'a> {
+    pub token: &'a str,
+    pub password: &'a str,
+}
+
+/// Request body for deactivateAccount.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeactivateAccountRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delete_after: Option<String>,
+}
+
+/// Query parameters for resolveHandle.
+#[derive(Debug, Serialize)]
+pub struct ResolveHandleQuery<'a> {
+    pub handle: &'a str,
+}
+
+/// Response from resolveHandle.
+#[derive(Debug, Deserialize)]
+pub struct ResolveHandleResponse {
+    pub did: String,
+}
+
+/// Query parameters for describeRepo.
+#[derive(Debug, Serialize)]
+pub struct DescribeRepoQuery<'a> {
+    pub repo: &'a str,
+}
+
+/// Response from describeRepo; only the fields used are kept.
+#[derive(Debug, Deserialize)]
+pub struct DescribeRepoResponse {
+    #[serde(default)]
+    pub collections: Vec<String>,
+}
+
 /// Query parameters for listRecords.
 #[derive(Debug, Serialize)]
 pub struct ListRecordsQuery<'a> {
@@ -145,6 +315,132 @@ pub struct CreateRecordRequest<'a> {
 pub struct CreateRecordResponse {
     pub uri: String,
     pub cid: String,
+    #[serde(default)]
+    pub commit: Option<CommitMeta>,
+}
+
+/// The commit a write produced. Older PDSes leave it out of responses.
+#[derive(Debug, Deserialize)]
+pub struct CommitMeta {
+    pub cid: String,
+    pub rev: String,
+}
+
+/// Request body for putRecord.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PutRecordRequest<'a> {
+    pub repo: &'a str,
+    pub collection: &'a str,
+    pub rkey: &'a str,
+    pub record: &'a serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub swap_record: Option<&'a str>,
+}
+
+/// Response from putRecord.
+#[derive(Debug, Deserialize)]
+pub struct PutRecordResponse {
+    pub uri: String,
+    pub cid: String,
+    #[serde(default)]
+    pub commit: Option<CommitMeta>,
+}
+
+/// Request body for applyWrites.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyWritesRequest<'a, W> {
+    pub repo: &'a str,
+    pub writes: Vec<W>,
+}
+
+/// A create operation within applyWrites.
+#[derive(Debug, Serialize)]
+pub struct ApplyWritesCreate<'a> {
+    #[serde(rename = "$type")]
+    pub kind: &'static str,
+    pub collection: &'a str,
+    pub value: &'a serde_json::Value,
+}
+
+/// A delete operation within applyWrites.
+#[derive(Debug, Serialize)]
+pub struct ApplyWritesDelete<'a> {
+    #[serde(rename = "$type")]
+    pub kind: &'static str,
+    pub collection: &'a str,
+    pub rkey: &'a str,
+}
+
+/// Response from applyWrites.
+#[derive(Debug, Deserialize)]
+pub struct ApplyWritesResponse {
+    #[serde(default)]
+    pub results: Option<Vec<ApplyWritesResult>>,
+    #[serde(default)]
+    pub commit: Option<CommitMeta>,
+}
+
+/// Per-write result from applyWrites.
+#[derive(Debug, Deserialize)]
+pub struct ApplyWritesResult {
+    pub uri: Option<String>,
+}
+
+/// Response from uploadBlob.
+#[derive(Debug, Deserialize)]
+pub struct UploadBlobResponse {
+    pub blob: muat_core::repo::BlobRef,
+}
+
+/// Query parameters for getBlob.
+#[derive(Debug, Serialize)]
+pub struct GetBlobQuery<'a> {
+    pub did: &'a str,
+    pub cid: &'a str,
+}
+
+/// Query parameters for getRepo.
+#[derive(Debug, Serialize)]
+pub struct GetRepoQuery<'a> {
+    pub did: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<&'a str>,
+}
+
+/// Query parameters for sync.getRecord.
+#[derive(Debug, Serialize)]
+pub struct GetSyncRecordQuery<'a> {
+    pub did: &'a str,
+    pub collection: &'a str,
+    pub rkey: &'a str,
+}
+
+/// Query parameters for listBlobs.
+#[derive(Debug, Serialize)]
+pub struct ListBlobsQuery<'a> {
+    pub did: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<&'a str>,
+}
+
+/// Response from listBlobs.
+#[derive(Debug, Deserialize)]
+pub struct ListBlobsResponse {
+    pub cids: Vec<String>,
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+/// Response from queryLabels.
+#[derive(Debug, Deserialize)]
+pub struct QueryLabelsResponse {
+    pub labels: Vec<LabelEvent>,
+    #[serde(default)]
+    pub cursor: Option<String>,
 }
 
 /// Request body for deleteRecord.
@@ -160,6 +456,13 @@ pub struct DeleteRecordRequest<'a> {
     pub swap_commit: Option<&'a str>,
 }
 
+/// Response from deleteRecord, which older PDSes send empty.
+#[derive(Debug, Deserialize)]
+pub struct DeleteRecordResponse {
+    #[serde(default)]
+    pub commit: Option<CommitMeta>,
+}
+
 /// XRPC error response format.
 #[derive(Debug, Deserialize)]
 pub struct XrpcErrorResponse {
@@ -0,0 +1,234 @@
+//! How WebSocket and frame failures surface as `FirehoseError`s.
+
+#![cfg(feature = "native-ws")]
+
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+use muat_core::error::{Error, FirehoseError};
+use muat_core::repo::RepoEvent;
+use muat_core::{PdsUrl, Result};
+use muat_xrpc::{FirehoseLimits, XrpcFirehose};
+use wiremock::matchers::method;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// A DAG-CBOR text string shorter than 24 bytes.
+fn text(value: &str) -> Vec<u8> {
+    let mut out = vec![0x60 + value.len() as u8];
+    out.extend(value.as_bytes());
+    out
+}
+
+/// An event frame header, `{"t": kind, "op": 1}`.
+fn event_frame(kind: &str) -> Vec<u8> {
+    let mut frame = vec![0xa2];
+    frame.extend(text("t"));
+    frame.extend(text(kind));
+    frame.extend(text("op"));
+    frame.push(0x01);
+    frame
+}
+
+/// An error frame, `{"op": -1}` then `{"error": name, "message": message}`.
+fn error_frame(name: &str, message: &str) -> Vec<u8> {
+    let mut frame = vec![0xa1];
+    frame.extend(text("op"));
+    frame.push(0x20);
+    frame.push(0xa2);
+    frame.extend(text("error"));
+    frame.extend(text(name));
+    frame.extend(text("message"));
+    frame.extend(text(message));
+    frame
+}
+
+/// An info frame, `{"t": "#info", "op": 1}` then `{"name": name, "message": message}`.
+fn info_frame(name: &str, message: &str) -> Vec<u8> {
+    let mut frame = event_frame("#info");
+    frame.push(0xa2);
+    frame.extend(text("name"));
+    frame.extend(text(name));
+    frame.extend(text("message"));
+    frame.extend(text(message));
+    frame
+}
+
+/// Serve one WebSocket connection that sends `frames` and then, if
+/// `close` is set, closes cleanly; otherwise the TCP connection is dropped.
+async fn serve(frames: Vec<Vec<u8>>, close: bool) -> PdsUrl {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        for frame in frames {
+            socket.send(Message::Binary(frame.into())).await.unwrap();
+        }
+        if close {
+            socket.close(None).await.unwrap();
+        }
+    });
+    PdsUrl::new(format!("http://{}", addr)).unwrap()
+}
+
+async fn collect(pds: &PdsUrl) -> Vec<Result<RepoEvent>> {
+    let firehose = XrpcFirehose::from_websocket(pds, None).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(10), firehose.collect())
+        .await
+        .unwrap()
+}
+
+fn firehose_error(item: &Result<RepoEvent>) -> &FirehoseError {
+    match item {
+        Err(Error::Firehose(e)) => e,
+        other => panic!("expected a firehose error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_error_frames_become_server_errors() {
+    let pds = serve(
+        vec![
+            event_frame("#commit"),
+            error_frame("FutureCursor", "cursor is in the future"),
+        ],
+        true,
+    )
+    .await;
+
+    let items = collect(&pds).await;
+    assert_eq!(items.len(), 2);
+    assert!(matches!(&items[0], Ok(RepoEvent::Unknown { .. })));
+    let error = firehose_error(&items[1]);
+    assert!(
+        matches!(error, FirehoseError::ServerError { name, message }
+            if name == "FutureCursor" && message.as_deref() == Some("cursor is in the future")),
+        "{error:?}"
+    );
+    assert!(!error.is_retryable());
+}
+
+#[tokio::test]
+async fn test_outdated_cursor_needs_backfill() {
+    let pds = serve(
+        vec![
+            info_frame("OutdatedCursor", "too old"),
+            event_frame("#commit"),
+            info_frame("Other", "hello"),
+        ],
+        true,
+    )
+    .await;
+
+    let items = collect(&pds).await;
+    assert_eq!(items.len(), 3);
+    let error = firehose_error(&items[0]);
+    assert!(
+        matches!(error, FirehoseError::CursorTooOld { message } if message.as_deref() == Some("too old")),
+        "{error:?}"
+    );
+    assert!(error.needs_backfill());
+    assert!(!error.is_retryable());
+    assert!(matches!(&items[1], Ok(RepoEvent::Unknown { .. })));
+    assert!(
+        matches!(&items[2], Ok(RepoEvent::Info(info)) if info.name == "Other" && info.message.as_deref() == Some("hello"))
+    );
+}
+
+#[tokio::test]
+async fn test_undecodable_frames_do_not_end_the_stream() {
+    let pds = serve(vec![vec![0xff, 0x00], event_frame("#identity")], true).await;
+
+    let items = collect(&pds).await;
+    assert_eq!(items.len(), 2);
+    assert!(
+        matches!(firehose_error(&items[0]), FirehoseError::DecodeError { frame_kind, .. } if frame_kind == "binary")
+    );
+    assert!(matches!(&items[1], Ok(RepoEvent::Unknown { .. })));
+}
+
+#[tokio::test]
+async fn test_dropped_connection_is_a_disconnect() {
+    let pds = serve(vec![event_frame("#commit")], false).await;
+
+    let items = collect(&pds).await;
+    assert!(items[0].is_ok());
+    let error = firehose_error(items.last().unwrap());
+    assert!(
+        matches!(error, FirehoseError::Disconnected { .. }),
+        "{error:?}"
+    );
+    assert!(error.is_retryable());
+}
+
+#[tokio::test]
+async fn test_oversized_messages_end_the_stream() {
+    let mut giant = event_frame("#commit");
+    giant.resize(64 * 1024, 0);
+    let pds = serve(vec![event_frame("#commit"), giant], true).await;
+
+    let limits = FirehoseLimits::new().message_size(16 * 1024);
+    assert_eq!(limits.max_frame_size(), 16 * 1024);
+    let firehose = XrpcFirehose::from_websocket_with_limits(&pds, None, limits)
+        .await
+        .unwrap();
+    let items: Vec<_> = tokio::time::timeout(Duration::from_secs(10), firehose.collect())
+        .await
+        .unwrap();
+
+    assert_eq!(items.len(), 2);
+    assert!(items[0].is_ok());
+    let error = firehose_error(&items[1]);
+    assert!(
+        matches!(
+            error,
+            FirehoseError::MessageTooLarge {
+                size: 65536,
+                limit: 16384
+            }
+        ),
+        "{error:?}"
+    );
+    assert!(!error.is_retryable());
+}
+
+#[tokio::test]
+async fn test_rejected_upgrade_is_a_connect_failure() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+    let pds = PdsUrl::new(server.uri()).unwrap();
+
+    let Err(Error::Firehose(error)) = XrpcFirehose::from_websocket(&pds, None).await else {
+        panic!("expected the upgrade to be rejected");
+    };
+    assert!(
+        matches!(
+            error,
+            FirehoseError::ConnectFailed {
+                status: Some(404),
+                ..
+            }
+        ),
+        "{error:?}"
+    );
+    assert!(!error.is_retryable());
+
+    // Nothing listening at all: worth retrying later.
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let closed = PdsUrl::new(format!("http://{}", listener.local_addr().unwrap())).unwrap();
+    drop(listener);
+    let Err(Error::Firehose(error)) = XrpcFirehose::from_websocket(&closed, None).await else {
+        panic!("expected the connection to fail");
+    };
+    assert!(matches!(
+        error,
+        FirehoseError::ConnectFailed { status: None, .. }
+    ));
+    assert!(error.is_retryable());
+}
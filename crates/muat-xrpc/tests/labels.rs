@@ -0,0 +1,187 @@
+//! Label subscriptions over a WebSocket and label queries over XRPC.
+
+#![cfg(feature = "native-ws")]
+
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+use muat_core::error::{Error, FirehoseError};
+use muat_core::{Did, LabelEvent, PdsUrl, Result};
+use muat_xrpc::XrpcPds;
+use serde_json::json;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const SRC: &str = "did:plc:labelaaaaaaaaaaaaaaaaaaa";
+const POST: &str = "at://did:plc:abcaaaaaaaaaaaaaaaaaaaaa/app.bsky.feed.post/3k2a";
+
+/// A DAG-CBOR item head for major type `major` with a small argument.
+fn head(major: u8, n: usize) -> Vec<u8> {
+    match n {
+        0..=23 => vec![major << 5 | n as u8],
+        _ => vec![major << 5 | 24, n as u8],
+    }
+}
+
+fn text(value: &str) -> Vec<u8> {
+    let mut out = head(3, value.len());
+    out.extend(value.as_bytes());
+    out
+}
+
+fn map(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let mut out = head(5, entries.len());
+    for (key, value) in entries {
+        out.extend(text(key));
+        out.extend(value);
+    }
+    out
+}
+
+fn frame(kind: &str, body: Vec<u8>) -> Vec<u8> {
+    let mut out = map(&[("t", text(kind)), ("op", vec![0x01])]);
+    out.extend(body);
+    out
+}
+
+/// A `#labels` message with one label per `(val, neg)`.
+fn labels_frame(seq: u8, labels: &[(&str, bool)]) -> Vec<u8> {
+    let mut items = head(4, labels.len());
+    for (val, neg) in labels {
+        items.extend(map(&[
+            ("ver", vec![0x01]),
+            ("src", text(SRC)),
+            ("uri", text(POST)),
+            ("val", text(val)),
+            ("neg", vec![if *neg { 0xf5 } else { 0xf4 }]),
+            ("cts", text("2024-09-09T19:46:02.329Z")),
+            ("sig", [head(2, 4), vec![1, 2, 3, 4]].concat()),
+        ]));
+    }
+    frame("#labels", map(&[("seq", vec![seq]), ("labels", items)]))
+}
+
+/// Serve one WebSocket connection at any path that sends `frames`, then
+/// closes.
+async fn serve(frames: Vec<Vec<u8>>) -> PdsUrl {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+        for frame in frames {
+            socket.send(Message::Binary(frame.into())).await.unwrap();
+        }
+        socket.close(None).await.unwrap();
+    });
+    PdsUrl::new(format!("http://{}", addr)).unwrap()
+}
+
+async fn collect(pds: &PdsUrl) -> Vec<Result<LabelEvent>> {
+    let labels = XrpcPds::new(pds.clone())
+        .subscribe_labels(Some(3))
+        .await
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(10), labels.collect())
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_subscribe_labels_yields_each_label() {
+    let pds = serve(vec![
+        frame(
+            "#info",
+            map(&[("name", text("OutdatedCursor")), ("message", text("old"))]),
+        ),
+        labels_frame(4, &[("spam", false), ("porn", false)]),
+        frame("#future", map(&[("seq", vec![0x05])])),
+        labels_frame(6, &[("spam", true)]),
+    ])
+    .await;
+
+    let labels: Vec<LabelEvent> = collect(&pds)
+        .await
+        .into_iter()
+        .collect::<Result<_>>()
+        .unwrap();
+    let summary: Vec<(i64, &str, bool)> = labels
+        .iter()
+        .map(|label| (label.seq, label.val.as_str(), label.neg))
+        .collect();
+    assert_eq!(
+        summary,
+        [(4, "spam", false), (4, "porn", false), (6, "spam", true)]
+    );
+    assert_eq!(labels[0].src, SRC);
+    assert_eq!(labels[0].uri, POST);
+    assert_eq!(labels[0].cts.timestamp_millis(), 1_725_911_162_329);
+    assert!(labels[0].cid.is_none() && labels[0].exp.is_none());
+}
+
+#[tokio::test]
+async fn test_malformed_labels_are_decode_errors() {
+    let missing_val = frame(
+        "#labels",
+        map(&[
+            ("seq", vec![0x04]),
+            (
+                "labels",
+                [head(4, 1), map(&[("src", text(SRC)), ("uri", text(POST))])].concat(),
+            ),
+        ]),
+    );
+    let pds = serve(vec![missing_val, labels_frame(5, &[("spam", false)])]).await;
+
+    let items = collect(&pds).await;
+    assert_eq!(items.len(), 2);
+    assert!(
+        matches!(&items[0], Err(Error::Firehose(FirehoseError::DecodeError { frame_kind, .. })) if frame_kind == "labels"),
+        "{:?}",
+        items[0]
+    );
+    assert!(matches!(&items[1], Ok(label) if label.seq == 5));
+}
+
+#[tokio::test]
+async fn test_query_labels_sends_patterns_and_sources() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.label.queryLabels"))
+        .and(query_param("uriPatterns", "at://did:plc:abcaaaaaaaaaaaaaaaaaaaaa/*"))
+        .and(query_param("sources", SRC))
+        .and(query_param("limit", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "cursor": "2",
+            "labels": [
+                { "ver": 1, "src": SRC, "uri": POST, "val": "spam", "cts": "2024-09-09T19:46:02.329Z" },
+                { "src": SRC, "uri": POST, "val": "spam", "neg": true, "cts": "2024-09-10T00:00:00Z" },
+            ],
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let pds = XrpcPds::new(PdsUrl::new(server.uri()).unwrap());
+
+    let page = pds
+        .query_labels(
+            &["at://did:plc:abcaaaaaaaaaaaaaaaaaaaaa/*"],
+            &[Did::new(SRC).unwrap()],
+            Some(2),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.cursor.as_deref(), Some("2"));
+    assert_eq!(page.labels.len(), 2);
+    assert!(!page.labels[0].neg && page.labels[1].neg);
+    assert!(page.labels.iter().all(|label| label.seq == 0));
+
+    assert!(matches!(
+        pds.query_labels(&[], &[], None, None).await,
+        Err(Error::InvalidInput(_))
+    ));
+}
@@ -0,0 +1,143 @@
+//! Metrics recorded with the `metrics` feature.
+//!
+//! Run with `cargo test -p muat-xrpc --features metrics`.
+
+#![cfg(feature = "metrics")]
+
+use metrics_util::CompositeKey;
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+use muat_core::{Credentials, Pds, PdsUrl};
+use muat_xrpc::{XrpcFirehose, XrpcPds};
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Run `test` on a current-thread runtime with a local recorder installed,
+/// so tests do not see each other's metrics.
+fn with_recorder<F: Future>(test: F) -> Vec<(CompositeKey, DebugValue)> {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    metrics::with_local_recorder(&recorder, || runtime.block_on(test));
+    snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .map(|(key, _, _, value)| (key, value))
+        .collect()
+}
+
+/// The value of the metric with this name and these labels.
+fn value<'a>(
+    snapshot: &'a [(CompositeKey, DebugValue)],
+    name: &str,
+    labels: &[(&str, &str)],
+) -> Option<&'a DebugValue> {
+    snapshot
+        .iter()
+        .find(|(key, _)| {
+            let key = key.key();
+            key.name() == name
+                && labels.iter().all(|(k, v)| {
+                    key.labels()
+                        .any(|label| label.key() == *k && label.value() == *v)
+                })
+        })
+        .map(|(_, value)| value)
+}
+
+#[test]
+fn test_xrpc_requests_are_counted_and_timed() {
+    let snapshot = with_recorder(async {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/xrpc/com.atproto.server.createSession"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "error": "AuthenticationRequired",
+                "message": "Invalid identifier or password"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/xrpc/com.atproto.server.describeServer"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "did": "did:web:pds.test",
+                "availableUserDomains": []
+            })))
+            .mount(&server)
+            .await;
+
+        let pds = XrpcPds::new(PdsUrl::new(server.uri()).unwrap());
+        pds.describe().await.unwrap();
+        pds.describe().await.unwrap();
+        assert!(
+            pds.login(Credentials::new("alice.test", "wrong"))
+                .await
+                .is_err()
+        );
+    });
+
+    let describe = "com.atproto.server.describeServer";
+    let login = "com.atproto.server.createSession";
+    assert_eq!(
+        value(
+            &snapshot,
+            "muat_xrpc_requests_total",
+            &[("method", describe), ("status", "200")]
+        ),
+        Some(&DebugValue::Counter(2))
+    );
+    assert_eq!(
+        value(
+            &snapshot,
+            "muat_xrpc_requests_total",
+            &[("method", login), ("status", "401")]
+        ),
+        Some(&DebugValue::Counter(1))
+    );
+    assert_eq!(
+        value(
+            &snapshot,
+            "muat_xrpc_errors_total",
+            &[("method", login), ("error", "AuthenticationRequired")]
+        ),
+        Some(&DebugValue::Counter(1))
+    );
+    match value(
+        &snapshot,
+        "muat_xrpc_request_duration_seconds",
+        &[("method", describe)],
+    ) {
+        Some(DebugValue::Histogram(samples)) => assert_eq!(samples.len(), 2),
+        other => panic!("expected a latency histogram, got {other:?}"),
+    }
+    assert_eq!(
+        value(&snapshot, "muat_xrpc_errors_total", &[("method", describe)]),
+        None
+    );
+}
+
+#[test]
+fn test_failed_firehose_connections_are_counted() {
+    let snapshot = with_recorder(async {
+        // Bind and drop a listener to find a port nothing is listening on.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let pds = PdsUrl::new(format!("http://127.0.0.1:{port}")).unwrap();
+        assert!(XrpcFirehose::from_websocket(&pds, None).await.is_err());
+    });
+
+    assert_eq!(
+        value(
+            &snapshot,
+            "muat_firehose_connections_total",
+            &[("source", "firehose"), ("result", "error")]
+        ),
+        Some(&DebugValue::Counter(1))
+    );
+}
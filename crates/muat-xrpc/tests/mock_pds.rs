@@ -3,11 +3,23 @@
 //! These tests use wiremock to simulate a PDS server and test the library's
 //! behavior without requiring network access or real credentials.
 
-use muat_core::{AtUri, Credentials, Nsid, Pds, PdsUrl, Session};
-use muat_xrpc::XrpcPds;
+use std::time::Duration;
+
+use futures_util::StreamExt;
+
+use muat_core::error::{AuthError, TransportError};
+use muat_core::repo::{dag_cbor_cid, to_dag_cbor, verify_inclusion};
+use muat_core::testing::check_list_records_order;
+use muat_core::{
+    AtUri, ByteRange, Cid, Credentials, DeleteCollectionOptions, Did, Error, ListRecordsOptions,
+    Nsid, Pds, PdsUrl, RecordValue, Session, Tid,
+};
+use muat_xrpc::{HttpMethod, HttpRequest, HttpResponse, HttpTransport, XrpcPds};
 use serde_json::json;
-use wiremock::matchers::{body_json, header, method, path};
-use wiremock::{Mock, MockServer, ResponseTemplate};
+use wiremock::matchers::{
+    body_bytes, body_json, body_partial_json, header, headers, method, path, query_param,
+};
+use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
 
 /// Helper to create a PDS URL from a mock server.
 fn mock_pds_url(server: &MockServer) -> PdsUrl {
@@ -30,7 +42,7 @@ async fn test_login_success() {
             "password": "secret123"
         })))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "did": "did:plc:test123",
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
             "handle": "alice.test",
             "accessJwt": "test-access-token",
             "refreshJwt": "test-refresh-token"
@@ -42,7 +54,63 @@ async fn test_login_success() {
     let credentials = Credentials::new("alice.test", "secret123");
     let session = pds.login(credentials).await.unwrap();
 
-    assert_eq!(session.did().as_str(), "did:plc:test123");
+    assert_eq!(session.did().as_str(), "did:plc:test234aaaaaaaaaaaaaaaaa");
+}
+
+#[tokio::test]
+async fn test_login_with_email_identifier() {
+    let server = MockServer::start().await;
+
+    // Emails are sent as given, including plus addressing and case.
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .and(body_json(json!({
+            "identifier": "Alice+bsky@Example.com",
+            "password": "secret123"
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "email": "alice+bsky@example.com",
+            "accessJwt": "test-access-token",
+            "refreshJwt": "test-refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let session = pds
+        .login(Credentials::new("Alice+bsky@Example.com", "secret123"))
+        .await
+        .unwrap();
+
+    assert_eq!(session.did().as_str(), "did:plc:test234aaaaaaaaaaaaaaaaa");
+}
+
+#[tokio::test]
+async fn test_login_with_path_prefix() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/pds/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "test-access-token",
+            "refreshJwt": "test-refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    let pds_url =
+        PdsUrl::new(format!("http://127.0.0.1:{}/pds/", server.address().port())).unwrap();
+    let pds = XrpcPds::new(pds_url);
+    let session = pds
+        .login(Credentials::new("alice.test", "secret123"))
+        .await
+        .unwrap();
+
+    assert_eq!(session.did().as_str(), "did:plc:test234aaaaaaaaaaaaaaaaa");
 }
 
 #[tokio::test]
@@ -67,6 +135,70 @@ async fn test_login_invalid_credentials() {
     assert!(err.contains("401"));
 }
 
+#[tokio::test]
+async fn test_login_reports_account_status() {
+    let cases = [
+        (
+            "AccountTakedown",
+            "Account has been taken down",
+            "account taken down",
+        ),
+        (
+            "AccountSuspended",
+            "Account is suspended",
+            "account suspended",
+        ),
+        (
+            "AccountTakedown",
+            "Account has been suspended until 2030-01-01",
+            "account suspended",
+        ),
+    ];
+
+    for (code, message, expected) in cases {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/xrpc/com.atproto.server.createSession"))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "error": code,
+                "message": message
+            })))
+            .mount(&server)
+            .await;
+
+        let pds = XrpcPds::new(mock_pds_url(&server));
+        match pds.login(Credentials::new("alice.test", "secret")).await {
+            Err(Error::Auth(e)) => assert!(e.to_string().starts_with(expected), "{}", e),
+            other => panic!("expected an auth error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_login_reports_rate_limit() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("retry-after", "120")
+                .set_body_json(json!({
+                    "error": "RateLimitExceeded",
+                    "message": "Rate Limit Exceeded"
+                })),
+        )
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    match pds.login(Credentials::new("alice.test", "wrong")).await {
+        Err(Error::Auth(AuthError::RateLimited { retry_after })) => {
+            assert_eq!(retry_after, Some(Duration::from_secs(120)));
+        }
+        other => panic!("expected a rate limit error, got {:?}", other.map(|_| ())),
+    }
+}
+
 #[tokio::test]
 async fn test_session_refresh_success() {
     let server = MockServer::start().await;
@@ -75,7 +207,7 @@ async fn test_session_refresh_success() {
     Mock::given(method("POST"))
         .and(path("/xrpc/com.atproto.server.createSession"))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "did": "did:plc:test123",
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
             "handle": "alice.test",
             "accessJwt": "old-access-token",
             "refreshJwt": "old-refresh-token"
@@ -88,7 +220,7 @@ async fn test_session_refresh_success() {
         .and(path("/xrpc/com.atproto.server.refreshSession"))
         .and(header("authorization", "Bearer old-refresh-token"))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "did": "did:plc:test123",
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
             "handle": "alice.test",
             "accessJwt": "new-access-token",
             "refreshJwt": "new-refresh-token"
@@ -115,7 +247,7 @@ async fn test_session_refresh_expired_token() {
     Mock::given(method("POST"))
         .and(path("/xrpc/com.atproto.server.createSession"))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "did": "did:plc:test123",
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
             "handle": "alice.test",
             "accessJwt": "access-token",
             "refreshJwt": "expired-refresh-token"
@@ -151,7 +283,7 @@ async fn test_list_records_success() {
     Mock::given(method("POST"))
         .and(path("/xrpc/com.atproto.server.createSession"))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "did": "did:plc:test123",
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
             "handle": "alice.test",
             "accessJwt": "access-token",
             "refreshJwt": "refresh-token"
@@ -165,12 +297,12 @@ async fn test_list_records_success() {
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
             "records": [
                 {
-                    "uri": "at://did:plc:test123/org.test.record/abc123",
+                    "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/abc123",
                     "cid": "bafytest1",
                     "value": {"$type": "org.test.record", "text": "Hello, world!"}
                 },
                 {
-                    "uri": "at://did:plc:test123/org.test.record/def456",
+                    "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/def456",
                     "cid": "bafytest2",
                     "value": {"$type": "org.test.record", "text": "Another record"}
                 }
@@ -213,7 +345,7 @@ async fn test_list_records_empty() {
     Mock::given(method("POST"))
         .and(path("/xrpc/com.atproto.server.createSession"))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "did": "did:plc:test123",
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
             "handle": "alice.test",
             "accessJwt": "access-token",
             "refreshJwt": "refresh-token"
@@ -245,6 +377,153 @@ async fn test_list_records_empty() {
     assert!(result.cursor.is_none());
 }
 
+#[tokio::test]
+async fn test_public_reads_need_no_session() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.identity.resolveHandle"))
+        .and(query_param("handle", "alice.test"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.repo.getRecord"))
+        .and(query_param("rkey", "abc123"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/abc123",
+            "cid": "bafytest1",
+            "value": {"$type": "org.test.record", "text": "public"}
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.repo.listRecords"))
+        .and(query_param("repo", "did:plc:test234aaaaaaaaaaaaaaaaa"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "records": [{
+                "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/abc123",
+                "cid": "bafytest1",
+                "value": {"$type": "org.test.record", "text": "public"}
+            }]
+        })))
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let did = pds.resolve_handle("alice.test").await.unwrap();
+    assert_eq!(did.as_str(), "did:plc:test234aaaaaaaaaaaaaaaaa");
+
+    let uri = AtUri::new("at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/abc123").unwrap();
+    let record = pds.get_record_public(&uri).await.unwrap();
+    assert_eq!(record.value.get("text").unwrap(), "public");
+
+    let collection = Nsid::new("org.test.record").unwrap();
+    let page = pds
+        .list_records_public(&did, &collection, &ListRecordsOptions::new())
+        .await
+        .unwrap();
+    assert_eq!(page.records.len(), 1);
+    assert_eq!(page.records[0].uri, uri);
+
+    let requests = server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 3);
+    assert!(
+        requests
+            .iter()
+            .all(|request| !request.headers.contains_key("authorization"))
+    );
+}
+
+/// Serves listRecords like the reference PDS: descending rkeys unless
+/// `reverse=true`, with the last rkey of a full page as the cursor.
+struct ReferenceListRecords {
+    rkeys: Vec<&'static str>,
+}
+
+impl Respond for ReferenceListRecords {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let param = |name: &str| {
+            request
+                .url
+                .query_pairs()
+                .find(|(k, _)| k == name)
+                .map(|(_, v)| v.into_owned())
+        };
+        let reverse = param("reverse").as_deref() == Some("true");
+        let limit = param("limit").and_then(|l| l.parse().ok()).unwrap_or(50);
+        let cursor = param("cursor");
+
+        let mut rkeys = self.rkeys.clone();
+        if !reverse {
+            rkeys.reverse();
+        }
+        let page: Vec<&str> = rkeys
+            .into_iter()
+            .filter(|rkey| match &cursor {
+                Some(c) if reverse => *rkey > c.as_str(),
+                Some(c) => *rkey < c.as_str(),
+                None => true,
+            })
+            .take(limit)
+            .collect();
+
+        let records: Vec<_> = page
+            .iter()
+            .map(|rkey| {
+                json!({
+                    "uri": format!("at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/{rkey}"),
+                    "cid": "bafytest",
+                    "value": {"$type": "org.test.record"}
+                })
+            })
+            .collect();
+        let cursor = (page.len() == limit)
+            .then(|| page.last().copied())
+            .flatten();
+
+        ResponseTemplate::new(200).set_body_json(json!({ "records": records, "cursor": cursor }))
+    }
+}
+
+#[tokio::test]
+async fn test_list_records_order_conformance() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    let rkeys = vec!["3kaaa", "3kbbb", "3kccc", "3kddd"];
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.repo.listRecords"))
+        .respond_with(ReferenceListRecords {
+            rkeys: rkeys.clone(),
+        })
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+
+    let collection = Nsid::new("org.test.record").unwrap();
+    check_list_records_order(&session, session.did(), &collection, &rkeys).await;
+}
+
 #[tokio::test]
 async fn test_create_record_success() {
     let server = MockServer::start().await;
@@ -252,7 +531,7 @@ async fn test_create_record_success() {
     Mock::given(method("POST"))
         .and(path("/xrpc/com.atproto.server.createSession"))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "did": "did:plc:test123",
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
             "handle": "alice.test",
             "accessJwt": "access-token",
             "refreshJwt": "refresh-token"
@@ -264,7 +543,7 @@ async fn test_create_record_success() {
         .and(path("/xrpc/com.atproto.repo.createRecord"))
         .and(header("authorization", "Bearer access-token"))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "uri": "at://did:plc:test123/org.test.record/newrecord123",
+            "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/newrecord123",
             "cid": "bafynewrecord"
         })))
         .mount(&server)
@@ -286,7 +565,7 @@ async fn test_create_record_success() {
         .await
         .unwrap();
 
-    assert_eq!(uri.rkey().as_str(), "newrecord123");
+    assert_eq!(uri.rkey().unwrap().as_str(), "newrecord123");
 }
 
 #[tokio::test]
@@ -296,7 +575,7 @@ async fn test_delete_record_success() {
     Mock::given(method("POST"))
         .and(path("/xrpc/com.atproto.server.createSession"))
         .respond_with(ResponseTemplate::new(200).set_body_json(json!({
-            "did": "did:plc:test123",
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
             "handle": "alice.test",
             "accessJwt": "access-token",
             "refreshJwt": "refresh-token"
@@ -317,55 +596,1638 @@ async fn test_delete_record_success() {
         .await
         .unwrap();
 
-    let uri = AtUri::new("at://did:plc:test123/org.test.record/todelete").unwrap();
+    let uri = AtUri::new("at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/todelete").unwrap();
     let result = session.delete_record(&uri).await;
 
     assert!(result.is_ok());
 }
 
-// ============================================================================
-// Error Handling Tests
-// ============================================================================
+#[tokio::test]
+async fn test_get_record_at_sends_cid() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.repo.getRecord"))
+        .and(query_param("cid", "bafycurrent"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/abc123",
+            "cid": "bafycurrent",
+            "value": {"$type": "org.test.record", "text": "current"}
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.repo.getRecord"))
+        .and(query_param("cid", "bafyolder"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "error": "RecordNotFound",
+            "message": "Could not locate record"
+        })))
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+    let uri = AtUri::new("at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/abc123").unwrap();
+
+    let record = session.get_record_at(&uri, "bafycurrent").await.unwrap();
+    assert_eq!(record.value.get("text").unwrap(), "current");
+
+    let err = pds.get_record_at(&uri, "bafyolder").await.unwrap_err();
+    assert!(
+        matches!(err, Error::Protocol(ref e) if e.is_not_found()),
+        "{err}"
+    );
+}
 
 #[tokio::test]
-async fn test_non_json_error_response() {
+async fn test_iter_all_records_reads_every_collection() {
     let server = MockServer::start().await;
 
     Mock::given(method("POST"))
         .and(path("/xrpc/com.atproto.server.createSession"))
-        .respond_with(
-            ResponseTemplate::new(500)
-                .set_body_string("Internal Server Error")
-                .insert_header("content-type", "text/plain"),
-        )
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.repo.describeRepo"))
+        .and(query_param("repo", "did:plc:test234aaaaaaaaaaaaaaaaa"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "handleIsCorrect": true,
+            "collections": ["org.test.second", "org.test.first"]
+        })))
         .mount(&server)
         .await;
 
+    for (collection, rkeys) in [
+        ("org.test.first", &["a", "b"][..]),
+        ("org.test.second", &["c"]),
+    ] {
+        let records: Vec<_> = rkeys
+            .iter()
+            .map(|rkey| {
+                json!({
+                    "uri": format!("at://did:plc:test234aaaaaaaaaaaaaaaaa/{}/{}", collection, rkey),
+                    "cid": "bafytest1",
+                    "value": {"$type": collection}
+                })
+            })
+            .collect();
+        Mock::given(method("GET"))
+            .and(path("/xrpc/com.atproto.repo.listRecords"))
+            .and(query_param("collection", collection))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "records": records })))
+            .mount(&server)
+            .await;
+    }
+
     let pds = XrpcPds::new(mock_pds_url(&server));
-    let credentials = Credentials::new("alice.test", "secret");
-    let result = pds.login(credentials).await;
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
 
-    assert!(result.is_err());
-    // Should handle non-JSON error gracefully
-    let err = result.unwrap_err().to_string();
-    assert!(err.contains("500"));
+    let collections = session.list_collections(session.did()).await.unwrap();
+    assert_eq!(
+        collections.iter().map(Nsid::as_str).collect::<Vec<_>>(),
+        ["org.test.first", "org.test.second"]
+    );
+
+    let rkeys: Vec<String> = session
+        .iter_all_records(session.did())
+        .map(|record| record.unwrap().uri.rkey().unwrap().to_string())
+        .collect()
+        .await;
+    assert_eq!(rkeys, ["a", "b", "c"]);
 }
 
 #[tokio::test]
-async fn test_empty_error_response() {
+async fn test_create_records_bulk_uses_apply_writes() {
     let server = MockServer::start().await;
 
     Mock::given(method("POST"))
         .and(path("/xrpc/com.atproto.server.createSession"))
-        .respond_with(ResponseTemplate::new(503))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.applyWrites"))
+        .and(body_partial_json(json!({
+            "repo": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "writes": [
+                {"$type": "com.atproto.repo.applyWrites#create", "collection": "org.test.record"},
+                {"$type": "com.atproto.repo.applyWrites#create", "collection": "org.test.record"}
+            ]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "commit": {"cid": "bafycommit", "rev": "rev1"},
+            "results": [
+                {"$type": "com.atproto.repo.applyWrites#createResult", "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/a", "cid": "bafya"},
+                {"$type": "com.atproto.repo.applyWrites#createResult", "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/b", "cid": "bafyb"}
+            ]
+        })))
+        .expect(1)
         .mount(&server)
         .await;
 
     let pds = XrpcPds::new(mock_pds_url(&server));
-    let credentials = Credentials::new("alice.test", "secret");
-    let result = pds.login(credentials).await;
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
 
-    assert!(result.is_err());
-    let err = result.unwrap_err().to_string();
-    assert!(err.contains("503"));
+    let collection = Nsid::new("org.test.record").unwrap();
+    let values = vec![
+        RecordValue::with_type("org.test.record", json!({"n": 1})).unwrap(),
+        RecordValue::with_type("org.test.record", json!({"n": 2})).unwrap(),
+    ];
+    let report = session
+        .create_records_bulk(&collection, values, 4, None)
+        .await;
+
+    assert!(report.is_complete());
+    let rkeys: Vec<String> = report
+        .succeeded
+        .iter()
+        .map(|uri| uri.rkey().unwrap().as_str().to_string())
+        .collect();
+    assert_eq!(rkeys, ["a", "b"]);
+}
+
+#[tokio::test]
+async fn test_create_records_bulk_falls_back_to_create_record() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.applyWrites"))
+        .respond_with(ResponseTemplate::new(501).set_body_json(json!({
+            "error": "MethodNotImplemented",
+            "message": "Method Not Implemented"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.createRecord"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/single",
+            "cid": "bafysingle"
+        })))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+
+    let collection = Nsid::new("org.test.record").unwrap();
+    let values = (0..3)
+        .map(|n| RecordValue::with_type("org.test.record", json!({ "n": n })).unwrap())
+        .collect();
+    let report = session
+        .create_records_bulk(&collection, values, 2, None)
+        .await;
+
+    assert_eq!(report.succeeded.len(), 3);
+    assert!(report.is_complete());
+}
+
+#[tokio::test]
+async fn test_create_records_bulk_reports_failed_values() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.applyWrites"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "error": "InvalidRequest",
+            "message": "Record is too large"
+        })))
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+
+    let collection = Nsid::new("org.test.record").unwrap();
+    let values: Vec<RecordValue> = (0..2)
+        .map(|n| RecordValue::with_type("org.test.record", json!({ "n": n })).unwrap())
+        .collect();
+    let report = session
+        .create_records_bulk(&collection, values.clone(), 2, None)
+        .await;
+
+    // The call is atomic, so both values fail with its error.
+    assert!(report.succeeded.is_empty());
+    let failed: Vec<&RecordValue> = report.failed.iter().map(|(value, _)| value).collect();
+    assert_eq!(failed, values.iter().collect::<Vec<_>>());
+    assert!(matches!(
+        &report.failed[0].1,
+        Error::Protocol(e) if e.status == 400
+    ));
+}
+
+#[tokio::test]
+async fn test_delete_collection_uses_apply_writes() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.repo.listRecords"))
+        .and(query_param("cursor", "b"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"records": []})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.repo.listRecords"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "records": [
+                {
+                    "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/a",
+                    "cid": "bafya",
+                    "value": {"$type": "org.test.record"}
+                },
+                {
+                    "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/b",
+                    "cid": "bafyb",
+                    "value": {"$type": "org.test.record"}
+                }
+            ],
+            "cursor": "b"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.applyWrites"))
+        .and(body_partial_json(json!({
+            "repo": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "writes": [
+                {"$type": "com.atproto.repo.applyWrites#delete", "collection": "org.test.record", "rkey": "a"},
+                {"$type": "com.atproto.repo.applyWrites#delete", "collection": "org.test.record", "rkey": "b"}
+            ]
+        })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "commit": {"cid": "bafycommit", "rev": "rev1"},
+            "results": [
+                {"$type": "com.atproto.repo.applyWrites#deleteResult"},
+                {"$type": "com.atproto.repo.applyWrites#deleteResult"}
+            ]
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.deleteRecord"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+
+    let collection = Nsid::new("org.test.record").unwrap();
+    let report = session
+        .delete_collection(session.did(), &collection, &DeleteCollectionOptions::new())
+        .await
+        .unwrap();
+
+    assert_eq!(report.scanned, 2);
+    assert_eq!(report.deleted, 2);
+    assert!(report.is_complete());
+}
+
+#[tokio::test]
+async fn test_upload_and_get_blob() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.uploadBlob"))
+        .and(header("content-type", "image/png"))
+        .and(header("authorization", "Bearer access-token"))
+        .and(body_bytes(b"png-bytes".to_vec()))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "blob": {
+                "$type": "blob",
+                "ref": {"$link": "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e"},
+                "mimeType": "image/png",
+                "size": 9
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.sync.getBlob"))
+        .and(query_param("did", "did:plc:test234aaaaaaaaaaaaaaaaa"))
+        .and(query_param(
+            "cid",
+            "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(b"png-bytes".to_vec()))
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+
+    let blob = session
+        .upload_blob(b"png-bytes".to_vec(), "image/png")
+        .await
+        .unwrap();
+    assert_eq!(
+        blob.cid.as_str(),
+        "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e"
+    );
+    assert_eq!(blob.size, 9);
+
+    let data = session.get_blob(blob.cid.as_str()).await.unwrap();
+    assert_eq!(data, b"png-bytes");
+}
+
+#[tokio::test]
+async fn test_get_blob_range_sends_range_header() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.sync.getBlob"))
+        .and(header("range", "bytes=2-4"))
+        .respond_with(
+            ResponseTemplate::new(206)
+                .insert_header("content-range", "bytes 2-4/10")
+                .set_body_bytes(b"234".to_vec()),
+        )
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.sync.getBlob"))
+        .and(header("range", "bytes=10-"))
+        .respond_with(ResponseTemplate::new(416).insert_header("content-range", "bytes */10"))
+        .mount(&server)
+        .await;
+
+    // A server that ignores ranges sends the whole blob.
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.sync.getBlob"))
+        .and(header("range", "bytes=7-"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(b"0123456789".to_vec()))
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+    let cid = "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e";
+
+    let data = session
+        .get_blob_range(cid, ByteRange::new(2, Some(3)))
+        .await
+        .unwrap();
+    assert_eq!(data, b"234");
+
+    // Resuming a download that already finished reads nothing.
+    let data = session
+        .get_blob_range(cid, ByteRange::from_offset(10))
+        .await
+        .unwrap();
+    assert!(data.is_empty());
+
+    let data = session
+        .get_blob_range(cid, ByteRange::from_offset(7))
+        .await
+        .unwrap();
+    assert_eq!(data, b"789");
+}
+
+/// A CARv1 file holding `blocks`, with `root` as its root if given.
+fn car_bytes(root: Option<&Cid>, blocks: &[&[u8]]) -> Vec<u8> {
+    let roots: Vec<_> = root.iter().map(|cid| json!({ "$link": cid })).collect();
+    let header = to_dag_cbor(&json!({ "version": 1, "roots": roots })).unwrap();
+    let sections = std::iter::once(header).chain(
+        blocks
+            .iter()
+            .map(|block| [dag_cbor_cid(block).to_bytes(), block.to_vec()].concat()),
+    );
+    let mut car = Vec::new();
+    for section in sections {
+        // Section lengths are unsigned varints.
+        let mut len = section.len();
+        while len >= 0x80 {
+            car.push((len as u8 & 0x7f) | 0x80);
+            len >>= 7;
+        }
+        car.push(len as u8);
+        car.extend(section);
+    }
+    car
+}
+
+#[tokio::test]
+async fn test_sync_car_endpoints_need_no_session() {
+    let server = MockServer::start().await;
+    let did = Did::new("did:plc:test234aaaaaaaaaaaaaaaaa").unwrap();
+    let record = to_dag_cbor(&json!({ "text": "hello" })).unwrap();
+    let commit = to_dag_cbor(&json!({ "did": did.as_str(), "rev": "3k2a" })).unwrap();
+    let (record_cid, commit_cid) = (dag_cbor_cid(&record), dag_cbor_cid(&commit));
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.sync.getBlocks"))
+        .and(query_param("did", did.as_str()))
+        .and(query_param("cids", record_cid.as_str()))
+        .and(query_param("cids", commit_cid.as_str()))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "application/vnd.ipld.car")
+                .set_body_bytes(car_bytes(None, &[&record, &commit])),
+        )
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.sync.getRepo"))
+        .and(query_param("did", did.as_str()))
+        .and(query_param("since", "3jzfcijpj2z2a"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_bytes(car_bytes(Some(&commit_cid), &[&commit])),
+        )
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let car = pds
+        .get_blocks(&did, &[record_cid.clone(), commit_cid.clone()])
+        .await
+        .unwrap();
+    assert!(car.roots().is_empty());
+    let cids: Vec<_> = car.blocks().map(|block| block.unwrap().cid).collect();
+    assert_eq!(cids, [record_cid.clone(), commit_cid.clone()]);
+    let block = car.get(&record_cid).unwrap().unwrap();
+    assert_eq!(block.decode().unwrap(), json!({ "text": "hello" }));
+
+    let since = Tid::new("3jzfcijpj2z2a").unwrap();
+    let car = pds.get_repo_since(&did, Some(&since)).await.unwrap();
+    assert_eq!(car.roots(), [commit_cid]);
+    assert_eq!(car.blocks().count(), 1);
+}
+
+#[tokio::test]
+async fn test_get_record_proof_verifies_against_the_commit() {
+    let server = MockServer::start().await;
+    let uri = AtUri::new("at://did:plc:test234aaaaaaaaaaaaaaaaa/app.bsky.feed.post/3k2a").unwrap();
+    let record = to_dag_cbor(&json!({ "$type": "app.bsky.feed.post", "text": "hi" })).unwrap();
+    let record_cid = dag_cbor_cid(&record);
+    let node = to_dag_cbor(&json!({
+        "l": null,
+        "e": [{
+            "p": 0,
+            "k": { "$bytes": "YXBwLmJza3kuZmVlZC5wb3N0LzNrMmE" }, // app.bsky.feed.post/3k2a
+            "v": { "$link": record_cid },
+            "t": null,
+        }],
+    }))
+    .unwrap();
+    let commit = to_dag_cbor(&json!({
+        "did": uri.repo().as_str(),
+        "version": 3,
+        "data": { "$link": dag_cbor_cid(&node) },
+        "rev": "3k2a",
+        "prev": null,
+        "sig": { "$bytes": "AAAA" },
+    }))
+    .unwrap();
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.sync.getRecord"))
+        .and(query_param("did", uri.repo().as_str()))
+        .and(query_param("collection", "app.bsky.feed.post"))
+        .and(query_param("rkey", "3k2a"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(car_bytes(
+            Some(&dag_cbor_cid(&commit)),
+            &[&commit, &node, &record],
+        )))
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let car = pds.get_record_proof(&uri).await.unwrap();
+    let proof = verify_inclusion(&car, &uri).unwrap();
+    assert_eq!(proof.cid, Some(record_cid));
+    assert_eq!(proof.commit.rev, "3k2a");
+    let record = proof.record.unwrap();
+    assert_eq!(record.value.get("text"), Some(&json!("hi")));
+}
+
+#[tokio::test]
+async fn test_binary_xrpc_calls_keep_bodies_and_content_types() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    // Not valid UTF-8, so nothing may decode it on the way through.
+    let car = vec![0x3a, 0xa2, 0x65, 0xff, 0x00, 0xfe];
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.sync.getRepo"))
+        .and(query_param("did", "did:plc:test234aaaaaaaaaaaaaaaaa"))
+        .and(header("authorization", "Bearer access-token"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "application/vnd.ipld.car")
+                .set_body_bytes(car.clone()),
+        )
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.importRepo"))
+        .and(header("content-type", "application/vnd.ipld.car"))
+        .and(body_bytes(car.clone()))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"{"imported":true}"#.as_bytes().to_vec(),
+            "application/json; charset=utf-8",
+        ))
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+
+    let get_repo = Nsid::new("com.atproto.sync.getRepo").unwrap();
+    let response = session
+        .query_binary(&get_repo, &[("did", session.did().as_str())])
+        .await
+        .unwrap();
+    assert_eq!(response.body, car);
+    assert_eq!(
+        response.mime_type().as_deref(),
+        Some("application/vnd.ipld.car")
+    );
+    assert!(!response.is_json());
+
+    let import_repo = Nsid::new("com.atproto.repo.importRepo").unwrap();
+    let response = session
+        .procedure_binary(&import_repo, car, "application/vnd.ipld.car")
+        .await
+        .unwrap();
+    assert!(response.is_json());
+    let output: serde_json::Value = response.json().unwrap();
+    assert_eq!(output, json!({ "imported": true }));
+}
+
+#[tokio::test]
+async fn test_raw_xrpc_calls_attach_access_token() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.identity.resolveHandle"))
+        .and(query_param("handle", "bob.test"))
+        .and(header("authorization", "Bearer access-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:bobaaaaaaaaaaaaaaaaaaaaa"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/org.example.echo"))
+        .and(header("authorization", "Bearer access-token"))
+        .and(body_json(json!({"text": "hi"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"text": "hi"})))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/org.example.notify"))
+        .and(header("authorization", "Bearer access-token"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+
+    let resolve = Nsid::new("com.atproto.identity.resolveHandle").unwrap();
+    let output: serde_json::Value = session
+        .xrpc_query(&resolve, &[("handle", "bob.test")])
+        .await
+        .unwrap();
+    assert_eq!(output["did"], "did:plc:bobaaaaaaaaaaaaaaaaaaaaa");
+
+    let echo = Nsid::new("org.example.echo").unwrap();
+    let output: serde_json::Value = session
+        .xrpc_procedure(&echo, &json!({"text": "hi"}))
+        .await
+        .unwrap();
+    assert_eq!(output["text"], "hi");
+
+    let notify = Nsid::new("org.example.notify").unwrap();
+    session
+        .xrpc_procedure_no_response(&notify, &json!({}))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_describe_server_and_validate_session() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.server.describeServer"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:web:pds.test",
+            "availableUserDomains": [".pds.test"],
+            "phoneVerificationRequired": true,
+            "links": { "termsOfService": "https://pds.test/tos" },
+            "contact": { "email": "admin@pds.test" }
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.server.getSession"))
+        .and(header("authorization", "Bearer good-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.server.getSession"))
+        .and(header("authorization", "Bearer stale-token"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+            "error": "ExpiredToken",
+            "message": "Token has expired"
+        })))
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let description = pds.describe().await.unwrap();
+    assert_eq!(description.did, "did:web:pds.test");
+    assert_eq!(description.available_user_domains, vec![".pds.test"]);
+    assert!(!description.invite_code_required);
+    assert!(description.phone_verification_required);
+    assert_eq!(
+        description.links.terms_of_service.as_deref(),
+        Some("https://pds.test/tos")
+    );
+    assert_eq!(description.links.privacy_policy, None);
+    assert_eq!(description.contact_email.as_deref(), Some("admin@pds.test"));
+
+    let session = |token: &str| {
+        muat_xrpc::XrpcSession::from_persisted_with_pds(
+            pds.clone(),
+            muat_core::Did::new("did:plc:test234aaaaaaaaaaaaaaaaa").unwrap(),
+            muat_core::AccessToken::new(token),
+            None,
+        )
+        .unwrap()
+    };
+    session("good-token").validate().await.unwrap();
+    assert!(session("stale-token").validate().await.is_err());
+}
+
+#[tokio::test]
+async fn test_create_account_checks_custom_user_domains() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.server.describeServer"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:web:pds.example.org",
+            "availableUserDomains": [".pds.example.org"]
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createAccount"))
+        .and(body_partial_json(
+            json!({ "handle": "alice.pds.example.org" }),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.pds.example.org",
+            "accessJwt": "access",
+            "refreshJwt": "refresh"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+
+    // Rejected before createAccount is called.
+    for handle in ["alice.bsky.social", "alice"] {
+        let err = pds
+            .create_account(handle, Some("secret"), None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidInput(_)), "{:?}", err);
+    }
+    assert_eq!(
+        pds.check_new_handle("Bob.PDS.example.org")
+            .await
+            .unwrap()
+            .as_str(),
+        "bob.pds.example.org"
+    );
+
+    let created = pds
+        .create_account("Alice.pds.example.org", Some("secret"), None, None)
+        .await
+        .unwrap();
+    assert_eq!(created.handle, "alice.pds.example.org");
+}
+
+#[tokio::test]
+async fn test_create_account_requires_advertised_invite_code() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.server.describeServer"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:web:pds.example.org",
+            "availableUserDomains": [".pds.example.org"],
+            "inviteCodeRequired": true
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createAccount"))
+        .and(body_partial_json(
+            json!({ "inviteCode": "pds-example-org-abcde" }),
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.pds.example.org",
+            "accessJwt": "access",
+            "refreshJwt": "refresh"
+        })))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+
+    // Rejected before createAccount is called.
+    for invite_code in [None, Some("")] {
+        let err = pds
+            .create_account("alice.pds.example.org", Some("secret"), None, invite_code)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::InvalidInput(_)), "{:?}", err);
+        assert!(err.to_string().contains("invite code"), "{}", err);
+    }
+
+    pds.create_account(
+        "alice.pds.example.org",
+        Some("secret"),
+        None,
+        Some("pds-example-org-abcde"),
+    )
+    .await
+    .unwrap();
+}
+
+// ============================================================================
+// Error Handling Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_non_json_error_response() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(
+            ResponseTemplate::new(500)
+                .set_body_string("Internal Server Error")
+                .insert_header("content-type", "text/plain"),
+        )
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let credentials = Credentials::new("alice.test", "secret");
+    let result = pds.login(credentials).await;
+
+    assert!(result.is_err());
+    // Should handle non-JSON error gracefully
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("500"));
+}
+
+#[tokio::test]
+async fn test_empty_error_response() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let credentials = Credentials::new("alice.test", "secret");
+    let result = pds.login(credentials).await;
+
+    assert!(result.is_err());
+    let err = result.unwrap_err().to_string();
+    assert!(err.contains("503"));
+}
+
+// ============================================================================
+// Custom Transport Tests
+// ============================================================================
+
+/// Transport double that records requests and replies with a canned response.
+#[derive(Debug, Default)]
+struct RecordingTransport {
+    requests: std::sync::Mutex<Vec<HttpRequest>>,
+}
+
+#[async_trait::async_trait]
+impl HttpTransport for RecordingTransport {
+    async fn send(&self, request: HttpRequest) -> muat_core::Result<HttpResponse> {
+        self.requests.lock().unwrap().push(request);
+        let body = json!({
+            "did": "did:plc:transportaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "transport-access",
+            "refreshJwt": "transport-refresh"
+        });
+        Ok(HttpResponse::new(200, serde_json::to_vec(&body).unwrap()))
+    }
+}
+
+#[tokio::test]
+async fn test_login_with_custom_transport() {
+    let transport = std::sync::Arc::new(RecordingTransport::default());
+    let pds = XrpcPds::with_transport(
+        PdsUrl::new("https://pds.example.com").unwrap(),
+        transport.clone(),
+    );
+
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+    assert_eq!(session.did().as_str(), "did:plc:transportaaaaaaaaaaaaaaa");
+
+    let requests = transport.requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].method, HttpMethod::Post);
+    assert_eq!(
+        requests[0].url,
+        "https://pds.example.com/xrpc/com.atproto.server.createSession"
+    );
+    assert_eq!(requests[0].header("content-type"), Some("application/json"));
+}
+
+// ============================================================================
+// Middleware Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_header_provider_and_interceptor_apply_to_session_requests() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .and(header("atproto-proxy", "did:web:api.bsky.chat#bsky_chat"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/chat.bsky.convo.listConvos"))
+        .and(header("authorization", "Bearer access-token"))
+        .and(header("atproto-proxy", "did:web:api.bsky.chat#bsky_chat"))
+        .and(header("x-request-source", "muat-test"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"convos": []})))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server))
+        .with_header_provider(|| {
+            vec![(
+                "atproto-proxy".to_string(),
+                "did:web:api.bsky.chat#bsky_chat".to_string(),
+            )]
+        })
+        .with_interceptor(|request| {
+            request
+                .headers
+                .push(("x-request-source".to_string(), "muat-test".to_string()));
+        });
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+
+    let list = Nsid::new("chat.bsky.convo.listConvos").unwrap();
+    let output: serde_json::Value = session.xrpc_query(&list, &[("limit", "10")]).await.unwrap();
+    assert_eq!(output["convos"], json!([]));
+}
+
+#[tokio::test]
+async fn test_service_proxy_applies_to_xrpc_calls_only() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/app.bsky.actor.getProfile"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"handle": "bob.test"})))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.createRecord"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/abc",
+            "cid": "bafytest"
+        })))
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+    let appview = session
+        .with_service_proxy("did:web:api.bsky.app#bsky_appview")
+        .unwrap();
+    assert_eq!(
+        appview.service_proxy(),
+        Some("did:web:api.bsky.app#bsky_appview")
+    );
+    assert_eq!(session.service_proxy(), None);
+
+    let get_profile = Nsid::new("app.bsky.actor.getProfile").unwrap();
+    let _: serde_json::Value = appview
+        .xrpc_query(&get_profile, &[("actor", "bob.test")])
+        .await
+        .unwrap();
+    let _: serde_json::Value = session
+        .xrpc_query(&get_profile, &[("actor", "bob.test")])
+        .await
+        .unwrap();
+    let collection = Nsid::new("org.test.record").unwrap();
+    let value = RecordValue::with_type("org.test.record", json!({})).unwrap();
+    appview.create_record(&collection, &value).await.unwrap();
+
+    let proxies: Vec<(String, Option<String>)> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| {
+            (
+                request.url.path().to_string(),
+                request
+                    .headers
+                    .get("atproto-proxy")
+                    .map(|v| v.to_str().unwrap().to_string()),
+            )
+        })
+        .collect();
+    let proxied = Some("did:web:api.bsky.app#bsky_appview".to_string());
+    assert_eq!(
+        proxies[1..],
+        [
+            ("/xrpc/app.bsky.actor.getProfile".to_string(), proxied),
+            ("/xrpc/app.bsky.actor.getProfile".to_string(), None),
+            ("/xrpc/com.atproto.repo.createRecord".to_string(), None),
+        ]
+    );
+
+    for invalid in [
+        "did:web:api.bsky.app",
+        "api.bsky.app#bsky_appview",
+        "did:web:x#",
+    ] {
+        let err = session.with_service_proxy(invalid).unwrap_err();
+        assert!(matches!(err, Error::InvalidInput(_)), "{invalid}");
+    }
+}
+
+#[tokio::test]
+async fn test_accept_labelers_combine_with_service_proxy() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    let labelers = "did:plc:ar7c4by46qjdydhdevvrndac;redact, did:plc:newitj5jo3uel7o4mnf3vj2o";
+    Mock::given(method("GET"))
+        .and(path("/xrpc/app.bsky.actor.getProfile"))
+        .and(headers(
+            "atproto-accept-labelers",
+            vec![
+                "did:plc:ar7c4by46qjdydhdevvrndac;redact",
+                "did:plc:newitj5jo3uel7o4mnf3vj2o",
+            ],
+        ))
+        .and(header("atproto-proxy", "did:web:api.bsky.app#bsky_appview"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({"handle": "bob.test"})))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+    let entries = [
+        "did:plc:ar7c4by46qjdydhdevvrndac; redact",
+        "did:plc:newitj5jo3uel7o4mnf3vj2o",
+    ];
+
+    // Either order keeps both headers.
+    let labelled_first = session
+        .with_accept_labelers(&entries)
+        .unwrap()
+        .with_service_proxy("did:web:api.bsky.app#bsky_appview")
+        .unwrap();
+    let proxied_first = session
+        .with_service_proxy("did:web:api.bsky.app#bsky_appview")
+        .unwrap()
+        .with_accept_labelers(&entries)
+        .unwrap();
+    assert_eq!(labelled_first.accept_labelers(), Some(labelers));
+    assert_eq!(session.accept_labelers(), None);
+
+    let get_profile = Nsid::new("app.bsky.actor.getProfile").unwrap();
+    for handle in [labelled_first, proxied_first] {
+        let _: serde_json::Value = handle
+            .xrpc_query(&get_profile, &[("actor", "bob.test")])
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(
+        session.with_accept_labelers(&[]).unwrap().accept_labelers(),
+        None
+    );
+    for invalid in [
+        "ar7c4by46qjdydhdevvrndac",
+        "did:plc:ar7c4by46qjdydhdevvrndac;hide",
+    ] {
+        let err = session.with_accept_labelers(&[invalid]).unwrap_err();
+        assert!(matches!(err, Error::InvalidInput(_)), "{invalid}");
+    }
+}
+
+#[tokio::test]
+async fn test_read_after_write_sends_latest_repo_rev() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.createRecord"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/abc",
+            "cid": "bafytest",
+            "commit": {"cid": "bafycommit2", "rev": "3l2bbbbbbbbbb"}
+        })))
+        .mount(&server)
+        .await;
+
+    // A write reporting an older rev, as a reordered response might.
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.putRecord"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/abc",
+            "cid": "bafytest",
+            "commit": {"cid": "bafycommit1", "rev": "3l2aaaaaaaaaa"}
+        })))
+        .mount(&server)
+        .await;
+
+    // Older PDSes answer deleteRecord with no body.
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.deleteRecord"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.repo.getRecord"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/abc",
+            "cid": "bafytest",
+            "value": {"$type": "org.test.record"}
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/app.bsky.feed.getPostThread"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server));
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+    let tracked = session
+        .with_service_proxy("did:web:api.bsky.app#bsky_appview")
+        .unwrap()
+        .with_read_after_write();
+    let uri = AtUri::new("at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/abc").unwrap();
+    let collection = Nsid::new("org.test.record").unwrap();
+    let value = RecordValue::with_type("org.test.record", json!({})).unwrap();
+    let thread = Nsid::new("app.bsky.feed.getPostThread").unwrap();
+
+    tracked.get_record(&uri).await.unwrap();
+    assert_eq!(tracked.repo_rev(), None);
+    tracked.create_record(&collection, &value).await.unwrap();
+    assert_eq!(tracked.repo_rev().as_deref(), Some("3l2bbbbbbbbbb"));
+    let rkey = uri.rkey().unwrap();
+    tracked
+        .put_record(&collection, rkey, &value, None)
+        .await
+        .unwrap();
+    tracked.delete_record(&uri).await.unwrap();
+    assert_eq!(tracked.repo_rev().as_deref(), Some("3l2bbbbbbbbbb"));
+    tracked.get_record(&uri).await.unwrap();
+    let _: serde_json::Value = tracked
+        .xrpc_query(&thread, &[("uri", uri.to_string())])
+        .await
+        .unwrap();
+    session.get_record(&uri).await.unwrap();
+    assert_eq!(session.repo_rev(), None);
+
+    let sent: Vec<(String, Option<String>)> = server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| {
+            (
+                request.url.path().to_string(),
+                request
+                    .headers
+                    .get("atproto-accept-repo-rev")
+                    .map(|v| v.to_str().unwrap().to_string()),
+            )
+        })
+        .collect();
+    let rev = Some("3l2bbbbbbbbbb".to_string());
+    assert_eq!(
+        sent[1..],
+        [
+            ("/xrpc/com.atproto.repo.getRecord".to_string(), None),
+            ("/xrpc/com.atproto.repo.createRecord".to_string(), None),
+            ("/xrpc/com.atproto.repo.putRecord".to_string(), None),
+            ("/xrpc/com.atproto.repo.deleteRecord".to_string(), None),
+            ("/xrpc/com.atproto.repo.getRecord".to_string(), rev.clone()),
+            ("/xrpc/app.bsky.feed.getPostThread".to_string(), rev),
+            ("/xrpc/com.atproto.repo.getRecord".to_string(), None),
+        ]
+    );
+}
+
+/// Middleware that answers `describeServer` itself and counts the rest.
+#[derive(Debug, Default)]
+struct CachingMiddleware {
+    passed_through: std::sync::atomic::AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl muat_xrpc::Middleware for CachingMiddleware {
+    async fn handle(
+        &self,
+        request: HttpRequest,
+        next: muat_xrpc::Next<'_>,
+    ) -> muat_core::Result<HttpResponse> {
+        if request
+            .url
+            .ends_with("/xrpc/com.atproto.server.describeServer")
+        {
+            let body = json!({"did": "did:web:cached.test", "availableUserDomains": []});
+            return Ok(HttpResponse::new(200, serde_json::to_vec(&body).unwrap()));
+        }
+        self.passed_through
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        next.run(request).await
+    }
+}
+
+#[tokio::test]
+async fn test_middleware_can_answer_requests() {
+    let transport = std::sync::Arc::new(RecordingTransport::default());
+    let caching = std::sync::Arc::new(CachingMiddleware::default());
+    let pds = XrpcPds::with_transport(
+        PdsUrl::new("https://pds.example.com").unwrap(),
+        transport.clone(),
+    )
+    .with_middleware(caching.clone());
+
+    let description = pds.describe().await.unwrap();
+    assert_eq!(description.did, "did:web:cached.test");
+    assert!(transport.requests.lock().unwrap().is_empty());
+
+    pds.login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+    assert_eq!(transport.requests.lock().unwrap().len(), 1);
+    assert_eq!(
+        caching
+            .passed_through
+            .load(std::sync::atomic::Ordering::SeqCst),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_malformed_tokens_are_rejected_before_sending() {
+    let transport = std::sync::Arc::new(RecordingTransport::default());
+    let pds = XrpcPds::with_transport(
+        PdsUrl::new("https://pds.example.com").unwrap(),
+        transport.clone(),
+    );
+    let did = muat_core::Did::new("did:plc:test234aaaaaaaaaaaaaaaaa").unwrap();
+    let restore = |access: &str, refresh: Option<&str>| {
+        muat_xrpc::XrpcSession::from_persisted_with_pds(
+            pds.clone(),
+            did.clone(),
+            muat_core::AccessToken::new(access),
+            refresh.map(muat_core::RefreshToken::new),
+        )
+    };
+
+    for (access, refresh) in [
+        ("access\ntoken", None),
+        ("", None),
+        ("access-token", Some("refresh token")),
+    ] {
+        let err = restore(access, refresh).unwrap_err();
+        assert!(
+            matches!(err, Error::Auth(AuthError::MalformedToken(_))),
+            "{err}"
+        );
+        assert!(!err.to_string().contains("token\n"));
+    }
+    assert!(restore("access-token", Some("refresh-token")).is_ok());
+    assert!(transport.requests.lock().unwrap().is_empty());
+}
+
+/// Transport double that tracks the peak number of concurrent requests.
+#[derive(Debug, Default)]
+struct SlowTransport {
+    in_flight: std::sync::atomic::AtomicUsize,
+    peak: std::sync::atomic::AtomicUsize,
+}
+
+#[async_trait::async_trait]
+impl HttpTransport for SlowTransport {
+    async fn send(&self, _request: HttpRequest) -> muat_core::Result<HttpResponse> {
+        use std::sync::atomic::Ordering;
+
+        let current = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.peak.fetch_max(current, Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        let body = json!({
+            "uri": "at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/abc",
+            "cid": "bafytest",
+            "value": {"$type": "org.test.record"}
+        });
+        Ok(HttpResponse::new(200, serde_json::to_vec(&body).unwrap()))
+    }
+}
+
+#[tokio::test]
+async fn test_session_max_in_flight_caps_concurrency() {
+    let transport = std::sync::Arc::new(SlowTransport::default());
+    let pds = XrpcPds::with_transport(
+        PdsUrl::new("https://pds.example.com").unwrap(),
+        transport.clone(),
+    );
+    let session = muat_xrpc::XrpcSession::from_persisted_with_pds(
+        pds,
+        muat_core::Did::new("did:plc:test234aaaaaaaaaaaaaaaaa").unwrap(),
+        muat_core::AccessToken::new("access-token"),
+        None,
+    )
+    .unwrap()
+    .with_max_in_flight(2);
+
+    let uri = AtUri::new("at://did:plc:test234aaaaaaaaaaaaaaaaa/org.test.record/abc").unwrap();
+    let calls = (0..8).map(|_| session.get_record(&uri));
+    let results = futures_util::future::join_all(calls).await;
+
+    assert!(results.iter().all(|r| r.is_ok()));
+    assert_eq!(transport.peak.load(std::sync::atomic::Ordering::SeqCst), 2);
+    assert_eq!(session.available_permits(), Some(2));
+}
+
+// ============================================================================
+// Unix Socket Transport Tests
+// ============================================================================
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_login_over_unix_socket() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::UnixListener;
+
+    let dir = std::env::temp_dir().join(format!("muat-unix-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let socket = dir.join("pds.sock");
+    let _ = std::fs::remove_file(&socket);
+    let listener = UnixListener::bind(&socket).unwrap();
+
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 4096];
+        let n = stream.read(&mut buf).await.unwrap();
+        let request = String::from_utf8_lossy(&buf[..n]).to_string();
+
+        let body = json!({
+            "did": "did:plc:unix234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })
+        .to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+        request
+    });
+
+    let pds_url = PdsUrl::new(format!("unix://{}", socket.display())).unwrap();
+    let session = XrpcPds::new(pds_url)
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+
+    assert_eq!(session.did().as_str(), "did:plc:unix234aaaaaaaaaaaaaaaaa");
+
+    let request = server.await.unwrap();
+    assert!(request.starts_with("POST /xrpc/com.atproto.server.createSession HTTP/1.1"));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_timeouts_layer_over_pds_defaults() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.server.createSession"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "did": "did:plc:test234aaaaaaaaaaaaaaaaa",
+            "handle": "alice.test",
+            "accessJwt": "access-token",
+            "refreshJwt": "refresh-token"
+        })))
+        .mount(&server)
+        .await;
+
+    Mock::given(method("GET"))
+        .and(path("/xrpc/com.atproto.sync.getBlob"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_bytes(b"png-bytes".to_vec())
+                .set_delay(Duration::from_millis(300)),
+        )
+        .mount(&server)
+        .await;
+
+    Mock::given(method("POST"))
+        .and(path("/xrpc/com.atproto.repo.uploadBlob"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({
+                    "blob": {
+                        "$type": "blob",
+                        "ref": {"$link": "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e"},
+                        "mimeType": "image/png",
+                        "size": 9
+                    }
+                }))
+                .set_delay(Duration::from_millis(300)),
+        )
+        .mount(&server)
+        .await;
+
+    let pds = XrpcPds::new(mock_pds_url(&server))
+        .with_timeout(Duration::from_millis(50))
+        .with_endpoint_timeout("com.atproto.repo.uploadBlob", Duration::from_secs(10));
+    let session = pds
+        .login(Credentials::new("alice.test", "secret"))
+        .await
+        .unwrap();
+    let cid = "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e";
+
+    // The default applies where nothing more specific is set.
+    let err = session.get_blob(cid).await.unwrap_err();
+    assert!(
+        matches!(
+            err,
+            Error::Transport(TransportError::Timeout { duration_ms: 50 })
+        ),
+        "{err:?}"
+    );
+
+    // A per-endpoint timeout overrides the default.
+    session
+        .upload_blob(b"png-bytes".to_vec(), "image/png")
+        .await
+        .unwrap();
+
+    // A handle's timeout overrides both, without changing the session.
+    let patient = session.with_timeout(Duration::from_secs(10));
+    assert_eq!(patient.get_blob(cid).await.unwrap(), b"png-bytes");
+    let hasty = session.with_timeout(Duration::from_millis(50));
+    let err = hasty
+        .upload_blob(b"png-bytes".to_vec(), "image/png")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(
+            err,
+            Error::Transport(TransportError::Timeout { duration_ms: 50 })
+        ),
+        "{err:?}"
+    );
+    assert!(session.get_blob(cid).await.is_err());
 }
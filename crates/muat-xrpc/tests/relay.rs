@@ -0,0 +1,147 @@
+//! Relay client tests: crawl requests and cursor-tracking subscriptions.
+
+#![cfg(feature = "native-ws")]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{Value, json};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+
+use muat_core::repo::{RepoEvent, to_dag_cbor};
+use muat_core::{CursorStore, MemoryCursorStore, PdsUrl};
+use muat_xrpc::RelayClient;
+use wiremock::matchers::{body_json, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const RECORD_CID: &str = "bafyreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm";
+
+/// An event frame: the header `{"t": kind, "op": 1}`, then `body`.
+fn frame(kind: &str, body: Value) -> Vec<u8> {
+    let mut frame = to_dag_cbor(&json!({ "t": kind, "op": 1 })).unwrap();
+    frame.extend(to_dag_cbor(&body).unwrap());
+    frame
+}
+
+/// Serve one WebSocket connection that sends `frames` and closes,
+/// reporting the request URI it was opened with.
+async fn serve(frames: Vec<Vec<u8>>) -> (PdsUrl, oneshot::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (uri_tx, uri_rx) = oneshot::channel();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        // The handshake callback's error type is tungstenite's, not ours.
+        #[allow(clippy::result_large_err)]
+        let callback = |request: &Request, response: Response| {
+            let _ = uri_tx.send(request.uri().to_string());
+            Ok(response)
+        };
+        let mut socket = tokio_tungstenite::accept_hdr_async(stream, callback)
+            .await
+            .unwrap();
+        for frame in frames {
+            socket.send(Message::Binary(frame.into())).await.unwrap();
+        }
+        socket.close(None).await.unwrap();
+    });
+    (PdsUrl::new(format!("http://{}", addr)).unwrap(), uri_rx)
+}
+
+#[tokio::test]
+async fn test_request_crawl_and_notify_of_update() {
+    let server = MockServer::start().await;
+    for nsid in [
+        "com.atproto.sync.requestCrawl",
+        "com.atproto.sync.notifyOfUpdate",
+    ] {
+        Mock::given(method("POST"))
+            .and(path(format!("/xrpc/{}", nsid)))
+            .and(body_json(json!({ "hostname": "pds.example.com" })))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    let relay = RelayClient::new(
+        PdsUrl::new(format!("http://127.0.0.1:{}", server.address().port())).unwrap(),
+    );
+    relay.request_crawl("pds.example.com").await.unwrap();
+    relay.notify_of_update("pds.example.com").await.unwrap();
+}
+
+#[tokio::test]
+async fn test_subscribe_resumes_from_the_saved_cursor() {
+    let (relay_url, uri) = serve(vec![
+        frame(
+            "#identity",
+            json!({ "did": "did:plc:alice", "seq": 42, "time": "2024-01-01T00:00:00Z" }),
+        ),
+        frame(
+            "#commit",
+            json!({
+                "repo": "did:plc:alice",
+                "rev": "3kabc",
+                "since": null,
+                "seq": 43,
+                "time": "2024-01-01T00:00:01Z",
+                "ops": [
+                    { "action": "create", "path": "app.bsky.feed.post/1", "cid": { "$link": RECORD_CID } },
+                    { "action": "delete", "path": "app.bsky.feed.post/0", "cid": null },
+                ],
+                "blocks": { "$bytes": "AAEC" },
+                "blobs": [],
+            }),
+        ),
+    ])
+    .await;
+
+    let store = MemoryCursorStore::new();
+    let relay = RelayClient::new(relay_url).with_cursor_store(Arc::new(store.clone()));
+    relay.save_cursor(41).await.unwrap();
+
+    let mut firehose = relay.subscribe().await.unwrap();
+    assert_eq!(firehose.last_seq(), Some(41));
+    let events: Vec<_> = tokio::time::timeout(Duration::from_secs(10), (&mut firehose).collect())
+        .await
+        .unwrap();
+
+    assert!(uri.await.unwrap().contains("cursor=41"));
+    assert_eq!(events.len(), 2);
+    assert!(matches!(&events[0], Ok(RepoEvent::Identity(e)) if e.did == "did:plc:alice"));
+    let Ok(RepoEvent::Commit(commit)) = &events[1] else {
+        panic!("expected a commit, got {:?}", events[1]);
+    };
+    assert_eq!(commit.rev, "3kabc");
+    assert_eq!(commit.since, None);
+    assert_eq!(commit.blocks, vec![0, 1, 2]);
+    assert_eq!(commit.ops.len(), 2);
+    assert_eq!(commit.ops[0].cid.as_deref(), Some(RECORD_CID));
+    assert_eq!(commit.ops[1].cid, None);
+
+    assert_eq!(firehose.last_seq(), Some(43));
+    assert_eq!(store.load(relay.host()).await.unwrap(), Some(41));
+    firehose.checkpoint().await.unwrap();
+    assert_eq!(store.load(relay.host()).await.unwrap(), Some(43));
+    assert_eq!(relay.cursor().await.unwrap(), Some(43));
+}
+
+#[tokio::test]
+async fn test_cursors_are_kept_per_relay_host() {
+    let store: Arc<dyn CursorStore> = Arc::new(MemoryCursorStore::new());
+    let a = RelayClient::new(PdsUrl::new("https://relay-a.example.com").unwrap())
+        .with_cursor_store(store.clone());
+    let b = RelayClient::new(PdsUrl::new("http://127.0.0.1:2470").unwrap())
+        .with_cursor_store(store.clone());
+    assert_eq!(a.host(), "relay-a.example.com");
+    assert_eq!(b.host(), "127.0.0.1:2470");
+
+    a.save_cursor(7).await.unwrap();
+    assert_eq!(a.cursor().await.unwrap(), Some(7));
+    assert_eq!(b.cursor().await.unwrap(), None);
+}